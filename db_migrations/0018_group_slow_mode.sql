@@ -0,0 +1,7 @@
+-- Migration 0018: Add per-group slow mode settings
+--
+-- slow_mode_interval_secs: minimum number of seconds between two chat messages from the
+--   same member (0 disables slow mode)
+-- slow_mode_admins_exempt: whether group admins bypass the interval (1 = exempt)
+ALTER TABLE group_information ADD COLUMN slow_mode_interval_secs INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE group_information ADD COLUMN slow_mode_admins_exempt INTEGER NOT NULL DEFAULT 1;
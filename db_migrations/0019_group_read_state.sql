@@ -0,0 +1,15 @@
+-- Migration 0019: Track how far each account has read in each group
+--
+-- last_read_at: Unix timestamp in MILLISECONDS, comparable with aggregated_messages.created_at.
+--   Messages created after it count as unread. Only ever moves forward.
+CREATE TABLE group_read_state (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    last_read_at INTEGER NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+    UNIQUE(account_id, mls_group_id)
+);
@@ -0,0 +1,15 @@
+-- Migration 0020: NIP-51 mute list entries per account
+--
+-- private: whether the entry lives in the encrypted content of the kind 10000 mute list
+--   event rather than in its public tags. Entries are republished with the same visibility.
+CREATE TABLE account_mutes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    pubkey TEXT NOT NULL, -- Hex encoded nostr public key of the muted user
+    private BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+    UNIQUE(account_id, pubkey)
+);
@@ -0,0 +1,5 @@
+-- Migration 0021: Remember which account the user is looking at
+--
+-- active_account_pubkey: hex encoded public key of the active account, NULL when none is set.
+--   Cleared when that account logs out.
+ALTER TABLE app_settings ADD COLUMN active_account_pubkey TEXT;
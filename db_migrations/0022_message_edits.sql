@@ -0,0 +1,6 @@
+-- Migration 0022: Collapse message edits onto the original message
+--
+-- edited_at: when the author last edited the message, in milliseconds. NULL for messages
+--   that were never edited. content and content_tokens of an edited message hold the latest
+--   edit; the edits themselves are cached as rows of their own kind.
+ALTER TABLE aggregated_messages ADD COLUMN edited_at INTEGER;
@@ -0,0 +1,5 @@
+-- Migration 0023: Delivery status of messages sent by the account
+--
+-- delivery_status: JSON encoded DeliveryStatus of a kind 9 message the account sent.
+--   NULL for messages received from other members, which are always delivered.
+ALTER TABLE aggregated_messages ADD COLUMN delivery_status TEXT;
@@ -0,0 +1,24 @@
+-- Migration 0024: Outbox of chat messages that no relay accepted
+--
+-- Stores the inner (unsigned) event of the message rather than its MLS wrapper, so it can be
+-- encrypted again for the group's current epoch when retried. Re-creating the inner event from
+-- these columns yields the same event ID, which matches the cached message.
+--
+-- event_created_at: Unix timestamp in SECONDS of the inner event.
+-- tags: JSON encoded tags of the inner event.
+CREATE TABLE outbox (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    event_id TEXT NOT NULL,
+    kind INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    tags TEXT NOT NULL,
+    event_created_at INTEGER NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+    UNIQUE(account_id, event_id)
+);
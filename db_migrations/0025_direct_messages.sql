@@ -0,0 +1,28 @@
+-- Migration 0025: NIP-17 private direct messages
+--
+-- One row per kind 14 rumor received or sent by an account. Direct messages are not MLS
+-- groups; a conversation is identified by the account and the other participant.
+--
+-- message_id: ID of the rumor (the unsigned inner event), not of the giftwrap.
+-- other_pubkey: The conversation partner; the recipient for messages the account sent.
+-- created_at: Unix timestamp in MILLISECONDS, like aggregated_messages.created_at.
+-- tags: JSON encoded tags of the rumor.
+CREATE TABLE direct_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    message_id TEXT NOT NULL
+        CHECK (length(message_id) = 64 AND message_id GLOB '[0-9a-fA-F]*'),
+    other_pubkey TEXT NOT NULL
+        CHECK (length(other_pubkey) = 64 AND other_pubkey GLOB '[0-9a-fA-F]*'),
+    author TEXT NOT NULL
+        CHECK (length(author) = 64 AND author GLOB '[0-9a-fA-F]*'),
+    content TEXT NOT NULL,
+    tags TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+    UNIQUE(account_id, message_id)
+);
+
+CREATE INDEX idx_direct_messages_conversation
+    ON direct_messages(account_id, other_pubkey, created_at);
@@ -0,0 +1,15 @@
+-- Migration 0026: Per-relay sync watermarks
+--
+-- The newest event each relay delivered to an account's subscriptions, so subscriptions can
+-- resume from where each relay left off instead of from the account-wide last_synced_at.
+--
+-- last_seen_at: Unix timestamp in SECONDS of the newest event's created_at, as used by
+-- subscription filters. For giftwraps this is the (backdated) giftwrap timestamp.
+CREATE TABLE relay_sync_watermarks (
+    account_id INTEGER NOT NULL,
+    relay_url TEXT NOT NULL,
+    last_seen_at INTEGER NOT NULL,
+
+    PRIMARY KEY (account_id, relay_url),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
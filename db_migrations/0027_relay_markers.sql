@@ -0,0 +1,6 @@
+-- Migration 0027: NIP-65 read/write markers
+--
+-- A NIP-65 relay list marks each relay as `read`, `write` or neither, which means both.
+-- Existing rows predate markers and were used for both, so they default to read_write.
+-- Inbox and key package relay lists have no markers and always use read_write.
+ALTER TABLE user_relays ADD COLUMN marker TEXT NOT NULL DEFAULT 'read_write';
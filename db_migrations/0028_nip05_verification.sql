@@ -0,0 +1,9 @@
+-- Migration 0028: Cached NIP-05 verification of users
+--
+-- nip05_identifier: The identifier that was checked. A result only counts while it matches
+-- the nip05 field of the user's metadata, so changing the identifier invalidates it.
+-- nip05_verified: Whether the identifier's domain maps it to the user's pubkey (0 or 1)
+-- nip05_verified_at: Unix timestamp in MILLISECONDS of the check, NULL if never checked
+ALTER TABLE users ADD COLUMN nip05_identifier TEXT;
+ALTER TABLE users ADD COLUMN nip05_verified INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN nip05_verified_at INTEGER;
@@ -0,0 +1,14 @@
+-- Migration 0029: Per-group notification level of each account
+--
+-- level: 'all', 'mentions_only' or 'muted'. Groups without a row notify for every message.
+CREATE TABLE group_notification_settings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    level TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+    UNIQUE(account_id, mls_group_id)
+);
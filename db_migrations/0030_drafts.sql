@@ -0,0 +1,15 @@
+-- Migration 0030: Unsent message drafts of each account, per group
+--
+-- Drafts are local only and never published. The draft of a group is removed once the
+-- account sends a chat message to it.
+CREATE TABLE drafts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    content TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+    UNIQUE(account_id, mls_group_id)
+);
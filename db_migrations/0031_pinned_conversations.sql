@@ -0,0 +1,14 @@
+-- Migration 0031: Conversations each account pinned to the top of its chat list
+--
+-- position: 0-based rank among the account's pins, kept contiguous. Pins are local only.
+CREATE TABLE pinned_conversations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    position INTEGER NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+    UNIQUE(account_id, mls_group_id)
+);
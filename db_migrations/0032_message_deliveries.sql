@@ -0,0 +1,20 @@
+-- Migration 0032: Delivery status of chat messages the account sent
+--
+-- Sent messages are only cached once they are synced from MDK or echoed back by a relay,
+-- so their status is kept here from the moment they are sent and copied to
+-- aggregated_messages.delivery_status when they are cached.
+--
+-- created_at: milliseconds, like aggregated_messages.created_at
+-- delivery_status: JSON encoded DeliveryStatus
+CREATE TABLE message_deliveries (
+    message_id TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    author TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    delivery_status TEXT NOT NULL,
+
+    PRIMARY KEY (message_id, mls_group_id)
+);
+
+CREATE INDEX idx_message_deliveries_group_author
+    ON message_deliveries(mls_group_id, author, created_at);
@@ -0,0 +1,22 @@
+-- Migration 0033: Key relay sync watermarks by subscription
+--
+-- Each of an account's subscriptions (follow list, giftwraps, group messages) resumes from
+-- the newest event it processed on a relay, instead of sharing one watermark per relay.
+--
+-- subscription: the subscription the events arrived on (follow_list, giftwrap, group_messages)
+-- last_seen_at: Unix timestamp in SECONDS of the newest processed event's created_at, as used
+-- by subscription filters. For giftwraps this is the (backdated) giftwrap timestamp.
+--
+-- Existing watermarks were advanced before events were processed and can't be attributed to a
+-- subscription, so they are dropped; relays resume from the account-wide last_synced_at.
+DROP TABLE relay_sync_watermarks;
+
+CREATE TABLE relay_sync_watermarks (
+    account_id INTEGER NOT NULL,
+    subscription TEXT NOT NULL,
+    relay_url TEXT NOT NULL,
+    last_seen_at INTEGER NOT NULL,
+
+    PRIMARY KEY (account_id, subscription, relay_url),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
@@ -0,0 +1,13 @@
+-- Migration 0034: Groups each account left whose removal isn't committed yet
+--
+-- Leaving only proposes the account's own removal, so MDK keeps the group active until an
+-- admin commits it. Groups listed here get no message subscription in the meantime.
+CREATE TABLE left_groups (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+    UNIQUE(account_id, mls_group_id)
+);
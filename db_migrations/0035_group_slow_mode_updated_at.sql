@@ -0,0 +1,6 @@
+-- Migration 0035: Remember when the slow mode settings in effect were published
+--
+-- slow_mode_updated_at: created_at (unix SECONDS) of the settings message in effect, NULL
+--   while the defaults apply. Settings messages that aren't newer are ignored, so a delayed
+--   or replayed message can't roll the settings back.
+ALTER TABLE group_information ADD COLUMN slow_mode_updated_at INTEGER;
@@ -0,0 +1,7 @@
+-- Migration 0036: Remember when a user's NIP-05 identifier was last checked, even if it failed
+--
+-- nip05_checked_at: Unix timestamp in MILLISECONDS of the last check, NULL if never checked.
+--   Unlike nip05_verified_at it is also set when the domain couldn't be reached or the
+--   identifier is invalid, so background re-checks move on instead of retrying the same users.
+ALTER TABLE users ADD COLUMN nip05_checked_at INTEGER;
+UPDATE users SET nip05_checked_at = nip05_verified_at;
@@ -77,6 +77,11 @@ impl Scenario for ChatMediaUploadScenario {
             .execute(&mut self.context)
             .await?;
 
+        // Test a PDF declared as PNG is corrected to (or rejected as) its detected type
+        UploadMismatchedMimeTypeTestCase::new("media_uploader", "media_upload_test_group")
+            .execute(&mut self.context)
+            .await?;
+
         // Test download_chat_media API (download → decrypt → cache)
         DownloadChatMediaTestCase::new("media_uploader", "media_upload_test_group")
             .execute(&mut self.context)
@@ -97,6 +102,7 @@ impl Scenario for ChatMediaUploadScenario {
         tracing::info!("  • Video (MP4) upload verified");
         tracing::info!("  • Audio (MP3) upload verified");
         tracing::info!("  • Document (PDF) upload verified");
+        tracing::info!("  • Mismatched declared MIME type handled");
         tracing::info!("  • Chat media download API verified (MIP-04 compliant)");
         tracing::info!("  • Unsupported format (BMP) rejection verified");
 
@@ -3,6 +3,7 @@ pub mod receive_message_with_media;
 pub mod send_message_with_media;
 pub mod upload_audio;
 pub mod upload_chat_image;
+pub mod upload_mismatched_mime_type;
 pub mod upload_pdf;
 pub mod upload_unsupported_format;
 pub mod upload_video;
@@ -12,6 +13,7 @@ pub use receive_message_with_media::*;
 pub use send_message_with_media::*;
 pub use upload_audio::*;
 pub use upload_chat_image::*;
+pub use upload_mismatched_mime_type::*;
 pub use upload_pdf::*;
 pub use upload_unsupported_format::*;
 pub use upload_video::*;
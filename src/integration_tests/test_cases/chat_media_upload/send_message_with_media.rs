@@ -1,7 +1,6 @@
 use crate::WhitenoiseError;
 use crate::integration_tests::core::*;
 use async_trait::async_trait;
-use nostr_sdk::prelude::*;
 
 /// Test case for sending messages with media attachments and verifying aggregation links them correctly
 pub struct SendMessageWithMediaTestCase {
@@ -18,26 +17,6 @@ impl SendMessageWithMediaTestCase {
             message_content: "Check out this image! 📸".to_string(),
         }
     }
-
-    /// Build imeta tag per MIP-04 spec
-    /// Format: `["imeta", "url <blossom_url>", "m <mime_type>", "filename <name>", "x <hash>", "v <version>"]`
-    /// Note: MIP-04 requires filename and version fields
-    fn build_imeta_tag(
-        &self,
-        hash_hex: &str,
-        blossom_url: &str,
-        mime_type: &str,
-    ) -> Result<Tag, WhitenoiseError> {
-        Tag::parse(vec![
-            "imeta",
-            &format!("url {}", blossom_url),
-            &format!("m {}", mime_type),
-            "filename image.jpg",
-            &format!("x {}", hash_hex),
-            "v mip04-v1",
-        ])
-        .map_err(|e| WhitenoiseError::Other(anyhow::anyhow!("Failed to create imeta tag: {}", e)))
-    }
 }
 
 #[async_trait]
@@ -63,12 +42,8 @@ impl TestCase for SendMessageWithMediaTestCase {
         })?;
         let media_hash_hex = hex::encode(original_hash);
 
-        let blossom_url = media_file.blossom_url.as_ref().ok_or_else(|| {
-            WhitenoiseError::Configuration("Uploaded media has no blossom URL".to_string())
-        })?;
-
-        let imeta_tag =
-            self.build_imeta_tag(&media_hash_hex, blossom_url, &media_file.mime_type)?;
+        // MIP-04 imeta tag, with dimensions and blurhash for images
+        let imeta_tag = media_file.imeta_tag()?;
 
         // Send message with imeta tag
         let send_result = context
@@ -0,0 +1,105 @@
+use crate::WhitenoiseError;
+use crate::integration_tests::core::*;
+use async_trait::async_trait;
+use nostr_sdk::Url;
+
+/// Test case for uploading a file whose declared MIME type doesn't match its contents
+pub struct UploadMismatchedMimeTypeTestCase {
+    account_name: String,
+    group_name: String,
+}
+
+impl UploadMismatchedMimeTypeTestCase {
+    pub fn new(account_name: &str, group_name: &str) -> Self {
+        Self {
+            account_name: account_name.to_string(),
+            group_name: group_name.to_string(),
+        }
+    }
+
+    /// Create a temporary PDF file, to be declared as a PNG image
+    fn create_test_pdf(&self) -> Result<tempfile::NamedTempFile, WhitenoiseError> {
+        use std::io::Write;
+
+        let mut temp_file = tempfile::NamedTempFile::new().map_err(|e| {
+            WhitenoiseError::Other(anyhow::anyhow!("Failed to create temp file: {}", e))
+        })?;
+
+        temp_file.write_all(b"%PDF-1.4\n").map_err(|e| {
+            WhitenoiseError::Other(anyhow::anyhow!("Failed to write PDF data: {}", e))
+        })?;
+
+        temp_file.flush().map_err(|e| {
+            WhitenoiseError::Other(anyhow::anyhow!("Failed to flush temp file: {}", e))
+        })?;
+
+        Ok(temp_file)
+    }
+}
+
+#[async_trait]
+impl TestCase for UploadMismatchedMimeTypeTestCase {
+    async fn run(&self, context: &mut ScenarioContext) -> Result<(), WhitenoiseError> {
+        tracing::info!(
+            "Uploading a PDF declared as image/png for group {} using account: {}",
+            self.group_name,
+            self.account_name
+        );
+
+        let account = context.get_account(&self.account_name)?;
+        let group = context.get_group(&self.group_name)?;
+
+        let temp_file = self.create_test_pdf()?;
+        let temp_path = temp_file
+            .path()
+            .to_str()
+            .ok_or_else(|| WhitenoiseError::Other(anyhow::anyhow!("Invalid temp path")))?;
+
+        let blossom_url = if cfg!(debug_assertions) {
+            Some(Url::parse("http://localhost:3000").unwrap())
+        } else {
+            None
+        };
+
+        let result = context
+            .whitenoise
+            .upload_chat_media_with_mime_type(
+                account,
+                &group.mls_group_id,
+                temp_path,
+                "image/png",
+                blossom_url,
+                None,
+            )
+            .await;
+
+        drop(temp_file);
+
+        // Depending on the configuration the upload is corrected to the detected type or
+        // rejected; either way it must never be stored as an image
+        if context.whitenoise.config.reject_mismatched_media_types {
+            match result {
+                Err(WhitenoiseError::MimeMismatch { declared, detected }) => {
+                    assert_eq!(declared, "image/png");
+                    assert_eq!(detected, "application/pdf");
+                    tracing::info!("✓ Mismatched MIME type correctly rejected");
+                }
+                other => {
+                    return Err(WhitenoiseError::Other(anyhow::anyhow!(
+                        "Expected MimeMismatch error, got: {:?}",
+                        other
+                    )));
+                }
+            }
+        } else {
+            let media_file = result?;
+            assert_eq!(media_file.mime_type, "application/pdf");
+            assert!(media_file.file_metadata.as_ref().is_none_or(|metadata| {
+                metadata.blurhash.is_none() && metadata.dimensions.is_none()
+            }));
+            tracing::info!("✓ Mismatched MIME type corrected to the detected type");
+        }
+
+        Ok(())
+    }
+}
@@ -6,6 +6,7 @@ use std::collections::HashSet;
 /// Test case that subscribes to group messages and verifies the initial snapshot
 /// contains all expected messages with correct state.
 pub struct VerifyInitialMessagesTestCase {
+    account_name: String,
     group_name: String,
     expected_message_keys: Vec<String>,
     expected_with_reactions: Vec<String>,
@@ -14,8 +15,9 @@ pub struct VerifyInitialMessagesTestCase {
 }
 
 impl VerifyInitialMessagesTestCase {
-    pub fn new(group_name: &str) -> Self {
+    pub fn new(account_name: &str, group_name: &str) -> Self {
         Self {
+            account_name: account_name.to_string(),
             group_name: group_name.to_string(),
             expected_message_keys: vec![],
             expected_with_reactions: vec![],
@@ -53,11 +55,12 @@ impl TestCase for VerifyInitialMessagesTestCase {
             self.group_name
         );
 
+        let account = context.get_account(&self.account_name)?;
         let group = context.get_group(&self.group_name)?;
 
         let subscription = context
             .whitenoise
-            .subscribe_to_group_messages(&group.mls_group_id)
+            .subscribe_to_group_messages(account, &group.mls_group_id)
             .await?;
 
         tracing::info!(
@@ -13,12 +13,13 @@ use tokio::sync::{Mutex, broadcast};
 ///
 /// Usage:
 /// ```ignore
-/// let verifier = VerifyStreamUpdateTestCase::new("group", UpdateTrigger::NewMessage);
+/// let verifier = VerifyStreamUpdateTestCase::new("alice", "group", UpdateTrigger::NewMessage);
 /// verifier.subscribe(&context).await?;
 /// // ... perform action that triggers the update ...
 /// verifier.execute(&mut context).await?;
 /// ```
 pub struct VerifyStreamUpdateTestCase {
+    account_name: String,
     group_name: String,
     expected_trigger: UpdateTrigger,
     expected_message_key: Option<String>,
@@ -28,8 +29,9 @@ pub struct VerifyStreamUpdateTestCase {
 }
 
 impl VerifyStreamUpdateTestCase {
-    pub fn new(group_name: &str, expected_trigger: UpdateTrigger) -> Self {
+    pub fn new(account_name: &str, group_name: &str, expected_trigger: UpdateTrigger) -> Self {
         Self {
+            account_name: account_name.to_string(),
             group_name: group_name.to_string(),
             expected_trigger,
             expected_message_key: None,
@@ -59,10 +61,11 @@ impl VerifyStreamUpdateTestCase {
 
     /// Subscribe to the group. Must be called before `execute()`.
     pub async fn subscribe(&self, context: &ScenarioContext) -> Result<(), WhitenoiseError> {
+        let account = context.get_account(&self.account_name)?;
         let group = context.get_group(&self.group_name)?;
         let subscription = context
             .whitenoise
-            .subscribe_to_group_messages(&group.mls_group_id)
+            .subscribe_to_group_messages(account, &group.mls_group_id)
             .await?;
 
         let mut guard = self.receiver.lock().await;
@@ -1,7 +1,16 @@
-use std::sync::{Mutex, OnceLock};
+use std::sync::{OnceLock, RwLock};
 
-use tracing_appender::non_blocking::WorkerGuard;
-use tracing_subscriber::{filter::EnvFilter, fmt::Layer, prelude::*, registry::Registry};
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_subscriber::{
+    filter::EnvFilter,
+    fmt::{
+        Layer,
+        writer::{MakeWriter, OptionalWriter},
+    },
+    prelude::*,
+    registry::Registry,
+    reload,
+};
 
 mod nostr_manager;
 mod types;
@@ -15,55 +24,144 @@ pub mod integration_tests;
 // Re-export main types for library users
 
 // Core types
-pub use types::{ImageType, MessageWithTokens};
+pub use types::{ImageType, MessageWithTokens, RetryConfig, RetryErrorClass, RetryPolicy};
+pub use whitenoise::database::DatabasePoolConfig;
 pub use whitenoise::{Whitenoise, WhitenoiseConfig};
 
 // Error handling
+pub use whitenoise::blossom::BlossomError;
 pub use whitenoise::error::WhitenoiseError;
 
 // Account and user management
+pub use whitenoise::account_sync::SyncReport;
 pub use whitenoise::accounts::Account;
-pub use whitenoise::users::{User, UserSyncMode};
+pub use whitenoise::messaging_readiness::MessagingReadiness;
+pub use whitenoise::onboarding::OnboardingState;
+pub use whitenoise::secrets_store::SecretsStatus;
+pub use whitenoise::self_check::{CheckResult, CheckStatus, SelfCheckReport};
+pub use whitenoise::storage_usage::{AccountStorageUsage, StorageUsage};
+pub use whitenoise::users::{RefreshPolicy, User, UserSyncMode};
 
 // Settings and configuration
-pub use whitenoise::app_settings::{AppSettings, ThemeMode};
+pub use whitenoise::app_settings::{AppSettings, NotificationLevel, ThemeMode};
 
 // Groups and relays
-pub use whitenoise::group_information::{GroupInformation, GroupType};
-pub use whitenoise::relays::{Relay, RelayType};
+pub use whitenoise::group_information::{GroupInformation, GroupType, SlowMode};
+pub use whitenoise::group_sync::GroupSyncStatus;
+pub use whitenoise::groups::{AddMembersOutcome, CreateGroupOutcome, GroupMember};
+pub use whitenoise::history_export::{ExportFormat, ExportOptions};
+pub use whitenoise::relays::{DeliveryPreview, GroupRelayStatus, Relay, RelayMarker, RelayType};
+pub use whitenoise::welcomes::WelcomePreview;
+
+// Chat list
+pub use whitenoise::chat_list::{ChatAvatar, ChatListItem, Conversation, ConversationFilter};
 
 // Media files
 pub use whitenoise::database::media_files::{FileMetadata, MediaFile};
+pub use whitenoise::media_files::{MediaFileInfo, MediaUpload};
 
 // Messaging
+pub use whitenoise::link_previews::LinkPreview;
 pub use whitenoise::message_aggregator::{
-    ChatMessage, EmojiReaction, ReactionSummary, UserReaction,
+    ChatMessage, ChatMessageRef, DeliveryStatus, EmojiReaction, GroupActivity, MESSAGE_EDIT_KIND,
+    Mention, MessageEdit, MessageSearchResult, ReactionAction, ReactionSummary, ThreadNode,
+    UserReaction,
 };
 
 // Nostr integration
 pub use nostr_manager::parser::SerializableToken;
+pub use nostr_manager::{
+    CacheStats, FetchTimeouts, PublishOutcome, ReconnectPolicy, RelayHealth, RelayPublishStatus,
+    SubscriptionBreakdown, SubscriptionCategory, SubscriptionStatus,
+};
+
+// Event processing metrics
+pub use whitenoise::event_processor::{DurationHistogram, EventProcessorStats, HistogramBucket};
 
 // Group message streaming
 pub use whitenoise::message_streaming::{GroupMessageSubscription, MessageUpdate, UpdateTrigger};
 
-static TRACING_GUARDS: OnceLock<Mutex<Option<(WorkerGuard, WorkerGuard)>>> = OnceLock::new();
+/// Active log file writer and the guard that keeps its worker thread alive.
+///
+/// Swapped out by [`reinit_tracing`] so the log file can move to a new directory without
+/// installing a second global subscriber. Dropping the guard flushes and closes the old file.
+static LOG_FILE_WRITER: RwLock<Option<(NonBlocking, WorkerGuard)>> = RwLock::new(None);
+static STDOUT_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
 static TRACING_INIT: OnceLock<()> = OnceLock::new();
 
+/// Handle for replacing the filter of the global subscriber, see [`set_log_filter`].
+static LOG_FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Serializes tests that swap the log file writer or change the log filter, since both are
+/// process-wide.
+#[cfg(test)]
+pub(crate) static TRACING_TEST_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Filter directives used when `RUST_LOG` isn't set
+const DEFAULT_LOG_FILTER: &str = "info,refinery_core=warn,refinery=warn";
+
+/// [`MakeWriter`] that forwards to whatever log file writer is currently installed.
+struct LogFileMakeWriter;
+
+impl<'a> MakeWriter<'a> for LogFileMakeWriter {
+    type Writer = OptionalWriter<NonBlocking>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        match LOG_FILE_WRITER.read() {
+            Ok(writer) => match writer.as_ref() {
+                Some((non_blocking, _)) => OptionalWriter::some(non_blocking.clone()),
+                None => OptionalWriter::none(),
+            },
+            Err(_) => OptionalWriter::none(),
+        }
+    }
+}
+
+const LOG_FILE_PREFIX: &str = "whitenoise";
+const LOG_FILE_SUFFIX: &str = "log";
+
+/// Path of the log file written to today, which may not exist yet.
+///
+/// Mirrors the naming of the daily rolling appender, which rotates at midnight UTC.
+fn current_log_file_path(logs_dir: &std::path::Path) -> std::path::PathBuf {
+    logs_dir.join(format!(
+        "{}.{}.{}",
+        LOG_FILE_PREFIX,
+        chrono::Utc::now().format("%Y-%m-%d"),
+        LOG_FILE_SUFFIX
+    ))
+}
+
+fn create_log_file_writer(
+    logs_dir: &std::path::Path,
+) -> Result<(NonBlocking, WorkerGuard), WhitenoiseError> {
+    let file_appender = tracing_appender::rolling::RollingFileAppender::builder()
+        .rotation(tracing_appender::rolling::Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .build(logs_dir)
+        .map_err(|e| WhitenoiseError::LoggingSetup(e.to_string()))?;
+
+    Ok(tracing_appender::non_blocking(file_appender))
+}
+
+/// Installs `writer` as the log file writer, dropping (and flushing) the previous one.
+fn swap_log_file_writer(writer: Option<(NonBlocking, WorkerGuard)>) {
+    let previous = match LOG_FILE_WRITER.write() {
+        Ok(mut current) => std::mem::replace(&mut *current, writer),
+        Err(poisoned) => std::mem::replace(&mut *poisoned.into_inner(), writer),
+    };
+    // Drop outside the lock so the worker can flush without blocking new writers
+    drop(previous);
+}
+
 fn init_tracing(logs_dir: &std::path::Path) {
     TRACING_INIT.get_or_init(|| {
-        let file_appender = tracing_appender::rolling::RollingFileAppender::builder()
-            .rotation(tracing_appender::rolling::Rotation::DAILY)
-            .filename_prefix("whitenoise")
-            .filename_suffix("log")
-            .build(logs_dir)
-            .expect("Failed to create file appender");
-
-        let (non_blocking_file, file_guard) = tracing_appender::non_blocking(file_appender);
-        let (non_blocking_stdout, stdout_guard) = tracing_appender::non_blocking(std::io::stdout());
+        let file_writer = create_log_file_writer(logs_dir).expect("Failed to create file appender");
+        swap_log_file_writer(Some(file_writer));
 
-        TRACING_GUARDS
-            .set(Mutex::new(Some((file_guard, stdout_guard))))
-            .ok();
+        let (non_blocking_stdout, stdout_guard) = tracing_appender::non_blocking(std::io::stdout());
+        STDOUT_GUARD.set(stdout_guard).ok();
 
         let stdout_layer = Layer::new()
             .with_writer(non_blocking_stdout)
@@ -71,17 +169,109 @@ fn init_tracing(logs_dir: &std::path::Path) {
             .with_target(true);
 
         let file_layer = Layer::new()
-            .with_writer(non_blocking_file)
+            .with_writer(LogFileMakeWriter)
             .with_ansi(false)
             .with_target(true);
 
+        let (filter_layer, filter_handle) = reload::Layer::new(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER)),
+        );
+        LOG_FILTER.set(filter_handle).ok();
+
         Registry::default()
-            .with(
-                EnvFilter::try_from_default_env()
-                    .unwrap_or_else(|_| EnvFilter::new("info,refinery_core=warn,refinery=warn")),
-            )
+            .with(filter_layer)
             .with(stdout_layer)
             .with(file_layer)
             .init();
     });
 }
+
+/// Points file logging at `logs_dir`, releasing the previous log file.
+///
+/// Initializes tracing if it hasn't been yet. The global subscriber is only installed once;
+/// re-initializing swaps the file writer underneath it so old appender guards are dropped
+/// instead of leaking their file handles.
+fn reinit_tracing(logs_dir: &std::path::Path) -> Result<(), WhitenoiseError> {
+    if TRACING_INIT.get().is_none() {
+        init_tracing(logs_dir);
+        return Ok(());
+    }
+
+    let file_writer = create_log_file_writer(logs_dir)?;
+    swap_log_file_writer(Some(file_writer));
+    Ok(())
+}
+
+/// Replaces the log filter of the global subscriber with `directives`.
+///
+/// `directives` use the `RUST_LOG` syntax (e.g. `debug` or `info,whitenoise=trace`). Invalid
+/// directives are rejected and leave the current filter in place.
+fn set_log_filter(directives: &str) -> Result<(), WhitenoiseError> {
+    let filter = EnvFilter::try_new(directives)
+        .map_err(|e| WhitenoiseError::InvalidLogFilter(format!("{}: {}", directives, e)))?;
+    let handle = LOG_FILTER
+        .get()
+        .ok_or_else(|| WhitenoiseError::LoggingSetup("Tracing is not initialized".to_string()))?;
+    handle
+        .reload(filter)
+        .map_err(|e| WhitenoiseError::LoggingSetup(e.to_string()))
+}
+
+/// Flushes and closes the current log file. Logging to file resumes on the next
+/// [`reinit_tracing`] call; stdout logging is unaffected.
+fn release_log_file_writer() {
+    swap_log_file_writer(None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn read_logs(dir: &std::path::Path) -> String {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| std::fs::read_to_string(entry.path()).unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn test_reinit_tracing_writes_to_new_dir() {
+        let _tracing = TRACING_TEST_LOCK.blocking_lock();
+        let first_dir = TempDir::new().unwrap();
+        let second_dir = TempDir::new().unwrap();
+        let third_dir = TempDir::new().unwrap();
+
+        init_tracing(first_dir.path());
+        reinit_tracing(second_dir.path()).unwrap();
+
+        let marker = format!("reinit-marker-{}", uuid::Uuid::new_v4());
+        tracing::warn!(target: "whitenoise::tests", "{}", marker);
+
+        // Moving on to another directory drops the previous guard, flushing the log file
+        reinit_tracing(third_dir.path()).unwrap();
+
+        assert!(read_logs(second_dir.path()).contains(&marker));
+        assert!(!read_logs(third_dir.path()).contains(&marker));
+    }
+
+    #[test]
+    fn test_set_log_filter_rejects_invalid_directives() {
+        let _tracing = TRACING_TEST_LOCK.blocking_lock();
+        let logs_dir = TempDir::new().unwrap();
+        init_tracing(logs_dir.path());
+
+        assert!(matches!(
+            set_log_filter("whitenoise=notalevel"),
+            Err(WhitenoiseError::InvalidLogFilter(_))
+        ));
+
+        set_log_filter("debug,refinery=warn").unwrap();
+        assert!(tracing::enabled!(target: "whitenoise::tests", tracing::Level::DEBUG));
+        set_log_filter(DEFAULT_LOG_FILTER).unwrap();
+        assert!(!tracing::enabled!(target: "whitenoise::tests", tracing::Level::DEBUG));
+    }
+}
@@ -0,0 +1,122 @@
+//! Inspection and pruning of the client's local event cache
+//!
+//! Fetched events are kept in the client's database so single-event lookups can be answered
+//! without a relay round trip. The cache is capped at a fixed number of events, but those can
+//! still add up; these functions show how much it holds and drop old events on request.
+//! Subscriptions are unaffected, they live in the relay pool and not in the cache.
+
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{NostrManager, Result};
+
+/// Size and age of the local Nostr event cache
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    /// Number of cached events
+    pub event_count: usize,
+    /// Approximate size of the cached events, measured as their JSON encoding
+    pub approximate_bytes: usize,
+    /// `created_at` of the oldest cached event, `None` when the cache is empty
+    pub oldest: Option<Timestamp>,
+    /// `created_at` of the newest cached event, `None` when the cache is empty
+    pub newest: Option<Timestamp>,
+}
+
+impl NostrManager {
+    /// Counts the events in the local cache along with their size and age range
+    pub(crate) async fn cache_stats(&self) -> Result<CacheStats> {
+        let events = self.client.database().query(Filter::new()).await?;
+
+        let mut stats = CacheStats::default();
+        for event in events.iter() {
+            stats.event_count += 1;
+            stats.approximate_bytes += event.as_json().len();
+            stats.oldest = Some(
+                stats
+                    .oldest
+                    .map_or(event.created_at, |oldest| oldest.min(event.created_at)),
+            );
+            stats.newest = Some(
+                stats
+                    .newest
+                    .map_or(event.created_at, |newest| newest.max(event.created_at)),
+            );
+        }
+        Ok(stats)
+    }
+
+    /// Drops cached events created before `older_than`
+    ///
+    /// Events created at or after `older_than` stay cached.
+    pub(crate) async fn prune_cache(&self, older_than: Timestamp) -> Result<()> {
+        if older_than.as_u64() == 0 {
+            return Ok(());
+        }
+
+        let filter = Filter::new().until(Timestamp::from(older_than.as_u64() - 1));
+        self.client.database().delete(filter).await?;
+
+        tracing::debug!(
+            target: "whitenoise::nostr_manager::prune_cache",
+            "Pruned cached events created before {}",
+            older_than
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::whitenoise::event_tracker::NoEventTracker;
+
+    #[tokio::test]
+    async fn test_prune_cache_keeps_recent_events() {
+        let (event_sender, _) = mpsc::channel(100);
+        let nostr_manager = NostrManager::new(
+            event_sender,
+            Arc::new(NoEventTracker),
+            NostrManager::default_timeout(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            nostr_manager.cache_stats().await.unwrap(),
+            CacheStats::default()
+        );
+
+        let keys = Keys::generate();
+        for created_at in [1_000, 2_000, 3_000] {
+            let event = EventBuilder::text_note("cached")
+                .custom_created_at(Timestamp::from(created_at))
+                .sign_with_keys(&keys)
+                .unwrap();
+            nostr_manager
+                .client
+                .database()
+                .save_event(&event)
+                .await
+                .unwrap();
+        }
+
+        let stats = nostr_manager.cache_stats().await.unwrap();
+        assert_eq!(stats.event_count, 3);
+        assert!(stats.approximate_bytes > 0);
+        assert_eq!(stats.oldest, Some(Timestamp::from(1_000)));
+        assert_eq!(stats.newest, Some(Timestamp::from(3_000)));
+
+        nostr_manager
+            .prune_cache(Timestamp::from(2_000))
+            .await
+            .unwrap();
+
+        let stats = nostr_manager.cache_stats().await.unwrap();
+        assert_eq!(stats.event_count, 2);
+        assert_eq!(stats.oldest, Some(Timestamp::from(2_000)));
+    }
+}
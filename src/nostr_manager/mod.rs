@@ -3,20 +3,34 @@ use std::time::Duration;
 use ::rand::RngCore;
 use nostr_sdk::prelude::*;
 use thiserror::Error;
-use tokio::sync::mpsc::Sender;
 
 // use crate::media::blossom::BlossomClient;
 use crate::{
-    types::ProcessableEvent,
+    types::{ProcessableEvent, ProcessableEventSender},
     whitenoise::{database::DatabaseError, event_tracker::EventTracker},
 };
 
+pub(crate) mod event_cache;
+pub(crate) mod nostr_client;
 pub mod parser;
+pub(crate) mod publish_outcome;
 pub mod publisher;
 pub mod query;
+pub(crate) mod reconnect;
+pub(crate) mod relay_auth;
+pub(crate) mod relay_health;
+pub(crate) mod relay_metrics;
 pub mod subscriptions;
 pub mod utils;
 
+pub use event_cache::CacheStats;
+pub use publish_outcome::{PublishOutcome, RelayPublishStatus};
+pub use query::FetchTimeouts;
+pub use reconnect::ReconnectPolicy;
+pub use relay_metrics::RelayHealth;
+pub(crate) use subscriptions::SyncSince;
+pub use subscriptions::{SubscriptionBreakdown, SubscriptionCategory, SubscriptionStatus};
+
 #[derive(Error, Debug)]
 pub enum NostrManagerError {
     #[error("Whitenoise Instance Error: {0}")]
@@ -25,6 +39,8 @@ pub enum NostrManagerError {
     Client(#[from] nostr_sdk::client::Error),
     #[error("Database Error: {0}")]
     Database(#[from] DatabaseError),
+    #[error("Nostr Database Error: {0}")]
+    NostrDatabase(#[from] nostr_sdk::database::DatabaseError),
     #[error("Signer Error: {0}")]
     Signer(#[from] nostr_sdk::signer::SignerError),
     #[error("Error with secrets store: {0}")]
@@ -47,20 +63,40 @@ pub enum NostrManagerError {
     FailedToTrackPublishedEvent(String),
     #[error("Invalid timestamp")]
     InvalidTimestamp,
+    #[error("Invalid event: {0}")]
+    InvalidSignature(String),
 }
 
 #[derive(Clone)]
 pub struct NostrManager {
     pub(crate) client: Client,
+    /// Publishes, fetches and subscribes; goes through `client`'s relay pool unless replaced
+    pub(crate) network: std::sync::Arc<dyn nostr_client::NostrClient>,
     session_salt: [u8; 16],
     timeout: Duration,
+    pub(crate) fetch_timeouts: query::FetchTimeouts,
+    /// How failed publishes are retried, `None` to report the first attempt as is
+    publish_retry: Option<crate::types::RetryPolicy>,
     pub(crate) event_tracker: std::sync::Arc<dyn EventTracker>,
     signer_lock: std::sync::Arc<tokio::sync::Mutex<()>>,
+    paused_categories:
+        std::sync::Arc<std::sync::RwLock<std::collections::HashSet<SubscriptionCategory>>>,
+    degraded_relays: std::sync::Arc<std::sync::RwLock<std::collections::HashSet<RelayUrl>>>,
+    relay_health: std::sync::Arc<relay_metrics::RelayHealthTracker>,
+    relay_auth: std::sync::Arc<relay_auth::RelayAuthTracker>,
+    reconnect_policy: ReconnectPolicy,
+    relay_backoff: std::sync::Arc<reconnect::RelayBackoff>,
     // blossom: BlossomClient,
 }
 
 pub type Result<T> = std::result::Result<T, NostrManagerError>;
 
+/// Maximum number of events kept in the client's in-memory event cache
+const EVENT_CACHE_SIZE: usize = 10_000;
+
+/// File in the data directory the session salt is kept in when it is persisted
+pub(crate) const SESSION_SALT_FILE: &str = "session_salt";
+
 impl NostrManager {
     /// Default timeout for client requests
     pub(crate) fn default_timeout() -> Duration {
@@ -70,16 +106,45 @@ impl NostrManager {
     ///
     /// # Arguments
     ///
-    /// * `event_sender` - Channel sender for forwarding events to Whitenoise for processing
+    /// * `event_sender` - Queues for forwarding events to Whitenoise for processing, routed
+    ///   by priority (a plain channel sender is used for both lanes)
     /// * `timeout` - Timeout for client requests
     pub(crate) async fn new(
-        event_sender: Sender<crate::types::ProcessableEvent>,
+        event_sender: impl Into<ProcessableEventSender>,
+        event_tracker: std::sync::Arc<dyn EventTracker>,
+        timeout: Duration,
+    ) -> Result<Self> {
+        Self::with_reconnect_policy(
+            event_sender,
+            event_tracker,
+            timeout,
+            ReconnectPolicy::default(),
+        )
+        .await
+    }
+
+    /// Create a new Nostr manager that reconnects relays according to `reconnect_policy`
+    ///
+    /// Relays reporting repeated failures in the notification handler are backed off as
+    /// described in [`ReconnectPolicy`].
+    pub(crate) async fn with_reconnect_policy(
+        event_sender: impl Into<ProcessableEventSender>,
         event_tracker: std::sync::Arc<dyn EventTracker>,
         timeout: Duration,
+        reconnect_policy: ReconnectPolicy,
     ) -> Result<Self> {
-        let opts = ClientOptions::default();
+        // Reconnection intervals are per-relay options, applied when relays are added to the pool.
+        // AUTH challenges are answered per account by Whitenoise, since the client usually
+        // has no signer set when they arrive.
+        let opts = ClientOptions::default().automatic_authentication(false);
+
+        // Keep fetched events in memory so single-event lookups can be answered locally
+        let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            max_events: Some(EVENT_CACHE_SIZE),
+        });
 
-        let client = { Client::builder().opts(opts).build() };
+        let client = { Client::builder().opts(opts).database(database).build() };
 
         // Generate a random session salt
         let mut session_salt = [0u8; 16];
@@ -91,23 +156,60 @@ impl NostrManager {
             "Setting up notification handler..."
         );
 
+        let relay_health = std::sync::Arc::new(relay_metrics::RelayHealthTracker::new());
+        let relay_backoff = std::sync::Arc::new(reconnect::RelayBackoff::new());
+        let relay_auth = std::sync::Arc::new(relay_auth::RelayAuthTracker::new());
+
         // Spawn notification handler in a background task to prevent blocking
         let client_clone = client.clone();
-        let event_sender_clone = event_sender.clone();
+        let event_sender_clone: ProcessableEventSender = event_sender.into();
+        let relay_health_clone = relay_health.clone();
+        let relay_backoff_clone = relay_backoff.clone();
+        let relay_auth_clone = relay_auth.clone();
+        let reconnect_policy_clone = reconnect_policy.clone();
         tokio::spawn(async move {
+            let backoff_client = client_clone.clone();
             if let Err(e) = client_clone
                 .handle_notifications(move |notification| {
                     let sender = event_sender_clone.clone();
+                    let relay_health = relay_health_clone.clone();
+                    let relay_backoff = relay_backoff_clone.clone();
+                    let relay_auth = relay_auth_clone.clone();
+                    let reconnect_policy = reconnect_policy_clone.clone();
+                    let backoff_client = backoff_client.clone();
                     async move {
                         match notification {
                             RelayPoolNotification::Message { relay_url, message } => {
+                                relay_health.record_message(&relay_url, &message);
+
+                                // Back off relays that keep failing instead of hammering them
+                                if reconnect::is_failure_message(&message) {
+                                    if let Some(delay) =
+                                        relay_backoff.record_failure(&relay_url, &reconnect_policy)
+                                    {
+                                        reconnect::disconnect_for(
+                                            &backoff_client,
+                                            relay_backoff.clone(),
+                                            relay_url.clone(),
+                                            delay,
+                                        )
+                                        .await;
+                                    }
+                                } else if matches!(
+                                    message,
+                                    RelayMessage::Event { .. } | RelayMessage::EndOfStoredEvents(_)
+                                ) {
+                                    relay_backoff.record_success(&relay_url);
+                                }
+
                                 // Extract events and send to Whitenoise queue
                                 match message {
                                     RelayMessage::Event { subscription_id, event } => {
                                         if let Err(_e) = sender
-                                            .send(ProcessableEvent::new_nostr_event(
+                                            .send(ProcessableEvent::new_relay_event(
                                                 event.as_ref().clone(),
                                                 Some(subscription_id.to_string()),
+                                                relay_url,
                                             ))
                                             .await
                                         {
@@ -119,7 +221,26 @@ impl NostrManager {
                                             return Ok(true); // Exit notification loop
                                         }
                                     }
+                                    RelayMessage::Auth { challenge } => {
+                                        if let Err(_e) = sender
+                                            .send(ProcessableEvent::RelayAuth {
+                                                relay_url,
+                                                challenge: challenge.into_owned(),
+                                            })
+                                            .await
+                                        {
+                                            tracing::debug!(
+                                                target: "whitenoise::nostr_client::handle_notifications",
+                                                "Message channel closed, exiting notification handler"
+                                            );
+                                            return Ok(true); // Exit notification loop
+                                        }
+                                    }
                                     _ => {
+                                        if let RelayMessage::Ok { event_id, status, message } = &message {
+                                            relay_auth.record_ok(&relay_url, event_id, *status, message);
+                                        }
+
                                         // Handle other relay messages as before
                                         let message_str = match message {
                                             RelayMessage::Ok { .. } => "Ok".to_string(),
@@ -177,15 +298,89 @@ impl NostrManager {
             "NostrManager initialization completed"
         );
 
+        let network = std::sync::Arc::new(nostr_client::RelayPoolClient::new(
+            client.clone(),
+            reconnect_policy.clone(),
+            relay_backoff.clone(),
+        ));
         Ok(Self {
             client,
+            network,
             session_salt,
             timeout,
+            fetch_timeouts: query::FetchTimeouts::default(),
+            publish_retry: None,
             event_tracker,
             signer_lock: std::sync::Arc::new(tokio::sync::Mutex::new(())),
+            paused_categories: std::sync::Arc::new(std::sync::RwLock::new(
+                std::collections::HashSet::new(),
+            )),
+            degraded_relays: std::sync::Arc::new(std::sync::RwLock::new(
+                std::collections::HashSet::new(),
+            )),
+            relay_health,
+            relay_auth,
+            reconnect_policy,
+            relay_backoff,
         })
     }
 
+    /// Publish, fetch and subscribe through `network` instead of the client's relay pool
+    pub(crate) fn with_network(
+        mut self,
+        network: std::sync::Arc<dyn nostr_client::NostrClient>,
+    ) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Use `fetch_timeouts` for one-off fetches instead of the defaults
+    pub(crate) fn with_fetch_timeouts(mut self, fetch_timeouts: FetchTimeouts) -> Self {
+        self.fetch_timeouts = fetch_timeouts;
+        self
+    }
+
+    /// Retry publishes that failed on some relays according to `publish_retry`
+    pub(crate) fn with_publish_retry(
+        mut self,
+        publish_retry: Option<crate::types::RetryPolicy>,
+    ) -> Self {
+        self.publish_retry = publish_retry;
+        self
+    }
+
+    /// Salt subscription ids with `session_salt` instead of a random per-session salt
+    pub(crate) fn with_session_salt(mut self, session_salt: [u8; 16]) -> Self {
+        self.session_salt = session_salt;
+        self
+    }
+
+    /// Read the session salt stored at `path`, generating and storing a random one if there
+    /// is none yet or the stored one is unreadable
+    pub(crate) fn load_or_create_session_salt(path: &std::path::Path) -> std::io::Result<[u8; 16]> {
+        match std::fs::read_to_string(path) {
+            Ok(stored) => {
+                if let Ok(salt) = hex::decode(stored.trim())
+                    && let Ok(salt) = <[u8; 16]>::try_from(salt)
+                {
+                    return Ok(salt);
+                }
+                tracing::warn!(
+                    target: "whitenoise::nostr_manager::load_or_create_session_salt",
+                    "Stored session salt at {:?} is invalid, replacing it",
+                    path
+                );
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let mut session_salt = [0u8; 16];
+        ::rand::rng().fill_bytes(&mut session_salt);
+        std::fs::write(path, hex::encode(session_salt))?;
+        Ok(session_salt)
+    }
+
     /// Reusable helper to execute operations with a temporary signer.
     ///
     /// This helper ensures that the signer is always unset after the operation completes,
@@ -213,7 +408,7 @@ impl NostrManager {
         inbox_relays: &[RelayUrl],
         group_relays: &[RelayUrl],
         nostr_group_ids: &[String],
-        since: Option<Timestamp>,
+        since: &SyncSince,
         signer: impl NostrSigner + 'static,
     ) -> Result<()> {
         tracing::debug!(
@@ -247,8 +442,13 @@ impl NostrManager {
         );
         self.with_signer(signer, || async {
             self.ensure_relays_connected(group_relays).await?;
-            self.setup_group_messages_subscription(pubkey, nostr_group_ids, group_relays, None)
-                .await
+            self.setup_group_messages_subscription(
+                pubkey,
+                nostr_group_ids,
+                group_relays,
+                &SyncSince::default(),
+            )
+            .await
         })
         .await
     }
@@ -281,7 +481,7 @@ impl NostrManager {
                 inbox_relays,
                 group_relays,
                 nostr_group_ids,
-                Some(buffer_time),
+                &Some(buffer_time).into(),
             )
             .await
         })
@@ -295,7 +495,9 @@ impl NostrManager {
             "Deleting Nostr data"
         );
         self.client.unset_signer().await;
-        self.client.unsubscribe_all().await;
+        self.network.unsubscribe_all().await;
+        self.relay_health.reset();
+        self.relay_backoff.reset();
         Ok(())
     }
 
@@ -329,123 +531,39 @@ impl NostrManager {
     /// - The relay URL is not found in the client's relay pool
     /// - There's an error retrieving the relay instance from the client
     /// - The client is in an invalid state
+    /// Checks that the event's id matches its contents and its signature is valid
+    ///
+    /// Events from relays were verified by nostr-sdk on arrival, but ones taken from the
+    /// cache or handed over by the app weren't, so handlers that decrypt or apply events
+    /// check them again first.
+    pub(crate) fn verify_event(&self, event: &Event) -> Result<()> {
+        event
+            .verify()
+            .map_err(|e| NostrManagerError::InvalidSignature(e.to_string()))
+    }
+
     pub(crate) async fn get_relay_status(&self, relay_url: &RelayUrl) -> Result<RelayStatus> {
-        let relay = self.client.relay(relay_url).await?;
-        Ok(relay.status())
+        let status = self.network.relay_status(relay_url).await?;
+        self.relay_health.record_status(relay_url, status);
+        Ok(status)
     }
 
     /// Ensures that the client is connected to all the specified relay URLs.
     ///
-    /// This method checks each relay URL in the provided list and adds it to the client's
-    /// relay pool if it's not already connected. It then attempts to establish connections
-    /// to any newly added relays.
+    /// This method adds each relay URL in the provided list to the client's relay pool if
+    /// it isn't there yet, then attempts to establish connections to the relays that aren't
+    /// backing off. The relays are managed by the manager's [`nostr_client::NostrClient`].
     ///
     /// This is essential for subscription setup and event publishing to work correctly,
     /// as the nostr-sdk client needs to be connected to relays before it can subscribe
     /// to them or publish events to them.
     pub(crate) async fn ensure_relays_connected(&self, relay_urls: &[RelayUrl]) -> Result<()> {
-        if relay_urls.is_empty() {
-            return Ok(());
-        }
-
-        tracing::debug!(
-            target: "whitenoise::nostr_manager::ensure_relays_connected",
-            "Ensuring connection to {} relay URLs",
-            relay_urls.len()
-        );
-
-        let relay_futures = relay_urls
-            .iter()
-            .map(|relay_url| self.ensure_relay_in_client(relay_url));
-        let results = futures::future::join_all(relay_futures).await;
-
-        let mut successful_relays = 0usize;
-        let mut last_error: Option<NostrManagerError> = None;
-
-        for (relay_url, result) in relay_urls.iter().zip(results.into_iter()) {
-            match result {
-                Ok(_) => successful_relays += 1,
-                Err(err) => {
-                    tracing::warn!(
-                        target: "whitenoise::nostr_manager::ensure_relays_connected",
-                        "Continuing without relay {}: {}",
-                        relay_url,
-                        err
-                    );
-                    last_error = Some(err);
-                }
-            }
-        }
-
-        if successful_relays == 0 {
-            let err = last_error.unwrap_or(NostrManagerError::NoRelayConnections);
-            tracing::error!(
-                target: "whitenoise::nostr_manager::ensure_relays_connected",
-                "Failed to ensure any relays connected: {}",
-                err
-            );
-            return Err(err);
-        }
-
-        if successful_relays < relay_urls.len() {
-            tracing::debug!(
-                target: "whitenoise::nostr_manager::ensure_relays_connected",
-                "Ensured {} of {} relay connections; continuing best-effort",
-                successful_relays,
-                relay_urls.len()
-            );
-        }
-
-        self.client.connect().await;
-
-        tracing::debug!(
-            target: "whitenoise::nostr_manager::ensure_relays_connected",
-            "Relay connections ensuring completed"
-        );
-
-        Ok(())
+        self.network.connect_relays(relay_urls).await
     }
 
-    /// Ensures that the client is connected to the specified relay URL.
-    async fn ensure_relay_in_client(&self, relay_url: &RelayUrl) -> Result<()> {
-        match self.client.relay(relay_url).await {
-            Ok(_) => {
-                tracing::debug!(
-                    target: "whitenoise::nostr_manager::ensure_relays_connected",
-                    "Relay {} already connected",
-                    relay_url
-                );
-                Ok(())
-            }
-            Err(_) => {
-                // Relay not found in client, add it
-                tracing::debug!(
-                    target: "whitenoise::nostr_manager::ensure_relays_connected",
-                    "Adding new relay: {}",
-                    relay_url
-                );
-
-                match self.client.add_relay(relay_url.clone()).await {
-                    Ok(_) => {
-                        tracing::debug!(
-                            target: "whitenoise::nostr_manager::ensure_relays_connected",
-                            "Successfully added relay: {}",
-                            relay_url
-                        );
-                        Ok(())
-                    }
-                    Err(e) => {
-                        tracing::debug!(
-                            target: "whitenoise::nostr_manager::ensure_relays_connected",
-                            "Failed to add relay {}: {}",
-                            relay_url,
-                            e
-                        );
-                        Err(NostrManagerError::Client(e))
-                    }
-                }
-            }
-        }
+    /// Relays in the client's relay pool
+    pub(crate) async fn relays(&self) -> Vec<RelayUrl> {
+        self.network.relays().await
     }
 
     /// Counts active subscriptions for a specific account by checking subscription IDs.
@@ -453,7 +571,7 @@ impl NostrManager {
     pub(crate) async fn count_subscriptions_for_account(&self, pubkey: &PublicKey) -> usize {
         let hash = self.create_pubkey_hash(pubkey);
         let prefix = format!("{}_", hash);
-        self.client
+        self.network
             .subscriptions()
             .await
             .keys()
@@ -463,7 +581,7 @@ impl NostrManager {
 
     /// Counts active global subscriptions by checking for subscription IDs that start with "global_users_".
     pub(crate) async fn count_global_subscriptions(&self) -> usize {
-        self.client
+        self.network
             .subscriptions()
             .await
             .keys()
@@ -473,8 +591,13 @@ impl NostrManager {
 
     /// Checks if at least one relay in the provided list is connected or connecting.
     /// Returns true if any relay is in Connected or Connecting state.
+    ///
+    /// Relays in backoff never count, even while their socket is still closing.
     pub(crate) async fn has_any_relay_connected(&self, relay_urls: &[RelayUrl]) -> bool {
         for relay_url in relay_urls {
+            if self.is_relay_backing_off(relay_url) {
+                continue;
+            }
             match self.get_relay_status(relay_url).await {
                 Ok(RelayStatus::Connected | RelayStatus::Connecting) => return true,
                 _ => continue,
@@ -491,6 +614,42 @@ mod subscription_monitoring_tests {
     use std::sync::Arc;
     use tokio::sync::mpsc;
 
+    #[tokio::test]
+    async fn test_persisted_session_salt_keeps_subscription_ids_stable() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(SESSION_SALT_FILE);
+        let salt = NostrManager::load_or_create_session_salt(&path).unwrap();
+        assert_eq!(
+            NostrManager::load_or_create_session_salt(&path).unwrap(),
+            salt
+        );
+
+        let pubkey = Keys::generate().public_key();
+        let mut hashes = Vec::new();
+        for _ in 0..2 {
+            let (event_sender, _receiver) = mpsc::channel(100);
+            let nostr_manager = NostrManager::new(
+                event_sender,
+                Arc::new(NoEventTracker),
+                NostrManager::default_timeout(),
+            )
+            .await
+            .unwrap()
+            .with_session_salt(salt);
+            hashes.push(nostr_manager.create_pubkey_hash(&pubkey));
+        }
+        assert_eq!(hashes[0], hashes[1]);
+
+        // An unreadable salt is replaced
+        std::fs::write(&path, "not a salt").unwrap();
+        let replaced = NostrManager::load_or_create_session_salt(&path).unwrap();
+        assert_ne!(replaced, salt);
+        assert_eq!(
+            NostrManager::load_or_create_session_salt(&path).unwrap(),
+            replaced
+        );
+    }
+
     #[tokio::test]
     async fn test_count_subscriptions_for_account_empty() {
         let (event_sender, _receiver) = mpsc::channel(100);
@@ -507,6 +666,29 @@ mod subscription_monitoring_tests {
         assert_eq!(count, 0);
     }
 
+    #[tokio::test]
+    async fn test_subscription_breakdown_empty() {
+        let (event_sender, _receiver) = mpsc::channel(100);
+        let event_tracker = Arc::new(NoEventTracker);
+        let nostr_manager =
+            NostrManager::new(event_sender, event_tracker, NostrManager::default_timeout())
+                .await
+                .unwrap();
+
+        let pubkey = Keys::generate().public_key();
+        let breakdown = nostr_manager.subscription_breakdown(&pubkey, &[]).await;
+
+        assert_eq!(breakdown, SubscriptionBreakdown::default());
+        assert_eq!(
+            breakdown.missing(false),
+            vec![
+                SubscriptionCategory::FollowLists,
+                SubscriptionCategory::Giftwraps
+            ]
+        );
+        assert_eq!(breakdown.missing(true).len(), 3);
+    }
+
     #[tokio::test]
     async fn test_count_global_subscriptions_empty() {
         let (event_sender, _receiver) = mpsc::channel(100);
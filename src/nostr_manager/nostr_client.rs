@@ -0,0 +1,639 @@
+//! Network-facing operations of the Nostr manager
+//!
+//! [`NostrClient`] is the part of [`NostrManager`](super::NostrManager) that talks to
+//! relays: connecting them, publishing, fetching, streaming and subscribing. The manager goes
+//! through a
+//! [`RelayPoolClient`] unless it is given another client, such as [`MemoryNostrClient`],
+//! an in-memory relay pool that lets tests run without relays.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use nostr_sdk::prelude::*;
+use tokio::sync::mpsc;
+
+use super::reconnect::{self, RelayBackoff};
+use super::{NostrManagerError, PublishOutcome, ReconnectPolicy, Result};
+
+/// How many streamed events are buffered before the stream waits for the reader
+const STREAM_BUFFER_SIZE: usize = 100;
+
+/// Operations that go over the network to relays
+#[async_trait]
+pub(crate) trait NostrClient: Send + Sync {
+    /// Add the missing `relays` to the pool and connect them
+    ///
+    /// Fails only when none of the relays could be added.
+    async fn connect_relays(&self, relays: &[RelayUrl]) -> Result<()>;
+
+    /// Relays in the pool
+    async fn relays(&self) -> Vec<RelayUrl>;
+
+    /// Connection status of a relay in the pool
+    async fn relay_status(&self, relay_url: &RelayUrl) -> Result<RelayStatus>;
+
+    /// Send a signed event to `relays` and report what each relay did with it
+    async fn send_event_to(&self, relays: &[RelayUrl], event: &Event) -> Result<PublishOutcome>;
+
+    /// Fetch the events matching `filter` stored on `relays`, waiting at most `timeout`
+    async fn fetch_events_from(
+        &self,
+        relays: &[RelayUrl],
+        filter: Filter,
+        timeout: Duration,
+    ) -> Result<Vec<Event>>;
+
+    /// Stream the events matching `filter` stored on `relays` as they arrive
+    ///
+    /// The stream ends once every relay sent all its stored events or `timeout` elapsed.
+    async fn stream_events_from(
+        &self,
+        relays: &[RelayUrl],
+        filter: Filter,
+        timeout: Duration,
+    ) -> Result<mpsc::Receiver<Event>>;
+
+    /// Subscribe to events matching `filter` on `relays`
+    ///
+    /// Matching events are forwarded to the event processor, tagged with `subscription_id`.
+    /// A subscription with the same id on one of the relays is replaced.
+    async fn subscribe_to(
+        &self,
+        subscription_id: SubscriptionId,
+        relays: &[RelayUrl],
+        filter: Filter,
+    ) -> Result<()>;
+
+    /// Close a subscription on every relay holding it
+    async fn unsubscribe(&self, subscription_id: &SubscriptionId);
+
+    /// Close every subscription on every relay
+    async fn unsubscribe_all(&self);
+
+    /// Active subscriptions, with the relays holding each of them
+    async fn subscriptions(&self) -> HashMap<SubscriptionId, Vec<RelayUrl>>;
+}
+
+/// [`NostrClient`] backed by the relay pool of a nostr-sdk [`Client`]
+///
+/// Relays are added with the reconnection options of the manager's [`ReconnectPolicy`], and
+/// ones in backoff are left disconnected until their delay has passed.
+pub(crate) struct RelayPoolClient {
+    client: Client,
+    reconnect_policy: ReconnectPolicy,
+    relay_backoff: Arc<RelayBackoff>,
+}
+
+impl RelayPoolClient {
+    pub(crate) fn new(
+        client: Client,
+        reconnect_policy: ReconnectPolicy,
+        relay_backoff: Arc<RelayBackoff>,
+    ) -> Self {
+        Self {
+            client,
+            reconnect_policy,
+            relay_backoff,
+        }
+    }
+
+    /// Add the relay to the pool unless it's there already
+    ///
+    /// Newly added relays are watched so they're backed off once their connection keeps
+    /// failing.
+    async fn ensure_relay_in_pool(&self, relay_url: &RelayUrl) -> Result<()> {
+        if self.client.relay(relay_url).await.is_ok() {
+            tracing::debug!(
+                target: "whitenoise::nostr_manager::ensure_relays_connected",
+                "Relay {} already connected",
+                relay_url
+            );
+            return Ok(());
+        }
+
+        tracing::debug!(
+            target: "whitenoise::nostr_manager::ensure_relays_connected",
+            "Adding new relay: {}",
+            relay_url
+        );
+
+        match self
+            .client
+            .pool()
+            .add_relay(relay_url.clone(), self.reconnect_policy.relay_options())
+            .await
+        {
+            Ok(added) => {
+                tracing::debug!(
+                    target: "whitenoise::nostr_manager::ensure_relays_connected",
+                    "Successfully added relay: {}",
+                    relay_url
+                );
+                if added && let Ok(relay) = self.client.relay(relay_url).await {
+                    reconnect::watch_relay_status(
+                        self.client.clone(),
+                        relay,
+                        self.relay_backoff.clone(),
+                        self.reconnect_policy.clone(),
+                    );
+                }
+                Ok(())
+            }
+            Err(e) => {
+                tracing::debug!(
+                    target: "whitenoise::nostr_manager::ensure_relays_connected",
+                    "Failed to add relay {}: {}",
+                    relay_url,
+                    e
+                );
+                Err(NostrManagerError::Client(e.into()))
+            }
+        }
+    }
+
+    /// Connect every relay in the pool that isn't backing off
+    async fn connect_relays_not_backing_off(&self) -> Result<()> {
+        let backing_off = self.relay_backoff.backing_off();
+        if backing_off.is_empty() {
+            self.client.connect().await;
+            return Ok(());
+        }
+
+        for relay_url in self.client.relays().await.into_keys() {
+            if !backing_off.contains(&relay_url) {
+                self.client.connect_relay(&relay_url).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl NostrClient for RelayPoolClient {
+    async fn connect_relays(&self, relays: &[RelayUrl]) -> Result<()> {
+        if relays.is_empty() {
+            return Ok(());
+        }
+
+        tracing::debug!(
+            target: "whitenoise::nostr_manager::ensure_relays_connected",
+            "Ensuring connection to {} relay URLs",
+            relays.len()
+        );
+
+        let results = futures::future::join_all(
+            relays
+                .iter()
+                .map(|relay_url| self.ensure_relay_in_pool(relay_url)),
+        )
+        .await;
+
+        let mut successful_relays = 0usize;
+        let mut last_error: Option<NostrManagerError> = None;
+
+        for (relay_url, result) in relays.iter().zip(results.into_iter()) {
+            match result {
+                Ok(_) => successful_relays += 1,
+                Err(err) => {
+                    tracing::warn!(
+                        target: "whitenoise::nostr_manager::ensure_relays_connected",
+                        "Continuing without relay {}: {}",
+                        relay_url,
+                        err
+                    );
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        if successful_relays == 0 {
+            let err = last_error.unwrap_or(NostrManagerError::NoRelayConnections);
+            tracing::error!(
+                target: "whitenoise::nostr_manager::ensure_relays_connected",
+                "Failed to ensure any relays connected: {}",
+                err
+            );
+            return Err(err);
+        }
+
+        if successful_relays < relays.len() {
+            tracing::debug!(
+                target: "whitenoise::nostr_manager::ensure_relays_connected",
+                "Ensured {} of {} relay connections; continuing best-effort",
+                successful_relays,
+                relays.len()
+            );
+        }
+
+        // Relays in backoff stay disconnected until their delay has passed
+        self.connect_relays_not_backing_off().await?;
+
+        tracing::debug!(
+            target: "whitenoise::nostr_manager::ensure_relays_connected",
+            "Relay connections ensuring completed"
+        );
+
+        Ok(())
+    }
+
+    async fn relays(&self) -> Vec<RelayUrl> {
+        self.client.relays().await.into_keys().collect()
+    }
+
+    async fn relay_status(&self, relay_url: &RelayUrl) -> Result<RelayStatus> {
+        Ok(self.client.relay(relay_url).await?.status())
+    }
+
+    async fn send_event_to(&self, relays: &[RelayUrl], event: &Event) -> Result<PublishOutcome> {
+        let output = self.client.send_event_to(relays, event).await?;
+        Ok(PublishOutcome::from_output(output, relays))
+    }
+
+    async fn fetch_events_from(
+        &self,
+        relays: &[RelayUrl],
+        filter: Filter,
+        timeout: Duration,
+    ) -> Result<Vec<Event>> {
+        let events = self
+            .client
+            .fetch_events_from(relays, filter, timeout)
+            .await?;
+        Ok(events.into_iter().collect())
+    }
+
+    async fn stream_events_from(
+        &self,
+        relays: &[RelayUrl],
+        filter: Filter,
+        timeout: Duration,
+    ) -> Result<mpsc::Receiver<Event>> {
+        let mut stream = self
+            .client
+            .stream_events_from(relays, filter, timeout)
+            .await?;
+
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER_SIZE);
+        tokio::spawn(async move {
+            while let Some(event) = stream.next().await {
+                if sender.send(event).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(receiver)
+    }
+
+    async fn subscribe_to(
+        &self,
+        subscription_id: SubscriptionId,
+        relays: &[RelayUrl],
+        filter: Filter,
+    ) -> Result<()> {
+        self.client
+            .subscribe_with_id_to(relays, subscription_id, filter, None)
+            .await?;
+        Ok(())
+    }
+
+    async fn unsubscribe(&self, subscription_id: &SubscriptionId) {
+        self.client.unsubscribe(subscription_id).await;
+    }
+
+    async fn unsubscribe_all(&self) {
+        self.client.unsubscribe_all().await;
+    }
+
+    async fn subscriptions(&self) -> HashMap<SubscriptionId, Vec<RelayUrl>> {
+        let mut subscriptions: HashMap<SubscriptionId, Vec<RelayUrl>> = HashMap::new();
+        for (relay_url, relay) in self.client.relays().await {
+            for subscription_id in relay.subscriptions().await.into_keys() {
+                subscriptions
+                    .entry(subscription_id)
+                    .or_default()
+                    .push(relay_url.clone());
+            }
+        }
+        subscriptions
+    }
+}
+
+#[cfg(test)]
+pub(crate) use memory::MemoryNostrClient;
+
+#[cfg(test)]
+mod memory {
+    use std::collections::HashSet;
+    use std::sync::RwLock;
+
+    use super::*;
+    use crate::nostr_manager::RelayPublishStatus;
+    use crate::types::{ProcessableEvent, ProcessableEventSender};
+
+    /// In-memory relay pool for tests
+    ///
+    /// Connecting a relay only adds it to the pool, after which it reports as connected.
+    /// Every relay accepts every event. Published events are stored per relay, fetches and
+    /// streams answer from what's stored, and subscriptions forward matching events to the
+    /// event processor, the way relays deliver them through the notification handler.
+    #[derive(Default)]
+    pub(crate) struct MemoryNostrClient {
+        relays: RwLock<HashSet<RelayUrl>>,
+        events: RwLock<Vec<(RelayUrl, Event)>>,
+        subscriptions: RwLock<HashMap<SubscriptionId, HashMap<RelayUrl, Filter>>>,
+        event_sender: Option<ProcessableEventSender>,
+    }
+
+    impl MemoryNostrClient {
+        /// Forward events matching a subscription to `event_sender`
+        pub(crate) fn with_event_sender(event_sender: ProcessableEventSender) -> Self {
+            Self {
+                event_sender: Some(event_sender),
+                ..Self::default()
+            }
+        }
+
+        /// Every event published so far, with the relay it was published to
+        pub(crate) fn published(&self) -> Vec<(RelayUrl, Event)> {
+            self.events.read().unwrap().clone()
+        }
+
+        fn stored(&self, relays: &[RelayUrl], filter: &Filter) -> Vec<Event> {
+            let mut events: Vec<Event> = Vec::new();
+            for (relay_url, event) in self.events.read().unwrap().iter() {
+                if relays.contains(relay_url)
+                    && matches(filter, event)
+                    && !events.iter().any(|e| e.id == event.id)
+                {
+                    events.push(event.clone());
+                }
+            }
+            // Relays return the newest events first
+            events.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+            if let Some(limit) = filter.limit {
+                events.truncate(limit);
+            }
+            events
+        }
+
+        async fn deliver(&self, relay_url: &RelayUrl, event: &Event) {
+            let Some(sender) = &self.event_sender else {
+                return;
+            };
+            let matching: Vec<SubscriptionId> = self
+                .subscriptions
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|(_, filters)| {
+                    filters
+                        .get(relay_url)
+                        .is_some_and(|filter| matches(filter, event))
+                })
+                .map(|(id, _)| id.clone())
+                .collect();
+            for subscription_id in matching {
+                let _ = sender
+                    .send(ProcessableEvent::new_relay_event(
+                        event.clone(),
+                        Some(subscription_id.to_string()),
+                        relay_url.clone(),
+                    ))
+                    .await;
+            }
+        }
+    }
+
+    /// Whether `event` matches `filter`, for the fields the tests use
+    fn matches(filter: &Filter, event: &Event) -> bool {
+        filter
+            .ids
+            .as_ref()
+            .is_none_or(|ids| ids.contains(&event.id))
+            && filter
+                .authors
+                .as_ref()
+                .is_none_or(|authors| authors.contains(&event.pubkey))
+            && filter
+                .kinds
+                .as_ref()
+                .is_none_or(|kinds| kinds.contains(&event.kind))
+            && filter.since.is_none_or(|since| event.created_at >= since)
+            && filter.until.is_none_or(|until| event.created_at <= until)
+            && filter.generic_tags.iter().all(|(tag, values)| {
+                event.tags.iter().any(|event_tag| {
+                    event_tag.single_letter_tag() == Some(*tag)
+                        && event_tag
+                            .content()
+                            .is_some_and(|content| values.contains(content))
+                })
+            })
+    }
+
+    #[async_trait]
+    impl NostrClient for MemoryNostrClient {
+        async fn connect_relays(&self, relays: &[RelayUrl]) -> Result<()> {
+            self.relays.write().unwrap().extend(relays.iter().cloned());
+            Ok(())
+        }
+
+        async fn relays(&self) -> Vec<RelayUrl> {
+            self.relays.read().unwrap().iter().cloned().collect()
+        }
+
+        async fn relay_status(&self, relay_url: &RelayUrl) -> Result<RelayStatus> {
+            if self.relays.read().unwrap().contains(relay_url) {
+                Ok(RelayStatus::Connected)
+            } else {
+                Ok(RelayStatus::Disconnected)
+            }
+        }
+
+        async fn send_event_to(
+            &self,
+            relays: &[RelayUrl],
+            event: &Event,
+        ) -> Result<PublishOutcome> {
+            self.events
+                .write()
+                .unwrap()
+                .extend(relays.iter().map(|url| (url.clone(), event.clone())));
+            for relay_url in relays {
+                self.deliver(relay_url, event).await;
+            }
+
+            Ok(PublishOutcome {
+                event_id: event.id,
+                relays: relays
+                    .iter()
+                    .map(|url| (url.clone(), RelayPublishStatus::Accepted))
+                    .collect(),
+            })
+        }
+
+        async fn fetch_events_from(
+            &self,
+            relays: &[RelayUrl],
+            filter: Filter,
+            _timeout: Duration,
+        ) -> Result<Vec<Event>> {
+            Ok(self.stored(relays, &filter))
+        }
+
+        async fn stream_events_from(
+            &self,
+            relays: &[RelayUrl],
+            filter: Filter,
+            _timeout: Duration,
+        ) -> Result<mpsc::Receiver<Event>> {
+            let events = self.stored(relays, &filter);
+            let (sender, receiver) = mpsc::channel(events.len().max(1));
+            for event in events {
+                let _ = sender.try_send(event);
+            }
+            Ok(receiver)
+        }
+
+        async fn subscribe_to(
+            &self,
+            subscription_id: SubscriptionId,
+            relays: &[RelayUrl],
+            filter: Filter,
+        ) -> Result<()> {
+            // Like relays, send what's stored before anything new
+            let stored: Vec<(RelayUrl, Event)> = self
+                .events
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|(url, event)| relays.contains(url) && matches(&filter, event))
+                .cloned()
+                .collect();
+            self.subscriptions
+                .write()
+                .unwrap()
+                .entry(subscription_id.clone())
+                .or_default()
+                .extend(relays.iter().map(|url| (url.clone(), filter.clone())));
+            if let Some(sender) = &self.event_sender {
+                for (relay_url, event) in stored {
+                    let _ = sender
+                        .send(ProcessableEvent::new_relay_event(
+                            event,
+                            Some(subscription_id.to_string()),
+                            relay_url,
+                        ))
+                        .await;
+                }
+            }
+            Ok(())
+        }
+
+        async fn unsubscribe(&self, subscription_id: &SubscriptionId) {
+            self.subscriptions.write().unwrap().remove(subscription_id);
+        }
+
+        async fn unsubscribe_all(&self) {
+            self.subscriptions.write().unwrap().clear();
+        }
+
+        async fn subscriptions(&self) -> HashMap<SubscriptionId, Vec<RelayUrl>> {
+            self.subscriptions
+                .read()
+                .unwrap()
+                .iter()
+                .map(|(id, filters)| (id.clone(), filters.keys().cloned().collect()))
+                .collect()
+        }
+    }
+
+    mod tests {
+        use super::*;
+
+        #[tokio::test]
+        async fn test_memory_client_connects_publishes_fetches_and_delivers_to_subscriptions() {
+            let relay = RelayUrl::parse("wss://memory.example.com").unwrap();
+            let other_relay = RelayUrl::parse("wss://other.example.com").unwrap();
+            let keys = Keys::generate();
+
+            let (priority_sender, _priority_receiver) = mpsc::channel(10);
+            let (normal_sender, mut normal_receiver) = mpsc::channel(10);
+            let client = MemoryNostrClient::with_event_sender(ProcessableEventSender::new(
+                priority_sender,
+                normal_sender,
+            ));
+
+            client
+                .connect_relays(std::slice::from_ref(&relay))
+                .await
+                .unwrap();
+            assert_eq!(client.relays().await, vec![relay.clone()]);
+            assert_eq!(
+                client.relay_status(&relay).await.unwrap(),
+                RelayStatus::Connected
+            );
+            assert_eq!(
+                client.relay_status(&other_relay).await.unwrap(),
+                RelayStatus::Disconnected
+            );
+
+            let subscription_id = SubscriptionId::new("test_metadata");
+            client
+                .subscribe_to(
+                    subscription_id.clone(),
+                    std::slice::from_ref(&relay),
+                    Filter::new().kind(Kind::Metadata).author(keys.public_key()),
+                )
+                .await
+                .unwrap();
+
+            let metadata = EventBuilder::metadata(&Metadata::new().name("alice"))
+                .sign_with_keys(&keys)
+                .unwrap();
+            let note = EventBuilder::text_note("hello")
+                .sign_with_keys(&keys)
+                .unwrap();
+            for event in [metadata.clone(), note] {
+                let outcome = client
+                    .send_event_to(std::slice::from_ref(&relay), &event)
+                    .await
+                    .unwrap();
+                assert!(outcome.is_success());
+            }
+
+            // Only the metadata matches the subscription
+            match normal_receiver.try_recv().unwrap() {
+                ProcessableEvent::NostrEvent {
+                    event,
+                    subscription_id: Some(id),
+                    ..
+                } => {
+                    assert_eq!(event.id, metadata.id);
+                    assert_eq!(id, subscription_id.to_string());
+                }
+                other => panic!("Expected a subscription event, got {:?}", other),
+            }
+            assert!(normal_receiver.try_recv().is_err());
+            assert_eq!(client.published().len(), 2);
+
+            let fetched = client
+                .fetch_events_from(
+                    std::slice::from_ref(&relay),
+                    Filter::new().kind(Kind::Metadata),
+                    Duration::from_secs(1),
+                )
+                .await
+                .unwrap();
+            assert_eq!(fetched, vec![metadata.clone()]);
+
+            let mut stream = client
+                .stream_events_from(&[other_relay], Filter::new(), Duration::from_secs(1))
+                .await
+                .unwrap();
+            assert!(stream.recv().await.is_none());
+        }
+    }
+}
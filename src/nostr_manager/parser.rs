@@ -1,9 +1,18 @@
 //! This module contains the logic for parsing Nostr events into tokens.
 
+use std::collections::HashMap;
+
+use nostr_sdk::nips::nip19::{FromBech32, Nip19Profile};
 use nostr_sdk::parser::{NostrParser, Token};
+use nostr_sdk::{PublicKey, Url};
 use serde::{Deserialize, Serialize};
 
 use crate::nostr_manager::NostrManager;
+use crate::whitenoise::utils::preview_text;
+
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "heic", "avif"];
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mov", "webm", "mkv"];
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "m4a", "ogg", "opus", "wav", "aac", "flac"];
 
 /// Parser trait for parsing content into tokens
 /// This trait is designed to be thread-safe for use with Flutter Rust Bridge (FRB)
@@ -54,6 +63,86 @@ impl<'a> From<Token<'a>> for SerializableToken {
     }
 }
 
+impl SerializableToken {
+    /// Renders tokens as a short plain-text preview, e.g. for the chat list
+    ///
+    /// Links to images, videos and audio become an emoji and label ("📷 Photo"), other links
+    /// shorten to their host, and mentions become `@name` for users in `names` or a shortened
+    /// npub otherwise. Line breaks and runs of whitespace collapse into single spaces, and the
+    /// result is truncated like other message previews.
+    ///
+    /// # Arguments
+    /// * `tokens` - The parsed message content
+    /// * `names` - Display names of users that may be mentioned
+    pub fn to_preview_string(
+        tokens: &[SerializableToken],
+        names: &HashMap<PublicKey, String>,
+    ) -> String {
+        let rendered: String = tokens
+            .iter()
+            .map(|token| match token {
+                SerializableToken::Nostr(uri) => nostr_preview(uri, names),
+                SerializableToken::Url(url) => url_preview(url),
+                SerializableToken::Hashtag(tag) => format!("#{}", tag),
+                SerializableToken::Text(text) => text.clone(),
+                SerializableToken::LineBreak | SerializableToken::Whitespace => " ".to_string(),
+            })
+            .collect();
+
+        preview_text(&rendered.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+}
+
+/// `@name` for mentions of known users, a shortened bech32 entity otherwise
+fn nostr_preview(uri: &str, names: &HashMap<PublicKey, String>) -> String {
+    let entity = uri.strip_prefix("nostr:").unwrap_or(uri);
+    let pubkey = if entity.starts_with("npub1") {
+        PublicKey::from_bech32(entity).ok()
+    } else if entity.starts_with("nprofile1") {
+        Nip19Profile::from_bech32(entity)
+            .ok()
+            .map(|profile| profile.public_key)
+    } else {
+        None
+    };
+
+    let short: String = entity.chars().take(12).collect();
+    match pubkey {
+        Some(pubkey) => match names.get(&pubkey) {
+            Some(name) => format!("@{}", name),
+            None => format!("@{}…", short),
+        },
+        None => format!("{}…", short),
+    }
+}
+
+/// A media label for links to media files, the host for other links
+fn url_preview(url: &str) -> String {
+    let Ok(parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+
+    let extension = parsed
+        .path()
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+    if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        return "📷 Photo".to_string();
+    }
+    if VIDEO_EXTENSIONS.contains(&extension.as_str()) {
+        return "🎥 Video".to_string();
+    }
+    if AUDIO_EXTENSIONS.contains(&extension.as_str()) {
+        return "🎵 Audio".to_string();
+    }
+
+    match parsed.host_str() {
+        Some(host) => host.strip_prefix("www.").unwrap_or(host).to_string(),
+        None => url.to_string(),
+    }
+}
+
 impl NostrManager {
     /// Parses a string into a vector of serializable tokens.
     ///
@@ -361,6 +450,31 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_to_preview_string() {
+        use nostr_sdk::{Keys, ToBech32};
+
+        let nostr = setup_nostr_manager().await;
+        let alice = Keys::generate().public_key();
+        let stranger = Keys::generate().public_key().to_bech32().unwrap();
+        let names = HashMap::from([(alice, "alice".to_string())]);
+        let content = format!(
+            "hey nostr:{}\nlook https://www.example.com/post?id=1 #nostr\n\nhttps://cdn.example.com/cat.JPG nostr:{}",
+            alice.to_bech32().unwrap(),
+            stranger,
+        );
+
+        let preview = SerializableToken::to_preview_string(&nostr.parse(&content), &names);
+        assert_eq!(
+            preview,
+            format!(
+                "hey @alice look example.com #nostr 📷 Photo @{}…",
+                &stranger[..12]
+            )
+        );
+        assert_eq!(SerializableToken::to_preview_string(&[], &names), "");
+    }
+
     #[tokio::test]
     async fn test_error_cases() {
         // Test with a very long string
@@ -0,0 +1,242 @@
+//! Per-relay results of publishing an event
+
+use std::collections::HashMap;
+
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::types::RetryErrorClass;
+
+/// Machine-readable prefixes relays put in front of `OK` and `CLOSED` messages (NIP-01)
+const MACHINE_READABLE_PREFIXES: [&str; 9] = [
+    "duplicate",
+    "pow",
+    "blocked",
+    "rate-limited",
+    "invalid",
+    "restricted",
+    "mute",
+    "error",
+    "auth-required",
+];
+
+/// What a single relay did with a published event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelayPublishStatus {
+    /// The relay answered `OK` with `true`
+    Accepted,
+
+    /// The relay answered `OK` with `false`, or the event could not be sent to it
+    Rejected { reason: String },
+
+    /// The relay didn't answer in time
+    Timeout,
+}
+
+impl RelayPublishStatus {
+    /// The NIP-01 machine-readable prefix of a rejection reason, such as `blocked`
+    pub fn rejection_prefix(&self) -> Option<&str> {
+        let RelayPublishStatus::Rejected { reason } = self else {
+            return None;
+        };
+        let (prefix, _) = reason.split_once(':')?;
+        let prefix = prefix.trim();
+        MACHINE_READABLE_PREFIXES
+            .contains(&prefix)
+            .then_some(prefix)
+    }
+
+    /// Whether publishing to the relay again could succeed, `None` once it accepted
+    ///
+    /// Timeouts, connection errors (rejections without a machine-readable prefix), rate
+    /// limits and relay-side errors are transient. Rejections of the event itself or its
+    /// author, such as `invalid` or `blocked`, are permanent.
+    pub fn retry_class(&self) -> Option<RetryErrorClass> {
+        match self {
+            RelayPublishStatus::Accepted => None,
+            RelayPublishStatus::Timeout => Some(RetryErrorClass::Transient),
+            RelayPublishStatus::Rejected { .. } => match self.rejection_prefix() {
+                None | Some("rate-limited") | Some("error") => Some(RetryErrorClass::Transient),
+                Some(_) => Some(RetryErrorClass::Permanent),
+            },
+        }
+    }
+}
+
+/// Result of publishing an event, for each relay it was sent to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishOutcome {
+    pub event_id: EventId,
+    pub relays: HashMap<RelayUrl, RelayPublishStatus>,
+}
+
+impl PublishOutcome {
+    /// Build the outcome from the relays' `OK` messages
+    ///
+    /// Target relays missing from the output never answered and count as timed out.
+    pub(crate) fn from_output(output: Output<EventId>, targets: &[RelayUrl]) -> Self {
+        let mut relays: HashMap<RelayUrl, RelayPublishStatus> = targets
+            .iter()
+            .map(|url| (url.clone(), RelayPublishStatus::Timeout))
+            .collect();
+        for url in output.success {
+            relays.insert(url, RelayPublishStatus::Accepted);
+        }
+        for (url, reason) in output.failed {
+            let status = if reason.to_lowercase().contains("timeout") {
+                RelayPublishStatus::Timeout
+            } else {
+                RelayPublishStatus::Rejected { reason }
+            };
+            relays.insert(url, status);
+        }
+
+        Self {
+            event_id: output.val,
+            relays,
+        }
+    }
+
+    pub fn id(&self) -> &EventId {
+        &self.event_id
+    }
+
+    /// Whether at least one relay accepted the event
+    pub fn is_success(&self) -> bool {
+        self.accepted().next().is_some()
+    }
+
+    /// Relays that accepted the event
+    pub fn accepted(&self) -> impl Iterator<Item = &RelayUrl> {
+        self.relays
+            .iter()
+            .filter(|(_, status)| **status == RelayPublishStatus::Accepted)
+            .map(|(url, _)| url)
+    }
+
+    /// Relays that rejected the event or didn't answer
+    pub fn failed(&self) -> impl Iterator<Item = (&RelayUrl, &RelayPublishStatus)> {
+        self.relays
+            .iter()
+            .filter(|(_, status)| **status != RelayPublishStatus::Accepted)
+    }
+
+    /// Take the statuses of the relays in `retry`, a later attempt of the same event
+    pub(crate) fn merge_retry(&mut self, retry: PublishOutcome) {
+        self.relays.extend(retry.relays);
+    }
+
+    /// Log every relay that didn't accept the event
+    pub(crate) fn log_failures(&self) {
+        for (url, status) in self.failed() {
+            match status {
+                RelayPublishStatus::Rejected { reason } => tracing::warn!(
+                    target: "whitenoise::nostr_manager::publish_outcome",
+                    "Relay {} rejected event {} ({}): {}",
+                    url,
+                    self.event_id,
+                    status.rejection_prefix().unwrap_or("unknown"),
+                    reason
+                ),
+                _ => tracing::warn!(
+                    target: "whitenoise::nostr_manager::publish_outcome",
+                    "Relay {} timed out publishing event {}",
+                    url,
+                    self.event_id
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_outcome_from_output() {
+        let accepted = RelayUrl::parse("wss://accepted.example.com").unwrap();
+        let rejected = RelayUrl::parse("wss://rejected.example.com").unwrap();
+        let timed_out = RelayUrl::parse("wss://timeout.example.com").unwrap();
+        let silent = RelayUrl::parse("wss://silent.example.com").unwrap();
+        let output = Output {
+            val: EventId::all_zeros(),
+            success: HashSet::from([accepted.clone()]),
+            failed: HashMap::from([
+                (
+                    rejected.clone(),
+                    "blocked: pubkey not on the whitelist".to_string(),
+                ),
+                (timed_out.clone(), "timeout".to_string()),
+            ]),
+        };
+
+        let outcome = PublishOutcome::from_output(
+            output,
+            &[
+                accepted.clone(),
+                rejected.clone(),
+                timed_out.clone(),
+                silent.clone(),
+            ],
+        );
+        assert!(outcome.is_success());
+        assert_eq!(outcome.accepted().collect::<Vec<_>>(), vec![&accepted]);
+        assert_eq!(outcome.failed().count(), 3);
+        assert_eq!(
+            outcome.relays[&rejected].rejection_prefix(),
+            Some("blocked")
+        );
+        assert_eq!(outcome.relays[&timed_out], RelayPublishStatus::Timeout);
+        assert_eq!(outcome.relays[&silent], RelayPublishStatus::Timeout);
+    }
+
+    #[test]
+    fn test_retry_class_separates_transient_failures() {
+        let rejected = |reason: &str| RelayPublishStatus::Rejected {
+            reason: reason.to_string(),
+        };
+        assert_eq!(RelayPublishStatus::Accepted.retry_class(), None);
+        assert_eq!(
+            RelayPublishStatus::Timeout.retry_class(),
+            Some(RetryErrorClass::Transient)
+        );
+        assert_eq!(
+            rejected("relay not connected").retry_class(),
+            Some(RetryErrorClass::Transient)
+        );
+        assert_eq!(
+            rejected("rate-limited: slow down").retry_class(),
+            Some(RetryErrorClass::Transient)
+        );
+        assert_eq!(
+            rejected("invalid: bad signature").retry_class(),
+            Some(RetryErrorClass::Permanent)
+        );
+        assert_eq!(
+            rejected("blocked: pubkey not on the whitelist").retry_class(),
+            Some(RetryErrorClass::Permanent)
+        );
+    }
+
+    #[test]
+    fn test_rejection_prefix() {
+        let status = RelayPublishStatus::Rejected {
+            reason: "not a prefix: whatever".to_string(),
+        };
+        assert_eq!(status.rejection_prefix(), None);
+        let status = RelayPublishStatus::Rejected {
+            reason: "no prefix at all".to_string(),
+        };
+        assert_eq!(status.rejection_prefix(), None);
+        assert_eq!(RelayPublishStatus::Accepted.rejection_prefix(), None);
+
+        let outcome = PublishOutcome {
+            event_id: EventId::all_zeros(),
+            relays: HashMap::new(),
+        };
+        assert!(!outcome.is_success());
+    }
+}
@@ -1,10 +1,14 @@
 //! This module contains functions for publishing Nostr events and handling the publish tracking process.
 
+use std::{collections::HashMap, time::Duration};
+
+use ::rand::Rng;
 use nostr_sdk::prelude::*;
 
 use crate::{
-    RelayType,
-    nostr_manager::{NostrManager, NostrManagerError, Result},
+    RelayMarker, RelayType,
+    nostr_manager::{NostrManager, NostrManagerError, PublishOutcome, Result},
+    types::RetryInfo,
 };
 
 impl NostrManager {
@@ -33,7 +37,7 @@ impl NostrManager {
                     tracing::debug!(
                         target: "whitenoise::nostr_manager::background_publish_event_to",
                         "Successfully published message to {} relay(s)",
-                        output.success.len()
+                        output.accepted().count()
                     );
                 }
                 Err(e) => {
@@ -63,7 +67,7 @@ impl NostrManager {
         account_pubkey: PublicKey,
         relays: &[RelayUrl],
         signer: impl NostrSigner + 'static,
-    ) -> Result<Output<EventId>> {
+    ) -> Result<PublishOutcome> {
         let wrapped_event =
             EventBuilder::gift_wrap(&signer, receiver, rumor, extra_tags.to_vec()).await?;
         self.publish_event_to(wrapped_event, &account_pubkey, relays)
@@ -78,7 +82,7 @@ impl NostrManager {
         metadata: &Metadata,
         relays: &[RelayUrl],
         signer: impl NostrSigner + 'static,
-    ) -> Result<Output<EventId>> {
+    ) -> Result<PublishOutcome> {
         let event_builder = EventBuilder::metadata(metadata);
         self.publish_event_builder_with_signer(event_builder, relays, signer)
             .await
@@ -86,18 +90,24 @@ impl NostrManager {
 
     /// Publishes a Nostr relay list event using the provided signer.
     ///
+    /// NIP-65 relay lists mark each relay with its entry in `markers`; relays missing from
+    /// `markers`, and relays of the other list types, are published unmarked (read+write).
     /// The event is automatically tracked in the database if published successfully.
     pub(crate) async fn publish_relay_list_with_signer(
         &self,
         relay_list: &[RelayUrl],
+        markers: &HashMap<RelayUrl, RelayMarker>,
         relay_type: RelayType,
         target_relays: &[RelayUrl],
         signer: impl NostrSigner + 'static,
-    ) -> Result<()> {
+    ) -> Result<PublishOutcome> {
         let tags: Vec<Tag> = match relay_type {
             RelayType::Nip65 => relay_list
                 .iter()
-                .map(|relay| Tag::reference(relay.to_string()))
+                .map(|relay| {
+                    let marker = markers.get(relay).copied().unwrap_or_default();
+                    Tag::relay_metadata(relay.clone(), marker.metadata())
+                })
                 .collect(),
             RelayType::Inbox | RelayType::KeyPackage => relay_list
                 .iter()
@@ -111,7 +121,7 @@ impl NostrManager {
             .await?;
         tracing::debug!(target: "whitenoise::nostr_manager::publish_relay_list_with_signer", "Published relay list event to Nostr: {:?}", result);
 
-        Ok(())
+        Ok(result)
     }
 
     /// Publishes a Nostr follow list event using the provided signer.
@@ -147,6 +157,46 @@ impl NostrManager {
         Ok(())
     }
 
+    /// Publishes a NIP-51 mute list event using the provided signer.
+    ///
+    /// Public entries are published as `p` tags; private entries are NIP-44 encrypted to the
+    /// signer's own key and stored in the content. Unlike the follow list, an empty mute list
+    /// is still published so that unmuting the last user reaches other clients.
+    pub(crate) async fn publish_mute_list_with_signer(
+        &self,
+        public: &[PublicKey],
+        private: &[PublicKey],
+        target_relays: &[RelayUrl],
+        signer: impl NostrSigner + 'static,
+    ) -> Result<()> {
+        let content = if private.is_empty() {
+            String::new()
+        } else {
+            let private_tags: Vec<Tag> = private
+                .iter()
+                .map(|pubkey| Tag::public_key(*pubkey))
+                .collect();
+            let own_pubkey = signer.get_public_key().await?;
+            signer
+                .nip44_encrypt(&own_pubkey, &serde_json::to_string(&private_tags)?)
+                .await?
+        };
+        let tags: Vec<Tag> = public
+            .iter()
+            .map(|pubkey| Tag::public_key(*pubkey))
+            .collect();
+        let event = EventBuilder::new(Kind::MuteList, content).tags(tags);
+        let result = self
+            .publish_event_builder_with_signer(event, target_relays, signer)
+            .await?;
+        tracing::debug!(
+            target: "whitenoise::nostr_manager::publish_mute_list_with_signer",
+            "Published mute list event to Nostr: {:?}",
+            result
+        );
+        Ok(())
+    }
+
     /// Publishes a Nostr MLS key package event using the provided signer.
     ///
     /// The event is automatically tracked in the database if published successfully.
@@ -156,7 +206,7 @@ impl NostrManager {
         relays: &[RelayUrl],
         tags: &[Tag],
         signer: impl NostrSigner + 'static,
-    ) -> Result<Output<EventId>> {
+    ) -> Result<PublishOutcome> {
         let key_package_event_builder =
             EventBuilder::new(Kind::MlsKeyPackage, encoded_key_package).tags(tags.to_vec());
 
@@ -172,7 +222,7 @@ impl NostrManager {
         event_id: &EventId,
         relays: &[RelayUrl],
         signer: impl NostrSigner + 'static,
-    ) -> Result<Output<EventId>> {
+    ) -> Result<PublishOutcome> {
         let event_deletion_event_builder =
             EventBuilder::delete(EventDeletionRequest::new().id(*event_id));
         self.publish_event_builder_with_signer(event_deletion_event_builder, relays, signer)
@@ -190,7 +240,7 @@ impl NostrManager {
         event_ids: &[EventId],
         relays: &[RelayUrl],
         signer: impl NostrSigner + 'static,
-    ) -> Result<Output<EventId>> {
+    ) -> Result<PublishOutcome> {
         if event_ids.is_empty() {
             return Err(NostrManagerError::WhitenoiseInstance(
                 "Cannot publish batch deletion with empty event_ids list".to_string(),
@@ -209,18 +259,23 @@ impl NostrManager {
     /// This method publishes a pre-signed event to a list of relay URLs. It ensures that the client
     /// is connected to all specified relays before attempting to publish. The event is automatically
     /// tracked in the database if published successfully to at least one relay.
+    ///
+    /// Returns what each relay did with the event; relays that didn't accept it are logged
+    /// with their NIP-01 rejection reason.
     pub(crate) async fn publish_event_to(
         &self,
         event: Event,
         account_pubkey: &PublicKey,
         relays: &[RelayUrl],
-    ) -> Result<Output<EventId>> {
+    ) -> Result<PublishOutcome> {
         // Ensure we're connected to all target relays before publishing
         self.ensure_relays_connected(relays).await?;
-        let result = self.client.send_event_to(relays, &event).await?;
+        let outcome = self.network.send_event_to(relays, &event).await?;
+        let result = self.retry_failed_relays(&event, outcome).await;
+        result.log_failures();
 
         // Track the published event if we have a successful result (best-effort)
-        if !result.success.is_empty() {
+        if result.is_success() {
             self.event_tracker
                 .track_published_event(result.id(), account_pubkey)
                 .await
@@ -236,28 +291,31 @@ impl NostrManager {
     /// The method ensures that the client is connected to all specified relays before attempting to publish.
     ///
     /// Automatically tracks published events in the database using the signer's public key.
+    /// Returns what each relay did with the event, as [`NostrManager::publish_event_to`] does.
     async fn publish_event_builder_with_signer(
         &self,
         event_builder: EventBuilder,
         relays: &[RelayUrl],
         signer: impl NostrSigner + 'static,
-    ) -> Result<Output<EventId>> {
+    ) -> Result<PublishOutcome> {
         // Get the public key from the signer for account lookup
         let pubkey = signer.get_public_key().await?;
 
+        // Sign up front so failed relays can be sent the same event again
+        let event = event_builder.sign(&signer).await?;
+
         // Ensure we're connected to all target relays before publishing
         self.ensure_relays_connected(relays).await?;
-        let result = self
+        let outcome = self
             .with_signer(signer, || async {
-                self.client
-                    .send_event_builder_to(relays, event_builder)
-                    .await
-                    .map_err(NostrManagerError::Client)
+                self.network.send_event_to(relays, &event).await
             })
             .await?;
+        let result = self.retry_failed_relays(&event, outcome).await;
+        result.log_failures();
 
         // Track the published event if we have a successful result (best-effort)
-        if !result.success.is_empty() {
+        if result.is_success() {
             self.event_tracker
                 .track_published_event(result.id(), &pubkey)
                 .await
@@ -266,6 +324,64 @@ impl NostrManager {
 
         Ok(result)
     }
+
+    /// Sends the event again to relays that failed transiently, as configured by `publish_retry`
+    ///
+    /// Retries back off exponentially with up to 50% random jitter. Only relays whose last
+    /// failure is retryable are sent the event again; relays that accepted or permanently
+    /// rejected it keep their status, so a partially successful publish finishes once the
+    /// remaining relays succeed, fail for good, or the attempts run out.
+    async fn retry_failed_relays(
+        &self,
+        event: &Event,
+        mut outcome: PublishOutcome,
+    ) -> PublishOutcome {
+        let Some(policy) = &self.publish_retry else {
+            return outcome;
+        };
+        let mut retry_info = RetryInfo::from_policy(policy);
+
+        while let Some(next_retry) = retry_info.next_attempt() {
+            let pending: Vec<RelayUrl> = outcome
+                .failed()
+                .filter(|(_, status)| {
+                    status
+                        .retry_class()
+                        .is_some_and(|class| next_retry.retryable.contains(&class))
+                })
+                .map(|(url, _)| url.clone())
+                .collect();
+            if pending.is_empty() {
+                break;
+            }
+
+            let delay_ms = next_retry.delay_ms();
+            let jitter_ms = ::rand::rng().random_range(0..=delay_ms / 2);
+            tracing::debug!(
+                target: "whitenoise::nostr_manager::retry_failed_relays",
+                "Retrying event {} on {} relay(s) in {}ms (attempt {}/{})",
+                event.id,
+                pending.len(),
+                delay_ms + jitter_ms,
+                next_retry.attempt,
+                next_retry.max_attempts
+            );
+            tokio::time::sleep(Duration::from_millis(delay_ms + jitter_ms)).await;
+
+            match self.network.send_event_to(&pending, event).await {
+                Ok(retry) => outcome.merge_retry(retry),
+                Err(e) => tracing::warn!(
+                    target: "whitenoise::nostr_manager::retry_failed_relays",
+                    "Retry of event {} failed: {}",
+                    event.id,
+                    e
+                ),
+            }
+            retry_info = next_retry;
+        }
+
+        outcome
+    }
 }
 
 #[cfg(test)]
@@ -725,7 +841,7 @@ mod publish_tests {
             Ok(output) => {
                 // All relay publishes should have failed
                 assert!(
-                    output.success.is_empty(),
+                    !output.is_success(),
                     "No relays should have succeeded with unreachable endpoints"
                 );
             }
@@ -775,8 +891,8 @@ mod publish_tests {
                 assert_eq!(*output.id(), event.id);
                 tracing::debug!(
                     "Published to {} successful relays, {} failed",
-                    output.success.len(),
-                    output.failed.len()
+                    output.accepted().count(),
+                    output.failed().count()
                 );
             }
             Err(e) => {
@@ -951,8 +1067,8 @@ mod publish_tests {
             Ok(output) => {
                 tracing::debug!(
                     "Published gift wrap to {} successful relays, {} failed",
-                    output.success.len(),
-                    output.failed.len()
+                    output.accepted().count(),
+                    output.failed().count()
                 );
             }
             Err(e) => {
@@ -1,12 +1,48 @@
 //! This module contains functions for querying Nostr events from relays.
 
+use std::collections::HashMap;
+use std::time::Duration;
+
 use nostr_sdk::prelude::*;
 
 use crate::{
     RelayType,
-    nostr_manager::{NostrManager, Result, utils::is_event_timestamp_valid},
+    nostr_manager::{
+        NostrManager, Result,
+        utils::{adjust_since_for_giftwrap, is_event_timestamp_valid},
+    },
 };
 
+/// Most authors put in a single filter by batched fetches
+const MAX_AUTHORS_PER_FILTER: usize = 500;
+
+/// How long one-off fetches of each kind of event wait for relays
+///
+/// A fetch doesn't fail when its timeout fires: it returns the events that arrived by then,
+/// so one slow relay only shortens the result rather than failing the fetch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchTimeouts {
+    /// Profile metadata (kind 0)
+    pub metadata: Duration,
+    /// NIP-65, inbox and key package relay lists
+    pub relay_lists: Duration,
+    /// Contact lists (kind 3), which can be large
+    pub contact_lists: Duration,
+    /// MLS key packages
+    pub key_packages: Duration,
+}
+
+impl Default for FetchTimeouts {
+    fn default() -> Self {
+        Self {
+            metadata: Duration::from_secs(5),
+            relay_lists: Duration::from_secs(5),
+            contact_lists: Duration::from_secs(10),
+            key_packages: Duration::from_secs(10),
+        }
+    }
+}
+
 impl NostrManager {
     pub(crate) async fn fetch_metadata_from(
         &self,
@@ -14,9 +50,9 @@ impl NostrManager {
         pubkey: PublicKey,
     ) -> Result<Option<Event>> {
         let filter: Filter = Filter::new().author(pubkey).kind(Kind::Metadata);
-        let events: Events = self
-            .client
-            .fetch_events_from(nip65_relay_urls, filter, self.timeout)
+        let events = self
+            .network
+            .fetch_events_from(nip65_relay_urls, filter, self.fetch_timeouts.metadata)
             .await?;
         Self::latest_from_events(events)
     }
@@ -29,12 +65,192 @@ impl NostrManager {
     ) -> Result<Option<Event>> {
         let filter = Filter::new().author(pubkey).kind(relay_type.into());
         let events = self
-            .client
-            .fetch_events_from(nip65_relay_urls, filter, self.timeout)
+            .network
+            .fetch_events_from(nip65_relay_urls, filter, self.fetch_timeouts.relay_lists)
             .await?;
         Self::latest_from_events(events)
     }
 
+    /// Fetches the newest NIP-02 contact list (kind 3) published by `pubkey`.
+    pub(crate) async fn fetch_contact_list(
+        &self,
+        pubkey: PublicKey,
+        relay_urls: &[RelayUrl],
+    ) -> Result<Option<Event>> {
+        let filter = Filter::new().author(pubkey).kind(Kind::ContactList);
+        let events = self
+            .network
+            .fetch_events_from(relay_urls, filter, self.fetch_timeouts.contact_lists)
+            .await?;
+        Self::latest_from_events(events)
+    }
+
+    /// Fetches the newest relay list of the given kind for each author in a single request.
+    ///
+    /// Authors without a relay list on the queried relays are absent from the result.
+    pub(crate) async fn fetch_relay_lists(
+        &self,
+        authors: &[PublicKey],
+        relay_type: RelayType,
+        relay_urls: &[RelayUrl],
+    ) -> Result<HashMap<PublicKey, Event>> {
+        self.fetch_latest_by_author(
+            authors,
+            relay_type.into(),
+            relay_urls,
+            self.fetch_timeouts.relay_lists,
+        )
+        .await
+    }
+
+    /// Fetches the newest metadata event (kind 0) of each author in a single request.
+    ///
+    /// Authors without metadata on the queried relays are absent from the result.
+    pub(crate) async fn fetch_metadata_batch(
+        &self,
+        authors: &[PublicKey],
+        relay_urls: &[RelayUrl],
+    ) -> Result<HashMap<PublicKey, Event>> {
+        self.fetch_latest_by_author(
+            authors,
+            Kind::Metadata,
+            relay_urls,
+            self.fetch_timeouts.metadata,
+        )
+        .await
+    }
+
+    /// Fetches the newest replaceable event of `kind` for each author.
+    ///
+    /// Authors are queried [`MAX_AUTHORS_PER_FILTER`] at a time, so long author lists don't
+    /// produce REQ messages that relays reject as oversized.
+    async fn fetch_latest_by_author(
+        &self,
+        authors: &[PublicKey],
+        kind: Kind,
+        relay_urls: &[RelayUrl],
+        timeout: Duration,
+    ) -> Result<HashMap<PublicKey, Event>> {
+        let mut latest: HashMap<PublicKey, Event> = HashMap::new();
+        if authors.is_empty() || relay_urls.is_empty() {
+            return Ok(latest);
+        }
+
+        for chunk in authors.chunks(MAX_AUTHORS_PER_FILTER) {
+            let filter = Filter::new().authors(chunk.iter().copied()).kind(kind);
+            let events = self
+                .network
+                .fetch_events_from(relay_urls, filter, timeout)
+                .await?;
+
+            for event in events.into_iter().filter(is_event_timestamp_valid) {
+                match latest.get(&event.pubkey) {
+                    Some(existing)
+                        if (existing.created_at, existing.id) >= (event.created_at, event.id) => {}
+                    _ => {
+                        latest.insert(event.pubkey, event);
+                    }
+                }
+            }
+        }
+        Ok(latest)
+    }
+
+    /// Fetches a single event by id, from the local event cache or else from the given relays.
+    ///
+    /// Events fetched from relays are added to the cache. Returns `None` if none of the relays
+    /// has the event within `timeout` (the manager's request timeout when `None`).
+    pub(crate) async fn fetch_event_by_id(
+        &self,
+        id: EventId,
+        relays: &[RelayUrl],
+        timeout: Option<Duration>,
+    ) -> Result<Option<Event>> {
+        if let Some(event) = self.client.database().event_by_id(&id).await? {
+            return Ok(Some(event));
+        }
+        if relays.is_empty() {
+            return Ok(None);
+        }
+
+        let filter = Filter::new().id(id).limit(1);
+        let events = self
+            .network
+            .fetch_events_from(relays, filter, timeout.unwrap_or(self.timeout))
+            .await?;
+        // Relays may ignore the id filter; only trust an event that actually has the id
+        let Some(event) = events.into_iter().find(|event| event.id == id) else {
+            return Ok(None);
+        };
+        self.client.database().save_event(&event).await?;
+        Ok(Some(event))
+    }
+
+    /// Fetches the group messages (kind 445) of groups from the given relays
+    ///
+    /// `nostr_group_ids` are the hex-encoded IDs the messages carry in their `h` tag. Every
+    /// message is fetched when `since` is `None`.
+    pub(crate) async fn fetch_group_messages(
+        &self,
+        relays: &[RelayUrl],
+        nostr_group_ids: &[String],
+        since: Option<Timestamp>,
+    ) -> Result<Vec<Event>> {
+        let mut filter = Filter::new()
+            .kind(Kind::MlsGroupMessage)
+            .custom_tags(SingleLetterTag::lowercase(Alphabet::H), nostr_group_ids);
+        if let Some(since) = since {
+            filter = filter.since(since);
+        }
+        self.network
+            .fetch_events_from(relays, filter, self.timeout)
+            .await
+    }
+
+    /// Fetches the profile metadata and lists (relay, contact and mute lists) `pubkey` published
+    ///
+    /// Every version is fetched when `since` is `None`.
+    pub(crate) async fn fetch_account_lists(
+        &self,
+        pubkey: PublicKey,
+        relays: &[RelayUrl],
+        since: Option<Timestamp>,
+    ) -> Result<Vec<Event>> {
+        let mut filter = Filter::new().author(pubkey).kinds([
+            Kind::Metadata,
+            Kind::RelayList,
+            Kind::InboxRelays,
+            Kind::MlsKeyPackageRelays,
+            Kind::ContactList,
+            Kind::MuteList,
+        ]);
+        if let Some(since) = since {
+            filter = filter.since(since);
+        }
+        self.network
+            .fetch_events_from(relays, filter, self.fetch_timeouts.contact_lists)
+            .await
+    }
+
+    /// Fetches the giftwraps (kind 1059) addressed to `pubkey`
+    ///
+    /// Giftwraps are backdated for privacy, so `since` is moved back by the same lookback the
+    /// giftwrap subscription uses. Every giftwrap is fetched when `since` is `None`.
+    pub(crate) async fn fetch_giftwraps(
+        &self,
+        pubkey: PublicKey,
+        inbox_relays: &[RelayUrl],
+        since: Option<Timestamp>,
+    ) -> Result<Vec<Event>> {
+        let mut filter = Filter::new().kind(Kind::GiftWrap).pubkey(pubkey);
+        if let Some(since) = adjust_since_for_giftwrap(since) {
+            filter = filter.since(since);
+        }
+        self.network
+            .fetch_events_from(inbox_relays, filter, self.timeout)
+            .await
+    }
+
     // TODO: Add key package validation logic here to check key package tags for correct extensions and version
     // We don't want to do this quite yet as we were publishing incorrect tags for a while. MLS will validate the actual values of the KeyPackage so we can't actually use a bad KeyPackage.
     pub(crate) async fn fetch_user_key_package(
@@ -44,13 +260,13 @@ impl NostrManager {
     ) -> Result<Option<Event>> {
         let filter = Filter::new().kind(Kind::MlsKeyPackage).author(pubkey);
         let events = self
-            .client
-            .fetch_events_from(relays, filter, self.timeout)
+            .network
+            .fetch_events_from(relays, filter, self.fetch_timeouts.key_packages)
             .await?;
         Self::latest_from_events(events)
     }
 
-    fn latest_from_events(events: Events) -> Result<Option<Event>> {
+    fn latest_from_events(events: Vec<Event>) -> Result<Option<Event>> {
         let latest = events
             .into_iter()
             .filter(is_event_timestamp_valid)
@@ -596,3 +812,35 @@ mod contact_list_logic_tests {
         assert_eq!(meta2.name, Some("bob".to_string()));
     }
 }
+
+#[cfg(test)]
+mod fetch_event_tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_fetch_event_by_id_uses_cache() {
+        let (sender, _receiver) = tokio::sync::mpsc::channel(10);
+        let nostr = NostrManager::new(
+            sender,
+            Arc::new(crate::whitenoise::event_tracker::NoEventTracker),
+            NostrManager::default_timeout(),
+        )
+        .await
+        .unwrap();
+
+        let event = EventBuilder::text_note("cached")
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        nostr.client.database().save_event(&event).await.unwrap();
+
+        // Without relays only the cache can answer
+        let fetched = nostr.fetch_event_by_id(event.id, &[], None).await.unwrap();
+        assert_eq!(fetched, Some(event));
+        let missing = nostr
+            .fetch_event_by_id(EventId::all_zeros(), &[], None)
+            .await
+            .unwrap();
+        assert!(missing.is_none());
+    }
+}
@@ -0,0 +1,467 @@
+//! Relay reconnection backoff
+//!
+//! nostr-sdk retries a dropped relay on a short interval of its own, which keeps a flaky
+//! relay in a tight reconnect loop. Relays that keep failing are disconnected instead and
+//! left alone for an exponentially growing, jittered delay before they are tried again.
+//! Failures are counted from dropped or refused connections (see [`watch_relay_status`]),
+//! from failed health probes and from relays answering with errors.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ::rand::Rng;
+use dashmap::DashMap;
+use nostr_sdk::prelude::*;
+
+use super::NostrManager;
+
+/// How relays are reconnected after failures
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    /// Delay before the first reconnection attempt
+    pub initial_delay: Duration,
+
+    /// Upper bound for any delay, jitter included
+    pub max_delay: Duration,
+
+    /// Factor the delay grows by with every consecutive backoff (values below 1.0 count as 1.0)
+    pub multiplier: f64,
+
+    /// Fraction of the delay randomly added or removed, from 0.0 (none) to 1.0
+    pub jitter: f64,
+
+    /// Consecutive failures after which a relay is backed off
+    pub failure_threshold: u32,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(10 * 60),
+            multiplier: 2.0,
+            jitter: 0.2,
+            failure_threshold: 3,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Delay before reconnecting after `attempt` consecutive backoffs, without jitter
+    ///
+    /// Grows from `initial_delay` by `multiplier` per attempt and never exceeds `max_delay`.
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let initial = self.initial_delay.min(self.max_delay).as_secs_f64();
+        let exponent = i32::try_from(attempt).unwrap_or(i32::MAX);
+        let delay = initial * self.multiplier.max(1.0).powi(exponent);
+
+        if !delay.is_finite() || delay >= self.max_delay.as_secs_f64() {
+            return self.max_delay;
+        }
+        Duration::from_secs_f64(delay)
+    }
+
+    /// [`Self::delay_for_attempt`] with random jitter applied
+    pub fn delay_with_jitter(&self, attempt: u32) -> Duration {
+        self.jittered_delay(attempt, ::rand::rng().random::<f64>())
+    }
+
+    /// Apply jitter using `sample` from `[0, 1]`: 0.0 removes the full jitter fraction,
+    /// 0.5 leaves the delay unchanged and 1.0 adds the full fraction
+    pub(crate) fn jittered_delay(&self, attempt: u32, sample: f64) -> Duration {
+        let delay = self.delay_for_attempt(attempt).as_secs_f64();
+        let jitter = if self.jitter.is_nan() {
+            0.0
+        } else {
+            self.jitter.clamp(0.0, 1.0)
+        };
+        let sample = if sample.is_nan() {
+            0.5
+        } else {
+            sample.clamp(0.0, 1.0)
+        };
+
+        let jittered = delay + delay * jitter * (sample * 2.0 - 1.0);
+        if jittered >= self.max_delay.as_secs_f64() {
+            return self.max_delay;
+        }
+        Duration::try_from_secs_f64(jittered.max(0.0)).unwrap_or(self.max_delay)
+    }
+
+    /// Options for relays added to the pool
+    ///
+    /// Reconnection intervals are a per-relay setting in nostr-sdk rather than a
+    /// `ClientOptions` one, so the policy is applied whenever the manager adds a relay.
+    pub(crate) fn relay_options(&self) -> RelayOptions {
+        RelayOptions::default()
+            .reconnect(true)
+            .retry_interval(self.initial_delay.min(self.max_delay))
+            .adjust_retry_interval(true)
+    }
+}
+
+#[derive(Debug, Default)]
+struct BackoffState {
+    /// Failures since the last success or backoff
+    failures: u32,
+    /// Backoffs since the last success, used as the attempt for the next delay
+    attempt: u32,
+    /// When the current backoff started and how long it lasts
+    backoff: Option<(Instant, Duration)>,
+}
+
+impl BackoffState {
+    fn is_backing_off(&self) -> bool {
+        self.backoff
+            .is_some_and(|(started, delay)| started.elapsed() < delay)
+    }
+}
+
+/// Consecutive failures and active backoffs per relay
+#[derive(Debug, Default)]
+pub(crate) struct RelayBackoff {
+    relays: DashMap<RelayUrl, BackoffState>,
+}
+
+impl RelayBackoff {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a failure, returning the backoff delay if the relay just reached the threshold
+    ///
+    /// Failures reported while the relay is already backing off are ignored.
+    pub(crate) fn record_failure(
+        &self,
+        relay_url: &RelayUrl,
+        policy: &ReconnectPolicy,
+    ) -> Option<Duration> {
+        let mut state = self.relays.entry(relay_url.clone()).or_default();
+        if state.is_backing_off() {
+            return None;
+        }
+
+        state.failures = state.failures.saturating_add(1);
+        if state.failures < policy.failure_threshold.max(1) {
+            return None;
+        }
+        Some(Self::begin(&mut state, policy))
+    }
+
+    /// Start a backoff for the relay regardless of its failure count
+    pub(crate) fn start(&self, relay_url: &RelayUrl, policy: &ReconnectPolicy) -> Duration {
+        let mut state = self.relays.entry(relay_url.clone()).or_default();
+        Self::begin(&mut state, policy)
+    }
+
+    fn begin(state: &mut BackoffState, policy: &ReconnectPolicy) -> Duration {
+        let delay = policy.delay_with_jitter(state.attempt);
+        state.attempt = state.attempt.saturating_add(1);
+        state.failures = 0;
+        state.backoff = Some((Instant::now(), delay));
+        delay
+    }
+
+    /// Forget the relay's failures after it answered normally
+    pub(crate) fn record_success(&self, relay_url: &RelayUrl) {
+        if let Some(mut state) = self.relays.get_mut(relay_url)
+            && !state.is_backing_off()
+        {
+            *state = BackoffState::default();
+        }
+    }
+
+    pub(crate) fn is_backing_off(&self, relay_url: &RelayUrl) -> bool {
+        self.relays
+            .get(relay_url)
+            .is_some_and(|state| state.is_backing_off())
+    }
+
+    /// Relays currently backing off
+    pub(crate) fn backing_off(&self) -> Vec<RelayUrl> {
+        self.relays
+            .iter()
+            .filter(|state| state.is_backing_off())
+            .map(|state| state.key().clone())
+            .collect()
+    }
+
+    /// Forget all failures and backoffs
+    pub(crate) fn reset(&self) {
+        self.relays.clear();
+    }
+}
+
+/// Count a relay status change, returning the backoff delay if the relay just reached the
+/// failure threshold
+///
+/// Only `Disconnected` counts as a failure: nostr-sdk reports it when a connection attempt
+/// fails or an open connection drops. `Terminated` follows our own disconnects, including
+/// the ones that start a backoff.
+fn record_status(
+    backoff: &RelayBackoff,
+    relay_url: &RelayUrl,
+    status: RelayStatus,
+    policy: &ReconnectPolicy,
+) -> Option<Duration> {
+    match status {
+        RelayStatus::Disconnected => backoff.record_failure(relay_url, policy),
+        _ => None,
+    }
+}
+
+/// Back off the relay whenever its connection keeps failing
+///
+/// Listens to the relay's status changes until the relay is removed from the pool.
+pub(crate) fn watch_relay_status(
+    client: Client,
+    relay: Relay,
+    backoff: Arc<RelayBackoff>,
+    policy: ReconnectPolicy,
+) {
+    let mut notifications = relay.notifications();
+    tokio::spawn(async move {
+        loop {
+            match notifications.recv().await {
+                Ok(RelayNotification::RelayStatus { status }) => {
+                    if let Some(delay) = record_status(&backoff, relay.url(), status, &policy) {
+                        disconnect_for(&client, backoff.clone(), relay.url().clone(), delay).await;
+                    }
+                }
+                Ok(RelayNotification::Shutdown)
+                | Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+            }
+        }
+    });
+}
+
+/// Whether a relay message reports that the relay failed or is throttling us
+pub(crate) fn is_failure_message(message: &RelayMessage) -> bool {
+    const FAILURE_PREFIXES: [&str; 2] = ["error:", "rate-limited:"];
+    match message {
+        RelayMessage::Closed { message, .. } => FAILURE_PREFIXES
+            .iter()
+            .any(|prefix| message.starts_with(prefix)),
+        RelayMessage::Ok {
+            status: false,
+            message,
+            ..
+        } => FAILURE_PREFIXES
+            .iter()
+            .any(|prefix| message.starts_with(prefix)),
+        _ => false,
+    }
+}
+
+/// Disconnect the relay and reconnect it once `delay` has passed
+///
+/// The relay is disconnected rather than left to nostr-sdk, which would keep retrying it on
+/// its own short interval.
+pub(crate) async fn disconnect_for(
+    client: &Client,
+    backoff: Arc<RelayBackoff>,
+    relay_url: RelayUrl,
+    delay: Duration,
+) {
+    tracing::warn!(
+        target: "whitenoise::nostr_manager::reconnect",
+        "Backing off relay {} for {}ms",
+        relay_url,
+        delay.as_millis()
+    );
+
+    if let Err(e) = client.disconnect_relay(&relay_url).await {
+        tracing::debug!(
+            target: "whitenoise::nostr_manager::reconnect",
+            "Failed to disconnect relay {}: {}",
+            relay_url,
+            e
+        );
+    }
+
+    let client = client.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        // A data reset clears the backoff; the relay is then reconnected on next use
+        if backoff.relays.contains_key(&relay_url)
+            && let Err(e) = client.connect_relay(&relay_url).await
+        {
+            tracing::debug!(
+                target: "whitenoise::nostr_manager::reconnect",
+                "Failed to reconnect relay {} after backoff: {}",
+                relay_url,
+                e
+            );
+        }
+    });
+}
+
+impl NostrManager {
+    /// Temporarily stop using a misbehaving relay
+    ///
+    /// The relay is disconnected for the policy's next backoff delay, which grows with every
+    /// backoff until the relay answers normally again. Returns the delay.
+    pub(crate) async fn back_off_relay(&self, relay_url: &RelayUrl) -> Duration {
+        let delay = self.relay_backoff.start(relay_url, &self.reconnect_policy);
+        disconnect_for(
+            &self.client,
+            self.relay_backoff.clone(),
+            relay_url.clone(),
+            delay,
+        )
+        .await;
+        delay
+    }
+
+    /// Whether the relay is currently backed off and must not count as connected
+    pub(crate) fn is_relay_backing_off(&self, relay_url: &RelayUrl) -> bool {
+        self.relay_backoff.is_backing_off(relay_url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            multiplier: 2.0,
+            jitter: 0.5,
+            failure_threshold: 2,
+        }
+    }
+
+    #[test]
+    fn test_delay_grows_exponentially_up_to_max() {
+        let policy = policy();
+        assert_eq!(policy.delay_for_attempt(0), Duration::from_secs(1));
+        assert_eq!(policy.delay_for_attempt(1), Duration::from_secs(2));
+        assert_eq!(policy.delay_for_attempt(5), Duration::from_secs(32));
+        assert_eq!(policy.delay_for_attempt(6), Duration::from_secs(60));
+        assert_eq!(policy.delay_for_attempt(u32::MAX), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_delay_does_not_overflow_with_extreme_values() {
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::MAX,
+            max_delay: Duration::MAX,
+            multiplier: f64::MAX,
+            jitter: 1.0,
+            failure_threshold: 1,
+        };
+        assert_eq!(policy.delay_for_attempt(u32::MAX), Duration::MAX);
+        assert!(policy.jittered_delay(u32::MAX, 1.0) <= Duration::MAX);
+
+        // Initial delay above the maximum and a shrinking multiplier are both capped
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_secs(120),
+            multiplier: 0.5,
+            ..self::policy()
+        };
+        assert_eq!(policy.delay_for_attempt(0), Duration::from_secs(60));
+        assert_eq!(policy.delay_for_attempt(3), Duration::from_secs(60));
+
+        let policy = ReconnectPolicy {
+            multiplier: f64::NAN,
+            ..self::policy()
+        };
+        assert_eq!(policy.delay_for_attempt(10), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds_and_max() {
+        let policy = policy();
+        assert_eq!(policy.jittered_delay(2, 0.0), Duration::from_secs(2));
+        assert_eq!(policy.jittered_delay(2, 0.5), Duration::from_secs(4));
+        assert_eq!(policy.jittered_delay(2, 1.0), Duration::from_secs(6));
+
+        // Jitter never pushes the delay past the maximum
+        assert_eq!(policy.jittered_delay(10, 1.0), Duration::from_secs(60));
+
+        for attempt in 0..20 {
+            let delay = policy.delay_with_jitter(attempt);
+            let base = policy.delay_for_attempt(attempt);
+            assert!(delay >= base / 2);
+            assert!(delay <= policy.max_delay);
+        }
+    }
+
+    #[test]
+    fn test_backoff_starts_after_threshold_and_resets_on_success() {
+        let backoff = RelayBackoff::new();
+        let policy = ReconnectPolicy {
+            jitter: 0.0,
+            ..policy()
+        };
+        let relay_url = RelayUrl::parse("wss://relay.example.com").unwrap();
+
+        assert_eq!(backoff.record_failure(&relay_url, &policy), None);
+        assert!(!backoff.is_backing_off(&relay_url));
+        assert_eq!(
+            backoff.record_failure(&relay_url, &policy),
+            Some(Duration::from_secs(1))
+        );
+        assert!(backoff.is_backing_off(&relay_url));
+        assert_eq!(backoff.backing_off(), vec![relay_url.clone()]);
+
+        // Failures during a backoff don't extend it, and the next backoff is longer
+        assert_eq!(backoff.record_failure(&relay_url, &policy), None);
+        assert_eq!(backoff.start(&relay_url, &policy), Duration::from_secs(2));
+
+        backoff.reset();
+        assert!(!backoff.is_backing_off(&relay_url));
+        assert_eq!(backoff.record_failure(&relay_url, &policy), None);
+        backoff.record_success(&relay_url);
+        assert_eq!(backoff.record_failure(&relay_url, &policy), None);
+    }
+
+    #[test]
+    fn test_dropped_connections_start_backoff() {
+        let backoff = RelayBackoff::new();
+        let policy = ReconnectPolicy {
+            jitter: 0.0,
+            ..policy()
+        };
+        let relay_url = RelayUrl::parse("wss://relay.example.com").unwrap();
+
+        // Our own disconnects and connection progress aren't failures
+        for status in [
+            RelayStatus::Terminated,
+            RelayStatus::Connecting,
+            RelayStatus::Connected,
+        ] {
+            assert_eq!(record_status(&backoff, &relay_url, status, &policy), None);
+        }
+        assert_eq!(
+            record_status(&backoff, &relay_url, RelayStatus::Disconnected, &policy),
+            None
+        );
+        assert_eq!(
+            record_status(&backoff, &relay_url, RelayStatus::Disconnected, &policy),
+            Some(Duration::from_secs(1))
+        );
+        assert!(backoff.is_backing_off(&relay_url));
+    }
+
+    #[test]
+    fn test_failure_messages() {
+        let sub = SubscriptionId::new("sub");
+        assert!(is_failure_message(&RelayMessage::closed(
+            sub.clone(),
+            "rate-limited: slow down"
+        )));
+        assert!(is_failure_message(&RelayMessage::closed(
+            sub.clone(),
+            "error: shutting down"
+        )));
+        assert!(!is_failure_message(&RelayMessage::closed(
+            sub.clone(),
+            "auth-required: sign in"
+        )));
+        assert!(!is_failure_message(&RelayMessage::eose(sub)));
+    }
+}
@@ -0,0 +1,205 @@
+//! NIP-42 authentication with relays
+//!
+//! Relays that require authentication send an AUTH challenge and refuse to serve
+//! subscriptions until a client answers it with a signed kind 22242 event. The notification
+//! handler forwards challenges to Whitenoise, which answers them for every account using the
+//! relay through [`NostrManager::authenticate_relay_with_signer`]. The relay's OK reply is
+//! fed back here, so each challenge is answered once per account and a rejected one isn't
+//! retried until the relay sends a new challenge.
+
+use std::collections::HashSet;
+
+use dashmap::DashMap;
+use nostr_sdk::prelude::*;
+use tokio::sync::Notify;
+
+use super::{NostrManager, NostrManagerError, Result};
+
+/// Where authentication with a relay stands
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum RelayAuthStatus {
+    /// An answer to the current challenge was sent and the relay hasn't replied yet
+    Pending,
+    /// The relay accepted an answer to its current challenge
+    Authenticated,
+    /// The relay rejected an answer to its current challenge
+    Failed { reason: String },
+}
+
+#[derive(Debug)]
+struct RelayAuth {
+    challenge: String,
+    answered: HashSet<PublicKey>,
+    pending: HashSet<EventId>,
+    status: RelayAuthStatus,
+}
+
+/// The authentication state of every relay that sent a challenge
+#[derive(Debug, Default)]
+pub(crate) struct RelayAuthTracker {
+    relays: DashMap<RelayUrl, RelayAuth>,
+    replies: Notify,
+}
+
+impl RelayAuthTracker {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claims the answer to `challenge` from `relay_url` for `pubkey`
+    ///
+    /// Returns `false` when the account already answered this challenge, so repeated
+    /// challenges don't make us loop. A new challenge starts over.
+    fn begin(&self, relay_url: &RelayUrl, challenge: &str, pubkey: PublicKey) -> bool {
+        let mut entry = self
+            .relays
+            .entry(relay_url.clone())
+            .or_insert_with(|| RelayAuth {
+                challenge: challenge.to_string(),
+                answered: HashSet::new(),
+                pending: HashSet::new(),
+                status: RelayAuthStatus::Pending,
+            });
+        if entry.challenge != challenge {
+            *entry = RelayAuth {
+                challenge: challenge.to_string(),
+                answered: HashSet::new(),
+                pending: HashSet::new(),
+                status: RelayAuthStatus::Pending,
+            };
+        }
+        entry.answered.insert(pubkey)
+    }
+
+    fn record_sent(&self, relay_url: &RelayUrl, event_id: EventId) {
+        if let Some(mut entry) = self.relays.get_mut(relay_url) {
+            entry.pending.insert(event_id);
+            if entry.status != RelayAuthStatus::Authenticated {
+                entry.status = RelayAuthStatus::Pending;
+            }
+        }
+    }
+
+    /// Records a relay's OK reply, if it answers one of our AUTH events
+    ///
+    /// One accepted answer authenticates the connection, so a later rejection for another
+    /// account doesn't undo it.
+    pub(crate) fn record_ok(
+        &self,
+        relay_url: &RelayUrl,
+        event_id: &EventId,
+        accepted: bool,
+        message: &str,
+    ) {
+        let Some(mut entry) = self.relays.get_mut(relay_url) else {
+            return;
+        };
+        if !entry.pending.remove(event_id) {
+            return;
+        }
+
+        if accepted {
+            entry.status = RelayAuthStatus::Authenticated;
+        } else if entry.status != RelayAuthStatus::Authenticated {
+            entry.status = RelayAuthStatus::Failed {
+                reason: message.to_string(),
+            };
+        }
+        drop(entry);
+        self.replies.notify_waiters();
+    }
+
+    pub(crate) fn status(&self, relay_url: &RelayUrl) -> Option<RelayAuthStatus> {
+        self.relays.get(relay_url).map(|entry| entry.status.clone())
+    }
+
+    fn is_pending(&self, relay_url: &RelayUrl, event_id: &EventId) -> bool {
+        self.relays
+            .get(relay_url)
+            .is_some_and(|entry| entry.pending.contains(event_id))
+    }
+}
+
+impl NostrManager {
+    /// Answers a relay's AUTH challenge for an account and waits for the relay's reply.
+    ///
+    /// Returns the relay's status afterwards, or `None` if the account already answered this
+    /// challenge. A relay that doesn't reply within the request timeout stays
+    /// [`RelayAuthStatus::Pending`].
+    pub(crate) async fn authenticate_relay_with_signer(
+        &self,
+        relay_url: &RelayUrl,
+        challenge: &str,
+        pubkey: PublicKey,
+        signer: impl NostrSigner + 'static,
+    ) -> Result<Option<RelayAuthStatus>> {
+        if !self.relay_auth.begin(relay_url, challenge, pubkey) {
+            return Ok(None);
+        }
+
+        let auth_event = self
+            .with_signer(signer, || async {
+                Ok(self
+                    .client
+                    .sign_event_builder(EventBuilder::auth(challenge, relay_url.clone()))
+                    .await?)
+            })
+            .await?;
+
+        let replied = self.relay_auth.replies.notified();
+        tokio::pin!(replied);
+        replied.as_mut().enable();
+        self.relay_auth.record_sent(relay_url, auth_event.id);
+        self.client
+            .send_msg_to([relay_url.clone()], ClientMessage::auth(auth_event.clone()))
+            .await
+            .map_err(NostrManagerError::Client)?;
+
+        let _ = tokio::time::timeout(self.timeout, async {
+            while self.relay_auth.is_pending(relay_url, &auth_event.id) {
+                replied.as_mut().await;
+                replied.set(self.relay_auth.replies.notified());
+                replied.as_mut().enable();
+            }
+        })
+        .await;
+
+        Ok(self.relay_auth.status(relay_url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker_answers_each_challenge_once() {
+        let tracker = RelayAuthTracker::new();
+        let relay_url = RelayUrl::parse("wss://auth.example.com").unwrap();
+        let alice = Keys::generate().public_key();
+        let bob = Keys::generate().public_key();
+
+        assert!(tracker.begin(&relay_url, "challenge-1", alice));
+        assert!(!tracker.begin(&relay_url, "challenge-1", alice));
+        assert!(tracker.begin(&relay_url, "challenge-1", bob));
+
+        let alice_auth = EventId::all_zeros();
+        tracker.record_sent(&relay_url, alice_auth);
+        assert_eq!(tracker.status(&relay_url), Some(RelayAuthStatus::Pending));
+
+        // Replies to events that aren't AUTH answers are ignored
+        tracker.record_ok(&relay_url, &EventId::from_byte_array([1; 32]), false, "no");
+        assert_eq!(tracker.status(&relay_url), Some(RelayAuthStatus::Pending));
+
+        tracker.record_ok(&relay_url, &alice_auth, false, "restricted: not a member");
+        assert_eq!(
+            tracker.status(&relay_url),
+            Some(RelayAuthStatus::Failed {
+                reason: "restricted: not a member".to_string()
+            })
+        );
+
+        // A new challenge can be answered again
+        assert!(tracker.begin(&relay_url, "challenge-2", alice));
+    }
+}
@@ -0,0 +1,217 @@
+//! Relay health probing
+//!
+//! A relay can report `Connected` at the socket level while no longer answering
+//! requests. The probe sends each connected relay a `limit: 0` REQ, which any live relay
+//! answers with an immediate EOSE, and reconnects relays that stay silent.
+
+use std::collections::HashSet;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use nostr_sdk::prelude::*;
+
+use super::{NostrManager, NostrManagerError, Result};
+
+/// Operations the health probe needs from the relay pool
+#[async_trait]
+pub(crate) trait RelayProber: Send + Sync {
+    /// Relays currently reporting a connected socket
+    async fn connected_relays(&self) -> Vec<RelayUrl>;
+
+    /// Whether the relay answered a lightweight request within `timeout`
+    async fn probe(&self, relay_url: &RelayUrl, timeout: Duration) -> bool;
+
+    /// Drop and re-establish the connection to the relay
+    async fn reconnect(&self, relay_url: &RelayUrl) -> Result<()>;
+}
+
+/// Outcome of one probe round
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct RelayHealthReport {
+    /// Relays that were probed
+    pub probed: usize,
+    /// Relays that failed to answer in time
+    pub degraded: Vec<RelayUrl>,
+    /// Degraded relays whose reconnection was started successfully
+    pub reconnected: Vec<RelayUrl>,
+}
+
+/// Probe every connected relay, updating `degraded` and reconnecting unresponsive relays.
+///
+/// Relays that answer are removed from `degraded`; relays that don't are added to it until
+/// a later round sees them answer again.
+pub(crate) async fn probe_relays(
+    prober: &dyn RelayProber,
+    timeout: Duration,
+    degraded: &RwLock<HashSet<RelayUrl>>,
+) -> RelayHealthReport {
+    let relays = prober.connected_relays().await;
+    let results =
+        futures::future::join_all(relays.iter().map(|url| prober.probe(url, timeout))).await;
+
+    let mut report = RelayHealthReport {
+        probed: relays.len(),
+        ..Default::default()
+    };
+
+    for (relay_url, responsive) in relays.into_iter().zip(results) {
+        if responsive {
+            if let Ok(mut degraded) = degraded.write() {
+                degraded.remove(&relay_url);
+            }
+            continue;
+        }
+
+        tracing::warn!(
+            target: "whitenoise::nostr_manager::relay_health",
+            "Relay {} did not answer within {}ms, marking degraded and reconnecting",
+            relay_url,
+            timeout.as_millis()
+        );
+        if let Ok(mut degraded) = degraded.write() {
+            degraded.insert(relay_url.clone());
+        }
+
+        match prober.reconnect(&relay_url).await {
+            Ok(()) => report.reconnected.push(relay_url.clone()),
+            Err(e) => tracing::warn!(
+                target: "whitenoise::nostr_manager::relay_health",
+                "Failed to reconnect relay {}: {}",
+                relay_url,
+                e
+            ),
+        }
+        report.degraded.push(relay_url);
+    }
+
+    report
+}
+
+#[async_trait]
+impl RelayProber for NostrManager {
+    async fn connected_relays(&self) -> Vec<RelayUrl> {
+        self.client
+            .relays()
+            .await
+            .into_iter()
+            .filter(|(_, relay)| relay.status() == RelayStatus::Connected)
+            .map(|(url, _)| url)
+            .collect()
+    }
+
+    async fn probe(&self, relay_url: &RelayUrl, timeout: Duration) -> bool {
+        let Ok(relay) = self.client.relay(relay_url).await else {
+            return false;
+        };
+
+        // A limit of 0 asks for no stored events, so a live relay replies with EOSE right away
+        let request =
+            relay.fetch_events(Filter::new().limit(0), timeout, ReqExitPolicy::ExitOnEOSE);
+        matches!(tokio::time::timeout(timeout, request).await, Ok(Ok(_)))
+    }
+
+    async fn reconnect(&self, relay_url: &RelayUrl) -> Result<()> {
+        // A relay that keeps failing probes is backed off rather than reconnected right away
+        if let Some(delay) = self
+            .relay_backoff
+            .record_failure(relay_url, &self.reconnect_policy)
+        {
+            super::reconnect::disconnect_for(
+                &self.client,
+                self.relay_backoff.clone(),
+                relay_url.clone(),
+                delay,
+            )
+            .await;
+            self.relay_health
+                .record_status(relay_url, RelayStatus::Disconnected);
+            return Ok(());
+        }
+
+        self.client.disconnect_relay(relay_url).await?;
+        self.relay_health
+            .record_status(relay_url, RelayStatus::Disconnected);
+        self.client
+            .connect_relay(relay_url)
+            .await
+            .map_err(NostrManagerError::Client)
+    }
+}
+
+impl NostrManager {
+    /// Probe all connected relays once and reconnect the ones that don't answer
+    pub(crate) async fn probe_relay_health(&self, timeout: Duration) -> RelayHealthReport {
+        probe_relays(self, timeout, &self.degraded_relays).await
+    }
+
+    /// Relays that failed the most recent health probe
+    pub(crate) fn degraded_relays(&self) -> Vec<RelayUrl> {
+        self.degraded_relays
+            .read()
+            .map(|degraded| degraded.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Relay pool where some relays never answer probes
+    struct MockProber {
+        relays: Vec<RelayUrl>,
+        silent: HashSet<RelayUrl>,
+        reconnected: Mutex<Vec<RelayUrl>>,
+    }
+
+    #[async_trait]
+    impl RelayProber for MockProber {
+        async fn connected_relays(&self) -> Vec<RelayUrl> {
+            self.relays.clone()
+        }
+
+        async fn probe(&self, relay_url: &RelayUrl, timeout: Duration) -> bool {
+            if self.silent.contains(relay_url) {
+                // Simulate a zombie connection: nothing arrives before the timeout
+                tokio::time::sleep(timeout).await;
+                return false;
+            }
+            true
+        }
+
+        async fn reconnect(&self, relay_url: &RelayUrl) -> Result<()> {
+            self.reconnected.lock().unwrap().push(relay_url.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unresponsive_relay_is_marked_degraded_and_reconnected() {
+        let healthy = RelayUrl::parse("wss://healthy.example.com").unwrap();
+        let zombie = RelayUrl::parse("wss://zombie.example.com").unwrap();
+        let mut prober = MockProber {
+            relays: vec![healthy.clone(), zombie.clone()],
+            silent: HashSet::from([zombie.clone()]),
+            reconnected: Mutex::new(Vec::new()),
+        };
+        let degraded = RwLock::new(HashSet::new());
+
+        let report = probe_relays(&prober, Duration::from_millis(20), &degraded).await;
+
+        assert_eq!(report.probed, 2);
+        assert_eq!(report.degraded, vec![zombie.clone()]);
+        assert_eq!(report.reconnected, vec![zombie.clone()]);
+        assert_eq!(*prober.reconnected.lock().unwrap(), vec![zombie.clone()]);
+        assert!(degraded.read().unwrap().contains(&zombie));
+        assert!(!degraded.read().unwrap().contains(&healthy));
+
+        // Once the relay answers again it is no longer degraded
+        prober.silent.clear();
+        let report = probe_relays(&prober, Duration::from_millis(20), &degraded).await;
+
+        assert!(report.degraded.is_empty());
+        assert!(degraded.read().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+//! Rolling per-relay connection quality metrics
+//!
+//! Metrics are fed from the notification handler and from relay status lookups, keyed by
+//! relay URL rather than by the SDK's relay handle, so they carry over when a relay
+//! reconnects or is removed and re-added to the pool.
+
+use dashmap::DashMap;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::NostrManager;
+
+/// Events older than this when they arrive are stored events, not live ones, and are not
+/// counted towards latency
+const LIVE_EVENT_WINDOW_SECS: u64 = 60;
+
+/// Weight of the newest sample in the rolling latency average
+const LATENCY_SMOOTHING: f64 = 0.1;
+
+/// Connection quality of a single relay since startup or the last data reset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelayHealth {
+    /// The relay these metrics belong to
+    pub relay_url: RelayUrl,
+
+    /// Number of times the relay was seen going from not connected to connected
+    pub successful_connects: u64,
+
+    /// Number of times the relay was seen dropping an established connection
+    pub disconnects: u64,
+
+    /// Number of NOTICE and CLOSED messages received from the relay
+    pub error_count: u64,
+
+    /// Text of the most recent NOTICE or CLOSED message
+    pub last_error: Option<String>,
+
+    /// Rolling average of the delay between a live event's creation and its arrival
+    pub average_event_latency_ms: Option<f64>,
+
+    /// When the relay last finished sending stored events for a subscription
+    pub last_eose_at: Option<Timestamp>,
+}
+
+impl RelayHealth {
+    fn new(relay_url: RelayUrl) -> Self {
+        Self {
+            relay_url,
+            successful_connects: 0,
+            disconnects: 0,
+            error_count: 0,
+            last_error: None,
+            average_event_latency_ms: None,
+            last_eose_at: None,
+        }
+    }
+
+    fn record_error(&mut self, message: &str) {
+        self.error_count += 1;
+        self.last_error = Some(message.to_string());
+    }
+
+    fn record_latency(&mut self, latency_ms: f64) {
+        self.average_event_latency_ms = Some(match self.average_event_latency_ms {
+            Some(average) => average + LATENCY_SMOOTHING * (latency_ms - average),
+            None => latency_ms,
+        });
+    }
+}
+
+#[derive(Debug)]
+struct TrackedRelay {
+    health: RelayHealth,
+    connected: bool,
+}
+
+/// Collects [`RelayHealth`] for every relay the client talks to
+#[derive(Debug, Default)]
+pub(crate) struct RelayHealthTracker {
+    relays: DashMap<RelayUrl, TrackedRelay>,
+}
+
+impl RelayHealthTracker {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an observed connection status, counting transitions in and out of `Connected`
+    pub(crate) fn record_status(&self, relay_url: &RelayUrl, status: RelayStatus) {
+        let mut tracked = self
+            .relays
+            .entry(relay_url.clone())
+            .or_insert_with(|| TrackedRelay {
+                health: RelayHealth::new(relay_url.clone()),
+                connected: false,
+            });
+
+        let connected = status == RelayStatus::Connected;
+        if connected && !tracked.connected {
+            tracked.health.successful_connects += 1;
+        } else if !connected && tracked.connected {
+            tracked.health.disconnects += 1;
+        }
+        tracked.connected = connected;
+    }
+
+    /// Update metrics from a message the relay sent
+    pub(crate) fn record_message(&self, relay_url: &RelayUrl, message: &RelayMessage) {
+        // Anything the relay sends means the connection is up
+        self.record_status(relay_url, RelayStatus::Connected);
+
+        let Some(mut tracked) = self.relays.get_mut(relay_url) else {
+            return;
+        };
+        match message {
+            RelayMessage::Notice(notice) => tracked.health.record_error(notice),
+            RelayMessage::Closed { message, .. } => tracked.health.record_error(message),
+            RelayMessage::EndOfStoredEvents(_) => {
+                tracked.health.last_eose_at = Some(Timestamp::now());
+            }
+            RelayMessage::Event { event, .. } => {
+                let now = Timestamp::now().as_u64();
+                let created_at = event.created_at.as_u64();
+                if created_at <= now && now - created_at <= LIVE_EVENT_WINDOW_SECS {
+                    tracked
+                        .health
+                        .record_latency(((now - created_at) * 1000) as f64);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn get(&self, relay_url: &RelayUrl) -> Option<RelayHealth> {
+        self.relays
+            .get(relay_url)
+            .map(|tracked| tracked.health.clone())
+    }
+
+    /// Metrics for every tracked relay, ordered by URL
+    pub(crate) fn all(&self) -> Vec<RelayHealth> {
+        let mut all: Vec<RelayHealth> = self
+            .relays
+            .iter()
+            .map(|tracked| tracked.health.clone())
+            .collect();
+        all.sort_by(|a, b| a.relay_url.as_str().cmp(b.relay_url.as_str()));
+        all
+    }
+
+    /// Forget all metrics
+    pub(crate) fn reset(&self) {
+        self.relays.clear();
+    }
+}
+
+impl NostrManager {
+    /// Record the current status of every relay in the pool, so disconnects are counted
+    /// even when no message arrives to reveal them
+    async fn observe_relay_statuses(&self) {
+        for relay_url in self.network.relays().await {
+            if let Ok(status) = self.network.relay_status(&relay_url).await {
+                self.relay_health.record_status(&relay_url, status);
+            }
+        }
+    }
+
+    /// Connection quality of a relay, or `None` if the client has never used it
+    pub(crate) async fn relay_health(&self, relay_url: &RelayUrl) -> Option<RelayHealth> {
+        self.observe_relay_statuses().await;
+        self.relay_health.get(relay_url)
+    }
+
+    /// Connection quality of every relay the client has used, ordered by URL
+    pub(crate) async fn all_relay_health(&self) -> Vec<RelayHealth> {
+        self.observe_relay_statuses().await;
+        self.relay_health.all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_accumulate_across_reconnects() {
+        let tracker = RelayHealthTracker::new();
+        let relay_url = RelayUrl::parse("wss://relay.example.com").unwrap();
+
+        tracker.record_status(&relay_url, RelayStatus::Connected);
+        tracker.record_message(&relay_url, &RelayMessage::notice("rate limited"));
+        tracker.record_status(&relay_url, RelayStatus::Disconnected);
+        tracker.record_status(&relay_url, RelayStatus::Connecting);
+        tracker.record_message(
+            &relay_url,
+            &RelayMessage::closed(SubscriptionId::new("sub"), "auth-required: sign in"),
+        );
+        tracker.record_message(&relay_url, &RelayMessage::eose(SubscriptionId::new("sub")));
+
+        let health = tracker.get(&relay_url).unwrap();
+        assert_eq!(health.successful_connects, 2);
+        assert_eq!(health.disconnects, 1);
+        assert_eq!(health.error_count, 2);
+        assert_eq!(health.last_error.as_deref(), Some("auth-required: sign in"));
+        assert!(health.last_eose_at.is_some());
+
+        tracker.reset();
+        assert!(tracker.get(&relay_url).is_none());
+        assert!(tracker.all().is_empty());
+    }
+
+    #[test]
+    fn test_latency_only_counts_live_events() {
+        let tracker = RelayHealthTracker::new();
+        let relay_url = RelayUrl::parse("wss://relay.example.com").unwrap();
+        let keys = Keys::generate();
+
+        let stored = EventBuilder::text_note("old")
+            .custom_created_at(Timestamp::from(Timestamp::now().as_u64() - 3600))
+            .sign_with_keys(&keys)
+            .unwrap();
+        tracker.record_message(
+            &relay_url,
+            &RelayMessage::event(SubscriptionId::new("sub"), stored),
+        );
+        assert_eq!(
+            tracker.get(&relay_url).unwrap().average_event_latency_ms,
+            None
+        );
+
+        let live = EventBuilder::text_note("new")
+            .sign_with_keys(&keys)
+            .unwrap();
+        tracker.record_message(
+            &relay_url,
+            &RelayMessage::event(SubscriptionId::new("sub"), live),
+        );
+        let latency = tracker.get(&relay_url).unwrap().average_event_latency_ms;
+        assert!(latency.is_some_and(|ms| ms <= (LIVE_EVENT_WINDOW_SECS * 1000) as f64));
+    }
+}
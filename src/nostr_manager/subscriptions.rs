@@ -1,7 +1,7 @@
 //! Subscription functions for NostrManager
 //! This mostly handles subscribing and processing events as they come in while the user is active.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::time::Duration;
 
 use nostr_sdk::prelude::*;
@@ -13,7 +13,231 @@ use crate::nostr_manager::{
     NostrManager, NostrManagerError, Result, utils::adjust_since_for_giftwrap,
 };
 
+/// Groups of subscriptions that can be paused and resumed independently
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SubscriptionCategory {
+    /// Global user subscriptions for metadata and relay lists of known users
+    Metadata,
+    /// Per-account follow list subscriptions
+    FollowLists,
+    /// Per-account giftwrap (welcome) subscriptions
+    Giftwraps,
+    /// Per-account MLS group message subscriptions
+    GroupMessages,
+}
+
+impl SubscriptionCategory {
+    const ALL: [Self; 4] = [
+        Self::Metadata,
+        Self::FollowLists,
+        Self::Giftwraps,
+        Self::GroupMessages,
+    ];
+
+    /// Whether a subscription ID belongs to this category
+    fn matches(&self, subscription_id: &SubscriptionId) -> bool {
+        let id = subscription_id.as_str();
+        match self {
+            Self::Metadata => id.starts_with("global_users_"),
+            Self::FollowLists => id.ends_with("_user_follow_list"),
+            Self::Giftwraps => id.ends_with("_giftwrap"),
+            Self::GroupMessages => id.ends_with("_mls_messages"),
+        }
+    }
+
+    /// The category a subscription ID belongs to
+    pub(crate) fn from_subscription_id(subscription_id: &str) -> Option<Self> {
+        let subscription_id = SubscriptionId::new(subscription_id);
+        Self::ALL
+            .into_iter()
+            .find(|category| category.matches(&subscription_id))
+    }
+
+    /// Name of the category as stored in the database
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Metadata => "metadata",
+            Self::FollowLists => "follow_list",
+            Self::Giftwraps => "giftwrap",
+            Self::GroupMessages => "group_messages",
+        }
+    }
+
+    /// Parse a category name stored with [`Self::as_str`]
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.as_str() == name)
+    }
+}
+
+/// Where an account's subscriptions resume from, per subscription and relay
+///
+/// Relays with a watermark in `per_relay` for a subscription resume that subscription from
+/// it, so a relay that was unreachable catches up on what it missed without the others
+/// re-sending what was already received. Relays without one resume from `default`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SyncSince {
+    pub default: Option<Timestamp>,
+    pub per_relay: HashMap<SubscriptionCategory, HashMap<RelayUrl, Timestamp>>,
+}
+
+impl SyncSince {
+    /// The `since` to subscribe to `category` with on `relay_url`
+    pub(crate) fn for_relay(
+        &self,
+        category: SubscriptionCategory,
+        relay_url: &RelayUrl,
+    ) -> Option<Timestamp> {
+        self.per_relay
+            .get(&category)
+            .and_then(|watermarks| watermarks.get(relay_url))
+            .copied()
+            .or(self.default)
+    }
+
+    /// Relays grouped by the `since` they resume `category` from, so each group takes one
+    /// request
+    fn group_relays(
+        &self,
+        category: SubscriptionCategory,
+        relays: &[RelayUrl],
+    ) -> BTreeMap<Option<Timestamp>, Vec<RelayUrl>> {
+        let mut groups: BTreeMap<Option<Timestamp>, Vec<RelayUrl>> = BTreeMap::new();
+        for relay_url in relays {
+            groups
+                .entry(self.for_relay(category, relay_url))
+                .or_default()
+                .push(relay_url.clone());
+        }
+        groups
+    }
+}
+
+impl From<Option<Timestamp>> for SyncSince {
+    fn from(default: Option<Timestamp>) -> Self {
+        Self {
+            default,
+            per_relay: HashMap::new(),
+        }
+    }
+}
+
+/// Where one kind of per-account subscription is active
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubscriptionStatus {
+    /// Number of relay subscriptions of this kind, one per relay holding it
+    pub count: usize,
+    /// Relays holding the subscription
+    pub relays: Vec<RelayUrl>,
+}
+
+impl SubscriptionStatus {
+    fn add(&mut self, relay_url: RelayUrl) {
+        self.count += 1;
+        self.relays.push(relay_url);
+    }
+}
+
+/// Per-account subscriptions and the relays holding them, see
+/// [`NostrManager::subscription_breakdown`]
+///
+/// A status with a zero count means the subscription isn't active on any relay.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubscriptionBreakdown {
+    /// The account's follow list subscription (on its NIP-65 relays)
+    pub follow_list: SubscriptionStatus,
+    /// The account's giftwrap subscription, wherever it is active
+    pub giftwrap: SubscriptionStatus,
+    /// The giftwrap subscription on the account's inbox relays, where giftwraps are sent
+    pub inbox: SubscriptionStatus,
+    /// Inbox relays of the account that don't hold its giftwrap subscription
+    pub inbox_missing: Vec<RelayUrl>,
+    /// The account's MLS group message subscription (on its groups' relays)
+    pub group_messages: SubscriptionStatus,
+}
+
+impl SubscriptionBreakdown {
+    /// Categories whose subscription isn't active on any relay
+    ///
+    /// Group messages only count as missing when `expect_group_messages` is set, since an
+    /// account without groups has no group message subscription. Giftwraps count as missing
+    /// when no inbox relay holds the subscription, even if other relays do.
+    pub fn missing(&self, expect_group_messages: bool) -> Vec<SubscriptionCategory> {
+        let mut missing = Vec::new();
+        if self.follow_list.count == 0 {
+            missing.push(SubscriptionCategory::FollowLists);
+        }
+        if self.giftwrap.count == 0 || (self.inbox.count == 0 && !self.inbox_missing.is_empty()) {
+            missing.push(SubscriptionCategory::Giftwraps);
+        }
+        if expect_group_messages && self.group_messages.count == 0 {
+            missing.push(SubscriptionCategory::GroupMessages);
+        }
+        missing
+    }
+}
+
 impl NostrManager {
+    /// Returns true if subscriptions of `category` are currently paused
+    pub(crate) fn is_subscription_category_paused(&self, category: SubscriptionCategory) -> bool {
+        self.paused_categories
+            .read()
+            .map(|paused| paused.contains(&category))
+            .unwrap_or(false)
+    }
+
+    /// Returns the currently paused subscription categories
+    pub(crate) fn paused_subscription_categories(&self) -> HashSet<SubscriptionCategory> {
+        self.paused_categories
+            .read()
+            .map(|paused| paused.clone())
+            .unwrap_or_default()
+    }
+
+    /// Marks `categories` as paused and closes their active subscriptions.
+    ///
+    /// While paused, the setup functions skip these categories, so periodic refreshes
+    /// don't resurrect them until [`Self::resume_subscription_categories`] is called.
+    pub(crate) async fn pause_subscription_categories(&self, categories: &[SubscriptionCategory]) {
+        if let Ok(mut paused) = self.paused_categories.write() {
+            paused.extend(categories.iter().copied());
+        }
+
+        let subscription_ids: Vec<SubscriptionId> = self
+            .network
+            .subscriptions()
+            .await
+            .into_keys()
+            .filter(|id| categories.iter().any(|category| category.matches(id)))
+            .collect();
+
+        tracing::debug!(
+            target: "whitenoise::nostr_manager::pause_subscription_categories",
+            "Pausing {:?} ({} subscriptions)",
+            categories,
+            subscription_ids.len()
+        );
+
+        futures::future::join_all(
+            subscription_ids
+                .iter()
+                .map(|id| self.network.unsubscribe(id)),
+        )
+        .await;
+    }
+
+    /// Clears the paused flag for `categories`.
+    ///
+    /// This doesn't re-create any subscriptions; callers are expected to refresh them.
+    pub(crate) fn resume_subscription_categories(&self, categories: &[SubscriptionCategory]) {
+        if let Ok(mut paused) = self.paused_categories.write() {
+            for category in categories {
+                paused.remove(category);
+            }
+        }
+    }
+
     /// Create a short hash from a pubkey for use in subscription IDs
     /// Uses first 12 characters of SHA256 hash for privacy and collision resistance, salted per session
     pub(crate) fn create_pubkey_hash(&self, pubkey: &PublicKey) -> String {
@@ -32,6 +256,9 @@ impl NostrManager {
         signer: impl NostrSigner + 'static,
         since: Option<Timestamp>,
     ) -> Result<()> {
+        if self.is_subscription_category_paused(SubscriptionCategory::Metadata) {
+            return Ok(());
+        }
         tracing::debug!(
             target: "whitenoise::nostr_manager::setup_batched_relay_subscriptions_with_signer",
             "Setting up batched relay subscriptions with signer (users={}, defaults={})",
@@ -209,10 +436,9 @@ impl NostrManager {
 
         self.ensure_relays_connected(std::slice::from_ref(&relay_url))
             .await?;
-        self.client
-            .subscribe_with_id_to(vec![relay_url], subscription_id, filter, None)
-            .await?;
-        Ok(())
+        self.network
+            .subscribe_to(subscription_id, &[relay_url], filter)
+            .await
     }
 
     /// Refresh subscriptions for a specific user across all their relays
@@ -223,6 +449,9 @@ impl NostrManager {
         default_relays: &[RelayUrl],
         signer: impl NostrSigner + 'static,
     ) -> Result<()> {
+        if self.is_subscription_category_paused(SubscriptionCategory::Metadata) {
+            return Ok(());
+        }
         tracing::debug!(
             target: "whitenoise::nostr_manager::refresh_user_global_subscriptions_with_signer",
             "Refreshing user global subscriptions with signer"
@@ -314,7 +543,7 @@ impl NostrManager {
         let buffer_time = Timestamp::now() - Duration::from_secs(10);
 
         let subscription_id = self.batched_subscription_id(&relay_url, batch_id);
-        self.client.unsubscribe(&subscription_id).await;
+        self.network.unsubscribe(&subscription_id).await;
 
         self.subscribe_user_batch(relay_url, batch_users, subscription_id, Some(buffer_time))
             .await
@@ -327,7 +556,7 @@ impl NostrManager {
         inbox_relays: &[RelayUrl],
         group_relays: &[RelayUrl],
         nostr_group_ids: &[String],
-        since: Option<Timestamp>,
+        since: &SyncSince,
     ) -> Result<()> {
         tracing::debug!(
             target: "whitenoise::nostr_manager::setup_account_subscriptions",
@@ -366,8 +595,11 @@ impl NostrManager {
         &self,
         pubkey: PublicKey,
         user_relays: &[RelayUrl],
-        since: Option<Timestamp>,
+        since: &SyncSince,
     ) -> Result<()> {
+        if self.is_subscription_category_paused(SubscriptionCategory::FollowLists) {
+            return Ok(());
+        }
         tracing::debug!(
             target: "whitenoise::nostr_manager::setup_user_follow_list_subscription",
             "Setting up subscription for pubkey {} on {} relays",
@@ -377,19 +609,19 @@ impl NostrManager {
         let pubkey_hash = self.create_pubkey_hash(&pubkey);
         let subscription_id = SubscriptionId::new(format!("{}_user_follow_list", pubkey_hash));
 
-        let mut user_follow_list_filter = Filter::new().kind(Kind::ContactList).author(pubkey);
-        if let Some(since) = since {
-            user_follow_list_filter = user_follow_list_filter.since(since);
-        }
+        // The account's mute list is kept in sync alongside its follow list
+        for (since, relays) in since.group_relays(SubscriptionCategory::FollowLists, user_relays) {
+            let mut user_follow_list_filter = Filter::new()
+                .kinds([Kind::ContactList, Kind::MuteList])
+                .author(pubkey);
+            if let Some(since) = since {
+                user_follow_list_filter = user_follow_list_filter.since(since);
+            }
 
-        self.client
-            .subscribe_with_id_to(
-                user_relays,
-                subscription_id.clone(),
-                user_follow_list_filter,
-                None,
-            )
-            .await?;
+            self.network
+                .subscribe_to(subscription_id.clone(), &relays, user_follow_list_filter)
+                .await?;
+        }
 
         tracing::debug!(
             target: "whitenoise::nostr_manager::setup_user_follow_list_subscription",
@@ -404,8 +636,11 @@ impl NostrManager {
         &self,
         pubkey: PublicKey,
         inbox_relays: &[RelayUrl],
-        since: Option<Timestamp>,
+        since: &SyncSince,
     ) -> Result<()> {
+        if self.is_subscription_category_paused(SubscriptionCategory::Giftwraps) {
+            return Ok(());
+        }
         tracing::debug!(
             target: "whitenoise::nostr_manager::setup_giftwrap_subscription",
             "Setting up giftwrap subscription"
@@ -413,19 +648,21 @@ impl NostrManager {
         let pubkey_hash = self.create_pubkey_hash(&pubkey);
         let subscription_id = SubscriptionId::new(format!("{}_giftwrap", pubkey_hash));
 
-        let mut giftwrap_filter = Filter::new().kind(Kind::GiftWrap).pubkey(pubkey);
-        if let Some(since) = since {
-            // Account for NIP-59 backdated timestamps - giftwrap events may be timestamped
-            // in the past for privacy, so we look back further than last_synced_at
-            let adjusted_since = adjust_since_for_giftwrap(Some(since));
-            if let Some(adjusted) = adjusted_since {
-                giftwrap_filter = giftwrap_filter.since(adjusted);
+        for (since, relays) in since.group_relays(SubscriptionCategory::Giftwraps, inbox_relays) {
+            let mut giftwrap_filter = Filter::new().kind(Kind::GiftWrap).pubkey(pubkey);
+            if let Some(since) = since {
+                // Account for NIP-59 backdated timestamps - giftwrap events may be timestamped
+                // in the past for privacy, so we look back further than last_synced_at
+                let adjusted_since = adjust_since_for_giftwrap(Some(since));
+                if let Some(adjusted) = adjusted_since {
+                    giftwrap_filter = giftwrap_filter.since(adjusted);
+                }
             }
-        }
 
-        self.client
-            .subscribe_with_id_to(inbox_relays, subscription_id, giftwrap_filter, None)
-            .await?;
+            self.network
+                .subscribe_to(subscription_id.clone(), &relays, giftwrap_filter)
+                .await?;
+        }
 
         tracing::debug!(
             target: "whitenoise::nostr_manager::setup_giftwrap_subscription",
@@ -434,19 +671,98 @@ impl NostrManager {
         Ok(())
     }
 
+    /// Re-targets the giftwrap subscription for `pubkey` at a new set of inbox relays.
+    ///
+    /// Only the giftwrap subscription is replaced; follow list and group message
+    /// subscriptions are left untouched. The old subscription is cleared from every
+    /// relay first so relays that were dropped from the inbox list stop serving it.
+    pub(crate) async fn update_giftwrap_subscription_with_signer(
+        &self,
+        pubkey: PublicKey,
+        inbox_relays: &[RelayUrl],
+        signer: impl NostrSigner + 'static,
+    ) -> Result<()> {
+        tracing::debug!(
+            target: "whitenoise::nostr_manager::update_giftwrap_subscription_with_signer",
+            "Re-targeting giftwrap subscription to {} inbox relays",
+            inbox_relays.len()
+        );
+        let buffer_time = Timestamp::now() - Duration::from_secs(10);
+        let pubkey_hash = self.create_pubkey_hash(&pubkey);
+        let subscription_id = SubscriptionId::new(format!("{}_giftwrap", pubkey_hash));
+        self.network.unsubscribe(&subscription_id).await;
+
+        self.with_signer(signer, || async {
+            self.ensure_relays_connected(inbox_relays).await?;
+            self.setup_giftwrap_subscription(pubkey, inbox_relays, &Some(buffer_time).into())
+                .await
+        })
+        .await
+    }
+
+    /// Returns the relays that currently hold the giftwrap subscription for `pubkey`.
+    pub(crate) async fn giftwrap_subscription_relays(&self, pubkey: &PublicKey) -> Vec<RelayUrl> {
+        let pubkey_hash = self.create_pubkey_hash(pubkey);
+        let subscription_id = SubscriptionId::new(format!("{}_giftwrap", pubkey_hash));
+
+        self.network
+            .subscriptions()
+            .await
+            .remove(&subscription_id)
+            .unwrap_or_default()
+    }
+
+    /// Returns how many of each of the account's subscriptions are active, and where
+    ///
+    /// `inbox_relays` are the account's inbox relays; the giftwrap subscription is checked
+    /// against them separately, since giftwraps sent to the account only arrive there.
+    pub(crate) async fn subscription_breakdown(
+        &self,
+        pubkey: &PublicKey,
+        inbox_relays: &[RelayUrl],
+    ) -> SubscriptionBreakdown {
+        let pubkey_hash = self.create_pubkey_hash(pubkey);
+        let follow_list_id = SubscriptionId::new(format!("{}_user_follow_list", pubkey_hash));
+        let giftwrap_id = SubscriptionId::new(format!("{}_giftwrap", pubkey_hash));
+        let group_messages_id = SubscriptionId::new(format!("{}_mls_messages", pubkey_hash));
+
+        let mut breakdown = SubscriptionBreakdown::default();
+        let mut subscriptions = self.network.subscriptions().await;
+        for relay_url in subscriptions.remove(&follow_list_id).unwrap_or_default() {
+            breakdown.follow_list.add(relay_url);
+        }
+        for relay_url in subscriptions.remove(&giftwrap_id).unwrap_or_default() {
+            if inbox_relays.contains(&relay_url) {
+                breakdown.inbox.add(relay_url.clone());
+            }
+            breakdown.giftwrap.add(relay_url);
+        }
+        for relay_url in subscriptions.remove(&group_messages_id).unwrap_or_default() {
+            breakdown.group_messages.add(relay_url);
+        }
+        breakdown.inbox_missing = inbox_relays
+            .iter()
+            .filter(|relay_url| !breakdown.inbox.relays.contains(relay_url))
+            .cloned()
+            .collect();
+        breakdown
+    }
+
     /// Set up subscription for group messages - can be updated when groups change
     pub(crate) async fn setup_group_messages_subscription(
         &self,
         pubkey: PublicKey,
         nostr_group_ids: &[String],
         group_relays: &[RelayUrl],
-        since: Option<Timestamp>,
+        since: &SyncSince,
     ) -> Result<()> {
         tracing::debug!(
             target: "whitenoise::nostr_manager::setup_group_messages_subscription",
             "Setting up group messages subscription"
         );
-        if nostr_group_ids.is_empty() {
+        if nostr_group_ids.is_empty()
+            || self.is_subscription_category_paused(SubscriptionCategory::GroupMessages)
+        {
             // No groups yet, skip subscription
             return Ok(());
         }
@@ -454,18 +770,21 @@ impl NostrManager {
         let pubkey_hash = self.create_pubkey_hash(&pubkey);
         let subscription_id = SubscriptionId::new(format!("{}_mls_messages", pubkey_hash));
 
-        let mut mls_message_filter = Filter::new()
-            .kind(Kind::MlsGroupMessage)
-            .custom_tags(SingleLetterTag::lowercase(Alphabet::H), nostr_group_ids);
+        for (since, relays) in since.group_relays(SubscriptionCategory::GroupMessages, group_relays)
+        {
+            let mut mls_message_filter = Filter::new()
+                .kind(Kind::MlsGroupMessage)
+                .custom_tags(SingleLetterTag::lowercase(Alphabet::H), nostr_group_ids);
+
+            if let Some(since) = since {
+                mls_message_filter = mls_message_filter.since(since);
+            }
 
-        if let Some(since) = since {
-            mls_message_filter = mls_message_filter.since(since);
+            self.network
+                .subscribe_to(subscription_id.clone(), &relays, mls_message_filter)
+                .await?;
         }
 
-        self.client
-            .subscribe_with_id_to(group_relays, subscription_id, mls_message_filter, None)
-            .await?;
-
         tracing::debug!(
             target: "whitenoise::nostr_manager::setup_group_messages_subscription",
             "Group messages subscription set up"
@@ -473,6 +792,78 @@ impl NostrManager {
         Ok(())
     }
 
+    /// Re-targets the MLS group messages subscription for `pubkey` at a new set of groups.
+    ///
+    /// Only the group messages subscription is replaced. The old subscription is cleared
+    /// from every relay first so relays only used by dropped groups stop serving it; with no
+    /// groups left the subscription is simply removed.
+    pub(crate) async fn update_group_messages_subscription_with_signer(
+        &self,
+        pubkey: PublicKey,
+        group_relays: &[RelayUrl],
+        nostr_group_ids: &[String],
+        signer: impl NostrSigner + 'static,
+    ) -> Result<()> {
+        tracing::debug!(
+            target: "whitenoise::nostr_manager::update_group_messages_subscription_with_signer",
+            "Re-targeting group messages subscription to {} groups",
+            nostr_group_ids.len()
+        );
+        let buffer_time = Timestamp::now() - Duration::from_secs(10);
+        let pubkey_hash = self.create_pubkey_hash(&pubkey);
+        let subscription_id = SubscriptionId::new(format!("{}_mls_messages", pubkey_hash));
+        self.network.unsubscribe(&subscription_id).await;
+
+        self.with_signer(signer, || async {
+            self.ensure_relays_connected(group_relays).await?;
+            self.setup_group_messages_subscription(
+                pubkey,
+                nostr_group_ids,
+                group_relays,
+                &Some(buffer_time).into(),
+            )
+            .await
+        })
+        .await
+    }
+
+    /// Sets up the account's subscriptions again on a single relay.
+    ///
+    /// Subscriptions the account has on `relay_url` are replaced; the ones on other relays are
+    /// left untouched. Used once a relay that closed our subscriptions accepts authentication.
+    pub(crate) async fn resubscribe_account_on_relay_with_signer(
+        &self,
+        pubkey: PublicKey,
+        relay_url: &RelayUrl,
+        user_relays: &[RelayUrl],
+        inbox_relays: &[RelayUrl],
+        group_relays: &[RelayUrl],
+        nostr_group_ids: &[String],
+        signer: impl NostrSigner + 'static,
+    ) -> Result<()> {
+        let on_relay = |relays: &[RelayUrl]| -> Vec<RelayUrl> {
+            relays
+                .iter()
+                .filter(|relay| *relay == relay_url)
+                .cloned()
+                .collect()
+        };
+        let buffer_time = Timestamp::now() - Duration::from_secs(10);
+
+        self.with_signer(signer, || async {
+            self.setup_account_subscriptions(
+                pubkey,
+                &on_relay(user_relays),
+                &on_relay(inbox_relays),
+                &on_relay(group_relays),
+                nostr_group_ids,
+                &Some(buffer_time).into(),
+            )
+            .await
+        })
+        .await
+    }
+
     /// Unsubscribe from all account-specific subscriptions for a given pubkey.
     /// This includes user follow list, giftwrap, and MLS group message subscriptions.
     pub(crate) async fn unsubscribe_account_subscriptions(&self, pubkey: &PublicKey) -> Result<()> {
@@ -486,7 +877,7 @@ impl NostrManager {
 
         let unsubscribe_futures = subscription_ids
             .iter()
-            .map(|id| self.client.unsubscribe(id));
+            .map(|id| self.network.unsubscribe(id));
 
         futures::future::join_all(unsubscribe_futures).await;
 
@@ -502,6 +893,57 @@ mod tests {
     use std::sync::Arc;
     use tokio::sync::mpsc;
 
+    #[test]
+    fn test_sync_since_groups_relays_by_watermark() {
+        let seen = RelayUrl::parse("wss://seen.example.com").unwrap();
+        let fresh = RelayUrl::parse("wss://fresh.example.com").unwrap();
+        let default = Timestamp::from(1_000);
+        let watermark = Timestamp::from(2_000);
+
+        let mut since = SyncSince::from(Some(default));
+        since
+            .per_relay
+            .entry(SubscriptionCategory::Giftwraps)
+            .or_default()
+            .insert(seen.clone(), watermark);
+
+        let giftwraps = SubscriptionCategory::Giftwraps;
+        assert_eq!(since.for_relay(giftwraps, &seen), Some(watermark));
+        assert_eq!(since.for_relay(giftwraps, &fresh), Some(default));
+        // Other subscriptions on the same relay don't share the watermark
+        assert_eq!(
+            since.for_relay(SubscriptionCategory::GroupMessages, &seen),
+            Some(default)
+        );
+
+        let groups = since.group_relays(giftwraps, &[seen.clone(), fresh.clone()]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[&Some(watermark)], vec![seen]);
+        assert_eq!(groups[&Some(default)], vec![fresh]);
+    }
+
+    #[test]
+    fn test_subscription_category_from_subscription_id() {
+        assert_eq!(
+            SubscriptionCategory::from_subscription_id("abc123_giftwrap"),
+            Some(SubscriptionCategory::Giftwraps)
+        );
+        assert_eq!(
+            SubscriptionCategory::from_subscription_id("abc123_mls_messages"),
+            Some(SubscriptionCategory::GroupMessages)
+        );
+        assert_eq!(
+            SubscriptionCategory::from_subscription_id("abc123_other"),
+            None
+        );
+        for category in SubscriptionCategory::ALL {
+            assert_eq!(
+                SubscriptionCategory::parse(category.as_str()),
+                Some(category)
+            );
+        }
+    }
+
     #[tokio::test]
     async fn test_create_pubkey_hash() {
         let (event_sender, _) = mpsc::channel(100);
@@ -1,8 +1,14 @@
-use std::{collections::HashSet, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
 use nostr_sdk::prelude::*;
 
-use crate::nostr_manager::NostrManager;
+use crate::{
+    RelayMarker,
+    nostr_manager::{NostrManager, Result},
+};
 
 /// Maximum allowed skew for event timestamps in the future (1 hour)
 pub(crate) const MAX_FUTURE_SKEW: Duration = Duration::from_secs(60 * 60);
@@ -51,6 +57,34 @@ impl NostrManager {
             .collect()
     }
 
+    /// Extracts the public and private entries of a NIP-51 mute list event.
+    ///
+    /// Private entries are decrypted with the signer, which must belong to the event's author.
+    /// Content encrypted with NIP-04 by older clients is still accepted.
+    pub(crate) async fn mute_list_from_event(
+        event: &Event,
+        signer: &impl NostrSigner,
+    ) -> Result<(Vec<PublicKey>, Vec<PublicKey>)> {
+        let public = Self::pubkeys_from_event(event);
+        if event.content.is_empty() {
+            return Ok((public, Vec::new()));
+        }
+
+        let decrypted = if event.content.contains("?iv=") {
+            signer.nip04_decrypt(&event.pubkey, &event.content).await?
+        } else {
+            signer.nip44_decrypt(&event.pubkey, &event.content).await?
+        };
+        let private_tags: Vec<Vec<String>> = serde_json::from_str(&decrypted)?;
+        let private = private_tags
+            .iter()
+            .filter(|tag| tag.first().map(String::as_str) == Some("p"))
+            .filter_map(|tag| tag.get(1).and_then(|hex| PublicKey::parse(hex).ok()))
+            .collect();
+
+        Ok((public, private))
+    }
+
     /// Extracts relay URLs from an event's tags.
     pub(crate) fn relay_urls_from_event(event: &Event) -> HashSet<RelayUrl> {
         event
@@ -64,6 +98,33 @@ impl NostrManager {
             .collect()
     }
 
+    /// Extracts the read/write markers of a NIP-65 relay list.
+    ///
+    /// Relays without a marker are read+write. Other relay list kinds have no markers, so
+    /// the result is empty for them.
+    pub(crate) fn relay_markers_from_event(event: &Event) -> HashMap<RelayUrl, RelayMarker> {
+        if event.kind != Kind::RelayList {
+            return HashMap::new();
+        }
+
+        event
+            .tags
+            .iter()
+            .filter(|tag| Self::is_r_tag(tag))
+            .filter_map(|tag| {
+                let url = tag
+                    .content()
+                    .and_then(|content| RelayUrl::parse(content).ok())?;
+                let marker = match tag.as_slice().get(2).map(String::as_str) {
+                    Some("read") => RelayMarker::Read,
+                    Some("write") => RelayMarker::Write,
+                    _ => RelayMarker::ReadWrite,
+                };
+                Some((url, marker))
+            })
+            .collect()
+    }
+
     /// Determines if a tag is relevant for the given relay list event kind.
     /// Different relay list kinds use different tag types:
     /// - Kind::RelayList (10002) uses "r" tags (TagKind::SingleLetter)
@@ -119,6 +180,43 @@ mod tests {
         assert!(!parsed_relays.contains(&RelayUrl::parse("wss://should-be-ignored.com").unwrap()));
     }
 
+    #[tokio::test]
+    async fn test_relay_markers_from_event() {
+        let keys = Keys::generate();
+        let tags = vec![
+            Tag::reference("wss://both.example.com"),
+            Tag::custom(
+                TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::R)),
+                ["wss://write.example.com", "write"],
+            ),
+            Tag::custom(
+                TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::R)),
+                ["wss://read.example.com", "read"],
+            ),
+        ];
+        let event = EventBuilder::new(Kind::RelayList, "")
+            .tags(tags)
+            .sign(&keys)
+            .await
+            .unwrap();
+
+        assert_eq!(NostrManager::relay_urls_from_event(&event).len(), 3);
+
+        let markers = NostrManager::relay_markers_from_event(&event);
+        assert_eq!(
+            markers[&RelayUrl::parse("wss://both.example.com").unwrap()],
+            RelayMarker::ReadWrite
+        );
+        assert_eq!(
+            markers[&RelayUrl::parse("wss://write.example.com").unwrap()],
+            RelayMarker::Write
+        );
+        assert_eq!(
+            markers[&RelayUrl::parse("wss://read.example.com").unwrap()],
+            RelayMarker::Read
+        );
+    }
+
     #[tokio::test]
     async fn test_relay_urls_from_event_inbox_relays() {
         use nostr_sdk::prelude::*;
@@ -339,6 +437,31 @@ mod tests {
         assert_eq!(result.len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_mute_list_from_event_decrypts_private_entries() {
+        let keys = Keys::generate();
+        let public_entry = Keys::generate().public_key();
+        let private_entry = Keys::generate().public_key();
+
+        let private_tags = serde_json::to_string(&[Tag::public_key(private_entry)]).unwrap();
+        let content = keys
+            .nip44_encrypt(&keys.public_key(), &private_tags)
+            .await
+            .unwrap();
+        let event = EventBuilder::new(Kind::MuteList, content)
+            .tags([Tag::public_key(public_entry)])
+            .sign(&keys)
+            .await
+            .unwrap();
+
+        let (public, private) = NostrManager::mute_list_from_event(&event, &keys)
+            .await
+            .unwrap();
+
+        assert_eq!(public, vec![public_entry]);
+        assert_eq!(private, vec![private_entry]);
+    }
+
     #[tokio::test]
     async fn test_pubkeys_from_event_with_mixed_valid_and_invalid() {
         let keys1 = Keys::generate();
@@ -2,6 +2,78 @@ use crate::{nostr_manager::parser::SerializableToken, whitenoise::error::Whiteno
 use mdk_core::prelude::*;
 use nostr_sdk::prelude::*;
 use serde::Serialize;
+use std::collections::HashMap;
+use tokio::sync::mpsc::{
+    Sender,
+    error::{SendError, TrySendError},
+};
+
+/// Broad classification of event processing failures for retry decisions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetryErrorClass {
+    /// Failures that may succeed later (database locks, relay hiccups, out-of-order MLS messages)
+    Transient,
+    /// Failures that will fail the same way every time (undecryptable or malformed events)
+    Permanent,
+}
+
+/// Retry behaviour for a class of events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of retry attempts allowed
+    pub max_attempts: u32,
+    /// Base delay in milliseconds for exponential backoff
+    pub base_delay_ms: u64,
+    /// Error classes that are worth retrying
+    pub retryable: Vec<RetryErrorClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            base_delay_ms: 1000,
+            retryable: vec![RetryErrorClass::Transient],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Default retries of publishes that relays didn't accept: three quick attempts
+    pub fn for_publishing() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 500,
+            retryable: vec![RetryErrorClass::Transient],
+        }
+    }
+}
+
+/// Per-kind retry configuration for the event processor
+///
+/// Kinds without an override use `default_policy`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetryConfig {
+    pub default_policy: RetryPolicy,
+    pub per_kind: HashMap<Kind, RetryPolicy>,
+}
+
+impl RetryConfig {
+    /// Override the retry policy for a specific event kind
+    pub fn with_kind_policy(mut self, kind: Kind, policy: RetryPolicy) -> Self {
+        self.per_kind.insert(kind, policy);
+        self
+    }
+
+    pub fn policy_for(&self, kind: Kind) -> &RetryPolicy {
+        self.per_kind.get(&kind).unwrap_or(&self.default_policy)
+    }
+
+    /// Fresh retry state for an event of the given kind
+    pub fn retry_info_for(&self, kind: Kind) -> RetryInfo {
+        RetryInfo::from_policy(self.policy_for(kind))
+    }
+}
 
 /// Retry information for failed event processing
 #[derive(Debug, Clone)]
@@ -12,14 +84,21 @@ pub struct RetryInfo {
     pub max_attempts: u32,
     /// Base delay in milliseconds for exponential backoff
     pub base_delay_ms: u64,
+    /// Error classes that are worth retrying
+    pub retryable: Vec<RetryErrorClass>,
 }
 
 impl RetryInfo {
     pub fn new() -> Self {
+        Self::from_policy(&RetryPolicy::default())
+    }
+
+    pub fn from_policy(policy: &RetryPolicy) -> Self {
         Self {
             attempt: 0,
-            max_attempts: 10,
-            base_delay_ms: 1000,
+            max_attempts: policy.max_attempts,
+            base_delay_ms: policy.base_delay_ms,
+            retryable: policy.retryable.clone(),
         }
     }
 
@@ -29,8 +108,7 @@ impl RetryInfo {
         } else {
             Some(Self {
                 attempt: self.attempt + 1,
-                max_attempts: self.max_attempts,
-                base_delay_ms: self.base_delay_ms,
+                ..self.clone()
             })
         }
     }
@@ -42,6 +120,11 @@ impl RetryInfo {
     pub fn should_retry(&self) -> bool {
         self.attempt < self.max_attempts
     }
+
+    /// Whether a failure with the given error should be retried
+    pub fn should_retry_error(&self, error: &WhitenoiseError) -> bool {
+        self.should_retry() && self.retryable.contains(&error.retry_class())
+    }
 }
 
 impl Default for RetryInfo {
@@ -57,10 +140,17 @@ pub enum ProcessableEvent {
     NostrEvent {
         event: Event,
         subscription_id: Option<String>,
+        /// The relay that delivered the event, `None` for retries and locally created events
+        relay_url: Option<RelayUrl>,
         retry_info: RetryInfo,
     },
     /// A relay message for logging/monitoring purposes
     RelayMessage(RelayUrl, String),
+    /// A NIP-42 AUTH challenge from a relay
+    RelayAuth {
+        relay_url: RelayUrl,
+        challenge: String,
+    },
 }
 
 impl ProcessableEvent {
@@ -69,9 +159,108 @@ impl ProcessableEvent {
         Self::NostrEvent {
             event,
             subscription_id,
+            relay_url: None,
+            retry_info: RetryInfo::new(),
+        }
+    }
+
+    /// Create a new NostrEvent received from a relay, with default retry settings
+    pub fn new_relay_event(
+        event: Event,
+        subscription_id: Option<String>,
+        relay_url: RelayUrl,
+    ) -> Self {
+        Self::NostrEvent {
+            event,
+            subscription_id,
+            relay_url: Some(relay_url),
             retry_info: RetryInfo::new(),
         }
     }
+
+    /// Whether the event is time-sensitive and belongs in the priority queue
+    ///
+    /// Invites and group messages are what users wait on, and relays asking for
+    /// authentication hold back everything they'd deliver; everything else (metadata,
+    /// relay lists, follow lists, relay messages) can wait behind them.
+    pub fn is_priority(&self) -> bool {
+        match self {
+            Self::NostrEvent { event, .. } => matches!(
+                event.kind,
+                Kind::GiftWrap | Kind::MlsWelcome | Kind::MlsGroupMessage
+            ),
+            Self::RelayMessage(..) => false,
+            Self::RelayAuth { .. } => true,
+        }
+    }
+}
+
+/// Normal events that can wait for room in the normal queue before senders are held up
+const NORMAL_OVERFLOW_CAPACITY: usize = 1000;
+
+/// Sending side of the event processor's two queues
+///
+/// Events are routed by [`ProcessableEvent::is_priority`]. The processor always drains the
+/// priority queue first.
+#[derive(Debug, Clone)]
+pub struct ProcessableEventSender {
+    priority: Sender<ProcessableEvent>,
+    normal: Sender<ProcessableEvent>,
+    /// Buffer in front of `normal`, forwarded in order by a single task
+    overflow: Sender<ProcessableEvent>,
+}
+
+impl ProcessableEventSender {
+    /// Must be called from within a Tokio runtime, which runs the overflow forwarder
+    pub fn new(priority: Sender<ProcessableEvent>, normal: Sender<ProcessableEvent>) -> Self {
+        let (overflow, mut overflow_rx) = tokio::sync::mpsc::channel(NORMAL_OVERFLOW_CAPACITY);
+        let forward_to = normal.clone();
+        tokio::spawn(async move {
+            while let Some(event) = overflow_rx.recv().await {
+                // A closed queue means the processor shut down
+                if forward_to.send(event).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            priority,
+            normal,
+            overflow,
+        }
+    }
+
+    /// Queue an event in its lane
+    ///
+    /// Priority events wait for room in their queue, which applies back-pressure to the
+    /// sender. Normal events go through a bounded buffer that a single task forwards to the
+    /// normal queue in order, so a flood of low-priority events can't hold up the priority
+    /// events that arrive behind it; senders only wait once that buffer is full too. Fails
+    /// only if the processor has shut down.
+    pub async fn send(&self, event: ProcessableEvent) -> Result<(), SendError<ProcessableEvent>> {
+        if event.is_priority() {
+            return self.priority.send(event).await;
+        }
+
+        match self.overflow.try_send(event) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Closed(event)) => Err(SendError(event)),
+            Err(TrySendError::Full(event)) => self.overflow.send(event).await,
+        }
+    }
+
+    /// Senders of the priority and normal queues
+    pub(crate) fn lanes(&self) -> (&Sender<ProcessableEvent>, &Sender<ProcessableEvent>) {
+        (&self.priority, &self.normal)
+    }
+}
+
+/// A single channel used for both lanes, for callers that don't need prioritization
+impl From<Sender<ProcessableEvent>> for ProcessableEventSender {
+    fn from(sender: Sender<ProcessableEvent>) -> Self {
+        Self::new(sender.clone(), sender)
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -285,6 +474,52 @@ pub fn detect_media_type(data: &[u8]) -> Result<MediaTypeDetection, WhitenoiseEr
     detect_non_image_type(data)
 }
 
+/// Check a caller-declared MIME type against the type detected from the file's contents
+///
+/// Parameters and case are ignored, as are aliases of the same format (e.g. `image/jpg`).
+/// A mismatch is an error only when `reject_mismatch` is set; otherwise it is logged and the
+/// detected type is meant to be used instead.
+pub(crate) fn check_declared_mime_type(
+    declared: &str,
+    detection: &MediaTypeDetection,
+    reject_mismatch: bool,
+) -> Result<(), WhitenoiseError> {
+    fn canonical(mime_type: &str) -> String {
+        let mime_type = mime_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        match mime_type.as_str() {
+            "image/jpg" => "image/jpeg".to_string(),
+            "audio/x-wav" => "audio/wav".to_string(),
+            "audio/m4a" => "audio/mp4".to_string(),
+            _ => mime_type,
+        }
+    }
+
+    let detected = detection.mime_type();
+    if canonical(declared) == canonical(detected) {
+        return Ok(());
+    }
+
+    if reject_mismatch {
+        return Err(WhitenoiseError::MimeMismatch {
+            declared: declared.to_string(),
+            detected: detected.to_string(),
+        });
+    }
+
+    tracing::warn!(
+        target: "whitenoise::types::check_declared_mime_type",
+        "Media declared as {} is {}, using the detected type",
+        declared,
+        detected
+    );
+    Ok(())
+}
+
 /// Detect non-image media types using the infer crate with explicit whitelist
 ///
 /// This function uses an explicit whitelist to only accept specific formats,
@@ -716,6 +951,29 @@ mod tests {
         assert_eq!(result.extension(), "pdf");
     }
 
+    #[test]
+    fn test_check_declared_mime_type() {
+        let pdf = detect_media_type(b"%PDF-1.4\n").unwrap();
+        let wav = detect_media_type(&[
+            b'R', b'I', b'F', b'F', 0x00, 0x00, 0x00, 0x00, b'W', b'A', b'V', b'E',
+        ])
+        .unwrap();
+
+        assert!(check_declared_mime_type("application/pdf", &pdf, true).is_ok());
+        assert!(check_declared_mime_type("Application/PDF; charset=binary", &pdf, true).is_ok());
+        assert!(check_declared_mime_type("audio/wav", &wav, true).is_ok());
+
+        // A mislabeled file is only rejected when asked to
+        assert!(check_declared_mime_type("image/png", &pdf, false).is_ok());
+        match check_declared_mime_type("image/png", &pdf, true) {
+            Err(WhitenoiseError::MimeMismatch { declared, detected }) => {
+                assert_eq!(declared, "image/png");
+                assert_eq!(detected, "application/pdf");
+            }
+            other => panic!("Expected MimeMismatch, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_detect_media_type_rejects_bmp() {
         // BMP is detectable by infer but NOT in our whitelist
@@ -833,4 +1091,54 @@ mod tests {
         ];
         assert!(detect_media_type(&avi).is_err());
     }
+
+    #[tokio::test]
+    async fn test_event_sender_routes_by_priority_without_blocking() {
+        let (priority_tx, mut priority_rx) = tokio::sync::mpsc::channel(1);
+        let (normal_tx, mut normal_rx) = tokio::sync::mpsc::channel(1);
+        let sender = ProcessableEventSender::new(priority_tx, normal_tx);
+        let keys = Keys::generate();
+        let event = |kind: Kind| {
+            ProcessableEvent::new_nostr_event(
+                EventBuilder::new(kind, "").sign_with_keys(&keys).unwrap(),
+                None,
+            )
+        };
+
+        // Fill the normal queue; further normal events must not block the sender
+        sender.send(event(Kind::Metadata)).await.unwrap();
+        tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            sender.send(event(Kind::ContactList)),
+        )
+        .await
+        .expect("sending to a full normal queue should not block")
+        .unwrap();
+
+        sender.send(event(Kind::GiftWrap)).await.unwrap();
+        let received = priority_rx.recv().await.unwrap();
+        assert!(received.is_priority());
+
+        // Overflowed normal events arrive in order once there is room
+        for kind in [Kind::Metadata, Kind::ContactList] {
+            match normal_rx.recv().await.unwrap() {
+                ProcessableEvent::NostrEvent { event, .. } => assert_eq!(event.kind, kind),
+                other => panic!("unexpected event {:?}", other),
+            }
+        }
+        assert!(
+            !ProcessableEvent::RelayMessage(
+                RelayUrl::parse("wss://relay.example.com").unwrap(),
+                "Ok".to_string()
+            )
+            .is_priority()
+        );
+        assert!(
+            ProcessableEvent::RelayAuth {
+                relay_url: RelayUrl::parse("wss://relay.example.com").unwrap(),
+                challenge: "challenge".to_string(),
+            }
+            .is_priority()
+        );
+    }
 }
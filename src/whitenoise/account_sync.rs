@@ -0,0 +1,237 @@
+//! Syncing an account on demand
+//!
+//! Accounts normally sync through their subscriptions, which pick up from `last_synced_at`.
+//! [`Whitenoise::resync_account`] fetches the same events right away and waits for them to be
+//! processed, so the UI can show progress when the user knows they are behind, e.g. after
+//! switching devices.
+
+use chrono::Utc;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::whitenoise::{Whitenoise, accounts::Account, error::Result, relays::Relay};
+
+/// How many events a [`Whitenoise::resync_account`] fetched, by kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncReport {
+    /// Profile metadata events (kind 0) of the account
+    pub metadata: usize,
+
+    /// NIP-65, inbox and key package relay lists of the account
+    pub relay_lists: usize,
+
+    /// Contact and mute lists of the account
+    pub other_lists: usize,
+
+    /// Giftwraps addressed to the account, which carry welcomes and direct messages
+    pub giftwraps: usize,
+
+    /// Messages of the account's groups
+    pub group_messages: usize,
+
+    /// Fetched events that were already processed
+    pub already_processed: usize,
+
+    /// Fetched events that couldn't be processed
+    pub failed: usize,
+}
+
+impl Whitenoise {
+    /// Fetches everything for an account since a point in time and waits until it's processed.
+    ///
+    /// Fetches the account's metadata and lists, the giftwraps addressed to it and the messages
+    /// of its groups, in that order so groups joined through a fetched welcome get their
+    /// messages too. Events that fail to process are counted rather than failing the resync.
+    /// `last_synced_at` is moved up to now once everything is processed, unless an event
+    /// failed: the subscriptions then start early enough to fetch it again.
+    ///
+    /// # Arguments
+    /// * `account` - The account to sync
+    /// * `since` - Only fetch events from this point on; everything is fetched when `None`
+    pub async fn resync_account(
+        &'static self,
+        account: &Account,
+        since: Option<Timestamp>,
+    ) -> Result<SyncReport> {
+        let mut report = SyncReport::default();
+
+        let write_relays = Relay::urls(&account.write_relays(self).await?);
+        self.nostr.ensure_relays_connected(&write_relays).await?;
+        let lists = self
+            .nostr
+            .fetch_account_lists(account.pubkey, &write_relays, since)
+            .await?;
+        for event in &lists {
+            match event.kind {
+                Kind::Metadata => report.metadata += 1,
+                Kind::RelayList | Kind::InboxRelays | Kind::MlsKeyPackageRelays => {
+                    report.relay_lists += 1
+                }
+                _ => report.other_lists += 1,
+            }
+        }
+        self.process_fetched_events(account, lists, &mut report)
+            .await;
+
+        // Relay lists may have just changed, so inbox relays are read after processing them
+        let inbox_relays = Relay::urls(&account.inbox_relays(self).await?);
+        self.nostr.ensure_relays_connected(&inbox_relays).await?;
+        let giftwraps = self
+            .nostr
+            .fetch_giftwraps(account.pubkey, &inbox_relays, since)
+            .await?;
+        report.giftwraps = giftwraps.len();
+        self.process_fetched_events(account, giftwraps, &mut report)
+            .await;
+
+        let (group_relays, nostr_group_ids) = self.extract_groups_relays_and_ids(account).await?;
+        if !nostr_group_ids.is_empty() {
+            self.nostr.ensure_relays_connected(&group_relays).await?;
+            let group_messages = self
+                .nostr
+                .fetch_group_messages(&group_relays, &nostr_group_ids, since)
+                .await?;
+            report.group_messages = group_messages.len();
+            self.process_fetched_events(account, group_messages, &mut report)
+                .await;
+        }
+
+        if report.failed == 0 {
+            Account::update_last_synced_max(
+                &account.pubkey,
+                Utc::now().timestamp_millis(),
+                &self.database,
+            )
+            .await?;
+        }
+
+        tracing::info!(
+            target: "whitenoise::account_sync",
+            "Resynced account {}: {:?}",
+            account.pubkey.to_hex(),
+            report
+        );
+        Ok(report)
+    }
+
+    /// Processes fetched events oldest first, counting the ones skipped or failed
+    async fn process_fetched_events(
+        &'static self,
+        account: &Account,
+        mut events: Vec<Event>,
+        report: &mut SyncReport,
+    ) {
+        events.sort_by_key(|event| (event.created_at, event.id));
+        for event in &events {
+            match self.process_fetched_account_event(event, account).await {
+                Ok(true) => {}
+                Ok(false) => report.already_processed += 1,
+                Err(e) => {
+                    tracing::warn!(
+                        target: "whitenoise::account_sync",
+                        "Failed to process event {} (kind {}) for {}: {}",
+                        event.id.to_hex(),
+                        event.kind.as_u16(),
+                        account.pubkey.to_hex(),
+                        e
+                    );
+                    report.failed += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::*;
+
+    #[tokio::test]
+    async fn test_resync_account_updates_last_synced_at() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let whitenoise: &'static Whitenoise = Box::leak(Box::new(whitenoise));
+        let account = whitenoise.create_identity().await.unwrap();
+        let before = Utc::now().timestamp_millis();
+
+        let report = whitenoise.resync_account(&account, None).await.unwrap();
+        assert_eq!(report.failed, 0);
+
+        let account = Account::find_by_pubkey(&account.pubkey, &whitenoise.database)
+            .await
+            .unwrap();
+        assert!(account.last_synced_at.unwrap().timestamp_millis() >= before);
+    }
+
+    #[tokio::test]
+    async fn test_resync_account_processes_missed_events() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let whitenoise: &'static Whitenoise = Box::leak(Box::new(whitenoise));
+        let account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(whitenoise, 1).await;
+        let group_id = create_group_with_joined_member(whitenoise, &account, &members[0].0).await;
+        let keys = whitenoise
+            .secrets_store
+            .get_nostr_keys_for_pubkey(&account.pubkey)
+            .unwrap();
+
+        // Without subscriptions, events only reach the account through a resync
+        whitenoise
+            .nostr
+            .unsubscribe_account_subscriptions(&account.pubkey)
+            .await
+            .unwrap();
+        // So `since` leaves out the events published while setting up the account
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+
+        let muted = Keys::generate().public_key();
+        let mute_list = EventBuilder::new(Kind::MuteList, "")
+            .tags([Tag::public_key(muted)])
+            .sign(&keys)
+            .await
+            .unwrap();
+        let since = Some(mute_list.created_at);
+        let write_relays = Relay::urls(&account.write_relays(whitenoise).await.unwrap());
+        whitenoise
+            .nostr
+            .publish_event_to(mute_list, &account.pubkey, &write_relays)
+            .await
+            .unwrap();
+
+        let report = whitenoise.resync_account(&account, since).await.unwrap();
+        assert_eq!(report.other_lists, 1);
+        assert_eq!(report.already_processed, 0);
+        assert_eq!(report.failed, 0);
+        assert_eq!(whitenoise.muted_users(&account).await.unwrap(), vec![muted]);
+
+        // A group message that can't be processed is counted and keeps `last_synced_at`
+        let synced_at = Account::find_by_pubkey(&account.pubkey, &whitenoise.database)
+            .await
+            .unwrap()
+            .last_synced_at;
+        let mdk = Account::create_mdk(account.pubkey, &whitenoise.config.data_dir).unwrap();
+        let group = mdk.get_group(&group_id).unwrap().unwrap();
+        let garbage = EventBuilder::new(Kind::MlsGroupMessage, "garbage")
+            .tag(Tag::custom(
+                TagKind::h(),
+                [hex::encode(group.nostr_group_id)],
+            ))
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        let group_relays: Vec<RelayUrl> = mdk.get_relays(&group_id).unwrap().into_iter().collect();
+        whitenoise
+            .nostr
+            .publish_event_to(garbage, &account.pubkey, &group_relays)
+            .await
+            .unwrap();
+
+        let report = whitenoise.resync_account(&account, since).await.unwrap();
+        assert_eq!(report.already_processed, 1);
+        assert_eq!(report.group_messages, 1);
+        assert_eq!(report.failed, 1);
+        let account = Account::find_by_pubkey(&account.pubkey, &whitenoise.database)
+            .await
+            .unwrap();
+        assert_eq!(account.last_synced_at, synced_at);
+    }
+}
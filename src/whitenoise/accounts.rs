@@ -1,19 +1,27 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 use chrono::{DateTime, Utc};
 use mdk_core::prelude::*;
 use mdk_sqlite_storage::MdkSqliteStorage;
-use nostr_blossom::client::BlossomClient;
+use nostr_sdk::nips::nip49::{self, EncryptedSecretKey};
 use nostr_sdk::prelude::*;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
 use crate::RelayType;
-use crate::nostr_manager::{NostrManager, NostrManagerError};
+use crate::nostr_manager::{NostrManager, NostrManagerError, SyncSince};
 use crate::types::ImageType;
+use crate::whitenoise::app_settings::AppSettings;
+use crate::whitenoise::blossom;
+use crate::whitenoise::database::account_mutes::{AccountMutes, MutedPubkey};
+use crate::whitenoise::database::left_groups::LeftGroups;
+use crate::whitenoise::database::media_files::MediaFile;
+use crate::whitenoise::database::relay_sync_watermarks::RelaySyncWatermarks;
 use crate::whitenoise::error::Result;
-use crate::whitenoise::relays::Relay;
+use crate::whitenoise::relays::{Relay, RelayListDiff, RelayMarker};
+use crate::whitenoise::secrets_store::SecretsStatus;
 use crate::whitenoise::users::User;
 use crate::whitenoise::{Whitenoise, WhitenoiseError};
 
@@ -128,6 +136,74 @@ impl Account {
         Ok(relays)
     }
 
+    /// Retrieves the account's NIP-65 relays along with their read/write markers.
+    pub async fn nip65_relays_with_markers(
+        &self,
+        whitenoise: &Whitenoise,
+    ) -> Result<Vec<(Relay, RelayMarker)>> {
+        let user = self.user(&whitenoise.database).await?;
+        let relays = user
+            .relays_with_markers(RelayType::Nip65, &whitenoise.database)
+            .await?;
+        Ok(relays)
+    }
+
+    /// NIP-65 relays the account publishes its events to.
+    ///
+    /// Falls back to all NIP-65 relays if none is marked for writing.
+    pub(crate) async fn write_relays(&self, whitenoise: &Whitenoise) -> Result<Vec<Relay>> {
+        self.nip65_relays_preferring(whitenoise, RelayMarker::is_write)
+            .await
+    }
+
+    async fn nip65_relays_preferring(
+        &self,
+        whitenoise: &Whitenoise,
+        preferred: fn(&RelayMarker) -> bool,
+    ) -> Result<Vec<Relay>> {
+        let relays = self.nip65_relays_with_markers(whitenoise).await?;
+        let has_preferred = relays.iter().any(|(_, marker)| preferred(marker));
+        Ok(relays
+            .into_iter()
+            .filter(|(_, marker)| !has_preferred || preferred(marker))
+            .map(|(relay, _)| relay)
+            .collect())
+    }
+
+    /// Marks one of the account's NIP-65 relays as read, write or both.
+    ///
+    /// Stores the marker and publishes the updated relay list to the Nostr network.
+    ///
+    /// # Arguments
+    ///
+    /// * `relay` - One of the account's NIP-65 relays
+    /// * `marker` - How the relay should be used
+    /// * `whitenoise` - The Whitenoise instance for database and network operations
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::UserRelayNotFound`] if the relay isn't in the account's
+    /// NIP-65 relay list.
+    pub async fn set_relay_marker(
+        &self,
+        relay: &Relay,
+        marker: RelayMarker,
+        whitenoise: &Whitenoise,
+    ) -> Result<()> {
+        let user = self.user(&whitenoise.database).await?;
+        user.set_relay_marker(relay, RelayType::Nip65, marker, &whitenoise.database)
+            .await?;
+        whitenoise
+            .background_publish_account_relay_list(self, RelayType::Nip65, None)
+            .await?;
+        whitenoise
+            .handle_account_relay_list_change(self, RelayType::Nip65)
+            .await;
+        tracing::debug!(target: "whitenoise::accounts::set_relay_marker", "Marked relay {:?} as {:?}", relay.url, marker);
+
+        Ok(())
+    }
+
     /// Adds a relay to the account's relay list for the specified relay type.
     ///
     /// This method adds a relay to the account's local relay configuration and automatically
@@ -155,6 +231,9 @@ impl Account {
         whitenoise
             .background_publish_account_relay_list(self, relay_type, None)
             .await?;
+        whitenoise
+            .handle_account_relay_list_change(self, relay_type)
+            .await;
         tracing::debug!(target: "whitenoise::accounts::add_relay", "Added relay to account: {:?}", relay.url);
 
         Ok(())
@@ -186,10 +265,118 @@ impl Account {
         whitenoise
             .background_publish_account_relay_list(self, relay_type, None)
             .await?;
+        whitenoise
+            .handle_account_relay_list_change(self, relay_type)
+            .await;
         tracing::debug!(target: "whitenoise::accounts::remove_relay", "Removed relay from account: {:?}", relay.url);
         Ok(())
     }
 
+    /// Replaces the account's relay list of the given type with `relays`.
+    ///
+    /// The relay list currently published by the account is fetched first, and a new relay
+    /// list event is only published if it differs from `relays`. Republishing an identical
+    /// list would only bump the replaceable event's timestamp and make other clients and our
+    /// own subscriptions react to a change that didn't happen.
+    ///
+    /// If the published list can't be fetched, the new list is published anyway.
+    ///
+    /// # Arguments
+    ///
+    /// * `relay_type` - The type of relay list to replace
+    /// * `relays` - The complete new relay list
+    /// * `whitenoise` - The Whitenoise instance for database and network operations
+    ///
+    /// # Returns
+    ///
+    /// Whether a new relay list was published (`false` if nothing changed)
+    pub async fn update_relays(
+        &self,
+        relay_type: RelayType,
+        relays: &[Relay],
+        whitenoise: &Whitenoise,
+    ) -> Result<bool> {
+        let requested: HashSet<RelayUrl> = Relay::urls(relays).into_iter().collect();
+
+        let user = self.user(&whitenoise.database).await?;
+        let local_relays = user.relays(relay_type, &whitenoise.database).await?;
+        let local: HashSet<RelayUrl> = Relay::urls(&local_relays).into_iter().collect();
+        let local_diff = RelayListDiff::between(&local, &requested);
+        if !local_diff.is_empty() {
+            for relay in local_relays
+                .iter()
+                .filter(|relay| local_diff.removed.contains(&relay.url))
+            {
+                user.remove_relay(relay, relay_type, &whitenoise.database)
+                    .await?;
+            }
+            let added: Vec<Relay> = relays
+                .iter()
+                .filter(|relay| local_diff.added.contains(&relay.url))
+                .cloned()
+                .collect();
+            user.add_relays(&added, relay_type, &whitenoise.database)
+                .await?;
+        }
+
+        let nip65_urls = Relay::urls(&self.nip65_relays(whitenoise).await?);
+        let published_diff = match whitenoise
+            .nostr
+            .fetch_user_relays(self.pubkey, relay_type, &nip65_urls)
+            .await
+        {
+            Ok(event) => {
+                let published = event
+                    .map(|event| NostrManager::relay_urls_from_event(&event))
+                    .unwrap_or_default();
+                Some(RelayListDiff::between(&published, &requested))
+            }
+            Err(e) => {
+                tracing::warn!(
+                    target: "whitenoise::accounts::update_relays",
+                    "Failed to fetch published {:?} relay list, publishing anyway: {}",
+                    relay_type,
+                    e
+                );
+                None
+            }
+        };
+
+        let published = match published_diff {
+            Some(diff) if diff.is_empty() => {
+                tracing::debug!(
+                    target: "whitenoise::accounts::update_relays",
+                    "{:?} relay list unchanged, not publishing",
+                    relay_type
+                );
+                false
+            }
+            diff => {
+                if let Some(diff) = diff {
+                    tracing::debug!(
+                        target: "whitenoise::accounts::update_relays",
+                        "Publishing {:?} relay list: added {:?}, removed {:?}",
+                        relay_type,
+                        diff.added,
+                        diff.removed
+                    );
+                }
+                whitenoise
+                    .background_publish_account_relay_list(self, relay_type, Some(relays))
+                    .await?;
+                true
+            }
+        };
+
+        if !local_diff.is_empty() {
+            whitenoise
+                .handle_account_relay_list_change(self, relay_type)
+                .await;
+        }
+
+        Ok(published)
+    }
+
     /// Retrieves the cached metadata for this account.
     ///
     /// This method returns the account's stored metadata from the local database without
@@ -241,21 +428,21 @@ impl Account {
         server: Url,
         whitenoise: &Whitenoise,
     ) -> Result<String> {
-        let client = BlossomClient::new(server);
         let keys = whitenoise
             .secrets_store
             .get_nostr_keys_for_pubkey(&self.pubkey)?;
         let data = tokio::fs::read(file_path).await?;
-
-        let descriptor = client
-            .upload_blob(
-                data,
-                Some(image_type.mime_type().to_string()),
-                None,
-                Some(&keys),
-            )
-            .await
-            .map_err(|err| WhitenoiseError::Other(anyhow::anyhow!(err)))?;
+        let hash: [u8; 32] = Sha256::digest(&data).into();
+
+        let descriptor = blossom::upload_blob(
+            &server,
+            data,
+            &hash,
+            image_type.mime_type(),
+            &keys,
+            whitenoise.config.blossom_auth_expiry,
+        )
+        .await?;
 
         Ok(descriptor.url.to_string())
     }
@@ -312,6 +499,35 @@ impl Whitenoise {
     /// * `nsec_or_hex_privkey` - The user's private key as a nsec string or hex-encoded string.
     pub async fn login(&self, nsec_or_hex_privkey: String) -> Result<Account> {
         let keys = Keys::parse(&nsec_or_hex_privkey)?;
+        self.login_with_keys(keys).await
+    }
+
+    /// Logs in an existing user using a passphrase-encrypted private key (NIP-49).
+    ///
+    /// Fails with [`WhitenoiseError::WrongNcryptsecPassphrase`] if the passphrase doesn't
+    /// decrypt the key, and with [`WhitenoiseError::InvalidNcryptsec`] if the input isn't a
+    /// valid `ncryptsec1...` string.
+    ///
+    /// # Arguments
+    ///
+    /// * `ncryptsec` - The encrypted private key, as exported by [`Whitenoise::export_account_ncryptsec`]
+    /// * `passphrase` - The passphrase the key was encrypted with
+    pub async fn login_with_ncryptsec(&self, ncryptsec: &str, passphrase: &str) -> Result<Account> {
+        let encrypted = EncryptedSecretKey::from_bech32(ncryptsec.trim())
+            .map_err(|e| WhitenoiseError::InvalidNcryptsec(e.to_string()))?;
+        let passphrase = passphrase.to_string();
+        // scrypt is deliberately slow, keep it off the async runtime
+        let secret_key = tokio::task::spawn_blocking(move || {
+            encrypted.decrypt(passphrase).map_err(|e| match e {
+                nip49::Error::ChaCha20Poly1305(_) => WhitenoiseError::WrongNcryptsecPassphrase,
+                e => WhitenoiseError::InvalidNcryptsec(e.to_string()),
+            })
+        })
+        .await??;
+        self.login_with_keys(Keys::new(secret_key)).await
+    }
+
+    async fn login_with_keys(&self, keys: Keys) -> Result<Account> {
         let pubkey = keys.public_key();
         tracing::debug!(target: "whitenoise::login", "Logging in with pubkey: {}", pubkey.to_hex());
 
@@ -345,6 +561,7 @@ impl Whitenoise {
     ///
     /// This method performs the following steps:
     /// - Removes the account from the database.
+    /// - Cancels background tasks still running for the account.
     /// - Removes the private key from the secret store.
     /// - Updates the active account if the logged-out account was active.
     /// - Removes the account from the in-memory accounts list.
@@ -357,6 +574,9 @@ impl Whitenoise {
     pub async fn logout(&self, pubkey: &PublicKey) -> Result<()> {
         let account = Account::find_by_pubkey(pubkey, &self.database).await?;
 
+        // Stop background work before the account's data goes away
+        self.cancel_background_fetch(pubkey);
+
         // Unsubscribe from account-specific subscriptions before logout
         if let Err(e) = self.nostr.unsubscribe_account_subscriptions(pubkey).await {
             tracing::warn!(
@@ -373,9 +593,170 @@ impl Whitenoise {
         // Remove the private key from the secret store
         self.secrets_store.remove_private_key_for_pubkey(pubkey)?;
 
+        // Hand the active account over to the oldest remaining account, if any
+        if AppSettings::active_account_pubkey(&self.database).await? == Some(*pubkey) {
+            let next = Account::first(&self.database).await?;
+            AppSettings::update_active_account_pubkey(
+                next.as_ref().map(|account| &account.pubkey),
+                &self.database,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes an account and all of its local data, leaving other accounts untouched.
+    ///
+    /// Unlike [`Whitenoise::logout`], which keeps the account's MLS state so logging back in
+    /// restores its groups, this also deletes the account's MLS storage and its cached media.
+    /// The account is logged out as part of the purge. Nothing is deleted from relays.
+    ///
+    /// # Arguments
+    ///
+    /// * `pubkey` - The public key of the account to purge
+    pub async fn purge_account(&self, pubkey: &PublicKey) -> Result<()> {
+        Account::find_by_pubkey(pubkey, &self.database).await?;
+
+        // Media records go away with the account row, so collect their files first
+        let orphaned_media = MediaFile::delete_by_account(&self.database, pubkey).await?;
+
+        // Remove MLS storage while the account still exists, so a failure here can be retried
+        Self::remove_mls_storage(&self.config.data_dir, pubkey).await?;
+
+        self.logout(pubkey).await?;
+
+        for path in orphaned_media {
+            if let Err(e) = tokio::fs::remove_file(&path).await
+                && e.kind() != std::io::ErrorKind::NotFound
+            {
+                tracing::warn!(
+                    target: "whitenoise::purge_account",
+                    "Failed to remove cached media file {:?}: {}",
+                    path,
+                    e
+                );
+            }
+        }
+
+        self.contact_list_guards.remove(pubkey);
+        self.contact_list_debounce.remove(pubkey);
+        self.typing_indicators_sent
+            .retain(|(account_pubkey, _), _| account_pubkey != pubkey);
+        self.messaging_readiness_cache.remove(pubkey);
+
+        tracing::info!(
+            target: "whitenoise::purge_account",
+            "Purged account {}",
+            pubkey.to_hex()
+        );
+        Ok(())
+    }
+
+    /// Deletes an account's MLS storage: the SQLite file `create_mdk` opens and its `-wal`/`-shm`
+    /// siblings. A directory at any of those paths is removed as a whole.
+    async fn remove_mls_storage(data_dir: &Path, pubkey: &PublicKey) -> Result<()> {
+        let mls_dir = data_dir.join("mls");
+        let name = pubkey.to_hex();
+        for path in [
+            mls_dir.join(&name),
+            mls_dir.join(format!("{name}-wal")),
+            mls_dir.join(format!("{name}-shm")),
+        ] {
+            let removed = match tokio::fs::metadata(&path).await {
+                Ok(metadata) if metadata.is_dir() => tokio::fs::remove_dir_all(&path).await,
+                Ok(_) => tokio::fs::remove_file(&path).await,
+                Err(e) => Err(e),
+            };
+            match removed {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
         Ok(())
     }
 
+    /// Makes the account the one the user is currently looking at.
+    ///
+    /// The choice is persisted locally and survives restarts; nothing is published to relays.
+    /// Subscriptions for the active account are set up before those of other accounts.
+    ///
+    /// # Arguments
+    ///
+    /// * `pubkey` - The public key of the account to activate (must be logged in)
+    pub async fn set_active_account(&self, pubkey: &PublicKey) -> Result<()> {
+        let account = Account::find_by_pubkey(pubkey, &self.database).await?;
+        AppSettings::update_active_account_pubkey(Some(&account.pubkey), &self.database).await
+    }
+
+    /// Returns the account the user is currently looking at.
+    ///
+    /// Returns `None` if no account was ever activated, or if the active account has since
+    /// been removed.
+    pub async fn active_account(&self) -> Result<Option<Account>> {
+        let Some(pubkey) = AppSettings::active_account_pubkey(&self.database).await? else {
+            return Ok(None);
+        };
+        match Account::find_by_pubkey(&pubkey, &self.database).await {
+            Ok(account) => Ok(Some(account)),
+            Err(WhitenoiseError::AccountNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Checks that the account's private key is present in the secrets store and usable.
+    ///
+    /// Keys can become inaccessible after keyring permission changes or OS migrations,
+    /// which otherwise surfaces as confusing signing failures. Use
+    /// [`Whitenoise::reimport_secret`] to repair a key reported as missing or mismatched.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account whose key should be checked.
+    pub fn verify_secrets(&self, account: &Account) -> SecretsStatus {
+        let status = self.secrets_store.verify_private_key(&account.pubkey);
+        if status != SecretsStatus::Usable {
+            tracing::warn!(
+                target: "whitenoise::verify_secrets",
+                "Private key for account {} is not usable: {:?}",
+                account.pubkey.to_hex(),
+                status
+            );
+        }
+        status
+    }
+
+    /// Re-imports the private key for an existing account into the secrets store.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account to repair.
+    /// * `nsec_or_hex_privkey` - The account's private key as a nsec string or hex-encoded string.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::InvalidInput`] if the key does not belong to the account, or an
+    /// error if the key cannot be stored or is still unusable after storing it.
+    pub fn reimport_secret(&self, account: &Account, nsec_or_hex_privkey: String) -> Result<()> {
+        let keys = Keys::parse(&nsec_or_hex_privkey)?;
+        if keys.public_key() != account.pubkey {
+            return Err(WhitenoiseError::InvalidInput(
+                "Private key does not belong to this account".to_string(),
+            ));
+        }
+
+        self.secrets_store.store_private_key(&keys)?;
+
+        match self.verify_secrets(account) {
+            SecretsStatus::Usable => Ok(()),
+            status => Err(WhitenoiseError::Other(anyhow::anyhow!(
+                "Private key is still not usable after re-import: {:?}",
+                status
+            ))),
+        }
+    }
+
     /// Returns the total number of accounts stored in the database.
     ///
     /// This method queries the database to count all accounts that have been created
@@ -520,7 +901,7 @@ impl Whitenoise {
 
     async fn load_default_relays(&self) -> Result<Vec<Relay>> {
         let mut default_relays = Vec::new();
-        for Relay { url, .. } in Relay::defaults() {
+        for Relay { url, .. } in self.default_relays() {
             let relay = self.find_or_create_relay_by_url(&url).await?;
             default_relays.push(relay);
         }
@@ -714,6 +1095,7 @@ impl Whitenoise {
         self.nostr
             .publish_relay_list_with_signer(
                 &relays_urls,
+                &HashMap::new(),
                 relay_type,
                 &target_relays_urls,
                 keys.clone(),
@@ -732,9 +1114,9 @@ impl Whitenoise {
             .secrets_store
             .get_nostr_keys_for_pubkey(&account.pubkey)?;
         let user = account.user(&self.database).await?;
-        let relays = account.nip65_relays(self).await?;
+        let relays = account.write_relays(self).await?;
 
-        tokio::spawn(async move {
+        self.spawn_account_task(account.pubkey, async move {
             tracing::debug!(target: "whitenoise::accounts::background_publish_user_metadata", "Background task: Publishing metadata for account: {:?}", account_clone.pubkey);
 
             let relays_urls = Relay::urls(&relays);
@@ -777,15 +1159,25 @@ impl Whitenoise {
         } else {
             account.nip65_relays(self).await?
         };
+        let markers: HashMap<RelayUrl, RelayMarker> = if relay_type == RelayType::Nip65 {
+            account
+                .nip65_relays_with_markers(self)
+                .await?
+                .into_iter()
+                .map(|(relay, marker)| (relay.url, marker))
+                .collect()
+        } else {
+            HashMap::new()
+        };
 
-        tokio::spawn(async move {
+        self.spawn_account_task(account.pubkey, async move {
             tracing::debug!(target: "whitenoise::accounts::background_publish_account_relay_list", "Background task: Publishing relay list for account: {:?}", account_clone.pubkey);
 
             let relays_urls = Relay::urls(&relays);
             let target_relays_urls = Relay::urls(&target_relays);
 
             nostr
-                .publish_relay_list_with_signer(&relays_urls, relay_type, &target_relays_urls, keys)
+                .publish_relay_list_with_signer(&relays_urls, &markers, relay_type, &target_relays_urls, keys)
                 .await?;
 
             tracing::debug!(target: "whitenoise::accounts::background_publish_account_relay_list", "Successfully published relay list for account: {:?}", account_clone.pubkey);
@@ -800,14 +1192,14 @@ impl Whitenoise {
     ) -> Result<()> {
         let account_clone = account.clone();
         let nostr = self.nostr.clone();
-        let relays = account.nip65_relays(self).await?;
+        let relays = account.write_relays(self).await?;
         let keys = self
             .secrets_store
             .get_nostr_keys_for_pubkey(&account.pubkey)?;
         let follows = account.follows(&self.database).await?;
         let follows_pubkeys = follows.iter().map(|f| f.pubkey).collect::<Vec<_>>();
 
-        tokio::spawn(async move {
+        self.spawn_account_task(account.pubkey, async move {
             tracing::debug!(target: "whitenoise::accounts::background_publish_account_follow_list", "Background task: Publishing follow list for account: {:?}", account_clone.pubkey);
 
             let relays_urls = Relay::urls(&relays);
@@ -821,17 +1213,117 @@ impl Whitenoise {
         Ok(())
     }
 
+    pub(crate) async fn background_publish_account_mute_list(
+        &self,
+        account: &Account,
+    ) -> Result<()> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        let account_clone = account.clone();
+        let nostr = self.nostr.clone();
+        let relays = account.write_relays(self).await?;
+        let keys = self
+            .secrets_store
+            .get_nostr_keys_for_pubkey(&account.pubkey)?;
+        let (private, public): (Vec<MutedPubkey>, Vec<MutedPubkey>) =
+            AccountMutes::find_by_account(account_id, &self.database)
+                .await?
+                .into_iter()
+                .partition(|entry| entry.private);
+        let public_pubkeys = public.iter().map(|m| m.pubkey).collect::<Vec<_>>();
+        let private_pubkeys = private.iter().map(|m| m.pubkey).collect::<Vec<_>>();
+
+        self.spawn_account_task(account.pubkey, async move {
+            tracing::debug!(target: "whitenoise::accounts::background_publish_account_mute_list", "Background task: Publishing mute list for account: {:?}", account_clone.pubkey);
+
+            let relays_urls = Relay::urls(&relays);
+            nostr
+                .publish_mute_list_with_signer(
+                    &public_pubkeys,
+                    &private_pubkeys,
+                    &relays_urls,
+                    keys,
+                )
+                .await?;
+
+            tracing::debug!(target: "whitenoise::accounts::background_publish_account_mute_list", "Successfully published mute list for account: {:?}", account_clone.pubkey);
+            Ok::<(), WhitenoiseError>(())
+        });
+        Ok(())
+    }
+
+    /// Runs a background task on behalf of an account
+    ///
+    /// The task is tracked so [`Whitenoise::cancel_background_fetch`] can abort it when the
+    /// account logs out.
+    pub(crate) fn spawn_account_task<F>(&self, pubkey: PublicKey, task: F)
+    where
+        F: Future<Output = Result<()>> + Send + 'static,
+    {
+        // Spawn while holding the entry, so a cancel can't run between spawning and tracking
+        let mut tasks = self.account_background_tasks.entry(pubkey).or_default();
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(tokio::spawn(task).abort_handle());
+    }
+
+    /// Cancels the background fetches and publishes still running for an account
+    ///
+    /// Called on logout, so tasks don't keep writing for an account that no longer exists.
+    ///
+    /// # Returns
+    /// The number of tasks that were still running
+    pub fn cancel_background_fetch(&self, pubkey: &PublicKey) -> usize {
+        let Some((_, tasks)) = self.account_background_tasks.remove(pubkey) else {
+            return 0;
+        };
+
+        let running: Vec<_> = tasks
+            .into_iter()
+            .filter(|task| !task.is_finished())
+            .collect();
+        for task in &running {
+            task.abort();
+        }
+        if !running.is_empty() {
+            tracing::debug!(
+                target: "whitenoise::accounts::cancel_background_fetch",
+                "Cancelled {} background tasks for {}",
+                running.len(),
+                pubkey.to_hex()
+            );
+        }
+        running.len()
+    }
+
+    /// Whether any background task is still running for the account
+    pub fn has_background_fetch(&self, pubkey: &PublicKey) -> bool {
+        self.account_background_tasks
+            .get(pubkey)
+            .is_some_and(|tasks| tasks.iter().any(|task| !task.is_finished()))
+    }
+
     /// Extract group data including relay URLs and group IDs for subscription setup.
+    ///
+    /// Only groups the account is still active in count: inactive groups and groups it left,
+    /// whose removal may not be committed yet, are skipped.
     pub(crate) async fn extract_groups_relays_and_ids(
         &self,
         account: &Account,
     ) -> Result<(Vec<RelayUrl>, Vec<String>)> {
+        let left_groups = match account.id {
+            Some(account_id) => LeftGroups::find(account_id, &self.database).await?,
+            None => Vec::new(),
+        };
         let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
         let groups = mdk.get_groups()?;
         let mut group_relays_set = HashSet::new();
         let mut group_ids = vec![];
 
         for group in &groups {
+            if group.state != group_types::GroupState::Active
+                || left_groups.contains(&group.mls_group_id)
+            {
+                continue;
+            }
             let relays = mdk.get_relays(&group.mls_group_id)?;
             group_relays_set.extend(relays);
             group_ids.push(hex::encode(group.nostr_group_id));
@@ -882,6 +1374,22 @@ impl Whitenoise {
             ),
         }
 
+        // Subscriptions resume from their own watermark on relays that delivered events to
+        // them before. Until the account has synced once, everything is fetched from every relay.
+        let mut sync_since = SyncSince::from(since);
+        if since.is_some()
+            && let Some(account_id) = account.id
+        {
+            let mut watermarks =
+                RelaySyncWatermarks::find_by_account(account_id, &self.database).await?;
+            for relay_watermarks in watermarks.values_mut() {
+                for seen_at in relay_watermarks.values_mut() {
+                    *seen_at = Timestamp::from(seen_at.as_u64().saturating_sub(10));
+                }
+            }
+            sync_since.per_relay = watermarks;
+        }
+
         let keys = self
             .secrets_store
             .get_nostr_keys_for_pubkey(&account.pubkey)?;
@@ -893,7 +1401,7 @@ impl Whitenoise {
                 &inbox_relays,
                 &group_relays_urls,
                 &nostr_group_ids,
-                since,
+                &sync_since,
                 keys,
             )
             .await?;
@@ -921,7 +1429,8 @@ impl Whitenoise {
             account.pubkey
         );
 
-        let user_relays: Vec<RelayUrl> = Relay::urls(&account.nip65_relays(self).await?);
+        // The account publishes its own follow and mute lists to its write relays
+        let user_relays: Vec<RelayUrl> = Relay::urls(&account.write_relays(self).await?);
 
         let inbox_relays: Vec<RelayUrl> = Relay::urls(&account.inbox_relays(self).await?);
 
@@ -944,6 +1453,77 @@ impl Whitenoise {
             .await
             .map_err(WhitenoiseError::from)
     }
+
+    /// Reacts to a local edit of one of the account's relay lists.
+    ///
+    /// Inbox relay edits move the giftwrap subscription to the new relay set (unless
+    /// disabled via `WhitenoiseConfig::resubscribe_giftwrap_on_inbox_change`), otherwise
+    /// invites sent to the new inbox relays would never reach us. Failures are logged
+    /// rather than returned since the relay list itself was already saved and published.
+    pub(crate) async fn handle_account_relay_list_change(
+        &self,
+        account: &Account,
+        relay_type: RelayType,
+    ) {
+        if relay_type != RelayType::Inbox || !self.config.resubscribe_giftwrap_on_inbox_change {
+            return;
+        }
+
+        if let Err(e) = self.refresh_giftwrap_subscription(account).await {
+            tracing::warn!(
+                target: "whitenoise::accounts::handle_account_relay_list_change",
+                "Failed to move giftwrap subscription after inbox relay change for {}: {}",
+                account.pubkey.to_hex(),
+                e
+            );
+        }
+    }
+
+    /// Re-targets the account's giftwrap subscription at its current inbox relays.
+    ///
+    /// After re-subscribing, verifies that at least one of the inbox relays is connected
+    /// (or connecting) so a relay edit that leaves the account unreachable surfaces as an
+    /// error instead of silently dropping invites.
+    ///
+    /// If the account has no inbox relays the existing subscription is left in place.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account whose giftwrap subscription should be moved
+    pub(crate) async fn refresh_giftwrap_subscription(&self, account: &Account) -> Result<()> {
+        let inbox_relays: Vec<RelayUrl> = Relay::urls(&account.inbox_relays(self).await?);
+
+        if inbox_relays.is_empty() {
+            tracing::warn!(
+                target: "whitenoise::accounts::refresh_giftwrap_subscription",
+                "Account {} has no inbox relays, keeping existing giftwrap subscription",
+                account.pubkey.to_hex()
+            );
+            return Ok(());
+        }
+
+        let keys = self
+            .secrets_store
+            .get_nostr_keys_for_pubkey(&account.pubkey)?;
+
+        self.nostr
+            .update_giftwrap_subscription_with_signer(account.pubkey, &inbox_relays, keys)
+            .await?;
+
+        if !self.nostr.has_any_relay_connected(&inbox_relays).await {
+            return Err(WhitenoiseError::NostrManager(
+                NostrManagerError::NoRelayConnections,
+            ));
+        }
+
+        tracing::debug!(
+            target: "whitenoise::accounts::refresh_giftwrap_subscription",
+            "Giftwrap subscription moved to {} inbox relays for {}",
+            inbox_relays.len(),
+            account.pubkey.to_hex()
+        );
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -966,9 +1546,13 @@ pub mod test_utils {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::nostr_manager::nostr_client::MemoryNostrClient;
+    use crate::types::ProcessableEvent;
     use crate::whitenoise::accounts::Account;
+    use crate::whitenoise::database::media_files::MediaFileParams;
     use crate::whitenoise::test_utils::*;
     use chrono::{TimeDelta, Utc};
+    use std::sync::Arc;
 
     #[tokio::test]
     #[ignore]
@@ -983,6 +1567,116 @@ mod tests {
             .unwrap();
     }
 
+    #[tokio::test]
+    async fn test_logout_cancels_background_tasks() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        assert_eq!(
+            whitenoise.cancel_background_fetch(&Keys::generate().public_key()),
+            0
+        );
+
+        let (finished_sender, finished_receiver) = tokio::sync::oneshot::channel::<()>();
+        whitenoise.spawn_account_task(account.pubkey, async move {
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            let _ = finished_sender.send(());
+            Ok(())
+        });
+        assert!(whitenoise.has_background_fetch(&account.pubkey));
+
+        whitenoise.logout(&account.pubkey).await.unwrap();
+        assert!(!whitenoise.has_background_fetch(&account.pubkey));
+        // The aborted task dropped its sender without sending
+        assert!(finished_receiver.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_purge_account_keeps_other_accounts() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let purged = whitenoise.create_identity().await.unwrap();
+        let kept = whitenoise.create_identity().await.unwrap();
+
+        // Open both accounts' real MLS stores; the purged one stays open, as it would in the app
+        let _purged_mdk = Account::create_mdk(purged.pubkey, &whitenoise.config.data_dir).unwrap();
+        drop(Account::create_mdk(kept.pubkey, &whitenoise.config.data_dir).unwrap());
+        let mls_path = |account: &Account, suffix: &str| {
+            whitenoise
+                .config
+                .data_dir
+                .join("mls")
+                .join(format!("{}{suffix}", account.pubkey.to_hex()))
+        };
+        assert!(mls_path(&purged, "").is_file());
+        assert!(mls_path(&kept, "").is_file());
+
+        // Both accounts are in a group sharing one cached file; the purged one has another
+        let group_id = GroupId::from_slice(&[7u8; 8]);
+        let media_dir = whitenoise.config.data_dir.join("media_test");
+        std::fs::create_dir_all(&media_dir).unwrap();
+        let shared_file = media_dir.join("shared.jpg");
+        let own_file = media_dir.join("own.jpg");
+        for (account, path, hash) in [
+            (&purged, &shared_file, [1u8; 32]),
+            (&kept, &shared_file, [1u8; 32]),
+            (&purged, &own_file, [2u8; 32]),
+        ] {
+            std::fs::write(path, b"data").unwrap();
+            MediaFile::save(
+                &whitenoise.database,
+                &group_id,
+                &account.pubkey,
+                MediaFileParams {
+                    file_path: path,
+                    original_file_hash: None,
+                    encrypted_file_hash: &hash,
+                    mime_type: "image/jpeg",
+                    media_type: "chat_media",
+                    blossom_url: None,
+                    nostr_key: None,
+                    file_metadata: None,
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        whitenoise.purge_account(&purged.pubkey).await.unwrap();
+
+        assert!(matches!(
+            Account::find_by_pubkey(&purged.pubkey, &whitenoise.database).await,
+            Err(WhitenoiseError::AccountNotFound)
+        ));
+        assert_eq!(
+            whitenoise.secrets_store.verify_private_key(&purged.pubkey),
+            SecretsStatus::Missing
+        );
+        for suffix in ["", "-wal", "-shm"] {
+            assert!(!mls_path(&purged, suffix).exists());
+        }
+        assert!(!own_file.exists());
+
+        Account::find_by_pubkey(&kept.pubkey, &whitenoise.database)
+            .await
+            .unwrap();
+        assert_eq!(
+            whitenoise.secrets_store.verify_private_key(&kept.pubkey),
+            SecretsStatus::Usable
+        );
+        assert!(mls_path(&kept, "").is_file());
+        assert!(shared_file.exists());
+        let kept_media = MediaFile::find_by_group(&whitenoise.database, &group_id)
+            .await
+            .unwrap();
+        assert_eq!(kept_media.len(), 1);
+        assert_eq!(kept_media[0].account_pubkey, kept.pubkey);
+
+        // Purging an unknown account fails without touching anything
+        assert!(matches!(
+            whitenoise.purge_account(&purged.pubkey).await,
+            Err(WhitenoiseError::AccountNotFound)
+        ));
+    }
+
     #[tokio::test]
     async fn test_load_accounts() {
         let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
@@ -1086,6 +1780,36 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_update_relays_only_publishes_changes() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+
+        // Give the initial relay lists time to be published
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+        let inbox_relays = account.inbox_relays(&whitenoise).await.unwrap();
+        let published = account
+            .update_relays(RelayType::Inbox, &inbox_relays, &whitenoise)
+            .await
+            .unwrap();
+        assert!(
+            !published,
+            "An unchanged relay list should not be republished"
+        );
+
+        let fewer = &inbox_relays[..1];
+        let published = account
+            .update_relays(RelayType::Inbox, fewer, &whitenoise)
+            .await
+            .unwrap();
+        assert!(published);
+        assert_eq!(
+            Relay::urls(&account.inbox_relays(&whitenoise).await.unwrap()),
+            Relay::urls(fewer)
+        );
+    }
+
     /// Helper function to verify that an account has all three relay lists properly configured
     async fn verify_account_relay_lists_setup(whitenoise: &Whitenoise, account: &Account) {
         // Verify all three relay lists are set up with default relays
@@ -1247,6 +1971,66 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_ncryptsec_export_and_login() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+
+        let ncryptsec = whitenoise
+            .export_account_ncryptsec(&account, "correct horse")
+            .await
+            .unwrap();
+        assert!(ncryptsec.starts_with("ncryptsec1"));
+        whitenoise.logout(&account.pubkey).await.unwrap();
+
+        assert!(matches!(
+            whitenoise.login_with_ncryptsec(&ncryptsec, "wrong").await,
+            Err(WhitenoiseError::WrongNcryptsecPassphrase)
+        ));
+        assert!(matches!(
+            whitenoise
+                .login_with_ncryptsec("ncryptsec1notakey", "correct horse")
+                .await,
+            Err(WhitenoiseError::InvalidNcryptsec(_))
+        ));
+
+        let restored = whitenoise
+            .login_with_ncryptsec(&ncryptsec, "correct horse")
+            .await
+            .unwrap();
+        assert_eq!(restored.pubkey, account.pubkey);
+    }
+
+    #[tokio::test]
+    async fn test_active_account_follows_switches_and_logout() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        assert!(whitenoise.active_account().await.unwrap().is_none());
+
+        let first = whitenoise.create_identity().await.unwrap();
+        let second = whitenoise.create_identity().await.unwrap();
+
+        whitenoise.set_active_account(&second.pubkey).await.unwrap();
+        let active = whitenoise.active_account().await.unwrap().unwrap();
+        assert_eq!(active.pubkey, second.pubkey);
+
+        // Unknown accounts can't be activated and leave the active account untouched
+        let unknown = Keys::generate().public_key();
+        assert!(matches!(
+            whitenoise.set_active_account(&unknown).await,
+            Err(WhitenoiseError::AccountNotFound)
+        ));
+        let active = whitenoise.active_account().await.unwrap().unwrap();
+        assert_eq!(active.pubkey, second.pubkey);
+
+        // Logging out the active account hands over to the remaining one
+        whitenoise.logout(&second.pubkey).await.unwrap();
+        let active = whitenoise.active_account().await.unwrap().unwrap();
+        assert_eq!(active.pubkey, first.pubkey);
+
+        whitenoise.logout(&first.pubkey).await.unwrap();
+        assert!(whitenoise.active_account().await.unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn test_multiple_accounts_each_have_proper_setup() {
         let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
@@ -1413,6 +2197,44 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_verify_secrets_and_reimport() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let (account, keys) = create_test_account(&whitenoise).await;
+        account.save(&whitenoise.database).await.unwrap();
+        whitenoise.secrets_store.store_private_key(&keys).unwrap();
+
+        assert_eq!(whitenoise.verify_secrets(&account), SecretsStatus::Usable);
+
+        // Simulate the key becoming inaccessible
+        whitenoise
+            .secrets_store
+            .remove_private_key_for_pubkey(&account.pubkey)
+            .unwrap();
+        assert_eq!(whitenoise.verify_secrets(&account), SecretsStatus::Missing);
+
+        // A key for another identity is rejected and doesn't repair the account
+        let other_keys = Keys::generate();
+        let result =
+            whitenoise.reimport_secret(&account, other_keys.secret_key().to_bech32().unwrap());
+        assert!(matches!(result, Err(WhitenoiseError::InvalidInput(_))));
+        assert_eq!(whitenoise.verify_secrets(&account), SecretsStatus::Missing);
+
+        whitenoise
+            .reimport_secret(&account, keys.secret_key().to_bech32().unwrap())
+            .unwrap();
+        assert_eq!(whitenoise.verify_secrets(&account), SecretsStatus::Usable);
+        let restored = whitenoise
+            .secrets_store
+            .get_nostr_keys_for_pubkey(&account.pubkey)
+            .unwrap();
+        assert!(
+            EventBuilder::text_note("signing works again")
+                .sign_with_keys(&restored)
+                .is_ok()
+        );
+    }
+
     #[tokio::test]
     async fn test_extract_groups_relays_and_ids_no_groups() {
         let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
@@ -1488,4 +2310,111 @@ mod tests {
             "Group ID should match the created group"
         );
     }
+
+    #[tokio::test]
+    async fn test_inbox_relay_change_moves_giftwrap_subscription() {
+        let (mut whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let (event_sender, mut events) = tokio::sync::mpsc::channel(1000);
+        whitenoise.nostr =
+            whitenoise
+                .nostr
+                .clone()
+                .with_network(Arc::new(MemoryNostrClient::with_event_sender(
+                    event_sender.into(),
+                )));
+        let account = whitenoise.create_identity().await.unwrap();
+
+        let kept_relay = RelayUrl::parse("ws://localhost:8080").unwrap();
+        let dropped_relay = RelayUrl::parse("ws://localhost:7777").unwrap();
+
+        let relay = whitenoise
+            .find_or_create_relay_by_url(&dropped_relay)
+            .await
+            .unwrap();
+        account
+            .remove_relay(&relay, RelayType::Inbox, &whitenoise)
+            .await
+            .unwrap();
+
+        let giftwrap_relays = whitenoise
+            .nostr
+            .giftwrap_subscription_relays(&account.pubkey)
+            .await;
+        assert!(
+            giftwrap_relays.contains(&kept_relay),
+            "Giftwrap subscription should remain on the kept inbox relay"
+        );
+        assert!(
+            !giftwrap_relays.contains(&dropped_relay),
+            "Giftwrap subscription should be removed from the dropped inbox relay"
+        );
+
+        // Self-test: a welcome giftwrapped to the account on the new inbox relays still arrives
+        let mut rumor = UnsignedEvent::new(
+            account.pubkey,
+            Timestamp::now(),
+            Kind::MlsWelcome,
+            vec![],
+            "self-test welcome".to_string(),
+        );
+        rumor.ensure_id();
+        let sender_keys = create_test_keys();
+        let giftwrap = EventBuilder::gift_wrap(&sender_keys, &account.pubkey, rumor, vec![])
+            .await
+            .unwrap();
+        whitenoise
+            .nostr
+            .network
+            .send_event_to(std::slice::from_ref(&kept_relay), &giftwrap)
+            .await
+            .unwrap();
+
+        let received = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while let Some(processable) = events.recv().await {
+                if let ProcessableEvent::NostrEvent {
+                    event,
+                    subscription_id: Some(subscription_id),
+                    ..
+                } = processable
+                    && event.id == giftwrap.id
+                    && subscription_id.ends_with("_giftwrap")
+                {
+                    return true;
+                }
+            }
+            false
+        })
+        .await
+        .unwrap_or(false);
+        assert!(
+            received,
+            "Welcome giftwrap should arrive through the moved subscription"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inbox_relay_change_without_resubscribe_keeps_giftwrap_subscription() {
+        let (mut whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        whitenoise.config.resubscribe_giftwrap_on_inbox_change = false;
+        let account = whitenoise.create_identity().await.unwrap();
+
+        let dropped_relay = RelayUrl::parse("ws://localhost:7777").unwrap();
+        let relay = whitenoise
+            .find_or_create_relay_by_url(&dropped_relay)
+            .await
+            .unwrap();
+        account
+            .remove_relay(&relay, RelayType::Inbox, &whitenoise)
+            .await
+            .unwrap();
+
+        let giftwrap_relays = whitenoise
+            .nostr
+            .giftwrap_subscription_relays(&account.pubkey)
+            .await;
+        assert!(
+            giftwrap_relays.contains(&dropped_relay),
+            "Giftwrap subscription should not move when re-subscription is disabled"
+        );
+    }
 }
@@ -1,9 +1,16 @@
 use std::{fmt, str::FromStr};
 
 use chrono::{DateTime, Utc};
+use mdk_core::prelude::GroupId;
 use serde::{Deserialize, Serialize};
 
-use crate::{Whitenoise, whitenoise::Result};
+use crate::{
+    Whitenoise,
+    whitenoise::{
+        Result, accounts::Account,
+        database::group_notification_settings::GroupNotificationSettings, error::WhitenoiseError,
+    },
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub enum ThemeMode {
@@ -41,6 +48,55 @@ impl FromStr for ThemeMode {
     }
 }
 
+/// Which new messages in a group raise a notification
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub enum NotificationLevel {
+    /// Every message from another member
+    #[default]
+    All,
+    /// Only messages that mention the account
+    MentionsOnly,
+    /// No messages
+    Muted,
+}
+
+impl fmt::Display for NotificationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotificationLevel::All => write!(f, "all"),
+            NotificationLevel::MentionsOnly => write!(f, "mentions_only"),
+            NotificationLevel::Muted => write!(f, "muted"),
+        }
+    }
+}
+
+impl FromStr for NotificationLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "all" => Ok(NotificationLevel::All),
+            "mentions_only" => Ok(NotificationLevel::MentionsOnly),
+            "muted" => Ok(NotificationLevel::Muted),
+            _ => Err(format!("Invalid notification level: {}", s)),
+        }
+    }
+}
+
+impl NotificationLevel {
+    /// Whether a new message from another member should raise a notification
+    ///
+    /// # Arguments
+    /// * `mentions_account` - Whether the message mentions the account
+    pub fn should_notify(&self, mentions_account: bool) -> bool {
+        match self {
+            NotificationLevel::All => true,
+            NotificationLevel::MentionsOnly => mentions_account,
+            NotificationLevel::Muted => false,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppSettings {
     pub id: i64,
@@ -94,6 +150,38 @@ impl Whitenoise {
     pub async fn update_theme_mode(&self, theme_mode: ThemeMode) -> Result<()> {
         AppSettings::update_theme_mode(theme_mode, &self.database).await
     }
+
+    /// Sets which new messages in a group raise a notification for the account.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account to set the level for
+    /// * `group_id` - The group to set the level for
+    /// * `level` - The new [`NotificationLevel`]
+    pub async fn set_group_notifications(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        level: NotificationLevel,
+    ) -> Result<()> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        GroupNotificationSettings::set(account_id, group_id, &level, &self.database).await
+    }
+
+    /// The account's notification level for a group, [`NotificationLevel::All`] unless changed
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account to look up
+    /// * `group_id` - The group to look up
+    pub async fn group_notifications(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+    ) -> Result<NotificationLevel> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        GroupNotificationSettings::find(account_id, group_id, &self.database).await
+    }
 }
 
 #[cfg(test)]
@@ -114,6 +202,25 @@ mod tests {
         assert!(ThemeMode::from_str("neon").is_err());
     }
 
+    #[test]
+    fn notification_level_round_trips_and_gates_mentions() {
+        for level in [
+            NotificationLevel::All,
+            NotificationLevel::MentionsOnly,
+            NotificationLevel::Muted,
+        ] {
+            assert_eq!(
+                NotificationLevel::from_str(&level.to_string()).unwrap(),
+                level
+            );
+        }
+
+        assert!(NotificationLevel::All.should_notify(false));
+        assert!(NotificationLevel::MentionsOnly.should_notify(true));
+        assert!(!NotificationLevel::MentionsOnly.should_notify(false));
+        assert!(!NotificationLevel::Muted.should_notify(true));
+    }
+
     #[test]
     fn app_settings_new_sets_id_and_theme() {
         let settings = AppSettings::new(ThemeMode::Dark);
@@ -0,0 +1,351 @@
+//! Encrypted backups of the account data
+//!
+//! A backup holds a snapshot of the database, the MLS storage of every account and the
+//! accounts' secret keys. It is encrypted with a random key, which is itself stored in the
+//! backup encrypted with the user's passphrase (NIP-49, scrypt), so a wrong passphrase is
+//! detected before anything is decrypted. Logs, the media cache and the Nostr event cache
+//! are not included; they are rebuilt or re-fetched after a restore.
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::Path;
+
+use base64::{Engine, engine::general_purpose};
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, KeyInit, Nonce,
+    aead::{Aead, Payload},
+};
+use nostr_sdk::nips::nip49::{self, EncryptedSecretKey, KeySecurity};
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use sqlx::{ConnectOptions, Connection, sqlite::SqliteConnectOptions};
+use tokio::io::AsyncReadExt;
+
+use crate::whitenoise::{
+    Whitenoise,
+    accounts::Account,
+    error::{Result, WhitenoiseError},
+};
+
+/// Current backup format version
+const BACKUP_VERSION: u32 = 1;
+
+/// scrypt cost of the passphrase key derivation, as recommended by NIP-49
+pub(crate) const PASSPHRASE_LOG_N: u8 = 16;
+
+/// First bytes of every SQLite database file
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// Backup file as written to disk
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupEnvelope {
+    version: u32,
+
+    /// Key the contents are encrypted with, encrypted with the passphrase (`ncryptsec`)
+    encrypted_key: String,
+
+    /// Base64 encoded ChaCha20-Poly1305 nonce
+    nonce: String,
+
+    /// Base64 encoded encrypted [`BackupContents`]
+    ciphertext: String,
+}
+
+/// Everything needed to restore the accounts, before encryption
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupContents {
+    created_at: Timestamp,
+
+    /// Base64 encoded snapshot of the database
+    database: String,
+
+    /// Base64 encoded files of the MLS storage directory, keyed by file name
+    mls_files: BTreeMap<String, String>,
+
+    /// Hex encoded secret keys of the backed up accounts
+    secret_keys: Vec<String>,
+}
+
+impl Whitenoise {
+    /// Writes an encrypted backup of all accounts to `dest`
+    ///
+    /// The backup holds a consistent snapshot of the database and of the MLS storage, and the
+    /// secret keys of all accounts. Databases are snapshotted through SQLite rather than
+    /// copied, so it is safe to back up while the app is running. Logs, the media cache and
+    /// the Nostr event cache are not included.
+    ///
+    /// # Arguments
+    /// * `dest` - File to write the backup to; replaced if it exists
+    /// * `passphrase` - Passphrase the backup is encrypted with
+    pub async fn export_backup(&self, dest: &Path, passphrase: &str) -> Result<()> {
+        if passphrase.is_empty() {
+            return Err(WhitenoiseError::InvalidInput(
+                "Backup passphrase must not be empty".to_string(),
+            ));
+        }
+
+        // Snapshots hold unencrypted data, so keep them next to the data they came from
+        let work_dir = tempfile::Builder::new()
+            .prefix(".backup")
+            .tempdir_in(&self.config.data_dir)?;
+
+        let database_snapshot = work_dir.path().join("database.sqlite");
+        self.database.snapshot_into(&database_snapshot).await?;
+
+        let mut mls_files = BTreeMap::new();
+        let mls_dir = self.config.data_dir.join("mls");
+        if mls_dir.exists() {
+            for entry in std::fs::read_dir(&mls_dir)? {
+                let path = entry?.path();
+                let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                // Write-ahead logs are folded into the snapshot of their database
+                if !path.is_file() || name.ends_with("-wal") || name.ends_with("-shm") {
+                    continue;
+                }
+                let snapshot = work_dir.path().join(name);
+                snapshot_file(&path, &snapshot).await?;
+                mls_files.insert(name.to_string(), encode(&tokio::fs::read(&snapshot).await?));
+            }
+        }
+
+        let mut secret_keys = Vec::new();
+        for account in Account::all(&self.database).await? {
+            let keys = self
+                .secrets_store
+                .get_nostr_keys_for_pubkey(&account.pubkey)?;
+            secret_keys.push(keys.secret_key().to_secret_hex());
+        }
+
+        let contents = BackupContents {
+            created_at: Timestamp::now(),
+            database: encode(&tokio::fs::read(&database_snapshot).await?),
+            mls_files,
+            secret_keys,
+        };
+        let envelope = encrypt_contents(&contents, passphrase)?;
+
+        let tmp_dest = dest.with_extension("tmp");
+        tokio::fs::write(&tmp_dest, serde_json::to_vec(&envelope)?).await?;
+        tokio::fs::rename(&tmp_dest, dest).await?;
+
+        tracing::info!(
+            target: "whitenoise::backup::export_backup",
+            "Exported backup of {} account(s) to {:?}",
+            contents.secret_keys.len(),
+            dest
+        );
+        Ok(())
+    }
+
+    /// Restores accounts from a backup written by [`Self::export_backup`]
+    ///
+    /// The backup is decrypted and checked before anything is changed. Event processing is
+    /// then shut down, the secret keys are stored, and the MLS storage and database contents
+    /// are replaced with those from the backup. Restart the app afterwards to load the
+    /// restored accounts.
+    ///
+    /// # Arguments
+    /// * `src` - Backup file to restore from
+    /// * `passphrase` - Passphrase the backup was encrypted with
+    pub async fn import_backup(&self, src: &Path, passphrase: &str) -> Result<()> {
+        let envelope: BackupEnvelope = serde_json::from_slice(&tokio::fs::read(src).await?)
+            .map_err(|e| WhitenoiseError::InvalidBackup(e.to_string()))?;
+        let contents = decrypt_contents(&envelope, passphrase)?;
+
+        let keys = contents
+            .secret_keys
+            .iter()
+            .map(|secret| Keys::parse(secret))
+            .collect::<std::result::Result<Vec<Keys>, _>>()?;
+        let database = decode(&contents.database)?;
+        let mls_files = contents
+            .mls_files
+            .iter()
+            .map(|(name, data)| {
+                if Path::new(name).file_name() != Some(OsStr::new(name)) {
+                    return Err(WhitenoiseError::InvalidBackup(format!(
+                        "Invalid MLS file name: {name}"
+                    )));
+                }
+                Ok((name.as_str(), decode(data)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.shutdown().await?;
+
+        for keys in &keys {
+            self.secrets_store.store_private_key(keys)?;
+        }
+
+        // Build the new MLS directory next to the old one and swap it in
+        let work_dir = tempfile::Builder::new()
+            .prefix(".restore")
+            .tempdir_in(&self.config.data_dir)?;
+        let restored_mls_dir = work_dir.path().join("mls");
+        tokio::fs::create_dir_all(&restored_mls_dir).await?;
+        for (name, data) in &mls_files {
+            tokio::fs::write(restored_mls_dir.join(name), data).await?;
+        }
+        let mls_dir = self.config.data_dir.join("mls");
+        if mls_dir.exists() {
+            tokio::fs::remove_dir_all(&mls_dir).await?;
+        }
+        tokio::fs::rename(&restored_mls_dir, &mls_dir).await?;
+
+        let database_snapshot = work_dir.path().join("database.sqlite");
+        tokio::fs::write(&database_snapshot, database).await?;
+        self.database.restore_from(&database_snapshot).await?;
+
+        tracing::info!(
+            target: "whitenoise::backup::import_backup",
+            "Restored backup of {} account(s) created at {}",
+            keys.len(),
+            contents.created_at
+        );
+        Ok(())
+    }
+}
+
+/// Copy a file, taking a consistent snapshot if it is a SQLite database
+async fn snapshot_file(src: &Path, dest: &Path) -> Result<()> {
+    let mut header = [0u8; SQLITE_HEADER.len()];
+    let mut file = tokio::fs::File::open(src).await?;
+    let is_sqlite = file.read_exact(&mut header).await.is_ok() && header == SQLITE_HEADER;
+    drop(file);
+    if !is_sqlite {
+        tokio::fs::copy(src, dest).await?;
+        return Ok(());
+    }
+
+    let mut conn = SqliteConnectOptions::new()
+        .filename(src)
+        .read_only(true)
+        .connect()
+        .await?;
+    sqlx::query("VACUUM INTO ?")
+        .bind(dest.to_string_lossy().into_owned())
+        .execute(&mut conn)
+        .await?;
+    conn.close().await?;
+    Ok(())
+}
+
+fn encrypt_contents(contents: &BackupContents, passphrase: &str) -> Result<BackupEnvelope> {
+    let data_key = SecretKey::generate();
+    let encrypted_key =
+        EncryptedSecretKey::new(&data_key, passphrase, PASSPHRASE_LOG_N, KeySecurity::Medium)
+            .map_err(|e| WhitenoiseError::Other(e.into()))?
+            .to_bech32()
+            .map_err(|e| WhitenoiseError::Other(e.into()))?;
+
+    let nonce_bytes: [u8; 12] = rand::random();
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&data_key.to_secret_bytes()));
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce_bytes),
+            Payload {
+                msg: &serde_json::to_vec(contents)?,
+                aad: &BACKUP_VERSION.to_be_bytes(),
+            },
+        )
+        .map_err(|_| WhitenoiseError::Other(anyhow::anyhow!("Failed to encrypt backup")))?;
+
+    Ok(BackupEnvelope {
+        version: BACKUP_VERSION,
+        encrypted_key,
+        nonce: encode(&nonce_bytes),
+        ciphertext: encode(&ciphertext),
+    })
+}
+
+fn decrypt_contents(envelope: &BackupEnvelope, passphrase: &str) -> Result<BackupContents> {
+    if envelope.version > BACKUP_VERSION {
+        return Err(WhitenoiseError::InvalidBackup(format!(
+            "Unsupported backup version {}",
+            envelope.version
+        )));
+    }
+
+    let data_key = EncryptedSecretKey::from_bech32(&envelope.encrypted_key)
+        .map_err(|e| WhitenoiseError::InvalidBackup(e.to_string()))?
+        .decrypt(passphrase)
+        .map_err(|e| match e {
+            nip49::Error::ChaCha20Poly1305(_) => WhitenoiseError::WrongBackupPassphrase,
+            e => WhitenoiseError::InvalidBackup(e.to_string()),
+        })?;
+
+    let nonce = decode(&envelope.nonce)?;
+    if nonce.len() != 12 {
+        return Err(WhitenoiseError::InvalidBackup(
+            "Invalid nonce length".to_string(),
+        ));
+    }
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&data_key.to_secret_bytes()));
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &decode(&envelope.ciphertext)?,
+                aad: &envelope.version.to_be_bytes(),
+            },
+        )
+        .map_err(|_| WhitenoiseError::InvalidBackup("Backup contents are corrupted".to_string()))?;
+
+    serde_json::from_slice(&plaintext).map_err(|e| WhitenoiseError::InvalidBackup(e.to_string()))
+}
+
+fn encode(data: &[u8]) -> String {
+    general_purpose::STANDARD.encode(data)
+}
+
+fn decode(data: &str) -> Result<Vec<u8>> {
+    general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| WhitenoiseError::InvalidBackup(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::*;
+
+    #[tokio::test]
+    async fn test_export_and_import_backup() {
+        let (whitenoise, data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let backup_path = data_temp.path().join("whitenoise.backup");
+
+        whitenoise
+            .export_backup(&backup_path, "correct horse")
+            .await
+            .unwrap();
+
+        // An account created after the backup is gone once it's restored
+        let later_account = whitenoise.create_identity().await.unwrap();
+
+        let result = whitenoise.import_backup(&backup_path, "wrong").await;
+        assert!(matches!(
+            result,
+            Err(WhitenoiseError::WrongBackupPassphrase)
+        ));
+        assert_eq!(Account::all(&whitenoise.database).await.unwrap().len(), 2);
+
+        whitenoise
+            .import_backup(&backup_path, "correct horse")
+            .await
+            .unwrap();
+
+        let accounts = Account::all(&whitenoise.database).await.unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].pubkey, account.pubkey);
+        assert_ne!(accounts[0].pubkey, later_account.pubkey);
+        assert!(
+            whitenoise
+                .secrets_store
+                .get_nostr_keys_for_pubkey(&account.pubkey)
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! Uploads to Blossom servers
+//!
+//! Every upload is authorized with a BUD-02 auth event scoped to the hash of the blob being
+//! uploaded and valid only for [`WhitenoiseConfig::blossom_auth_expiry`], which stricter
+//! servers require. Servers refusing the authorization are reported as
+//! [`BlossomError::Unauthorized`], apart from servers that can't be reached.
+//!
+//! [`WhitenoiseConfig::blossom_auth_expiry`]: crate::WhitenoiseConfig::blossom_auth_expiry
+
+use std::time::Duration;
+
+use nostr_blossom::bud01::{
+    BlossomAuthorizationOptions, BlossomAuthorizationScope, BlossomAuthorizationVerb,
+};
+use nostr_blossom::bud02::BlobDescriptor;
+use nostr_blossom::client::BlossomClient;
+use nostr_sdk::hashes::{Hash, sha256::Hash as Sha256Hash};
+use nostr_sdk::prelude::*;
+use thiserror::Error;
+
+/// How long an upload may take before it is abandoned
+const BLOSSOM_UPLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How the Blossom client reports the HTTP statuses servers answer with when they refuse an
+/// authorization
+const UNAUTHORIZED_STATUSES: [&str; 2] = ["status 401", "status 403"];
+
+#[derive(Error, Debug)]
+pub enum BlossomError {
+    #[error("Blossom server {server} refused the upload authorization: {reason}")]
+    Unauthorized { server: Url, reason: String },
+
+    #[error("Upload to Blossom server {server} timed out after {} seconds", timeout.as_secs())]
+    Timeout { server: Url, timeout: Duration },
+
+    #[error("Upload to Blossom server {server} failed: {reason}")]
+    Network { server: Url, reason: String },
+}
+
+impl BlossomError {
+    /// Classify an error returned by the Blossom client
+    ///
+    /// The client reports HTTP failures as text including "status <code>", so refusals are
+    /// recognized by their status. Other numbers in the text, e.g. in the server's URL, don't
+    /// count.
+    fn from_client_error(server: &Url, err: impl std::fmt::Display) -> Self {
+        let reason = err.to_string();
+        let has_status = |status: &str| {
+            reason.match_indices(status).any(|(start, _)| {
+                !reason[start + status.len()..].starts_with(|c: char| c.is_ascii_digit())
+            })
+        };
+        if UNAUTHORIZED_STATUSES
+            .iter()
+            .any(|status| has_status(status))
+        {
+            BlossomError::Unauthorized {
+                server: server.clone(),
+                reason,
+            }
+        } else {
+            BlossomError::Network {
+                server: server.clone(),
+                reason,
+            }
+        }
+    }
+}
+
+/// Upload authorization valid for `expiry` and only for the blob with `blob_hash`
+fn upload_authorization(blob_hash: &[u8; 32], expiry: Duration) -> BlossomAuthorizationOptions {
+    BlossomAuthorizationOptions {
+        content: Some("Upload blob".to_string()),
+        expiration: Some(Timestamp::now() + expiry),
+        action: Some(BlossomAuthorizationVerb::Upload),
+        scope: Some(BlossomAuthorizationScope::BlobSHA256(
+            Sha256Hash::from_byte_array(*blob_hash),
+        )),
+    }
+}
+
+/// Uploads a blob to a Blossom server, signing the authorization with `signer`
+///
+/// # Arguments
+/// * `server` - Blossom server to upload to
+/// * `data` - The blob
+/// * `blob_hash` - SHA-256 of `data`, which the authorization is scoped to
+/// * `mime_type` - MIME type sent as the blob's content type
+/// * `signer` - Signs the authorization
+/// * `auth_expiry` - How long the authorization stays valid
+pub(crate) async fn upload_blob<T>(
+    server: &Url,
+    data: Vec<u8>,
+    blob_hash: &[u8; 32],
+    mime_type: &str,
+    signer: &T,
+    auth_expiry: Duration,
+) -> Result<BlobDescriptor, BlossomError>
+where
+    T: NostrSigner,
+{
+    let client = BlossomClient::new(server.clone());
+    let upload = client.upload_blob(
+        data,
+        Some(mime_type.to_string()),
+        Some(upload_authorization(blob_hash, auth_expiry)),
+        Some(signer),
+    );
+
+    tokio::time::timeout(BLOSSOM_UPLOAD_TIMEOUT, upload)
+        .await
+        .map_err(|_| BlossomError::Timeout {
+            server: server.clone(),
+            timeout: BLOSSOM_UPLOAD_TIMEOUT,
+        })?
+        .map_err(|err| BlossomError::from_client_error(server, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upload_authorization_is_scoped_and_short_lived() {
+        let blob_hash = [7u8; 32];
+        let before = Timestamp::now();
+        let auth = upload_authorization(&blob_hash, Duration::from_secs(60));
+
+        assert!(matches!(
+            auth.action,
+            Some(BlossomAuthorizationVerb::Upload)
+        ));
+        assert!(matches!(
+            auth.scope,
+            Some(BlossomAuthorizationScope::BlobSHA256(hash))
+                if hash.to_byte_array() == blob_hash
+        ));
+        let expiration = auth.expiration.unwrap();
+        assert!(expiration >= before + Duration::from_secs(60));
+        assert!(expiration <= Timestamp::now() + Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_refused_authorization_is_distinct_from_network_errors() {
+        let server = Url::parse("https://blossom.example.com").unwrap();
+
+        let refused = BlossomError::from_client_error(&server, "Upload failed with status 401");
+        assert!(matches!(refused, BlossomError::Unauthorized { .. }));
+        let forbidden = BlossomError::from_client_error(&server, "Upload failed with status 403");
+        assert!(matches!(forbidden, BlossomError::Unauthorized { .. }));
+
+        let unreachable = BlossomError::from_client_error(&server, "error sending request");
+        assert!(matches!(unreachable, BlossomError::Network { .. }));
+
+        // Status-like numbers elsewhere in the text aren't refusals
+        let server_error = BlossomError::from_client_error(
+            &server,
+            "Upload to http://blossom.example.com:4010/403 failed with status 500",
+        );
+        assert!(matches!(server_error, BlossomError::Network { .. }));
+        let other_status = BlossomError::from_client_error(&server, "failed with status 4031");
+        assert!(matches!(other_status, BlossomError::Network { .. }));
+    }
+}
@@ -0,0 +1,607 @@
+use std::collections::{HashMap, HashSet};
+
+use mdk_core::prelude::*;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::nostr_manager::parser::SerializableToken;
+use crate::whitenoise::{
+    Whitenoise,
+    accounts::Account,
+    aggregated_message::AggregatedMessage,
+    database::{
+        direct_messages::DirectMessages, group_read_state::GroupReadState,
+        pinned_conversations::PinnedConversations,
+    },
+    error::{Result, WhitenoiseError},
+    group_information::{GroupInformation, GroupType},
+    message_aggregator::ChatMessage,
+    users::{User, profile_name},
+    utils::preview_text,
+};
+
+/// Picture to show next to a chat in the chat list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChatAvatar {
+    /// The group's encrypted image, resolved with [`Whitenoise::get_group_image_path`]
+    GroupImage { image_hash: [u8; 32] },
+
+    /// Picture URL from the other member's profile metadata
+    Picture(String),
+}
+
+/// Everything the chat list needs to render one group or direct message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatListItem {
+    /// The group this item belongs to (`None` for NIP-17 direct messages, which have no group)
+    pub mls_group_id: Option<GroupId>,
+
+    /// Whether this is a group or a direct message
+    pub group_type: GroupType,
+
+    /// Group name, or the other member's name for direct messages (`None` if their
+    /// metadata isn't cached yet)
+    pub title: Option<String>,
+
+    /// The other member of a direct message, so their metadata can be fetched lazily
+    pub other_member: Option<PublicKey>,
+
+    /// Group image or the other member's profile picture
+    pub avatar: Option<ChatAvatar>,
+
+    /// Start of the newest message's content as plain text, see
+    /// [`SerializableToken::to_preview_string`] (`None` if the chat has no messages yet)
+    pub last_message_preview: Option<String>,
+
+    /// Author of the newest message
+    pub last_message_author: Option<PublicKey>,
+
+    /// When the newest message was sent
+    pub last_message_at: Option<Timestamp>,
+
+    /// Number of messages from other members the account hasn't read yet
+    pub unread_count: u64,
+
+    /// Whether the account pinned this chat to the top of the list
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// Which conversations [`Whitenoise::list_conversations`] returns
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConversationFilter {
+    /// Only conversations of this type (`None` for both groups and direct messages)
+    pub group_type: Option<GroupType>,
+
+    /// Only conversations with messages the account hasn't read
+    pub unread_only: bool,
+
+    /// Only conversations with a message sent within this long ago
+    pub active_within: Option<std::time::Duration>,
+}
+
+/// An MLS group or direct message of an account, with what's needed to sort and badge it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Conversation {
+    pub group_information: GroupInformation,
+
+    /// When the newest message was sent (`None` if there are no messages yet)
+    pub last_activity_at: Option<Timestamp>,
+
+    /// Number of messages from other members the account hasn't read yet
+    pub unread_count: u64,
+
+    /// Whether the account pinned this conversation to the top of the list
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl Whitenoise {
+    /// Fetch the data for every entry of the account's chat list
+    ///
+    /// Covers all active groups and direct messages, including NIP-17 conversations. Pinned
+    /// chats come first in pin order, then the rest most recently active first; chats without
+    /// messages come last. Last messages, unread counts and member metadata are each loaded
+    /// for all chats with a single database query.
+    ///
+    /// # Arguments
+    /// * `account` - The account whose chat list to build
+    pub async fn fetch_chat_list_previews(&self, account: &Account) -> Result<Vec<ChatListItem>> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        let groups = self.groups(account, true).await?;
+        let group_ids: Vec<GroupId> = groups.iter().map(|g| g.mls_group_id.clone()).collect();
+
+        let group_types: HashMap<GroupId, GroupType> =
+            GroupInformation::get_by_mls_group_ids(account.pubkey, &group_ids, self)
+                .await?
+                .into_iter()
+                .map(|info| (info.mls_group_id, info.group_type))
+                .collect();
+
+        let mut last_messages: HashMap<GroupId, _> =
+            AggregatedMessage::find_last_messages_by_groups(&group_ids, &self.database)
+                .await?
+                .into_iter()
+                .collect();
+
+        let mut unread_counts =
+            GroupReadState::unread_counts(account_id, &account.pubkey, &group_ids, &self.database)
+                .await?;
+
+        // The other member of each direct message
+        let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
+        let mut other_members: HashMap<GroupId, PublicKey> = HashMap::new();
+        for group_id in &group_ids {
+            if group_types.get(group_id) == Some(&GroupType::DirectMessage)
+                && let Some(other) = mdk
+                    .get_members(group_id)?
+                    .into_iter()
+                    .find(|member| *member != account.pubkey)
+            {
+                other_members.insert(group_id.clone(), other);
+            }
+        }
+
+        let private_messages =
+            DirectMessages::find_last_messages(account_id, &self.database).await?;
+
+        let pins: HashMap<GroupId, usize> = PinnedConversations::find(account_id, &self.database)
+            .await?
+            .into_iter()
+            .enumerate()
+            .map(|(position, group_id)| (group_id, position))
+            .collect();
+
+        let pubkeys: Vec<PublicKey> = other_members
+            .values()
+            .copied()
+            .chain(private_messages.iter().map(|message| message.other_pubkey))
+            .chain(
+                last_messages
+                    .values()
+                    .flat_map(|message| message.mentions.iter().map(|mention| mention.pubkey)),
+            )
+            .collect();
+        let metadata: HashMap<PublicKey, Metadata> =
+            User::find_by_pubkeys(&pubkeys, &self.database)
+                .await?
+                .into_iter()
+                .map(|user| (user.pubkey, user.metadata))
+                .collect();
+        let names: HashMap<PublicKey, String> = metadata
+            .iter()
+            .filter_map(|(pubkey, metadata)| Some((*pubkey, profile_name(metadata)?.clone())))
+            .collect();
+
+        let mut items: Vec<ChatListItem> = groups
+            .into_iter()
+            .map(|group| {
+                let group_type = group_types
+                    .get(&group.mls_group_id)
+                    .cloned()
+                    .unwrap_or_default();
+                let other_member = other_members.get(&group.mls_group_id).copied();
+                let other_metadata = other_member.and_then(|pubkey| metadata.get(&pubkey));
+
+                let (title, avatar) = match group_type {
+                    GroupType::DirectMessage => direct_message_title_and_avatar(other_metadata),
+                    GroupType::Group => (
+                        Some(group.name.clone()),
+                        group
+                            .image_hash
+                            .map(|image_hash| ChatAvatar::GroupImage { image_hash }),
+                    ),
+                };
+
+                let last_message = last_messages.remove(&group.mls_group_id);
+                ChatListItem {
+                    pinned: pins.contains_key(&group.mls_group_id),
+                    unread_count: unread_counts
+                        .remove(&group.mls_group_id)
+                        .unwrap_or_default(),
+                    mls_group_id: Some(group.mls_group_id),
+                    group_type,
+                    title,
+                    other_member,
+                    avatar,
+                    last_message_preview: last_message
+                        .as_ref()
+                        .map(|message| message_preview(message, &names)),
+                    last_message_author: last_message.as_ref().map(|message| message.author),
+                    last_message_at: last_message.map(|message| message.created_at),
+                }
+            })
+            .collect();
+
+        items.extend(private_messages.into_iter().map(|message| {
+            let (title, avatar) =
+                direct_message_title_and_avatar(metadata.get(&message.other_pubkey));
+            ChatListItem {
+                mls_group_id: None,
+                group_type: GroupType::DirectMessage,
+                title,
+                other_member: Some(message.other_pubkey),
+                avatar,
+                last_message_preview: Some(SerializableToken::to_preview_string(
+                    &self.nostr.parse(&message.content),
+                    &names,
+                )),
+                last_message_author: Some(message.author),
+                last_message_at: Some(message.created_at),
+                unread_count: 0,
+                pinned: false,
+            }
+        }));
+
+        let pin_position = |item: &ChatListItem| {
+            item.mls_group_id
+                .as_ref()
+                .and_then(|group_id| pins.get(group_id))
+                .copied()
+                .unwrap_or(usize::MAX)
+        };
+        items.sort_by(|a, b| {
+            pin_position(a)
+                .cmp(&pin_position(b))
+                .then_with(|| b.last_message_at.cmp(&a.last_message_at))
+        });
+        Ok(items)
+    }
+
+    /// List the account's conversations, pinned ones first, then most recently active first
+    ///
+    /// Covers the active MLS groups of the account; NIP-17 direct messages have no group and
+    /// are only part of [`Whitenoise::fetch_chat_list_previews`]. Type, activity and unread
+    /// counts are filtered in a single database query. Conversations without messages come last.
+    ///
+    /// # Arguments
+    /// * `account` - The account whose conversations to list
+    /// * `filter` - Which conversations to include
+    pub async fn list_conversations(
+        &self,
+        account: &Account,
+        filter: ConversationFilter,
+    ) -> Result<Vec<Conversation>> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        let group_ids: Vec<GroupId> = self
+            .groups(account, true)
+            .await?
+            .into_iter()
+            .map(|group| group.mls_group_id)
+            .collect();
+
+        GroupInformation::find_conversations(
+            account_id,
+            &account.pubkey,
+            &group_ids,
+            &filter,
+            &self.database,
+        )
+        .await
+    }
+
+    /// Pin a group to the top of the account's chat list
+    ///
+    /// `position` is the 0-based rank among the pinned chats; pins at or after it move down
+    /// one place, and positions past the end pin the group last. Pinning an already pinned
+    /// group moves it. Pins are kept on this device only.
+    ///
+    /// # Arguments
+    /// * `account` - The account pinning the group
+    /// * `group_id` - The group to pin
+    /// * `position` - Where among the pinned chats to put it
+    ///
+    /// # Errors
+    /// Returns [`WhitenoiseError::GroupNotFound`] if the account doesn't know the group.
+    pub async fn pin_conversation(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        position: usize,
+    ) -> Result<()> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
+        mdk.get_group(group_id)?
+            .ok_or(WhitenoiseError::GroupNotFound)?;
+        PinnedConversations::pin(account_id, group_id, position, &self.database).await?;
+        Ok(())
+    }
+
+    /// Unpin a group from the account's chat list; does nothing if it isn't pinned
+    ///
+    /// # Arguments
+    /// * `account` - The account unpinning the group
+    /// * `group_id` - The group to unpin
+    pub async fn unpin_conversation(&self, account: &Account, group_id: &GroupId) -> Result<()> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        PinnedConversations::unpin(account_id, group_id, &self.database).await?;
+        Ok(())
+    }
+
+    /// The active groups the account pinned, first pin first
+    ///
+    /// Pins of groups the account was removed from are kept in case it is added back, but
+    /// aren't listed.
+    ///
+    /// # Arguments
+    /// * `account` - The account whose pins to list
+    pub async fn pinned_conversations(&self, account: &Account) -> Result<Vec<GroupId>> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        let active: HashSet<GroupId> = self
+            .groups(account, true)
+            .await?
+            .into_iter()
+            .map(|group| group.mls_group_id)
+            .collect();
+        Ok(PinnedConversations::find(account_id, &self.database)
+            .await?
+            .into_iter()
+            .filter(|group_id| active.contains(group_id))
+            .collect())
+    }
+}
+
+/// Plain-text preview of a cached message, from its tokens when it has been parsed
+fn message_preview(message: &ChatMessage, names: &HashMap<PublicKey, String>) -> String {
+    if message.content_tokens.is_empty() {
+        preview_text(&message.content)
+    } else {
+        SerializableToken::to_preview_string(&message.content_tokens, names)
+    }
+}
+
+/// Title and avatar of a direct message, taken from the other member's metadata
+fn direct_message_title_and_avatar(
+    metadata: Option<&Metadata>,
+) -> (Option<String>, Option<ChatAvatar>) {
+    (
+        metadata.and_then(|m| m.display_name.clone().or(m.name.clone())),
+        metadata
+            .and_then(|m| m.picture.clone())
+            .map(ChatAvatar::Picture),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::message_aggregator::ChatMessage;
+    use crate::whitenoise::test_utils::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_fetch_chat_list_previews() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 2).await;
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let group = whitenoise
+            .create_group(
+                &creator_account,
+                vec![members[0].0.pubkey],
+                config,
+                Some(GroupType::Group),
+            )
+            .await
+            .unwrap();
+
+        let dm_config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let dm = whitenoise
+            .create_group(
+                &creator_account,
+                vec![members[1].0.pubkey],
+                dm_config,
+                Some(GroupType::DirectMessage),
+            )
+            .await
+            .unwrap();
+
+        let message = ChatMessage {
+            id: format!("{:0>64}", "a1"),
+            author: members[0].0.pubkey,
+            content: "hello from the group".to_string(),
+            created_at: Timestamp::now(),
+            tags: Tags::new(),
+            is_reply: false,
+            reply_to_id: None,
+            reply_to: None,
+            quoted: None,
+            is_deleted: false,
+            edited_at: None,
+            is_muted: false,
+            content_tokens: vec![],
+            mentions: vec![],
+            reactions: Default::default(),
+            kind: 9,
+            media_attachments: vec![],
+            delivery_status: Default::default(),
+        };
+        AggregatedMessage::insert_message(&message, &group.mls_group_id, &whitenoise.database)
+            .await
+            .unwrap();
+
+        let items = whitenoise
+            .fetch_chat_list_previews(&creator_account)
+            .await
+            .unwrap();
+        assert_eq!(items.len(), 2);
+
+        // The group with a message sorts first
+        let group_item = &items[0];
+        assert_eq!(group_item.mls_group_id, Some(group.mls_group_id));
+        assert_eq!(group_item.title.as_deref(), Some(group.name.as_str()));
+        assert_eq!(
+            group_item.last_message_preview.as_deref(),
+            Some("hello from the group")
+        );
+        assert_eq!(group_item.last_message_author, Some(members[0].0.pubkey));
+        assert_eq!(group_item.unread_count, 1);
+
+        // The DM has no messages yet but still identifies the other member
+        let dm_item = &items[1];
+        assert_eq!(dm_item.mls_group_id, Some(dm.mls_group_id));
+        assert_eq!(dm_item.group_type, GroupType::DirectMessage);
+        assert_eq!(dm_item.other_member, Some(members[1].0.pubkey));
+        assert_eq!(dm_item.last_message_preview, None);
+        assert_eq!(dm_item.unread_count, 0);
+
+        // NIP-17 conversations are listed without a group
+        let alice = Keys::generate().public_key();
+        let mut rumor = EventBuilder::private_msg_rumor(
+            creator_account.pubkey,
+            "psst\nhttps://example.com/cat.jpg",
+        )
+        .build(alice);
+        rumor.created_at = Timestamp::now() + Duration::from_secs(60);
+        rumor.ensure_id();
+        DirectMessages::insert(
+            creator_account.id.unwrap(),
+            &alice,
+            &rumor,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        let items = whitenoise
+            .fetch_chat_list_previews(&creator_account)
+            .await
+            .unwrap();
+        assert_eq!(items.len(), 3);
+        let private_item = &items[0];
+        assert_eq!(private_item.mls_group_id, None);
+        assert_eq!(private_item.group_type, GroupType::DirectMessage);
+        assert_eq!(private_item.other_member, Some(alice));
+        // Rendered like group messages, not shown raw
+        assert_eq!(
+            private_item.last_message_preview.as_deref(),
+            Some("psst 📷 Photo")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pinned_conversations_sort_ahead_of_recency() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let mut groups = Vec::new();
+        for _ in 0..3 {
+            let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+            let group = whitenoise
+                .create_group(
+                    &creator_account,
+                    vec![members[0].0.pubkey],
+                    config,
+                    Some(GroupType::Group),
+                )
+                .await
+                .unwrap();
+            groups.push(group.mls_group_id);
+        }
+
+        // Only the first group has a message, so it is the most recent
+        let message = ChatMessage {
+            id: format!("{:0>64}", "b1"),
+            author: members[0].0.pubkey,
+            content: "recent".to_string(),
+            created_at: Timestamp::now(),
+            tags: Tags::new(),
+            is_reply: false,
+            reply_to_id: None,
+            reply_to: None,
+            quoted: None,
+            is_deleted: false,
+            edited_at: None,
+            is_muted: false,
+            content_tokens: vec![],
+            mentions: vec![],
+            reactions: Default::default(),
+            kind: 9,
+            media_attachments: vec![],
+            delivery_status: Default::default(),
+        };
+        AggregatedMessage::insert_message(&message, &groups[0], &whitenoise.database)
+            .await
+            .unwrap();
+
+        whitenoise
+            .pin_conversation(&creator_account, &groups[2], 0)
+            .await
+            .unwrap();
+        whitenoise
+            .pin_conversation(&creator_account, &groups[1], 0)
+            .await
+            .unwrap();
+        assert_eq!(
+            whitenoise
+                .pinned_conversations(&creator_account)
+                .await
+                .unwrap(),
+            vec![groups[1].clone(), groups[2].clone()]
+        );
+
+        let items = whitenoise
+            .fetch_chat_list_previews(&creator_account)
+            .await
+            .unwrap();
+        let order: Vec<_> = items
+            .iter()
+            .map(|item| (item.mls_group_id.clone().unwrap(), item.pinned))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                (groups[1].clone(), true),
+                (groups[2].clone(), true),
+                (groups[0].clone(), false),
+            ]
+        );
+
+        // Moving a pin reorders the others
+        whitenoise
+            .pin_conversation(&creator_account, &groups[1], 5)
+            .await
+            .unwrap();
+        let conversations = whitenoise
+            .list_conversations(&creator_account, ConversationFilter::default())
+            .await
+            .unwrap();
+        let order: Vec<_> = conversations
+            .iter()
+            .map(|c| (c.group_information.mls_group_id.clone(), c.pinned))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                (groups[2].clone(), true),
+                (groups[1].clone(), true),
+                (groups[0].clone(), false),
+            ]
+        );
+
+        whitenoise
+            .unpin_conversation(&creator_account, &groups[2])
+            .await
+            .unwrap();
+        assert_eq!(
+            whitenoise
+                .pinned_conversations(&creator_account)
+                .await
+                .unwrap(),
+            vec![groups[1].clone()]
+        );
+
+        let unknown = GroupId::from_slice(&[9; 32]);
+        assert!(matches!(
+            whitenoise
+                .pin_conversation(&creator_account, &unknown, 0)
+                .await,
+            Err(WhitenoiseError::GroupNotFound)
+        ));
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+
+use nostr_sdk::prelude::*;
+
+use super::{Database, DatabaseError};
+
+type Result<T> = std::result::Result<T, DatabaseError>;
+
+/// An entry of an account's NIP-51 mute list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MutedPubkey {
+    pub pubkey: PublicKey,
+
+    /// Whether the entry is kept in the encrypted part of the mute list
+    pub private: bool,
+}
+
+/// Mute list entries per account
+pub(crate) struct AccountMutes;
+
+impl AccountMutes {
+    /// Add a pubkey to the account's mute list, returning whether it wasn't muted yet
+    ///
+    /// An existing entry keeps its visibility.
+    pub(crate) async fn add(
+        account_id: i64,
+        pubkey: &PublicKey,
+        private: bool,
+        database: &Database,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO account_mutes (account_id, pubkey, private)
+             VALUES (?, ?, ?)
+             ON CONFLICT(account_id, pubkey) DO NOTHING",
+        )
+        .bind(account_id)
+        .bind(pubkey.to_hex())
+        .bind(private)
+        .execute(&database.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remove a pubkey from the account's mute list, returning whether it was muted
+    pub(crate) async fn remove(
+        account_id: i64,
+        pubkey: &PublicKey,
+        database: &Database,
+    ) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM account_mutes WHERE account_id = ? AND pubkey = ?")
+            .bind(account_id)
+            .bind(pubkey.to_hex())
+            .execute(&database.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// All entries of the account's mute list, oldest first
+    pub(crate) async fn find_by_account(
+        account_id: i64,
+        database: &Database,
+    ) -> Result<Vec<MutedPubkey>> {
+        let rows: Vec<(String, bool)> = sqlx::query_as(
+            "SELECT pubkey, private FROM account_mutes WHERE account_id = ? ORDER BY id",
+        )
+        .bind(account_id)
+        .fetch_all(&database.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(pubkey, private)| {
+                PublicKey::parse(&pubkey)
+                    .ok()
+                    .map(|pubkey| MutedPubkey { pubkey, private })
+            })
+            .collect())
+    }
+
+    /// The muted pubkeys of the account, regardless of visibility
+    pub(crate) async fn pubkeys(
+        account_id: i64,
+        database: &Database,
+    ) -> Result<HashSet<PublicKey>> {
+        Ok(Self::find_by_account(account_id, database)
+            .await?
+            .into_iter()
+            .map(|entry| entry.pubkey)
+            .collect())
+    }
+
+    /// Replace the account's whole mute list, as received in a mute list event
+    ///
+    /// A pubkey listed both publicly and privately is kept as a public entry.
+    pub(crate) async fn replace_all(
+        account_id: i64,
+        public: &[PublicKey],
+        private: &[PublicKey],
+        database: &Database,
+    ) -> Result<()> {
+        let mut tx = database.pool.begin().await?;
+
+        sqlx::query("DELETE FROM account_mutes WHERE account_id = ?")
+            .bind(account_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let entries = private
+            .iter()
+            .map(|pubkey| (pubkey, true))
+            .chain(public.iter().map(|pubkey| (pubkey, false)));
+        for (pubkey, is_private) in entries {
+            sqlx::query(
+                "INSERT INTO account_mutes (account_id, pubkey, private)
+                 VALUES (?, ?, ?)
+                 ON CONFLICT(account_id, pubkey) DO UPDATE SET private = excluded.private",
+            )
+            .bind(account_id)
+            .bind(pubkey.to_hex())
+            .bind(is_private)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::create_mock_whitenoise;
+
+    #[tokio::test]
+    async fn test_add_remove_and_replace_mutes() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let account_id = account.id.unwrap();
+        let db = &whitenoise.database;
+        let first = Keys::generate().public_key();
+        let second = Keys::generate().public_key();
+
+        assert!(
+            AccountMutes::add(account_id, &first, true, db)
+                .await
+                .unwrap()
+        );
+        assert!(
+            AccountMutes::add(account_id, &second, false, db)
+                .await
+                .unwrap()
+        );
+        // Muting again keeps the existing entry as it is
+        assert!(
+            !AccountMutes::add(account_id, &second, true, db)
+                .await
+                .unwrap()
+        );
+
+        assert_eq!(
+            AccountMutes::find_by_account(account_id, db).await.unwrap(),
+            vec![
+                MutedPubkey {
+                    pubkey: first,
+                    private: true
+                },
+                MutedPubkey {
+                    pubkey: second,
+                    private: false
+                },
+            ]
+        );
+
+        assert!(AccountMutes::remove(account_id, &first, db).await.unwrap());
+        assert!(!AccountMutes::remove(account_id, &first, db).await.unwrap());
+        assert_eq!(
+            AccountMutes::pubkeys(account_id, db).await.unwrap(),
+            HashSet::from([second])
+        );
+
+        // A pubkey in both parts of the event ends up public
+        AccountMutes::replace_all(account_id, &[first], &[first, second], db)
+            .await
+            .unwrap();
+        let entries = AccountMutes::find_by_account(account_id, db).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.contains(&MutedPubkey {
+            pubkey: first,
+            private: false
+        }));
+        assert!(entries.contains(&MutedPubkey {
+            pubkey: second,
+            private: true
+        }));
+    }
+}
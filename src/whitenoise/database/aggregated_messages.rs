@@ -1,16 +1,22 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use chrono::{DateTime, Utc};
 use mdk_core::prelude::{GroupId, message_types::Message};
 use nostr_sdk::prelude::*;
 
-use super::{Database, DatabaseError, utils::parse_timestamp};
+use super::{
+    Database, DatabaseError,
+    utils::{create_column_decode_error, parse_timestamp},
+};
 use crate::nostr_manager::parser::SerializableToken;
 use crate::whitenoise::{
     aggregated_message::AggregatedMessage,
     media_files::MediaFile,
-    message_aggregator::{ChatMessage, ReactionSummary},
-    utils::timestamp_to_datetime,
+    message_aggregator::{
+        ChatMessage, ChatMessageRef, DeliveryStatus, GroupActivity, MESSAGE_EDIT_KIND,
+        ReactionSummary, activity, edit_handler, mentions, processor,
+    },
+    utils::{preview_text, timestamp_to_datetime},
 };
 
 type Result<T> = std::result::Result<T, DatabaseError>;
@@ -27,9 +33,11 @@ struct AggregatedMessageRow {
     pub tags: Tags,
     pub reply_to_id: Option<EventId>,
     pub deletion_event_id: Option<EventId>,
+    pub edited_at: Option<DateTime<Utc>>,
     pub content_tokens: Vec<SerializableToken>,
     pub reactions: ReactionSummary,
     pub media_attachments: Vec<MediaFile>,
+    pub delivery_status: DeliveryStatus,
 }
 
 impl<'r, R> sqlx::FromRow<'r, R> for AggregatedMessageRow
@@ -100,6 +108,14 @@ where
             None => None,
         };
 
+        // Convert optional edited_at from milliseconds to DateTime<Utc>
+        let edited_at = match row.try_get::<Option<i64>, _>("edited_at")? {
+            Some(ms) => Some(DateTime::from_timestamp_millis(ms).ok_or_else(|| {
+                create_column_decode_error("edited_at", "Invalid timestamp value")
+            })?),
+            None => None,
+        };
+
         // Deserialize JSONB fields from JSON strings
         let content_tokens_str: String = row.try_get("content_tokens")?;
         let content_tokens =
@@ -123,6 +139,15 @@ where
             }
         })?;
 
+        // NULL for received messages, which are always delivered
+        let delivery_status = match row.try_get::<Option<String>, _>("delivery_status")? {
+            Some(json) => serde_json::from_str(&json).map_err(|e| sqlx::Error::ColumnDecode {
+                index: "delivery_status".to_string(),
+                source: Box::new(e),
+            })?,
+            None => DeliveryStatus::default(),
+        };
+
         Ok(Self {
             id,
             message_id,
@@ -134,9 +159,11 @@ where
             tags,
             reply_to_id,
             deletion_event_id,
+            edited_at,
             content_tokens,
             reactions,
             media_attachments,
+            delivery_status,
         })
     }
 }
@@ -195,18 +222,330 @@ impl AggregatedMessage {
         let rows: Vec<AggregatedMessageRow> = sqlx::query_as(
             "SELECT * FROM aggregated_messages
              WHERE kind = 9 AND mls_group_id = ?
-             ORDER BY created_at",
+             ORDER BY created_at, message_id",
+        )
+        .bind(group_id.as_slice())
+        .fetch_all(&database.pool)
+        .await?;
+
+        Self::rows_to_chat_messages(rows, group_id, database).await
+    }
+
+    /// Fetch the newest kind 9 message that hasn't been deleted for each of the given groups
+    ///
+    /// Uses a single query. Groups without any such message are absent from the result.
+    pub async fn find_last_messages_by_groups(
+        group_ids: &[GroupId],
+        database: &Database,
+    ) -> Result<Vec<(GroupId, ChatMessage)>> {
+        if group_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Build dynamic query with correct number of placeholders
+        let placeholders = "?,".repeat(group_ids.len());
+        let placeholders = placeholders.trim_end_matches(',');
+
+        let query = format!(
+            "SELECT * FROM (
+               SELECT am.*, ROW_NUMBER() OVER (
+                 PARTITION BY am.mls_group_id ORDER BY am.created_at DESC, am.id DESC
+               ) AS position
+               FROM aggregated_messages am
+               WHERE am.kind = 9
+                 AND am.deletion_event_id IS NULL
+                 AND am.mls_group_id IN ({})
+             )
+             WHERE position = 1",
+            placeholders
+        );
+
+        let mut query_builder = sqlx::query_as::<_, AggregatedMessageRow>(&query);
+        for group_id in group_ids {
+            query_builder = query_builder.bind(group_id.as_slice());
+        }
+
+        query_builder
+            .fetch_all(&database.pool)
+            .await?
+            .into_iter()
+            .map(|row| {
+                let group_id = row.mls_group_id.clone();
+                Self::row_to_chat_message(row).map(|message| (group_id, message))
+            })
+            .collect()
+    }
+
+    /// Find when an author last sent a chat message (kind 9) to a group
+    ///
+    /// Deleted messages count too, and so do messages the account sent that aren't cached
+    /// yet. Queries use indexes:
+    /// idx_aggregated_messages_kind_group(kind, mls_group_id, created_at) and
+    /// idx_message_deliveries_group_author(mls_group_id, author, created_at)
+    pub async fn find_last_message_time_by_author(
+        group_id: &GroupId,
+        author: &PublicKey,
+        database: &Database,
+    ) -> Result<Option<Timestamp>> {
+        let created_at: Option<i64> = sqlx::query_scalar(
+            "SELECT MAX(created_at) FROM (
+               SELECT MAX(created_at) AS created_at FROM aggregated_messages
+               WHERE kind = 9 AND mls_group_id = ? AND author = ?
+               UNION ALL
+               SELECT MAX(created_at) AS created_at FROM message_deliveries
+               WHERE mls_group_id = ? AND author = ?
+             )",
+        )
+        .bind(group_id.as_slice())
+        .bind(author.to_hex())
+        .bind(group_id.as_slice())
+        .bind(author.to_hex())
+        .fetch_one(&database.pool)
+        .await?;
+
+        Ok(created_at.map(|millis| Timestamp::from((millis / 1000) as u64)))
+    }
+
+    /// Summarize the account's activity in each of the given groups since `since` (inclusive)
+    ///
+    /// Message and reaction counts, unread counts and last activity come from one aggregate
+    /// query over the cache and the account's read positions. Only new messages that might
+    /// mention someone are loaded, to check whether they mention the account. Deleted
+    /// messages are ignored and unread counts follow `GroupReadState::unread_counts`.
+    /// Reactions from `muted` users never count, their messages only when `hide_muted` is
+    /// unset. Every requested group is present in the result, in the order given.
+    pub async fn summarize_activity(
+        account_id: i64,
+        account_pubkey: &PublicKey,
+        group_ids: &[GroupId],
+        since: Timestamp,
+        muted: &HashSet<PublicKey>,
+        hide_muted: bool,
+        database: &Database,
+    ) -> Result<Vec<GroupActivity>> {
+        let mut activities: Vec<GroupActivity> = group_ids
+            .iter()
+            .map(|group_id| GroupActivity::new(group_id.clone()))
+            .collect();
+        if group_ids.is_empty() {
+            return Ok(activities);
+        }
+
+        // Build dynamic query with correct number of placeholders
+        let placeholders = "?,".repeat(group_ids.len());
+        let placeholders = placeholders.trim_end_matches(',');
+
+        let since_ms = (since.as_u64() as i64).saturating_mul(1000);
+        let muted_json =
+            serde_json::to_string(&muted.iter().map(|pk| pk.to_hex()).collect::<Vec<_>>())?;
+        let hidden_json = if hide_muted {
+            muted_json.clone()
+        } else {
+            "[]".to_string()
+        };
+
+        // Reactions are stored with their timestamps in seconds
+        let query = format!(
+            "SELECT am.mls_group_id,
+                    SUM(am.author != ? AND am.created_at >= ?
+                        AND am.author NOT IN (SELECT value FROM json_each(?))) AS new_messages,
+                    SUM(CASE WHEN am.author = ? THEN (
+                          SELECT COUNT(*) FROM json_each(am.reactions, '$.user_reactions') r
+                          WHERE json_extract(r.value, '$.user') != ?
+                            AND json_extract(r.value, '$.created_at') >= ?
+                            AND json_extract(r.value, '$.user')
+                                NOT IN (SELECT value FROM json_each(?))
+                        ) ELSE 0 END) AS reactions_to_own_messages,
+                    SUM(am.author != ?
+                        AND am.created_at > COALESCE(rs.last_read_at, -1)) AS unread_messages,
+                    MAX(am.created_at) AS last_activity_at
+             FROM aggregated_messages am
+             LEFT JOIN group_read_state rs
+               ON rs.mls_group_id = am.mls_group_id AND rs.account_id = ?
+             WHERE am.kind = 9
+               AND am.deletion_event_id IS NULL
+               AND am.mls_group_id IN ({})
+             GROUP BY am.mls_group_id",
+            placeholders
+        );
+
+        let account_hex = account_pubkey.to_hex();
+        let mut query_builder = sqlx::query_as::<_, (Vec<u8>, i64, i64, i64, i64)>(&query)
+            .bind(&account_hex)
+            .bind(since_ms)
+            .bind(&hidden_json)
+            .bind(&account_hex)
+            .bind(&account_hex)
+            .bind(since.as_u64() as i64)
+            .bind(&muted_json)
+            .bind(&account_hex)
+            .bind(account_id);
+        for group_id in group_ids {
+            query_builder = query_builder.bind(group_id.as_slice());
+        }
+
+        let mut by_group: HashMap<GroupId, &mut GroupActivity> = activities
+            .iter_mut()
+            .map(|activity| (activity.group_id.clone(), activity))
+            .collect();
+        for (group_id_bytes, new_messages, reactions, unread, last_activity_ms) in
+            query_builder.fetch_all(&database.pool).await?
+        {
+            if let Some(activity) = by_group.get_mut(&GroupId::from_slice(&group_id_bytes)) {
+                activity.new_messages = new_messages as usize;
+                activity.reactions_to_own_messages = reactions as usize;
+                activity.unread_messages = unread as usize;
+                activity.last_activity_at = Some(Timestamp::from((last_activity_ms / 1000) as u64));
+            }
+        }
+
+        // Candidates for mentions: new messages with a `p` tag or a `nostr:` reference
+        let query = format!(
+            "SELECT * FROM aggregated_messages am
+             WHERE am.kind = 9
+               AND am.deletion_event_id IS NULL
+               AND am.mls_group_id IN ({})
+               AND am.author != ?
+               AND am.created_at >= ?
+               AND am.author NOT IN (SELECT value FROM json_each(?))
+               AND (EXISTS (SELECT 1 FROM json_each(am.tags) t
+                            WHERE json_extract(t.value, '$[0]') = 'p')
+                    OR am.content_tokens LIKE '%nostr:%')",
+            placeholders
+        );
+
+        let mut query_builder = sqlx::query_as::<_, AggregatedMessageRow>(&query);
+        for group_id in group_ids {
+            query_builder = query_builder.bind(group_id.as_slice());
+        }
+        let rows = query_builder
+            .bind(&account_hex)
+            .bind(since_ms)
+            .bind(&hidden_json)
+            .fetch_all(&database.pool)
+            .await?;
+
+        for row in rows {
+            let group_id = row.mls_group_id.clone();
+            let message = Self::row_to_chat_message(row)?;
+            if activity::mentions_pubkey(&message, account_pubkey)
+                && let Some(activity) = by_group.get_mut(&group_id)
+            {
+                activity.mentions += 1;
+            }
+        }
+
+        Ok(activities)
+    }
+
+    /// Fetch one page of kind 9 messages for a group, newest first
+    ///
+    /// Returns up to `limit` messages created strictly before `before` (or the most recent
+    /// messages when `before` is `None`), ordered by `created_at` descending with event ids
+    /// breaking ties, i.e. the reverse of the order aggregated messages are returned in.
+    ///
+    /// Timestamps only have second precision, so the next page is requested by passing the
+    /// `created_at` of the oldest message returned. To keep that from skipping messages, a
+    /// page never ends partway through a second: messages sharing the oldest second of a full
+    /// page are left for the next page. If a whole page would share one second, every message
+    /// from that second is returned, even if that exceeds `limit`.
+    pub async fn find_messages_by_group_paginated(
+        group_id: &GroupId,
+        before: Option<Timestamp>,
+        limit: usize,
+        database: &Database,
+    ) -> Result<Vec<ChatMessage>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let before_ms = match before {
+            Some(before) => timestamp_to_datetime(before)
+                .map_err(|_| DatabaseError::InvalidTimestamp {
+                    timestamp: before.as_u64() as i64,
+                })?
+                .timestamp_millis(),
+            None => i64::MAX,
+        };
+
+        // One extra row tells us whether the page is full and where the next one starts
+        let mut rows: Vec<AggregatedMessageRow> = sqlx::query_as(
+            "SELECT * FROM aggregated_messages
+             WHERE kind = 9 AND mls_group_id = ? AND created_at < ?
+             ORDER BY created_at DESC, message_id DESC
+             LIMIT ?",
+        )
+        .bind(group_id.as_slice())
+        .bind(before_ms)
+        .bind(limit as i64 + 1)
+        .fetch_all(&database.pool)
+        .await?;
+
+        if rows.len() > limit {
+            let boundary = rows[limit].created_at;
+            rows.truncate(limit);
+            rows.retain(|row| row.created_at != boundary);
+
+            if rows.is_empty() {
+                rows = sqlx::query_as(
+                    "SELECT * FROM aggregated_messages
+                     WHERE kind = 9 AND mls_group_id = ? AND created_at = ?
+                     ORDER BY message_id DESC",
+                )
+                .bind(group_id.as_slice())
+                .bind(boundary.timestamp_millis())
+                .fetch_all(&database.pool)
+                .await?;
+            }
+        }
+
+        Self::rows_to_chat_messages(rows, group_id, database).await
+    }
+
+    /// Fetch a page of a group's kind 9 messages oldest first, for walking its whole history
+    ///
+    /// Returns up to `limit` messages that come after `after`, the `created_at` and id of the
+    /// last message of the previous page, ordered by `created_at` with ids breaking ties. Pass
+    /// `None` for the first page; an empty page marks the end of history.
+    pub async fn find_messages_by_group_after(
+        group_id: &GroupId,
+        after: Option<(Timestamp, &str)>,
+        limit: usize,
+        database: &Database,
+    ) -> Result<Vec<ChatMessage>> {
+        let (after_ms, after_id) = match after {
+            Some((created_at, id)) => (
+                timestamp_to_datetime(created_at)
+                    .map_err(|_| DatabaseError::InvalidTimestamp {
+                        timestamp: created_at.as_u64() as i64,
+                    })?
+                    .timestamp_millis(),
+                id,
+            ),
+            None => (i64::MIN, ""),
+        };
+
+        let rows: Vec<AggregatedMessageRow> = sqlx::query_as(
+            "SELECT * FROM aggregated_messages
+             WHERE kind = 9 AND mls_group_id = ?
+               AND (created_at > ? OR (created_at = ? AND message_id > ?))
+             ORDER BY created_at, message_id
+             LIMIT ?",
         )
         .bind(group_id.as_slice())
+        .bind(after_ms)
+        .bind(after_ms)
+        .bind(after_id)
+        .bind(limit as i64)
         .fetch_all(&database.pool)
         .await?;
 
-        rows.into_iter().map(Self::row_to_chat_message).collect()
+        Self::rows_to_chat_messages(rows, group_id, database).await
     }
 
-    /// Save all events (kind 9, 7, 5) from sync in ONE transaction with single batch INSERT
+    /// Save all events (kind 9, 7, 5 and edits) from sync in ONE transaction with single batch INSERT
     ///
-    /// All events inserted in one batch - kind 9 gets full data, kind 7/5 get empty defaults
+    /// All events inserted in one batch - kind 9 gets full data, other kinds get empty defaults
     /// Single pass - no UPDATE needed. This ensures atomicity: either all events are saved or none are
     pub async fn save_events(
         events: Vec<Message>,                 // All events (kind 9, 7, 5)
@@ -246,27 +585,39 @@ impl AggregatedMessage {
                         .get(&message.id.to_string())
                         .ok_or_else(|| DatabaseError::Sqlx(sqlx::Error::RowNotFound))?;
 
+                    // Edited messages are saved with their latest edit
+                    let content = if chat_msg.is_deleted {
+                        &message.content
+                    } else {
+                        &chat_msg.content
+                    };
+
                     sqlx::query(
                         "INSERT OR IGNORE INTO aggregated_messages
                          (message_id, mls_group_id, author, created_at, kind, content, tags,
-                          reply_to_id, content_tokens, reactions, media_attachments)
-                         VALUES (?, ?, ?, ?, 9, ?, ?, ?, ?, ?, ?)",
+                          reply_to_id, edited_at, content_tokens, reactions, media_attachments,
+                          delivery_status)
+                         VALUES (?, ?, ?, ?, 9, ?, ?, ?, ?, ?, ?, ?,
+                                 COALESCE((SELECT delivery_status FROM message_deliveries
+                                           WHERE message_id = ?1 AND mls_group_id = ?2), ?))",
                     )
                     .bind(message.id.to_string())
                     .bind(group_id.as_slice())
                     .bind(message.pubkey.to_hex())
                     .bind(created_at.timestamp_millis())
-                    .bind(&message.content)
+                    .bind(content)
                     .bind(serde_json::to_string(&message.tags)?)
                     .bind(chat_msg.reply_to_id.as_ref())
+                    .bind(edited_at_millis(chat_msg.edited_at)?)
                     .bind(serde_json::to_string(&chat_msg.content_tokens)?)
                     .bind(serde_json::to_string(&chat_msg.reactions)?)
                     .bind(serde_json::to_string(&chat_msg.media_attachments)?)
+                    .bind(delivery_status_json(chat_msg.delivery_status)?)
                     .execute(&mut *tx)
                     .await?;
                 }
                 _ => {
-                    // Kind 7/5: Use empty defaults
+                    // Kind 7/5 and edits: Use empty defaults
                     sqlx::query(
                         "INSERT OR IGNORE INTO aggregated_messages
                          (message_id, mls_group_id, author, created_at, kind, content, tags,
@@ -309,15 +660,19 @@ impl AggregatedMessage {
         sqlx::query(
             "INSERT INTO aggregated_messages
              (message_id, mls_group_id, author, created_at, kind, content, tags,
-              reply_to_id, content_tokens, reactions, media_attachments)
-             VALUES (?, ?, ?, ?, 9, ?, ?, ?, ?, ?, ?)
+              reply_to_id, edited_at, content_tokens, reactions, media_attachments,
+              delivery_status)
+             VALUES (?, ?, ?, ?, 9, ?, ?, ?, ?, ?, ?, ?, ?)
              ON CONFLICT(message_id, mls_group_id) DO UPDATE SET
                content = excluded.content,
                tags = excluded.tags,
                reply_to_id = excluded.reply_to_id,
+               edited_at = excluded.edited_at,
                content_tokens = excluded.content_tokens,
                reactions = excluded.reactions,
-               media_attachments = excluded.media_attachments",
+               media_attachments = excluded.media_attachments,
+               delivery_status = COALESCE(aggregated_messages.delivery_status,
+                                          excluded.delivery_status)",
         )
         .bind(&message.id)
         .bind(group_id.as_slice())
@@ -326,15 +681,153 @@ impl AggregatedMessage {
         .bind(&message.content)
         .bind(serde_json::to_string(&message.tags)?)
         .bind(&message.reply_to_id)
+        .bind(edited_at_millis(message.edited_at)?)
         .bind(serde_json::to_string(&message.content_tokens)?)
         .bind(serde_json::to_string(&message.reactions)?)
         .bind(serde_json::to_string(&message.media_attachments)?)
+        .bind(delivery_status_json(message.delivery_status)?)
+        .execute(&database.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a kind 9 message the account just sent as pending
+    ///
+    /// Sent messages are only cached once they are synced, so the status is kept apart
+    /// until then and copied onto the message when it gets cached.
+    pub async fn record_pending_delivery(
+        message: &ChatMessage,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<()> {
+        let created_at = timestamp_to_datetime(message.created_at).map_err(|_| {
+            DatabaseError::InvalidTimestamp {
+                timestamp: message.created_at.as_u64() as i64,
+            }
+        })?;
+
+        sqlx::query(
+            "INSERT OR REPLACE INTO message_deliveries
+             (message_id, mls_group_id, author, created_at, delivery_status)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&message.id)
+        .bind(group_id.as_slice())
+        .bind(message.author.to_hex())
+        .bind(created_at.timestamp_millis())
+        .bind(serde_json::to_string(&DeliveryStatus::Pending)?)
         .execute(&database.pool)
         .await?;
 
         Ok(())
     }
 
+    /// Find the delivery status recorded for a kind 9 message the account sent
+    pub async fn find_delivery_status(
+        message_id: &str,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<Option<DeliveryStatus>> {
+        let delivery_status: Option<String> = sqlx::query_scalar(
+            "SELECT delivery_status FROM message_deliveries
+             WHERE message_id = ? AND mls_group_id = ?",
+        )
+        .bind(message_id)
+        .bind(group_id.as_slice())
+        .fetch_optional(&database.pool)
+        .await?;
+
+        Ok(delivery_status
+            .map(|json| serde_json::from_str(&json))
+            .transpose()?)
+    }
+
+    /// Update the delivery status of a kind 9 message the account sent
+    ///
+    /// Updates the cached message too, if it was cached already.
+    pub async fn update_delivery_status(
+        message_id: &str,
+        group_id: &GroupId,
+        delivery_status: DeliveryStatus,
+        database: &Database,
+    ) -> Result<()> {
+        let delivery_status = delivery_status_json(delivery_status)?;
+        let mut tx = database.pool.begin().await?;
+
+        sqlx::query(
+            "UPDATE message_deliveries
+             SET delivery_status = ?
+             WHERE message_id = ? AND mls_group_id = ?",
+        )
+        .bind(&delivery_status)
+        .bind(message_id)
+        .bind(group_id.as_slice())
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "UPDATE aggregated_messages
+             SET delivery_status = ?
+             WHERE message_id = ? AND mls_group_id = ? AND kind = 9",
+        )
+        .bind(&delivery_status)
+        .bind(message_id)
+        .bind(group_id.as_slice())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Mark every message still pending as failed
+    ///
+    /// Publishing doesn't survive a restart, so messages left pending by a previous run
+    /// never reach the relays. Returns the number of messages marked.
+    pub async fn fail_pending_deliveries(database: &Database) -> Result<u64> {
+        let failed = serde_json::to_string(&DeliveryStatus::Failed)?;
+        let pending = serde_json::to_string(&DeliveryStatus::Pending)?;
+        let mut tx = database.pool.begin().await?;
+
+        let recorded = sqlx::query(
+            "UPDATE message_deliveries
+             SET delivery_status = ?
+             WHERE delivery_status = ?",
+        )
+        .bind(&failed)
+        .bind(&pending)
+        .execute(&mut *tx)
+        .await?;
+
+        // Count cached messages only when they weren't recorded above
+        let unrecorded = sqlx::query(
+            "UPDATE aggregated_messages
+             SET delivery_status = ?
+             WHERE kind = 9 AND delivery_status = ?
+               AND NOT EXISTS (SELECT 1 FROM message_deliveries d
+                               WHERE d.message_id = aggregated_messages.message_id
+                                 AND d.mls_group_id = aggregated_messages.mls_group_id)",
+        )
+        .bind(&failed)
+        .bind(&pending)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "UPDATE aggregated_messages
+             SET delivery_status = ?
+             WHERE kind = 9 AND delivery_status = ?",
+        )
+        .bind(&failed)
+        .bind(&pending)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(recorded.rows_affected() + unrecorded.rows_affected())
+    }
+
     /// Insert a kind 7 reaction event (audit trail)
     pub async fn insert_reaction(
         reaction: &Message,
@@ -410,6 +903,70 @@ impl AggregatedMessage {
         Ok(())
     }
 
+    /// Insert an edit event (audit trail and edit history)
+    pub async fn insert_edit(
+        edit: &Message,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<()> {
+        let created_at = timestamp_to_datetime(edit.created_at).map_err(|_| {
+            DatabaseError::InvalidTimestamp {
+                timestamp: edit.created_at.as_u64() as i64,
+            }
+        })?;
+
+        let empty_tokens = Vec::<SerializableToken>::new();
+        let empty_reactions = ReactionSummary::default();
+        let empty_media = Vec::<MediaFile>::new();
+
+        sqlx::query(
+            "INSERT INTO aggregated_messages
+             (message_id, mls_group_id, author, created_at, kind, content, tags,
+              content_tokens, reactions, media_attachments)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(message_id, mls_group_id) DO NOTHING",
+        )
+        .bind(edit.id.to_string())
+        .bind(group_id.as_slice())
+        .bind(edit.pubkey.to_hex())
+        .bind(created_at.timestamp_millis())
+        .bind(MESSAGE_EDIT_KIND as i64)
+        .bind(&edit.content)
+        .bind(serde_json::to_string(&edit.tags)?)
+        .bind(serde_json::to_string(&empty_tokens)?)
+        .bind(serde_json::to_string(&empty_reactions)?)
+        .bind(serde_json::to_string(&empty_media)?)
+        .execute(&database.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replace a kind 9 message's content with an edit
+    pub async fn update_edit(
+        message_id: &str,
+        group_id: &GroupId,
+        content: &str,
+        content_tokens: &[SerializableToken],
+        edited_at: Timestamp,
+        database: &Database,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE aggregated_messages
+             SET content = ?, content_tokens = ?, edited_at = ?
+             WHERE message_id = ? AND mls_group_id = ? AND kind = 9",
+        )
+        .bind(content)
+        .bind(serde_json::to_string(content_tokens)?)
+        .bind(edited_at_millis(Some(edited_at))?)
+        .bind(message_id)
+        .bind(group_id.as_slice())
+        .execute(&database.pool)
+        .await?;
+
+        Ok(())
+    }
+
     /// Update a kind 9 message's reaction summary
     pub async fn update_reactions(
         message_id: &str,
@@ -452,12 +1009,18 @@ impl AggregatedMessage {
         Ok(())
     }
 
-    /// Delete ALL cached events for a group
+    /// Delete ALL cached events for a group, along with the delivery status of sent messages
     pub async fn delete_by_group(group_id: &GroupId, database: &Database) -> Result<()> {
+        let mut tx = database.pool.begin().await?;
         sqlx::query("DELETE FROM aggregated_messages WHERE mls_group_id = ?")
             .bind(group_id.as_slice())
-            .execute(&database.pool)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM message_deliveries WHERE mls_group_id = ?")
+            .bind(group_id.as_slice())
+            .execute(&mut *tx)
             .await?;
+        tx.commit().await?;
         Ok(())
     }
 
@@ -476,7 +1039,15 @@ impl AggregatedMessage {
         .fetch_optional(&database.pool)
         .await?;
 
-        row.map(Self::row_to_chat_message).transpose()
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let mut message = Self::row_to_chat_message(row)?;
+        Self::resolve_reply_previews(std::slice::from_mut(&mut message), group_id, database)
+            .await?;
+        Self::resolve_quote_previews(std::slice::from_mut(&mut message), group_id, database)
+            .await?;
+        Ok(Some(message))
     }
 
     /// Find a cached reaction (kind 7) by its event ID
@@ -500,11 +1071,10 @@ impl AggregatedMessage {
         Ok(row.map(AggregatedMessageRow::into_aggregated_message))
     }
 
-    /// Find orphaned reactions targeting a specific message
-    /// Returns reactions (kind 7) that reference the target message_id
-    /// Uses json_each to properly parse the tags array
-    pub async fn find_orphaned_reactions(
+    /// Find an author's reactions (kind 7) on a message that haven't been deleted yet
+    pub async fn find_active_reactions_by_author(
         message_id: &str,
+        author: &PublicKey,
         group_id: &GroupId,
         database: &Database,
     ) -> Result<Vec<AggregatedMessage>> {
@@ -512,6 +1082,8 @@ impl AggregatedMessage {
             "SELECT am.* FROM aggregated_messages am
              WHERE am.kind = 7
                AND am.mls_group_id = ?
+               AND am.author = ?
+               AND am.deletion_event_id IS NULL
                AND EXISTS (
                  SELECT 1 FROM json_each(am.tags) AS tag
                  WHERE json_extract(tag.value, '$[0]') = 'e'
@@ -519,6 +1091,7 @@ impl AggregatedMessage {
                )",
         )
         .bind(group_id.as_slice())
+        .bind(author.to_hex())
         .bind(message_id)
         .fetch_all(&database.pool)
         .await
@@ -530,29 +1103,144 @@ impl AggregatedMessage {
             .collect())
     }
 
-    /// Find orphaned deletions targeting a specific message
-    /// Returns the event IDs of deletions (kind 5) that reference the target message_id
+    /// Find a cached edit by its event ID
+    pub async fn find_edit_by_id(
+        edit_id: &str,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<Option<AggregatedMessage>> {
+        let row: Option<AggregatedMessageRow> = sqlx::query_as(
+            "SELECT * FROM aggregated_messages
+             WHERE message_id = ? AND mls_group_id = ? AND kind = ?",
+        )
+        .bind(edit_id)
+        .bind(group_id.as_slice())
+        .bind(MESSAGE_EDIT_KIND as i64)
+        .fetch_optional(&database.pool)
+        .await?;
+
+        Ok(row.map(AggregatedMessageRow::into_aggregated_message))
+    }
+
+    /// Find edits that reference a specific message (or edit), oldest first
     /// Uses json_each to properly parse the tags array
-    pub async fn find_orphaned_deletions(
+    pub async fn find_edits(
         message_id: &str,
         group_id: &GroupId,
         database: &Database,
-    ) -> Result<Vec<EventId>> {
-        let ids: Vec<String> = sqlx::query_scalar(
-            "SELECT am.message_id FROM aggregated_messages am
-             WHERE am.kind = 5
+    ) -> Result<Vec<AggregatedMessage>> {
+        let rows: Vec<AggregatedMessageRow> = sqlx::query_as(
+            "SELECT am.* FROM aggregated_messages am
+             WHERE am.kind = ?
                AND am.mls_group_id = ?
                AND EXISTS (
                  SELECT 1 FROM json_each(am.tags) AS tag
                  WHERE json_extract(tag.value, '$[0]') = 'e'
                    AND json_extract(tag.value, '$[1]') = ?
-               )",
+               )
+             ORDER BY am.created_at, am.id",
         )
+        .bind(MESSAGE_EDIT_KIND as i64)
         .bind(group_id.as_slice())
         .bind(message_id)
         .fetch_all(&database.pool)
-        .await
-        .map_err(DatabaseError::Sqlx)?;
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(AggregatedMessageRow::into_aggregated_message)
+            .collect())
+    }
+
+    /// Find every cached edit of a message, including edits of its edits, oldest first
+    pub async fn find_edit_chain(
+        message_id: &str,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<Vec<AggregatedMessage>> {
+        let mut edits = Vec::new();
+        let mut pending = vec![message_id.to_string()];
+        while let Some(id) = pending.pop() {
+            let found = Self::find_edits(&id, group_id, database).await?;
+            pending.extend(found.iter().map(|edit| edit.event_id.to_hex()));
+            edits.extend(found);
+        }
+
+        edits.sort_by_key(|edit| edit.created_at);
+        Ok(edits)
+    }
+
+    /// Follow cached edits of edits back to the ID of the message they ultimately edit
+    ///
+    /// Returns `message_id` itself when it isn't a cached edit.
+    pub async fn resolve_edit_root(
+        message_id: &str,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<String> {
+        let mut root_id = message_id.to_string();
+        while let Some(edit) = Self::find_edit_by_id(&root_id, group_id, database).await? {
+            match edit_handler::extract_edit_target_id(&edit.tags) {
+                Some(target_id) => root_id = target_id,
+                None => break,
+            }
+        }
+        Ok(root_id)
+    }
+
+    /// Find orphaned reactions targeting a specific message
+    /// Returns reactions (kind 7) that reference the target message_id
+    /// Uses json_each to properly parse the tags array
+    pub async fn find_orphaned_reactions(
+        message_id: &str,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<Vec<AggregatedMessage>> {
+        let rows: Vec<AggregatedMessageRow> = sqlx::query_as(
+            "SELECT am.* FROM aggregated_messages am
+             WHERE am.kind = 7
+               AND am.mls_group_id = ?
+               AND EXISTS (
+                 SELECT 1 FROM json_each(am.tags) AS tag
+                 WHERE json_extract(tag.value, '$[0]') = 'e'
+                   AND json_extract(tag.value, '$[1]') = ?
+               )",
+        )
+        .bind(group_id.as_slice())
+        .bind(message_id)
+        .fetch_all(&database.pool)
+        .await
+        .map_err(DatabaseError::Sqlx)?;
+
+        Ok(rows
+            .into_iter()
+            .map(AggregatedMessageRow::into_aggregated_message)
+            .collect())
+    }
+
+    /// Find orphaned deletions targeting a specific message
+    /// Returns the event IDs of deletions (kind 5) that reference the target message_id
+    /// Uses json_each to properly parse the tags array
+    pub async fn find_orphaned_deletions(
+        message_id: &str,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<Vec<EventId>> {
+        let ids: Vec<String> = sqlx::query_scalar(
+            "SELECT am.message_id FROM aggregated_messages am
+             WHERE am.kind = 5
+               AND am.mls_group_id = ?
+               AND EXISTS (
+                 SELECT 1 FROM json_each(am.tags) AS tag
+                 WHERE json_extract(tag.value, '$[0]') = 'e'
+                   AND json_extract(tag.value, '$[1]') = ?
+               )",
+        )
+        .bind(group_id.as_slice())
+        .bind(message_id)
+        .fetch_all(&database.pool)
+        .await
+        .map_err(DatabaseError::Sqlx)?;
 
         Ok(ids
             .into_iter()
@@ -561,9 +1249,121 @@ impl AggregatedMessage {
     }
 
     /// Convert database row to ChatMessage
+    /// Convert a group's rows to chat messages with the previews of their reply targets
+    async fn rows_to_chat_messages(
+        rows: Vec<AggregatedMessageRow>,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<Vec<ChatMessage>> {
+        let mut messages = rows
+            .into_iter()
+            .map(Self::row_to_chat_message)
+            .collect::<Result<Vec<_>>>()?;
+        Self::resolve_reply_previews(&mut messages, group_id, database).await?;
+        Self::resolve_quote_previews(&mut messages, group_id, database).await?;
+        Ok(messages)
+    }
+
+    /// Set the preview of every reply from its cached target, looking the targets up in one
+    /// query
+    ///
+    /// Replies to messages that are deleted or not cached keep only the target id.
+    async fn resolve_reply_previews(
+        messages: &mut [ChatMessage],
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<()> {
+        let target_ids: HashSet<String> = messages
+            .iter()
+            .filter_map(|message| message.reply_to.as_ref())
+            .map(|reply_to| reply_to.id.clone())
+            .collect();
+        if target_ids.is_empty() {
+            return Ok(());
+        }
+
+        let placeholders = "?,".repeat(target_ids.len());
+        let placeholders = placeholders.trim_end_matches(',');
+        let query = format!(
+            "SELECT message_id, content FROM aggregated_messages
+             WHERE kind = 9 AND mls_group_id = ? AND deletion_event_id IS NULL
+               AND message_id IN ({})",
+            placeholders
+        );
+
+        let mut query_builder =
+            sqlx::query_as::<_, (String, String)>(&query).bind(group_id.as_slice());
+        for target_id in &target_ids {
+            query_builder = query_builder.bind(target_id);
+        }
+        let previews: HashMap<String, String> = query_builder
+            .fetch_all(&database.pool)
+            .await?
+            .into_iter()
+            .map(|(id, content)| (id, preview_text(&content)))
+            .collect();
+
+        for message in messages.iter_mut() {
+            if let Some(reply_to) = message.reply_to.as_mut() {
+                reply_to.preview = previews.get(&reply_to.id).cloned();
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify every quote whose target is cached, looking the targets up in one query
+    ///
+    /// See [`processor::verify_quote`]; quotes of messages that aren't cached keep the preview
+    /// taken when quoting.
+    async fn resolve_quote_previews(
+        messages: &mut [ChatMessage],
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<()> {
+        let target_ids: HashSet<String> = messages
+            .iter()
+            .filter_map(|message| message.quoted.as_ref())
+            .map(|quoted| quoted.id.clone())
+            .collect();
+        if target_ids.is_empty() {
+            return Ok(());
+        }
+
+        let placeholders = "?,".repeat(target_ids.len());
+        let placeholders = placeholders.trim_end_matches(',');
+        let query = format!(
+            "SELECT * FROM aggregated_messages
+             WHERE kind = 9 AND mls_group_id = ? AND message_id IN ({})",
+            placeholders
+        );
+
+        let mut query_builder =
+            sqlx::query_as::<_, AggregatedMessageRow>(&query).bind(group_id.as_slice());
+        for target_id in &target_ids {
+            query_builder = query_builder.bind(target_id);
+        }
+        let targets: HashMap<String, ChatMessage> = query_builder
+            .fetch_all(&database.pool)
+            .await?
+            .into_iter()
+            .map(Self::row_to_chat_message)
+            .map(|target| target.map(|target| (target.id.clone(), target)))
+            .collect::<Result<_>>()?;
+
+        for message in messages.iter_mut() {
+            if let Some(quoted) = message.quoted.as_mut()
+                && let Some(target) = targets.get(&quoted.id)
+            {
+                processor::verify_quote(quoted, &message.tags, target);
+            }
+        }
+        Ok(())
+    }
+
     fn row_to_chat_message(row: AggregatedMessageRow) -> Result<ChatMessage> {
         // Convert DateTime<Utc> to Timestamp (seconds)
         let created_at = Timestamp::from(row.created_at.timestamp() as u64);
+        let mentions = mentions::extract_mentions(&row.content);
 
         Ok(ChatMessage {
             id: row.message_id.to_string(),
@@ -573,19 +1373,52 @@ impl AggregatedMessage {
             tags: row.tags,
             is_reply: row.reply_to_id.is_some(),
             reply_to_id: row.reply_to_id.map(|id| id.to_string()),
+            reply_to: row
+                .reply_to_id
+                .map(|id| ChatMessageRef::unresolved(id.to_string())),
+            quoted: processor::extract_quote(&row.tags),
             is_deleted: row.deletion_event_id.is_some(),
+            edited_at: row
+                .edited_at
+                .map(|edited_at| Timestamp::from(edited_at.timestamp() as u64)),
+            is_muted: false,
             content_tokens: row.content_tokens,
+            mentions,
             reactions: row.reactions,
             kind: row.kind.as_u16(),
             media_attachments: row.media_attachments,
+            delivery_status: row.delivery_status,
         })
     }
 }
 
+/// Encode a delivery status for the `delivery_status` column (NULL for received messages)
+fn delivery_status_json(delivery_status: DeliveryStatus) -> Result<Option<String>> {
+    if delivery_status == DeliveryStatus::default() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::to_string(&delivery_status)?))
+}
+
+/// Convert an optional edit time to milliseconds for the `edited_at` column
+fn edited_at_millis(edited_at: Option<Timestamp>) -> Result<Option<i64>> {
+    edited_at
+        .map(|edited_at| {
+            timestamp_to_datetime(edited_at)
+                .map(|datetime| datetime.timestamp_millis())
+                .map_err(|_| DatabaseError::InvalidTimestamp {
+                    timestamp: edited_at.as_u64() as i64,
+                })
+        })
+        .transpose()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::whitenoise::database::group_read_state::GroupReadState;
     use crate::whitenoise::group_information::{GroupInformation, GroupType};
+    use crate::whitenoise::message_aggregator::UserReaction;
     use crate::whitenoise::test_utils::create_mock_whitenoise;
     use nostr_sdk::Keys;
 
@@ -601,23 +1434,12 @@ mod tests {
     }
 
     fn create_test_chat_message(seed: u8, author: PublicKey) -> ChatMessage {
-        // Create a valid 64-character hex string by repeating a pattern
-        let id = format!("{:0>64}", format!("{:x}", seed));
-
-        ChatMessage {
-            id,
-            author,
-            content: "Test message".to_string(),
-            created_at: Timestamp::now(),
-            tags: Tags::new(),
-            is_reply: false,
-            reply_to_id: None,
-            is_deleted: false,
-            content_tokens: vec![],
-            reactions: ReactionSummary::default(),
-            kind: 9,
-            media_attachments: vec![],
-        }
+        ChatMessage::test_message(
+            &format!("{seed:064x}"),
+            "Test message",
+            Timestamp::now().as_u64(),
+        )
+        .with_author(author)
     }
 
     #[tokio::test]
@@ -682,6 +1504,175 @@ mod tests {
         assert_eq!(messages[0].content, message.content);
     }
 
+    #[tokio::test]
+    async fn test_find_last_message_time_by_author() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let group_id = GroupId::from_slice(&[1; 32]);
+        setup_group(&group_id, &whitenoise.database).await;
+
+        let author = Keys::generate().public_key();
+        let other = Keys::generate().public_key();
+        assert_eq!(
+            AggregatedMessage::find_last_message_time_by_author(
+                &group_id,
+                &author,
+                &whitenoise.database
+            )
+            .await
+            .unwrap(),
+            None
+        );
+
+        for (i, (pubkey, created_at)) in [(author, 100), (author, 200), (other, 300)]
+            .into_iter()
+            .enumerate()
+        {
+            let mut message = create_test_chat_message(i as u8 + 1, pubkey);
+            message.created_at = Timestamp::from(created_at);
+            AggregatedMessage::insert_message(&message, &group_id, &whitenoise.database)
+                .await
+                .unwrap();
+        }
+
+        let last_sent_at = AggregatedMessage::find_last_message_time_by_author(
+            &group_id,
+            &author,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        assert_eq!(last_sent_at, Some(Timestamp::from(200)));
+    }
+
+    #[tokio::test]
+    async fn test_summarize_activity_counts_each_category() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let account_id = account.id.unwrap();
+        let me = account.pubkey;
+        let alice = Keys::generate().public_key();
+        let bob = Keys::generate().public_key();
+        let group_id = GroupId::from_slice(&[1; 32]);
+        let quiet_group_id = GroupId::from_slice(&[2; 32]);
+        setup_group(&group_id, &whitenoise.database).await;
+        let since = Timestamp::from(100);
+
+        let reaction = |user: PublicKey, created_at: u64| UserReaction {
+            user,
+            emoji: "👍".to_string(),
+            created_at: Timestamp::from(created_at),
+        };
+
+        let mut own_message = create_test_chat_message(1, me);
+        own_message.reactions.user_reactions = vec![
+            reaction(alice, 40),  // before `since`
+            reaction(alice, 120), // counted
+            reaction(bob, 130),   // counted
+            reaction(me, 140),    // own reaction
+        ];
+
+        let mut tag_mention = create_test_chat_message(2, alice);
+        tag_mention.tags = Tags::from_list(vec![Tag::public_key(me)]);
+
+        let mut uri_mention = create_test_chat_message(3, bob);
+        uri_mention.content_tokens = vec![
+            SerializableToken::Text("hey ".to_string()),
+            SerializableToken::Nostr(me.to_nostr_uri().unwrap()),
+        ];
+
+        let mut other_mention = create_test_chat_message(4, bob);
+        other_mention.content_tokens =
+            vec![SerializableToken::Nostr(alice.to_nostr_uri().unwrap())];
+
+        let mut deleted = create_test_chat_message(5, alice);
+        deleted.tags = Tags::from_list(vec![Tag::public_key(me)]);
+
+        for (mut message, created_at) in [
+            (create_test_chat_message(6, alice), 90), // before `since`
+            (own_message, 50),
+            (create_test_chat_message(7, me), 115), // own message
+            (tag_mention, 110),
+            (uri_mention, 150),
+            (other_mention, 160),
+            (deleted.clone(), 170),
+        ] {
+            message.created_at = Timestamp::from(created_at);
+            AggregatedMessage::insert_message(&message, &group_id, &whitenoise.database)
+                .await
+                .unwrap();
+        }
+        AggregatedMessage::mark_deleted(
+            &deleted.id,
+            &group_id,
+            &EventId::all_zeros().to_hex(),
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        GroupReadState::mark_read(
+            account_id,
+            &group_id,
+            Timestamp::from(150),
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+
+        let summary = AggregatedMessage::summarize_activity(
+            account_id,
+            &me,
+            &[group_id.clone(), quiet_group_id.clone()],
+            since,
+            &HashSet::new(),
+            true,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(summary.len(), 2);
+        assert_eq!(summary[0].group_id, group_id);
+        assert_eq!(summary[0].new_messages, 3);
+        assert_eq!(summary[0].mentions, 2);
+        assert_eq!(summary[0].reactions_to_own_messages, 2);
+        assert_eq!(summary[0].unread_messages, 1);
+        assert_eq!(summary[0].last_activity_at, Some(Timestamp::from(160)));
+        assert_eq!(summary[1], GroupActivity::new(quiet_group_id));
+        assert!(summary[1].is_empty());
+
+        // Muted users' reactions never count, their messages only while they're shown
+        let muted = HashSet::from([bob]);
+        let summary = AggregatedMessage::summarize_activity(
+            account_id,
+            &me,
+            std::slice::from_ref(&group_id),
+            since,
+            &muted,
+            true,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        assert_eq!(summary[0].new_messages, 1);
+        assert_eq!(summary[0].mentions, 1);
+        assert_eq!(summary[0].reactions_to_own_messages, 1);
+
+        let summary = AggregatedMessage::summarize_activity(
+            account_id,
+            &me,
+            std::slice::from_ref(&group_id),
+            since,
+            &muted,
+            false,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        assert_eq!(summary[0].new_messages, 3);
+        assert_eq!(summary[0].mentions, 2);
+        assert_eq!(summary[0].reactions_to_own_messages, 1);
+    }
+
     #[tokio::test]
     async fn test_insert_multiple_messages() {
         let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
@@ -904,4 +1895,400 @@ mod tests {
         assert_eq!(messages[0].reactions.by_emoji.len(), 1);
         assert!(messages[0].reactions.by_emoji.contains_key("👍"));
     }
+
+    #[tokio::test]
+    async fn test_edits_are_cached_and_applied() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let group_id = GroupId::from_slice(&[9; 32]);
+        setup_group(&group_id, &whitenoise.database).await;
+
+        let keys = Keys::generate();
+        let message = create_test_chat_message(50, keys.public_key());
+        AggregatedMessage::insert_message(&message, &group_id, &whitenoise.database)
+            .await
+            .unwrap();
+
+        let kind = Kind::Custom(MESSAGE_EDIT_KIND);
+        let created_at = Timestamp::now();
+        let tags = vec![Tag::parse(vec!["e", &message.id]).unwrap()];
+        let mut event = UnsignedEvent::new(keys.public_key(), created_at, kind, tags, "Edited");
+        event.ensure_id();
+        let edit = Message {
+            id: event.id.unwrap(),
+            pubkey: keys.public_key(),
+            created_at,
+            kind,
+            tags: event.tags.clone(),
+            content: "Edited".to_string(),
+            mls_group_id: group_id.clone(),
+            event,
+            wrapper_event_id: EventId::all_zeros(),
+            state: mdk_core::prelude::message_types::MessageState::Processed,
+        };
+        AggregatedMessage::insert_edit(&edit, &group_id, &whitenoise.database)
+            .await
+            .unwrap();
+
+        let edits = AggregatedMessage::find_edits(&message.id, &group_id, &whitenoise.database)
+            .await
+            .unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].event_id, edit.id);
+        assert_eq!(edits[0].content, "Edited");
+        assert!(
+            AggregatedMessage::find_edit_by_id(&edit.id.to_hex(), &group_id, &whitenoise.database)
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        AggregatedMessage::update_edit(
+            &message.id,
+            &group_id,
+            &edit.content,
+            &[],
+            created_at,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+
+        // Edits are not messages of their own
+        let messages = AggregatedMessage::find_messages_by_group(&group_id, &whitenoise.database)
+            .await
+            .unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "Edited");
+        assert_eq!(messages[0].edited_at, Some(created_at));
+    }
+
+    #[tokio::test]
+    async fn test_delivery_status() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let group_id = GroupId::from_slice(&[10; 32]);
+        setup_group(&group_id, &whitenoise.database).await;
+
+        let author = Keys::generate().public_key();
+        let received = create_test_chat_message(60, author);
+        let mut sent = create_test_chat_message(61, author);
+        sent.delivery_status = DeliveryStatus::Pending;
+        for message in [&received, &sent] {
+            AggregatedMessage::insert_message(message, &group_id, &whitenoise.database)
+                .await
+                .unwrap();
+        }
+
+        // Re-caching the sent message as received doesn't reset its status
+        let mut echoed = sent.clone();
+        echoed.delivery_status = DeliveryStatus::default();
+        AggregatedMessage::insert_message(&echoed, &group_id, &whitenoise.database)
+            .await
+            .unwrap();
+        let find = async |id: &str| {
+            AggregatedMessage::find_by_id(id, &group_id, &whitenoise.database)
+                .await
+                .unwrap()
+                .unwrap()
+                .delivery_status
+        };
+        assert_eq!(find(&sent.id).await, DeliveryStatus::Pending);
+        assert_eq!(find(&received.id).await, DeliveryStatus::Sent { relays: 0 });
+
+        AggregatedMessage::update_delivery_status(
+            &sent.id,
+            &group_id,
+            DeliveryStatus::Sent { relays: 2 },
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        assert_eq!(find(&sent.id).await, DeliveryStatus::Sent { relays: 2 });
+
+        AggregatedMessage::update_delivery_status(
+            &sent.id,
+            &group_id,
+            DeliveryStatus::Pending,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        let failed = AggregatedMessage::fail_pending_deliveries(&whitenoise.database)
+            .await
+            .unwrap();
+        assert_eq!(failed, 1);
+        assert_eq!(find(&sent.id).await, DeliveryStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_recorded_delivery_before_caching() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let group_id = GroupId::from_slice(&[11; 32]);
+        setup_group(&group_id, &whitenoise.database).await;
+
+        let author = Keys::generate().public_key();
+        let sent = create_test_chat_message(62, author);
+        AggregatedMessage::record_pending_delivery(&sent, &group_id, &whitenoise.database)
+            .await
+            .unwrap();
+        let find = async || {
+            AggregatedMessage::find_delivery_status(&sent.id, &group_id, &whitenoise.database)
+                .await
+                .unwrap()
+        };
+        assert_eq!(find().await, Some(DeliveryStatus::Pending));
+
+        // Uncached messages count for slow mode
+        let last_sent_at = AggregatedMessage::find_last_message_time_by_author(
+            &group_id,
+            &author,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        assert_eq!(last_sent_at, Some(sent.created_at));
+
+        AggregatedMessage::update_delivery_status(
+            &sent.id,
+            &group_id,
+            DeliveryStatus::Sent { relays: 1 },
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        assert_eq!(find().await, Some(DeliveryStatus::Sent { relays: 1 }));
+        assert!(
+            AggregatedMessage::find_by_id(&sent.id, &group_id, &whitenoise.database)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        AggregatedMessage::delete_by_group(&group_id, &whitenoise.database)
+            .await
+            .unwrap();
+        assert_eq!(find().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_cached_replies_carry_target_preview() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let group_id = GroupId::from_slice(&[1; 32]);
+        setup_group(&group_id, &whitenoise.database).await;
+        let author = Keys::generate().public_key();
+
+        let mut target = create_test_chat_message(1, author);
+        target.content = "The original message".to_string();
+        target.created_at = Timestamp::from(100);
+        let mut reply = create_test_chat_message(2, author);
+        reply.created_at = Timestamp::from(200);
+        reply.is_reply = true;
+        reply.reply_to_id = Some(target.id.clone());
+        for message in [&target, &reply] {
+            AggregatedMessage::insert_message(message, &group_id, &whitenoise.database)
+                .await
+                .unwrap();
+        }
+
+        let messages = AggregatedMessage::find_messages_by_group(&group_id, &whitenoise.database)
+            .await
+            .unwrap();
+        let reply_to = messages[1].reply_to.as_ref().unwrap();
+        assert_eq!(reply_to.id, target.id);
+        assert_eq!(reply_to.preview.as_deref(), Some("The original message"));
+
+        // The target doesn't have to be on the same page
+        let page = AggregatedMessage::find_messages_by_group_paginated(
+            &group_id,
+            None,
+            1,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(
+            page[0].reply_to.as_ref().unwrap().preview.as_deref(),
+            Some("The original message")
+        );
+
+        // Replies to deleted messages only keep the target id
+        AggregatedMessage::mark_deleted(
+            &target.id,
+            &group_id,
+            &EventId::all_zeros().to_hex(),
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        let found = AggregatedMessage::find_by_id(&reply.id, &group_id, &whitenoise.database)
+            .await
+            .unwrap()
+            .unwrap();
+        let reply_to = found.reply_to.unwrap();
+        assert_eq!(reply_to.id, target.id);
+        assert_eq!(reply_to.preview, None);
+    }
+
+    #[tokio::test]
+    async fn test_cached_quotes_are_checked_against_target() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let group_id = GroupId::from_slice(&[1; 32]);
+        setup_group(&group_id, &whitenoise.database).await;
+        let author = Keys::generate().public_key();
+        let quoter = Keys::generate().public_key();
+
+        let mut target = create_test_chat_message(1, author);
+        target.content = "The original message".to_string();
+        let quote_of = |seed: u8, claimed_author: &PublicKey, preview: &str| {
+            let mut quote = create_test_chat_message(seed, quoter);
+            quote.tags = Tags::from_list(vec![
+                Tag::parse(vec![
+                    "q",
+                    target.id.as_str(),
+                    "",
+                    claimed_author.to_hex().as_str(),
+                    preview,
+                ])
+                .unwrap(),
+            ]);
+            quote
+        };
+        let honest = quote_of(2, &author, "Stale preview");
+        let forged = quote_of(3, &quoter, "Made-up text");
+        for message in [&target, &honest, &forged] {
+            AggregatedMessage::insert_message(message, &group_id, &whitenoise.database)
+                .await
+                .unwrap();
+        }
+
+        let preview = async |message: &ChatMessage| {
+            AggregatedMessage::find_by_id(&message.id, &group_id, &whitenoise.database)
+                .await
+                .unwrap()
+                .unwrap()
+                .quoted
+                .unwrap()
+                .preview
+        };
+        assert_eq!(
+            preview(&honest).await.as_deref(),
+            Some("The original message")
+        );
+        assert_eq!(preview(&forged).await, None);
+
+        // Once the target is deleted, only the preview of a quote naming its author is kept
+        AggregatedMessage::mark_deleted(
+            &target.id,
+            &group_id,
+            &EventId::all_zeros().to_hex(),
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        let messages = AggregatedMessage::find_messages_by_group(&group_id, &whitenoise.database)
+            .await
+            .unwrap();
+        let quoted = |message: &ChatMessage| {
+            messages
+                .iter()
+                .find(|m| m.id == message.id)
+                .and_then(|m| m.quoted.clone())
+                .unwrap()
+                .preview
+        };
+        assert_eq!(quoted(&honest).as_deref(), Some("Stale preview"));
+        assert_eq!(quoted(&forged), None);
+    }
+
+    #[tokio::test]
+    async fn test_find_messages_by_group_paginated() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let group_id = GroupId::from_slice(&[8; 32]);
+        setup_group(&group_id, &whitenoise.database).await;
+
+        let author = Keys::generate().public_key();
+
+        // Seconds 100, 200, 200, 300, 400: two messages share a second, and are cached in
+        // the opposite order of their ids
+        for (seed, secs) in [(50, 100), (52, 200), (51, 200), (53, 300), (54, 400)] {
+            let mut message = create_test_chat_message(seed, author);
+            message.created_at = Timestamp::from(secs);
+            AggregatedMessage::insert_message(&message, &group_id, &whitenoise.database)
+                .await
+                .unwrap();
+        }
+
+        // Reactions are stored on the message row, so they come along with the page
+        let mut reactions = ReactionSummary::default();
+        reactions.by_emoji.insert(
+            "🔥".to_string(),
+            crate::whitenoise::message_aggregator::EmojiReaction {
+                emoji: "🔥".to_string(),
+                count: 1,
+                users: vec![author],
+            },
+        );
+        let oldest_id = create_test_chat_message(50, author).id;
+        AggregatedMessage::update_reactions(
+            &oldest_id,
+            &group_id,
+            &reactions,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+
+        let page_secs = |page: &[ChatMessage]| {
+            page.iter()
+                .map(|m| m.created_at.as_u64())
+                .collect::<Vec<_>>()
+        };
+
+        let first = AggregatedMessage::find_messages_by_group_paginated(
+            &group_id,
+            None,
+            3,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        // The third slot would split second 200, so the page stops before it
+        assert_eq!(page_secs(&first), vec![400, 300]);
+
+        let second = AggregatedMessage::find_messages_by_group_paginated(
+            &group_id,
+            Some(first.last().unwrap().created_at),
+            3,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        assert_eq!(page_secs(&second), vec![200, 200, 100]);
+        // Ties are ordered by event id, not by when they were cached
+        assert_eq!(second[0].id, create_test_chat_message(52, author).id);
+        assert_eq!(second[1].id, create_test_chat_message(51, author).id);
+        assert!(second[2].reactions.by_emoji.contains_key("🔥"));
+
+        let third = AggregatedMessage::find_messages_by_group_paginated(
+            &group_id,
+            Some(second.last().unwrap().created_at),
+            3,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        assert!(third.is_empty());
+
+        // A page that would consist only of one split second returns the whole second
+        let tied = AggregatedMessage::find_messages_by_group_paginated(
+            &group_id,
+            Some(Timestamp::from(300)),
+            1,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        assert_eq!(page_secs(&tied), vec![200, 200]);
+        assert_eq!(tied[0].id, create_test_chat_message(52, author).id);
+    }
 }
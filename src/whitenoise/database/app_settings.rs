@@ -1,6 +1,7 @@
 use std::str::FromStr;
 
 use chrono::{DateTime, Utc};
+use nostr_sdk::PublicKey;
 
 use super::{Database, utils::parse_timestamp};
 use crate::whitenoise::{
@@ -130,6 +131,45 @@ impl AppSettings {
 
         Ok(())
     }
+
+    /// The public key of the active account, if one is set.
+    pub(crate) async fn active_account_pubkey(
+        database: &Database,
+    ) -> Result<Option<PublicKey>, WhitenoiseError> {
+        let pubkey: Option<Option<String>> =
+            sqlx::query_scalar("SELECT active_account_pubkey FROM app_settings WHERE id = 1")
+                .fetch_optional(&database.pool)
+                .await
+                .map_err(|e| WhitenoiseError::Database(e.into()))?;
+
+        Ok(pubkey.flatten().and_then(|hex| PublicKey::parse(&hex).ok()))
+    }
+
+    /// Sets or clears the active account.
+    ///
+    /// Creates the settings row with default values if it doesn't exist yet.
+    pub(crate) async fn update_active_account_pubkey(
+        pubkey: Option<&PublicKey>,
+        database: &Database,
+    ) -> Result<(), WhitenoiseError> {
+        let now = Utc::now().timestamp_millis();
+        sqlx::query(
+            "INSERT INTO app_settings (id, theme_mode, created_at, updated_at, active_account_pubkey)
+             VALUES (1, ?, ?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET
+               active_account_pubkey = excluded.active_account_pubkey,
+               updated_at = excluded.updated_at",
+        )
+        .bind(ThemeMode::default().to_string())
+        .bind(now)
+        .bind(now)
+        .bind(pubkey.map(|pubkey| pubkey.to_hex()))
+        .execute(&database.pool)
+        .await
+        .map_err(|e| WhitenoiseError::Database(e.into()))?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
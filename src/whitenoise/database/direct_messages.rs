@@ -0,0 +1,184 @@
+use nostr_sdk::prelude::*;
+
+use super::{Database, DatabaseError};
+
+type Result<T> = std::result::Result<T, DatabaseError>;
+
+/// A NIP-17 direct message as stored for an account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StoredDirectMessage {
+    /// ID of the rumor
+    pub id: EventId,
+    /// The conversation partner
+    pub other_pubkey: PublicKey,
+    pub author: PublicKey,
+    pub content: String,
+    pub tags: Tags,
+    pub created_at: Timestamp,
+}
+
+type DirectMessageRow = (String, String, String, String, String, i64);
+
+/// NIP-17 direct messages per account
+pub(crate) struct DirectMessages;
+
+impl DirectMessages {
+    /// Store a direct message rumor, returning whether it wasn't stored yet
+    ///
+    /// The same rumor arrives once per giftwrap (e.g. the copy the account wraps to itself),
+    /// so duplicates are ignored.
+    pub(crate) async fn insert(
+        account_id: i64,
+        other_pubkey: &PublicKey,
+        rumor: &UnsignedEvent,
+        database: &Database,
+    ) -> Result<bool> {
+        let message_id = rumor.id.unwrap_or_else(|| {
+            EventId::new(
+                &rumor.pubkey,
+                &rumor.created_at,
+                &rumor.kind,
+                &rumor.tags,
+                &rumor.content,
+            )
+        });
+        let result = sqlx::query(
+            "INSERT INTO direct_messages
+               (account_id, message_id, other_pubkey, author, content, tags, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(account_id, message_id) DO NOTHING",
+        )
+        .bind(account_id)
+        .bind(message_id.to_hex())
+        .bind(other_pubkey.to_hex())
+        .bind(rumor.pubkey.to_hex())
+        .bind(&rumor.content)
+        .bind(serde_json::to_string(&rumor.tags)?)
+        .bind((rumor.created_at.as_u64() as i64).saturating_mul(1000))
+        .execute(&database.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// All messages the account exchanged with `other_pubkey`, oldest first
+    pub(crate) async fn find_conversation(
+        account_id: i64,
+        other_pubkey: &PublicKey,
+        database: &Database,
+    ) -> Result<Vec<StoredDirectMessage>> {
+        let rows: Vec<DirectMessageRow> = sqlx::query_as(
+            "SELECT message_id, other_pubkey, author, content, tags, created_at
+             FROM direct_messages
+             WHERE account_id = ? AND other_pubkey = ?
+             ORDER BY created_at, id",
+        )
+        .bind(account_id)
+        .bind(other_pubkey.to_hex())
+        .fetch_all(&database.pool)
+        .await?;
+
+        rows.into_iter().map(Self::from_row).collect()
+    }
+
+    /// The newest message of each of the account's conversations
+    pub(crate) async fn find_last_messages(
+        account_id: i64,
+        database: &Database,
+    ) -> Result<Vec<StoredDirectMessage>> {
+        let rows: Vec<DirectMessageRow> = sqlx::query_as(
+            "SELECT message_id, other_pubkey, author, content, tags, created_at FROM (
+               SELECT dm.*, ROW_NUMBER() OVER (
+                 PARTITION BY dm.other_pubkey ORDER BY dm.created_at DESC, dm.id DESC
+               ) AS position
+               FROM direct_messages dm
+               WHERE dm.account_id = ?
+             )
+             WHERE position = 1",
+        )
+        .bind(account_id)
+        .fetch_all(&database.pool)
+        .await?;
+
+        rows.into_iter().map(Self::from_row).collect()
+    }
+
+    fn from_row(
+        (message_id, other_pubkey, author, content, tags, created_at): DirectMessageRow,
+    ) -> Result<StoredDirectMessage> {
+        Ok(StoredDirectMessage {
+            id: EventId::from_hex(&message_id).map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+            other_pubkey: PublicKey::from_hex(&other_pubkey)
+                .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+            author: PublicKey::from_hex(&author).map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+            content,
+            tags: serde_json::from_str(&tags)?,
+            created_at: Timestamp::from((created_at / 1000) as u64),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::create_mock_whitenoise;
+
+    fn rumor(author: PublicKey, recipient: PublicKey, content: &str, at: u64) -> UnsignedEvent {
+        let mut rumor = UnsignedEvent::new(
+            author,
+            Timestamp::from(at),
+            Kind::PrivateDirectMessage,
+            vec![Tag::public_key(recipient)],
+            content,
+        );
+        rumor.ensure_id();
+        rumor
+    }
+
+    #[tokio::test]
+    async fn test_conversations_and_last_messages() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let account_id = account.id.unwrap();
+        let db = &whitenoise.database;
+        let alice = Keys::generate().public_key();
+        let bob = Keys::generate().public_key();
+
+        let first = rumor(alice, account.pubkey, "Hi", 100);
+        assert!(
+            DirectMessages::insert(account_id, &alice, &first, db)
+                .await
+                .unwrap()
+        );
+        // The copy from a second giftwrap is ignored
+        assert!(
+            !DirectMessages::insert(account_id, &alice, &first, db)
+                .await
+                .unwrap()
+        );
+        let reply = rumor(account.pubkey, alice, "Hello Alice", 200);
+        DirectMessages::insert(account_id, &alice, &reply, db)
+            .await
+            .unwrap();
+        let other = rumor(bob, account.pubkey, "Hey", 150);
+        DirectMessages::insert(account_id, &bob, &other, db)
+            .await
+            .unwrap();
+
+        let conversation = DirectMessages::find_conversation(account_id, &alice, db)
+            .await
+            .unwrap();
+        assert_eq!(conversation.len(), 2);
+        assert_eq!(conversation[0].author, alice);
+        assert_eq!(conversation[1].content, "Hello Alice");
+        assert_eq!(conversation[1].created_at, Timestamp::from(200));
+        assert_eq!(Some(conversation[1].id), reply.id);
+
+        let mut last = DirectMessages::find_last_messages(account_id, db)
+            .await
+            .unwrap();
+        last.sort_by_key(|message| message.created_at);
+        assert_eq!(last.len(), 2);
+        assert_eq!(last[0].other_pubkey, bob);
+        assert_eq!(last[1].content, "Hello Alice");
+    }
+}
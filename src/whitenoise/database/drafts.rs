@@ -0,0 +1,119 @@
+use mdk_core::prelude::GroupId;
+
+use super::{Database, DatabaseError};
+
+type Result<T> = std::result::Result<T, DatabaseError>;
+
+/// Unsent message each account was typing per group
+///
+/// Drafts never leave the device.
+pub(crate) struct Drafts;
+
+impl Drafts {
+    /// Store the account's draft for the group, replacing any previous one
+    pub(crate) async fn save(
+        account_id: i64,
+        group_id: &GroupId,
+        content: &str,
+        database: &Database,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO drafts (account_id, mls_group_id, content)
+             VALUES (?, ?, ?)
+             ON CONFLICT(account_id, mls_group_id) DO UPDATE SET
+               content = excluded.content,
+               updated_at = CURRENT_TIMESTAMP",
+        )
+        .bind(account_id)
+        .bind(group_id.as_slice())
+        .bind(content)
+        .execute(&database.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The account's draft for the group, if it has one
+    pub(crate) async fn find(
+        account_id: i64,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<Option<String>> {
+        let content: Option<String> = sqlx::query_scalar(
+            "SELECT content FROM drafts
+             WHERE account_id = ? AND mls_group_id = ?",
+        )
+        .bind(account_id)
+        .bind(group_id.as_slice())
+        .fetch_optional(&database.pool)
+        .await?;
+
+        Ok(content)
+    }
+
+    /// Remove the account's draft for the group; does nothing if it has none
+    pub(crate) async fn delete(
+        account_id: i64,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<()> {
+        sqlx::query("DELETE FROM drafts WHERE account_id = ? AND mls_group_id = ?")
+            .bind(account_id)
+            .bind(group_id.as_slice())
+            .execute(&database.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::create_mock_whitenoise;
+
+    #[tokio::test]
+    async fn test_save_find_and_delete_draft() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let account_id = account.id.unwrap();
+        let group_id = GroupId::from_slice(&[1; 32]);
+        let other_group_id = GroupId::from_slice(&[2; 32]);
+
+        assert_eq!(
+            Drafts::find(account_id, &group_id, &whitenoise.database)
+                .await
+                .unwrap(),
+            None
+        );
+
+        for content in ["Hel", "Hello there"] {
+            Drafts::save(account_id, &group_id, content, &whitenoise.database)
+                .await
+                .unwrap();
+            assert_eq!(
+                Drafts::find(account_id, &group_id, &whitenoise.database)
+                    .await
+                    .unwrap()
+                    .as_deref(),
+                Some(content)
+            );
+        }
+        assert_eq!(
+            Drafts::find(account_id, &other_group_id, &whitenoise.database)
+                .await
+                .unwrap(),
+            None
+        );
+
+        Drafts::delete(account_id, &group_id, &whitenoise.database)
+            .await
+            .unwrap();
+        assert_eq!(
+            Drafts::find(account_id, &group_id, &whitenoise.database)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! SQLCipher encryption at rest
+//!
+//! Only compiled with the `sqlcipher` feature, which links SQLCipher in place of SQLite.
+
+use std::path::Path;
+
+use sqlx::{ConnectOptions, Connection, SqlitePool, sqlite::SqliteConnectOptions};
+use tokio::io::AsyncReadExt;
+
+use super::DatabaseError;
+
+/// First bytes of every unencrypted SQLite database file
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// Whether `path` holds an unencrypted SQLite database
+///
+/// Missing and empty files are not plaintext databases; SQLCipher encrypts them on first use.
+pub(super) async fn is_plaintext_database(path: &Path) -> Result<bool, DatabaseError> {
+    let mut file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    let mut header = [0u8; SQLITE_HEADER.len()];
+    Ok(file.read_exact(&mut header).await.is_ok() && header == SQLITE_HEADER)
+}
+
+/// Encrypt a plaintext database in place with the passphrase
+///
+/// The database is exported into a new encrypted file, which then replaces the original.
+/// The original's write-ahead log is folded into the export, so no committed data is lost.
+pub(super) async fn encrypt_plaintext_database(
+    path: &Path,
+    passphrase: &str,
+) -> Result<(), DatabaseError> {
+    tracing::info!(
+        target: "whitenoise::database::encryption",
+        "Encrypting plaintext database {:?}",
+        path
+    );
+
+    let encrypted_path = path.with_extension("encrypting");
+    if encrypted_path.exists() {
+        tokio::fs::remove_file(&encrypted_path).await?;
+    }
+
+    let mut conn = SqliteConnectOptions::new().filename(path).connect().await?;
+    sqlx::query("ATTACH DATABASE ? AS encrypted KEY ?")
+        .bind(encrypted_path.to_string_lossy().into_owned())
+        .bind(passphrase)
+        .execute(&mut conn)
+        .await?;
+    sqlx::query("SELECT sqlcipher_export('encrypted')")
+        .execute(&mut conn)
+        .await?;
+    sqlx::query("DETACH DATABASE encrypted")
+        .execute(&mut conn)
+        .await?;
+    conn.close().await?;
+
+    // Leftover WAL files belong to the plaintext database and must not be applied to the new one
+    for suffix in ["-wal", "-shm"] {
+        let mut sidecar = path.as_os_str().to_owned();
+        sidecar.push(suffix);
+        match tokio::fs::remove_file(&sidecar).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    tokio::fs::rename(&encrypted_path, path).await?;
+
+    Ok(())
+}
+
+/// Export the database behind the pool into a new unencrypted file at `dest`
+pub(super) async fn export_plaintext(pool: &SqlitePool, dest: &Path) -> Result<(), DatabaseError> {
+    let mut conn = pool.acquire().await?;
+    sqlx::query("ATTACH DATABASE ? AS plaintext KEY ''")
+        .bind(dest.to_string_lossy().into_owned())
+        .execute(&mut *conn)
+        .await?;
+    let result = sqlx::query("SELECT sqlcipher_export('plaintext')")
+        .execute(&mut *conn)
+        .await;
+    sqlx::query("DETACH DATABASE plaintext")
+        .execute(&mut *conn)
+        .await?;
+    result?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Database, DatabasePoolConfig};
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_plaintext_database_is_encrypted_on_open() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+
+        let db = Database::new(db_path.clone()).await.unwrap();
+        sqlx::query("INSERT INTO relays (url) VALUES ('wss://relay.example.com')")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        db.pool.close().await;
+        assert!(is_plaintext_database(&db_path).await.unwrap());
+
+        let db = Database::new_encrypted(db_path.clone(), "secret", &DatabasePoolConfig::default())
+            .await
+            .unwrap();
+        assert!(!is_plaintext_database(&db_path).await.unwrap());
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM relays")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        db.pool.close().await;
+
+        let result =
+            Database::new_encrypted(db_path, "wrong", &DatabasePoolConfig::default()).await;
+        assert!(matches!(result, Err(DatabaseError::DecryptionFailed)));
+    }
+}
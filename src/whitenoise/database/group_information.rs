@@ -2,11 +2,14 @@ use std::str::FromStr;
 
 use chrono::{DateTime, Utc};
 use mdk_core::prelude::GroupId;
+use nostr_sdk::{PublicKey, Timestamp};
+use sqlx::{FromRow, Row};
 
 use super::{Database, utils::parse_timestamp};
 use crate::whitenoise::{
+    chat_list::{Conversation, ConversationFilter},
     error::WhitenoiseError,
-    group_information::{GroupInformation, GroupType},
+    group_information::{GroupInformation, GroupType, SlowMode},
 };
 
 /// Internal database row representation for group_information table
@@ -15,6 +18,8 @@ struct GroupInformationRow {
     id: i64,
     mls_group_id: GroupId,
     group_type: String,
+    slow_mode_interval_secs: i64,
+    slow_mode_admins_exempt: bool,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
 }
@@ -25,12 +30,15 @@ where
     &'r str: sqlx::ColumnIndex<R>,
     String: sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
     i64: sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+    bool: sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
     Vec<u8>: sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
 {
     fn from_row(row: &'r R) -> Result<Self, sqlx::Error> {
         let id: i64 = row.try_get("id")?;
         let mls_group_id_bytes: Vec<u8> = row.try_get("mls_group_id")?;
         let group_type: String = row.try_get("group_type")?;
+        let slow_mode_interval_secs: i64 = row.try_get("slow_mode_interval_secs")?;
+        let slow_mode_admins_exempt: bool = row.try_get("slow_mode_admins_exempt")?;
 
         let mls_group_id = GroupId::from_slice(&mls_group_id_bytes);
         let created_at = parse_timestamp(row, "created_at")?;
@@ -40,6 +48,8 @@ where
             id,
             mls_group_id,
             group_type,
+            slow_mode_interval_secs,
+            slow_mode_admins_exempt,
             created_at,
             updated_at,
         })
@@ -59,6 +69,10 @@ impl GroupInformationRow {
             id: Some(self.id),
             mls_group_id: self.mls_group_id,
             group_type,
+            slow_mode: SlowMode {
+                interval_secs: self.slow_mode_interval_secs.max(0) as u64,
+                admins_exempt: self.slow_mode_admins_exempt,
+            },
             created_at: self.created_at,
             updated_at: self.updated_at,
         })
@@ -85,7 +99,8 @@ impl GroupInformation {
         database: &Database,
     ) -> Result<Self, WhitenoiseError> {
         let group_information_row = sqlx::query_as::<_, GroupInformationRow>(
-            "SELECT id, mls_group_id, group_type, created_at, updated_at FROM group_information WHERE mls_group_id = ?",
+            "SELECT id, mls_group_id, group_type, slow_mode_interval_secs, slow_mode_admins_exempt, created_at, updated_at
+             FROM group_information WHERE mls_group_id = ?",
         )
         .bind(mls_group_id.as_slice())
         .fetch_one(&database.pool)
@@ -156,7 +171,7 @@ impl GroupInformation {
         let placeholders = placeholders.trim_end_matches(',');
 
         let query = format!(
-            "SELECT id, mls_group_id, group_type, created_at, updated_at
+            "SELECT id, mls_group_id, group_type, slow_mode_interval_secs, slow_mode_admins_exempt, created_at, updated_at
              FROM group_information
              WHERE mls_group_id IN ({})",
             placeholders
@@ -175,6 +190,166 @@ impl GroupInformation {
             .collect::<Result<Vec<_>, _>>()
     }
 
+    /// Updates the slow mode settings of an existing GroupInformation record.
+    ///
+    /// Settings only replace ones published earlier, so messages applied out of order can't
+    /// roll them back. The account's own changes also replace settings published in the same
+    /// second, since they are made after everything already applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `mls_group_id` - The MLS group ID of the record to update
+    /// * `slow_mode` - The new slow mode settings
+    /// * `published_at` - When the settings message was created
+    /// * `own_change` - Whether the account itself published the settings
+    /// * `database` - A reference to the `Database` instance for database operations
+    ///
+    /// # Returns
+    ///
+    /// Returns the updated `GroupInformation`, or `None` if newer stored settings were kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WhitenoiseError`] if no record exists for the group or the update fails.
+    pub(crate) async fn update_slow_mode(
+        mls_group_id: &GroupId,
+        slow_mode: &SlowMode,
+        published_at: Timestamp,
+        own_change: bool,
+        database: &Database,
+    ) -> Result<Option<Self>, WhitenoiseError> {
+        let interval_secs = i64::try_from(slow_mode.interval_secs).map_err(|_| {
+            WhitenoiseError::InvalidInput(format!(
+                "Slow mode interval {}s is out of range",
+                slow_mode.interval_secs
+            ))
+        })?;
+        let published_at = published_at.as_u64() as i64;
+        let replaces_before = if own_change {
+            published_at + 1
+        } else {
+            published_at
+        };
+
+        let row = sqlx::query_as::<_, GroupInformationRow>(
+            "UPDATE group_information
+             SET slow_mode_interval_secs = ?, slow_mode_admins_exempt = ?, slow_mode_updated_at = ?, updated_at = ?
+             WHERE mls_group_id = ? AND (slow_mode_updated_at IS NULL OR slow_mode_updated_at < ?)
+             RETURNING id, mls_group_id, group_type, slow_mode_interval_secs, slow_mode_admins_exempt, created_at, updated_at",
+        )
+        .bind(interval_secs)
+        .bind(slow_mode.admins_exempt)
+        .bind(published_at)
+        .bind(Utc::now().timestamp_millis())
+        .bind(mls_group_id.as_slice())
+        .bind(replaces_before)
+        .fetch_optional(&database.pool)
+        .await?;
+
+        match row {
+            Some(row) => row.into_group_information().map(Some),
+            None => {
+                // Fails if there's no record at all rather than only newer settings
+                Self::find_by_mls_group_id(mls_group_id, database).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Loads the conversations among the given groups that match `filter`, with their last
+    /// activity and unread count, in one query.
+    ///
+    /// Activity and unread counts follow `GroupReadState::unread_counts`: only chat messages
+    /// that weren't deleted count, and the account's own messages are never unread. Results
+    /// are ordered by last activity, newest first, with groups without messages last. Groups
+    /// without a record are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WhitenoiseError`] if the database query fails.
+    pub(crate) async fn find_conversations(
+        account_id: i64,
+        account_pubkey: &PublicKey,
+        mls_group_ids: &[GroupId],
+        filter: &ConversationFilter,
+        database: &Database,
+    ) -> Result<Vec<Conversation>, WhitenoiseError> {
+        if mls_group_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Build dynamic query with correct number of placeholders
+        let placeholders = "?,".repeat(mls_group_ids.len());
+        let placeholders = placeholders.trim_end_matches(',');
+
+        let query = format!(
+            "SELECT * FROM (
+               SELECT gi.id, gi.mls_group_id, gi.group_type, gi.slow_mode_interval_secs,
+                      gi.slow_mode_admins_exempt, gi.created_at, gi.updated_at,
+                      (SELECT MAX(am.created_at) FROM aggregated_messages am
+                        WHERE am.mls_group_id = gi.mls_group_id
+                          AND am.kind = 9
+                          AND am.deletion_event_id IS NULL) AS last_activity_at,
+                      (SELECT COUNT(*) FROM aggregated_messages am
+                        LEFT JOIN group_read_state rs
+                          ON rs.mls_group_id = am.mls_group_id AND rs.account_id = ?
+                        WHERE am.mls_group_id = gi.mls_group_id
+                          AND am.kind = 9
+                          AND am.author != ?
+                          AND am.deletion_event_id IS NULL
+                          AND am.created_at > COALESCE(rs.last_read_at, -1)) AS unread_count,
+                      pc.position AS pin_position
+               FROM group_information gi
+               LEFT JOIN pinned_conversations pc
+                 ON pc.mls_group_id = gi.mls_group_id AND pc.account_id = ?
+               WHERE gi.mls_group_id IN ({})
+                 AND (? IS NULL OR gi.group_type = ?)
+             )
+             WHERE (? = 0 OR unread_count > 0)
+               AND (? IS NULL OR last_activity_at >= ?)
+             ORDER BY pin_position IS NULL, pin_position,
+                      last_activity_at IS NULL, last_activity_at DESC",
+            placeholders
+        );
+
+        let group_type = filter.group_type.as_ref().map(GroupType::to_string);
+        let active_since_ms = filter.active_within.map(|within| {
+            Utc::now().timestamp_millis() - i64::try_from(within.as_millis()).unwrap_or(i64::MAX)
+        });
+
+        let mut query_builder = sqlx::query(&query)
+            .bind(account_id)
+            .bind(account_pubkey.to_hex())
+            .bind(account_id);
+        for group_id in mls_group_ids {
+            query_builder = query_builder.bind(group_id.as_slice());
+        }
+        let rows = query_builder
+            .bind(group_type.clone())
+            .bind(group_type)
+            .bind(filter.unread_only)
+            .bind(active_since_ms)
+            .bind(active_since_ms)
+            .fetch_all(&database.pool)
+            .await?;
+
+        rows.iter()
+            .map(|row| -> Result<Conversation, WhitenoiseError> {
+                let last_activity_at: Option<i64> = row.try_get("last_activity_at")?;
+                let unread_count: i64 = row.try_get("unread_count")?;
+                let pin_position: Option<i64> = row.try_get("pin_position")?;
+                Ok(Conversation {
+                    group_information: GroupInformationRow::from_row(row)?
+                        .into_group_information()?,
+                    last_activity_at: last_activity_at
+                        .map(|ms| Timestamp::from((ms / 1000).max(0) as u64)),
+                    unread_count: unread_count.max(0) as u64,
+                    pinned: pin_position.is_some(),
+                })
+            })
+            .collect()
+    }
+
     // Private helper method for creating and persisting new records
     async fn insert_new(
         mls_group_id: &GroupId,
@@ -186,7 +361,7 @@ impl GroupInformation {
         let row = sqlx::query_as::<_, GroupInformationRow>(
             "INSERT INTO group_information (mls_group_id, group_type, created_at, updated_at)
              VALUES (?, ?, ?, ?)
-             RETURNING id, mls_group_id, group_type, created_at, updated_at",
+             RETURNING id, mls_group_id, group_type, slow_mode_interval_secs, slow_mode_admins_exempt, created_at, updated_at",
         )
         .bind(mls_group_id.as_slice())
         .bind(group_type.to_string())
@@ -407,4 +582,215 @@ mod tests {
         assert_eq!(found_dm.group_type, GroupType::DirectMessage);
         assert_eq!(found_dm.id, dm_group_info.id);
     }
+
+    #[tokio::test]
+    async fn test_update_slow_mode() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let group_id = GroupId::from_slice(&[12; 32]);
+
+        let (group_info, _) = GroupInformation::find_or_create_by_mls_group_id(
+            &group_id,
+            Some(GroupType::Group),
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        assert_eq!(group_info.slow_mode, SlowMode::default());
+
+        let slow_mode = SlowMode {
+            interval_secs: 30,
+            admins_exempt: false,
+        };
+        let updated = GroupInformation::update_slow_mode(
+            &group_id,
+            &slow_mode,
+            Timestamp::from(1_000),
+            false,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(updated.id, group_info.id);
+        assert_eq!(updated.slow_mode, slow_mode);
+
+        // Settings published earlier, or at the same time, don't replace the stored ones
+        for published_at in [999, 1_000] {
+            let stale = GroupInformation::update_slow_mode(
+                &group_id,
+                &SlowMode::default(),
+                Timestamp::from(published_at),
+                false,
+                &whitenoise.database,
+            )
+            .await
+            .unwrap();
+            assert!(stale.is_none());
+        }
+
+        // The account's own change made in the same second still applies
+        let own = SlowMode {
+            interval_secs: 60,
+            admins_exempt: true,
+        };
+        let updated = GroupInformation::update_slow_mode(
+            &group_id,
+            &own,
+            Timestamp::from(1_000),
+            true,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(updated.slow_mode, own);
+
+        let found = GroupInformation::find_by_mls_group_id(&group_id, &whitenoise.database)
+            .await
+            .unwrap();
+        assert_eq!(found.slow_mode, own);
+    }
+
+    #[tokio::test]
+    async fn test_update_slow_mode_missing_group() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let group_id = GroupId::from_slice(&[13; 32]);
+
+        let result = GroupInformation::update_slow_mode(
+            &group_id,
+            &SlowMode::default(),
+            Timestamp::now(),
+            false,
+            &whitenoise.database,
+        )
+        .await;
+        assert!(matches!(
+            result.unwrap_err(),
+            WhitenoiseError::SqlxError(sqlx::Error::RowNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_find_conversations_filters_by_type_unread_and_activity() {
+        use crate::whitenoise::aggregated_message::AggregatedMessage;
+        use crate::whitenoise::database::group_read_state::GroupReadState;
+        use crate::whitenoise::message_aggregator::ChatMessage;
+
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let account_id = account.id.unwrap();
+        let other = nostr_sdk::Keys::generate().public_key();
+        let now = Timestamp::now().as_u64();
+
+        let recent_group = GroupId::from_slice(&[1; 32]);
+        let old_group = GroupId::from_slice(&[2; 32]);
+        let dm = GroupId::from_slice(&[3; 32]);
+        let empty_group = GroupId::from_slice(&[4; 32]);
+        for (group_id, group_type) in [
+            (&recent_group, GroupType::Group),
+            (&old_group, GroupType::Group),
+            (&dm, GroupType::DirectMessage),
+            (&empty_group, GroupType::Group),
+        ] {
+            GroupInformation::find_or_create_by_mls_group_id(
+                group_id,
+                Some(group_type),
+                &whitenoise.database,
+            )
+            .await
+            .unwrap();
+        }
+
+        let ten_days = 10 * 24 * 60 * 60;
+        for (seed, group_id, created_at) in [
+            (1u8, &recent_group, now - 60),
+            (2, &old_group, now - ten_days),
+            (3, &dm, now - 120),
+        ] {
+            let message =
+                ChatMessage::test_message(&format!("{seed:064x}"), "Test message", created_at)
+                    .with_author(other);
+            AggregatedMessage::insert_message(&message, group_id, &whitenoise.database)
+                .await
+                .unwrap();
+        }
+        GroupReadState::mark_read(account_id, &dm, Timestamp::from(now), &whitenoise.database)
+            .await
+            .unwrap();
+
+        let group_ids = [
+            recent_group.clone(),
+            old_group.clone(),
+            dm.clone(),
+            empty_group.clone(),
+        ];
+        let find = |filter: ConversationFilter| {
+            let database = &whitenoise.database;
+            let group_ids = &group_ids;
+            let pubkey = account.pubkey;
+            async move {
+                GroupInformation::find_conversations(
+                    account_id, &pubkey, group_ids, &filter, database,
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|conversation| conversation.group_information.mls_group_id)
+                .collect::<Vec<_>>()
+            }
+        };
+
+        // Most recent first, groups without messages last
+        assert_eq!(
+            find(ConversationFilter::default()).await,
+            vec![
+                recent_group.clone(),
+                dm.clone(),
+                old_group.clone(),
+                empty_group.clone()
+            ]
+        );
+        assert_eq!(
+            find(ConversationFilter {
+                group_type: Some(GroupType::DirectMessage),
+                ..Default::default()
+            })
+            .await,
+            vec![dm.clone()]
+        );
+        assert_eq!(
+            find(ConversationFilter {
+                unread_only: true,
+                ..Default::default()
+            })
+            .await,
+            vec![recent_group.clone(), old_group.clone()]
+        );
+        assert_eq!(
+            find(ConversationFilter {
+                group_type: Some(GroupType::Group),
+                active_within: Some(std::time::Duration::from_secs(24 * 60 * 60)),
+                ..Default::default()
+            })
+            .await,
+            vec![recent_group.clone()]
+        );
+
+        let conversations = GroupInformation::find_conversations(
+            account_id,
+            &account.pubkey,
+            &[recent_group.clone(), empty_group],
+            &ConversationFilter::default(),
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            conversations[0].last_activity_at,
+            Some(Timestamp::from(now - 60))
+        );
+        assert_eq!(conversations[0].unread_count, 1);
+        assert_eq!(conversations[1].last_activity_at, None);
+        assert_eq!(conversations[1].unread_count, 0);
+    }
 }
@@ -0,0 +1,94 @@
+use std::str::FromStr;
+
+use mdk_core::prelude::GroupId;
+
+use super::{Database, DatabaseError};
+use crate::whitenoise::{app_settings::NotificationLevel, error::WhitenoiseError};
+
+/// Notification level each account chose per group
+///
+/// Only groups whose level was changed have a row; the rest use [`NotificationLevel::All`].
+pub(crate) struct GroupNotificationSettings;
+
+impl GroupNotificationSettings {
+    /// Set the account's notification level for the group
+    pub(crate) async fn set(
+        account_id: i64,
+        group_id: &GroupId,
+        level: &NotificationLevel,
+        database: &Database,
+    ) -> Result<(), WhitenoiseError> {
+        sqlx::query(
+            "INSERT INTO group_notification_settings (account_id, mls_group_id, level)
+             VALUES (?, ?, ?)
+             ON CONFLICT(account_id, mls_group_id) DO UPDATE SET
+               level = excluded.level,
+               updated_at = CURRENT_TIMESTAMP",
+        )
+        .bind(account_id)
+        .bind(group_id.as_slice())
+        .bind(level.to_string())
+        .execute(&database.pool)
+        .await
+        .map_err(DatabaseError::Sqlx)?;
+
+        Ok(())
+    }
+
+    /// The account's notification level for the group
+    pub(crate) async fn find(
+        account_id: i64,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<NotificationLevel, WhitenoiseError> {
+        let level: Option<String> = sqlx::query_scalar(
+            "SELECT level FROM group_notification_settings
+             WHERE account_id = ? AND mls_group_id = ?",
+        )
+        .bind(account_id)
+        .bind(group_id.as_slice())
+        .fetch_optional(&database.pool)
+        .await
+        .map_err(DatabaseError::Sqlx)?;
+
+        match level {
+            Some(level) => NotificationLevel::from_str(&level).map_err(|e| {
+                WhitenoiseError::Configuration(format!("Invalid notification level: {}", e))
+            }),
+            None => Ok(NotificationLevel::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::create_mock_whitenoise;
+
+    #[tokio::test]
+    async fn test_set_and_find_notification_level() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let account_id = account.id.unwrap();
+        let group_id = GroupId::from_slice(&[1; 32]);
+
+        assert_eq!(
+            GroupNotificationSettings::find(account_id, &group_id, &whitenoise.database)
+                .await
+                .unwrap(),
+            NotificationLevel::All
+        );
+
+        for level in [NotificationLevel::Muted, NotificationLevel::MentionsOnly] {
+            GroupNotificationSettings::set(account_id, &group_id, &level, &whitenoise.database)
+                .await
+                .unwrap();
+            assert_eq!(
+                GroupNotificationSettings::find(account_id, &group_id, &whitenoise.database)
+                    .await
+                    .unwrap(),
+                level
+            );
+        }
+    }
+}
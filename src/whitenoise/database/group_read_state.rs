@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+
+use mdk_core::prelude::GroupId;
+use nostr_sdk::prelude::*;
+
+use super::{Database, DatabaseError};
+
+type Result<T> = std::result::Result<T, DatabaseError>;
+
+/// How far an account has read in a group
+///
+/// Read positions are timestamps rather than message ids so they stay meaningful when
+/// messages are deleted and can be compared directly against cached messages.
+pub(crate) struct GroupReadState;
+
+impl GroupReadState {
+    /// Record that the account has read the group up to `up_to`
+    ///
+    /// Never moves the read position backward: marking an older timestamp as read leaves the
+    /// stored position unchanged. Returns the resulting position.
+    pub(crate) async fn mark_read(
+        account_id: i64,
+        group_id: &GroupId,
+        up_to: Timestamp,
+        database: &Database,
+    ) -> Result<Timestamp> {
+        let last_read_at: i64 = sqlx::query_scalar(
+            "INSERT INTO group_read_state (account_id, mls_group_id, last_read_at)
+             VALUES (?, ?, ?)
+             ON CONFLICT(account_id, mls_group_id) DO UPDATE SET
+               last_read_at = MAX(last_read_at, excluded.last_read_at),
+               updated_at = CURRENT_TIMESTAMP
+             RETURNING last_read_at",
+        )
+        .bind(account_id)
+        .bind(group_id.as_slice())
+        .bind(Self::to_millis(up_to))
+        .fetch_one(&database.pool)
+        .await?;
+
+        Ok(Timestamp::from((last_read_at / 1000) as u64))
+    }
+
+    /// The account's read position in the group, or `None` if it never marked it read
+    pub(crate) async fn last_read_at(
+        account_id: i64,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<Option<Timestamp>> {
+        let last_read_at: Option<i64> = sqlx::query_scalar(
+            "SELECT last_read_at FROM group_read_state
+             WHERE account_id = ? AND mls_group_id = ?",
+        )
+        .bind(account_id)
+        .bind(group_id.as_slice())
+        .fetch_optional(&database.pool)
+        .await?;
+
+        Ok(last_read_at.map(|ms| Timestamp::from((ms / 1000) as u64)))
+    }
+
+    /// Count unread cached chat messages in each of the given groups in one query
+    ///
+    /// A message is unread when it was created after the account's read position in its
+    /// group (or the group was never marked read), was not sent by the account itself and
+    /// has not been deleted. Every requested group is present in the result.
+    pub(crate) async fn unread_counts(
+        account_id: i64,
+        account_pubkey: &PublicKey,
+        group_ids: &[GroupId],
+        database: &Database,
+    ) -> Result<HashMap<GroupId, u64>> {
+        let mut counts: HashMap<GroupId, u64> =
+            group_ids.iter().map(|id| (id.clone(), 0)).collect();
+        if group_ids.is_empty() {
+            return Ok(counts);
+        }
+
+        // Build dynamic query with correct number of placeholders
+        let placeholders = "?,".repeat(group_ids.len());
+        let placeholders = placeholders.trim_end_matches(',');
+
+        let query = format!(
+            "SELECT am.mls_group_id, COUNT(*)
+             FROM aggregated_messages am
+             LEFT JOIN group_read_state rs
+               ON rs.mls_group_id = am.mls_group_id AND rs.account_id = ?
+             WHERE am.kind = 9
+               AND am.author != ?
+               AND am.deletion_event_id IS NULL
+               AND am.created_at > COALESCE(rs.last_read_at, -1)
+               AND am.mls_group_id IN ({})
+             GROUP BY am.mls_group_id",
+            placeholders
+        );
+
+        let mut query_builder = sqlx::query_as::<_, (Vec<u8>, i64)>(&query)
+            .bind(account_id)
+            .bind(account_pubkey.to_hex());
+        for group_id in group_ids {
+            query_builder = query_builder.bind(group_id.as_slice());
+        }
+
+        for (group_id_bytes, count) in query_builder.fetch_all(&database.pool).await? {
+            counts.insert(GroupId::from_slice(&group_id_bytes), count as u64);
+        }
+
+        Ok(counts)
+    }
+
+    /// Read positions are stored in milliseconds like `aggregated_messages.created_at`
+    fn to_millis(timestamp: Timestamp) -> i64 {
+        (timestamp.as_u64() as i64).saturating_mul(1000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::aggregated_message::AggregatedMessage;
+    use crate::whitenoise::group_information::{GroupInformation, GroupType};
+    use crate::whitenoise::message_aggregator::ChatMessage;
+    use crate::whitenoise::test_utils::create_mock_whitenoise;
+
+    fn chat_message(seed: u8, author: PublicKey, created_at: u64) -> ChatMessage {
+        ChatMessage::test_message(&format!("{seed:064x}"), "Test message", created_at)
+            .with_author(author)
+    }
+
+    #[tokio::test]
+    async fn test_mark_read_never_moves_backward() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let account_id = account.id.unwrap();
+        let group_id = GroupId::from_slice(&[1; 32]);
+
+        assert_eq!(
+            GroupReadState::last_read_at(account_id, &group_id, &whitenoise.database)
+                .await
+                .unwrap(),
+            None
+        );
+
+        let position = GroupReadState::mark_read(
+            account_id,
+            &group_id,
+            Timestamp::from(2000),
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        assert_eq!(position, Timestamp::from(2000));
+
+        let position = GroupReadState::mark_read(
+            account_id,
+            &group_id,
+            Timestamp::from(1000),
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        assert_eq!(position, Timestamp::from(2000));
+        assert_eq!(
+            GroupReadState::last_read_at(account_id, &group_id, &whitenoise.database)
+                .await
+                .unwrap(),
+            Some(Timestamp::from(2000))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unread_counts_skip_read_own_and_deleted_messages() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let account_id = account.id.unwrap();
+        let other = Keys::generate().public_key();
+        let read_group = GroupId::from_slice(&[1; 32]);
+        let unread_group = GroupId::from_slice(&[2; 32]);
+        let empty_group = GroupId::from_slice(&[3; 32]);
+
+        for group_id in [&read_group, &unread_group] {
+            GroupInformation::find_or_create_by_mls_group_id(
+                group_id,
+                Some(GroupType::Group),
+                &whitenoise.database,
+            )
+            .await
+            .unwrap();
+        }
+
+        let messages = [
+            (&read_group, chat_message(1, other, 1000)),
+            (&read_group, chat_message(2, other, 3000)),
+            (&read_group, chat_message(3, account.pubkey, 3001)),
+            (&read_group, chat_message(4, other, 3002)),
+            (&unread_group, chat_message(5, other, 1000)),
+        ];
+        for (group_id, message) in &messages {
+            AggregatedMessage::insert_message(message, group_id, &whitenoise.database)
+                .await
+                .unwrap();
+        }
+        AggregatedMessage::mark_deleted(
+            &messages[3].1.id,
+            &read_group,
+            &format!("{:0>64}", "ff"),
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+
+        GroupReadState::mark_read(
+            account_id,
+            &read_group,
+            Timestamp::from(2000),
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+
+        let counts = GroupReadState::unread_counts(
+            account_id,
+            &account.pubkey,
+            &[
+                read_group.clone(),
+                unread_group.clone(),
+                empty_group.clone(),
+            ],
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+
+        // Only the other member's message at 3000 is unread; the own and deleted ones are not
+        assert_eq!(counts[&read_group], 1);
+        assert_eq!(counts[&unread_group], 1);
+        assert_eq!(counts[&empty_group], 0);
+    }
+}
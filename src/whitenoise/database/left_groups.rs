@@ -0,0 +1,55 @@
+use mdk_core::prelude::GroupId;
+
+use super::{Database, DatabaseError};
+
+type Result<T> = std::result::Result<T, DatabaseError>;
+
+/// Groups each account left while MDK still lists them as active
+///
+/// A group stays here until the account joins it again, so a later resubscribe doesn't pick
+/// it back up before an admin commits the removal.
+pub(crate) struct LeftGroups;
+
+impl LeftGroups {
+    /// Record that the account left the group
+    pub(crate) async fn mark(
+        account_id: i64,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT OR IGNORE INTO left_groups (account_id, mls_group_id)
+             VALUES (?, ?)",
+        )
+        .bind(account_id)
+        .bind(group_id.as_slice())
+        .execute(&database.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Forget that the account left the group, once it joined again
+    pub(crate) async fn unmark(
+        account_id: i64,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<()> {
+        sqlx::query("DELETE FROM left_groups WHERE account_id = ? AND mls_group_id = ?")
+            .bind(account_id)
+            .bind(group_id.as_slice())
+            .execute(&database.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// The groups the account left
+    pub(crate) async fn find(account_id: i64, database: &Database) -> Result<Vec<GroupId>> {
+        let group_ids: Vec<Vec<u8>> =
+            sqlx::query_scalar("SELECT mls_group_id FROM left_groups WHERE account_id = ?")
+                .bind(account_id)
+                .fetch_all(&database.pool)
+                .await?;
+
+        Ok(group_ids.iter().map(|id| GroupId::from_slice(id)).collect())
+    }
+}
@@ -338,6 +338,40 @@ impl MediaFile {
         Ok(rows.into_iter().map(Into::into).collect())
     }
 
+    /// Finds all media files of a given type recorded for an account in a group
+    ///
+    /// Results are ordered by record creation time, oldest first.
+    ///
+    /// # Arguments
+    /// * `database` - Database connection
+    /// * `group_id` - The MLS group ID to fetch media files for
+    /// * `account_pubkey` - The account the records belong to
+    /// * `media_type` - The media type to filter on (e.g. "chat_media")
+    pub(crate) async fn find_by_group_account_and_type(
+        database: &Database,
+        group_id: &GroupId,
+        account_pubkey: &PublicKey,
+        media_type: &str,
+    ) -> Result<Vec<Self>, WhitenoiseError> {
+        let rows = sqlx::query_as::<_, MediaFileRow>(
+            "SELECT id, mls_group_id, account_pubkey, file_path,
+                    original_file_hash, encrypted_file_hash,
+                    mime_type, media_type, blossom_url, nostr_key,
+                    file_metadata, created_at
+             FROM media_files
+             WHERE mls_group_id = ? AND account_pubkey = ? AND media_type = ?
+             ORDER BY created_at ASC, id ASC",
+        )
+        .bind(group_id.as_slice())
+        .bind(account_pubkey.to_hex())
+        .bind(media_type)
+        .fetch_all(&database.pool)
+        .await
+        .map_err(DatabaseError::Sqlx)?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
     /// Finds a media file by original hash, group ID, and account (MIP-04 compliant lookup)
     ///
     /// This is the primary lookup method for media files referenced in imeta tags,
@@ -450,6 +484,48 @@ impl MediaFile {
         Ok(row.into())
     }
 
+    /// Deletes all media file records of an account
+    ///
+    /// Several accounts in the same group share one cached file, so only the paths of files
+    /// no other account still references are returned for removal from the cache.
+    ///
+    /// # Arguments
+    /// * `database` - The database connection
+    /// * `account_pubkey` - The account whose records to delete
+    pub(crate) async fn delete_by_account(
+        database: &Database,
+        account_pubkey: &PublicKey,
+    ) -> Result<Vec<PathBuf>, WhitenoiseError> {
+        let account_pubkey_hex = account_pubkey.to_hex();
+        let mut tx = database.pool.begin().await.map_err(DatabaseError::Sqlx)?;
+
+        let file_paths: Vec<String> = sqlx::query_scalar(
+            "DELETE FROM media_files WHERE account_pubkey = ? RETURNING file_path",
+        )
+        .bind(&account_pubkey_hex)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(DatabaseError::Sqlx)?;
+
+        let mut orphaned = Vec::new();
+        for file_path in file_paths {
+            let still_used: bool =
+                sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM media_files WHERE file_path = ?)")
+                    .bind(&file_path)
+                    .fetch_one(&mut *tx)
+                    .await
+                    .map_err(DatabaseError::Sqlx)?;
+            if !still_used {
+                orphaned.push(PathBuf::from(file_path));
+            }
+        }
+
+        tx.commit().await.map_err(DatabaseError::Sqlx)?;
+        orphaned.sort();
+        orphaned.dedup();
+        Ok(orphaned)
+    }
+
     /// Check if this media file is an image
     pub fn is_image(&self) -> bool {
         self.mime_type.starts_with("image/")
@@ -730,6 +806,51 @@ mod tests {
         assert!(found.is_none());
     }
 
+    #[tokio::test]
+    async fn test_find_by_group_account_and_type_filters_records() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = Database::new(db_path).await.unwrap();
+
+        let group_id = mdk_core::GroupId::from_slice(&[3u8; 8]);
+        let pubkey1 = PublicKey::from_slice(&[30u8; 32]).unwrap();
+        let pubkey2 = PublicKey::from_slice(&[40u8; 32]).unwrap();
+        create_test_account(&db, &pubkey1).await;
+        create_test_account(&db, &pubkey2).await;
+
+        for (pubkey, encrypted_hash, media_type) in [
+            (&pubkey1, [31u8; 32], "chat_media"),
+            (&pubkey1, [32u8; 32], "group_image"),
+            (&pubkey2, [33u8; 32], "chat_media"),
+        ] {
+            MediaFile::save(
+                &db,
+                &group_id,
+                pubkey,
+                MediaFileParams {
+                    file_path: &PathBuf::from(""),
+                    original_file_hash: None,
+                    encrypted_file_hash: &encrypted_hash,
+                    mime_type: "image/png",
+                    media_type,
+                    blossom_url: None,
+                    nostr_key: None,
+                    file_metadata: None,
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let media_files =
+            MediaFile::find_by_group_account_and_type(&db, &group_id, &pubkey1, "chat_media")
+                .await
+                .unwrap();
+
+        assert_eq!(media_files.len(), 1);
+        assert_eq!(media_files[0].encrypted_file_hash, [31u8; 32].to_vec());
+    }
+
     #[tokio::test]
     async fn test_find_by_group_empty_result() {
         let temp_dir = TempDir::new().unwrap();
@@ -1,23 +1,34 @@
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::LazyLock,
     time::{Duration, SystemTime},
 };
 
 use sqlx::{
-    Sqlite, SqlitePool,
+    Connection, Sqlite, SqliteConnection, SqlitePool,
     migrate::{MigrateDatabase, Migrator},
     sqlite::SqlitePoolOptions,
 };
 use thiserror::Error;
 
+pub mod account_mutes;
 pub mod accounts;
 pub mod aggregated_messages;
 pub mod app_settings;
+pub mod direct_messages;
+pub mod drafts;
+#[cfg(feature = "sqlcipher")]
+mod encryption;
 pub mod group_information;
+pub mod group_notification_settings;
+pub mod group_read_state;
+pub mod left_groups;
 pub mod media_files;
+pub mod outbox;
+pub mod pinned_conversations;
 pub mod processed_events;
 pub mod published_events;
+pub mod relay_sync_watermarks;
 pub mod relays;
 pub mod user_relays;
 pub mod users;
@@ -27,7 +38,42 @@ pub static MIGRATOR: LazyLock<Migrator> = LazyLock::new(|| sqlx::migrate!("./db_
 
 const DB_ACQUIRE_TIMEOUT_SECS: u64 = 5;
 const DB_MAX_CONNECTIONS: u32 = 10;
-const DB_BUSY_TIMEOUT_MS: u32 = 5000;
+const DB_BUSY_TIMEOUT_MS: u64 = 5000;
+
+// SQLite primary result codes, see https://www.sqlite.org/rescode.html
+const SQLITE_BUSY: i64 = 5;
+const SQLITE_LOCKED: i64 = 6;
+const SQLITE_CORRUPT: i64 = 11;
+const SQLITE_NOTADB: i64 = 26;
+
+/// Connection pool settings for the SQLite database
+///
+/// SQLite lets many connections read at once but only one write at a time, so a bigger pool
+/// helps read-heavy workloads (many accounts, many open chats) but not writes, and every
+/// connection keeps its own page cache in memory. Longer timeouts ride out bursts of
+/// contention instead of failing queries, at the cost of callers waiting longer before an
+/// error surfaces when the database really is stuck.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabasePoolConfig {
+    /// Most connections open at once; must be at least 1
+    pub max_connections: u32,
+
+    /// How long a query waits for a free connection before failing with a pool timeout
+    pub acquire_timeout: Duration,
+
+    /// How long a connection waits for another connection's lock before failing as busy
+    pub busy_timeout: Duration,
+}
+
+impl Default for DatabasePoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: DB_MAX_CONNECTIONS,
+            acquire_timeout: Duration::from_secs(DB_ACQUIRE_TIMEOUT_SECS),
+            busy_timeout: Duration::from_millis(DB_BUSY_TIMEOUT_MS),
+        }
+    }
+}
 
 #[derive(Error, Debug)]
 pub enum DatabaseError {
@@ -41,6 +87,35 @@ pub enum DatabaseError {
     InvalidTimestamp { timestamp: i64 },
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+    #[error("Failed to decrypt database: wrong key or not an encrypted database")]
+    DecryptionFailed,
+}
+
+impl DatabaseError {
+    /// Whether another connection or process holds a lock on the database
+    pub fn is_locked(&self) -> bool {
+        self.sqlite_primary_code()
+            .is_some_and(|code| code == SQLITE_BUSY || code == SQLITE_LOCKED)
+    }
+
+    /// Whether SQLite found the database file damaged or not a database at all
+    pub fn is_corrupt(&self) -> bool {
+        self.sqlite_primary_code()
+            .is_some_and(|code| code == SQLITE_CORRUPT || code == SQLITE_NOTADB)
+    }
+
+    /// Primary SQLite result code of the underlying error, without the extended bits
+    fn sqlite_primary_code(&self) -> Option<i64> {
+        let err = match self {
+            DatabaseError::Sqlx(err) => err,
+            DatabaseError::Migration(sqlx::migrate::MigrateError::Execute(err)) => err,
+            _ => return None,
+        };
+        err.as_database_error()
+            .and_then(|e| e.code())
+            .and_then(|code| code.parse::<i64>().ok())
+            .map(|code| code & 0xff)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -52,6 +127,40 @@ pub struct Database {
 
 impl Database {
     pub async fn new(db_path: PathBuf) -> Result<Self, DatabaseError> {
+        Self::open(db_path, None, &DatabasePoolConfig::default()).await
+    }
+
+    /// Opens the database like [`Self::new`], with custom connection pool settings
+    pub async fn new_with_pool_config(
+        db_path: PathBuf,
+        pool_config: &DatabasePoolConfig,
+    ) -> Result<Self, DatabaseError> {
+        Self::open(db_path, None, pool_config).await
+    }
+
+    /// Opens a database encrypted at rest with SQLCipher, creating it if it doesn't exist
+    ///
+    /// An existing plaintext database at `db_path` is encrypted with the passphrase first,
+    /// a one-time migration for databases created before encryption was enabled. Opening
+    /// an encrypted database with the wrong passphrase fails with
+    /// [`DatabaseError::DecryptionFailed`].
+    #[cfg(feature = "sqlcipher")]
+    pub async fn new_encrypted(
+        db_path: PathBuf,
+        passphrase: &str,
+        pool_config: &DatabasePoolConfig,
+    ) -> Result<Self, DatabaseError> {
+        if encryption::is_plaintext_database(&db_path).await? {
+            encryption::encrypt_plaintext_database(&db_path, passphrase).await?;
+        }
+        Self::open(db_path, Some(passphrase.to_string()), pool_config).await
+    }
+
+    async fn open(
+        db_path: PathBuf,
+        passphrase: Option<String>,
+        pool_config: &DatabasePoolConfig,
+    ) -> Result<Self, DatabaseError> {
         // Create parent directories if they don't exist
         if let Some(parent) = db_path.parent() {
             std::fs::create_dir_all(parent)?;
@@ -85,7 +194,15 @@ impl Database {
             }
         }
 
-        let pool = Self::create_connection_pool(&db_url).await?;
+        let is_encrypted = passphrase.is_some();
+        let pool = Self::create_connection_pool(&db_url, passphrase, pool_config)
+            .await
+            .map_err(|e| match e {
+                DatabaseError::Sqlx(ref err) if is_encrypted && is_not_a_database(err) => {
+                    DatabaseError::DecryptionFailed
+                }
+                e => e,
+            })?;
 
         // Automatically run migrations
         MIGRATOR.run(&pool).await?;
@@ -98,20 +215,35 @@ impl Database {
     }
 
     /// Creates and configures a SQLite connection pool
-    async fn create_connection_pool(db_url: &str) -> Result<SqlitePool, DatabaseError> {
+    ///
+    /// With a passphrase, every connection is keyed for SQLCipher before anything else runs
+    /// on it. Without SQLCipher the key pragma is ignored.
+    async fn create_connection_pool(
+        db_url: &str,
+        passphrase: Option<String>,
+        pool_config: &DatabasePoolConfig,
+    ) -> Result<SqlitePool, DatabaseError> {
         tracing::debug!("Creating connection pool...");
+        let busy_timeout_ms = pool_config.busy_timeout.as_millis();
         let pool = SqlitePoolOptions::new()
-            .acquire_timeout(Duration::from_secs(DB_ACQUIRE_TIMEOUT_SECS))
-            .max_connections(DB_MAX_CONNECTIONS)
-            .after_connect(|conn, _| {
+            .acquire_timeout(pool_config.acquire_timeout)
+            .max_connections(pool_config.max_connections)
+            .after_connect(move |conn, _| {
+                let passphrase = passphrase.clone();
                 Box::pin(async move {
                     let conn = &mut *conn;
+                    // The key must be set before the first read of the database
+                    if let Some(passphrase) = passphrase {
+                        sqlx::query(&key_pragma(&passphrase))
+                            .execute(&mut *conn)
+                            .await?;
+                    }
                     // Enable WAL mode for better concurrent access
                     sqlx::query("PRAGMA journal_mode=WAL")
                         .execute(&mut *conn)
                         .await?;
                     // Set busy timeout for lock contention
-                    sqlx::query(&format!("PRAGMA busy_timeout={DB_BUSY_TIMEOUT_MS}"))
+                    sqlx::query(&format!("PRAGMA busy_timeout={busy_timeout_ms}"))
                         .execute(&mut *conn)
                         .await?;
                     // Enable foreign keys and triggers
@@ -138,6 +270,124 @@ impl Database {
         Ok(())
     }
 
+    /// Number of migrations shipped with this build that haven't been applied to the database
+    pub async fn pending_migrations(&self) -> Result<usize, DatabaseError> {
+        let applied: Vec<i64> =
+            sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success = 1")
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(MIGRATOR
+            .iter()
+            .filter(|migration| !applied.contains(&migration.version))
+            .count())
+    }
+
+    /// Writes a consistent, unencrypted snapshot of the database to a new file at `dest`
+    ///
+    /// Uses `VACUUM INTO` (or `sqlcipher_export` with SQLCipher), which reads the database
+    /// through SQLite and so includes every transaction committed to the WAL. This makes it
+    /// safe to run while other connections keep writing. `dest` must not exist yet.
+    pub async fn snapshot_into(&self, dest: &Path) -> Result<(), DatabaseError> {
+        #[cfg(feature = "sqlcipher")]
+        encryption::export_plaintext(&self.pool, dest).await?;
+
+        #[cfg(not(feature = "sqlcipher"))]
+        sqlx::query("VACUUM INTO ?")
+            .bind(dest.to_string_lossy().into_owned())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Replaces the contents of every table with those of a snapshot taken by [`Self::snapshot_into`]
+    ///
+    /// The snapshot is migrated to the current schema first, so snapshots taken by older
+    /// versions can be restored. All tables are replaced in a single transaction.
+    pub async fn restore_from(&self, snapshot: &Path) -> Result<(), DatabaseError> {
+        let snapshot_db = Database::new(snapshot.to_path_buf()).await?;
+        snapshot_db.pool.close().await;
+
+        let mut conn = self.pool.acquire().await?;
+
+        // Foreign keys can't be toggled inside a transaction, and rows are copied table by table
+        sqlx::query("PRAGMA foreign_keys = OFF")
+            .execute(&mut *conn)
+            .await?;
+        // Snapshots are unencrypted; the empty key keeps SQLCipher from using the main key
+        sqlx::query("ATTACH DATABASE ? AS snapshot KEY ''")
+            .bind(snapshot.to_string_lossy().into_owned())
+            .execute(&mut *conn)
+            .await?;
+
+        let result = Self::copy_tables_from_snapshot(&mut conn).await;
+
+        sqlx::query("DETACH DATABASE snapshot")
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("PRAGMA foreign_keys = ON")
+            .execute(&mut *conn)
+            .await?;
+        result
+    }
+
+    async fn copy_tables_from_snapshot(conn: &mut SqliteConnection) -> Result<(), DatabaseError> {
+        let mut txn = conn.begin().await?;
+
+        let tables: Vec<(String,)> = sqlx::query_as(
+            "SELECT name FROM main.sqlite_master
+             WHERE type='table'
+             AND name NOT LIKE 'sqlite_%'
+             AND name != '_sqlx_migrations'",
+        )
+        .fetch_all(&mut *txn)
+        .await?;
+
+        for (table_name,) in tables {
+            sqlx::query(&format!("DELETE FROM main.\"{table_name}\""))
+                .execute(&mut *txn)
+                .await?;
+            sqlx::query(&format!(
+                "INSERT INTO main.\"{table_name}\" SELECT * FROM snapshot.\"{table_name}\""
+            ))
+            .execute(&mut *txn)
+            .await?;
+        }
+
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Moves a database file that can't be opened out of the way
+    ///
+    /// The file and its write-ahead log are renamed to `<name>.corrupt-<unix time>`, so a
+    /// fresh database can be created at `db_path` while the old data stays around for
+    /// inspection. Returns the path of the backup.
+    pub async fn move_aside(db_path: &Path) -> Result<PathBuf, DatabaseError> {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut backup = db_path.as_os_str().to_owned();
+        backup.push(format!(".corrupt-{timestamp}"));
+        let backup = PathBuf::from(backup);
+
+        tokio::fs::rename(db_path, &backup).await?;
+        for suffix in ["-wal", "-shm"] {
+            let mut sidecar = db_path.as_os_str().to_owned();
+            sidecar.push(suffix);
+            let mut sidecar_backup = backup.as_os_str().to_owned();
+            sidecar_backup.push(suffix);
+            match tokio::fs::rename(&sidecar, &sidecar_backup).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(backup)
+    }
+
     /// Deletes all data by dropping and recreating all tables
     ///
     /// This method:
@@ -183,6 +433,19 @@ impl Database {
     }
 }
 
+/// `PRAGMA key` statement for a passphrase; pragmas can't take bound parameters
+fn key_pragma(passphrase: &str) -> String {
+    format!("PRAGMA key = '{}'", passphrase.replace('\'', "''"))
+}
+
+/// Whether SQLite refused a file because it isn't a database, which is how SQLCipher reports
+/// a wrong key (`SQLITE_NOTADB`)
+fn is_not_a_database(err: &sqlx::Error) -> bool {
+    err.as_database_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == "26")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -198,6 +461,30 @@ mod tests {
         (db, temp_dir)
     }
 
+    #[tokio::test]
+    async fn test_database_uses_pool_config() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let pool_config = DatabasePoolConfig {
+            max_connections: 3,
+            acquire_timeout: Duration::from_secs(1),
+            busy_timeout: Duration::from_millis(250),
+        };
+        let db = Database::new_with_pool_config(temp_dir.path().join("test.db"), &pool_config)
+            .await
+            .unwrap();
+
+        assert_eq!(db.pool.options().get_max_connections(), 3);
+        assert_eq!(
+            db.pool.options().get_acquire_timeout(),
+            Duration::from_secs(1)
+        );
+        let busy_timeout: i64 = sqlx::query_scalar("PRAGMA busy_timeout")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(busy_timeout, 250);
+    }
+
     #[tokio::test]
     async fn test_database_creation() {
         let temp_dir = TempDir::new().expect("Failed to create temp directory");
@@ -226,6 +513,22 @@ mod tests {
         assert!(db_path.exists());
     }
 
+    #[tokio::test]
+    async fn test_garbage_file_is_reported_as_corrupt_and_can_be_moved_aside() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let db_path = temp_dir.path().join("test.db");
+        std::fs::write(&db_path, vec![0x42; 8192]).unwrap();
+
+        let err = Database::new(db_path.clone()).await.unwrap_err();
+        assert!(err.is_corrupt(), "unexpected error: {err:?}");
+        assert!(!err.is_locked());
+
+        let backup = Database::move_aside(&db_path).await.unwrap();
+        assert!(!db_path.exists());
+        assert_eq!(std::fs::read(&backup).unwrap(), vec![0x42; 8192]);
+        assert!(Database::new(db_path).await.is_ok());
+    }
+
     #[tokio::test]
     async fn test_database_migrations_applied() {
         let (db, _temp_dir) = create_test_db().await;
@@ -471,6 +774,18 @@ mod tests {
         assert!(table_names.contains(&"app_settings".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_pending_migrations() {
+        let (db, _temp_dir) = create_test_db().await;
+        assert_eq!(db.pending_migrations().await.unwrap(), 0);
+
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(db.pending_migrations().await.unwrap(), 1);
+    }
+
     #[tokio::test]
     async fn test_database_clone() {
         let (db, _temp_dir) = create_test_db().await;
@@ -495,4 +810,9 @@ mod tests {
         assert_eq!(result1.0, 1);
         assert_eq!(result2.0, 2);
     }
+
+    #[test]
+    fn test_key_pragma_escapes_quotes() {
+        assert_eq!(key_pragma("it's"), "PRAGMA key = 'it''s'");
+    }
 }
@@ -0,0 +1,189 @@
+use mdk_core::prelude::GroupId;
+use nostr_sdk::prelude::*;
+
+use super::{Database, DatabaseError};
+
+type Result<T> = std::result::Result<T, DatabaseError>;
+
+/// A chat message the account sent that no relay accepted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct OutboxEntry {
+    pub mls_group_id: GroupId,
+    pub event_id: EventId,
+    pub kind: Kind,
+    pub content: String,
+    pub tags: Tags,
+    pub created_at: Timestamp,
+
+    /// Number of times publishing the message failed
+    pub attempts: u32,
+}
+
+impl OutboxEntry {
+    /// Re-create the inner event of the message as the account originally signed off on it
+    ///
+    /// The event has the same ID as the original, so receivers and the message cache treat
+    /// a retried message as the original one.
+    pub(crate) fn unsigned_event(&self, author: PublicKey) -> UnsignedEvent {
+        let mut event = UnsignedEvent::new(
+            author,
+            self.created_at,
+            self.kind,
+            self.tags.clone(),
+            self.content.clone(),
+        );
+        event.ensure_id();
+        event
+    }
+}
+
+/// Outgoing messages per account that are waiting to be published again
+pub(crate) struct Outbox;
+
+impl Outbox {
+    /// Record a message that failed to publish, returning whether it wasn't queued yet
+    ///
+    /// Recording an already queued message counts another failed attempt.
+    pub(crate) async fn add(
+        account_id: i64,
+        group_id: &GroupId,
+        event: &UnsignedEvent,
+        database: &Database,
+    ) -> Result<bool> {
+        let event_id = event.id.unwrap_or_else(|| {
+            EventId::new(
+                &event.pubkey,
+                &event.created_at,
+                &event.kind,
+                &event.tags,
+                &event.content,
+            )
+        });
+        let inserted: bool = sqlx::query_scalar(
+            "INSERT INTO outbox
+               (account_id, mls_group_id, event_id, kind, content, tags, event_created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(account_id, event_id) DO UPDATE SET
+               attempts = attempts + 1,
+               updated_at = CURRENT_TIMESTAMP
+             RETURNING attempts = 1",
+        )
+        .bind(account_id)
+        .bind(group_id.as_slice())
+        .bind(event_id.to_hex())
+        .bind(event.kind.as_u16() as i64)
+        .bind(&event.content)
+        .bind(serde_json::to_string(&event.tags)?)
+        .bind(event.created_at.as_u64() as i64)
+        .fetch_one(&database.pool)
+        .await?;
+        Ok(inserted)
+    }
+
+    /// Remove a message from the account's outbox, returning whether it was queued
+    pub(crate) async fn remove(
+        account_id: i64,
+        event_id: &EventId,
+        database: &Database,
+    ) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM outbox WHERE account_id = ? AND event_id = ?")
+            .bind(account_id)
+            .bind(event_id.to_hex())
+            .execute(&database.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// All messages in the account's outbox, in the order they were sent
+    pub(crate) async fn find_by_account(
+        account_id: i64,
+        database: &Database,
+    ) -> Result<Vec<OutboxEntry>> {
+        let rows: Vec<(Vec<u8>, String, i64, String, String, i64, i64)> = sqlx::query_as(
+            "SELECT mls_group_id, event_id, kind, content, tags, event_created_at, attempts
+             FROM outbox WHERE account_id = ?
+             ORDER BY event_created_at, id",
+        )
+        .bind(account_id)
+        .fetch_all(&database.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(
+                |(group_id, event_id, kind, content, tags, created_at, attempts)| {
+                    Ok(OutboxEntry {
+                        mls_group_id: GroupId::from_slice(&group_id),
+                        event_id: EventId::from_hex(&event_id)
+                            .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                        kind: Kind::from(kind as u16),
+                        content,
+                        tags: serde_json::from_str(&tags)?,
+                        created_at: Timestamp::from(created_at as u64),
+                        attempts: attempts as u32,
+                    })
+                },
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::create_mock_whitenoise;
+
+    #[tokio::test]
+    async fn test_outbox_entries_round_trip() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let account_id = account.id.unwrap();
+        let db = &whitenoise.database;
+        let group_id = GroupId::from_slice(&[1; 32]);
+
+        let mut event = UnsignedEvent::new(
+            account.pubkey,
+            Timestamp::from(1000),
+            Kind::Custom(9),
+            vec![Tag::hashtag("outbox")],
+            "Hello",
+        );
+        event.ensure_id();
+
+        assert!(
+            Outbox::add(account_id, &group_id, &event, db)
+                .await
+                .unwrap()
+        );
+        // Failing again only counts the attempt
+        assert!(
+            !Outbox::add(account_id, &group_id, &event, db)
+                .await
+                .unwrap()
+        );
+
+        let entries = Outbox::find_by_account(account_id, db).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.mls_group_id, group_id);
+        assert_eq!(entry.attempts, 2);
+        // The re-created event is identical to the original
+        assert_eq!(entry.unsigned_event(account.pubkey).id, event.id);
+
+        assert!(
+            Outbox::remove(account_id, &entry.event_id, db)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !Outbox::remove(account_id, &entry.event_id, db)
+                .await
+                .unwrap()
+        );
+        assert!(
+            Outbox::find_by_account(account_id, db)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
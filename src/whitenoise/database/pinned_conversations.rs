@@ -0,0 +1,170 @@
+use mdk_core::prelude::GroupId;
+
+use super::{Database, DatabaseError};
+
+type Result<T> = std::result::Result<T, DatabaseError>;
+
+/// Groups each account pinned to the top of its chat list, in order
+///
+/// Positions are kept contiguous from 0, so they can be used as indexes.
+pub(crate) struct PinnedConversations;
+
+impl PinnedConversations {
+    /// The account's pinned groups, first pin first
+    pub(crate) async fn find(account_id: i64, database: &Database) -> Result<Vec<GroupId>> {
+        let group_ids: Vec<Vec<u8>> = sqlx::query_scalar(
+            "SELECT mls_group_id FROM pinned_conversations
+             WHERE account_id = ?
+             ORDER BY position",
+        )
+        .bind(account_id)
+        .fetch_all(&database.pool)
+        .await?;
+
+        Ok(group_ids.iter().map(|id| GroupId::from_slice(id)).collect())
+    }
+
+    /// Pin the group at `position`, moving it there if it is already pinned
+    ///
+    /// Pins at or after `position` move down one place; positions past the end pin the
+    /// group last.
+    pub(crate) async fn pin(
+        account_id: i64,
+        group_id: &GroupId,
+        position: usize,
+        database: &Database,
+    ) -> Result<()> {
+        Self::update(account_id, database, |group_ids| {
+            group_ids.retain(|id| id != group_id);
+            group_ids.insert(position.min(group_ids.len()), group_id.clone());
+        })
+        .await
+    }
+
+    /// Unpin the group, closing the gap it leaves; does nothing if it isn't pinned
+    pub(crate) async fn unpin(
+        account_id: i64,
+        group_id: &GroupId,
+        database: &Database,
+    ) -> Result<()> {
+        Self::update(account_id, database, |group_ids| {
+            group_ids.retain(|id| id != group_id)
+        })
+        .await
+    }
+
+    /// Apply `update` to the account's pins, in order, in one transaction
+    ///
+    /// The pins are read by deleting them, which takes the write lock before they are read,
+    /// so concurrent updates wait for each other instead of overwriting each other.
+    async fn update(
+        account_id: i64,
+        database: &Database,
+        update: impl FnOnce(&mut Vec<GroupId>),
+    ) -> Result<()> {
+        let mut tx = database.pool.begin().await?;
+        let mut pins: Vec<(Vec<u8>, i64)> = sqlx::query_as(
+            "DELETE FROM pinned_conversations WHERE account_id = ?
+             RETURNING mls_group_id, position",
+        )
+        .bind(account_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        pins.sort_by_key(|(_, position)| *position);
+
+        let mut group_ids: Vec<GroupId> =
+            pins.iter().map(|(id, _)| GroupId::from_slice(id)).collect();
+        update(&mut group_ids);
+
+        for (position, group_id) in group_ids.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO pinned_conversations (account_id, mls_group_id, position)
+                 VALUES (?, ?, ?)",
+            )
+            .bind(account_id)
+            .bind(group_id.as_slice())
+            .bind(position as i64)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::create_mock_whitenoise;
+
+    #[tokio::test]
+    async fn test_pin_reorder_and_unpin() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let account_id = account.id.unwrap();
+        let database = &whitenoise.database;
+        let [a, b, c] = [1u8, 2, 3].map(|byte| GroupId::from_slice(&[byte; 32]));
+
+        PinnedConversations::pin(account_id, &a, 0, database)
+            .await
+            .unwrap();
+        PinnedConversations::pin(account_id, &b, 0, database)
+            .await
+            .unwrap();
+        PinnedConversations::pin(account_id, &c, 99, database)
+            .await
+            .unwrap();
+        assert_eq!(
+            PinnedConversations::find(account_id, database)
+                .await
+                .unwrap(),
+            vec![b.clone(), a.clone(), c.clone()]
+        );
+
+        // Moving a pin shifts the others instead of duplicating it
+        PinnedConversations::pin(account_id, &c, 0, database)
+            .await
+            .unwrap();
+        assert_eq!(
+            PinnedConversations::find(account_id, database)
+                .await
+                .unwrap(),
+            vec![c.clone(), b.clone(), a.clone()]
+        );
+
+        PinnedConversations::unpin(account_id, &b, database)
+            .await
+            .unwrap();
+        assert_eq!(
+            PinnedConversations::find(account_id, database)
+                .await
+                .unwrap(),
+            vec![c, a]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_pins_are_all_kept() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let account_id = account.id.unwrap();
+        let database = &whitenoise.database;
+        let group_ids: Vec<GroupId> = (1u8..=5)
+            .map(|byte| GroupId::from_slice(&[byte; 32]))
+            .collect();
+
+        futures::future::try_join_all(
+            group_ids
+                .iter()
+                .map(|group_id| PinnedConversations::pin(account_id, group_id, 0, database)),
+        )
+        .await
+        .unwrap();
+
+        let pinned = PinnedConversations::find(account_id, database)
+            .await
+            .unwrap();
+        assert_eq!(pinned.len(), group_ids.len());
+        assert!(group_ids.iter().all(|group_id| pinned.contains(group_id)));
+    }
+}
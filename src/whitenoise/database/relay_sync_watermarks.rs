@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use nostr_sdk::prelude::*;
+
+use super::{Database, DatabaseError};
+use crate::nostr_manager::SubscriptionCategory;
+
+type Result<T> = std::result::Result<T, DatabaseError>;
+
+/// Newest event timestamp each relay delivered to each of an account's subscriptions, counting
+/// only events that were processed
+pub(crate) struct RelaySyncWatermarks;
+
+impl RelaySyncWatermarks {
+    /// Move the watermark of `subscription` on `relay_url` forward to `seen_at`; older
+    /// timestamps are ignored
+    pub(crate) async fn advance(
+        account_id: i64,
+        subscription: SubscriptionCategory,
+        relay_url: &RelayUrl,
+        seen_at: Timestamp,
+        database: &Database,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO relay_sync_watermarks (account_id, subscription, relay_url, last_seen_at)
+             VALUES (?, ?, ?, ?)
+             ON CONFLICT(account_id, subscription, relay_url) DO UPDATE SET
+               last_seen_at = MAX(last_seen_at, excluded.last_seen_at)",
+        )
+        .bind(account_id)
+        .bind(subscription.as_str())
+        .bind(relay_url.as_str())
+        .bind(seen_at.as_u64() as i64)
+        .execute(&database.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// The watermarks of every relay that delivered events to the account, per subscription
+    pub(crate) async fn find_by_account(
+        account_id: i64,
+        database: &Database,
+    ) -> Result<HashMap<SubscriptionCategory, HashMap<RelayUrl, Timestamp>>> {
+        let rows: Vec<(String, String, i64)> = sqlx::query_as(
+            "SELECT subscription, relay_url, last_seen_at FROM relay_sync_watermarks
+             WHERE account_id = ?",
+        )
+        .bind(account_id)
+        .fetch_all(&database.pool)
+        .await?;
+
+        let mut watermarks: HashMap<SubscriptionCategory, HashMap<RelayUrl, Timestamp>> =
+            HashMap::new();
+        for (subscription, relay_url, last_seen_at) in rows {
+            let (Some(subscription), Ok(relay_url)) = (
+                SubscriptionCategory::parse(&subscription),
+                RelayUrl::parse(&relay_url),
+            ) else {
+                continue;
+            };
+            watermarks
+                .entry(subscription)
+                .or_default()
+                .insert(relay_url, Timestamp::from(last_seen_at.max(0) as u64));
+        }
+        Ok(watermarks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::*;
+
+    #[tokio::test]
+    async fn test_watermarks_only_move_forward() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let account_id = account.id.unwrap();
+        let relay_a = RelayUrl::parse("wss://a.example.com").unwrap();
+        let relay_b = RelayUrl::parse("wss://b.example.com").unwrap();
+
+        let giftwraps = SubscriptionCategory::Giftwraps;
+        let group_messages = SubscriptionCategory::GroupMessages;
+        for (subscription, relay, seen_at) in [
+            (giftwraps, &relay_a, 200),
+            (giftwraps, &relay_a, 100),
+            (giftwraps, &relay_b, 50),
+            (group_messages, &relay_a, 20),
+        ] {
+            RelaySyncWatermarks::advance(
+                account_id,
+                subscription,
+                relay,
+                Timestamp::from(seen_at),
+                &whitenoise.database,
+            )
+            .await
+            .unwrap();
+        }
+
+        let watermarks = RelaySyncWatermarks::find_by_account(account_id, &whitenoise.database)
+            .await
+            .unwrap();
+        assert_eq!(watermarks[&giftwraps].len(), 2);
+        assert_eq!(watermarks[&giftwraps][&relay_a], Timestamp::from(200));
+        assert_eq!(watermarks[&giftwraps][&relay_b], Timestamp::from(50));
+        // Subscriptions on the same relay keep their own watermark
+        assert_eq!(watermarks[&group_messages][&relay_a], Timestamp::from(20));
+    }
+}
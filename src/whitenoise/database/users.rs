@@ -1,11 +1,14 @@
+use std::str::FromStr;
+
 use chrono::{DateTime, Utc};
 use nostr_sdk::{Metadata, PublicKey};
+use sqlx::{FromRow, Row};
 
 use super::{Database, DatabaseError, relays::RelayRow, utils::parse_timestamp};
 use crate::{
     WhitenoiseError,
     whitenoise::{
-        relays::{Relay, RelayType},
+        relays::{Relay, RelayMarker, RelayType},
         users::User,
     },
 };
@@ -149,6 +152,35 @@ impl User {
         Ok(user_row.into())
     }
 
+    /// Finds the users with the given public keys in a single query.
+    ///
+    /// Public keys without a user record are skipped rather than treated as an error.
+    pub(crate) async fn find_by_pubkeys(
+        pubkeys: &[PublicKey],
+        database: &Database,
+    ) -> Result<Vec<User>, WhitenoiseError> {
+        if pubkeys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Build dynamic query with correct number of placeholders
+        let placeholders = "?,".repeat(pubkeys.len());
+        let placeholders = placeholders.trim_end_matches(',');
+        let query = format!("SELECT * FROM users WHERE pubkey IN ({})", placeholders);
+
+        let mut query_builder = sqlx::query_as::<_, UserRow>(&query);
+        for pubkey in pubkeys {
+            query_builder = query_builder.bind(pubkey.to_hex());
+        }
+
+        let user_rows = query_builder
+            .fetch_all(&database.pool)
+            .await
+            .map_err(DatabaseError::Sqlx)?;
+
+        Ok(user_rows.into_iter().map(User::from).collect())
+    }
+
     /// Gets all relays of a specific type associated with this user.
     ///
     /// # Arguments
@@ -197,6 +229,96 @@ impl User {
         Ok(relays)
     }
 
+    /// Gets all relays of a specific type associated with this user, with their NIP-65 markers.
+    ///
+    /// Relays of types without markers (inbox and key package relays) are always
+    /// [`RelayMarker::ReadWrite`].
+    ///
+    /// # Arguments
+    ///
+    /// * `relay_type` - The type of relays to retrieve
+    /// * `database` - A reference to the `Database` instance for database operations
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WhitenoiseError`] if the database query fails.
+    pub(crate) async fn relays_with_markers(
+        &self,
+        relay_type: RelayType,
+        database: &Database,
+    ) -> Result<Vec<(Relay, RelayMarker)>, WhitenoiseError> {
+        let user_id = self.id.ok_or(WhitenoiseError::UserNotPersisted)?;
+
+        let rows = sqlx::query(
+            "SELECT r.id, r.url, r.created_at, r.updated_at, ur.marker
+             FROM relays r
+             INNER JOIN user_relays ur ON r.id = ur.relay_id
+             WHERE ur.user_id = ? AND ur.relay_type = ?",
+        )
+        .bind(user_id)
+        .bind(String::from(relay_type))
+        .fetch_all(&database.pool)
+        .await
+        .map_err(DatabaseError::Sqlx)?;
+
+        rows.iter()
+            .map(|row| {
+                let relay_row = RelayRow::from_row(row).map_err(DatabaseError::Sqlx)?;
+                let marker: String = row.try_get("marker").map_err(DatabaseError::Sqlx)?;
+                let marker = RelayMarker::from_str(&marker).map_err(|e| {
+                    DatabaseError::Sqlx(sqlx::Error::ColumnDecode {
+                        index: "marker".to_string(),
+                        source: e.into(),
+                    })
+                })?;
+                let relay = Relay {
+                    id: Some(relay_row.id),
+                    url: relay_row.url,
+                    created_at: relay_row.created_at,
+                    updated_at: relay_row.updated_at,
+                };
+                Ok((relay, marker))
+            })
+            .collect()
+    }
+
+    /// Sets the NIP-65 marker of one of this user's relays.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::UserRelayNotFound`] if the relay isn't one of the user's
+    /// relays of this type, or another [`WhitenoiseError`] if the update fails.
+    pub(crate) async fn set_relay_marker(
+        &self,
+        relay: &Relay,
+        relay_type: RelayType,
+        marker: RelayMarker,
+        database: &Database,
+    ) -> Result<(), WhitenoiseError> {
+        let user_id = self.id.ok_or(WhitenoiseError::UserNotPersisted)?;
+
+        let result = sqlx::query(
+            "UPDATE user_relays SET marker = ?, updated_at = ?
+             WHERE user_id = ?
+             AND relay_id = (SELECT id FROM relays WHERE url = ?)
+             AND relay_type = ?",
+        )
+        .bind(String::from(marker))
+        .bind(Utc::now().timestamp_millis())
+        .bind(user_id)
+        .bind(relay.url.to_string())
+        .bind(String::from(relay_type))
+        .execute(&database.pool)
+        .await
+        .map_err(DatabaseError::Sqlx)?;
+
+        if result.rows_affected() < 1 {
+            Err(WhitenoiseError::UserRelayNotFound)
+        } else {
+            Ok(())
+        }
+    }
+
     /// Saves this user to the database.
     ///
     /// # Arguments
@@ -502,6 +624,127 @@ impl User {
             Ok(())
         }
     }
+
+    /// Loads the cached NIP-05 result of a user, if they were ever checked.
+    pub(crate) async fn nip05_verification(
+        pubkey: &PublicKey,
+        database: &Database,
+    ) -> Result<Option<Nip05Verification>, WhitenoiseError> {
+        let row: Option<(String, bool, i64)> = sqlx::query_as(
+            "SELECT nip05_identifier, nip05_verified, nip05_verified_at FROM users
+             WHERE pubkey = ? AND nip05_identifier IS NOT NULL AND nip05_verified_at IS NOT NULL",
+        )
+        .bind(pubkey.to_hex())
+        .fetch_optional(&database.pool)
+        .await
+        .map_err(DatabaseError::Sqlx)?;
+
+        Ok(row.and_then(|(identifier, verified, verified_at)| {
+            Some(Nip05Verification {
+                identifier,
+                verified,
+                verified_at: DateTime::from_timestamp_millis(verified_at)?,
+            })
+        }))
+    }
+
+    /// Stores the result of checking a user's NIP-05 identifier.
+    ///
+    /// Does nothing for users that aren't in the database.
+    pub(crate) async fn save_nip05_verification(
+        pubkey: &PublicKey,
+        identifier: &str,
+        verified: bool,
+        database: &Database,
+    ) -> Result<(), WhitenoiseError> {
+        let now = Utc::now().timestamp_millis();
+        sqlx::query(
+            "UPDATE users SET nip05_identifier = ?, nip05_verified = ?, nip05_verified_at = ?,
+                              nip05_checked_at = ?
+             WHERE pubkey = ?",
+        )
+        .bind(identifier)
+        .bind(verified)
+        .bind(now)
+        .bind(now)
+        .bind(pubkey.to_hex())
+        .execute(&database.pool)
+        .await
+        .map_err(DatabaseError::Sqlx)?;
+        Ok(())
+    }
+
+    /// Records a check of a user's NIP-05 identifier that produced no result, because the
+    /// domain couldn't be reached or the identifier is invalid.
+    ///
+    /// A cached result for the same identifier is kept; one for another identifier is dropped.
+    /// Does nothing for users that aren't in the database.
+    pub(crate) async fn save_failed_nip05_check(
+        pubkey: &PublicKey,
+        identifier: &str,
+        database: &Database,
+    ) -> Result<(), WhitenoiseError> {
+        sqlx::query(
+            "UPDATE users
+             SET nip05_verified = CASE WHEN nip05_identifier IS ? THEN nip05_verified ELSE 0 END,
+                 nip05_verified_at =
+                     CASE WHEN nip05_identifier IS ? THEN nip05_verified_at ELSE NULL END,
+                 nip05_identifier = ?,
+                 nip05_checked_at = ?
+             WHERE pubkey = ?",
+        )
+        .bind(identifier)
+        .bind(identifier)
+        .bind(identifier)
+        .bind(Utc::now().timestamp_millis())
+        .bind(pubkey.to_hex())
+        .execute(&database.pool)
+        .await
+        .map_err(DatabaseError::Sqlx)?;
+        Ok(())
+    }
+
+    /// Finds users whose NIP-05 identifier needs checking, least recently checked first.
+    ///
+    /// These are users with a `nip05` in their metadata that was never checked, was last
+    /// checked before `checked_before`, or changed since it was checked. Failed checks count,
+    /// so identifiers that can't be verified wait their turn like the others.
+    pub(crate) async fn find_stale_nip05(
+        checked_before: DateTime<Utc>,
+        limit: usize,
+        database: &Database,
+    ) -> Result<Vec<(PublicKey, String)>, WhitenoiseError> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT pubkey, json_extract(metadata, '$.nip05') AS nip05 FROM users
+             WHERE COALESCE(json_extract(metadata, '$.nip05'), '') != ''
+               AND (nip05_checked_at IS NULL
+                    OR nip05_checked_at < ?
+                    OR nip05_identifier IS NOT json_extract(metadata, '$.nip05'))
+             ORDER BY COALESCE(nip05_checked_at, 0)
+             LIMIT ?",
+        )
+        .bind(checked_before.timestamp_millis())
+        .bind(limit as i64)
+        .fetch_all(&database.pool)
+        .await
+        .map_err(DatabaseError::Sqlx)?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(pubkey, nip05)| Some((PublicKey::from_hex(&pubkey).ok()?, nip05)))
+            .collect())
+    }
+}
+
+/// Cached result of checking a user's NIP-05 identifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Nip05Verification {
+    /// The identifier that was checked
+    pub identifier: String,
+    /// Whether the identifier's domain maps it to the user's pubkey
+    pub verified: bool,
+    /// When the check was made
+    pub verified_at: DateTime<Utc>,
 }
 
 #[cfg(test)]
@@ -1541,6 +1784,72 @@ mod tests {
         assert_eq!(user_relays.len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_set_relay_marker() {
+        use crate::whitenoise::test_utils::create_mock_whitenoise;
+
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+
+        let test_pubkey = nostr_sdk::Keys::generate().public_key();
+        let test_timestamp = chrono::Utc::now();
+        let user = User {
+            id: None,
+            pubkey: test_pubkey,
+            metadata: Metadata::new(),
+            created_at: test_timestamp,
+            updated_at: test_timestamp,
+        };
+        user.save(&whitenoise.database).await.unwrap();
+        let loaded_user = User::find_by_pubkey(&test_pubkey, &whitenoise.database)
+            .await
+            .unwrap();
+
+        let relay_url = nostr_sdk::RelayUrl::parse("wss://test-marker.example.com").unwrap();
+        let relay = whitenoise
+            .find_or_create_relay_by_url(&relay_url)
+            .await
+            .unwrap();
+        loaded_user
+            .add_relay(&relay, RelayType::Nip65, &whitenoise.database)
+            .await
+            .unwrap();
+
+        // New relays are used for both reading and writing
+        let relays = loaded_user
+            .relays_with_markers(RelayType::Nip65, &whitenoise.database)
+            .await
+            .unwrap();
+        assert_eq!(relays.len(), 1);
+        assert_eq!(relays[0].1, RelayMarker::ReadWrite);
+
+        loaded_user
+            .set_relay_marker(
+                &relay,
+                RelayType::Nip65,
+                RelayMarker::Write,
+                &whitenoise.database,
+            )
+            .await
+            .unwrap();
+        let relays = loaded_user
+            .relays_with_markers(RelayType::Nip65, &whitenoise.database)
+            .await
+            .unwrap();
+        assert_eq!(relays[0].0.url, relay_url);
+        assert_eq!(relays[0].1, RelayMarker::Write);
+
+        // The relay isn't an inbox relay of the user
+        let result = loaded_user
+            .set_relay_marker(
+                &relay,
+                RelayType::Inbox,
+                RelayMarker::Read,
+                &whitenoise.database,
+            )
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::UserRelayNotFound)));
+    }
+
     #[tokio::test]
     async fn test_remove_relay_not_in_database() {
         use crate::whitenoise::test_utils::create_mock_whitenoise;
@@ -0,0 +1,307 @@
+//! NIP-17 private direct messages
+//!
+//! A direct message is a kind 14 rumor, sealed by its author and giftwrapped once for the
+//! recipient and once for the author's own inbox, so the account's other devices see what it
+//! sent. The giftwrap is signed by a throwaway key, so the sender is only known from the seal;
+//! a rumor is only accepted when its `pubkey` matches the key that signed the seal.
+
+use nostr_sdk::prelude::*;
+
+use crate::whitenoise::{
+    Whitenoise,
+    accounts::Account,
+    database::direct_messages::{DirectMessages, StoredDirectMessage},
+    error::{Result, WhitenoiseError},
+    message_aggregator::{ChatMessage, DeliveryStatus, ReactionSummary, mentions},
+    relays::{Relay, RelayType},
+    users::UserSyncMode,
+};
+
+impl Whitenoise {
+    /// Sends a NIP-17 private direct message.
+    ///
+    /// The message is giftwrapped for the recipient and published to their inbox relays
+    /// (kind 10050). A second copy is wrapped for the account itself and published to its own
+    /// inbox relays, so the conversation stays complete on the account's other devices.
+    ///
+    /// # Arguments
+    /// * `account` - The account sending the message
+    /// * `recipient` - The public key of the other participant
+    /// * `content` - The message text
+    ///
+    /// # Returns
+    /// The sent message, with the delivery status of the recipient's copy
+    ///
+    /// # Errors
+    /// Returns [`WhitenoiseError::MissingInboxRelays`] if the recipient has not published any
+    /// inbox relays.
+    pub async fn send_dm(
+        &self,
+        account: &Account,
+        recipient: &PublicKey,
+        content: String,
+    ) -> Result<ChatMessage> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        let keys = self
+            .secrets_store
+            .get_nostr_keys_for_pubkey(&account.pubkey)?;
+
+        let recipient_relays = self.dm_inbox_relays(recipient).await?;
+        if recipient_relays.is_empty() {
+            return Err(WhitenoiseError::MissingInboxRelays(*recipient));
+        }
+
+        let mut rumor = EventBuilder::private_msg_rumor(*recipient, content).build(account.pubkey);
+        rumor.ensure_id();
+        DirectMessages::insert(account_id, recipient, &rumor, &self.database).await?;
+
+        let delivery_status = match self
+            .nostr
+            .publish_gift_wrap_to(
+                recipient,
+                rumor.clone(),
+                &[],
+                account.pubkey,
+                &Relay::urls(&recipient_relays),
+                keys.clone(),
+            )
+            .await
+        {
+            Ok(outcome) if outcome.is_success() => DeliveryStatus::Sent {
+                relays: outcome.accepted().count(),
+            },
+            Ok(_) => DeliveryStatus::Failed,
+            Err(e) => {
+                tracing::warn!(
+                    target: "whitenoise::direct_messages::send_dm",
+                    "Failed to publish direct message to {}: {}",
+                    recipient.to_hex(),
+                    e
+                );
+                DeliveryStatus::Failed
+            }
+        };
+
+        let own_relays = account.inbox_relays(self).await?;
+        if !own_relays.is_empty()
+            && let Err(e) = self
+                .nostr
+                .publish_gift_wrap_to(
+                    &account.pubkey,
+                    rumor.clone(),
+                    &[],
+                    account.pubkey,
+                    &Relay::urls(&own_relays),
+                    keys,
+                )
+                .await
+        {
+            tracing::warn!(
+                target: "whitenoise::direct_messages::send_dm",
+                "Failed to publish own copy of direct message: {}",
+                e
+            );
+        }
+
+        let mut message = self.direct_message_to_chat_message(&StoredDirectMessage {
+            id: rumor.id.unwrap_or_else(EventId::all_zeros),
+            other_pubkey: *recipient,
+            author: rumor.pubkey,
+            content: rumor.content,
+            tags: rumor.tags,
+            created_at: rumor.created_at,
+        });
+        message.delivery_status = delivery_status;
+        Ok(message)
+    }
+
+    /// Fetches the NIP-17 direct messages the account exchanged with another user.
+    ///
+    /// # Arguments
+    /// * `account` - The account whose conversation to fetch
+    /// * `other` - The public key of the other participant
+    ///
+    /// # Returns
+    /// The messages of the conversation, oldest first
+    pub async fn fetch_dm_conversation(
+        &self,
+        account: &Account,
+        other: &PublicKey,
+    ) -> Result<Vec<ChatMessage>> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        let messages = DirectMessages::find_conversation(account_id, other, &self.database).await?;
+        Ok(messages
+            .iter()
+            .map(|message| self.direct_message_to_chat_message(message))
+            .collect())
+    }
+
+    /// Store a direct message unwrapped from a giftwrap sent to the account
+    ///
+    /// The giftwrap's author is a throwaway key, so the sender is taken from the rumor after
+    /// checking it against the key that signed the seal. Messages the account sent itself
+    /// (the copy wrapped for its own inbox) are filed under their first other recipient.
+    pub(crate) async fn process_private_direct_message(
+        &self,
+        account: &Account,
+        unwrapped: UnwrappedGift,
+    ) -> Result<()> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        let rumor = unwrapped.rumor;
+        if unwrapped.sender != rumor.pubkey {
+            tracing::warn!(
+                target: "whitenoise::direct_messages::process_private_direct_message",
+                "Dropping direct message: rumor author {} does not match seal signer {}",
+                rumor.pubkey.to_hex(),
+                unwrapped.sender.to_hex()
+            );
+            return Ok(());
+        }
+
+        let other_pubkey = if rumor.pubkey == account.pubkey {
+            match rumor
+                .tags
+                .public_keys()
+                .find(|pubkey| **pubkey != account.pubkey)
+            {
+                Some(pubkey) => *pubkey,
+                None => {
+                    tracing::debug!(
+                        target: "whitenoise::direct_messages::process_private_direct_message",
+                        "Ignoring direct message without a recipient"
+                    );
+                    return Ok(());
+                }
+            }
+        } else {
+            rumor.pubkey
+        };
+
+        if self.muted_pubkeys(account).await?.contains(&other_pubkey) {
+            return Ok(());
+        }
+
+        DirectMessages::insert(account_id, &other_pubkey, &rumor, &self.database).await?;
+        Ok(())
+    }
+
+    /// The inbox relays of a user, fetching their relay lists if none are known yet
+    async fn dm_inbox_relays(&self, pubkey: &PublicKey) -> Result<Vec<Relay>> {
+        let user = self
+            .find_or_create_user_by_pubkey(pubkey, UserSyncMode::Background)
+            .await?;
+        let relays = user.relays(RelayType::Inbox, &self.database).await?;
+        if !relays.is_empty() {
+            return Ok(relays);
+        }
+
+        let user = self
+            .find_or_create_user_by_pubkey(pubkey, UserSyncMode::Blocking)
+            .await?;
+        user.relays(RelayType::Inbox, &self.database).await
+    }
+
+    fn direct_message_to_chat_message(&self, message: &StoredDirectMessage) -> ChatMessage {
+        let reply_to_id = message.tags.event_ids().next().map(|id| id.to_hex());
+        ChatMessage {
+            id: message.id.to_hex(),
+            author: message.author,
+            content: message.content.clone(),
+            created_at: message.created_at,
+            tags: message.tags.clone(),
+            is_reply: reply_to_id.is_some(),
+            reply_to_id,
+            reply_to: None,
+            quoted: None,
+            is_deleted: false,
+            edited_at: None,
+            is_muted: false,
+            content_tokens: self.nostr.parse(&message.content),
+            mentions: mentions::extract_mentions(&message.content),
+            reactions: ReactionSummary::default(),
+            kind: Kind::PrivateDirectMessage.as_u16(),
+            media_attachments: vec![],
+            delivery_status: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::*;
+
+    fn unwrapped_dm(sender: PublicKey, author: PublicKey, recipient: PublicKey) -> UnwrappedGift {
+        let mut rumor = EventBuilder::private_msg_rumor(recipient, "Hello there").build(author);
+        rumor.ensure_id();
+        UnwrappedGift { sender, rumor }
+    }
+
+    #[tokio::test]
+    async fn test_received_direct_messages_are_stored_by_rumor_author() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let alice = Keys::generate().public_key();
+        let mallory = Keys::generate().public_key();
+
+        // A seal signed by someone else than the rumor author is dropped
+        whitenoise
+            .process_private_direct_message(&account, unwrapped_dm(mallory, alice, account.pubkey))
+            .await
+            .unwrap();
+        assert!(
+            whitenoise
+                .fetch_dm_conversation(&account, &alice)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        whitenoise
+            .process_private_direct_message(&account, unwrapped_dm(alice, alice, account.pubkey))
+            .await
+            .unwrap();
+        // The account's own copy of a reply is filed under the recipient
+        whitenoise
+            .process_private_direct_message(
+                &account,
+                unwrapped_dm(account.pubkey, account.pubkey, alice),
+            )
+            .await
+            .unwrap();
+
+        let conversation = whitenoise
+            .fetch_dm_conversation(&account, &alice)
+            .await
+            .unwrap();
+        assert_eq!(conversation.len(), 2);
+        assert!(conversation.iter().any(|m| m.author == alice));
+        assert!(conversation.iter().any(|m| m.author == account.pubkey));
+        assert!(
+            conversation
+                .iter()
+                .all(|m| m.kind == Kind::PrivateDirectMessage.as_u16())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_dm_stores_message() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let sender = whitenoise.create_identity().await.unwrap();
+        let recipient = whitenoise.create_identity().await.unwrap();
+
+        let sent = whitenoise
+            .send_dm(&sender, &recipient.pubkey, "Hi!".to_string())
+            .await
+            .unwrap();
+        assert_eq!(sent.author, sender.pubkey);
+
+        let conversation = whitenoise
+            .fetch_dm_conversation(&sender, &recipient.pubkey)
+            .await
+            .unwrap();
+        assert_eq!(conversation.len(), 1);
+        assert_eq!(conversation[0].id, sent.id);
+        assert_eq!(conversation[0].content, "Hi!");
+    }
+}
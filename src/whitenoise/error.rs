@@ -1,11 +1,12 @@
-use nostr_sdk::prelude::PublicKey;
+use nostr_sdk::prelude::{EventId, PublicKey, RelayUrl};
 use thiserror::Error;
 
 use crate::{
     nostr_manager::NostrManagerError,
+    types::RetryErrorClass,
     whitenoise::{
-        accounts::AccountError, database::DatabaseError, message_aggregator::ProcessingError,
-        secrets_store::SecretsStoreError,
+        accounts::AccountError, blossom::BlossomError, database::DatabaseError,
+        message_aggregator::ProcessingError, secrets_store::SecretsStoreError,
     },
 };
 
@@ -22,6 +23,9 @@ pub enum WhitenoiseError {
     #[error("Logging setup error: {0}")]
     LoggingSetup(String),
 
+    #[error("Invalid log filter: {0}")]
+    InvalidLogFilter(String),
+
     #[error("Configuration error: {0}")]
     Configuration(String),
 
@@ -88,6 +92,15 @@ pub enum WhitenoiseError {
     #[error("Database error: {0}")]
     Database(#[from] DatabaseError),
 
+    #[error("Failed to open database: {0}")]
+    DbOpenFailed(DatabaseError),
+
+    #[error("Database is locked by another process: {0}")]
+    DbLocked(DatabaseError),
+
+    #[error("Database is corrupt: {0}")]
+    DbCorrupt(DatabaseError),
+
     #[error("Account error: {0}")]
     Account(#[from] AccountError),
 
@@ -100,12 +113,36 @@ pub enum WhitenoiseError {
     #[error("Nostr manager error: {0}")]
     NostrManager(#[from] NostrManagerError),
 
+    #[error("Account is not a member of the group")]
+    AccountNotGroupMember,
+
+    #[error("Cannot leave the group as its only admin; make another member an admin first")]
+    LastGroupAdmin,
+
     #[error("One or more members to remove are not in the group")]
     MembersNotInGroup,
 
     #[error("Welcome not found")]
     WelcomeNotFound,
 
+    #[error("Message not found")]
+    MessageNotFound,
+
+    #[error("Message has been deleted")]
+    MessageDeleted,
+
+    #[error("Invalid backup: {0}")]
+    InvalidBackup(String),
+
+    #[error("Wrong backup passphrase")]
+    WrongBackupPassphrase,
+
+    #[error("Invalid ncryptsec: {0}")]
+    InvalidNcryptsec(String),
+
+    #[error("Wrong ncryptsec passphrase")]
+    WrongNcryptsecPassphrase,
+
     #[error("nip04 direct message error")]
     Nip04Error(#[from] nostr_sdk::nips::nip04::Error),
 
@@ -142,6 +179,9 @@ pub enum WhitenoiseError {
     #[error("Unsupported media format: {0}")]
     UnsupportedMediaFormat(String),
 
+    #[error("Media file is too large: {size} bytes exceeds the {limit} byte limit")]
+    MediaFileTooLarge { size: u64, limit: u64 },
+
     #[error(
         "Cannot deliver MLS welcome for {member_pubkey}: no inbox/NIP-65 relays configured and account {account_pubkey} has no fallback relays"
     )]
@@ -149,6 +189,92 @@ pub enum WhitenoiseError {
         member_pubkey: PublicKey,
         account_pubkey: PublicKey,
     },
+
+    #[error("Cannot send direct message: {0} has no inbox relays")]
+    MissingInboxRelays(PublicKey),
+
+    #[error("Slow mode is active: wait {remaining_secs}s before sending another message")]
+    SlowModeActive { remaining_secs: u64 },
+
+    #[error("Link previews are disabled")]
+    LinkPreviewsDisabled,
+
+    #[error("Failed to fetch link preview: {0}")]
+    LinkPreview(String),
+
+    #[error("Failed to fetch NIP-05 identifiers: {0}")]
+    Nip05(String),
+
+    #[error("Group is out of sync: {0}")]
+    GroupOutOfSync(String),
+
+    #[error("Relay {0} requires authentication, but no account uses it")]
+    RelayAuthRequired(RelayUrl),
+
+    #[error("Authentication with relay {relay_url} failed: {reason}")]
+    RelayAuthFailed { relay_url: RelayUrl, reason: String },
+
+    #[error("Media was declared as {declared} but its contents are {detected}")]
+    MimeMismatch { declared: String, detected: String },
+
+    #[error("Event {0} has an invalid id or signature")]
+    InvalidSignature(EventId),
+
+    #[error(transparent)]
+    Blossom(#[from] BlossomError),
+}
+
+impl WhitenoiseError {
+    /// Whether retrying the failed operation could plausibly succeed
+    pub fn retry_class(&self) -> RetryErrorClass {
+        match self {
+            WhitenoiseError::Configuration(_)
+            | WhitenoiseError::InvalidEvent(_)
+            | WhitenoiseError::InvalidPublicKey
+            | WhitenoiseError::InvalidInput(_)
+            | WhitenoiseError::InvalidLogFilter(_)
+            | WhitenoiseError::InvalidTimestamp
+            | WhitenoiseError::NostrKey(_)
+            | WhitenoiseError::NostrTag(_)
+            | WhitenoiseError::Nip04Error(_)
+            | WhitenoiseError::SerializationError(_)
+            | WhitenoiseError::AccountNotAuthorized
+            | WhitenoiseError::AccountNotGroupMember
+            | WhitenoiseError::LastGroupAdmin
+            | WhitenoiseError::MessageDeleted
+            | WhitenoiseError::InvalidBackup(_)
+            | WhitenoiseError::WrongBackupPassphrase
+            | WhitenoiseError::InvalidNcryptsec(_)
+            | WhitenoiseError::WrongNcryptsecPassphrase
+            | WhitenoiseError::ImageDecryptionFailed(_)
+            | WhitenoiseError::HashMismatch { .. }
+            | WhitenoiseError::UnsupportedMediaFormat(_)
+            | WhitenoiseError::MediaFileTooLarge { .. }
+            | WhitenoiseError::LinkPreviewsDisabled
+            | WhitenoiseError::DbCorrupt(_)
+            | WhitenoiseError::GroupOutOfSync(_)
+            | WhitenoiseError::RelayAuthRequired(_)
+            | WhitenoiseError::RelayAuthFailed { .. }
+            | WhitenoiseError::MimeMismatch { .. }
+            | WhitenoiseError::InvalidSignature(_)
+            | WhitenoiseError::Blossom(BlossomError::Unauthorized { .. }) => {
+                RetryErrorClass::Permanent
+            }
+            _ => RetryErrorClass::Transient,
+        }
+    }
+
+    /// Classify an error from opening the database, so callers can tell a lock held by
+    /// another process from a damaged file
+    pub(crate) fn from_database_open(err: DatabaseError) -> Self {
+        if err.is_locked() {
+            WhitenoiseError::DbLocked(err)
+        } else if err.is_corrupt() {
+            WhitenoiseError::DbCorrupt(err)
+        } else {
+            WhitenoiseError::DbOpenFailed(err)
+        }
+    }
 }
 
 impl From<Box<dyn std::error::Error + Send + Sync>> for WhitenoiseError {
@@ -195,4 +321,22 @@ mod tests {
         assert!(message.contains(&member.to_string()));
         assert!(message.contains(&account.to_string()));
     }
+
+    #[test]
+    fn retry_class_separates_permanent_from_transient_errors() {
+        assert_eq!(
+            WhitenoiseError::Configuration("Failed to decrypt giftwrap".to_string()).retry_class(),
+            RetryErrorClass::Permanent
+        );
+        assert_eq!(
+            WhitenoiseError::SqlxError(sqlx::Error::PoolTimedOut).retry_class(),
+            RetryErrorClass::Transient
+        );
+    }
+
+    #[test]
+    fn slow_mode_active_format_includes_remaining_cooldown() {
+        let message = WhitenoiseError::SlowModeActive { remaining_secs: 42 }.to_string();
+        assert!(message.contains("42s"));
+    }
 }
@@ -2,20 +2,22 @@ use nostr_sdk::prelude::*;
 use sha2::{Digest, Sha256};
 
 use crate::{
-    nostr_manager::utils::cap_timestamp_to_now,
+    nostr_manager::{SubscriptionCategory, utils::cap_timestamp_to_now},
     types::RetryInfo,
     whitenoise::{
         Whitenoise,
         accounts::Account,
+        database::relay_sync_watermarks::RelaySyncWatermarks,
         error::{Result, WhitenoiseError},
     },
 };
 
 impl Whitenoise {
     pub(super) async fn process_account_event(
-        &self,
+        &'static self,
         event: Event,
         subscription_id: String,
+        relay_url: Option<RelayUrl>,
         retry_info: RetryInfo,
     ) {
         // Get the account from the subscription ID, skip if we can't find it
@@ -177,11 +179,40 @@ impl Whitenoise {
                     }
                     _ => {}
                 }
+
+                // Remember how far this relay has delivered to the subscription, so the next
+                // subscription setup only asks it for newer events
+                if let (Some(relay_url), Some(account_id), Some(subscription)) = (
+                    relay_url.as_ref(),
+                    account.id,
+                    SubscriptionCategory::from_subscription_id(&subscription_id),
+                ) && let Err(e) = RelaySyncWatermarks::advance(
+                    account_id,
+                    subscription,
+                    relay_url,
+                    cap_timestamp_to_now(event.created_at),
+                    &self.database,
+                )
+                .await
+                {
+                    tracing::warn!(
+                        target: "whitenoise::event_processor::process_account_event",
+                        "Failed to advance sync watermark for {}: {}",
+                        relay_url,
+                        e
+                    );
+                }
             }
             Err(e) => {
                 // Handle retry logic for actual processing errors
-                if retry_info.should_retry() {
+                if retry_info.should_retry_error(&e) {
                     self.schedule_retry(event, subscription_id, retry_info, e);
+                } else if retry_info.should_retry() {
+                    tracing::error!(
+                        target: "whitenoise::event_processor::process_account_event",
+                        "Event processing failed with non-retryable error, giving up: {}",
+                        e
+                    );
                 } else {
                     tracing::error!(
                         target: "whitenoise::event_processor::process_account_event",
@@ -194,6 +225,34 @@ impl Whitenoise {
         }
     }
 
+    /// Process an event that was fetched for an account rather than delivered by a subscription
+    ///
+    /// Events are skipped and recorded like subscription events, but failures are returned
+    /// instead of retried.
+    ///
+    /// Returns whether the event was processed.
+    pub(crate) async fn process_fetched_account_event(
+        &'static self,
+        event: &Event,
+        account: &Account,
+    ) -> Result<bool> {
+        if self
+            .should_skip_account_event_processing(event, account)
+            .await?
+            .is_some()
+        {
+            return Ok(false);
+        }
+
+        self.route_account_event_for_processing(event, account)
+            .await?;
+        self.nostr
+            .event_tracker
+            .track_processed_account_event(event, &account.pubkey)
+            .await?;
+        Ok(true)
+    }
+
     /// Extract the account pubkey from a subscription_id
     /// Subscription IDs follow the format: {hashed_pubkey}_{subscription_type}
     /// where hashed_pubkey = SHA256(session salt || accouny_pubkey)[..12]
@@ -311,7 +370,7 @@ impl Whitenoise {
 
     /// Route an event to the appropriate handler based on its kind
     async fn route_account_event_for_processing(
-        &self,
+        &'static self,
         event: &Event,
         account: &Account,
     ) -> Result<()> {
@@ -326,6 +385,7 @@ impl Whitenoise {
                 self.handle_relay_list(event.clone()).await
             }
             Kind::ContactList => self.handle_contact_list(account, event.clone()).await,
+            Kind::MuteList => self.handle_mute_list(account, event.clone()).await,
             _ => {
                 tracing::debug!(
                     target: "whitenoise::event_processor::route_event_for_processing",
@@ -381,8 +441,58 @@ fn validate_giftwrap_target(account: &Account, event: &Event) -> Result<()> {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::whitenoise::test_utils::*;
 
+    #[tokio::test]
+    async fn test_watermark_advances_only_for_processed_events() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let whitenoise: &'static Whitenoise = Box::leak(Box::new(whitenoise));
+        let account = whitenoise.create_identity().await.unwrap();
+        let account_id = account.id.unwrap();
+        let keys = whitenoise
+            .secrets_store
+            .get_nostr_keys_for_pubkey(&account.pubkey)
+            .unwrap();
+        let pubkey_hash = whitenoise.nostr.create_pubkey_hash(&account.pubkey);
+        let relay_url = RelayUrl::parse("wss://relay.example.com").unwrap();
+
+        // A giftwrap that can't be unwrapped fails to process
+        let giftwrap = EventBuilder::new(Kind::GiftWrap, "garbage")
+            .tag(Tag::public_key(account.pubkey))
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        whitenoise
+            .process_account_event(
+                giftwrap,
+                format!("{}_giftwrap", pubkey_hash),
+                Some(relay_url.clone()),
+                RetryInfo::new(),
+            )
+            .await;
+
+        let contact_list = EventBuilder::new(Kind::ContactList, "")
+            .sign_with_keys(&keys)
+            .unwrap();
+        whitenoise
+            .process_account_event(
+                contact_list.clone(),
+                format!("{}_user_follow_list", pubkey_hash),
+                Some(relay_url.clone()),
+                RetryInfo::new(),
+            )
+            .await;
+
+        let watermarks = RelaySyncWatermarks::find_by_account(account_id, &whitenoise.database)
+            .await
+            .unwrap();
+        assert!(!watermarks.contains_key(&SubscriptionCategory::Giftwraps));
+        assert_eq!(
+            watermarks[&SubscriptionCategory::FollowLists][&relay_url],
+            contact_list.created_at
+        );
+    }
+
     #[tokio::test]
     async fn test_extract_pubkey_from_subscription_id() {
         let (whitenoise, _, _) = create_mock_whitenoise().await;
@@ -1,5 +1,6 @@
 use nostr_sdk::prelude::*;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::Semaphore;
 
 use crate::{
@@ -14,10 +15,105 @@ use crate::{
     },
 };
 
+/// Contact list updates of one account held back by the debounce window
+#[derive(Debug)]
+pub(crate) struct ContactListDebounce {
+    /// When the current window closes
+    window_ends: Instant,
+    /// Newest update received during the window, with the account it belongs to
+    pending: Option<(Account, Event)>,
+}
+
 impl Whitenoise {
-    /// Contact list handler that performs the actual processing
+    /// Contact list handler that coalesces bursts of updates
+    ///
+    /// The first update after a quiet period is processed right away and opens a window of
+    /// [`WhitenoiseConfig::contact_list_debounce`](crate::WhitenoiseConfig::contact_list_debounce).
+    /// Updates arriving within the window are held back, and only the newest of them is
+    /// processed once the window ends, so the final state is never lost.
     /// Note: Event tracking (published/processed checks) is handled at the processor level
-    pub(crate) async fn handle_contact_list(&self, account: &Account, event: Event) -> Result<()> {
+    pub(crate) async fn handle_contact_list(
+        &'static self,
+        account: &Account,
+        event: Event,
+    ) -> Result<()> {
+        let window = self.config.contact_list_debounce;
+        if window.is_zero() {
+            return self.process_contact_list(account, event).await;
+        }
+
+        let now = Instant::now();
+        let flush_at = {
+            let mut state = self
+                .contact_list_debounce
+                .entry(account.pubkey)
+                .or_insert_with(|| ContactListDebounce {
+                    window_ends: now,
+                    pending: None,
+                });
+
+            if now >= state.window_ends && state.pending.is_none() {
+                state.window_ends = now + window;
+                None
+            } else {
+                let flush_scheduled = state.pending.is_some();
+                if state
+                    .pending
+                    .as_ref()
+                    .is_none_or(|(_, pending)| event.created_at >= pending.created_at)
+                {
+                    state.pending = Some((account.clone(), event.clone()));
+                }
+                tracing::debug!(
+                    target: "whitenoise::handle_contact_list",
+                    "Coalescing contact list event {} for account {}",
+                    event.id.to_hex(),
+                    account.pubkey.to_hex()
+                );
+                if flush_scheduled {
+                    return Ok(());
+                }
+                Some(state.window_ends)
+            }
+        };
+
+        match flush_at {
+            None => self.process_contact_list(account, event).await,
+            Some(flush_at) => {
+                self.schedule_contact_list_flush(account.pubkey, flush_at);
+                Ok(())
+            }
+        }
+    }
+
+    /// Process the newest held back contact list update of an account once its window ends
+    fn schedule_contact_list_flush(&'static self, pubkey: PublicKey, flush_at: Instant) {
+        tokio::spawn(async move {
+            tokio::time::sleep_until(flush_at.into()).await;
+
+            // Processing the held back update opens the next window
+            let pending = self
+                .contact_list_debounce
+                .get_mut(&pubkey)
+                .and_then(|mut state| {
+                    state.window_ends = Instant::now() + self.config.contact_list_debounce;
+                    state.pending.take()
+                });
+            if let Some((account, event)) = pending
+                && let Err(e) = self.process_contact_list(&account, event).await
+            {
+                tracing::error!(
+                    target: "whitenoise::handle_contact_list",
+                    "Failed to process coalesced contact list for {}: {}",
+                    pubkey.to_hex(),
+                    e
+                );
+            }
+        });
+    }
+
+    /// Apply a contact list update to the account's follows
+    async fn process_contact_list(&self, account: &Account, event: Event) -> Result<()> {
         // Acquire per-account semaphore permit to serialize contact list processing for this account
         let semaphore = self
             .contact_list_guards
@@ -111,3 +207,77 @@ impl Whitenoise {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::{create_mock_whitenoise, create_test_account};
+
+    fn contact_list(keys: &Keys, follows: &[PublicKey], created_at: u64) -> Event {
+        EventBuilder::new(Kind::ContactList, "")
+            .tags(follows.iter().map(|pubkey| Tag::public_key(*pubkey)))
+            .custom_created_at(Timestamp::from(created_at))
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_contact_list_bursts_are_coalesced() {
+        let (mut whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        whitenoise.config.contact_list_debounce = std::time::Duration::from_millis(500);
+        let whitenoise: &'static Whitenoise = Box::leak(Box::new(whitenoise));
+        let (account, keys) = create_test_account(whitenoise).await;
+        let account = account.save(&whitenoise.database).await.unwrap();
+        let followed: Vec<PublicKey> = (0..3).map(|_| Keys::generate().public_key()).collect();
+        let now = Timestamp::now().as_u64();
+
+        // The first update is processed right away
+        whitenoise
+            .handle_contact_list(&account, contact_list(&keys, &followed[..1], now))
+            .await
+            .unwrap();
+        assert_eq!(
+            account.follows(&whitenoise.database).await.unwrap().len(),
+            1
+        );
+
+        // Updates within the window are held back, keeping only the newest
+        let newest = contact_list(&keys, &followed, now + 2);
+        whitenoise
+            .handle_contact_list(&account, newest.clone())
+            .await
+            .unwrap();
+        whitenoise
+            .handle_contact_list(&account, contact_list(&keys, &followed[..2], now + 1))
+            .await
+            .unwrap();
+        assert_eq!(
+            account.follows(&whitenoise.database).await.unwrap().len(),
+            1
+        );
+
+        {
+            let state = whitenoise
+                .contact_list_debounce
+                .get(&account.pubkey)
+                .unwrap();
+            let (_, pending) = state.pending.as_ref().unwrap();
+            assert_eq!(pending.id, newest.id);
+        }
+
+        // The newest held back update is applied once the window ends
+        tokio::time::sleep(std::time::Duration::from_millis(1000)).await;
+        assert_eq!(
+            account.follows(&whitenoise.database).await.unwrap().len(),
+            3
+        );
+        assert!(
+            whitenoise
+                .contact_list_debounce
+                .get(&account.pubkey)
+                .unwrap()
+                .pending
+                .is_none()
+        );
+    }
+}
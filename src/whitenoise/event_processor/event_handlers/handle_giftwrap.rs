@@ -14,6 +14,16 @@ impl Whitenoise {
             account.pubkey.to_hex()
         );
 
+        if let Err(e) = self.nostr.verify_event(&event) {
+            tracing::warn!(
+                target: "whitenoise::event_handlers::handle_giftwrap",
+                "Dropping giftwrap {}: {}",
+                event.id,
+                e
+            );
+            return Err(WhitenoiseError::InvalidSignature(event.id));
+        }
+
         let keys = self
             .secrets_store
             .get_nostr_keys_for_pubkey(&account.pubkey)?;
@@ -27,6 +37,10 @@ impl Whitenoise {
                 self.process_welcome(account, event, unwrapped.rumor)
                     .await?;
             }
+            Kind::PrivateDirectMessage => {
+                self.process_private_direct_message(account, unwrapped)
+                    .await?;
+            }
             _ => {
                 tracing::debug!(
                     target: "whitenoise::event_handlers::handle_giftwrap",
@@ -190,4 +204,35 @@ mod tests {
         let result = whitenoise.handle_giftwrap(&account, giftwrap_event).await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_handle_giftwrap_rejects_tampered_event() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+
+        let mut rumor = UnsignedEvent::new(
+            account.pubkey,
+            Timestamp::now(),
+            Kind::TextNote,
+            vec![],
+            "original".to_string(),
+        );
+        rumor.ensure_id();
+        let giftwrap_event =
+            EventBuilder::gift_wrap(&create_test_keys(), &account.pubkey, rumor, vec![])
+                .await
+                .unwrap();
+
+        // Change the content without re-signing; deserializing doesn't verify the event
+        let mut json = serde_json::to_value(&giftwrap_event).unwrap();
+        json["content"] = serde_json::Value::String("tampered".to_string());
+        let tampered = Event::from_json(json.to_string()).unwrap();
+        assert!(whitenoise.nostr.verify_event(&giftwrap_event).is_ok());
+
+        let result = whitenoise.handle_giftwrap(&account, tampered).await;
+        assert!(matches!(
+            result,
+            Err(WhitenoiseError::InvalidSignature(id)) if id == giftwrap_event.id
+        ));
+    }
 }
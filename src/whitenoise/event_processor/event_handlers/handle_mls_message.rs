@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use mdk_core::prelude::message_types::Message;
 use mdk_core::prelude::{GroupId, MessageProcessingResult};
 use nostr_sdk::prelude::*;
@@ -7,9 +9,16 @@ use crate::whitenoise::{
     accounts::Account,
     aggregated_message::AggregatedMessage,
     error::{Result, WhitenoiseError},
+    group_information::{
+        GroupInformation, SLOW_MODE_SETTINGS_D_TAG, SLOW_MODE_SETTINGS_KIND, SlowMode,
+    },
+    group_sync,
     media_files::MediaFile,
-    message_aggregator::{ChatMessage, emoji_utils, reaction_handler},
+    message_aggregator::{
+        ChatMessage, MESSAGE_EDIT_KIND, edit_handler, emoji_utils, reaction_handler,
+    },
     message_streaming::{MessageUpdate, UpdateTrigger},
+    typing_indicators::TYPING_INDICATOR_KIND,
 };
 
 impl Whitenoise {
@@ -20,64 +29,24 @@ impl Whitenoise {
           account.pubkey.to_hex()
         );
 
+        if let Err(e) = self.nostr.verify_event(&event) {
+            tracing::warn!(
+                target: "whitenoise::event_handlers::handle_mls_message",
+                "Dropping MLS message {}: {}",
+                event.id,
+                e
+            );
+            return Err(WhitenoiseError::InvalidSignature(event.id));
+        }
+
         let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
-        match mdk.process_message(&event) {
+        let result = match mdk.process_message(&event) {
             Ok(result) => {
-                tracing::debug!(
-                  target: "whitenoise::event_handlers::handle_mls_message",
-                  "Handled MLS message - Result: {:?}",
-                  result
-                );
-
-                // Extract and store media references synchronously
-                if let Some((group_id, inner_event)) = Self::extract_message_details(&result) {
-                    let parsed_references = {
-                        let media_manager = mdk.media_manager(group_id.clone());
-                        self.media_files()
-                            .parse_imeta_tags_from_event(&inner_event, &media_manager)?
-                    };
-
-                    self.media_files()
-                        .store_parsed_media_references(
-                            &group_id,
-                            &account.pubkey,
-                            parsed_references,
-                        )
-                        .await?;
-
-                    // Cache the message and emit updates to subscribers
-                    let message = Self::build_message_from_event(&group_id, inner_event)?;
-
-                    match message.kind {
-                        Kind::Custom(9) => {
-                            let msg = self.cache_chat_message(&group_id, &message).await?;
-                            self.emit_message_update(&group_id, UpdateTrigger::NewMessage, msg);
-                        }
-                        Kind::Reaction => {
-                            if let Some(target) = self.cache_reaction(&group_id, &message).await? {
-                                self.emit_message_update(
-                                    &group_id,
-                                    UpdateTrigger::ReactionAdded,
-                                    target,
-                                );
-                            }
-                        }
-                        Kind::EventDeletion => {
-                            for (trigger, msg) in self.cache_deletion(&group_id, &message).await? {
-                                self.emit_message_update(&group_id, trigger, msg);
-                            }
-                        }
-                        _ => {
-                            tracing::debug!("Ignoring message kind {:?} for cache", message.kind);
-                        }
-                    }
-                }
-
-                // Background sync for group images (existing pattern)
-                if let MessageProcessingResult::Commit { mls_group_id } = result {
-                    Whitenoise::background_sync_group_image_cache_if_needed(account, &mls_group_id);
-                }
-                Ok(())
+                self.mark_group_in_sync(account, &event);
+                result
+            }
+            Err(e) if group_sync::is_epoch_desync(&e) => {
+                self.resync_group(account, &mdk, &event, e).await?
             }
             Err(e) => {
                 tracing::error!(
@@ -86,9 +55,111 @@ impl Whitenoise {
                     account.pubkey.to_hex(),
                     e
                 );
-                Err(WhitenoiseError::MdkCoreError(e))
+                return Err(WhitenoiseError::MdkCoreError(e));
+            }
+        };
+
+        tracing::debug!(
+          target: "whitenoise::event_handlers::handle_mls_message",
+          "Handled MLS message - Result: {:?}",
+          result
+        );
+
+        // Extract and store media references synchronously
+        if let Some((group_id, inner_event)) = Self::extract_message_details(&result) {
+            // Messages sent at an epoch before their sender was removed still decrypt, so
+            // only messages from current members are taken in
+            if !mdk.get_members(&group_id)?.contains(&inner_event.pubkey) {
+                tracing::warn!(
+                    target: "whitenoise::event_handlers::handle_mls_message",
+                    "Dropping message {} from {}, who is not a member of group {}",
+                    event.id,
+                    inner_event.pubkey.to_hex(),
+                    hex::encode(group_id.as_slice())
+                );
+                return Ok(());
+            }
+
+            let parsed_references = {
+                let media_manager = mdk.media_manager(group_id.clone());
+                self.media_files()
+                    .parse_imeta_tags_from_event(&inner_event, &media_manager)?
+            };
+
+            self.media_files()
+                .store_parsed_media_references(&group_id, &account.pubkey, parsed_references)
+                .await?;
+
+            // Cache the message and emit updates to subscribers
+            let message = Self::build_message_from_event(&group_id, inner_event)?;
+            let muted = self.muted_pubkeys(account).await?;
+
+            match message.kind {
+                Kind::Custom(9) => {
+                    let msg = self.cache_chat_message(&group_id, &message).await?;
+                    self.message_stream_manager
+                        .clear_typing(&group_id, &message.pubkey);
+                    let notify = self.should_notify(account, &group_id, &msg).await?;
+                    self.emit_message_update(
+                        &group_id,
+                        UpdateTrigger::NewMessage,
+                        msg,
+                        &muted,
+                        notify,
+                    );
+                }
+                Kind::Reaction => {
+                    if let Some(target) = self.cache_reaction(&group_id, &message).await? {
+                        self.emit_message_update(
+                            &group_id,
+                            UpdateTrigger::ReactionAdded,
+                            target,
+                            &muted,
+                            false,
+                        );
+                    }
+                }
+                Kind::EventDeletion => {
+                    for (trigger, msg) in self.cache_deletion(&group_id, &message).await? {
+                        self.emit_message_update(&group_id, trigger, msg, &muted, false);
+                    }
+                }
+                kind if kind.as_u16() == MESSAGE_EDIT_KIND => {
+                    if let Some(target) = self.cache_edit(&group_id, &message).await? {
+                        self.emit_message_update(
+                            &group_id,
+                            UpdateTrigger::MessageEdited,
+                            target,
+                            &muted,
+                            false,
+                        );
+                    }
+                }
+                kind if kind.as_u16() == SLOW_MODE_SETTINGS_KIND => {
+                    self.apply_slow_mode_settings(account, &group_id, &message)
+                        .await?;
+                }
+                kind if kind.as_u16() == TYPING_INDICATOR_KIND => {
+                    self.handle_typing_indicator(account, &group_id, &message, &muted);
+                }
+                _ => {
+                    tracing::debug!("Ignoring message kind {:?} for cache", message.kind);
+                }
             }
+            self.message_aggregator
+                .invalidate_group_state(&group_id)
+                .await;
+        }
+
+        // Background sync for group images (existing pattern)
+        if let MessageProcessingResult::Commit { mls_group_id } = result {
+            // The epoch advanced, so state aggregated at the previous one is stale
+            self.message_aggregator
+                .invalidate_group_state(&mls_group_id)
+                .await;
+            Whitenoise::background_sync_group_image_cache_if_needed(account, &mls_group_id);
         }
+        Ok(())
     }
 
     /// Extracts group_id and inner_event from MessageProcessingResult
@@ -131,14 +202,114 @@ impl Whitenoise {
     }
 
     /// Emit a message update to all subscribers of a group.
+    ///
+    /// The message is cached as received, but the update is hidden or marked when its author
+    /// is muted by the account, and reactions from muted users are left out. Updates for
+    /// messages of muted authors never notify.
     fn emit_message_update(
         &self,
         group_id: &GroupId,
         trigger: UpdateTrigger,
         message: ChatMessage,
+        muted: &HashSet<PublicKey>,
+        notify: bool,
     ) {
-        self.message_stream_manager
-            .emit(group_id, MessageUpdate { trigger, message });
+        if let Some(message) = self
+            .message_aggregator
+            .apply_muted_authors(vec![message], muted)
+            .pop()
+        {
+            let notify = notify && !message.is_muted;
+            self.message_stream_manager.emit(
+                group_id,
+                MessageUpdate {
+                    trigger,
+                    message,
+                    notify,
+                },
+            );
+        }
+    }
+
+    /// Whether a new chat message should raise a notification for the account
+    ///
+    /// The account's own messages never do; others follow the account's notification level
+    /// for the group, where a mention of the account gets through `MentionsOnly`.
+    async fn should_notify(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        message: &ChatMessage,
+    ) -> Result<bool> {
+        if message.author == account.pubkey {
+            return Ok(false);
+        }
+
+        let level = self.group_notifications(account, group_id).await?;
+        let mentions_account = message
+            .mentions
+            .iter()
+            .any(|mention| mention.pubkey == account.pubkey);
+        Ok(level.should_notify(mentions_account))
+    }
+
+    /// Apply slow mode settings published to the group by an admin.
+    ///
+    /// Settings messages sent by non-admins are ignored.
+    async fn apply_slow_mode_settings(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        message: &Message,
+    ) -> Result<()> {
+        if message.tags.identifier() != Some(SLOW_MODE_SETTINGS_D_TAG) {
+            return Ok(());
+        }
+
+        if !self
+            .group_admins(account, group_id)
+            .await?
+            .contains(&message.pubkey)
+        {
+            tracing::warn!(
+                target: "whitenoise::event_handlers::handle_mls_message",
+                "Ignoring slow mode settings from non-admin {} in group {}",
+                message.pubkey,
+                hex::encode(group_id.as_slice())
+            );
+            return Ok(());
+        }
+
+        let slow_mode: SlowMode = serde_json::from_str(&message.content)?;
+        GroupInformation::get_by_mls_group_id(account.pubkey, group_id, self).await?;
+        if GroupInformation::update_slow_mode(
+            group_id,
+            &slow_mode,
+            message.created_at,
+            false,
+            &self.database,
+        )
+        .await?
+        .is_none()
+        {
+            tracing::debug!(
+                target: "whitenoise::event_handlers::handle_mls_message",
+                "Ignoring slow mode settings {} in group {}, newer ones are applied already",
+                message.id,
+                hex::encode(group_id.as_slice())
+            );
+            return Ok(());
+        }
+
+        tracing::debug!(
+            target: "whitenoise::event_handlers::handle_mls_message",
+            "Applied slow mode settings ({}s, admins exempt: {}) to group {}",
+            slow_mode.interval_secs,
+            slow_mode.admins_exempt,
+            hex::encode(group_id.as_slice())
+        );
+
+        Ok(())
     }
 
     /// Cache a new chat message and return it for emission.
@@ -152,11 +323,19 @@ impl Whitenoise {
     ) -> Result<ChatMessage> {
         let media_files = MediaFile::find_by_group(&self.database, group_id).await?;
 
-        let chat_message = self
+        let mut chat_message = self
             .message_aggregator
             .process_single_message(message, &self.nostr, media_files)
             .await?;
 
+        // Messages the account sent carry the delivery status recorded when sending
+        if let Some(delivery_status) =
+            AggregatedMessage::find_delivery_status(&chat_message.id, group_id, &self.database)
+                .await?
+        {
+            chat_message.delivery_status = delivery_status;
+        }
+
         AggregatedMessage::insert_message(&chat_message, group_id, &self.database).await?;
 
         // Apply orphaned reactions/deletions - modifies in-place and returns final state
@@ -224,7 +403,7 @@ impl Whitenoise {
 
         let emoji = emoji_utils::validate_and_normalize_reaction(
             &reaction.content,
-            self.message_aggregator.config().normalize_emoji,
+            self.message_aggregator.config(),
         )?;
 
         reaction_handler::add_reaction_to_message(
@@ -245,6 +424,147 @@ impl Whitenoise {
         Ok(Some(target))
     }
 
+    /// Cache an edit and return the edited message for emission.
+    ///
+    /// Returns `Ok(None)` if the edited message isn't cached yet (orphaned edit) or the
+    /// edit was ignored because it isn't by the author or the message was deleted.
+    async fn cache_edit(
+        &self,
+        group_id: &GroupId,
+        message: &Message,
+    ) -> Result<Option<ChatMessage>> {
+        AggregatedMessage::insert_edit(message, group_id, &self.database).await?;
+
+        let Some(target_id) = edit_handler::extract_edit_target_id(&message.tags) else {
+            return Ok(None);
+        };
+
+        // Edits of an edit apply to the original message
+        let root_id =
+            AggregatedMessage::resolve_edit_root(&target_id, group_id, &self.database).await?;
+        let Some(mut target) =
+            AggregatedMessage::find_by_id(&root_id, group_id, &self.database).await?
+        else {
+            tracing::debug!(
+                target: "whitenoise::cache",
+                "Edit {} orphaned (target not yet cached)",
+                message.id,
+            );
+            return Ok(None);
+        };
+
+        let applied = self.apply_latest_edit(&mut target, group_id).await?;
+
+        tracing::debug!(
+            target: "whitenoise::cache",
+            "Cached edit {} of message {} in group {} (applied: {})",
+            message.id,
+            target.id,
+            hex::encode(group_id.as_slice()),
+            applied
+        );
+
+        Ok(applied.then_some(target))
+    }
+
+    /// Apply the newest edit by the author of a cached message, if it isn't applied yet.
+    ///
+    /// Looks at every cached edit of the message, including edits of its edits, so edits
+    /// that arrived out of order still end up with the newest one applied.
+    async fn apply_latest_edit(
+        &self,
+        message: &mut ChatMessage,
+        group_id: &GroupId,
+    ) -> Result<bool> {
+        let edits =
+            AggregatedMessage::find_edit_chain(&message.id, group_id, &self.database).await?;
+        let Some(latest) = edits
+            .into_iter()
+            .rev()
+            .find(|edit| edit.author == message.author)
+        else {
+            return Ok(false);
+        };
+
+        let edited_at = Timestamp::from(latest.created_at.timestamp() as u64);
+        if message.edited_at == Some(edited_at) && message.content == latest.content {
+            return Ok(false);
+        }
+        let content_tokens = self.nostr.parse(&latest.content);
+        if !edit_handler::apply_edit_to_message(
+            message,
+            &latest.author,
+            &latest.content,
+            content_tokens,
+            edited_at,
+        ) {
+            return Ok(false);
+        }
+
+        AggregatedMessage::update_edit(
+            &message.id,
+            group_id,
+            &message.content,
+            &message.content_tokens,
+            edited_at,
+            &self.database,
+        )
+        .await?;
+
+        Ok(true)
+    }
+
+    /// Apply a reaction, deletion or edit synced from MDK to the cached messages it targets.
+    ///
+    /// Sync aggregates only the events missing from the cache, so the aggregator never sees
+    /// targets that were cached earlier. Reactions to messages in `batch_message_ids` were
+    /// already aggregated and are skipped. Deletions are applied to every target, since the
+    /// aggregated batch doesn't persist them.
+    pub(crate) async fn apply_synced_event_to_cache(
+        &self,
+        event: &Message,
+        group_id: &GroupId,
+        batch_message_ids: &HashSet<String>,
+    ) -> Result<()> {
+        match event.kind {
+            Kind::Reaction => {
+                let Ok(target_id) = Self::extract_reaction_target_id(&event.tags) else {
+                    return Ok(());
+                };
+                if batch_message_ids.contains(&target_id) {
+                    return Ok(());
+                }
+                if let Err(e) = self.apply_reaction_to_target(event, group_id).await {
+                    tracing::debug!(
+                        target: "whitenoise::cache",
+                        "Skipping synced reaction {}: {}",
+                        event.id,
+                        e
+                    );
+                }
+            }
+            Kind::EventDeletion => {
+                self.apply_deletions_to_targets(event, group_id).await?;
+            }
+            _ if edit_handler::is_edit(event) => {
+                let Some(target_id) = edit_handler::extract_edit_target_id(&event.tags) else {
+                    return Ok(());
+                };
+                let root_id =
+                    AggregatedMessage::resolve_edit_root(&target_id, group_id, &self.database)
+                        .await?;
+                if let Some(mut message) =
+                    AggregatedMessage::find_by_id(&root_id, group_id, &self.database).await?
+                {
+                    self.apply_latest_edit(&mut message, group_id).await?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
     /// Cache a deletion and return updates for all affected messages.
     ///
     /// A single deletion can target multiple events (reactions and/or messages),
@@ -392,7 +712,7 @@ impl Whitenoise {
             .collect()
     }
 
-    /// Apply any orphaned reactions/deletions to a newly cached message.
+    /// Apply any orphaned reactions/edits/deletions to a newly cached message.
     ///
     /// Takes ownership of the message, modifies in-place, and returns the final state.
     /// This avoids re-fetching from the database after applying orphans.
@@ -423,7 +743,7 @@ impl Whitenoise {
         for reaction in orphaned_reactions {
             let reaction_emoji = match emoji_utils::validate_and_normalize_reaction(
                 &reaction.content,
-                self.message_aggregator.config().normalize_emoji,
+                self.message_aggregator.config(),
             ) {
                 Ok(emoji) => emoji,
                 Err(e) => {
@@ -456,6 +776,9 @@ impl Whitenoise {
             .await?;
         }
 
+        // Apply orphaned edits before deletions, which make the message uneditable
+        self.apply_latest_edit(&mut message, group_id).await?;
+
         // Apply orphaned deletions
         for deletion_event_id in orphaned_deletions {
             message.is_deleted = true;
@@ -0,0 +1,110 @@
+use nostr_sdk::prelude::*;
+
+use crate::{
+    nostr_manager::NostrManager,
+    whitenoise::{
+        Whitenoise,
+        accounts::Account,
+        database::{account_mutes::AccountMutes, processed_events::ProcessedEvent},
+        error::{Result, WhitenoiseError},
+        utils::timestamp_to_datetime,
+    },
+};
+
+impl Whitenoise {
+    /// Mute list handler, replacing the account's local mute list with the one from the event
+    /// Note: Event tracking (published/processed checks) is handled at the processor level
+    pub(crate) async fn handle_mute_list(&self, account: &Account, event: Event) -> Result<()> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+
+        if event.pubkey != account.pubkey {
+            tracing::debug!(
+                target: "whitenoise::handle_mute_list",
+                "Ignoring mute list event {} not authored by account {}",
+                event.id.to_hex(),
+                account.pubkey.to_hex()
+            );
+            return Ok(());
+        }
+
+        let event_timestamp = timestamp_to_datetime(event.created_at)?;
+        let current_event_time = ProcessedEvent::newest_event_timestamp_for_kind(
+            Some(account_id),
+            Kind::MuteList.as_u16(),
+            None,
+            &self.database,
+        )
+        .await?;
+
+        if let Some(current_time) = current_event_time
+            && event_timestamp.timestamp_millis() <= current_time.timestamp_millis()
+        {
+            tracing::debug!(
+                target: "whitenoise::handle_mute_list",
+                "Ignoring older mute list event (event: {}, current: {}) for account {}",
+                event_timestamp.timestamp_millis(),
+                current_time.timestamp_millis(),
+                account.pubkey.to_hex()
+            );
+            return Ok(());
+        }
+
+        let keys = self
+            .secrets_store
+            .get_nostr_keys_for_pubkey(&account.pubkey)?;
+        let (public, private) = NostrManager::mute_list_from_event(&event, &keys).await?;
+
+        AccountMutes::replace_all(account_id, &public, &private, &self.database).await?;
+
+        self.nostr
+            .event_tracker
+            .track_processed_account_event(&event, &account.pubkey)
+            .await?;
+
+        tracing::debug!(
+            target: "whitenoise::handle_mute_list",
+            "Successfully processed mute list with {} public and {} private entries for account {}",
+            public.len(),
+            private.len(),
+            account.pubkey.to_hex()
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::whitenoise::test_utils::*;
+    use nostr_sdk::prelude::*;
+
+    #[tokio::test]
+    async fn test_handle_mute_list_keeps_public_and_private_entries() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let keys = whitenoise
+            .secrets_store
+            .get_nostr_keys_for_pubkey(&account.pubkey)
+            .unwrap();
+        let public_entry = Keys::generate().public_key();
+        let private_entry = Keys::generate().public_key();
+
+        let private_tags = serde_json::to_string(&[Tag::public_key(private_entry)]).unwrap();
+        let content = keys
+            .nip44_encrypt(&keys.public_key(), &private_tags)
+            .await
+            .unwrap();
+        let event = EventBuilder::new(Kind::MuteList, content)
+            .tags([Tag::public_key(public_entry)])
+            .sign(&keys)
+            .await
+            .unwrap();
+
+        whitenoise.handle_mute_list(&account, event).await.unwrap();
+
+        let muted = whitenoise.muted_users(&account).await.unwrap();
+        assert_eq!(muted.len(), 2);
+        assert!(muted.contains(&public_entry));
+        assert!(muted.contains(&private_entry));
+    }
+}
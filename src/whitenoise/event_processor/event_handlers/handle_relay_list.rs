@@ -1,6 +1,7 @@
 use nostr_sdk::prelude::*;
 
 use crate::{
+    RelayType,
     nostr_manager::NostrManager,
     whitenoise::{
         Whitenoise, accounts::Account, database::processed_events::ProcessedEvent, error::Result,
@@ -34,9 +35,15 @@ impl Whitenoise {
         let relay_type = event.kind.into();
         let relay_urls = NostrManager::relay_urls_from_event(&event);
         let event_created_at = Some(timestamp_to_datetime(event.created_at)?);
-        let relays_changed = user
+        let mut relays_changed = user
             .sync_relay_urls(self, relay_type, &relay_urls, event_created_at)
             .await?;
+        if relay_type == RelayType::Nip65 {
+            let markers = NostrManager::relay_markers_from_event(&event);
+            relays_changed |= user
+                .sync_relay_markers(self, &markers, event_created_at)
+                .await?;
+        }
 
         if relays_changed {
             self.handle_subscriptions_refresh(&user, &event).await;
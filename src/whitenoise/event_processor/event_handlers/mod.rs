@@ -2,4 +2,7 @@ mod handle_contact_list;
 mod handle_giftwrap;
 mod handle_metadata;
 mod handle_mls_message;
+mod handle_mute_list;
 mod handle_relay_list;
+
+pub(crate) use handle_contact_list::ContactListDebounce;
@@ -57,8 +57,14 @@ impl Whitenoise {
             }
             Err(e) => {
                 // Handle retry logic for actual processing errors
-                if retry_info.should_retry() {
+                if retry_info.should_retry_error(&e) {
                     self.schedule_retry(event, subscription_id, retry_info, e);
+                } else if retry_info.should_retry() {
+                    tracing::error!(
+                        target: "whitenoise::event_processor::process_global_event",
+                        "Event processing failed with non-retryable error, giving up: {}",
+                        e
+                    );
                 } else {
                     tracing::error!(
                         target: "whitenoise::event_processor::process_global_event",
@@ -1,5 +1,5 @@
 use nostr_sdk::prelude::*;
-use tokio::sync::mpsc::Receiver;
+use tokio::sync::mpsc::{Receiver, Sender};
 
 use crate::{
     nostr_manager::utils::is_event_timestamp_valid,
@@ -13,16 +13,22 @@ use crate::{
 mod account_event_processor;
 mod event_handlers;
 mod global_event_processor;
+pub mod stats;
+
+pub(crate) use event_handlers::ContactListDebounce;
+
+pub use stats::{DurationHistogram, EventProcessorStats, HistogramBucket};
 
 impl Whitenoise {
     /// Start the event processing loop in a background task
     pub(crate) async fn start_event_processing_loop(
         whitenoise: &'static Whitenoise,
+        priority_receiver: Receiver<ProcessableEvent>,
         receiver: Receiver<ProcessableEvent>,
         shutdown_receiver: Receiver<()>,
     ) {
         tokio::spawn(async move {
-            Self::process_events(whitenoise, receiver, shutdown_receiver).await;
+            Self::process_events(whitenoise, priority_receiver, receiver, shutdown_receiver).await;
         });
     }
 
@@ -35,8 +41,14 @@ impl Whitenoise {
     }
 
     /// Main event processing loop
+    ///
+    /// The priority queue is always drained first; the normal queue is only read while the
+    /// priority queue is empty. Neither queue can block the other: the processor never waits
+    /// on a send, and senders route full-queue overflow of normal events off the relay
+    /// notification path (see [`crate::types::ProcessableEventSender::send`]).
     async fn process_events(
         whitenoise: &'static Whitenoise,
+        mut priority_receiver: Receiver<ProcessableEvent>,
         mut receiver: Receiver<ProcessableEvent>,
         mut shutdown: Receiver<()>,
     ) {
@@ -49,47 +61,8 @@ impl Whitenoise {
 
         loop {
             tokio::select! {
-                Some(event) = receiver.recv() => {
-                    tracing::debug!(
-                        target: "whitenoise::event_processor::process_events",
-                        "Received event for processing"
-                    );
+                biased;
 
-                    // Process the event
-                    match event {
-                        ProcessableEvent::NostrEvent { event, subscription_id, retry_info } => {
-                            // Validate timestamp before processing
-                            if !is_event_timestamp_valid(&event) {
-                                tracing::debug!(
-                                    target: "whitenoise::event_processor::process_events",
-                                    "Skipping event {} with invalid future timestamp: {}",
-                                    event.id.to_hex(),
-                                    event.created_at
-                                );
-                                continue;
-                            }
-
-                            let sub_id = match &subscription_id {
-                                Some(s) => s.clone(),
-                                None => {
-                                    tracing::warn!(
-                                        target: "whitenoise::event_processor::process_events",
-                                        "Event received without subscription ID, skipping"
-                                    );
-                                    continue;
-                                }
-                            };
-                            if whitenoise.is_event_global(&sub_id) {
-                                whitenoise.process_global_event(event, sub_id, retry_info).await;
-                            } else {
-                                whitenoise.process_account_event(event, sub_id, retry_info).await;
-                            }
-                        }
-                        ProcessableEvent::RelayMessage(relay_url, message) => {
-                            whitenoise.process_relay_message(relay_url, message).await;
-                        }
-                    }
-                }
                 Some(_) = shutdown.recv(), if !shutting_down => {
                     tracing::info!(
                         target: "whitenoise::event_processor::process_events",
@@ -98,6 +71,20 @@ impl Whitenoise {
                     shutting_down = true;
                     // Continue processing remaining events in queue, but don't wait for new shutdown signals
                 }
+                Some(event) = priority_receiver.recv() => {
+                    let (priority_sender, _) = whitenoise.event_sender.lanes();
+                    whitenoise
+                        .event_processor_metrics
+                        .record_queue_depth(true, Self::queued_after_recv(priority_sender));
+                    Self::process_event(whitenoise, event).await;
+                }
+                Some(event) = receiver.recv() => {
+                    let (_, sender) = whitenoise.event_sender.lanes();
+                    whitenoise
+                        .event_processor_metrics
+                        .record_queue_depth(false, Self::queued_after_recv(sender));
+                    Self::process_event(whitenoise, event).await;
+                }
                 else => {
                     if shutting_down {
                         tracing::debug!(
@@ -116,6 +103,77 @@ impl Whitenoise {
         }
     }
 
+    /// Queue depth including the event just taken off the queue
+    fn queued_after_recv(sender: &Sender<ProcessableEvent>) -> usize {
+        sender.max_capacity() - sender.capacity() + 1
+    }
+
+    /// Process a single event taken off either queue
+    async fn process_event(whitenoise: &'static Whitenoise, event: ProcessableEvent) {
+        tracing::debug!(
+            target: "whitenoise::event_processor::process_events",
+            "Received event for processing"
+        );
+
+        match event {
+            ProcessableEvent::NostrEvent {
+                event,
+                subscription_id,
+                relay_url,
+                retry_info,
+            } => {
+                // Validate timestamp before processing
+                if !is_event_timestamp_valid(&event) {
+                    tracing::debug!(
+                        target: "whitenoise::event_processor::process_events",
+                        "Skipping event {} with invalid future timestamp: {}",
+                        event.id.to_hex(),
+                        event.created_at
+                    );
+                    return;
+                }
+
+                let Some(sub_id) = subscription_id else {
+                    tracing::warn!(
+                        target: "whitenoise::event_processor::process_events",
+                        "Event received without subscription ID, skipping"
+                    );
+                    return;
+                };
+                // Fresh events get their retry budget from the per-kind config
+                let retry_info = if retry_info.attempt == 0 {
+                    whitenoise.config.retry_config.retry_info_for(event.kind)
+                } else {
+                    retry_info
+                };
+                let kind = event.kind;
+                let started = std::time::Instant::now();
+                if whitenoise.is_event_global(&sub_id) {
+                    whitenoise
+                        .process_global_event(event, sub_id, retry_info)
+                        .await;
+                } else {
+                    whitenoise
+                        .process_account_event(event, sub_id, relay_url, retry_info)
+                        .await;
+                }
+                whitenoise
+                    .event_processor_metrics
+                    .record_processed(kind, started.elapsed());
+            }
+            ProcessableEvent::RelayMessage(relay_url, message) => {
+                whitenoise.process_relay_message(relay_url, message).await;
+            }
+            ProcessableEvent::RelayAuth {
+                relay_url,
+                challenge,
+            } => {
+                // The handshake waits on the relay, so it mustn't hold up other events
+                whitenoise.spawn_relay_auth(relay_url, challenge);
+            }
+        }
+    }
+
     /// Process relay messages for logging/monitoring
     async fn process_relay_message(&self, relay_url: RelayUrl, message_type: String) {
         tracing::debug!(
@@ -139,6 +197,7 @@ impl Whitenoise {
         error: WhitenoiseError,
     ) {
         if let Some(next_retry) = retry_info.next_attempt() {
+            self.event_processor_metrics.record_retry();
             let delay_ms = next_retry.delay_ms();
             tracing::warn!(
                 target: "whitenoise::event_processor::schedule_retry",
@@ -152,6 +211,7 @@ impl Whitenoise {
             let retry_event = ProcessableEvent::NostrEvent {
                 event,
                 subscription_id: Some(subscription_id),
+                relay_url: None,
                 retry_info: next_retry,
             };
             let sender = self.event_sender.clone();
@@ -197,6 +257,45 @@ mod tests {
         // (We can't test queuing operations since those methods were removed)
     }
 
+    #[test]
+    fn test_retry_policy_per_error_class() {
+        use crate::types::{RetryConfig, RetryErrorClass, RetryPolicy};
+        use crate::whitenoise::error::WhitenoiseError;
+        use nostr_sdk::prelude::Kind;
+
+        let config = RetryConfig::default().with_kind_policy(
+            Kind::GiftWrap,
+            RetryPolicy {
+                max_attempts: 3,
+                base_delay_ms: 50,
+                retryable: vec![RetryErrorClass::Transient],
+            },
+        );
+
+        // Permanent failures stop immediately
+        let permanent = WhitenoiseError::InvalidEvent("undecryptable giftwrap".to_string());
+        let retry_info = config.retry_info_for(Kind::GiftWrap);
+        assert!(!retry_info.should_retry_error(&permanent));
+
+        // Transient failures retry up to the configured max for the kind
+        let transient = WhitenoiseError::SqlxError(sqlx::Error::PoolTimedOut);
+        let mut retry_info = config.retry_info_for(Kind::GiftWrap);
+        assert_eq!(retry_info.base_delay_ms, 50);
+        let mut retries = 0;
+        while retry_info.should_retry_error(&transient) {
+            retry_info = retry_info.next_attempt().unwrap();
+            retries += 1;
+        }
+        assert_eq!(retries, 3);
+
+        // Kinds without an override fall back to the default policy
+        let default_info = config.retry_info_for(Kind::MlsGroupMessage);
+        assert_eq!(
+            default_info.max_attempts,
+            RetryPolicy::default().max_attempts
+        );
+    }
+
     #[tokio::test]
     async fn test_future_timestamp_rejection() {
         use crate::nostr_manager::utils::is_event_timestamp_valid;
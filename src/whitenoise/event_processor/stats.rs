@@ -0,0 +1,258 @@
+//! Event processing loop metrics
+//!
+//! Counters are plain atomics updated inline by the processing loop, so recording stays cheap
+//! enough to do for every event.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use dashmap::DashMap;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::types::ProcessableEventSender;
+use crate::whitenoise::Whitenoise;
+
+/// Upper bounds of the processing duration histogram buckets, in milliseconds
+const DURATION_BUCKETS_MS: [u64; 8] = [1, 5, 10, 50, 100, 500, 1_000, 5_000];
+
+/// Snapshot of the event processing loop's metrics since startup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventProcessorStats {
+    /// Number of Nostr events handled, keyed by event kind
+    pub events_processed_by_kind: HashMap<u16, u64>,
+
+    /// Events waiting in the normal queue right now
+    ///
+    /// Once this reaches `queue_capacity`, further normal events wait in background tasks
+    /// until the processor catches up.
+    pub queue_depth: usize,
+
+    /// Maximum number of events the normal queue holds
+    pub queue_capacity: usize,
+
+    /// Largest normal queue depth seen by the processing loop
+    pub peak_queue_depth: usize,
+
+    /// Events waiting in the priority queue right now
+    ///
+    /// When this reaches `priority_queue_capacity` the relay notification handler blocks
+    /// until the processor catches up.
+    pub priority_queue_depth: usize,
+
+    /// Maximum number of events the priority queue holds
+    pub priority_queue_capacity: usize,
+
+    /// Largest priority queue depth seen by the processing loop
+    pub peak_priority_queue_depth: usize,
+
+    /// Number of failed events scheduled for another attempt
+    pub retries_scheduled: u64,
+
+    /// How long handling a single event took
+    pub processing_duration: DurationHistogram,
+}
+
+/// Distribution of event processing durations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DurationHistogram {
+    /// Non-cumulative bucket counts, in increasing order of upper bound
+    pub buckets: Vec<HistogramBucket>,
+
+    /// Number of recorded durations
+    pub count: u64,
+
+    /// Sum of all recorded durations, in milliseconds
+    pub total_ms: u64,
+
+    /// Longest recorded duration, in milliseconds
+    pub max_ms: u64,
+}
+
+/// One bucket of a [`DurationHistogram`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    /// Inclusive upper bound in milliseconds (`None` for the overflow bucket)
+    pub upper_bound_ms: Option<u64>,
+
+    /// Durations that fell into this bucket and no lower one
+    pub count: u64,
+}
+
+/// Live counters behind [`EventProcessorStats`]
+#[derive(Debug, Default)]
+pub(crate) struct EventProcessorMetrics {
+    processed_by_kind: DashMap<u16, u64>,
+    peak_queue_depth: AtomicUsize,
+    peak_priority_queue_depth: AtomicUsize,
+    retries_scheduled: AtomicU64,
+    duration_buckets: [AtomicU64; DURATION_BUCKETS_MS.len() + 1],
+    duration_count: AtomicU64,
+    duration_total_ms: AtomicU64,
+    duration_max_ms: AtomicU64,
+}
+
+impl EventProcessorMetrics {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that an event of `kind` was handled in `duration`
+    pub(crate) fn record_processed(&self, kind: Kind, duration: Duration) {
+        *self.processed_by_kind.entry(kind.as_u16()).or_insert(0) += 1;
+
+        let millis = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+        let bucket = DURATION_BUCKETS_MS
+            .iter()
+            .position(|bound| millis <= *bound)
+            .unwrap_or(DURATION_BUCKETS_MS.len());
+        self.duration_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.duration_count.fetch_add(1, Ordering::Relaxed);
+        // Saturate rather than wrap so a pathological duration can't reset the total
+        let _ =
+            self.duration_total_ms
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |total| {
+                    Some(total.saturating_add(millis))
+                });
+        self.duration_max_ms.fetch_max(millis, Ordering::Relaxed);
+    }
+
+    /// Record the queue depth observed when taking an event off either queue
+    pub(crate) fn record_queue_depth(&self, priority: bool, depth: usize) {
+        let peak = if priority {
+            &self.peak_priority_queue_depth
+        } else {
+            &self.peak_queue_depth
+        };
+        peak.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_retry(&self) {
+        self.retries_scheduled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Snapshot of the counters, combined with the current depth and capacity of both queues
+    pub(crate) fn snapshot(&self, event_sender: &ProcessableEventSender) -> EventProcessorStats {
+        let (priority, normal) = event_sender.lanes();
+        let queue_capacity = normal.max_capacity();
+        let queue_depth = queue_capacity.saturating_sub(normal.capacity());
+        let priority_queue_capacity = priority.max_capacity();
+        let priority_queue_depth = priority_queue_capacity.saturating_sub(priority.capacity());
+
+        let buckets = self
+            .duration_buckets
+            .iter()
+            .enumerate()
+            .map(|(i, count)| HistogramBucket {
+                upper_bound_ms: DURATION_BUCKETS_MS.get(i).copied(),
+                count: count.load(Ordering::Relaxed),
+            })
+            .collect();
+
+        EventProcessorStats {
+            events_processed_by_kind: self
+                .processed_by_kind
+                .iter()
+                .map(|entry| (*entry.key(), *entry.value()))
+                .collect(),
+            queue_depth,
+            queue_capacity,
+            peak_queue_depth: self
+                .peak_queue_depth
+                .load(Ordering::Relaxed)
+                .max(queue_depth),
+            priority_queue_depth,
+            priority_queue_capacity,
+            peak_priority_queue_depth: self
+                .peak_priority_queue_depth
+                .load(Ordering::Relaxed)
+                .max(priority_queue_depth),
+            retries_scheduled: self.retries_scheduled.load(Ordering::Relaxed),
+            processing_duration: DurationHistogram {
+                buckets,
+                count: self.duration_count.load(Ordering::Relaxed),
+                total_ms: self.duration_total_ms.load(Ordering::Relaxed),
+                max_ms: self.duration_max_ms.load(Ordering::Relaxed),
+            },
+        }
+    }
+}
+
+impl Whitenoise {
+    /// Metrics of the event processing loop since startup
+    ///
+    /// Queue depths are read live from the event channels; everything else is accumulated
+    /// as events are processed.
+    pub fn event_processor_stats(&self) -> EventProcessorStats {
+        self.event_processor_metrics.snapshot(&self.event_sender)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ProcessableEvent;
+    use crate::whitenoise::test_utils::create_mock_whitenoise;
+
+    #[tokio::test]
+    async fn test_metrics_snapshot() {
+        let (priority_tx, _priority_rx) = tokio::sync::mpsc::channel(10);
+        let (normal_tx, _normal_rx) = tokio::sync::mpsc::channel(500);
+        let relay_url = RelayUrl::parse("wss://relay.example.com").unwrap();
+        for _ in 0..4 {
+            normal_tx
+                .send(ProcessableEvent::RelayMessage(
+                    relay_url.clone(),
+                    "Ok".to_string(),
+                ))
+                .await
+                .unwrap();
+        }
+        let event_sender = ProcessableEventSender::new(priority_tx, normal_tx);
+
+        let metrics = EventProcessorMetrics::new();
+        metrics.record_processed(Kind::GiftWrap, Duration::from_millis(3));
+        metrics.record_processed(Kind::GiftWrap, Duration::from_millis(0));
+        metrics.record_processed(Kind::MlsGroupMessage, Duration::from_secs(60));
+        metrics.record_queue_depth(false, 7);
+        metrics.record_queue_depth(false, 2);
+        metrics.record_queue_depth(true, 3);
+        metrics.record_retry();
+
+        let stats = metrics.snapshot(&event_sender);
+        assert_eq!(stats.events_processed_by_kind[&1059], 2);
+        assert_eq!(stats.events_processed_by_kind[&445], 1);
+        assert_eq!(stats.queue_depth, 4);
+        assert_eq!(stats.queue_capacity, 500);
+        assert_eq!(stats.peak_queue_depth, 7);
+        assert_eq!(stats.priority_queue_depth, 0);
+        assert_eq!(stats.priority_queue_capacity, 10);
+        assert_eq!(stats.peak_priority_queue_depth, 3);
+        assert_eq!(stats.retries_scheduled, 1);
+
+        let histogram = &stats.processing_duration;
+        assert_eq!(histogram.count, 3);
+        assert_eq!(histogram.total_ms, 60_003);
+        assert_eq!(histogram.max_ms, 60_000);
+        assert_eq!(histogram.buckets.len(), DURATION_BUCKETS_MS.len() + 1);
+        assert_eq!(histogram.buckets[0].count, 1);
+        assert_eq!(histogram.buckets[1].count, 1);
+        let overflow = histogram.buckets.last().unwrap();
+        assert_eq!(overflow.upper_bound_ms, None);
+        assert_eq!(overflow.count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_event_processor_stats_reports_queue_capacity() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+
+        let stats = whitenoise.event_processor_stats();
+        assert_eq!(stats.queue_depth, 0);
+        let (priority, normal) = whitenoise.event_sender.lanes();
+        assert_eq!(stats.queue_capacity, normal.max_capacity());
+        assert_eq!(stats.priority_queue_capacity, priority.max_capacity());
+        assert!(stats.events_processed_by_kind.is_empty());
+        assert_eq!(stats.processing_duration.count, 0);
+    }
+}
@@ -1,12 +1,21 @@
-use nostr_sdk::PublicKey;
+use std::collections::HashSet;
 
+use nostr_sdk::{PublicKey, RelayUrl};
+
+use crate::nostr_manager::NostrManager;
 use crate::whitenoise::{
     Whitenoise,
     accounts::Account,
+    database::account_mutes::AccountMutes,
     error::{Result, WhitenoiseError},
-    users::User,
+    relays::{Relay, RelayType},
+    users::{User, UserSyncMode},
+    utils::timestamp_to_datetime,
 };
 
+/// Most contacts [`Whitenoise::import_follows_from_event`] adds in one import
+pub const MAX_IMPORTED_FOLLOWS: usize = 1000;
+
 impl Whitenoise {
     /// Creates a follow relationship between an account and a user.
     ///
@@ -83,12 +92,227 @@ impl Whitenoise {
     pub async fn follows(&self, account: &Account) -> Result<Vec<User>> {
         account.follows(&self.database).await
     }
+
+    /// Follows everyone another user follows.
+    ///
+    /// Fetches the NIP-02 contact list `source_pubkey` published, from their NIP-65 relays,
+    /// the account's own NIP-65 relays and the default relays, and adds its contacts to the
+    /// account's follows. Contacts the account already follows and the account itself are
+    /// skipped, and at most [`MAX_IMPORTED_FOLLOWS`] contacts are added, in list order. The
+    /// account's updated follow list is published and metadata of the new follows is fetched
+    /// in the background.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account that will follow the contacts
+    /// * `source_pubkey` - The user whose contact list to import
+    ///
+    /// # Returns
+    ///
+    /// The number of newly followed users (0 if `source_pubkey` has no contact list)
+    pub async fn import_follows_from_event(
+        &self,
+        account: &Account,
+        source_pubkey: &PublicKey,
+    ) -> Result<usize> {
+        let source = self
+            .find_or_create_user_by_pubkey(source_pubkey, UserSyncMode::Blocking)
+            .await?;
+        let mut query_relays: HashSet<RelayUrl> =
+            Relay::urls(&source.relays(RelayType::Nip65, &self.database).await?)
+                .into_iter()
+                .collect();
+        query_relays.extend(Relay::urls(&account.nip65_relays(self).await?));
+        query_relays.extend(Relay::urls(&self.default_relays()));
+        let query_relays: Vec<RelayUrl> = query_relays.into_iter().collect();
+
+        let Some(event) = self
+            .nostr
+            .fetch_contact_list(*source_pubkey, &query_relays)
+            .await?
+        else {
+            return Ok(0);
+        };
+
+        let mut follows: HashSet<PublicKey> = self
+            .follows(account)
+            .await?
+            .into_iter()
+            .map(|user| user.pubkey)
+            .collect();
+        let mut new_follows = Vec::new();
+        for pubkey in NostrManager::pubkeys_from_event(&event) {
+            if new_follows.len() == MAX_IMPORTED_FOLLOWS {
+                tracing::warn!(
+                    target: "whitenoise::follows::import_follows_from_event",
+                    "Contact list of {} is too long, importing only {} contacts",
+                    source_pubkey.to_hex(),
+                    MAX_IMPORTED_FOLLOWS
+                );
+                break;
+            }
+            if pubkey != account.pubkey && follows.insert(pubkey) {
+                new_follows.push(pubkey);
+            }
+        }
+        if new_follows.is_empty() {
+            return Ok(0);
+        }
+
+        account
+            .update_follows_from_event(follows.into_iter().collect(), &self.database)
+            .await?;
+        for pubkey in &new_follows {
+            let user = User::find_by_pubkey(pubkey, &self.database).await?;
+            self.background_fetch_user_data(&user).await?;
+        }
+        self.background_publish_account_follow_list(account).await?;
+
+        Ok(new_follows.len())
+    }
+
+    /// Discovers where the account's follows publish and listens for them there.
+    ///
+    /// Fetches the NIP-65 relay list of every followed user in one request, from the account's
+    /// own NIP-65 relays and the default relays, so follows whose lists never reached the
+    /// event processor are found too. Each list is stored with its read/write markers like a
+    /// received relay list event, replacing older lists and leaving unchanged ones untouched.
+    /// When any follow's relays changed, the global subscriptions are rebuilt so their
+    /// metadata and relay list updates are received from the relays they write to.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account whose follows to discover relays for
+    pub async fn discover_and_cache_contact_relays(&self, account: &Account) -> Result<()> {
+        let follows = self.follows(account).await?;
+        if follows.is_empty() {
+            return Ok(());
+        }
+
+        let mut query_relays: HashSet<RelayUrl> = Relay::urls(&account.nip65_relays(self).await?)
+            .into_iter()
+            .collect();
+        query_relays.extend(Relay::urls(&self.default_relays()));
+        let query_relays: Vec<RelayUrl> = query_relays.into_iter().collect();
+
+        let pubkeys: Vec<PublicKey> = follows.iter().map(|user| user.pubkey).collect();
+        let relay_lists = self
+            .nostr
+            .fetch_relay_lists(&pubkeys, RelayType::Nip65, &query_relays)
+            .await?;
+
+        let mut changed_users = 0usize;
+        for user in &follows {
+            let Some(event) = relay_lists.get(&user.pubkey) else {
+                continue;
+            };
+            let relay_urls = NostrManager::relay_urls_from_event(event);
+            if relay_urls.is_empty() {
+                continue;
+            }
+
+            let event_created_at = Some(timestamp_to_datetime(event.created_at)?);
+            let mut changed = user
+                .sync_relay_urls(self, RelayType::Nip65, &relay_urls, event_created_at)
+                .await?;
+            changed |= user
+                .sync_relay_markers(
+                    self,
+                    &NostrManager::relay_markers_from_event(event),
+                    event_created_at,
+                )
+                .await?;
+            if changed {
+                self.nostr
+                    .event_tracker
+                    .track_processed_global_event(event)
+                    .await?;
+                changed_users += 1;
+            }
+        }
+
+        tracing::info!(
+            target: "whitenoise::follows::discover_and_cache_contact_relays",
+            "Found relay lists for {} of {} follows, {} changed",
+            relay_lists.len(),
+            follows.len(),
+            changed_users
+        );
+
+        if changed_users > 0 {
+            Self::setup_global_users_subscriptions(self).await?;
+        }
+        Ok(())
+    }
+
+    /// Mutes a user for an account.
+    ///
+    /// The user is added as a private entry of the account's NIP-51 mute list, which is then
+    /// republished with the private entries encrypted to the account's own key. Messages and
+    /// reactions from muted users are hidden or marked when messages are aggregated, depending
+    /// on the aggregator's [`MutedAuthors`](crate::whitenoise::message_aggregator::MutedAuthors)
+    /// setting. Muting an already muted user does nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account muting the user (must exist in database with valid ID)
+    /// * `pubkey` - The public key of the user to mute
+    pub async fn mute_user(&self, account: &Account, pubkey: &PublicKey) -> Result<()> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        if AccountMutes::add(account_id, pubkey, true, &self.database).await? {
+            self.background_publish_account_mute_list(account).await?;
+        }
+        Ok(())
+    }
+
+    /// Unmutes a user for an account.
+    ///
+    /// Removes the user from the account's mute list, whether the entry was public or
+    /// private, and republishes the list. Unmuting a user that isn't muted does nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account unmuting the user (must exist in database with valid ID)
+    /// * `pubkey` - The public key of the user to unmute
+    pub async fn unmute_user(&self, account: &Account, pubkey: &PublicKey) -> Result<()> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        if AccountMutes::remove(account_id, pubkey, &self.database).await? {
+            self.background_publish_account_mute_list(account).await?;
+        }
+        Ok(())
+    }
+
+    /// Retrieves the public keys of all users an account has muted.
+    ///
+    /// Includes both the public and the private entries of the account's mute list, in the
+    /// order they were muted.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account whose mute list to retrieve (must exist in database with valid ID)
+    pub async fn muted_users(&self, account: &Account) -> Result<Vec<PublicKey>> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        Ok(AccountMutes::find_by_account(account_id, &self.database)
+            .await?
+            .into_iter()
+            .map(|entry| entry.pubkey)
+            .collect())
+    }
+
+    /// The public keys the account has muted, for filtering messages
+    pub(crate) async fn muted_pubkeys(&self, account: &Account) -> Result<HashSet<PublicKey>> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        Ok(AccountMutes::pubkeys(account_id, &self.database).await?)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use nostr_sdk::Keys;
+    use std::collections::HashSet;
 
+    use nostr_sdk::{Keys, PublicKey};
+
+    use crate::whitenoise::relays::{Relay, RelayType};
     use crate::whitenoise::test_utils::*;
 
     #[tokio::test]
@@ -341,4 +565,112 @@ mod tests {
                 .unwrap()
         );
     }
+
+    #[tokio::test]
+    async fn test_mute_and_unmute_user() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let target1 = Keys::generate().public_key();
+        let target2 = Keys::generate().public_key();
+
+        assert!(whitenoise.muted_users(&account).await.unwrap().is_empty());
+
+        whitenoise.mute_user(&account, &target1).await.unwrap();
+        whitenoise.mute_user(&account, &target2).await.unwrap();
+        whitenoise.mute_user(&account, &target1).await.unwrap();
+        assert_eq!(
+            whitenoise.muted_users(&account).await.unwrap(),
+            vec![target1, target2]
+        );
+
+        whitenoise.unmute_user(&account, &target1).await.unwrap();
+        whitenoise.unmute_user(&account, &target1).await.unwrap();
+        assert_eq!(
+            whitenoise.muted_users(&account).await.unwrap(),
+            vec![target2]
+        );
+
+        // Muting doesn't affect follows
+        assert!(whitenoise.follows(&account).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_discover_contact_relays_without_published_lists() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+
+        // Nothing to discover without follows
+        whitenoise
+            .discover_and_cache_contact_relays(&account)
+            .await
+            .unwrap();
+
+        // A follow that never published a relay list keeps an empty relay set
+        let target = Keys::generate().public_key();
+        whitenoise.follow_user(&account, &target).await.unwrap();
+        whitenoise
+            .discover_and_cache_contact_relays(&account)
+            .await
+            .unwrap();
+
+        let user = whitenoise.find_user_by_pubkey(&target).await.unwrap();
+        let relays = user
+            .relays(RelayType::Nip65, &whitenoise.database)
+            .await
+            .unwrap();
+        assert!(relays.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_follows_from_event() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        let source = whitenoise.create_identity().await.unwrap();
+        let already_followed = Keys::generate().public_key();
+        let new_contact = Keys::generate().public_key();
+        whitenoise
+            .follow_user(&account, &already_followed)
+            .await
+            .unwrap();
+
+        // Nothing to import before the source publishes a contact list
+        assert_eq!(
+            whitenoise
+                .import_follows_from_event(&account, &source.pubkey)
+                .await
+                .unwrap(),
+            0
+        );
+
+        let source_keys = whitenoise
+            .secrets_store
+            .get_nostr_keys_for_pubkey(&source.pubkey)
+            .unwrap();
+        whitenoise
+            .nostr
+            .publish_follow_list_with_signer(
+                &[already_followed, new_contact, account.pubkey],
+                &Relay::urls(&source.nip65_relays(&whitenoise).await.unwrap()),
+                source_keys,
+            )
+            .await
+            .unwrap();
+
+        // Only the contact the account didn't follow yet is added
+        assert_eq!(
+            whitenoise
+                .import_follows_from_event(&account, &source.pubkey)
+                .await
+                .unwrap(),
+            1
+        );
+        let follows: HashSet<PublicKey> = whitenoise
+            .follows(&account)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|user| user.pubkey)
+            .collect();
+        assert_eq!(follows, HashSet::from([already_followed, new_contact]));
+    }
 }
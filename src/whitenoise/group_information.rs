@@ -1,4 +1,4 @@
-use std::{fmt, str::FromStr};
+use std::{fmt, str::FromStr, time::Duration};
 
 use chrono::{DateTime, Utc};
 use mdk_core::prelude::GroupId;
@@ -40,11 +40,71 @@ impl FromStr for GroupType {
     }
 }
 
+/// Event kind of the MLS application message that admins use to publish slow mode settings
+///
+/// Uses the NIP-78 application-specific data kind with a [`SLOW_MODE_SETTINGS_D_TAG`] `d` tag,
+/// since the MLS group data extension has no room for client-specific settings.
+pub const SLOW_MODE_SETTINGS_KIND: u16 = 30078;
+
+/// `d` tag identifying a slow mode settings message
+pub const SLOW_MODE_SETTINGS_D_TAG: &str = "whitenoise/slow_mode";
+
+/// Per-group slow mode settings
+///
+/// When `interval_secs` is non-zero, a member may send at most one chat message per interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlowMode {
+    /// Minimum number of seconds between two chat messages from the same member (0 = disabled)
+    pub interval_secs: u64,
+    /// Whether group admins are exempt from the interval
+    pub admins_exempt: bool,
+}
+
+impl Default for SlowMode {
+    fn default() -> Self {
+        Self {
+            interval_secs: 0,
+            admins_exempt: true,
+        }
+    }
+}
+
+impl SlowMode {
+    pub fn is_enabled(&self) -> bool {
+        self.interval_secs > 0
+    }
+
+    /// Returns how long the sender still has to wait before sending another chat message
+    ///
+    /// # Arguments
+    /// * `last_sent_at` - Unix timestamp (seconds) of the sender's last chat message, if any
+    /// * `now` - Current unix timestamp (seconds)
+    /// * `is_admin` - Whether the sender is an admin of the group
+    ///
+    /// # Returns
+    /// * `None` if the sender may send now
+    /// * `Some(remaining)` with the remaining cooldown otherwise
+    pub fn remaining_cooldown(
+        &self,
+        last_sent_at: Option<u64>,
+        now: u64,
+        is_admin: bool,
+    ) -> Option<Duration> {
+        if !self.is_enabled() || (is_admin && self.admins_exempt) {
+            return None;
+        }
+
+        let next_allowed = last_sent_at?.saturating_add(self.interval_secs);
+        (next_allowed > now).then(|| Duration::from_secs(next_allowed - now))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GroupInformation {
     pub id: Option<i64>,
     pub mls_group_id: GroupId,
     pub group_type: GroupType,
+    pub slow_mode: SlowMode,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -220,6 +280,52 @@ mod tests {
         assert_eq!(dm_type, deserialized);
     }
 
+    #[test]
+    fn test_slow_mode_rate_limits_members() {
+        let slow_mode = SlowMode {
+            interval_secs: 60,
+            admins_exempt: true,
+        };
+
+        // No previous message, nothing to wait for
+        assert_eq!(slow_mode.remaining_cooldown(None, 1_000, false), None);
+        // Within the interval
+        assert_eq!(
+            slow_mode.remaining_cooldown(Some(1_000), 1_045, false),
+            Some(Duration::from_secs(15))
+        );
+        // Interval elapsed
+        assert_eq!(
+            slow_mode.remaining_cooldown(Some(1_000), 1_060, false),
+            None
+        );
+    }
+
+    #[test]
+    fn test_slow_mode_admin_exemption() {
+        let mut slow_mode = SlowMode {
+            interval_secs: 60,
+            admins_exempt: true,
+        };
+        assert_eq!(slow_mode.remaining_cooldown(Some(1_000), 1_001, true), None);
+
+        slow_mode.admins_exempt = false;
+        assert_eq!(
+            slow_mode.remaining_cooldown(Some(1_000), 1_001, true),
+            Some(Duration::from_secs(59))
+        );
+    }
+
+    #[test]
+    fn test_slow_mode_disabled() {
+        let slow_mode = SlowMode::default();
+        assert!(!slow_mode.is_enabled());
+        assert_eq!(
+            slow_mode.remaining_cooldown(Some(1_000), 1_000, false),
+            None
+        );
+    }
+
     #[test]
     fn test_infer_group_type_from_group_name() {
         assert_eq!(
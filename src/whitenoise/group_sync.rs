@@ -0,0 +1,529 @@
+//! Recovery of groups whose MLS state fell behind
+//!
+//! A member that misses a commit stays on the old epoch and can't decrypt anything sent after
+//! it. When a group message fails to decrypt for that reason, the group's messages are fetched
+//! from its relays and replayed in order, so the missed commits are applied, before the message
+//! is tried again. [`Whitenoise::group_sync_status`] tells the UI while that is going on, and
+//! whether it failed. A group that failed to catch up is tried again, with a growing delay,
+//! when a later message fails to decrypt.
+
+use std::time::Duration;
+
+use mdk_core::prelude::*;
+use mdk_sqlite_storage::MdkSqliteStorage;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::whitenoise::{
+    Whitenoise,
+    accounts::Account,
+    error::{Result, WhitenoiseError},
+};
+
+/// Whether an account's copy of a group keeps up with the group's MLS epoch
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GroupSyncStatus {
+    /// Messages decrypt normally
+    #[default]
+    InSync,
+
+    /// Missed commits are being fetched and replayed
+    CatchingUp,
+
+    /// Replaying the group's messages didn't catch up; new messages can't be decrypted
+    ///
+    /// The first message that fails to decrypt at or after `retry_at` replays the group again.
+    /// The delay doubles with every failed attempt, up to an hour.
+    Failed {
+        /// Why the last attempt failed
+        reason: String,
+        /// Failed attempts in a row
+        attempts: u32,
+        /// When the group may be replayed again
+        retry_at: Timestamp,
+    },
+}
+
+/// How far before the last processed message replaying a group starts, to allow for commits
+/// whose timestamps are a little off
+const REPLAY_LOOKBACK: Duration = Duration::from_secs(10 * 60);
+
+/// Delay before replaying a group again after the first failed attempt
+const RETRY_INITIAL_DELAY: Duration = Duration::from_secs(30);
+
+/// Longest delay between replays of a group that keeps failing to catch up
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// Delay before replaying a group again after `attempts` failed attempts in a row
+fn retry_delay(attempts: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+    RETRY_INITIAL_DELAY
+        .saturating_mul(factor)
+        .min(RETRY_MAX_DELAY)
+}
+
+/// Whether an MDK error means the message is from an epoch the account doesn't have
+///
+/// Other failures, such as duplicates, the account's own messages, messages of groups the
+/// account was removed from or undecryptable garbage, aren't fixed by catching up.
+pub(crate) fn is_epoch_desync(error: &mdk_core::Error) -> bool {
+    matches!(error, mdk_core::Error::ProcessMessageWrongEpoch)
+}
+
+impl Whitenoise {
+    /// Whether the account's copy of a group is in sync, catching up or stuck
+    ///
+    /// # Arguments
+    /// * `account` - The account whose copy of the group to check
+    /// * `group_id` - The group to check
+    ///
+    /// # Errors
+    /// Returns [`WhitenoiseError::GroupNotFound`] if the account doesn't know the group.
+    pub async fn group_sync_status(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+    ) -> Result<GroupSyncStatus> {
+        let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
+        let group = mdk
+            .get_group(group_id)?
+            .ok_or(WhitenoiseError::GroupNotFound)?;
+        let key = (account.pubkey, hex::encode(group.nostr_group_id));
+
+        Ok(self
+            .group_sync_status
+            .get(&key)
+            .map(|status| status.clone())
+            .unwrap_or_default())
+    }
+
+    /// Forgets a desync of the group a successfully processed message belongs to
+    pub(crate) fn mark_group_in_sync(&self, account: &Account, event: &Event) {
+        if let Some(nostr_group_id) = nostr_group_id_of(event) {
+            self.group_sync_status
+                .remove(&(account.pubkey, nostr_group_id.to_string()));
+        }
+    }
+
+    /// Catches a group up after `event` failed to decrypt for being from an unknown epoch
+    ///
+    /// Fetches the group's messages since shortly before the last message the account
+    /// processed from its relays, replays them oldest first, copies any recovered chat
+    /// messages into the cache and then processes `event` again. If `event` still can't be
+    /// processed the group is marked [`GroupSyncStatus::Failed`], and later messages fail fast
+    /// with [`WhitenoiseError::GroupOutOfSync`] until its `retry_at`, when the next message
+    /// that fails to decrypt replays the group again. A message that decrypts ends the desync.
+    pub(crate) async fn resync_group(
+        &self,
+        account: &Account,
+        mdk: &MDK<MdkSqliteStorage>,
+        event: &Event,
+        error: mdk_core::Error,
+    ) -> Result<MessageProcessingResult> {
+        let Some(nostr_group_id) = nostr_group_id_of(event).map(str::to_string) else {
+            return Err(WhitenoiseError::MdkCoreError(error));
+        };
+        let Some(group) = mdk
+            .get_groups()?
+            .into_iter()
+            .find(|group| hex::encode(group.nostr_group_id) == nostr_group_id)
+        else {
+            return Err(WhitenoiseError::MdkCoreError(error));
+        };
+
+        let key = (account.pubkey, nostr_group_id);
+        let failed_attempts = match self
+            .group_sync_status
+            .get(&key)
+            .map(|status| status.clone())
+        {
+            Some(GroupSyncStatus::CatchingUp) => {
+                return Err(WhitenoiseError::GroupOutOfSync(
+                    "Group is already catching up".to_string(),
+                ));
+            }
+            Some(GroupSyncStatus::Failed {
+                reason,
+                attempts,
+                retry_at,
+            }) => {
+                if Timestamp::now() < retry_at {
+                    return Err(WhitenoiseError::GroupOutOfSync(reason));
+                }
+                attempts
+            }
+            _ => 0,
+        };
+
+        tracing::warn!(
+            target: "whitenoise::group_sync",
+            "Group {} of account {} is behind ({}), catching up",
+            hex::encode(group.mls_group_id.as_slice()),
+            account.pubkey.to_hex(),
+            error
+        );
+        self.group_sync_status
+            .insert(key.clone(), GroupSyncStatus::CatchingUp);
+
+        let result = self
+            .replay_group_messages(account, mdk, &group, event)
+            .await;
+        match result {
+            Ok(result) => {
+                self.group_sync_status.remove(&key);
+                tracing::info!(
+                    target: "whitenoise::group_sync",
+                    "Group {} of account {} caught up",
+                    hex::encode(group.mls_group_id.as_slice()),
+                    account.pubkey.to_hex()
+                );
+                Ok(result)
+            }
+            Err(e) => {
+                let reason = format!("Could not catch up with the group: {}", e);
+                let attempts = failed_attempts.saturating_add(1);
+                self.group_sync_status.insert(
+                    key,
+                    GroupSyncStatus::Failed {
+                        reason: reason.clone(),
+                        attempts,
+                        retry_at: Timestamp::now() + retry_delay(attempts),
+                    },
+                );
+                Err(WhitenoiseError::GroupOutOfSync(reason))
+            }
+        }
+    }
+
+    async fn replay_group_messages(
+        &self,
+        account: &Account,
+        mdk: &MDK<MdkSqliteStorage>,
+        group: &group_types::Group,
+        event: &Event,
+    ) -> Result<MessageProcessingResult> {
+        let relays: Vec<RelayUrl> = mdk.get_relays(&group.mls_group_id)?.into_iter().collect();
+        // The commits the account missed came after the last message it could decrypt
+        let since = mdk
+            .get_messages(&group.mls_group_id)?
+            .iter()
+            .map(|message| message.created_at)
+            .max()
+            .map(|last_processed| last_processed - REPLAY_LOOKBACK);
+        let mut events = self
+            .nostr
+            .fetch_group_messages(&relays, &[hex::encode(group.nostr_group_id)], since)
+            .await?;
+        events.sort_by_key(|event| (event.created_at, event.id));
+
+        // Messages that were already processed or can't be read yet fail here, which is fine:
+        // only the commits the account missed need to go through
+        for replayed in events.iter().filter(|replayed| replayed.id != event.id) {
+            if let Err(e) = mdk.process_message(replayed) {
+                tracing::debug!(
+                    target: "whitenoise::group_sync",
+                    "Skipping replayed message {}: {}",
+                    replayed.id,
+                    e
+                );
+            }
+        }
+
+        let result = mdk.process_message(event)?;
+        self.sync_cache_for_group(
+            &account.pubkey,
+            &group.mls_group_id,
+            mdk.get_messages(&group.mls_group_id)?,
+        )
+        .await?;
+        Ok(result)
+    }
+}
+
+/// The hex-encoded Nostr group ID from a group message's `h` tag
+fn nostr_group_id_of(event: &Event) -> Option<&str> {
+    event.tags.find(TagKind::h()).and_then(|tag| tag.content())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::*;
+
+    #[tokio::test]
+    async fn test_group_sync_status() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let group = whitenoise
+            .create_group(&creator_account, vec![members[0].0.pubkey], config, None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            whitenoise
+                .group_sync_status(&creator_account, &group.mls_group_id)
+                .await
+                .unwrap(),
+            GroupSyncStatus::InSync
+        );
+
+        let failed = GroupSyncStatus::Failed {
+            reason: "missing commit".to_string(),
+            attempts: 1,
+            retry_at: Timestamp::now() + retry_delay(1),
+        };
+        whitenoise.group_sync_status.insert(
+            (creator_account.pubkey, hex::encode(group.nostr_group_id)),
+            failed.clone(),
+        );
+        assert_eq!(
+            whitenoise
+                .group_sync_status(&creator_account, &group.mls_group_id)
+                .await
+                .unwrap(),
+            failed
+        );
+
+        assert!(matches!(
+            whitenoise
+                .group_sync_status(&creator_account, &GroupId::from_slice(&[9; 32]))
+                .await,
+            Err(WhitenoiseError::GroupNotFound)
+        ));
+    }
+
+    /// A chat message from `author`, encrypted at the epoch of `author`'s copy of the group
+    fn create_chat_message(
+        mdk: &MDK<MdkSqliteStorage>,
+        author: &Account,
+        group_id: &GroupId,
+        content: &str,
+    ) -> Event {
+        let mut inner = UnsignedEvent::new(
+            author.pubkey,
+            Timestamp::now(),
+            Kind::Custom(9),
+            vec![],
+            content.to_string(),
+        );
+        inner.ensure_id();
+        mdk.create_message(group_id, inner).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_resync_group_replays_missed_commit() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member = &members[0].0;
+        let group_id = create_group_with_joined_member(&whitenoise, &creator, member).await;
+        let creator_mdk = Account::create_mdk(creator.pubkey, &whitenoise.config.data_dir).unwrap();
+        let member_mdk = Account::create_mdk(member.pubkey, &whitenoise.config.data_dir).unwrap();
+
+        let before = create_chat_message(&creator_mdk, &creator, &group_id, "before the commit");
+        member_mdk.process_message(&before).unwrap();
+
+        // The commit reaches the relays but the member never processes it
+        whitenoise
+            .update_group_relays(
+                &creator,
+                &group_id,
+                vec![RelayUrl::parse("ws://localhost:8080").unwrap()],
+            )
+            .await
+            .unwrap();
+
+        let after = create_chat_message(&creator_mdk, &creator, &group_id, "after the commit");
+        let error = member_mdk.process_message(&after).unwrap_err();
+        assert!(is_epoch_desync(&error), "unexpected error: {}", error);
+
+        whitenoise
+            .resync_group(member, &member_mdk, &after, error)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            whitenoise
+                .group_sync_status(member, &group_id)
+                .await
+                .unwrap(),
+            GroupSyncStatus::InSync
+        );
+        let messages = whitenoise
+            .fetch_aggregated_messages_for_group(&member.pubkey, &group_id)
+            .await
+            .unwrap();
+        assert!(
+            messages
+                .iter()
+                .any(|message| message.content == "after the commit")
+        );
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_an_hour() {
+        assert_eq!(retry_delay(1), Duration::from_secs(30));
+        assert_eq!(retry_delay(2), Duration::from_secs(60));
+        assert_eq!(retry_delay(3), Duration::from_secs(120));
+        assert_eq!(retry_delay(8), Duration::from_secs(3600));
+        assert_eq!(retry_delay(u32::MAX), Duration::from_secs(3600));
+    }
+
+    /// Key of the member's entry in `group_sync_status`
+    fn sync_status_key(
+        mdk: &MDK<MdkSqliteStorage>,
+        member: &Account,
+        group_id: &GroupId,
+    ) -> (PublicKey, String) {
+        let group = mdk.get_group(group_id).unwrap().unwrap();
+        (member.pubkey, hex::encode(group.nostr_group_id))
+    }
+
+    fn failed(attempts: u32, retry_at: Timestamp) -> GroupSyncStatus {
+        GroupSyncStatus::Failed {
+            reason: "missing commit".to_string(),
+            attempts,
+            retry_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_group_is_replayed_again_after_retry_at() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member = &members[0].0;
+        let group_id = create_group_with_joined_member(&whitenoise, &creator, member).await;
+        let creator_mdk = Account::create_mdk(creator.pubkey, &whitenoise.config.data_dir).unwrap();
+        let member_mdk = Account::create_mdk(member.pubkey, &whitenoise.config.data_dir).unwrap();
+        let key = sync_status_key(&member_mdk, member, &group_id);
+
+        whitenoise
+            .update_group_relays(
+                &creator,
+                &group_id,
+                vec![RelayUrl::parse("ws://localhost:8080").unwrap()],
+            )
+            .await
+            .unwrap();
+        let after = create_chat_message(&creator_mdk, &creator, &group_id, "after the commit");
+
+        // Before `retry_at` the group isn't replayed, although it could catch up now
+        let retry_at = Timestamp::now() + Duration::from_secs(60);
+        whitenoise
+            .group_sync_status
+            .insert(key.clone(), failed(1, retry_at));
+        let error = member_mdk.process_message(&after).unwrap_err();
+        let result = whitenoise
+            .resync_group(member, &member_mdk, &after, error)
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::GroupOutOfSync(_))));
+        assert_eq!(
+            whitenoise
+                .group_sync_status(member, &group_id)
+                .await
+                .unwrap(),
+            failed(1, retry_at)
+        );
+
+        // After it, the next message that fails to decrypt replays the group
+        whitenoise
+            .group_sync_status
+            .insert(key, failed(1, Timestamp::from(0)));
+        let error = member_mdk.process_message(&after).unwrap_err();
+        whitenoise
+            .resync_group(member, &member_mdk, &after, error)
+            .await
+            .unwrap();
+        assert_eq!(
+            whitenoise
+                .group_sync_status(member, &group_id)
+                .await
+                .unwrap(),
+            GroupSyncStatus::InSync
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_replay_backs_off_further() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member = &members[0].0;
+        let group_id = create_group_with_joined_member(&whitenoise, &creator, member).await;
+        let creator_mdk = Account::create_mdk(creator.pubkey, &whitenoise.config.data_dir).unwrap();
+        let member_mdk = Account::create_mdk(member.pubkey, &whitenoise.config.data_dir).unwrap();
+        let key = sync_status_key(&member_mdk, member, &group_id);
+
+        // A commit that never reaches the relays can't be caught up with
+        creator_mdk.self_update(&group_id).unwrap();
+        creator_mdk.merge_pending_commit(&group_id).unwrap();
+        let lost = create_chat_message(&creator_mdk, &creator, &group_id, "after a lost commit");
+
+        whitenoise
+            .group_sync_status
+            .insert(key, failed(2, Timestamp::from(0)));
+        let error = member_mdk.process_message(&lost).unwrap_err();
+        let result = whitenoise
+            .resync_group(member, &member_mdk, &lost, error)
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::GroupOutOfSync(_))));
+
+        match whitenoise
+            .group_sync_status(member, &group_id)
+            .await
+            .unwrap()
+        {
+            GroupSyncStatus::Failed {
+                attempts, retry_at, ..
+            } => {
+                assert_eq!(attempts, 3);
+                assert!(retry_at > Timestamp::now() + retry_delay(2));
+            }
+            other => panic!("unexpected status {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_is_epoch_desync_ignores_other_errors() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member = &members[0].0;
+        let group_id = create_group_with_joined_member(&whitenoise, &creator, member).await;
+        let creator_mdk = Account::create_mdk(creator.pubkey, &whitenoise.config.data_dir).unwrap();
+        let member_mdk = Account::create_mdk(member.pubkey, &whitenoise.config.data_dir).unwrap();
+        let nostr_group_id = hex::encode(
+            member_mdk
+                .get_group(&group_id)
+                .unwrap()
+                .unwrap()
+                .nostr_group_id,
+        );
+
+        // Garbage addressed to the group
+        let garbage = EventBuilder::new(Kind::MlsGroupMessage, "garbage")
+            .tag(Tag::custom(TagKind::h(), [nostr_group_id]))
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        let error = member_mdk.process_message(&garbage).unwrap_err();
+        assert!(!is_epoch_desync(&error), "unexpected desync: {}", error);
+
+        // A message of a group the account doesn't know
+        let unknown_group = EventBuilder::new(Kind::MlsGroupMessage, "garbage")
+            .tag(Tag::custom(TagKind::h(), [hex::encode([7u8; 32])]))
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        let error = member_mdk.process_message(&unknown_group).unwrap_err();
+        assert!(!is_epoch_desync(&error), "unexpected desync: {}", error);
+
+        // A message the member already processed
+        let message = create_chat_message(&creator_mdk, &creator, &group_id, "hello");
+        member_mdk.process_message(&message).unwrap();
+        if let Err(error) = member_mdk.process_message(&message) {
+            assert!(!is_epoch_desync(&error), "unexpected desync: {}", error);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
 
@@ -9,9 +10,9 @@ use mdk_core::extension::group_image;
 use mdk_core::media_processing::MediaProcessingOptions;
 use mdk_core::prelude::*;
 use mdk_sqlite_storage::MdkSqliteStorage;
-use nostr_blossom::client::BlossomClient;
 use nostr_sdk::prelude::*;
 use sha2::{Digest, Sha256};
+use tokio::sync::Semaphore;
 
 use crate::{
     RelayType,
@@ -19,15 +20,59 @@ use crate::{
     whitenoise::{
         Whitenoise,
         accounts::Account,
-        database::media_files::{FileMetadata, MediaFile},
+        aggregated_message::AggregatedMessage,
+        blossom,
+        database::{
+            left_groups::LeftGroups,
+            media_files::{FileMetadata, MediaFile},
+            pinned_conversations::PinnedConversations,
+        },
         error::{Result, WhitenoiseError},
-        group_information::{GroupInformation, GroupType},
-        media_files::MediaFileUpload,
+        group_information::{
+            GroupInformation, GroupType, SLOW_MODE_SETTINGS_D_TAG, SLOW_MODE_SETTINGS_KIND,
+            SlowMode,
+        },
+        media_files::{MediaFileInfo, MediaFileUpload, MediaUpload},
         relays::Relay,
-        users::User,
+        users::{User, profile_name},
     },
 };
 
+/// Result of [`Whitenoise::create_group_checked`]
+#[derive(Debug, Clone)]
+pub struct CreateGroupOutcome {
+    /// The created group
+    pub group: group_types::Group,
+
+    /// Requested members that were left out because no key package of theirs was found
+    pub missing_key_package: Vec<PublicKey>,
+}
+
+/// Result of [`Whitenoise::add_members_to_group`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddMembersOutcome {
+    /// Members that were added to the group
+    pub added: Vec<PublicKey>,
+
+    /// Requested members that were left out because no key package of theirs was found
+    pub missing_key_package: Vec<PublicKey>,
+}
+
+/// A member of a group with their cached profile, see [`Whitenoise::group_members_with_metadata`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupMember {
+    pub pubkey: PublicKey,
+
+    /// Whether the member is an admin according to the MLS group
+    pub is_admin: bool,
+
+    /// The member's profile, empty if none is cached
+    pub metadata: Metadata,
+
+    /// Display name, or name, from the profile; a shortened npub if it has neither
+    pub display_name: String,
+}
+
 /// Default timeout for Blossom HTTP operations (download and upload)
 /// Set to 300 seconds to accommodate large image files over slow connections
 const BLOSSOM_TIMEOUT: Duration = Duration::from_secs(300);
@@ -37,7 +82,7 @@ impl Whitenoise {
     ///
     /// In debug builds, uses localhost:3000 for local testing.
     /// In release builds, uses the production Blossom server.
-    fn default_blossom_url() -> Url {
+    pub(crate) fn default_blossom_url() -> Url {
         let url = if cfg!(debug_assertions) {
             "http://localhost:3000"
         } else {
@@ -46,6 +91,60 @@ impl Whitenoise {
         Url::parse(url).expect("Hardcoded Blossom URL should be valid")
     }
 
+    /// Returns the Blossom servers to upload to, in order
+    ///
+    /// An explicitly requested server goes first, followed by the configured servers.
+    /// Falls back to the default server when nothing is configured.
+    fn blossom_upload_servers(&self, requested: Option<Url>) -> Vec<Url> {
+        Self::dedup_blossom_servers(
+            requested
+                .into_iter()
+                .chain(self.config.blossom_servers.iter().cloned()),
+        )
+    }
+
+    /// Returns the Blossom servers to download from, in order
+    ///
+    /// The URL stored with the blob goes first, then the configured server that last
+    /// served a download, then the remaining configured servers.
+    pub(crate) fn blossom_download_servers(&self, stored: Option<Url>) -> Vec<Url> {
+        let last_successful = self
+            .last_successful_blossom_server
+            .read()
+            .ok()
+            .and_then(|server| server.clone());
+
+        Self::dedup_blossom_servers(
+            stored
+                .into_iter()
+                .chain(last_successful)
+                .chain(self.config.blossom_servers.iter().cloned()),
+        )
+    }
+
+    fn dedup_blossom_servers(candidates: impl Iterator<Item = Url>) -> Vec<Url> {
+        let mut servers: Vec<Url> = Vec::new();
+        for url in candidates {
+            if !servers.contains(&url) {
+                servers.push(url);
+            }
+        }
+        if servers.is_empty() {
+            servers.push(Self::default_blossom_url());
+        }
+        servers
+    }
+
+    /// Remembers a configured server that served a download so later downloads try it first
+    fn record_successful_blossom_server(&self, server: &Url) {
+        if !self.config.blossom_servers.contains(server) {
+            return;
+        }
+        if let Ok(mut last_successful) = self.last_successful_blossom_server.write() {
+            *last_successful = Some(server.clone());
+        }
+    }
+
     /// Ensures that group relays are available for publishing evolution events.
     /// Returns the validated relay URLs.
     ///
@@ -125,67 +224,167 @@ impl Whitenoise {
         config: NostrGroupConfigData,
         group_type: Option<GroupType>,
     ) -> Result<group_types::Group> {
-        let keys = self
-            .secrets_store
-            .get_nostr_keys_for_pubkey(&creator_account.pubkey)?;
+        let mut key_package_events: Vec<Event> = Vec::new();
+        let mut members = Vec::new();
+
+        for pk in member_pubkeys.iter() {
+            let (user, key_package) = self.fetch_member_key_package(creator_account, pk).await?;
+            let event = key_package.ok_or(WhitenoiseError::MdkCoreError(
+                mdk_core::Error::KeyPackage("Does not exist".to_owned()),
+            ))?;
+            key_package_events.push(event);
+            members.push(user);
+        }
 
+        tracing::debug!("Succefully fetched the key packages of members");
+
+        self.create_group_with_key_packages(
+            creator_account,
+            members,
+            key_package_events,
+            config,
+            group_type,
+        )
+        .await
+    }
+
+    /// Creates a new MLS group with the members that have a key package, skipping the rest
+    ///
+    /// Unlike [`Whitenoise::create_group`], which fails when any member has no key package
+    /// on their relays, this looks up every member's key package first and creates the group
+    /// with the members that have one. Members whose key package can't be found (or fetched)
+    /// are returned in [`CreateGroupOutcome::missing_key_package`] so the UI can tell the
+    /// user who couldn't be added.
+    ///
+    /// # Arguments
+    /// * `creator_account` - Account of the group creator (must be the active account)
+    /// * `member_pubkeys` - List of public keys for group members
+    /// * `config` - Group configuration data
+    /// * `group_type` - Optional explicit group type. If None, will be inferred from participant count
+    ///
+    /// # Errors
+    /// Fails like [`Whitenoise::create_group`] if none of the members has a key package.
+    pub async fn create_group_checked(
+        &self,
+        creator_account: &Account,
+        member_pubkeys: Vec<PublicKey>,
+        config: NostrGroupConfigData,
+        group_type: Option<GroupType>,
+    ) -> Result<CreateGroupOutcome> {
         let mut key_package_events: Vec<Event> = Vec::new();
         let mut members = Vec::new();
+        let mut missing_key_package = Vec::new();
 
         for pk in member_pubkeys.iter() {
-            let (mut user, created) = User::find_or_create_by_pubkey(pk, &self.database).await?;
-            if created {
-                // Fetch the user's relay lists and save them to the database
-                if let Err(e) = user.update_relay_lists(self).await {
-                    tracing::warn!(
-                        target: "whitenoise::accounts::groups::create_group",
-                        "Failed to update relay lists for new user {}: {}",
-                        user.pubkey,
-                        e
-                    );
-                    // Continue with group creation even if relay list update fails
+            match self.fetch_member_key_package(creator_account, pk).await {
+                Ok((user, Some(event))) => {
+                    key_package_events.push(event);
+                    members.push(user);
                 }
-                if let Err(e) = user.sync_metadata(self).await {
+                Ok((_, None)) => missing_key_package.push(*pk),
+                Err(e) => {
                     tracing::warn!(
-                        target: "whitenoise::accounts::groups::create_group",
-                        "Failed to sync metadata for new user {}: {}",
-                        user.pubkey,
+                        target: "whitenoise::accounts::groups::create_group_checked",
+                        "Failed to fetch key package of {}, skipping: {}",
+                        pk,
                         e
                     );
-                    // Continue with group creation even if metadata sync fails
+                    missing_key_package.push(*pk);
                 }
             }
-            let mut kp_relays = user.relays(RelayType::KeyPackage, &self.database).await?;
-            if kp_relays.is_empty() {
+        }
+
+        if members.is_empty() && !member_pubkeys.is_empty() {
+            return Err(WhitenoiseError::MdkCoreError(mdk_core::Error::KeyPackage(
+                "Does not exist".to_owned(),
+            )));
+        }
+
+        let group = self
+            .create_group_with_key_packages(
+                creator_account,
+                members,
+                key_package_events,
+                config,
+                group_type,
+            )
+            .await?;
+
+        Ok(CreateGroupOutcome {
+            group,
+            missing_key_package,
+        })
+    }
+
+    /// Looks up a prospective member and fetches their key package from their relays
+    ///
+    /// Users seen for the first time get their relay lists and metadata synced first.
+    /// Members without key package relays are looked up on the creator's relays.
+    async fn fetch_member_key_package(
+        &self,
+        creator_account: &Account,
+        pk: &PublicKey,
+    ) -> Result<(User, Option<Event>)> {
+        let (mut user, created) = User::find_or_create_by_pubkey(pk, &self.database).await?;
+        if created {
+            // Fetch the user's relay lists and save them to the database
+            if let Err(e) = user.update_relay_lists(self).await {
+                tracing::warn!(
+                    target: "whitenoise::accounts::groups::create_group",
+                    "Failed to update relay lists for new user {}: {}",
+                    user.pubkey,
+                    e
+                );
+                // Continue with group creation even if relay list update fails
+            }
+            if let Err(e) = user.sync_metadata(self).await {
                 tracing::warn!(
                     target: "whitenoise::accounts::groups::create_group",
-                    "User {} has no key package relays configured, falling back to account {} relays",
+                    "Failed to sync metadata for new user {}: {}",
                     user.pubkey,
+                    e
+                );
+                // Continue with group creation even if metadata sync fails
+            }
+        }
+        let mut kp_relays = user.relays(RelayType::KeyPackage, &self.database).await?;
+        if kp_relays.is_empty() {
+            tracing::warn!(
+                target: "whitenoise::accounts::groups::create_group",
+                "User {} has no key package relays configured, falling back to account {} relays",
+                user.pubkey,
+                creator_account.pubkey
+            );
+            kp_relays = creator_account.nip65_relays(self).await?;
+            if kp_relays.is_empty() {
+                tracing::warn!(
+                    target: "whitenoise::accounts::groups::create_group",
+                    "Account {} has no fallback relays configured, using defaults",
                     creator_account.pubkey
                 );
-                kp_relays = creator_account.nip65_relays(self).await?;
-                if kp_relays.is_empty() {
-                    tracing::warn!(
-                        target: "whitenoise::accounts::groups::create_group",
-                        "Account {} has no fallback relays configured, using defaults",
-                        creator_account.pubkey
-                    );
-                    kp_relays = Relay::defaults();
-                }
+                kp_relays = self.default_relays();
             }
-            let kp_relays_urls = Relay::urls(&kp_relays);
-            let some_event = self
-                .nostr
-                .fetch_user_key_package(*pk, &kp_relays_urls)
-                .await?;
-            let event = some_event.ok_or(WhitenoiseError::MdkCoreError(
-                mdk_core::Error::KeyPackage("Does not exist".to_owned()),
-            ))?;
-            key_package_events.push(event);
-            members.push(user);
         }
+        let kp_relays_urls = Relay::urls(&kp_relays);
+        let key_package = self
+            .nostr
+            .fetch_user_key_package(*pk, &kp_relays_urls)
+            .await?;
+        Ok((user, key_package))
+    }
 
-        tracing::debug!("Succefully fetched the key packages of members");
+    /// Creates the group in MLS and sends the welcomes, given each member's key package
+    async fn create_group_with_key_packages(
+        &self,
+        creator_account: &Account,
+        members: Vec<User>,
+        key_package_events: Vec<Event>,
+        config: NostrGroupConfigData,
+        group_type: Option<GroupType>,
+    ) -> Result<group_types::Group> {
+        let keys = self
+            .secrets_store
+            .get_nostr_keys_for_pubkey(&creator_account.pubkey)?;
 
         let group_relays = config.relays.clone();
         let group_name = config.name.clone();
@@ -194,11 +393,7 @@ impl Whitenoise {
         let create_group_result =
             mdk.create_group(&creator_account.pubkey, key_package_events.clone(), config)?;
 
-        let group_ids = mdk
-            .get_groups()?
-            .into_iter()
-            .map(|g| hex::encode(g.nostr_group_id))
-            .collect::<Vec<_>>();
+        let (_, group_ids) = self.extract_groups_relays_and_ids(creator_account).await?;
 
         let group = create_group_result.group;
         let welcome_rumors = create_group_result.welcome_rumors;
@@ -322,6 +517,56 @@ impl Whitenoise {
             .collect::<Vec<PublicKey>>())
     }
 
+    /// Lists the members of a group along with their cached profiles
+    ///
+    /// Membership and admin flags come from the account's MLS group state, so they are
+    /// current as of the last processed commit. Profiles are read from the users table
+    /// without fetching from relays. Admins are listed first, then members by name.
+    ///
+    /// # Arguments
+    /// * `account` - The account that has access to the group
+    /// * `group_id` - The MLS group ID
+    pub async fn group_members_with_metadata(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+    ) -> Result<Vec<GroupMember>> {
+        let (pubkeys, admins) = {
+            let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
+            let group = mdk
+                .get_group(group_id)
+                .map_err(WhitenoiseError::from)?
+                .ok_or(WhitenoiseError::GroupNotFound)?;
+            let members = mdk.get_members(group_id).map_err(WhitenoiseError::from)?;
+            (
+                members.into_iter().collect::<Vec<PublicKey>>(),
+                group.admin_pubkeys,
+            )
+        };
+
+        let mut metadata_by_pubkey: HashMap<PublicKey, Metadata> =
+            User::find_by_pubkeys(&pubkeys, &self.database)
+                .await?
+                .into_iter()
+                .map(|user| (user.pubkey, user.metadata))
+                .collect();
+
+        let mut members: Vec<GroupMember> = pubkeys
+            .into_iter()
+            .map(|pubkey| {
+                let metadata = metadata_by_pubkey.remove(&pubkey).unwrap_or_default();
+                GroupMember {
+                    pubkey,
+                    is_admin: admins.contains(&pubkey),
+                    display_name: member_display_name(&pubkey, &metadata),
+                    metadata,
+                }
+            })
+            .collect();
+        members.sort_by_cached_key(|member| (!member.is_admin, member.display_name.to_lowercase()));
+        Ok(members)
+    }
+
     pub async fn group_admins(
         &self,
         account: &Account,
@@ -337,6 +582,24 @@ impl Whitenoise {
             .collect::<Vec<PublicKey>>())
     }
 
+    /// Checks that the account is an active member of the group
+    ///
+    /// # Errors
+    /// * [`WhitenoiseError::GroupNotFound`] - If the account doesn't know the group
+    /// * [`WhitenoiseError::AccountNotGroupMember`] - If the account isn't an active member
+    pub(crate) fn ensure_active_member(&self, account: &Account, group_id: &GroupId) -> Result<()> {
+        let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
+        let group = mdk
+            .get_group(group_id)?
+            .ok_or(WhitenoiseError::GroupNotFound)?;
+        if group.state != group_types::GroupState::Active
+            || !mdk.get_members(group_id)?.contains(&account.pubkey)
+        {
+            return Err(WhitenoiseError::AccountNotGroupMember);
+        }
+        Ok(())
+    }
+
     /// Adds new members to an existing MLS group
     ///
     /// This method performs the complete workflow for adding members to a group:
@@ -345,50 +608,68 @@ impl Whitenoise {
     /// 3. Publishes the evolution event to the group's relays
     /// 4. Merges the pending commit to finalize the member addition
     /// 5. Sends welcome messages to each new member via gift wrap
+    /// 6. Refreshes the account's subscriptions
+    ///
+    /// Members without a key package are skipped rather than failing the whole addition, so
+    /// a member left out of [`Whitenoise::create_group_checked`] can be added once they
+    /// publish one.
     ///
     /// # Arguments
     /// * `account` - The account performing the member addition (must be group admin)
     /// * `group_id` - The ID of the group to add members to
     /// * `members` - Vector of public keys for the new members to add
+    ///
+    /// # Returns
+    /// Which members were added and which were skipped for lack of a key package
+    ///
+    /// # Errors
+    /// Returns [`WhitenoiseError::AccountNotAuthorized`] if the account is not a group admin,
+    /// and fails like [`Whitenoise::create_group`] if none of the members has a key package.
     pub async fn add_members_to_group(
         &self,
         account: &Account,
         group_id: &GroupId,
         members: Vec<PublicKey>,
-    ) -> Result<()> {
+    ) -> Result<AddMembersOutcome> {
+        if !self
+            .group_admins(account, group_id)
+            .await?
+            .contains(&account.pubkey)
+        {
+            return Err(WhitenoiseError::AccountNotAuthorized);
+        }
+
         let mut key_package_events: Vec<Event> = Vec::new();
         let keys = self
             .secrets_store
             .get_nostr_keys_for_pubkey(&account.pubkey)?;
         let mut users = Vec::new();
+        let mut missing_key_package = Vec::new();
 
         // Fetch key packages for all members
         for pk in members.iter() {
-            let (user, newly_created) = User::find_or_create_by_pubkey(pk, &self.database).await?;
-
-            if newly_created {
-                self.background_fetch_user_data(&user).await?;
-            }
-            // Try and get user's key package relays, if they don't have any, use account's default relays
-            let mut relays_to_use = user.relays(RelayType::KeyPackage, &self.database).await?;
-            if relays_to_use.is_empty() {
-                tracing::warn!(
-                    target: "whitenoise::accounts::groups::add_members_to_group",
-                    "User {} has no relays configured, using account's default relays",
-                    user.pubkey
-                );
-                relays_to_use = account.nip65_relays(self).await?;
+            match self.fetch_member_key_package(account, pk).await {
+                Ok((user, Some(event))) => {
+                    key_package_events.push(event);
+                    users.push(user);
+                }
+                Ok((_, None)) => missing_key_package.push(*pk),
+                Err(e) => {
+                    tracing::warn!(
+                        target: "whitenoise::accounts::groups::add_members_to_group",
+                        "Failed to fetch key package of {}, skipping: {}",
+                        pk,
+                        e
+                    );
+                    missing_key_package.push(*pk);
+                }
             }
-            let relays_to_use_urls = Relay::urls(&relays_to_use);
-            let some_event = self
-                .nostr
-                .fetch_user_key_package(*pk, &relays_to_use_urls)
-                .await?;
-            let event = some_event.ok_or(WhitenoiseError::MdkCoreError(
-                mdk_core::Error::KeyPackage("Does not exist".to_owned()),
-            ))?;
-            key_package_events.push(event);
-            users.push(user);
+        }
+
+        if users.is_empty() {
+            return Err(WhitenoiseError::MdkCoreError(mdk_core::Error::KeyPackage(
+                "Does not exist".to_owned(),
+            )));
         }
 
         let (relay_urls, evolution_event, welcome_rumors) = {
@@ -428,7 +709,7 @@ impl Whitenoise {
 
         // Evolution event published successfully
         // Fan out the welcome message to all members
-        for (welcome_rumor, user) in welcome_rumors.iter().zip(users) {
+        for (welcome_rumor, user) in welcome_rumors.iter().zip(&users) {
             // Get the public key of the member from the key package event
             let key_package_event_id =
                 welcome_rumor
@@ -452,7 +733,7 @@ impl Whitenoise {
 
             let relays_to_use = self
                 .resolve_member_delivery_relays(
-                    &user,
+                    user,
                     account,
                     "whitenoise::accounts::groups::add_members_to_group",
                 )
@@ -473,7 +754,18 @@ impl Whitenoise {
                 .map_err(WhitenoiseError::from)?;
         }
 
-        Ok(())
+        if let Err(e) = self.refresh_account_subscriptions(account).await {
+            tracing::warn!(
+                target: "whitenoise::accounts::groups::add_members_to_group",
+                "Failed to refresh subscriptions after adding members: {}",
+                e
+            );
+        }
+
+        Ok(AddMembersOutcome {
+            added: users.iter().map(|user| user.pubkey).collect(),
+            missing_key_package,
+        })
     }
 
     /// Removes members from an existing MLS group
@@ -483,16 +775,37 @@ impl Whitenoise {
     /// 2. Merges the pending commit to finalize the member removal
     /// 3. Publishes the evolution event to the group's relays
     ///
+    /// Once the commit is merged the removed members no longer hold the group's keys, so they
+    /// can't decrypt messages from the new epoch on, and messages they send are rejected.
+    ///
     /// # Arguments
     /// * `account` - The account performing the member removal (must be group admin)
     /// * `group_id` - The ID of the group to remove members from
     /// * `members` - Vector of public keys for the members to remove
+    ///
+    /// # Errors
+    /// Returns [`WhitenoiseError::AccountNotAuthorized`] if the account is not a group admin,
+    /// and [`WhitenoiseError::InvalidInput`] if `members` contains the account itself (use
+    /// [`Whitenoise::leave_group`] instead).
     pub async fn remove_members_from_group(
         &self,
         account: &Account,
         group_id: &GroupId,
         members: Vec<PublicKey>,
     ) -> Result<()> {
+        if members.contains(&account.pubkey) {
+            return Err(WhitenoiseError::InvalidInput(
+                "Cannot remove yourself from a group, use leave_group instead".to_string(),
+            ));
+        }
+        if !self
+            .group_admins(account, group_id)
+            .await?
+            .contains(&account.pubkey)
+        {
+            return Err(WhitenoiseError::AccountNotAuthorized);
+        }
+
         let (relay_urls, evolution_event) = {
             let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
             let relay_urls = Self::ensure_group_relays(&mdk, group_id)?;
@@ -509,25 +822,27 @@ impl Whitenoise {
         Ok(())
     }
 
-    /// Updates group metadata and publishes the change to group relays.
+    /// Rotates the account's keys in a group, moving the group to a new epoch
     ///
-    /// This method updates the group data and publishes the change to group relays.
+    /// Issues an MLS self-update commit, merges it and publishes it to the group's relays.
+    /// Use it after a suspected compromise of the account's device: key material an attacker
+    /// copied can't decrypt anything sent after the rotation.
+    ///
+    /// Chat media keys are derived from the exporter secret of the epoch the media was sent
+    /// in. Media sent before the rotation stays decryptable through the old epoch's secret,
+    /// which MDK keeps, while media sent afterwards is encrypted with the new one. Whitenoise
+    /// doesn't cache derived media keys; every upload and download asks MDK for the current
+    /// epoch's keys, so nothing needs to be invalidated here.
     ///
     /// # Arguments
-    /// * `account` - The account performing the group data update (must be group admin)
-    /// * `group_id` - The ID of the group to update
-    /// * `group_data` - The new group data to update
-    pub async fn update_group_data(
-        &self,
-        account: &Account,
-        group_id: &GroupId,
-        group_data: NostrGroupDataUpdate,
-    ) -> Result<()> {
+    /// * `account` - The account rotating its keys (must be a group member)
+    /// * `group_id` - The ID of the group to rekey
+    pub async fn rotate_group_key(&self, account: &Account, group_id: &GroupId) -> Result<()> {
         let (relay_urls, evolution_event) = {
             let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
             let relay_urls = Self::ensure_group_relays(&mdk, group_id)?;
 
-            let update_result = mdk.update_group_data(group_id, group_data)?;
+            let update_result = mdk.self_update(group_id)?;
             mdk.merge_pending_commit(group_id)?;
 
             (relay_urls, update_result.evolution_event)
@@ -539,65 +854,397 @@ impl Whitenoise {
         Ok(())
     }
 
-    /// Initiates the process to leave a group by creating a self-removal proposal.
+    /// Updates group metadata and publishes the change to group relays.
     ///
-    /// This method creates a self-removal proposal using the nostr-mls library and publishes
-    /// it to the group relays. The proposal will need to be committed by a group admin before
-    /// the removal is finalized.
+    /// This method updates the group data and publishes the change to group relays.
     ///
     /// # Arguments
-    /// * `account` - The account that wants to leave the group
-    /// * `group_id` - The ID of the group to leave
-    pub async fn leave_group(&self, account: &Account, group_id: &GroupId) -> Result<()> {
+    /// * `account` - The account performing the group data update (must be group admin)
+    /// * `group_id` - The ID of the group to update
+    /// * `group_data` - The new group data to update
+    pub async fn update_group_data(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        group_data: NostrGroupDataUpdate,
+    ) -> Result<()> {
         let (relay_urls, evolution_event) = {
             let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
             let relay_urls = Self::ensure_group_relays(&mdk, group_id)?;
 
-            // Create a self-removal proposal
-            let update_result = mdk.leave_group(group_id)?;
+            let update_result = mdk.update_group_data(group_id, group_data)?;
+            mdk.merge_pending_commit(group_id)?;
 
             (relay_urls, update_result.evolution_event)
         };
 
-        // Publish the self-removal proposal to the group
         self.nostr
             .publish_event_to(evolution_event, &account.pubkey, &relay_urls)
             .await?;
-
-        // TODO: Do any local updates to ensure that we're accurately reflecting that the account is trying to leave this group
         Ok(())
     }
 
-    /// Syncs group image cache if needed (smart, hash-based check)
-    ///
-    /// This method is called after processing welcomes and commits to proactively
-    /// cache group images. It only downloads if:
-    /// 1. The group has an image set
-    /// 2. The image_hash is not already cached
+    /// Updates a group's name, description and/or image and publishes the change.
     ///
-    /// This ensures images are ready before the UI needs them, while avoiding
-    /// redundant downloads.
+    /// Only the fields that are `Some` change; the rest of the group data is left as is.
+    /// A new image is encrypted and uploaded to Blossom (see [`Self::upload_group_image`])
+    /// before the group data update is committed and published to the group relays, so
+    /// every member converges on the same metadata.
     ///
     /// # Arguments
-    /// * `account` - The account viewing the group
-    /// * `group_id` - The MLS group ID
-    pub(crate) async fn sync_group_image_cache_if_needed(
+    /// * `account` - The account performing the update (must be group admin)
+    /// * `group_id` - The ID of the group to update
+    /// * `name` - The new group name
+    /// * `description` - The new group description
+    /// * `image` - The new group image
+    ///
+    /// # Errors
+    /// Returns [`WhitenoiseError::AccountNotAuthorized`] if the account is not a group admin.
+    pub async fn update_group_metadata(
         &self,
         account: &Account,
         group_id: &GroupId,
-    ) -> Result<()> {
-        let group: mdk_core::prelude::group_types::Group;
+        name: Option<String>,
+        description: Option<String>,
+        image: Option<MediaUpload>,
+    ) -> Result<GroupInformation> {
+        if !self
+            .group_admins(account, group_id)
+            .await?
+            .contains(&account.pubkey)
         {
-            // Get group data to check if it has an image
-            let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
-            group = mdk
-                .get_group(group_id)
-                .map_err(WhitenoiseError::from)?
-                .ok_or(WhitenoiseError::GroupNotFound)?;
+            return Err(WhitenoiseError::AccountNotAuthorized);
         }
 
-        // Check if group has an image set
-        let (image_hash, image_key, image_nonce) =
+        if name.is_some() || description.is_some() || image.is_some() {
+            let mut update = NostrGroupDataUpdate {
+                name,
+                description,
+                image_hash: None,
+                image_key: None,
+                image_nonce: None,
+                admins: None,
+                relays: None,
+            };
+
+            if let Some(image) = image {
+                let (image_hash, image_key, image_nonce) = self
+                    .upload_group_image(
+                        account,
+                        group_id,
+                        &image.file_path,
+                        image.blossom_server_url,
+                        image.options,
+                    )
+                    .await?;
+                update.image_hash = Some(Some(image_hash));
+                update.image_key = Some(Some(image_key));
+                update.image_nonce = Some(Some(image_nonce));
+            }
+
+            self.update_group_data(account, group_id, update).await?;
+        }
+
+        GroupInformation::get_by_mls_group_id(account.pubkey, group_id, self).await
+    }
+
+    /// Replaces a group's relays and publishes the change to its members.
+    ///
+    /// The update is published to both the old and the new relays, so members still
+    /// listening on a relay that is being dropped learn about the move. Afterwards the client
+    /// connects to the new relays and the account's group messages subscription moves to the
+    /// new set, which stops listening for the group on the removed relays.
+    ///
+    /// # Arguments
+    /// * `account` - The account changing the relays (must be group admin)
+    /// * `group_id` - The ID of the group to update
+    /// * `relays` - The group's new relays
+    ///
+    /// # Errors
+    /// Returns [`WhitenoiseError::AccountNotAuthorized`] if the account is not a group admin,
+    /// and [`WhitenoiseError::GroupMissingRelays`] if `relays` is empty.
+    pub async fn update_group_relays(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        relays: Vec<RelayUrl>,
+    ) -> Result<()> {
+        if !self
+            .group_admins(account, group_id)
+            .await?
+            .contains(&account.pubkey)
+        {
+            return Err(WhitenoiseError::AccountNotAuthorized);
+        }
+
+        let mut seen = HashSet::new();
+        let relays: Vec<RelayUrl> = relays
+            .into_iter()
+            .filter(|relay| seen.insert(relay.clone()))
+            .collect();
+        if relays.is_empty() {
+            return Err(WhitenoiseError::GroupMissingRelays);
+        }
+
+        self.nostr.ensure_relays_connected(&relays).await?;
+
+        let (publish_relays, evolution_event) = {
+            let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
+            let mut publish_relays = Self::ensure_group_relays(&mdk, group_id)?;
+
+            let update = NostrGroupDataUpdate {
+                name: None,
+                description: None,
+                image_hash: None,
+                image_key: None,
+                image_nonce: None,
+                admins: None,
+                relays: Some(relays.clone()),
+            };
+            let update_result = mdk.update_group_data(group_id, update)?;
+            mdk.merge_pending_commit(group_id)?;
+
+            for relay in &relays {
+                if !publish_relays.contains(relay) {
+                    publish_relays.push(relay.clone());
+                }
+            }
+            (publish_relays, update_result.evolution_event)
+        };
+
+        self.nostr
+            .publish_event_to(evolution_event, &account.pubkey, &publish_relays)
+            .await?;
+
+        self.retarget_group_messages_subscription(account).await
+    }
+
+    /// Configures slow mode for a group and publishes the settings to its members.
+    ///
+    /// The settings are sent to the group as an MLS application message so that every
+    /// member's client learns the new interval and enforces it on their send path.
+    ///
+    /// # Arguments
+    /// * `account` - The account changing the settings (must be group admin)
+    /// * `group_id` - The ID of the group to configure
+    /// * `slow_mode` - The new slow mode settings; an interval of 0 disables slow mode
+    pub async fn set_group_slow_mode(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        slow_mode: SlowMode,
+    ) -> Result<GroupInformation> {
+        if !self
+            .group_admins(account, group_id)
+            .await?
+            .contains(&account.pubkey)
+        {
+            return Err(WhitenoiseError::AccountNotAuthorized);
+        }
+
+        // Make sure the record exists before updating it
+        GroupInformation::get_by_mls_group_id(account.pubkey, group_id, self).await?;
+
+        let sent = self
+            .send_message_to_group(
+                account,
+                group_id,
+                serde_json::to_string(&slow_mode)?,
+                SLOW_MODE_SETTINGS_KIND,
+                Some(vec![Tag::identifier(SLOW_MODE_SETTINGS_D_TAG)]),
+            )
+            .await?;
+
+        match GroupInformation::update_slow_mode(
+            group_id,
+            &slow_mode,
+            sent.message.created_at,
+            true,
+            &self.database,
+        )
+        .await?
+        {
+            Some(group_info) => Ok(group_info),
+            // Settings another admin published later were applied already
+            None => GroupInformation::find_by_mls_group_id(group_id, &self.database).await,
+        }
+    }
+
+    /// Leaves a group by proposing the account's own removal.
+    ///
+    /// This method creates a self-removal proposal using the nostr-mls library and publishes
+    /// it to the group relays. MLS does not let a member commit its own removal, so the
+    /// proposal is committed by a group admin. Locally the group is dropped right away: the
+    /// account stops receiving the group's messages and its cached messages are cleared.
+    ///
+    /// # Arguments
+    /// * `account` - The account that wants to leave the group
+    /// * `group_id` - The ID of the group to leave
+    ///
+    /// # Errors
+    /// * [`WhitenoiseError::GroupNotFound`] - If the account doesn't know the group
+    /// * [`WhitenoiseError::AccountNotGroupMember`] - If the account is no longer a member
+    /// * [`WhitenoiseError::LastGroupAdmin`] - If the account is the only admin and other
+    ///   members remain, since nobody would be left to commit the removal
+    pub async fn leave_group(&self, account: &Account, group_id: &GroupId) -> Result<()> {
+        let (relay_urls, evolution_event) = {
+            let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
+            let group = mdk
+                .get_group(group_id)?
+                .ok_or(WhitenoiseError::GroupNotFound)?;
+            let members = mdk.get_members(group_id)?;
+
+            if !members.contains(&account.pubkey) {
+                return Err(WhitenoiseError::AccountNotGroupMember);
+            }
+            if members.len() > 1
+                && group.admin_pubkeys.len() == 1
+                && group.admin_pubkeys.contains(&account.pubkey)
+            {
+                return Err(WhitenoiseError::LastGroupAdmin);
+            }
+
+            let relay_urls = Self::ensure_group_relays(&mdk, group_id)?;
+
+            // Create a self-removal proposal
+            let update_result = mdk.leave_group(group_id)?;
+
+            (relay_urls, update_result.evolution_event)
+        };
+
+        // Publish the self-removal proposal to the group
+        self.nostr
+            .publish_event_to(evolution_event, &account.pubkey, &relay_urls)
+            .await?;
+
+        // The proposal is out, so local cleanup failures are logged rather than returned.
+        // MDK keeps the group active until an admin commits the removal, so it's marked as
+        // left to keep later resubscribes from picking it up again.
+        if let Some(account_id) = account.id
+            && let Err(e) = LeftGroups::mark(account_id, group_id, &self.database).await
+        {
+            tracing::warn!(
+                target: "whitenoise::accounts::groups::leave_group",
+                "Failed to mark group {} as left: {}",
+                hex::encode(group_id.as_slice()),
+                e
+            );
+        }
+        if let Err(e) = self.retarget_group_messages_subscription(account).await {
+            tracing::warn!(
+                target: "whitenoise::accounts::groups::leave_group",
+                "Failed to unsubscribe {} from messages of left group: {}",
+                account.pubkey.to_hex(),
+                e
+            );
+        }
+        if let Some(account_id) = account.id
+            && let Err(e) = PinnedConversations::unpin(account_id, group_id, &self.database).await
+        {
+            tracing::warn!(
+                target: "whitenoise::accounts::groups::leave_group",
+                "Failed to unpin left group {}: {}",
+                hex::encode(group_id.as_slice()),
+                e
+            );
+        }
+        if let Err(e) = self.clear_left_group_cache(account, group_id).await {
+            tracing::warn!(
+                target: "whitenoise::accounts::groups::leave_group",
+                "Failed to clear cached messages of left group {}: {}",
+                hex::encode(group_id.as_slice()),
+                e
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Re-targets the account's group messages subscription at the current relays of every
+    /// group the account is still active in.
+    async fn retarget_group_messages_subscription(&self, account: &Account) -> Result<()> {
+        let (group_relays, nostr_group_ids) = self.extract_groups_relays_and_ids(account).await?;
+
+        let keys = self
+            .secrets_store
+            .get_nostr_keys_for_pubkey(&account.pubkey)?;
+
+        self.nostr
+            .update_group_messages_subscription_with_signer(
+                account.pubkey,
+                &group_relays,
+                &nostr_group_ids,
+                keys,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Clears the aggregated message cache of a group the account left.
+    ///
+    /// The cache is shared by all local accounts, so it's kept while another local account
+    /// is still an active member of the group.
+    async fn clear_left_group_cache(&self, account: &Account, group_id: &GroupId) -> Result<()> {
+        for other in Account::all(&self.database).await? {
+            if other.pubkey == account.pubkey {
+                continue;
+            }
+            let left = match other.id {
+                Some(other_id) => LeftGroups::find(other_id, &self.database)
+                    .await?
+                    .contains(group_id),
+                None => false,
+            };
+            let mdk = Account::create_mdk(other.pubkey, &self.config.data_dir)?;
+            if !left
+                && mdk
+                    .get_group(group_id)?
+                    .is_some_and(|group| group.state == group_types::GroupState::Active)
+            {
+                return Ok(());
+            }
+        }
+
+        AggregatedMessage::delete_by_group(group_id, &self.database).await?;
+        self.message_aggregator
+            .clear_group_state(group_id)
+            .await
+            .map_err(|e| {
+                WhitenoiseError::from(anyhow::anyhow!("Failed to clear aggregator state: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Syncs group image cache if needed (smart, hash-based check)
+    ///
+    /// This method is called after processing welcomes and commits to proactively
+    /// cache group images. It only downloads if:
+    /// 1. The group has an image set
+    /// 2. The image_hash is not already cached
+    ///
+    /// This ensures images are ready before the UI needs them, while avoiding
+    /// redundant downloads.
+    ///
+    /// # Arguments
+    /// * `account` - The account viewing the group
+    /// * `group_id` - The MLS group ID
+    pub(crate) async fn sync_group_image_cache_if_needed(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+    ) -> Result<()> {
+        let group: mdk_core::prelude::group_types::Group;
+        {
+            // Get group data to check if it has an image
+            let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
+            group = mdk
+                .get_group(group_id)
+                .map_err(WhitenoiseError::from)?
+                .ok_or(WhitenoiseError::GroupNotFound)?;
+        }
+
+        // Check if group has an image set
+        let (image_hash, image_key, image_nonce) =
             match (group.image_hash, group.image_key, group.image_nonce) {
                 (Some(hash), Some(key), Some(nonce)) => (hash, key, nonce),
                 _ => return Ok(()), // No image set, nothing to do
@@ -676,7 +1323,7 @@ impl Whitenoise {
     /// Downloads, decrypts, and caches a group image if not already cached
     ///
     /// # Arguments
-    /// * `blossom_url` - Optional Blossom server URL (uses default if None)
+    /// * `blossom_url` - Optional stored Blossom URL, tried before the configured servers
     /// * `account_pubkey` - The account accessing the image
     /// * `group_id` - The MLS group ID
     /// * `image_hash` - SHA-256 hash of the encrypted image
@@ -710,20 +1357,21 @@ impl Whitenoise {
             return Ok(media_file);
         }
 
-        // Use provided URL or fall back to default
-        let blossom_url = blossom_url.unwrap_or_else(Self::default_blossom_url);
+        // Try the stored URL first, then the configured servers
+        let servers = self.blossom_download_servers(blossom_url);
 
         tracing::info!(
             target: "whitenoise::groups::download_and_cache_group_image",
-            "Downloading group image {} for group {} from {}",
+            "Downloading group image {} for group {} from {} server(s)",
             hash_hex,
             hex::encode(group_id.as_slice()),
-            blossom_url
+            servers.len()
         );
 
         // Download, verify, decrypt, and cache the image
-        let encrypted_data = Self::download_blob_from_blossom(&blossom_url, image_hash).await?;
-        Self::verify_blob_hash(&encrypted_data, image_hash)?;
+        let (encrypted_data, blossom_url) =
+            Self::download_blob_from_blossom_servers(&servers, image_hash, &mut |_, _| {}).await?;
+        self.record_successful_blossom_server(&blossom_url);
 
         let decrypted_data = Self::decrypt_group_image(&encrypted_data, image_key, image_nonce)?;
         let image_type = ImageType::detect(&decrypted_data).map_err(|e| {
@@ -897,29 +1545,94 @@ impl Whitenoise {
     }
 
     /// Downloads an encrypted blob from a Blossom server
-    async fn download_blob_from_blossom(
+    ///
+    /// `progress` is called with the bytes received so far and the total size the server
+    /// announced in `Content-Length`, if any. The timeout applies to each chunk rather than
+    /// the whole transfer, so large blobs aren't cut off while data keeps arriving.
+    async fn download_blob_from_blossom<F>(
         blossom_url: &Url,
         image_hash: &[u8; 32],
-    ) -> Result<Vec<u8>> {
-        use nostr::hashes::{Hash, sha256::Hash as Sha256Hash};
+        progress: &mut F,
+    ) -> Result<Vec<u8>>
+    where
+        F: FnMut(u64, Option<u64>),
+    {
+        let blob_url = blossom_url
+            .join(&hex::encode(image_hash))
+            .map_err(|e| WhitenoiseError::BlossomDownload(format!("Invalid blob URL: {}", e)))?;
+        let timed_out = || {
+            WhitenoiseError::BlossomDownload(format!(
+                "Download timed out after {} seconds",
+                BLOSSOM_TIMEOUT.as_secs()
+            ))
+        };
+        let failed = |e: reqwest::Error| {
+            WhitenoiseError::BlossomDownload(format!("Failed to download blob: {}", e))
+        };
 
-        let client = BlossomClient::new(blossom_url.clone());
-        let sha256 = Sha256Hash::from_slice(image_hash)
-            .map_err(|e| WhitenoiseError::Other(anyhow::anyhow!("Invalid SHA256 hash: {}", e)))?;
+        let mut response = tokio::time::timeout(BLOSSOM_TIMEOUT, reqwest::get(blob_url))
+            .await
+            .map_err(|_| timed_out())?
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(failed)?;
 
-        let download_future = client.get_blob(sha256, None, None, None::<&Keys>);
+        let total = response.content_length();
+        let mut data = Vec::new();
+        progress(0, total);
 
-        tokio::time::timeout(BLOSSOM_TIMEOUT, download_future)
+        while let Some(chunk) = tokio::time::timeout(BLOSSOM_TIMEOUT, response.chunk())
             .await
-            .map_err(|_| {
-                WhitenoiseError::BlossomDownload(format!(
-                    "Download timed out after {} seconds",
-                    BLOSSOM_TIMEOUT.as_secs()
-                ))
-            })?
-            .map_err(|e| {
-                WhitenoiseError::BlossomDownload(format!("Failed to download blob: {}", e))
-            })
+            .map_err(|_| timed_out())?
+            .map_err(failed)?
+        {
+            data.extend_from_slice(&chunk);
+            progress(data.len() as u64, total);
+        }
+
+        Ok(data)
+    }
+
+    /// Downloads an encrypted blob from the first server that returns it intact
+    ///
+    /// Servers are tried in order; a server whose blob fails hash verification is
+    /// treated like an unreachable one. Progress starts over from zero when falling
+    /// back to the next server.
+    ///
+    /// # Returns
+    /// The verified blob and the server it came from
+    async fn download_blob_from_blossom_servers<F>(
+        servers: &[Url],
+        expected_hash: &[u8; 32],
+        progress: &mut F,
+    ) -> Result<(Vec<u8>, Url)>
+    where
+        F: FnMut(u64, Option<u64>),
+    {
+        let mut last_error = None;
+
+        for server in servers {
+            let result = Self::download_blob_from_blossom(server, expected_hash, progress)
+                .await
+                .and_then(|data| Self::verify_blob_hash(&data, expected_hash).map(|_| data));
+
+            match result {
+                Ok(data) => return Ok((data, server.clone())),
+                Err(e) => {
+                    tracing::warn!(
+                        target: "whitenoise::groups::download_blob_from_blossom_servers",
+                        "Failed to download blob {} from {}: {}",
+                        hex::encode(expected_hash),
+                        server,
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            WhitenoiseError::BlossomDownload("No Blossom servers configured".to_string())
+        }))
     }
 
     /// Verifies that downloaded blob matches expected hash
@@ -938,6 +1651,28 @@ impl Whitenoise {
         Ok(())
     }
 
+    /// Checks that a cached file still hashes to `expected_hash`
+    ///
+    /// Unreadable files count as not intact, so the caller downloads them again.
+    async fn is_cached_file_intact(path: &Path, expected_hash: &[u8; 32]) -> bool {
+        let result = match tokio::fs::read(path).await {
+            Ok(data) => Self::verify_blob_hash(&data, expected_hash),
+            Err(e) => Err(WhitenoiseError::from(e)),
+        };
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!(
+                    target: "whitenoise::groups::is_cached_file_intact",
+                    "Ignoring cached file {}: {}",
+                    path.display(),
+                    e
+                );
+                false
+            }
+        }
+    }
+
     /// Decrypts a group image using the provided key and nonce
     fn decrypt_group_image(
         encrypted_data: &[u8],
@@ -954,23 +1689,29 @@ impl Whitenoise {
     /// This helper orchestrates the download from Blossom, hash verification,
     /// and MDK decryption in a single focused operation.
     ///
+    /// The stored Blossom URL is tried first, then the configured servers.
+    ///
     /// # Arguments
     /// * `account_pubkey` - The account downloading the media
-    /// * `data_dir` - The data directory for MDK storage
     /// * `group_id` - The MLS group ID
     /// * `media_file` - The MediaFile record containing URLs, hashes, and metadata
     /// * `original_file_hash` - SHA-256 of original content (for MDK decryption)
+    /// * `progress` - Called with bytes downloaded so far and the total, if known
     ///
     /// # Returns
     /// * `Ok(Vec<u8>)` - Decrypted file data
     /// * `Err(WhitenoiseError)` - If download, verification, or decryption fails
-    async fn download_and_decrypt_chat_media_blob(
+    async fn download_and_decrypt_chat_media_blob<F>(
+        &self,
         account_pubkey: &PublicKey,
-        data_dir: &Path,
         group_id: &GroupId,
         media_file: &MediaFile,
         original_file_hash: &[u8; 32],
-    ) -> Result<Vec<u8>> {
+        progress: &mut F,
+    ) -> Result<Vec<u8>>
+    where
+        F: FnMut(u64, Option<u64>),
+    {
         // Extract filename required for MDK AAD (cryptographically bound)
         let filename = media_file
             .file_metadata
@@ -998,18 +1739,25 @@ impl Whitenoise {
             WhitenoiseError::MediaCache(format!("Invalid Blossom URL '{}': {}", blossom_url_str, e))
         })?;
 
+        let servers = self.blossom_download_servers(Some(blossom_url));
+
         tracing::debug!(
             target: "whitenoise::groups::download_and_decrypt",
             "Downloading encrypted blob from: {}",
-            blossom_url
+            servers
+                .iter()
+                .map(Url::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
         );
 
         // Download encrypted blob (includes hash verification)
-        let encrypted_data =
-            Self::download_blob_from_blossom(&blossom_url, &encrypted_hash).await?;
+        let (encrypted_data, server) =
+            Self::download_blob_from_blossom_servers(&servers, &encrypted_hash, progress).await?;
+        self.record_successful_blossom_server(&server);
 
         // Decrypt using MDK
-        let mdk = Account::create_mdk(*account_pubkey, data_dir)?;
+        let mdk = Account::create_mdk(*account_pubkey, &self.config.data_dir)?;
         let media_manager = mdk.media_manager(group_id.clone());
 
         let reference = MediaReference {
@@ -1075,35 +1823,102 @@ impl Whitenoise {
     /// # Arguments
     /// * `blossom_server_url` - Blossom server URL to upload to
     /// * `encrypted_data` - The encrypted image data to upload
+    /// * `encrypted_hash` - SHA-256 of `encrypted_data`, which the upload authorization is scoped to
     /// * `mime_type` - MIME type of the original image
     /// * `upload_keypair` - Keypair for signing the upload
+    /// * `auth_expiry` - How long the upload authorization stays valid
     ///
     /// # Returns
     /// * `Ok(BlobDescriptor)` - Descriptor from Blossom server containing URL and hash
-    /// * `Err(WhitenoiseError)` - Upload timeout or network error
+    /// * `Err(WhitenoiseError)` - Refused authorization, upload timeout or network error
     async fn upload_encrypted_blob_to_blossom(
         blossom_server_url: &Url,
         encrypted_data: Vec<u8>,
+        encrypted_hash: &[u8; 32],
         mime_type: &str,
         upload_keypair: &Keys,
+        auth_expiry: Duration,
     ) -> Result<nostr_blossom::bud02::BlobDescriptor> {
-        let client = BlossomClient::new(blossom_server_url.clone());
-        let upload_future = client.upload_blob(
+        Ok(blossom::upload_blob(
+            blossom_server_url,
             encrypted_data,
-            Some(mime_type.to_string()),
-            None,
-            Some(upload_keypair),
-        );
+            encrypted_hash,
+            mime_type,
+            upload_keypair,
+            auth_expiry,
+        )
+        .await?)
+    }
 
-        tokio::time::timeout(BLOSSOM_TIMEOUT, upload_future)
+    /// Mirrors an encrypted blob to every given Blossom server
+    ///
+    /// Best-effort: succeeds if at least one server accepts the blob and returns
+    /// the expected hash. The descriptor from the first accepting server is returned.
+    ///
+    /// # Arguments
+    /// * `servers` - Blossom servers to upload to, in order of preference
+    /// * `encrypted_data` - The encrypted data to upload
+    /// * `expected_hash` - SHA-256 of `encrypted_data`
+    /// * `mime_type` - MIME type of the original file
+    /// * `upload_keypair` - Keypair for signing the upload
+    /// * `auth_expiry` - How long each upload authorization stays valid
+    async fn mirror_encrypted_blob_to_blossom(
+        servers: &[Url],
+        encrypted_data: Vec<u8>,
+        expected_hash: &[u8; 32],
+        mime_type: &str,
+        upload_keypair: &Keys,
+        auth_expiry: Duration,
+    ) -> Result<nostr_blossom::bud02::BlobDescriptor> {
+        let mut accepted = None;
+        let mut last_error = None;
+
+        for server in servers {
+            let result = Self::upload_encrypted_blob_to_blossom(
+                server,
+                encrypted_data.clone(),
+                expected_hash,
+                mime_type,
+                upload_keypair,
+                auth_expiry,
+            )
             .await
-            .map_err(|_| {
-                WhitenoiseError::Other(anyhow::anyhow!(
-                    "Upload timed out after {} seconds",
-                    BLOSSOM_TIMEOUT.as_secs()
-                ))
-            })?
-            .map_err(|err| WhitenoiseError::Other(anyhow::anyhow!(err)))
+            .and_then(|descriptor| {
+                // Verify the Blossom server returned the expected hash
+                let returned_hash_bytes: [u8; 32] = *descriptor.sha256.as_ref();
+                if &returned_hash_bytes != expected_hash {
+                    return Err(WhitenoiseError::HashMismatch {
+                        expected: hex::encode(expected_hash),
+                        actual: hex::encode(returned_hash_bytes),
+                    });
+                }
+                Ok(descriptor)
+            });
+
+            match result {
+                Ok(descriptor) => {
+                    if accepted.is_none() {
+                        accepted = Some(descriptor);
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        target: "whitenoise::groups::mirror_encrypted_blob_to_blossom",
+                        "Failed to upload blob {} to {}: {}",
+                        hex::encode(expected_hash),
+                        server,
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        accepted.ok_or_else(|| {
+            last_error.unwrap_or_else(|| {
+                WhitenoiseError::Other(anyhow::anyhow!("No Blossom servers configured"))
+            })
+        })
     }
 
     /// Uploads a group image to a Blossom server and returns the encrypted metadata.
@@ -1115,7 +1930,7 @@ impl Whitenoise {
     /// * `account` - The account performing the upload (must be a group admin)
     /// * `group_id` - The ID of the group to upload the image for
     /// * `file_path` - Path to the image file to upload
-    /// * `blossom_server_url` - Blossom server URL to upload to
+    /// * `blossom_server_url` - Optional Blossom server to try first; the image is mirrored to the configured servers as well
     /// * `options` - Optional media processing options (defaults to standard options if None)
     pub async fn upload_group_image(
         &self,
@@ -1160,27 +1975,18 @@ impl Whitenoise {
             WhitenoiseError::Other(anyhow::anyhow!("Failed to prepare group image: {}", e))
         })?;
 
-        let blossom_server_url = blossom_server_url.unwrap_or(Self::default_blossom_url());
+        let servers = self.blossom_upload_servers(blossom_server_url);
         // Upload encrypted data to Blossom using the derived keypair
-        let descriptor = Self::upload_encrypted_blob_to_blossom(
-            &blossom_server_url,
+        let descriptor = Self::mirror_encrypted_blob_to_blossom(
+            &servers,
             prepared.encrypted_data,
+            &prepared.encrypted_hash,
             image_type.mime_type(),
             &prepared.upload_keypair,
+            self.config.blossom_auth_expiry,
         )
         .await?;
 
-        // Verify the Blossom server returned the expected hash
-        // The descriptor.sha256 is a Hash type from bitcoin_hashes, convert to byte array
-        let returned_hash_bytes: [u8; 32] = *descriptor.sha256.as_ref();
-
-        if returned_hash_bytes != prepared.encrypted_hash {
-            return Err(WhitenoiseError::HashMismatch {
-                expected: hex::encode(prepared.encrypted_hash),
-                actual: hex::encode(returned_hash_bytes),
-            });
-        }
-
         tracing::debug!(
             target: "whitenoise::groups::upload_group_image",
             "Successfully uploaded group image for group {} to Blossom server. Hash: {}",
@@ -1241,15 +2047,20 @@ impl Whitenoise {
     /// Uses the encrypted media manager which derives encryption keys from the group secret.
     /// The encryption keys are not returned as they can be re-derived for decryption.
     ///
+    /// Uploading content that is already uploaded, or is being uploaded by a concurrent call,
+    /// doesn't go to Blossom again: the call waits for the other upload and returns its record.
+    ///
     /// # Arguments
     /// * `account` - The account uploading the media file
     /// * `group_id` - The ID of the group where the media will be used
     /// * `file_path` - Path to the media file to upload
-    /// * `blossom_server_url` - Optional Blossom server URL (uses default if None)
+    /// * `blossom_server_url` - Optional Blossom server to try first; the media is mirrored to the configured servers as well
     /// * `options` - Optional media processing options (defaults to standard options if None)
     ///
     /// # Returns
     /// * `Ok(MediaFile)` - MediaFile record containing file hash and metadata
+    /// * `Err(WhitenoiseError::MediaFileTooLarge)` - If the file, or the sanitized, encrypted
+    ///   file, exceeds [`Whitenoise::max_media_bytes`]; nothing is uploaded
     /// * `Err(WhitenoiseError)` - If media validation, upload, or caching fails
     pub async fn upload_chat_media(
         &self,
@@ -1259,11 +2070,83 @@ impl Whitenoise {
         blossom_server_url: Option<Url>,
         options: Option<MediaProcessingOptions>,
     ) -> Result<MediaFile> {
-        // Read the media file
-        let file_data = tokio::fs::read(file_path).await?;
+        self.upload_chat_media_inner(
+            account,
+            group_id,
+            file_path,
+            None,
+            blossom_server_url,
+            options,
+        )
+        .await
+    }
 
-        // Detect and validate media type from file content
+    /// Uploads a media file the caller labeled with a MIME type, checking the label against
+    /// the file's contents.
+    ///
+    /// The type is always taken from the file's contents, since a mislabeled file (e.g. a PDF
+    /// declared as `image/png`) would break rendering and blurhash generation. A mismatch is
+    /// logged and the detected type used, unless the config's `reject_mismatched_media_types`
+    /// is set.
+    ///
+    /// # Arguments
+    /// * `mime_type` - The MIME type the caller declared for the file
+    ///
+    /// See [`Whitenoise::upload_chat_media`] for the other arguments.
+    ///
+    /// # Errors
+    /// Returns [`WhitenoiseError::MimeMismatch`] if the contents don't match `mime_type` and
+    /// mismatches are rejected, and the errors of [`Whitenoise::upload_chat_media`] otherwise.
+    pub async fn upload_chat_media_with_mime_type(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        file_path: &str,
+        mime_type: &str,
+        blossom_server_url: Option<Url>,
+        options: Option<MediaProcessingOptions>,
+    ) -> Result<MediaFile> {
+        self.upload_chat_media_inner(
+            account,
+            group_id,
+            file_path,
+            Some(mime_type),
+            blossom_server_url,
+            options,
+        )
+        .await
+    }
+
+    async fn upload_chat_media_inner(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        file_path: &str,
+        declared_mime_type: Option<&str>,
+        blossom_server_url: Option<Url>,
+        options: Option<MediaProcessingOptions>,
+    ) -> Result<MediaFile> {
+        // Refuse oversized files before loading them into memory
+        let size = tokio::fs::metadata(file_path).await?.len();
+        if size > self.config.max_media_bytes {
+            return Err(WhitenoiseError::MediaFileTooLarge {
+                size,
+                limit: self.config.max_media_bytes,
+            });
+        }
+
+        // Read the media file
+        let file_data = tokio::fs::read(file_path).await?;
+
+        // Detect and validate media type from file content
         let media_detection = crate::types::detect_media_type(&file_data)?;
+        if let Some(declared_mime_type) = declared_mime_type {
+            crate::types::check_declared_mime_type(
+                declared_mime_type,
+                &media_detection,
+                self.config.reject_mismatched_media_types,
+            )?;
+        }
 
         tracing::debug!(
             target: "whitenoise::groups::upload_chat_media",
@@ -1296,69 +2179,114 @@ impl Whitenoise {
                 })?
         };
 
-        let blossom_server_url = blossom_server_url.unwrap_or_else(Self::default_blossom_url);
+        // MDK sanitizes the file as part of encryption, so this is the first point where
+        // the size of what will actually be uploaded is known
+        let size = prepared.encrypted_data.len() as u64;
+        if size > self.config.max_media_bytes {
+            return Err(WhitenoiseError::MediaFileTooLarge {
+                size,
+                limit: self.config.max_media_bytes,
+            });
+        }
 
-        // Generate fresh keys for upload authentication (for MIP-04 cleanup)
-        let upload_keys = nostr_sdk::Keys::generate();
-        let upload_keys_hex = upload_keys.secret_key().to_secret_hex();
+        // Concurrent uploads of identical content share one Blossom upload: later callers
+        // wait for the first and reuse the record it stored
+        let encrypted_hash = prepared.encrypted_hash;
+        let guard = self
+            .media_upload_guards
+            .entry(encrypted_hash)
+            .or_insert_with(|| Arc::new(Semaphore::new(1)))
+            .clone();
+
+        let result: Result<MediaFile> = async {
+            let _permit = guard.acquire().await.map_err(|_| {
+                WhitenoiseError::Other(anyhow::anyhow!("Failed to acquire media upload guard"))
+            })?;
 
-        // Upload encrypted data to Blossom
-        let descriptor = Self::upload_encrypted_blob_to_blossom(
-            &blossom_server_url,
-            prepared.encrypted_data,
-            &prepared.mime_type,
-            &upload_keys,
-        )
-        .await?;
+            if let Some(existing) = MediaFile::find_by_hash(&self.database, &encrypted_hash).await?
+                && existing.mls_group_id == *group_id
+                && existing.account_pubkey == account.pubkey
+                && existing.blossom_url.is_some()
+            {
+                tracing::debug!(
+                    target: "whitenoise::groups::upload_chat_media",
+                    "Chat media {} was already uploaded, reusing it",
+                    hex::encode(encrypted_hash)
+                );
+                return Ok(existing);
+            }
 
-        // Verify the Blossom server returned the expected hash
-        let returned_hash_bytes: [u8; 32] = *descriptor.sha256.as_ref();
-        if returned_hash_bytes != prepared.encrypted_hash {
-            return Err(WhitenoiseError::HashMismatch {
-                expected: hex::encode(prepared.encrypted_hash),
-                actual: hex::encode(returned_hash_bytes),
-            });
-        }
+            let servers = self.blossom_upload_servers(blossom_server_url);
 
-        tracing::debug!(
-            target: "whitenoise::groups::upload_chat_media",
-            "Successfully uploaded chat media for group {} to Blossom server. Hash: {}",
-            hex::encode(group_id.as_slice()),
-            hex::encode(prepared.encrypted_hash)
-        );
+            // Generate fresh keys for upload authentication (for MIP-04 cleanup)
+            let upload_keys = nostr_sdk::Keys::generate();
+            let upload_keys_hex = upload_keys.secret_key().to_secret_hex();
 
-        // Cache the decrypted media file locally
-        let hash_hex = hex::encode(prepared.encrypted_hash);
-        let cached_filename = format!("{}.{}", hash_hex, media_detection.extension());
+            // Upload encrypted data to every configured Blossom server
+            let descriptor = Self::mirror_encrypted_blob_to_blossom(
+                &servers,
+                prepared.encrypted_data,
+                &prepared.encrypted_hash,
+                &prepared.mime_type,
+                &upload_keys,
+                self.config.blossom_auth_expiry,
+            )
+            .await?;
+
+            tracing::debug!(
+                target: "whitenoise::groups::upload_chat_media",
+                "Successfully uploaded chat media for group {} to Blossom server. Hash: {}",
+                hex::encode(group_id.as_slice()),
+                hex::encode(prepared.encrypted_hash)
+            );
 
-        // Construct file metadata from the prepared media data
-        let file_metadata = if prepared.dimensions.is_some() || prepared.blurhash.is_some() {
-            Some(FileMetadata {
+            // Cache the decrypted media file locally
+            let hash_hex = hex::encode(prepared.encrypted_hash);
+            let cached_filename = format!("{}.{}", hash_hex, media_detection.extension());
+
+            // Construct file metadata from the prepared media data. The filename is always kept
+            // since decryption and the imeta tag need it; MDK only produces dimensions and a
+            // blurhash for images.
+            let file_metadata = FileMetadata {
                 original_filename: Some(prepared.filename.clone()),
                 dimensions: prepared.dimensions.map(|(w, h)| format!("{}x{}", w, h)),
                 blurhash: prepared.blurhash.clone(),
-            })
-        } else {
-            None
-        };
+            };
 
-        let upload = MediaFileUpload {
-            data: &file_data,
-            original_file_hash: Some(&prepared.original_hash),
-            encrypted_file_hash: prepared.encrypted_hash,
-            mime_type: &prepared.mime_type,
-            media_type: "chat_media",
-            blossom_url: Some(descriptor.url.as_str()),
-            nostr_key: Some(upload_keys_hex),
-            file_metadata: file_metadata.as_ref(),
-        };
+            let upload = MediaFileUpload {
+                data: &file_data,
+                original_file_hash: Some(&prepared.original_hash),
+                encrypted_file_hash: prepared.encrypted_hash,
+                mime_type: &prepared.mime_type,
+                media_type: "chat_media",
+                blossom_url: Some(descriptor.url.as_str()),
+                nostr_key: Some(upload_keys_hex),
+                file_metadata: Some(&file_metadata),
+            };
 
-        let media_file = self
-            .media_files()
-            .store_and_record(&account.pubkey, group_id, &cached_filename, upload)
-            .await?;
+            let media_file = self
+                .media_files()
+                .store_and_record(&account.pubkey, group_id, &cached_filename, upload)
+                .await?;
 
-        Ok(media_file)
+            Ok(media_file)
+        }
+        .await;
+
+        // The permit is released by now, also when the upload failed; drop the guard once
+        // nobody else is waiting on it
+        drop(guard);
+        self.media_upload_guards
+            .remove_if(&encrypted_hash, |_, guard| Arc::strong_count(guard) == 1);
+
+        result
+    }
+
+    /// Largest chat media file [`Whitenoise::upload_chat_media`] accepts, in bytes.
+    ///
+    /// Lets the UI reject oversized attachments before the user tries to send them.
+    pub fn max_media_bytes(&self) -> u64 {
+        self.config.max_media_bytes
     }
 
     /// Downloads a chat media file and returns the updated MediaFile record
@@ -1366,7 +2294,8 @@ impl Whitenoise {
     /// This method downloads and decrypts media files sent in group chat messages.
     /// It uses the original file hash from the imeta 'x' field per MIP-04 specification.
     ///
-    /// **On-demand download**: If the file is already cached, returns immediately.
+    /// **On-demand download**: If the file is already cached, returns immediately. A cached
+    /// file that no longer matches `original_file_hash` is downloaded again.
     /// **Idempotent**: Safe to call multiple times.
     ///
     /// # Arguments
@@ -1383,7 +2312,7 @@ impl Whitenoise {
     /// * `MediaCache("Not chat media")` - The MediaFile is not a chat_media type
     /// * `MediaCache("Missing required metadata")` - Required filename/dimensions are missing
     /// * `MediaCache("No Blossom URL")` - MediaFile has no Blossom download URL
-    /// * `HashMismatch` - Downloaded blob doesn't match expected hash
+    /// * `HashMismatch` - Downloaded blob or decrypted file doesn't match expected hash
     /// * Network errors during download
     /// * MDK decryption errors
     ///
@@ -1402,6 +2331,36 @@ impl Whitenoise {
         group_id: &GroupId,
         original_file_hash: &[u8; 32],
     ) -> Result<MediaFile> {
+        self.download_chat_media_with_progress(account, group_id, original_file_hash, |_, _| {})
+            .await
+    }
+
+    /// Downloads a chat media file like [`Whitenoise::download_chat_media`], reporting progress
+    ///
+    /// `progress` is called on the downloading task with the number of encrypted bytes
+    /// received so far and the total size announced by the Blossom server, if it sent one.
+    /// It is not called at all when the file is already cached. If a server fails partway
+    /// and the download falls back to another one, progress starts over from zero.
+    ///
+    /// Dropping the returned future aborts the download. Nothing is written to the cache
+    /// until the whole file has been downloaded, verified and decrypted, so a cancelled
+    /// download never leaves a partial file behind.
+    ///
+    /// # Arguments
+    /// * `account` - The account downloading the media
+    /// * `group_id` - The MLS group ID where the media was shared
+    /// * `original_file_hash` - The SHA-256 hash of the original file (from imeta 'x' field)
+    /// * `progress` - Called with `(bytes_received, total_bytes)` as data arrives
+    pub async fn download_chat_media_with_progress<F>(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        original_file_hash: &[u8; 32],
+        mut progress: F,
+    ) -> Result<MediaFile>
+    where
+        F: FnMut(u64, Option<u64>),
+    {
         // Find MediaFile record by original_file_hash + group + account (MIP-04 compliant)
         let media_file = MediaFile::find_by_original_hash_and_group(
             &self.database,
@@ -1418,8 +2377,12 @@ impl Whitenoise {
             ))
         })?;
 
-        // Check if already cached (early return for idempotency)
-        if !media_file.file_path.as_os_str().is_empty() && media_file.file_path.exists() {
+        // Check if already cached (early return for idempotency). A cached file that doesn't
+        // match its hash (e.g. corrupted on disk) is treated as a miss and downloaded again.
+        if !media_file.file_path.as_os_str().is_empty()
+            && media_file.file_path.exists()
+            && Self::is_cached_file_intact(&media_file.file_path, original_file_hash).await
+        {
             tracing::debug!(
                 target: "whitenoise::groups::download_chat_media",
                 "Media file already cached at: {}",
@@ -1437,14 +2400,18 @@ impl Whitenoise {
         }
 
         // Download and decrypt the media blob
-        let decrypted_data = Self::download_and_decrypt_chat_media_blob(
-            &account.pubkey,
-            &self.config.data_dir,
-            group_id,
-            &media_file,
-            original_file_hash,
-        )
-        .await?;
+        let decrypted_data = self
+            .download_and_decrypt_chat_media_blob(
+                &account.pubkey,
+                group_id,
+                &media_file,
+                original_file_hash,
+                &mut progress,
+            )
+            .await?;
+
+        // Never cache data that doesn't match the hash the sender announced
+        Self::verify_blob_hash(&decrypted_data, original_file_hash)?;
 
         // Detect MIME type and extension from decrypted content
         let media_detection = crate::types::detect_media_type(&decrypted_data)?;
@@ -1508,6 +2475,67 @@ impl Whitenoise {
         MediaFile::find_by_group(&self.database, group_id).await
     }
 
+    /// Lists the chat media shared in a group for a media gallery
+    ///
+    /// Returns every chat media file known to the account in this group, downloaded or
+    /// not, ordered by the time of the message that shared it (oldest first). Media whose
+    /// message has not been cached yet is listed last. Media from deleted messages is
+    /// omitted.
+    ///
+    /// # Arguments
+    /// * `account` - The account viewing the group
+    /// * `group_id` - The MLS group ID
+    ///
+    /// # Returns
+    /// * `Ok(Vec<MediaFileInfo>)` - Media files with metadata and download state
+    /// * `Err(WhitenoiseError)` - If the account doesn't exist or a database query fails
+    pub async fn group_media(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+    ) -> Result<Vec<MediaFileInfo>> {
+        Account::find_by_pubkey(&account.pubkey, &self.database).await?; // Verify account exists (security check)
+        let messages = self.visible_group_messages(account, group_id).await?;
+
+        // Map each attachment's encrypted hash to the message that carried it
+        let mut shared_in: HashMap<Vec<u8>, (String, Timestamp)> = HashMap::new();
+        let mut deleted: HashSet<Vec<u8>> = HashSet::new();
+        for message in &messages {
+            for attachment in &message.media_attachments {
+                if message.is_deleted {
+                    deleted.insert(attachment.encrypted_file_hash.clone());
+                } else {
+                    shared_in
+                        .entry(attachment.encrypted_file_hash.clone())
+                        .or_insert_with(|| (message.id.clone(), message.created_at));
+                }
+            }
+        }
+
+        let media_files = MediaFile::find_by_group_account_and_type(
+            &self.database,
+            group_id,
+            &account.pubkey,
+            "chat_media",
+        )
+        .await?;
+
+        let mut media = Vec::with_capacity(media_files.len());
+        for media_file in media_files {
+            let message = shared_in.get(&media_file.encrypted_file_hash).cloned();
+            if message.is_none() && deleted.contains(&media_file.encrypted_file_hash) {
+                continue;
+            }
+            let (message_id, sent_at) = message.unzip();
+            media.push(MediaFileInfo::from_media_file(media_file, message_id, sent_at).await);
+        }
+
+        // Stable sort keeps record order for media without a cached message
+        media.sort_by_key(|info| (info.sent_at.is_none(), info.sent_at));
+
+        Ok(media)
+    }
+
     /// Gets the local file path for a group's current image
     ///
     /// This is the primary method for UI/Flutter to retrieve group images.
@@ -1583,6 +2611,18 @@ impl Whitenoise {
     }
 }
 
+/// Display name or name from a member's profile, falling back to a shortened npub
+fn member_display_name(pubkey: &PublicKey, metadata: &Metadata) -> String {
+    if let Some(name) = profile_name(metadata) {
+        return name.clone();
+    }
+
+    match pubkey.to_bech32() {
+        Ok(npub) => format!("{}…{}", &npub[..12], &npub[npub.len() - 4..]),
+        Err(_) => pubkey.to_hex(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1652,6 +2692,146 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn test_remove_members_from_group_cuts_off_removed_member() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member_account = members[0].0.clone();
+        let group_id =
+            create_group_with_joined_member(&whitenoise, &creator_account, &member_account).await;
+
+        // Only admins can remove members, and admins can't remove themselves
+        let result = whitenoise
+            .remove_members_from_group(&member_account, &group_id, vec![creator_account.pubkey])
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::AccountNotAuthorized)));
+        let result = whitenoise
+            .remove_members_from_group(&creator_account, &group_id, vec![creator_account.pubkey])
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::InvalidInput(_))));
+
+        whitenoise
+            .remove_members_from_group(&creator_account, &group_id, vec![member_account.pubkey])
+            .await
+            .unwrap();
+        let remaining = whitenoise
+            .group_members(&creator_account, &group_id)
+            .await
+            .unwrap();
+        assert_eq!(remaining, vec![creator_account.pubkey]);
+
+        // The removed member can't read what is sent after the removal
+        let creator_mdk =
+            Account::create_mdk(creator_account.pubkey, &whitenoise.config.data_dir).unwrap();
+        let member_mdk =
+            Account::create_mdk(member_account.pubkey, &whitenoise.config.data_dir).unwrap();
+        let rumor =
+            EventBuilder::new(Kind::Custom(9), "after the removal").build(creator_account.pubkey);
+        let message_event = creator_mdk.create_message(&group_id, rumor).unwrap();
+        let result = member_mdk.process_message(&message_event);
+        assert!(!matches!(
+            result,
+            Ok(MessageProcessingResult::ApplicationMessage(_))
+        ));
+
+        // Nor do the remaining members take in what the removed member sends afterwards
+        let mut rumor =
+            EventBuilder::new(Kind::Custom(9), "still here?").build(member_account.pubkey);
+        rumor.ensure_id();
+        let rumor_id = rumor.id.unwrap();
+        let message_event = member_mdk.create_message(&group_id, rumor).unwrap();
+        let _ = whitenoise
+            .handle_mls_message(&creator_account, message_event)
+            .await;
+        assert!(
+            AggregatedMessage::find_by_id(&rumor_id.to_string(), &group_id, &whitenoise.database)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rotate_group_key_advances_epoch() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let group = whitenoise
+            .create_group(
+                &creator_account,
+                vec![members[0].0.pubkey],
+                create_nostr_group_config_data(vec![creator_account.pubkey]),
+                None,
+            )
+            .await
+            .unwrap();
+        let epoch = |whitenoise: &Whitenoise| {
+            Account::create_mdk(creator_account.pubkey, &whitenoise.config.data_dir)
+                .unwrap()
+                .get_group(&group.mls_group_id)
+                .unwrap()
+                .unwrap()
+                .epoch
+        };
+        let before = epoch(&whitenoise);
+
+        whitenoise
+            .rotate_group_key(&creator_account, &group.mls_group_id)
+            .await
+            .unwrap();
+        assert_eq!(epoch(&whitenoise), before + 1);
+
+        // Messages still go out under the new epoch
+        whitenoise
+            .send_message_to_group(
+                &creator_account,
+                &group.mls_group_id,
+                "after rotation".to_string(),
+                9,
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_create_group_checked_skips_members_without_key_package() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let member_account = whitenoise.create_identity().await.unwrap();
+        let without_key_package = create_test_keys().public_key();
+
+        let outcome = whitenoise
+            .create_group_checked(
+                &creator_account,
+                vec![member_account.pubkey, without_key_package],
+                create_nostr_group_config_data(vec![creator_account.pubkey]),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(outcome.missing_key_package, vec![without_key_package]);
+
+        let members = whitenoise
+            .group_members(&creator_account, &outcome.group.mls_group_id)
+            .await
+            .unwrap();
+        assert_eq!(members.len(), 2);
+        assert!(members.contains(&member_account.pubkey));
+
+        // Nobody left to add
+        let result = whitenoise
+            .create_group_checked(
+                &creator_account,
+                vec![without_key_package],
+                create_nostr_group_config_data(vec![creator_account.pubkey]),
+                None,
+            )
+            .await;
+        assert!(result.is_err());
+    }
+
     async fn case_create_group_success(
         whitenoise: &Whitenoise,
         creator_account: &Account,
@@ -1923,18 +3103,26 @@ mod tests {
             .map(|(acc, _)| acc.pubkey)
             .collect::<Vec<_>>();
 
-        let add_result = whitenoise
+        // A member without a key package is skipped instead of failing the whole addition
+        let without_key_package = create_test_keys().public_key();
+        let mut requested = new_member_pubkeys.clone();
+        requested.push(without_key_package);
+        let outcome = whitenoise
+            .add_members_to_group(&creator_account, &group.mls_group_id, requested)
+            .await
+            .expect("Failed to add members");
+        assert_eq!(outcome.added, new_member_pubkeys);
+        assert_eq!(outcome.missing_key_package, vec![without_key_package]);
+
+        // Nobody left to add
+        let result = whitenoise
             .add_members_to_group(
                 &creator_account,
                 &group.mls_group_id,
-                new_member_pubkeys.clone(),
+                vec![without_key_package],
             )
             .await;
-        assert!(
-            add_result.is_ok(),
-            "Failed to add members: {:?}",
-            add_result.unwrap_err()
-        );
+        assert!(result.is_err());
 
         // Verify new membership count
         let updated_members = whitenoise
@@ -2110,6 +3298,70 @@ mod tests {
         assert_eq!(Relay::urls(&resolved_relays), inbox_urls);
     }
 
+    #[tokio::test]
+    async fn test_set_group_slow_mode_requires_admin() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member_account = members[0].0.clone();
+        let group_id =
+            create_group_with_joined_member(&whitenoise, &creator_account, &member_account).await;
+
+        let result = whitenoise
+            .set_group_slow_mode(&member_account, &group_id, SlowMode::default())
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::AccountNotAuthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_slow_mode_settings_delivered_out_of_order() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member_account = members[0].0.clone();
+        let group_id =
+            create_group_with_joined_member(&whitenoise, &creator_account, &member_account).await;
+
+        let creator_mdk =
+            Account::create_mdk(creator_account.pubkey, &whitenoise.config.data_dir).unwrap();
+        let settings_event = |slow_mode: SlowMode, published_at: u64| {
+            let rumor = EventBuilder::new(
+                Kind::Custom(SLOW_MODE_SETTINGS_KIND),
+                serde_json::to_string(&slow_mode).unwrap(),
+            )
+            .tag(Tag::identifier(SLOW_MODE_SETTINGS_D_TAG))
+            .custom_created_at(Timestamp::from(published_at))
+            .build(creator_account.pubkey);
+            creator_mdk.create_message(&group_id, rumor).unwrap()
+        };
+        let older = SlowMode {
+            interval_secs: 30,
+            admins_exempt: false,
+        };
+        let newer = SlowMode {
+            interval_secs: 300,
+            admins_exempt: true,
+        };
+        let older_event = settings_event(older, 1_000);
+        let newer_event = settings_event(newer, 2_000);
+
+        // The newer settings arrive first; the delayed older ones must not replace them
+        whitenoise
+            .handle_mls_message(&member_account, newer_event)
+            .await
+            .unwrap();
+        whitenoise
+            .handle_mls_message(&member_account, older_event)
+            .await
+            .unwrap();
+
+        let group_information =
+            GroupInformation::find_by_mls_group_id(&group_id, &whitenoise.database)
+                .await
+                .unwrap();
+        assert_eq!(group_information.slow_mode, newer);
+    }
+
     #[tokio::test]
     async fn test_resolve_member_delivery_relays_uses_nip65_when_inbox_missing() {
         let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
@@ -2304,6 +3556,142 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_group_members_with_metadata() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 2).await;
+
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let group = whitenoise
+            .create_group(
+                &creator_account,
+                vec![members[0].0.pubkey, members[1].0.pubkey],
+                config,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let mut named = User::find_by_pubkey(&members[0].0.pubkey, &whitenoise.database)
+            .await
+            .unwrap();
+        named.metadata = Metadata::new().name("alice").display_name("Alice");
+        named.save(&whitenoise.database).await.unwrap();
+        let mut unnamed = User::find_by_pubkey(&members[1].0.pubkey, &whitenoise.database)
+            .await
+            .unwrap();
+        unnamed.metadata = Metadata::new();
+        unnamed.save(&whitenoise.database).await.unwrap();
+
+        let listed = whitenoise
+            .group_members_with_metadata(&creator_account, &group.mls_group_id)
+            .await
+            .unwrap();
+        assert_eq!(listed.len(), 3);
+        assert_eq!(listed[0].pubkey, creator_account.pubkey);
+        assert!(listed[0].is_admin);
+        assert_eq!(listed.iter().filter(|member| member.is_admin).count(), 1);
+
+        let alice = listed
+            .iter()
+            .find(|member| member.pubkey == members[0].0.pubkey)
+            .unwrap();
+        assert_eq!(alice.display_name, "Alice");
+        assert_eq!(alice.metadata.name.as_deref(), Some("alice"));
+
+        let unnamed = listed
+            .iter()
+            .find(|member| member.pubkey == members[1].0.pubkey)
+            .unwrap();
+        assert!(unnamed.display_name.starts_with("npub1"));
+        assert!(unnamed.display_name.contains('…'));
+    }
+
+    #[tokio::test]
+    async fn test_update_group_metadata_partial_update() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let (member_account, _) = &members[0];
+        let group_id =
+            create_group_with_joined_member(&whitenoise, &creator_account, member_account).await;
+        let group = whitenoise.group(&creator_account, &group_id).await.unwrap();
+
+        let group_information = whitenoise
+            .update_group_metadata(
+                &creator_account,
+                &group_id,
+                Some("Renamed Group".to_string()),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(group_information.mls_group_id, group_id);
+
+        let updated_group = whitenoise.group(&creator_account, &group_id).await.unwrap();
+        assert_eq!(updated_group.name, "Renamed Group");
+        assert_eq!(updated_group.description, group.description);
+        assert_eq!(updated_group.image_hash, group.image_hash);
+
+        // Non-admins may not change the metadata
+        let result = whitenoise
+            .update_group_metadata(
+                member_account,
+                &group_id,
+                Some("Hijacked".to_string()),
+                None,
+                None,
+            )
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::AccountNotAuthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_update_group_relays() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let (member_account, _) = &members[0];
+        // The member joins right away, so it sees the group without waiting on relays
+        let group_id =
+            create_group_with_joined_member(&whitenoise, &creator_account, member_account).await;
+
+        let new_relays = vec![
+            RelayUrl::parse("ws://localhost:7777").unwrap(),
+            RelayUrl::parse("ws://localhost:8080").unwrap(),
+            RelayUrl::parse("ws://localhost:7777").unwrap(),
+        ];
+        whitenoise
+            .update_group_relays(&creator_account, &group_id, new_relays)
+            .await
+            .unwrap();
+
+        let mdk = Account::create_mdk(creator_account.pubkey, &whitenoise.config.data_dir).unwrap();
+        let relays = mdk.get_relays(&group_id).unwrap();
+        assert_eq!(relays.len(), 2);
+        assert!(relays.contains(&RelayUrl::parse("ws://localhost:7777").unwrap()));
+
+        // A group needs a relay, and only admins may change them
+        assert!(matches!(
+            whitenoise
+                .update_group_relays(&creator_account, &group_id, vec![])
+                .await,
+            Err(WhitenoiseError::GroupMissingRelays)
+        ));
+        assert!(matches!(
+            whitenoise
+                .update_group_relays(
+                    member_account,
+                    &group_id,
+                    vec![RelayUrl::parse("ws://localhost:7777").unwrap()],
+                )
+                .await,
+            Err(WhitenoiseError::AccountNotAuthorized)
+        ));
+    }
+
     #[tokio::test]
     async fn test_leave_group() {
         let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
@@ -2351,8 +3739,119 @@ mod tests {
         // but that's part of the message processing pipeline that would be
         // tested separately in integration tests.
 
-        // For now, we just verify that the proposal was successfully created and published
-        // without errors, which indicates the leave_group method works correctly.
+        // Locally the creator no longer follows the group, which was its only one
+        let subscription_id = SubscriptionId::new(format!(
+            "{}_mls_messages",
+            whitenoise.nostr.create_pubkey_hash(&creator_account.pubkey)
+        ));
+        assert!(
+            !whitenoise
+                .nostr
+                .network
+                .subscriptions()
+                .await
+                .contains_key(&subscription_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_leave_group_rejects_last_admin_and_non_members() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let group = whitenoise
+            .create_group(&creator_account, vec![members[0].0.pubkey], config, None)
+            .await
+            .unwrap();
+
+        let result = whitenoise
+            .leave_group(&creator_account, &group.mls_group_id)
+            .await;
+        assert!(
+            matches!(result, Err(WhitenoiseError::LastGroupAdmin)),
+            "Expected LastGroupAdmin, got: {:?}",
+            result
+        );
+
+        let outsider = whitenoise.create_identity().await.unwrap();
+        let result = whitenoise.leave_group(&outsider, &group.mls_group_id).await;
+        assert!(
+            matches!(result, Err(WhitenoiseError::GroupNotFound)),
+            "Expected GroupNotFound, got: {:?}",
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn test_leave_group_unpins_the_group() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member = &members[0].0;
+        let group_id = create_group_with_joined_member(&whitenoise, &creator_account, member).await;
+
+        whitenoise
+            .pin_conversation(member, &group_id, 0)
+            .await
+            .unwrap();
+        let member_id = member.id.unwrap();
+        assert_eq!(
+            PinnedConversations::find(member_id, &whitenoise.database)
+                .await
+                .unwrap(),
+            vec![group_id.clone()]
+        );
+
+        whitenoise.leave_group(member, &group_id).await.unwrap();
+        assert!(
+            PinnedConversations::find(member_id, &whitenoise.database)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_left_group_is_not_resubscribed() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member = &members[0].0;
+        let group_id = create_group_with_joined_member(&whitenoise, &creator_account, member).await;
+
+        whitenoise.leave_group(member, &group_id).await.unwrap();
+
+        // The removal isn't committed yet, so MDK still lists the group as active
+        let mdk = Account::create_mdk(member.pubkey, &whitenoise.config.data_dir).unwrap();
+        assert_eq!(
+            mdk.get_group(&group_id).unwrap().unwrap().state,
+            group_types::GroupState::Active
+        );
+        let (_, nostr_group_ids) = whitenoise
+            .extract_groups_relays_and_ids(member)
+            .await
+            .unwrap();
+        assert!(nostr_group_ids.is_empty());
+
+        // Drop the account's subscriptions so ensuring them sets them all up again
+        whitenoise
+            .nostr
+            .unsubscribe_account_subscriptions(&member.pubkey)
+            .await
+            .unwrap();
+        whitenoise.ensure_all_subscriptions().await.unwrap();
+
+        let pubkey_hash = whitenoise.nostr.create_pubkey_hash(&member.pubkey);
+        let subscriptions = whitenoise.nostr.network.subscriptions().await;
+        assert!(
+            subscriptions.contains_key(&SubscriptionId::new(format!("{}_giftwrap", pubkey_hash)))
+        );
+        assert!(!subscriptions.contains_key(&SubscriptionId::new(format!(
+            "{}_mls_messages",
+            pubkey_hash
+        ))));
     }
 
     #[tokio::test]
@@ -2675,4 +4174,417 @@ mod tests {
             "Original filename should be stored"
         );
     }
+
+    #[tokio::test]
+    async fn test_upload_chat_media_releases_guard_when_upload_fails() {
+        use tempfile::NamedTempFile;
+
+        let (mut whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        whitenoise.config.blossom_servers = vec![];
+
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let group = whitenoise
+            .create_group(&creator_account, vec![members[0].0.pubkey], config, None)
+            .await
+            .unwrap();
+
+        let img = ::image::RgbaImage::from_pixel(16, 16, ::image::Rgba([0u8, 0, 255, 255]));
+        let temp_file = NamedTempFile::new().unwrap();
+        img.save_with_format(temp_file.path(), ::image::ImageFormat::Png)
+            .unwrap();
+        let upload = || {
+            whitenoise.upload_chat_media(
+                &creator_account,
+                &group.mls_group_id,
+                temp_file.path().to_str().unwrap(),
+                Some(Url::parse("http://127.0.0.1:1").unwrap()),
+                Some(MediaProcessingOptions {
+                    generate_blurhash: false,
+                    ..Default::default()
+                }),
+            )
+        };
+
+        let (first, second) = tokio::join!(upload(), upload());
+        assert!(first.is_err());
+        assert!(second.is_err());
+        assert!(whitenoise.media_upload_guards.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_identical_uploads_put_once() {
+        use tempfile::NamedTempFile;
+
+        let (mut whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        whitenoise.config.blossom_servers = vec![];
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let group = whitenoise
+            .create_group(&creator_account, vec![members[0].0.pubkey], config, None)
+            .await
+            .unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        let server_url = server.url();
+        let mock = server
+            .mock("PUT", "/upload")
+            .with_header("content-type", "application/json")
+            .with_body_from_request(move |request| {
+                let body = request.body().unwrap();
+                let hash = hex::encode(Sha256::digest(body));
+                serde_json::json!({
+                    "url": format!("{}/{}", server_url, hash),
+                    "sha256": hash,
+                    "size": body.len(),
+                    "type": "application/octet-stream",
+                    "uploaded": Timestamp::now().as_u64(),
+                })
+                .to_string()
+                .into_bytes()
+            })
+            .expect(1)
+            .create_async()
+            .await;
+
+        let img = ::image::RgbaImage::from_pixel(16, 16, ::image::Rgba([0u8, 255, 0, 255]));
+        let temp_file = NamedTempFile::new().unwrap();
+        img.save_with_format(temp_file.path(), ::image::ImageFormat::Png)
+            .unwrap();
+        let upload = || {
+            whitenoise.upload_chat_media(
+                &creator_account,
+                &group.mls_group_id,
+                temp_file.path().to_str().unwrap(),
+                Some(Url::parse(&server.url()).unwrap()),
+                Some(MediaProcessingOptions {
+                    generate_blurhash: false,
+                    ..Default::default()
+                }),
+            )
+        };
+
+        let (first, second) = tokio::join!(upload(), upload());
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_eq!(first.encrypted_file_hash, second.encrypted_file_hash);
+        mock.assert_async().await;
+        assert!(whitenoise.media_upload_guards.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_upload_chat_media_rejects_files_over_limit() {
+        use tempfile::NamedTempFile;
+
+        let (mut whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        whitenoise.config.max_media_bytes = 1024;
+        assert_eq!(whitenoise.max_media_bytes(), 1024);
+
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let group = whitenoise
+            .create_group(&creator_account, vec![members[0].0.pubkey], config, None)
+            .await
+            .unwrap();
+
+        // Noise doesn't compress, so the PNG stays well above the limit
+        let img = ::image::RgbaImage::from_fn(64, 64, |x, y| {
+            ::image::Rgba([(x * 31 + y * 17) as u8, (x * y) as u8, (x ^ y) as u8, 255])
+        });
+        let temp_file = NamedTempFile::new().unwrap();
+        img.save_with_format(temp_file.path(), ::image::ImageFormat::Png)
+            .unwrap();
+
+        let result = whitenoise
+            .upload_chat_media(
+                &creator_account,
+                &group.mls_group_id,
+                temp_file.path().to_str().unwrap(),
+                Some(Url::parse("http://localhost:3000").unwrap()),
+                Some(MediaProcessingOptions {
+                    generate_blurhash: false,
+                    ..Default::default()
+                }),
+            )
+            .await;
+
+        // The file is rejected by its size on disk, before it is read
+        let file_size = std::fs::metadata(temp_file.path()).unwrap().len();
+        assert!(matches!(
+            result,
+            Err(WhitenoiseError::MediaFileTooLarge { size, limit: 1024 }) if size == file_size
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_group_media_lists_shared_media_in_message_order() {
+        use crate::whitenoise::aggregated_message::AggregatedMessage;
+        use crate::whitenoise::database::media_files::MediaFileParams;
+        use crate::whitenoise::message_aggregator::{ChatMessage, ReactionSummary};
+
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let (account, _keys) = create_test_account(&whitenoise).await;
+        let account = account.save(&whitenoise.database).await.unwrap();
+        let group_id = GroupId::from_slice(&[7; 32]);
+        GroupInformation::find_or_create_by_mls_group_id(
+            &group_id,
+            Some(GroupType::Group),
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+
+        // A downloaded image and a video that has only been referenced
+        let image_data = b"decrypted image bytes";
+        let image_metadata = FileMetadata::new()
+            .with_filename("photo.png".to_string())
+            .with_dimensions("640x480".to_string())
+            .with_blurhash("LEHV6nWB2yk8".to_string());
+        let image = whitenoise
+            .media_files()
+            .store_and_record(
+                &account.pubkey,
+                &group_id,
+                "photo.png",
+                MediaFileUpload {
+                    data: image_data,
+                    original_file_hash: Some(&[1u8; 32]),
+                    encrypted_file_hash: [11u8; 32],
+                    mime_type: "image/png",
+                    media_type: "chat_media",
+                    blossom_url: Some("https://blossom.example.com/image"),
+                    nostr_key: None,
+                    file_metadata: Some(&image_metadata),
+                },
+            )
+            .await
+            .unwrap();
+
+        let video_metadata = FileMetadata::new()
+            .with_filename("clip.mp4".to_string())
+            .with_dimensions("1920x1080".to_string());
+        let video = MediaFile::save(
+            &whitenoise.database,
+            &group_id,
+            &account.pubkey,
+            MediaFileParams {
+                file_path: &PathBuf::from(""),
+                original_file_hash: Some(&[2u8; 32]),
+                encrypted_file_hash: &[22u8; 32],
+                mime_type: "video/mp4",
+                media_type: "chat_media",
+                blossom_url: Some("https://blossom.example.com/video"),
+                nostr_key: None,
+                file_metadata: Some(&video_metadata),
+            },
+        )
+        .await
+        .unwrap();
+
+        // The video was shared first, even though its record was created second
+        let now = Timestamp::now();
+        for (id, created_at, attachment) in
+            [(1, now, image), (2, now - Duration::from_secs(60), video)]
+        {
+            let message = ChatMessage {
+                id: format!("{:0>64x}", id),
+                author: account.pubkey,
+                content: String::new(),
+                created_at,
+                tags: Tags::new(),
+                is_reply: false,
+                reply_to_id: None,
+                reply_to: None,
+                quoted: None,
+                is_deleted: false,
+                edited_at: None,
+                is_muted: false,
+                content_tokens: vec![],
+                mentions: vec![],
+                reactions: ReactionSummary::default(),
+                kind: 9,
+                media_attachments: vec![attachment],
+                delivery_status: Default::default(),
+            };
+            AggregatedMessage::insert_message(&message, &group_id, &whitenoise.database)
+                .await
+                .unwrap();
+        }
+
+        let media = whitenoise.group_media(&account, &group_id).await.unwrap();
+        assert_eq!(media.len(), 2);
+
+        let video_info = &media[0];
+        assert_eq!(video_info.message_id, Some(format!("{:0>64x}", 2)));
+        assert_eq!(video_info.mime_type, "video/mp4");
+        assert_eq!(video_info.original_file_hash, Some(vec![2u8; 32]));
+        assert_eq!(video_info.original_filename.as_deref(), Some("clip.mp4"));
+        assert!(video_info.dimensions.is_none());
+        assert!(!video_info.is_downloaded());
+        assert!(video_info.size_bytes.is_none());
+
+        let image_info = &media[1];
+        assert_eq!(image_info.message_id, Some(format!("{:0>64x}", 1)));
+        assert_eq!(image_info.mime_type, "image/png");
+        assert_eq!(image_info.original_file_hash, Some(vec![1u8; 32]));
+        assert_eq!(image_info.dimensions.as_deref(), Some("640x480"));
+        assert_eq!(image_info.blurhash.as_deref(), Some("LEHV6nWB2yk8"));
+        assert!(image_info.is_downloaded());
+        assert_eq!(image_info.size_bytes, Some(image_data.len() as u64));
+    }
+
+    #[tokio::test]
+    async fn test_download_chat_media_verifies_cached_file() {
+        let (mut whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        whitenoise.config.blossom_servers = vec![Url::parse("http://127.0.0.1:1").unwrap()];
+        let (account, _keys) = create_test_account(&whitenoise).await;
+        let account = account.save(&whitenoise.database).await.unwrap();
+        let group_id = GroupId::from_slice(&[8; 32]);
+        let metadata = FileMetadata::new().with_filename("photo.png".to_string());
+
+        // An intact cached file is returned without downloading
+        let data = b"decrypted image bytes";
+        let original_hash: [u8; 32] = Sha256::digest(data).into();
+        whitenoise
+            .media_files()
+            .store_and_record(
+                &account.pubkey,
+                &group_id,
+                "intact.png",
+                MediaFileUpload {
+                    data,
+                    original_file_hash: Some(&original_hash),
+                    encrypted_file_hash: [31u8; 32],
+                    mime_type: "image/png",
+                    media_type: "chat_media",
+                    blossom_url: Some("http://127.0.0.1:1/intact"),
+                    nostr_key: None,
+                    file_metadata: Some(&metadata),
+                },
+            )
+            .await
+            .unwrap();
+        let cached = whitenoise
+            .download_chat_media(&account, &group_id, &original_hash)
+            .await
+            .unwrap();
+        assert_eq!(tokio::fs::read(&cached.file_path).await.unwrap(), data);
+
+        // A corrupted one is a cache miss, so the (unreachable) server is tried again
+        let corrupted_hash = [32u8; 32];
+        whitenoise
+            .media_files()
+            .store_and_record(
+                &account.pubkey,
+                &group_id,
+                "corrupted.png",
+                MediaFileUpload {
+                    data: b"truncated",
+                    original_file_hash: Some(&corrupted_hash),
+                    encrypted_file_hash: [33u8; 32],
+                    mime_type: "image/png",
+                    media_type: "chat_media",
+                    blossom_url: Some("http://127.0.0.1:1/corrupted"),
+                    nostr_key: None,
+                    file_metadata: Some(&metadata),
+                },
+            )
+            .await
+            .unwrap();
+        let result = whitenoise
+            .download_chat_media(&account, &group_id, &corrupted_hash)
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::BlossomDownload(_))));
+    }
+
+    #[tokio::test]
+    async fn test_blossom_server_ordering() {
+        let (mut whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        assert_eq!(
+            whitenoise.config.blossom_servers,
+            vec![Whitenoise::default_blossom_url()]
+        );
+
+        let primary = Url::parse("https://blossom-a.example.com").unwrap();
+        let secondary = Url::parse("https://blossom-b.example.com").unwrap();
+        let stored = Url::parse("https://blossom-c.example.com").unwrap();
+        whitenoise.config.blossom_servers = vec![primary.clone(), secondary.clone()];
+
+        // Explicit server first, configured servers after, without duplicates
+        assert_eq!(
+            whitenoise.blossom_upload_servers(Some(secondary.clone())),
+            vec![secondary.clone(), primary.clone()]
+        );
+
+        // Downloads prefer the configured server that last succeeded
+        whitenoise.record_successful_blossom_server(&secondary);
+        assert_eq!(
+            whitenoise.blossom_download_servers(Some(stored.clone())),
+            vec![stored.clone(), secondary.clone(), primary.clone()]
+        );
+
+        // Servers outside the configured list are never remembered
+        whitenoise.record_successful_blossom_server(&stored);
+        assert_eq!(
+            whitenoise.blossom_download_servers(None),
+            vec![secondary, primary]
+        );
+
+        // An empty configuration falls back to the default server
+        whitenoise.config.blossom_servers.clear();
+        assert_eq!(
+            whitenoise.blossom_upload_servers(None),
+            vec![Whitenoise::default_blossom_url()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_blob_tries_every_server() {
+        let servers = vec![
+            Url::parse("http://127.0.0.1:1").unwrap(),
+            Url::parse("http://127.0.0.1:2").unwrap(),
+        ];
+
+        let result =
+            Whitenoise::download_blob_from_blossom_servers(&servers, &[0u8; 32], &mut |_, _| {})
+                .await;
+        assert!(matches!(result, Err(WhitenoiseError::BlossomDownload(_))));
+
+        let result =
+            Whitenoise::download_blob_from_blossom_servers(&[], &[0u8; 32], &mut |_, _| {}).await;
+        assert!(matches!(result, Err(WhitenoiseError::BlossomDownload(_))));
+    }
+
+    #[tokio::test]
+    async fn test_download_blob_reports_progress() {
+        let blob = vec![7u8; 64 * 1024];
+        let hash: [u8; 32] = Sha256::digest(&blob).into();
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", format!("/{}", hex::encode(hash)).as_str())
+            .with_body(&blob)
+            .create_async()
+            .await;
+
+        let mut reports = Vec::new();
+        let (data, _) = Whitenoise::download_blob_from_blossom_servers(
+            &[Url::parse(&server.url()).unwrap()],
+            &hash,
+            &mut |received, total| reports.push((received, total)),
+        )
+        .await
+        .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(data, blob);
+        assert_eq!(reports.first(), Some(&(0, Some(blob.len() as u64))));
+        assert_eq!(
+            reports.last(),
+            Some(&(blob.len() as u64, Some(blob.len() as u64)))
+        );
+        assert!(reports.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
 }
@@ -0,0 +1,408 @@
+//! Exporting a group's message history
+//!
+//! [`Whitenoise::export_group_history`] writes the cached chat messages of a group, oldest
+//! first, as JSON or as a plain text transcript. Messages are read from the message cache a
+//! page at a time, so the MLS group state is never touched and long histories aren't loaded
+//! into memory at once. Media is exported by reference; its decrypted content is only
+//! downloaded and inlined into JSON exports when asked for with [`ExportOptions`].
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use base64::{Engine as _, engine::general_purpose};
+use chrono::{DateTime, Utc};
+use mdk_core::prelude::GroupId;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::whitenoise::{
+    Whitenoise,
+    accounts::Account,
+    aggregated_message::AggregatedMessage,
+    error::{Result, WhitenoiseError},
+    media_files::MediaFile,
+    message_aggregator::ChatMessage,
+    users::{User, profile_name},
+};
+
+/// How many messages are read from the cache at a time
+const EXPORT_PAGE_SIZE: usize = 500;
+
+/// The format of an exported message history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    /// A JSON document with the group and one entry per message
+    Json,
+    /// A human readable transcript, one message per paragraph
+    PlainText,
+}
+
+/// What to include in an exported message history
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportOptions {
+    /// Download, decrypt and embed attached media as base64 in JSON exports
+    ///
+    /// Media references (hash, type, URL and file name) are always exported. Plain text
+    /// transcripts only ever list media by reference.
+    pub inline_media: bool,
+}
+
+/// A media attachment of an exported message
+#[derive(Debug, Serialize)]
+struct ExportedMedia {
+    /// Hex-encoded SHA-256 of the decrypted file, if known
+    hash: Option<String>,
+    mime_type: String,
+    url: Option<String>,
+    filename: Option<String>,
+    /// Base64 of the decrypted file, when inlined
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<String>,
+}
+
+/// A message of an exported history
+#[derive(Debug, Serialize)]
+struct ExportedMessage {
+    id: String,
+    sender: String,
+    sender_name: Option<String>,
+    created_at: DateTime<Utc>,
+    content: String,
+    reply_to_id: Option<String>,
+    edited_at: Option<DateTime<Utc>>,
+    deleted: bool,
+    media: Vec<ExportedMedia>,
+}
+
+impl Whitenoise {
+    /// Exports the cached message history of a group with the default [`ExportOptions`].
+    ///
+    /// See [`Whitenoise::export_group_history_with_options`].
+    pub async fn export_group_history(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        format: ExportFormat,
+    ) -> Result<Vec<u8>> {
+        self.export_group_history_with_options(account, group_id, format, ExportOptions::default())
+            .await
+    }
+
+    /// Exports the cached message history of a group.
+    ///
+    /// Every chat message is exported, oldest first, with its sender's name, its time and its
+    /// content with mentions rendered as `@name`. Deleted messages are kept as entries without
+    /// content. This only reads the message cache; the group's MLS state is left untouched.
+    ///
+    /// # Arguments
+    /// * `account` - The account exporting the history; it must be a member of the group
+    /// * `group_id` - The group to export
+    /// * `format` - Whether to produce JSON or a plain text transcript
+    /// * `options` - Whether to inline media
+    ///
+    /// # Errors
+    /// Returns [`WhitenoiseError::GroupNotFound`] if the account isn't in the group.
+    pub async fn export_group_history_with_options(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        format: ExportFormat,
+        options: ExportOptions,
+    ) -> Result<Vec<u8>> {
+        let group = self.group(account, group_id).await?;
+        let exported_at = Utc::now();
+        let mut names: HashMap<PublicKey, Option<String>> = HashMap::new();
+        let mut out = Vec::new();
+
+        match format {
+            ExportFormat::Json => write!(
+                out,
+                "{{\"group_id\":{},\"group_name\":{},\"exported_at\":{},\"messages\":[",
+                serde_json::to_string(&hex::encode(group_id.as_slice()))?,
+                serde_json::to_string(&group.name)?,
+                serde_json::to_string(&exported_at)?,
+            )?,
+            ExportFormat::PlainText => writeln!(
+                out,
+                "{}\nExported {}\n",
+                group.name,
+                exported_at.format("%Y-%m-%d %H:%M:%S UTC")
+            )?,
+        }
+
+        let mut cursor: Option<(Timestamp, String)> = None;
+        let mut first = true;
+        loop {
+            let page = AggregatedMessage::find_messages_by_group_after(
+                group_id,
+                cursor.as_ref().map(|(at, id)| (*at, id.as_str())),
+                EXPORT_PAGE_SIZE,
+                &self.database,
+            )
+            .await?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            cursor = Some((last.created_at, last.id.clone()));
+            self.resolve_export_names(&page, &mut names).await?;
+
+            for message in &page {
+                let exported = self
+                    .export_message(account, group_id, message, &names, format, options)
+                    .await;
+                match format {
+                    ExportFormat::Json => {
+                        if !first {
+                            out.push(b',');
+                        }
+                        serde_json::to_writer(&mut out, &exported)?;
+                    }
+                    ExportFormat::PlainText => write_transcript_entry(&mut out, &exported)?,
+                }
+                first = false;
+            }
+        }
+
+        if format == ExportFormat::Json {
+            out.extend_from_slice(b"]}");
+        }
+        Ok(out)
+    }
+
+    /// Looks up the names of the senders and mentioned users of a page not seen before
+    async fn resolve_export_names(
+        &self,
+        page: &[ChatMessage],
+        names: &mut HashMap<PublicKey, Option<String>>,
+    ) -> Result<()> {
+        let mut unknown: Vec<PublicKey> = page
+            .iter()
+            .flat_map(|message| {
+                std::iter::once(message.author)
+                    .chain(message.mentions.iter().map(|mention| mention.pubkey))
+            })
+            .filter(|pubkey| !names.contains_key(pubkey))
+            .collect();
+        unknown.sort();
+        unknown.dedup();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+
+        for pubkey in &unknown {
+            names.insert(*pubkey, None);
+        }
+        for user in User::find_by_pubkeys(&unknown, &self.database).await? {
+            names.insert(user.pubkey, profile_name(&user.metadata).cloned());
+        }
+        Ok(())
+    }
+
+    async fn export_message(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        message: &ChatMessage,
+        names: &HashMap<PublicKey, Option<String>>,
+        format: ExportFormat,
+        options: ExportOptions,
+    ) -> ExportedMessage {
+        let inline_media = options.inline_media && format == ExportFormat::Json;
+        let mut media = Vec::with_capacity(message.media_attachments.len());
+        for attachment in &message.media_attachments {
+            let data = if inline_media && !message.is_deleted {
+                self.inline_media(account, group_id, attachment).await
+            } else {
+                None
+            };
+            media.push(ExportedMedia {
+                hash: attachment.original_file_hash.as_deref().map(hex::encode),
+                mime_type: attachment.mime_type.clone(),
+                url: attachment.blossom_url.clone(),
+                filename: attachment
+                    .file_metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.original_filename.clone()),
+                data,
+            });
+        }
+
+        ExportedMessage {
+            id: message.id.clone(),
+            sender: message.author.to_hex(),
+            sender_name: names.get(&message.author).cloned().flatten(),
+            created_at: timestamp_to_utc(message.created_at),
+            content: if message.is_deleted {
+                String::new()
+            } else {
+                render_content(message, names)
+            },
+            reply_to_id: message.reply_to_id.clone(),
+            edited_at: message.edited_at.map(timestamp_to_utc),
+            deleted: message.is_deleted,
+            media,
+        }
+    }
+
+    /// The base64 of a decrypted attachment, downloading it if it isn't cached yet
+    ///
+    /// Attachments that can't be fetched stay a reference rather than failing the export.
+    async fn inline_media(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        attachment: &MediaFile,
+    ) -> Option<String> {
+        let hash: [u8; 32] = attachment.original_file_hash.as_deref()?.try_into().ok()?;
+        let result = async {
+            let media_file = self.download_chat_media(account, group_id, &hash).await?;
+            Ok::<_, WhitenoiseError>(tokio::fs::read(&media_file.file_path).await?)
+        }
+        .await;
+
+        match result {
+            Ok(data) => Some(general_purpose::STANDARD.encode(data)),
+            Err(e) => {
+                tracing::warn!(
+                    target: "whitenoise::history_export",
+                    "Exporting media {} by reference only: {}",
+                    hex::encode(hash),
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+/// The message content with mentions replaced by `@name`, or a shortened npub for unknown users
+fn render_content(message: &ChatMessage, names: &HashMap<PublicKey, Option<String>>) -> String {
+    let mut rendered = String::with_capacity(message.content.len());
+    let mut offset = 0;
+    for mention in &message.mentions {
+        let Some(before) = message.content.get(offset..mention.start) else {
+            continue;
+        };
+        rendered.push_str(before);
+        match names.get(&mention.pubkey).cloned().flatten() {
+            Some(name) => rendered.push_str(&format!("@{}", name)),
+            None => {
+                let npub = mention
+                    .pubkey
+                    .to_bech32()
+                    .unwrap_or_else(|_| mention.pubkey.to_hex());
+                rendered.push_str(&format!("@{}…", &npub[..npub.len().min(12)]));
+            }
+        }
+        offset = mention.end;
+    }
+    rendered.push_str(message.content.get(offset..).unwrap_or_default());
+    rendered
+}
+
+fn write_transcript_entry(out: &mut Vec<u8>, message: &ExportedMessage) -> std::io::Result<()> {
+    let sender = message
+        .sender_name
+        .clone()
+        .unwrap_or_else(|| message.sender.clone());
+    write!(
+        out,
+        "[{}] {}",
+        message.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        sender
+    )?;
+    if message.deleted {
+        return writeln!(out, ": (deleted)\n");
+    }
+    if message.edited_at.is_some() {
+        write!(out, " (edited)")?;
+    }
+    writeln!(out, ": {}", message.content)?;
+    for media in &message.media {
+        writeln!(
+            out,
+            "    [{}: {}] {}",
+            media.mime_type,
+            media.filename.as_deref().unwrap_or("attachment"),
+            media.url.as_deref().unwrap_or("(no URL)")
+        )?;
+    }
+    writeln!(out)
+}
+
+fn timestamp_to_utc(timestamp: Timestamp) -> DateTime<Utc> {
+    DateTime::from_timestamp(timestamp.as_u64() as i64, 0).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::message_aggregator::mentions::extract_mentions;
+    use crate::whitenoise::test_utils::*;
+
+    #[tokio::test]
+    async fn test_export_group_history() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member = members[0].0.pubkey;
+        let group = whitenoise
+            .create_group(
+                &creator_account,
+                vec![member],
+                create_nostr_group_config_data(vec![creator_account.pubkey]),
+                None,
+            )
+            .await
+            .unwrap();
+        let group_id = &group.mls_group_id;
+
+        User {
+            id: None,
+            pubkey: member,
+            metadata: Metadata::new().name("Bob"),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+        .save(&whitenoise.database)
+        .await
+        .unwrap();
+
+        let id = |seed: u8| format!("{seed:064x}");
+        let greeting = format!("hi nostr:{}", member.to_bech32().unwrap());
+        let mut mentioning =
+            ChatMessage::test_message(&id(1), &greeting, 1000).with_author(creator_account.pubkey);
+        mentioning.mentions = extract_mentions(&greeting);
+        let messages = [
+            ChatMessage::test_message(&id(2), "second", 2000).with_author(creator_account.pubkey),
+            mentioning,
+            ChatMessage::test_message(&id(3), "third", 2000).with_author(member),
+        ];
+        for message in &messages {
+            AggregatedMessage::insert_message(message, group_id, &whitenoise.database)
+                .await
+                .unwrap();
+        }
+
+        let json = whitenoise
+            .export_group_history(&creator_account, group_id, ExportFormat::Json)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let exported = json["messages"].as_array().unwrap();
+        let contents: Vec<&str> = exported
+            .iter()
+            .map(|message| message["content"].as_str().unwrap())
+            .collect();
+        assert_eq!(contents, vec!["hi @Bob", "second", "third"]);
+        assert_eq!(exported[2]["sender_name"], "Bob");
+
+        let text = whitenoise
+            .export_group_history(&creator_account, group_id, ExportFormat::PlainText)
+            .await
+            .unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("] Bob: third"));
+        assert!(text.find("hi @Bob").unwrap() < text.find("second").unwrap());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::nostr_manager::PublishOutcome;
 use crate::whitenoise::Whitenoise;
 use crate::whitenoise::accounts::Account;
 use crate::whitenoise::error::{Result, WhitenoiseError};
@@ -34,11 +35,12 @@ impl Whitenoise {
         Ok(())
     }
 
+    /// Publishes a fresh key package for the account to `relays`, returning what each relay did
     pub(crate) async fn publish_key_package_to_relays(
         &self,
         account: &Account,
         relays: &[Relay],
-    ) -> Result<()> {
+    ) -> Result<PublishOutcome> {
         let (encoded_key_package, tags) = self.encoded_key_package(account, relays).await?;
         let relays_urls = Relay::urls(relays);
         let signer = self
@@ -50,19 +52,33 @@ impl Whitenoise {
             .await?;
 
         tracing::debug!(target: "whitenoise::publish_key_package_to_relays", "Published key package to relays: {:?}", result);
+        if !result.is_success() {
+            tracing::warn!(
+                target: "whitenoise::publish_key_package_to_relays",
+                "Key package for {} was not accepted by any relay",
+                account.pubkey
+            );
+        }
 
-        Ok(())
+        Ok(result)
     }
 
     /// Deletes the key package from the relays for the given account.
     ///
-    /// Returns `true` if a key package was found and deleted, `false` if no key package was found.
+    /// The key package is looked up on the account's key package relays. Returns `true` if a
+    /// key package was found and deleted, `false` if no key package was found.
     pub async fn delete_key_package_for_account(
         &self,
         account: &Account,
         event_id: &EventId,
         delete_mls_stored_keys: bool,
     ) -> Result<bool> {
+        let key_package_relays = account.key_package_relays(self).await?;
+        if key_package_relays.is_empty() {
+            return Err(WhitenoiseError::AccountMissingKeyPackageRelays);
+        }
+        let key_package_relays_urls = Relay::urls(&key_package_relays);
+
         let key_package_filter = Filter::new()
             .id(*event_id)
             .kind(Kind::MlsKeyPackage)
@@ -70,12 +86,16 @@ impl Whitenoise {
 
         let mut key_package_stream = self
             .nostr
-            .client
-            .stream_events(key_package_filter, Duration::from_secs(5))
+            .network
+            .stream_events_from(
+                &key_package_relays_urls,
+                key_package_filter,
+                self.nostr.fetch_timeouts.key_packages,
+            )
             .await?;
 
         let mut key_package_events = Vec::new();
-        while let Some(event) = key_package_stream.next().await {
+        while let Some(event) = key_package_stream.recv().await {
             key_package_events.push(event);
         }
         let signer = self
@@ -89,18 +109,11 @@ impl Whitenoise {
                 mdk.delete_key_package_from_storage(&key_package)?;
             }
 
-            let key_package_relays = account.key_package_relays(self).await?;
-            if key_package_relays.is_empty() {
-                return Err(WhitenoiseError::AccountMissingKeyPackageRelays);
-            }
-
-            let key_package_relays_urls = Relay::urls(&key_package_relays);
-
             let result = self
                 .nostr
                 .publish_event_deletion_with_signer(&event.id, &key_package_relays_urls, signer)
                 .await?;
-            return Ok(!result.success.is_empty());
+            return Ok(result.is_success());
         }
         Ok(false)
     }
@@ -143,12 +156,16 @@ impl Whitenoise {
 
         let mut key_package_stream = self
             .nostr
-            .client
-            .stream_events_from(relay_urls, key_package_filter, Duration::from_secs(10))
+            .network
+            .stream_events_from(
+                &relay_urls,
+                key_package_filter,
+                self.nostr.fetch_timeouts.key_packages,
+            )
             .await?;
 
         let mut key_package_events = Vec::new();
-        while let Some(event) = key_package_stream.next().await {
+        while let Some(event) = key_package_stream.recv().await {
             key_package_events.push(event);
         }
 
@@ -377,7 +394,7 @@ impl Whitenoise {
             .await
         {
             Ok(result) => {
-                if result.success.is_empty() {
+                if !result.is_success() {
                     tracing::error!(
                         target: "whitenoise::key_packages",
                         "{}Batch deletion event was not accepted by any relay",
@@ -388,7 +405,7 @@ impl Whitenoise {
                         target: "whitenoise::key_packages",
                         "{}Published batch deletion event to {} relay(s) for {} key packages",
                         context,
-                        result.success.len(),
+                        result.accepted().count(),
                         event_ids.len()
                     );
                 }
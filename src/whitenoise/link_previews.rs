@@ -0,0 +1,541 @@
+//! Previews of web links shared in messages
+//!
+//! Links in message content show up as [`SerializableToken::Url`] tokens. The UI asks for a
+//! preview of each lazily with [`Whitenoise::fetch_link_preview`], which reads the page's
+//! OpenGraph and Twitter card meta tags. Only the start of the page is downloaded, since the
+//! tags live in its `<head>`, and the most recent results are cached per URL for the lifetime
+//! of the instance.
+//!
+//! Fetching a preview tells the website that someone opened the chat, so previews can be
+//! turned off with [`WhitenoiseConfig::link_previews_enabled`](super::WhitenoiseConfig). Links
+//! come from other members, so pages on loopback, private and link-local addresses are never
+//! fetched, including through redirects.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use nostr_sdk::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::nostr_manager::parser::SerializableToken;
+use crate::whitenoise::{
+    Whitenoise,
+    error::{Result, WhitenoiseError},
+    message_aggregator::ChatMessage,
+};
+
+/// How long downloading a page for a link preview may take, redirects included
+const LINK_PREVIEW_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How much of a page is downloaded to look for meta tags
+const MAX_LINK_PREVIEW_BYTES: usize = 512 * 1024;
+
+/// How many redirects are followed to reach a page
+const MAX_LINK_PREVIEW_REDIRECTS: usize = 5;
+
+/// How many previews are cached; the oldest is dropped to make room for a new one
+pub(crate) const MAX_CACHED_LINK_PREVIEWS: usize = 1000;
+
+/// A cached preview and when it was cached
+#[derive(Debug, Clone)]
+pub(crate) struct CachedLinkPreview {
+    preview: LinkPreview,
+    cached_at: Instant,
+}
+
+/// Title, description and image of a web page, taken from its meta tags
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkPreview {
+    /// The URL the preview was requested for
+    pub url: String,
+    /// `og:title`, `twitter:title` or the page's `<title>`
+    pub title: Option<String>,
+    /// `og:description`, `twitter:description` or the `description` meta tag
+    pub description: Option<String>,
+    /// Absolute URL of `og:image` or `twitter:image`
+    pub image_url: Option<String>,
+}
+
+impl ChatMessage {
+    /// Web links in the message content, in order of appearance
+    pub fn urls(&self) -> Vec<&str> {
+        self.content_tokens
+            .iter()
+            .filter_map(|token| match token {
+                SerializableToken::Url(url) => Some(url.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Whitenoise {
+    /// Fetches the title, description and image of a web page for a link preview.
+    ///
+    /// Results are cached by URL, so asking again for a link that was already previewed
+    /// makes no request.
+    ///
+    /// # Errors
+    /// - [`WhitenoiseError::LinkPreviewsDisabled`] if previews are turned off in the config
+    /// - [`WhitenoiseError::InvalidInput`] if `url` isn't an http(s) URL
+    /// - [`WhitenoiseError::LinkPreview`] if the page can't be fetched in time, or is on a
+    ///   loopback, private or link-local address
+    pub async fn fetch_link_preview(&self, url: &str) -> Result<LinkPreview> {
+        if !self.config.link_previews_enabled {
+            return Err(WhitenoiseError::LinkPreviewsDisabled);
+        }
+
+        let parsed = Url::parse(url)
+            .map_err(|e| WhitenoiseError::InvalidInput(format!("Invalid URL {}: {}", url, e)))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(WhitenoiseError::InvalidInput(format!(
+                "Cannot preview {} links",
+                parsed.scheme()
+            )));
+        }
+
+        if let Some(cached) = self.link_preview_cache.get(url) {
+            return Ok(cached.preview.clone());
+        }
+
+        let allow_private_hosts = self.config.link_previews_allow_private_hosts;
+        let html = tokio::time::timeout(
+            LINK_PREVIEW_TIMEOUT,
+            Self::download_page_head(&parsed, allow_private_hosts),
+        )
+        .await
+        .map_err(|_| {
+            WhitenoiseError::LinkPreview(format!(
+                "Request timed out after {} seconds",
+                LINK_PREVIEW_TIMEOUT.as_secs()
+            ))
+        })??;
+        let mut preview = parse_link_preview(&parsed, &html);
+        preview.url = url.to_string();
+
+        self.cache_link_preview(url, preview.clone());
+        Ok(preview)
+    }
+
+    /// Cache a preview, dropping the oldest one when [`MAX_CACHED_LINK_PREVIEWS`] are cached
+    fn cache_link_preview(&self, url: &str, preview: LinkPreview) {
+        if self.link_preview_cache.len() >= MAX_CACHED_LINK_PREVIEWS
+            && let Some(oldest) = self
+                .link_preview_cache
+                .iter()
+                .min_by_key(|entry| entry.cached_at)
+                .map(|entry| entry.key().clone())
+        {
+            self.link_preview_cache.remove(&oldest);
+        }
+        self.link_preview_cache.insert(
+            url.to_string(),
+            CachedLinkPreview {
+                preview,
+                cached_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Downloads up to [`MAX_LINK_PREVIEW_BYTES`] of a page, following redirects
+    ///
+    /// Every hop is checked with [`link_preview_client`] before connecting.
+    async fn download_page_head(url: &Url, allow_private_hosts: bool) -> Result<String> {
+        let failed =
+            |e: reqwest::Error| WhitenoiseError::LinkPreview(format!("Request failed: {}", e));
+
+        let mut url = url.clone();
+        let mut redirects = 0;
+        let mut response = loop {
+            let client = link_preview_client(&url, allow_private_hosts).await?;
+            let response = client.get(url.clone()).send().await.map_err(failed)?;
+            if !response.status().is_redirection() {
+                break response.error_for_status().map_err(failed)?;
+            }
+
+            redirects += 1;
+            if redirects > MAX_LINK_PREVIEW_REDIRECTS {
+                return Err(WhitenoiseError::LinkPreview(
+                    "Too many redirects".to_string(),
+                ));
+            }
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .ok_or_else(|| {
+                    WhitenoiseError::LinkPreview("Redirect without a location".to_string())
+                })?;
+            url = url.join(location).map_err(|e| {
+                WhitenoiseError::LinkPreview(format!("Invalid redirect {}: {}", location, e))
+            })?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(WhitenoiseError::LinkPreview(format!(
+                    "Redirect to a {} link",
+                    url.scheme()
+                )));
+            }
+        };
+
+        let mut data = Vec::new();
+        while data.len() < MAX_LINK_PREVIEW_BYTES
+            && let Some(chunk) = response.chunk().await.map_err(failed)?
+        {
+            data.extend_from_slice(&chunk);
+        }
+        data.truncate(MAX_LINK_PREVIEW_BYTES);
+
+        Ok(String::from_utf8_lossy(&data).into_owned())
+    }
+}
+
+/// An HTTP client that only connects to `url`'s host at a public address
+///
+/// The host name is resolved once and the client is pinned to the checked address, so the
+/// name can't resolve to another address for the actual request. Redirects aren't followed
+/// by the client, since each hop has to be checked the same way.
+async fn link_preview_client(url: &Url, allow_private_hosts: bool) -> Result<reqwest::Client> {
+    let not_public = |host: &str| {
+        WhitenoiseError::LinkPreview(format!("Not previewing {}: not a public address", host))
+    };
+    let mut builder = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
+
+    if !allow_private_hosts {
+        let host = url
+            .host_str()
+            .ok_or_else(|| WhitenoiseError::LinkPreview(format!("{} has no host", url)))?;
+
+        // IPv6 literals are bracketed in URLs
+        if let Ok(ip) = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+        {
+            if !is_public_ip(ip) {
+                return Err(not_public(host));
+            }
+        } else {
+            let port = url.port_or_known_default().unwrap_or(80);
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+                .await
+                .map_err(|e| {
+                    WhitenoiseError::LinkPreview(format!("Cannot resolve {}: {}", host, e))
+                })?
+                .collect();
+            let Some(address) = addresses.first() else {
+                return Err(WhitenoiseError::LinkPreview(format!(
+                    "Cannot resolve {}",
+                    host
+                )));
+            };
+            if addresses.iter().any(|address| !is_public_ip(address.ip())) {
+                return Err(not_public(host));
+            }
+            builder = builder.resolve(host, *address);
+        }
+    }
+
+    builder
+        .build()
+        .map_err(|e| WhitenoiseError::LinkPreview(format!("Request failed: {}", e)))
+}
+
+/// Whether an address is reachable on the public internet, i.e. not loopback, private,
+/// link-local, shared (carrier-grade NAT), multicast, unspecified or reserved
+///
+/// IPv6 addresses that embed an IPv4 address (mapped, NAT64 and 6to4) are judged by it.
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            let shared = first == 100 && (second & 0xc0) == 64;
+            // 0.0.0.0/8 is "this network" and 240.0.0.0/4, which includes broadcast, is reserved
+            let reserved = first == 0 || first >= 240;
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || shared
+                || reserved)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let segments = ip.segments();
+            let embedded =
+                |high: u16, low: u16| Ipv4Addr::from((u32::from(high) << 16) | u32::from(low));
+            // NAT64 (64:ff9b::/96) and 6to4 (2002::/16) reach the embedded IPv4 address
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                return is_public_ip(IpAddr::V4(embedded(segments[6], segments[7])));
+            }
+            if segments[0] == 0x2002 {
+                return is_public_ip(IpAddr::V4(embedded(segments[1], segments[2])));
+            }
+            // Deprecated IPv4-compatible addresses (::a.b.c.d), loopback and unspecified
+            let ipv4_compatible = segments[..6] == [0; 6];
+            let unique_local = (segments[0] & 0xfe00) == 0xfc00;
+            let link_local = (segments[0] & 0xffc0) == 0xfe80;
+            !(ipv4_compatible || ip.is_multicast() || unique_local || link_local)
+        }
+    }
+}
+
+/// Extracts a preview from the meta tags of a page fetched from `base`
+fn parse_link_preview(base: &Url, html: &str) -> LinkPreview {
+    // ASCII lowercasing keeps byte offsets, so positions found in `lower` index into `html`
+    let lower = html.to_ascii_lowercase();
+    let mut meta: Vec<(String, String)> = Vec::new();
+
+    let mut offset = 0;
+    while let Some(start) = lower[offset..].find("<meta") {
+        let start = offset + start;
+        let Some(end) = lower[start..].find('>') else {
+            break;
+        };
+        let end = start + end;
+        let attributes = parse_attributes(&html[start + "<meta".len()..end]);
+        let key = attributes
+            .iter()
+            .find(|(name, _)| name == "property" || name == "name")
+            .map(|(_, value)| value.to_ascii_lowercase());
+        let content = attributes
+            .iter()
+            .find(|(name, _)| name == "content")
+            .map(|(_, value)| value.clone());
+        if let (Some(key), Some(content)) = (key, content) {
+            meta.push((key, content));
+        }
+        offset = end;
+    }
+
+    let find = |keys: &[&str]| {
+        keys.iter().find_map(|key| {
+            meta.iter()
+                .find(|(name, content)| name == key && !content.trim().is_empty())
+                .map(|(_, content)| decode_entities(content.trim()))
+        })
+    };
+
+    LinkPreview {
+        url: base.to_string(),
+        title: find(&["og:title", "twitter:title"]).or_else(|| page_title(html, &lower)),
+        description: find(&["og:description", "twitter:description", "description"]),
+        image_url: find(&["og:image", "og:image:url", "twitter:image"])
+            .and_then(|image| base.join(&image).ok())
+            .filter(|image| matches!(image.scheme(), "http" | "https"))
+            .map(|image| image.to_string()),
+    }
+}
+
+/// The text of the page's `<title>` element
+fn page_title(html: &str, lower: &str) -> Option<String> {
+    let start = lower.find("<title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = decode_entities(html[start..end].trim());
+    (!title.is_empty()).then_some(title)
+}
+
+/// Name/value pairs of the attributes in the inside of a tag, names lowercased
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = tag.trim_start();
+
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c == '=' || c == '/' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+            let (raw, remaining) = match after_eq.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let inner = &after_eq[1..];
+                    let close = inner.find(quote).unwrap_or(inner.len());
+                    (&inner[..close], inner.get(close + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let close = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                    (&after_eq[..close], &after_eq[close..])
+                }
+            };
+            value = raw.to_string();
+            rest = remaining;
+        } else if name.is_empty() {
+            // Skip a stray `/` of a self-closing tag
+            rest = &rest[1.min(rest.len())..];
+        }
+
+        if !name.is_empty() {
+            attributes.push((name, value));
+        }
+        rest = rest.trim_start();
+    }
+
+    attributes
+}
+
+/// Decodes the HTML entities common in titles and descriptions
+fn decode_entities(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::*;
+
+    #[test]
+    fn test_parse_link_preview_prefers_open_graph_tags() {
+        let base = Url::parse("https://example.com/articles/1").unwrap();
+        let html = r#"<html><head>
+            <title>Fallback title</title>
+            <META property="og:title" content="Tom &amp; Jerry" />
+            <meta name='twitter:title' content='Twitter title'>
+            <meta name="description" content="A classic">
+            <meta property="og:image" content="/images/cover.png">
+        </head><body></body></html>"#;
+
+        let preview = parse_link_preview(&base, html);
+        assert_eq!(preview.title.as_deref(), Some("Tom & Jerry"));
+        assert_eq!(preview.description.as_deref(), Some("A classic"));
+        assert_eq!(
+            preview.image_url.as_deref(),
+            Some("https://example.com/images/cover.png")
+        );
+
+        // Pages without meta tags still get their title
+        let preview = parse_link_preview(&base, "<title> Plain page </title>");
+        assert_eq!(preview.title.as_deref(), Some("Plain page"));
+        assert_eq!(preview.description, None);
+        assert_eq!(preview.image_url, None);
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:192.168.1.1",
+            "0.1.2.3",
+            "240.0.0.1",
+            "255.255.255.255",
+            "224.0.0.1",
+            "239.255.255.250",
+            "ff02::1",
+            "ff0e::1",
+            "::1.1.1.1",
+            "::127.0.0.1",
+            "64:ff9b::7f00:1",
+            "64:ff9b::10.0.0.1",
+            "2002:c0a8:101::1",
+            "2002:7f00:1::",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{} is not public", ip);
+        }
+        for ip in [
+            "93.184.216.34",
+            "2606:4700::1111",
+            "::ffff:1.1.1.1",
+            "64:ff9b::1.1.1.1",
+            "2002:5db8:d822::1",
+        ] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{} is public", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_link_preview_skips_private_hosts() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/page")
+            .with_status(200)
+            .expect(0)
+            .create_async()
+            .await;
+
+        for url in [
+            format!("{}/page", server.url()),
+            format!("http://localhost:{}/page", server.socket_address().port()),
+            "http://[::1]/page".to_string(),
+        ] {
+            assert!(matches!(
+                whitenoise.fetch_link_preview(&url).await,
+                Err(WhitenoiseError::LinkPreview(_))
+            ));
+        }
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_link_preview_cache_is_capped() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        for i in 0..=MAX_CACHED_LINK_PREVIEWS {
+            whitenoise.cache_link_preview(
+                &format!("https://example.com/{}", i),
+                LinkPreview::default(),
+            );
+        }
+
+        assert_eq!(
+            whitenoise.link_preview_cache.len(),
+            MAX_CACHED_LINK_PREVIEWS
+        );
+        assert!(
+            !whitenoise
+                .link_preview_cache
+                .contains_key("https://example.com/0")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_link_preview_caches_and_respects_config() {
+        let (mut whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        whitenoise.config.link_previews_allow_private_hosts = true;
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/page")
+            .with_status(200)
+            .with_body(r#"<meta property="og:title" content="Hello">"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let url = format!("{}/page", server.url());
+
+        let preview = whitenoise.fetch_link_preview(&url).await.unwrap();
+        assert_eq!(preview.url, url);
+        assert_eq!(preview.title.as_deref(), Some("Hello"));
+
+        // The second lookup is answered from the cache
+        let cached = whitenoise.fetch_link_preview(&url).await.unwrap();
+        assert_eq!(cached, preview);
+        mock.assert_async().await;
+
+        whitenoise.config.link_previews_enabled = false;
+        assert!(matches!(
+            whitenoise.fetch_link_preview(&url).await,
+            Err(WhitenoiseError::LinkPreviewsDisabled)
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+//! Reading back the application's own logs
+//!
+//! Lets a frontend show recent log output on a diagnostics screen or attach it to a bug
+//! report, without the user having to find the log files on disk. Only today's log file is
+//! read; entries are the plain lines written by the file layer, which start with a timestamp
+//! followed by the level:
+//!
+//! ```text
+//! 2025-01-01T12:00:00.000000Z  WARN whitenoise::messages: Failed to publish message
+//! ```
+
+use std::collections::VecDeque;
+use std::path::Path;
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::current_log_file_path;
+use crate::whitenoise::{Whitenoise, error::Result};
+
+/// Maximum number of entries returned by [`Whitenoise::recent_errors`]
+pub const RECENT_ERRORS_LIMIT: usize = 200;
+
+impl Whitenoise {
+    /// Returns the last `lines` lines of today's log file, oldest first.
+    ///
+    /// Returns an empty list if nothing has been logged to a file today yet. Output still
+    /// buffered by the background log writer may not show up right away.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - Maximum number of lines to return
+    pub async fn tail_logs(&self, lines: usize) -> Result<Vec<String>> {
+        tail_log_file(&self.config.logs_dir, lines).await
+    }
+
+    /// Returns today's most recent `WARN` and `ERROR` log entries, oldest first.
+    ///
+    /// Entries spanning several lines (such as errors with multi-line messages) are returned
+    /// as one string. At most [`RECENT_ERRORS_LIMIT`] entries are returned, and an empty list
+    /// if nothing has been logged to a file today yet.
+    pub async fn recent_errors(&self) -> Result<Vec<String>> {
+        recent_log_errors(&self.config.logs_dir).await
+    }
+}
+
+async fn tail_log_file(logs_dir: &Path, lines: usize) -> Result<Vec<String>> {
+    let mut tail = VecDeque::new();
+    read_log_lines(logs_dir, |line| {
+        if tail.len() == lines {
+            tail.pop_front();
+        }
+        if lines > 0 {
+            tail.push_back(line);
+        }
+    })
+    .await?;
+    Ok(tail.into())
+}
+
+async fn recent_log_errors(logs_dir: &Path) -> Result<Vec<String>> {
+    let mut errors: VecDeque<String> = VecDeque::new();
+    let mut in_error = false;
+    read_log_lines(logs_dir, |line| match log_level(&line) {
+        Some(level) => {
+            in_error = matches!(level, "WARN" | "ERROR");
+            if in_error {
+                if errors.len() == RECENT_ERRORS_LIMIT {
+                    errors.pop_front();
+                }
+                errors.push_back(line);
+            }
+        }
+        // A continuation of the previous entry
+        None => {
+            if in_error && let Some(entry) = errors.back_mut() {
+                entry.push('\n');
+                entry.push_str(&line);
+            }
+        }
+    })
+    .await?;
+    Ok(errors.into())
+}
+
+/// Feed every line of today's log file to `f`, doing nothing if the file doesn't exist
+async fn read_log_lines(logs_dir: &Path, mut f: impl FnMut(String)) -> Result<()> {
+    let file = match tokio::fs::File::open(current_log_file_path(logs_dir)).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut lines = BufReader::new(file).lines();
+    while let Some(line) = lines.next_line().await? {
+        f(line);
+    }
+    Ok(())
+}
+
+/// The level of a log line that starts a new entry, or `None` for continuation lines
+fn log_level(line: &str) -> Option<&str> {
+    const LEVELS: [&str; 5] = ["TRACE", "DEBUG", "INFO", "WARN", "ERROR"];
+    let mut parts = line.split_whitespace();
+    let timestamp = parts.next()?;
+    let level = parts.next()?;
+    (timestamp.ends_with('Z') && LEVELS.contains(&level)).then_some(level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_log_level() {
+        assert_eq!(
+            log_level("2025-01-01T12:00:00.000000Z  WARN whitenoise::messages: oops"),
+            Some("WARN")
+        );
+        assert_eq!(
+            log_level("2025-01-01T12:00:00.000000Z ERROR whitenoise: boom"),
+            Some("ERROR")
+        );
+        assert_eq!(log_level("    at some continuation WARN"), None);
+        assert_eq!(log_level(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_tail_logs_and_recent_errors() {
+        let logs_dir = TempDir::new().unwrap();
+        let logs_dir = logs_dir.path();
+
+        // Nothing logged today yet
+        assert!(tail_log_file(logs_dir, 10).await.unwrap().is_empty());
+        assert!(recent_log_errors(logs_dir).await.unwrap().is_empty());
+
+        std::fs::write(
+            current_log_file_path(logs_dir),
+            "2025-01-01T12:00:00.000000Z  INFO whitenoise: starting\n\
+             2025-01-01T12:00:01.000000Z  WARN whitenoise::relays: relay slow\n\
+             2025-01-01T12:00:02.000000Z ERROR whitenoise::messages: failed:\n\
+             second line of the error\n\
+             2025-01-01T12:00:03.000000Z DEBUG whitenoise: done\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            tail_log_file(logs_dir, 2).await.unwrap(),
+            vec![
+                "second line of the error".to_string(),
+                "2025-01-01T12:00:03.000000Z DEBUG whitenoise: done".to_string(),
+            ]
+        );
+        assert!(tail_log_file(logs_dir, 0).await.unwrap().is_empty());
+        assert_eq!(tail_log_file(logs_dir, 100).await.unwrap().len(), 5);
+
+        let errors = recent_log_errors(logs_dir).await.unwrap();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("relay slow"));
+        assert!(errors[1].ends_with("failed:\nsecond line of the error"));
+    }
+}
@@ -3,9 +3,11 @@ use std::path::{Path, PathBuf};
 use mdk_core::{
     GroupId,
     encrypted_media::{manager::EncryptedMediaManager, types::MediaReference},
+    media_processing::MediaProcessingOptions,
     prelude::MdkStorageProvider,
 };
 use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
 
 pub use crate::whitenoise::database::media_files::MediaFile;
 use crate::whitenoise::{
@@ -112,6 +114,128 @@ pub(crate) struct MediaFileUpload<'a> {
     pub file_metadata: Option<&'a FileMetadata>,
 }
 
+/// A local media file to encrypt and upload to Blossom
+#[derive(Debug, Clone, Default)]
+pub struct MediaUpload {
+    /// Path of the file to upload
+    pub file_path: String,
+    /// Blossom server to try first; the file is mirrored to the configured servers as well
+    pub blossom_server_url: Option<Url>,
+    /// Media processing options (defaults to standard options if None)
+    pub options: Option<MediaProcessingOptions>,
+}
+
+/// A group's media file as shown in a media gallery
+///
+/// Combines the cached [`MediaFile`] record with the message that shared it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaFileInfo {
+    /// ID of the message the media was shared in, if it has been cached
+    pub message_id: Option<String>,
+    /// When the message carrying the media was sent
+    pub sent_at: Option<Timestamp>,
+    /// SHA-256 of the decrypted content (MIP-04 x field)
+    pub original_file_hash: Option<Vec<u8>>,
+    /// SHA-256 of the encrypted blob
+    pub encrypted_file_hash: Vec<u8>,
+    pub mime_type: String,
+    pub original_filename: Option<String>,
+    /// Dimensions as `WIDTHxHEIGHT`, images only
+    pub dimensions: Option<String>,
+    /// Blurhash preview, images only
+    pub blurhash: Option<String>,
+    pub blossom_url: Option<String>,
+    /// Local path of the decrypted file, if downloaded
+    pub file_path: Option<PathBuf>,
+    /// Size of the decrypted file in bytes, if downloaded
+    pub size_bytes: Option<u64>,
+}
+
+impl MediaFileInfo {
+    pub(crate) async fn from_media_file(
+        media_file: MediaFile,
+        message_id: Option<String>,
+        sent_at: Option<Timestamp>,
+    ) -> Self {
+        let is_image = media_file.is_image();
+        let metadata = media_file.file_metadata.unwrap_or_default();
+
+        let file_path = (!media_file.file_path.as_os_str().is_empty())
+            .then_some(media_file.file_path)
+            .filter(|path| path.exists());
+        let size_bytes = match &file_path {
+            Some(path) => tokio::fs::metadata(path).await.ok().map(|m| m.len()),
+            None => None,
+        };
+
+        Self {
+            message_id,
+            sent_at,
+            original_file_hash: media_file.original_file_hash,
+            encrypted_file_hash: media_file.encrypted_file_hash,
+            mime_type: media_file.mime_type,
+            original_filename: metadata.original_filename,
+            dimensions: metadata.dimensions.filter(|_| is_image),
+            blurhash: metadata.blurhash.filter(|_| is_image),
+            blossom_url: media_file.blossom_url,
+            file_path,
+            size_bytes,
+        }
+    }
+
+    /// Whether the decrypted file is available locally
+    pub fn is_downloaded(&self) -> bool {
+        self.file_path.is_some()
+    }
+}
+
+impl MediaFile {
+    /// Builds the MIP-04 `imeta` tag that references this uploaded chat media in a message
+    ///
+    /// Images also carry `dim WxH` and `blurhash` when they were generated at upload, so
+    /// recipients can lay out a placeholder before downloading. Other media types never
+    /// include them.
+    ///
+    /// # Errors
+    /// Returns `MediaCache` if the record lacks the Blossom URL, original file hash or
+    /// original filename that MIP-04 requires.
+    pub fn imeta_tag(&self) -> Result<Tag> {
+        let blossom_url = self
+            .blossom_url
+            .as_deref()
+            .ok_or_else(|| WhitenoiseError::MediaCache("No Blossom URL".to_string()))?;
+        let original_hash = self
+            .original_file_hash
+            .as_deref()
+            .ok_or_else(|| WhitenoiseError::MediaCache("Missing original file hash".to_string()))?;
+        let metadata = self.file_metadata.clone().unwrap_or_default();
+        let filename = metadata.original_filename.ok_or_else(|| {
+            WhitenoiseError::MediaCache("Missing required filename metadata".to_string())
+        })?;
+
+        let mut parts = vec![
+            "imeta".to_string(),
+            format!("url {}", blossom_url),
+            format!("m {}", self.mime_type),
+            format!("filename {}", filename),
+            format!("x {}", hex::encode(original_hash)),
+        ];
+        if self.is_image() {
+            if let Some(dimensions) = metadata.dimensions {
+                parts.push(format!("dim {}", dimensions));
+            }
+            if let Some(blurhash) = metadata.blurhash {
+                parts.push(format!("blurhash {}", blurhash));
+            }
+        }
+        parts.push("v mip04-v1".to_string());
+
+        Tag::parse(parts).map_err(|e| {
+            WhitenoiseError::Other(anyhow::anyhow!("Failed to create imeta tag: {}", e))
+        })
+    }
+}
+
 /// High-level media files orchestration layer
 ///
 /// This module provides convenience methods that coordinate between:
@@ -492,6 +616,54 @@ mod tests {
         assert!(found.to_string_lossy().contains("abc123"));
     }
 
+    fn chat_media_file(mime_type: &str, file_metadata: FileMetadata) -> MediaFile {
+        MediaFile {
+            id: None,
+            mls_group_id: GroupId::from_slice(&[1; 32]),
+            account_pubkey: Keys::generate().public_key(),
+            file_path: PathBuf::new(),
+            original_file_hash: Some(vec![0xab; 32]),
+            encrypted_file_hash: vec![0xcd; 32],
+            mime_type: mime_type.to_string(),
+            media_type: "chat_media".to_string(),
+            blossom_url: Some(format!("https://blossom.example.com/{}", "cd".repeat(32))),
+            nostr_key: None,
+            file_metadata: Some(file_metadata),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_imeta_tag_includes_image_preview_fields() {
+        let metadata = FileMetadata::new()
+            .with_filename("photo.png".to_string())
+            .with_dimensions("800x600".to_string())
+            .with_blurhash("LEHV6nWB2yk8pyo0adR*.7kCMdnj".to_string());
+
+        let tag = chat_media_file("image/png", metadata.clone())
+            .imeta_tag()
+            .unwrap()
+            .to_vec();
+        assert_eq!(tag[0], "imeta");
+        assert!(tag.contains(&format!("x {}", "ab".repeat(32))));
+        assert!(tag.contains(&"filename photo.png".to_string()));
+        assert!(tag.contains(&"dim 800x600".to_string()));
+        assert!(tag.contains(&"blurhash LEHV6nWB2yk8pyo0adR*.7kCMdnj".to_string()));
+        assert_eq!(tag.last().map(String::as_str), Some("v mip04-v1"));
+
+        // Non-image media never advertises image preview fields
+        let tag = chat_media_file("application/pdf", metadata)
+            .imeta_tag()
+            .unwrap()
+            .to_vec();
+        assert!(!tag.iter().any(|part| part.starts_with("dim ")));
+        assert!(!tag.iter().any(|part| part.starts_with("blurhash ")));
+
+        // MIP-04 needs the filename, so a record without one can't be referenced
+        let result = chat_media_file("image/png", FileMetadata::new()).imeta_tag();
+        assert!(matches!(result, Err(WhitenoiseError::MediaCache(_))));
+    }
+
     #[test]
     fn test_extract_hash_from_blossom_url_valid() {
         let url = "https://blossom.example.com/0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
@@ -0,0 +1,71 @@
+//! Group activity summaries
+//!
+//! Activity is counted with aggregate queries over the message cache (see
+//! `AggregatedMessage::summarize_activity`), so digests and badge counts don't need to touch
+//! MLS storage or load a group's history. Mentions are the one thing decided here, since they
+//! can be `nostr:` references that need decoding.
+
+use nostr_sdk::prelude::*;
+
+use super::types::ChatMessage;
+use crate::nostr_manager::parser::SerializableToken;
+
+/// Whether a message mentions `pubkey`, by `p`-tagging it or referencing it with a `nostr:`
+/// URI in its content
+pub(crate) fn mentions_pubkey(message: &ChatMessage, pubkey: &PublicKey) -> bool {
+    message.tags.public_keys().any(|pk| pk == pubkey)
+        || message.content_tokens.iter().any(|token| match token {
+            SerializableToken::Nostr(uri) => match Nip21::parse(uri) {
+                Ok(Nip21::Pubkey(pk)) => pk == *pubkey,
+                Ok(Nip21::Profile(profile)) => profile.public_key == *pubkey,
+                _ => false,
+            },
+            _ => false,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(author: PublicKey) -> ChatMessage {
+        ChatMessage::test_message(&EventId::all_zeros().to_hex(), "hello", 100).with_author(author)
+    }
+
+    #[test]
+    fn test_mentions_pubkey_by_tag_and_uri() {
+        let me = Keys::generate().public_key();
+        let alice = Keys::generate().public_key();
+
+        let mut tag_mention = message(alice);
+        tag_mention.tags = Tags::from_list(vec![Tag::public_key(me)]);
+        assert!(mentions_pubkey(&tag_mention, &me));
+
+        let mut npub_mention = message(alice);
+        npub_mention.content_tokens = vec![
+            SerializableToken::Text("hey ".to_string()),
+            SerializableToken::Nostr(me.to_nostr_uri().unwrap()),
+        ];
+        assert!(mentions_pubkey(&npub_mention, &me));
+
+        let mut nprofile_mention = message(alice);
+        let nprofile = Nip19Profile::new(me, [RelayUrl::parse("wss://relay.example.com").unwrap()]);
+        nprofile_mention.content_tokens =
+            vec![SerializableToken::Nostr(nprofile.to_nostr_uri().unwrap())];
+        assert!(mentions_pubkey(&nprofile_mention, &me));
+    }
+
+    #[test]
+    fn test_mentions_pubkey_ignores_other_mentions() {
+        let me = Keys::generate().public_key();
+        let alice = Keys::generate().public_key();
+
+        let mut other_mention = message(me);
+        other_mention.tags = Tags::from_list(vec![Tag::public_key(alice)]);
+        other_mention.content_tokens =
+            vec![SerializableToken::Nostr(alice.to_nostr_uri().unwrap())];
+
+        assert!(!mentions_pubkey(&other_mention, &me));
+        assert!(!mentions_pubkey(&message(alice), &me));
+    }
+}
@@ -0,0 +1,123 @@
+//! Edit-specific processing logic
+//!
+//! An edit is an MLS application message of [`MESSAGE_EDIT_KIND`] whose content replaces the
+//! content of the message referenced by its e-tag. Edits of an edit apply to the original
+//! message. Only edits by the original author are honored, and deleted messages can't be
+//! edited; edits are collapsed onto the original, newest edit wins.
+
+use nostr_sdk::prelude::*;
+use std::collections::HashMap;
+
+use super::mentions;
+use super::types::ChatMessage;
+use crate::nostr_manager::parser::SerializableToken;
+use mdk_core::prelude::message_types::Message;
+
+/// Event kind of the MLS application message that edits an earlier chat message
+pub const MESSAGE_EDIT_KIND: u16 = 1010;
+
+/// Whether the message is an edit
+pub(crate) fn is_edit(message: &Message) -> bool {
+    message.kind.as_u16() == MESSAGE_EDIT_KIND
+}
+
+/// Extract the ID of the edited message from the edit's first e-tag
+pub(crate) fn extract_edit_target_id(tags: &Tags) -> Option<String> {
+    tags.iter()
+        .find(|tag| tag.kind() == TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::E)))
+        .and_then(|tag| tag.content().map(|s| s.to_string()))
+}
+
+/// Follow edits of edits back to the message they ultimately edit
+///
+/// `edit_targets` maps edit IDs to the ID their e-tag references.
+pub(crate) fn resolve_edit_root(target_id: &str, edit_targets: &HashMap<String, String>) -> String {
+    let mut root = target_id;
+    // Event IDs commit to the tags, so chains can't loop; the bound only guards bad input
+    for _ in 0..edit_targets.len() {
+        match edit_targets.get(root) {
+            Some(next) => root = next,
+            None => break,
+        }
+    }
+    root.to_string()
+}
+
+/// Apply an edit to its original message
+///
+/// Returns whether the edit was applied. Edits by anyone but the author, edits of deleted
+/// messages and edits older than the one already applied are ignored.
+pub(crate) fn apply_edit_to_message(
+    message: &mut ChatMessage,
+    editor: &PublicKey,
+    content: &str,
+    content_tokens: Vec<SerializableToken>,
+    edited_at: Timestamp,
+) -> bool {
+    if *editor != message.author || message.is_deleted {
+        return false;
+    }
+    if message.edited_at.is_some_and(|current| edited_at < current) {
+        return false;
+    }
+
+    message.content = content.to_string();
+    message.content_tokens = content_tokens;
+    message.mentions = mentions::extract_mentions(content);
+    message.edited_at = Some(edited_at);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_edit_root() {
+        let edit_targets = HashMap::from([
+            ("edit2".to_string(), "edit1".to_string()),
+            ("edit1".to_string(), "original".to_string()),
+        ]);
+        assert_eq!(resolve_edit_root("edit2", &edit_targets), "original");
+        assert_eq!(resolve_edit_root("original", &edit_targets), "original");
+        assert_eq!(resolve_edit_root("unknown", &HashMap::new()), "unknown");
+    }
+
+    #[test]
+    fn test_apply_edit_to_message() {
+        let author = Keys::generate().public_key();
+        let mut message =
+            ChatMessage::test_message("original", "Original", 100).with_author(author);
+
+        assert!(apply_edit_to_message(
+            &mut message,
+            &author,
+            "Second",
+            vec![],
+            Timestamp::from(200)
+        ));
+        assert_eq!(message.content, "Second");
+        assert_eq!(message.edited_at, Some(Timestamp::from(200)));
+
+        // An older edit arriving late doesn't overwrite a newer one
+        assert!(!apply_edit_to_message(
+            &mut message,
+            &author,
+            "First",
+            vec![],
+            Timestamp::from(150)
+        ));
+        assert_eq!(message.content, "Second");
+
+        // Only the author can edit
+        let other = Keys::generate().public_key();
+        assert!(!apply_edit_to_message(
+            &mut message,
+            &other,
+            "Hijacked",
+            vec![],
+            Timestamp::from(300)
+        ));
+        assert_eq!(message.content, "Second");
+    }
+}
@@ -1,16 +1,28 @@
-use super::types::ProcessingError;
+use super::types::{AggregatorConfig, ProcessingError};
 
 /// Validates and normalizes reaction content
+///
+/// Reactions listed in [`AggregatorConfig::emoji_equivalents`] resolve to their canonical
+/// emoji, before validation so custom shortcodes are accepted too.
 pub fn validate_and_normalize_reaction(
     content: &str,
-    normalize_emoji: bool,
+    config: &AggregatorConfig,
 ) -> Result<String, ProcessingError> {
+    if let Some(canonical) = config.emoji_equivalents.get(content) {
+        return Ok(canonical.clone());
+    }
+
     match content {
         "+" => Ok("👍".to_string()), // Normalize to thumbs up
         "-" => Ok("👎".to_string()), // Normalize to thumbs down
         emoji if is_valid_emoji(emoji) => {
-            if normalize_emoji {
-                Ok(normalize_emoji_string(emoji))
+            if config.normalize_emoji {
+                let normalized = normalize_emoji_string(emoji);
+                Ok(config
+                    .emoji_equivalents
+                    .get(&normalized)
+                    .cloned()
+                    .unwrap_or(normalized))
             } else {
                 Ok(emoji.to_string())
             }
@@ -91,12 +103,15 @@ pub fn normalize_emoji_string(emoji: &str) -> String {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use super::*;
 
     #[test]
     fn test_validate_plus_minus() {
-        assert_eq!(validate_and_normalize_reaction("+", true).unwrap(), "👍");
-        assert_eq!(validate_and_normalize_reaction("-", true).unwrap(), "👎");
+        let config = AggregatorConfig::default();
+        assert_eq!(validate_and_normalize_reaction("+", &config).unwrap(), "👍");
+        assert_eq!(validate_and_normalize_reaction("-", &config).unwrap(), "👎");
     }
 
     #[test]
@@ -119,16 +134,58 @@ mod tests {
 
         // Should remove variation selector
         assert_eq!(normalize_emoji_string("❤️"), "❤");
+
+        // ZWJ sequences keep their joiners and lose only the skin tones
+        assert_eq!(normalize_emoji_string("👩🏽\u{200D}💻"), "👩\u{200D}💻");
+        assert_eq!(
+            normalize_emoji_string("👨🏻\u{200D}🤝\u{200D}👨🏿"),
+            "👨\u{200D}🤝\u{200D}👨"
+        );
+    }
+
+    #[test]
+    fn test_emoji_equivalents() {
+        let config = AggregatorConfig {
+            emoji_equivalents: HashMap::from([
+                (":custom:".to_string(), "🦀".to_string()),
+                ("👩\u{200D}💻".to_string(), "🧑\u{200D}💻".to_string()),
+            ]),
+            ..Default::default()
+        };
+
+        // Shortcodes resolve even though they aren't emoji
+        assert_eq!(
+            validate_and_normalize_reaction(":custom:", &config).unwrap(),
+            "🦀"
+        );
+        // Skin tone variants are normalized before the lookup, so they share the bucket
+        assert_eq!(
+            validate_and_normalize_reaction("👩🏽\u{200D}💻", &config).unwrap(),
+            "🧑\u{200D}💻"
+        );
+        assert_eq!(
+            validate_and_normalize_reaction("👍🏽", &config).unwrap(),
+            validate_and_normalize_reaction("👍", &config).unwrap()
+        );
+
+        // Without a map, shortcodes stay invalid and skin tones are still stripped
+        let default = AggregatorConfig::default();
+        assert!(validate_and_normalize_reaction(":custom:", &default).is_err());
+        assert_eq!(
+            validate_and_normalize_reaction("👩🏽\u{200D}💻", &default).unwrap(),
+            "👩\u{200D}💻"
+        );
     }
 
     #[test]
     fn test_invalid_reactions() {
-        assert!(validate_and_normalize_reaction("invalid", true).is_err());
-        assert!(validate_and_normalize_reaction("", true).is_err());
+        let config = AggregatorConfig::default();
+        assert!(validate_and_normalize_reaction("invalid", &config).is_err());
+        assert!(validate_and_normalize_reaction("", &config).is_err());
         assert!(
             validate_and_normalize_reaction(
                 "way too long reaction string that exceeds limits",
-                true
+                &config
             )
             .is_err()
         );
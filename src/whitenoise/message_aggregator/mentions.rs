@@ -0,0 +1,134 @@
+//! Mentions of users in message content
+//!
+//! Mentions are `nostr:npub…` and `nostr:nprofile…` URIs (NIP-21/NIP-27). They are found
+//! directly in the content so their byte offsets are exact, and names are filled in from the
+//! cached user metadata when messages are read.
+
+use std::collections::HashMap;
+
+use nostr_sdk::prelude::*;
+
+use super::types::{ChatMessage, Mention};
+
+const NOSTR_URI_PREFIX: &str = "nostr:";
+
+/// Find the mentions in message content, with their names left unresolved
+///
+/// URIs whose bech32 part doesn't decode, and URIs of other entities such as notes,
+/// are skipped.
+pub(crate) fn extract_mentions(content: &str) -> Vec<Mention> {
+    content
+        .match_indices(NOSTR_URI_PREFIX)
+        .filter_map(|(start, _)| {
+            let entity_start = start + NOSTR_URI_PREFIX.len();
+            let rest = &content[entity_start..];
+            let entity_len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            let entity = &rest[..entity_len];
+
+            let pubkey = if entity.starts_with("npub1") {
+                PublicKey::from_bech32(entity).ok()
+            } else if entity.starts_with("nprofile1") {
+                Nip19Profile::from_bech32(entity)
+                    .ok()
+                    .map(|profile| profile.public_key)
+            } else {
+                None
+            }?;
+
+            Some(Mention {
+                pubkey,
+                display_name: None,
+                start,
+                end: entity_start + entity_len,
+            })
+        })
+        .collect()
+}
+
+/// Set the display name of every mention whose user is in `names`
+///
+/// Mentions of users missing from `names` are reset to unresolved.
+pub(crate) fn resolve_mentions(messages: &mut [ChatMessage], names: &HashMap<PublicKey, String>) {
+    for mention in messages
+        .iter_mut()
+        .flat_map(|message| message.mentions.iter_mut())
+    {
+        mention.display_name = names.get(&mention.pubkey).cloned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_multiple_mentions() {
+        let alice = Keys::generate().public_key();
+        let bob = Keys::generate().public_key();
+        let npub = alice.to_bech32().unwrap();
+        let nprofile =
+            Nip19Profile::new(bob, [RelayUrl::parse("wss://relay.example.com").unwrap()])
+                .to_bech32()
+                .unwrap();
+        let content = format!("hi nostr:{}, meet nostr:{}!", npub, nprofile);
+
+        let mentions = extract_mentions(&content);
+        assert_eq!(mentions.len(), 2);
+        assert_eq!(mentions[0].pubkey, alice);
+        assert_eq!(
+            &content[mentions[0].start..mentions[0].end],
+            format!("nostr:{}", npub)
+        );
+        assert_eq!(mentions[1].pubkey, bob);
+        assert_eq!(
+            &content[mentions[1].start..mentions[1].end],
+            format!("nostr:{}", nprofile)
+        );
+        assert!(mentions.iter().all(|m| m.display_name.is_none()));
+    }
+
+    #[test]
+    fn test_resolve_mentions_uses_known_names() {
+        let alice = Keys::generate().public_key();
+        let stranger = Keys::generate().public_key();
+        let content = format!(
+            "nostr:{} and nostr:{}",
+            alice.to_bech32().unwrap(),
+            stranger.to_bech32().unwrap()
+        );
+        let mut message = ChatMessage::test_message("message", &content, 100).with_author(alice);
+        message.mentions = extract_mentions(&content);
+        let mut messages = vec![message];
+
+        let names = HashMap::from([(alice, "Alice".to_string())]);
+        resolve_mentions(&mut messages, &names);
+
+        let mentions = &messages[0].mentions;
+        assert_eq!(mentions[0].display_name.as_deref(), Some("Alice"));
+        // Unknown users keep their pubkey so the UI can fall back to it
+        assert_eq!(mentions[1].pubkey, stranger);
+        assert_eq!(mentions[1].display_name, None);
+    }
+
+    #[test]
+    fn test_extract_mentions_skips_malformed_bech32() {
+        let npub = Keys::generate().public_key().to_bech32().unwrap();
+        // Flip the last character so the checksum no longer matches
+        let mut corrupted = npub.clone();
+        let last = corrupted.pop().unwrap();
+        corrupted.push(if last == 'q' { 'p' } else { 'q' });
+
+        let content = format!(
+            "nostr:{} nostr:npub1notbech32 nostr:note1abc nostr: nostr:{}",
+            corrupted, npub
+        );
+        let mentions = extract_mentions(&content);
+        assert_eq!(mentions.len(), 1);
+        assert_eq!(
+            &content[mentions[0].start..mentions[0].end],
+            format!("nostr:{}", npub)
+        );
+    }
+}
@@ -2,35 +2,50 @@
 //!
 //! This module provides functionality to aggregate raw Nostr MLS messages into structured
 //! ChatMessage objects suitable for frontend display. It handles message types including
-//! regular chat messages, reactions, deletions, and replies.
+//! regular chat messages, reactions, deletions, edits, and replies.
 
+pub(crate) mod activity;
+pub(crate) mod edit_handler;
 pub(crate) mod emoji_utils;
-mod processor;
+pub(crate) mod mentions;
+pub(crate) mod processor;
 pub(crate) mod reaction_handler;
+pub(crate) mod search;
+mod state;
+pub(crate) mod threads;
 mod types;
-// mod state;  // Future: For Phase 2 stateful implementation
 
 #[cfg(test)]
 mod tests;
 
+pub use edit_handler::MESSAGE_EDIT_KIND;
+pub use state::StateError;
 pub use types::{
-    AggregatorConfig, ChatMessage, EmojiReaction, GroupStatistics, ProcessingError,
-    ReactionSummary, UserReaction,
+    AggregatorConfig, ChatMessage, ChatMessageRef, DeliveryStatus, EmojiReaction, GroupActivity,
+    GroupStatistics, Mention, MessageEdit, MessageSearchResult, MutedAuthors, ProcessingError,
+    ReactionAction, ReactionSummary, ThreadNode, UserReaction,
 };
 
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
 use mdk_core::prelude::message_types::Message;
 use mdk_core::prelude::*;
 use nostr_sdk::PublicKey;
+use tokio::sync::RwLock;
 
 use crate::nostr_manager::parser::Parser;
 use crate::whitenoise::media_files::MediaFile;
+use state::GroupState;
 
 /// Main message aggregator - designed to be a singleton per Whitenoise instance
 /// Group-aware to ensure proper isolation between different group conversations
 pub struct MessageAggregator {
     config: AggregatorConfig,
-    // Future: state management for stateful mode, keyed by GroupId
-    // state: Arc<tokio::sync::RwLock<HashMap<GroupId, AggregatorState>>>,
+    /// Group state loaded from or persisted to disk, keyed by GroupId
+    state: RwLock<HashMap<GroupId, GroupState>>,
+    /// Directory holding persisted group state, set once the data dir is known
+    state_dir: Option<PathBuf>,
 }
 
 impl MessageAggregator {
@@ -45,10 +60,24 @@ impl MessageAggregator {
     pub fn with_config(config: AggregatorConfig) -> Self {
         Self {
             config,
-            // state: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+            state: RwLock::new(HashMap::new()),
+            state_dir: None,
         }
     }
 
+    /// Persist group state under `message_cache` in the given data directory
+    pub(crate) fn with_data_dir(mut self, data_dir: &Path) -> Self {
+        self.state_dir = Some(data_dir.join(state::STATE_DIR_NAME));
+        self
+    }
+
+    /// Directory to persist state to, if persistence is enabled
+    fn persistence_dir(&self) -> Option<&Path> {
+        self.state_dir
+            .as_deref()
+            .filter(|_| self.config.persist_state)
+    }
+
     /// Fetch and aggregate messages for a specific group
     /// This is the main entry point that handles the complete pipeline:
     /// 1. Fetch raw messages from mdk
@@ -56,6 +85,12 @@ impl MessageAggregator {
     /// 3. Aggregate reactions, replies, and deletions
     /// 4. Return structured ChatMessage objects
     ///
+    /// # Ordering
+    /// Messages are returned oldest first by `created_at`. Messages created in the same second
+    /// are ordered by event ID, compared as lowercase hex strings, so aggregating the same
+    /// messages always gives the same order regardless of the order they were passed in.
+    /// The message cache returns messages in this order too.
+    ///
     /// # Arguments
     /// * `pubkey` - The public key of the user requesting messages (for account access)
     /// * `group_id` - The group to fetch and aggregate messages for
@@ -112,6 +147,57 @@ impl MessageAggregator {
         processor::process_regular_message(message, parser, &media_files_map).await
     }
 
+    /// Hide or mark messages from muted authors, as configured by [`AggregatorConfig::muted_authors`]
+    ///
+    /// Reactions from muted authors are removed from every message's reaction summary.
+    ///
+    /// # Arguments
+    /// * `messages` - Aggregated messages, e.g. from the message cache
+    /// * `muted` - Public keys the requesting account has muted
+    pub fn apply_muted_authors(
+        &self,
+        messages: Vec<ChatMessage>,
+        muted: &HashSet<PublicKey>,
+    ) -> Vec<ChatMessage> {
+        processor::apply_muted_authors(messages, muted, self.config.muted_authors)
+    }
+
+    /// Shorten reactor lists to [`AggregatorConfig::max_reactors_per_emoji`], if set
+    ///
+    /// # Arguments
+    /// * `messages` - Aggregated messages about to be returned
+    /// * `viewer` - The requesting account, whose own reactions are always listed
+    pub fn cap_reactors(&self, messages: &mut [ChatMessage], viewer: &PublicKey) {
+        if let Some(max) = self.config.max_reactors_per_emoji {
+            processor::cap_reactors(messages, max, viewer);
+        }
+    }
+
+    /// Fill in the display names of the users mentioned in messages
+    ///
+    /// # Arguments
+    /// * `messages` - Aggregated messages whose mentions to resolve
+    /// * `names` - Display names from the cached metadata of the mentioned users; mentions
+    ///   of users without an entry stay unresolved
+    pub fn resolve_mentions(
+        &self,
+        messages: &mut [ChatMessage],
+        names: &HashMap<PublicKey, String>,
+    ) {
+        mentions::resolve_mentions(messages, names)
+    }
+
+    /// Shorten reaction summaries to [`AggregatorConfig::max_emoji_per_message`], if set
+    ///
+    /// # Arguments
+    /// * `messages` - Aggregated messages about to be returned
+    /// * `viewer` - The requesting account, whose own reaction is always listed
+    pub fn cap_emoji(&self, messages: &mut [ChatMessage], viewer: &PublicKey) {
+        if let Some(max) = self.config.max_emoji_per_message {
+            processor::cap_emoji(messages, max, viewer);
+        }
+    }
+
     /// Get the current configuration
     pub fn config(&self) -> &AggregatorConfig {
         &self.config
@@ -130,15 +216,101 @@ impl MessageAggregator {
     // Get the current aggregated messages for a specific group
     // pub async fn get_aggregated_messages_for_group(&self, group_id: &GroupId) -> Option<Vec<ChatMessage>>
 
-    // Persist state for a specific group to disk
-    // pub async fn persist_group_state(&self, group_id: &GroupId) -> Result<(), StateError>
+    /// Persist aggregated messages for a specific group to disk
+    ///
+    /// The state is tagged with the MLS `epoch` it was aggregated at and is only handed back
+    /// by [`Self::load_group_state`] for that same epoch. Does nothing when persistence is
+    /// disabled in the [`AggregatorConfig`].
+    pub async fn persist_group_state(
+        &self,
+        group_id: &GroupId,
+        epoch: u64,
+        messages: Vec<ChatMessage>,
+    ) -> Result<(), StateError> {
+        let Some(dir) = self.persistence_dir() else {
+            return Ok(());
+        };
+
+        let group_state = GroupState::new(epoch, messages);
+        state::write_group_state(dir, group_id, &group_state).await?;
+        self.state
+            .write()
+            .await
+            .insert(group_id.clone(), group_state);
+        Ok(())
+    }
+
+    /// Load the aggregated messages persisted for a specific group
+    ///
+    /// State is read from disk the first time a group is asked for and kept in memory after
+    /// that. Returns `None` when nothing was persisted, when persistence is disabled, or when
+    /// the group has moved past the epoch the state was aggregated at; stale state is cleared.
+    pub async fn load_group_state(
+        &self,
+        group_id: &GroupId,
+        epoch: u64,
+    ) -> Result<Option<Vec<ChatMessage>>, StateError> {
+        let Some(dir) = self.persistence_dir() else {
+            return Ok(None);
+        };
+
+        let cached = self.state.read().await.get(group_id).cloned();
+        let group_state = match cached {
+            Some(group_state) => group_state,
+            None => match state::read_group_state(dir, group_id).await? {
+                Some(group_state) => {
+                    self.state
+                        .write()
+                        .await
+                        .insert(group_id.clone(), group_state.clone());
+                    group_state
+                }
+                None => return Ok(None),
+            },
+        };
+
+        if group_state.epoch != epoch {
+            tracing::debug!(
+                target: "whitenoise::message_aggregator",
+                "Discarding aggregator state for group {}: epoch {} is stale (current {})",
+                hex::encode(group_id.as_slice()),
+                group_state.epoch,
+                epoch
+            );
+            self.clear_group_state(group_id).await?;
+            return Ok(None);
+        }
 
-    // Load persisted state for a specific group from disk
-    // pub async fn load_group_state(&self, group_id: &GroupId) -> Result<(), StateError>
+        Ok(Some(group_state.messages))
+    }
 
-    // Clear all cached/persisted state for a specific group.
-    // Useful when a user leaves a group or wants to reset message history.
-    // pub async fn clear_group_state(&self, group_id: &GroupId) -> Result<(), StateError>
+    /// Clear a group's state after its cached messages changed
+    ///
+    /// Persisted state is a snapshot of the group's message cache, so it has to be dropped
+    /// whenever a message, reaction, deletion, edit or delivery status of the group is cached.
+    /// Failures are logged rather than returned, since the cache write itself succeeded.
+    pub async fn invalidate_group_state(&self, group_id: &GroupId) {
+        if let Err(e) = self.clear_group_state(group_id).await {
+            tracing::warn!(
+                target: "whitenoise::message_aggregator",
+                "Failed to clear aggregator state for group {}: {}",
+                hex::encode(group_id.as_slice()),
+                e
+            );
+        }
+    }
+
+    /// Clear all cached/persisted state for a specific group.
+    /// Useful when a user leaves a group or wants to reset message history.
+    pub async fn clear_group_state(&self, group_id: &GroupId) -> Result<(), StateError> {
+        self.state.write().await.remove(group_id);
+
+        // Clear the disk copy even if persistence has since been disabled
+        if let Some(dir) = &self.state_dir {
+            state::remove_group_state(dir, group_id).await?;
+        }
+        Ok(())
+    }
 }
 
 impl Default for MessageAggregator {
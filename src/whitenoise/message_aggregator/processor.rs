@@ -4,12 +4,17 @@
 //! raw Nostr MLS messages into structured ChatMessage objects.
 
 use nostr_sdk::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use super::edit_handler;
+use super::mentions;
 use super::reaction_handler;
-use super::types::{AggregatorConfig, ChatMessage, ProcessingError};
+use super::types::{
+    AggregatorConfig, ChatMessage, ChatMessageRef, MutedAuthors, ProcessingError, ReactionSummary,
+};
 use crate::nostr_manager::parser::Parser;
 use crate::whitenoise::media_files::MediaFile;
+use crate::whitenoise::utils::preview_text;
 use mdk_core::prelude::message_types::Message;
 
 /// Process raw messages into aggregated chat messages
@@ -37,9 +42,15 @@ pub async fn process_messages(
 
     let mut processed_messages = HashMap::new();
     let mut orphaned_messages = Vec::new();
+    let mut edits = Vec::new();
 
     let mut sorted_messages = messages;
-    sorted_messages.sort_unstable_by(|a, b| a.created_at.cmp(&b.created_at));
+    // Ties are broken by event ID so same-second messages are always applied in the same order
+    sorted_messages.sort_unstable_by(|a, b| {
+        a.created_at
+            .cmp(&b.created_at)
+            .then_with(|| a.id.cmp(&b.id))
+    });
 
     if config.enable_debug_logging {
         tracing::debug!(
@@ -72,6 +83,7 @@ pub async fn process_messages(
                     orphaned_messages.push(message);
                 }
             }
+            _ if edit_handler::is_edit(message) => edits.push(message),
             _ => continue,
         }
     }
@@ -112,8 +124,15 @@ pub async fn process_messages(
         }
     }
 
+    // Pass 3: Collapse edits onto their original messages, now that deletions are known
+    apply_edits(&edits, &mut processed_messages, parser, config);
+
+    // Pass 4: Fill in previews for replies and quotes whose target is in this batch
+    resolve_reply_previews(&mut processed_messages);
+    resolve_quote_previews(&mut processed_messages);
+
     let mut result: Vec<ChatMessage> = processed_messages.into_values().collect();
-    result.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    result.sort_by(ChatMessage::cmp_chronological);
 
     if config.enable_debug_logging {
         tracing::debug!("Returning {} aggregated messages", result.len());
@@ -122,6 +141,128 @@ pub async fn process_messages(
     Ok(result)
 }
 
+/// Drop reactions from muted authors and hide or mark their messages
+pub(crate) fn apply_muted_authors(
+    messages: Vec<ChatMessage>,
+    muted: &HashSet<PublicKey>,
+    handling: MutedAuthors,
+) -> Vec<ChatMessage> {
+    if muted.is_empty() {
+        return messages;
+    }
+
+    messages
+        .into_iter()
+        .filter_map(|mut message| {
+            let muted_reactors: Vec<PublicKey> = message
+                .reactions
+                .user_reactions
+                .iter()
+                .map(|reaction| reaction.user)
+                .filter(|user| muted.contains(user))
+                .collect();
+            for user in &muted_reactors {
+                reaction_handler::remove_reaction_from_message(&mut message, user);
+            }
+
+            if !muted.contains(&message.author) {
+                return Some(message);
+            }
+            match handling {
+                MutedAuthors::Hide => None,
+                MutedAuthors::Mark => {
+                    message.is_muted = true;
+                    Some(message)
+                }
+            }
+        })
+        .collect()
+}
+
+/// Limit the reactors listed per emoji to the `max` earliest
+///
+/// Counts are left alone, and the viewer's own reaction is always kept so the UI can show
+/// it as selected.
+pub(crate) fn cap_reactors(messages: &mut [ChatMessage], max: usize, viewer: &PublicKey) {
+    for message in messages {
+        let reactions = &mut message.reactions;
+        if reactions
+            .by_emoji
+            .values()
+            .all(|emoji_reaction| emoji_reaction.users.len() <= max)
+        {
+            continue;
+        }
+
+        reactions
+            .user_reactions
+            .sort_by_key(|reaction| reaction.created_at);
+        let mut listed: HashMap<String, usize> = HashMap::new();
+        reactions.user_reactions.retain(|reaction| {
+            let count = listed.entry(reaction.emoji.clone()).or_default();
+            if reaction.user == *viewer || *count < max {
+                *count += 1;
+                true
+            } else {
+                false
+            }
+        });
+
+        for emoji_reaction in reactions.by_emoji.values_mut() {
+            emoji_reaction.users = reactions
+                .user_reactions
+                .iter()
+                .filter(|reaction| reaction.emoji == emoji_reaction.emoji)
+                .map(|reaction| reaction.user)
+                .collect();
+        }
+    }
+}
+
+/// Limit the emoji listed per message to the `max` most used
+///
+/// The viewer's own emoji is always kept, so the UI can show it as selected. Reactions on
+/// the emoji left out are removed and counted in
+/// [`ReactionSummary::more_count`](super::types::ReactionSummary::more_count).
+pub(crate) fn cap_emoji(messages: &mut [ChatMessage], max: usize, viewer: &PublicKey) {
+    for message in messages {
+        cap_summary_emoji(&mut message.reactions, max, viewer);
+    }
+}
+
+fn cap_summary_emoji(reactions: &mut ReactionSummary, max: usize, viewer: &PublicKey) {
+    if reactions.by_emoji.len() <= max {
+        return;
+    }
+
+    let viewer_emoji: HashSet<String> = reactions
+        .user_reactions
+        .iter()
+        .filter(|reaction| reaction.user == *viewer)
+        .map(|reaction| reaction.emoji.clone())
+        .collect();
+    let kept: HashSet<String> = reactions
+        .ranked()
+        .into_iter()
+        .take(max)
+        .map(|emoji_reaction| emoji_reaction.emoji.clone())
+        .chain(viewer_emoji)
+        .collect();
+
+    let mut more_count = 0;
+    reactions.by_emoji.retain(|emoji, emoji_reaction| {
+        let keep = kept.contains(emoji);
+        if !keep {
+            more_count += emoji_reaction.count;
+        }
+        keep
+    });
+    reactions
+        .user_reactions
+        .retain(|reaction| kept.contains(&reaction.emoji));
+    reactions.more_count += more_count;
+}
+
 /// Process a regular chat message (kind 9)
 pub(crate) async fn process_regular_message(
     message: &Message,
@@ -151,12 +292,18 @@ pub(crate) async fn process_regular_message(
         created_at: message.created_at,
         tags: message.tags.clone(),
         is_reply,
+        reply_to: reply_to_id.clone().map(ChatMessageRef::unresolved),
+        quoted: extract_quote(&message.tags),
         reply_to_id,
         is_deleted: false,
+        edited_at: None,
+        is_muted: false,
         content_tokens,
+        mentions: mentions::extract_mentions(&message.content),
         reactions: Default::default(),
         kind: u16::from(message.kind),
         media_attachments,
+        delivery_status: Default::default(),
     })
 }
 
@@ -182,6 +329,94 @@ fn extract_reply_info(tags: &Tags) -> Option<String> {
     None
 }
 
+/// The NIP-18 `q` tag of a message that quotes another
+fn quote_tag(tags: &Tags) -> Option<&Tag> {
+    tags.iter()
+        .find(|tag| tag.kind() == TagKind::SingleLetter(SingleLetterTag::lowercase(Alphabet::Q)))
+}
+
+/// Extract the quoted message from a NIP-18 `q` tag
+///
+/// Quotes made by Whitenoise carry a preview of the quoted content as the tag's fifth
+/// element (`["q", <id>, <relay>, <author>, <preview>]`); quotes without one are unresolved.
+/// The tag is written by the quoting member, so its preview is only trusted until the
+/// target is known, see [`verify_quote`].
+pub(crate) fn extract_quote(tags: &Tags) -> Option<ChatMessageRef> {
+    let tag = quote_tag(tags)?;
+    let id = tag.content()?.to_string();
+    let preview = tag
+        .as_slice()
+        .get(4)
+        .filter(|preview| !preview.is_empty())
+        .cloned();
+    Some(ChatMessageRef { id, preview })
+}
+
+/// Check a quote against its cached target and set its preview
+///
+/// A quote whose tag names someone other than the target's author gets no preview. Otherwise
+/// the preview is the target's current content, so edits show up in quotes; once the target
+/// is deleted, the preview taken when quoting is kept if the tag names the target's author.
+///
+/// # Arguments
+/// * `quoted` - The quote, as extracted from `tags`
+/// * `tags` - Tags of the quoting message
+/// * `target` - The quoted message
+pub(crate) fn verify_quote(quoted: &mut ChatMessageRef, tags: &Tags, target: &ChatMessage) {
+    let tag_author = quote_tag(tags)
+        .and_then(|tag| tag.as_slice().get(3))
+        .and_then(|author| PublicKey::from_hex(author).ok());
+
+    if tag_author.is_some_and(|author| author != target.author) {
+        quoted.preview = None;
+    } else if !target.is_deleted {
+        quoted.preview = Some(preview_text(&target.content));
+    } else if tag_author.is_none() {
+        quoted.preview = None;
+    }
+}
+
+/// Verify every quote whose target was processed in the same batch, see [`verify_quote`]
+///
+/// Other quotes keep the preview taken when quoting.
+fn resolve_quote_previews(processed_messages: &mut HashMap<String, ChatMessage>) {
+    let verified: Vec<(String, ChatMessageRef)> = processed_messages
+        .iter()
+        .filter_map(|(id, message)| {
+            let mut quoted = message.quoted.clone()?;
+            let target = processed_messages.get(&quoted.id)?;
+            verify_quote(&mut quoted, &message.tags, target);
+            Some((id.clone(), quoted))
+        })
+        .collect();
+
+    for (id, quoted) in verified {
+        if let Some(message) = processed_messages.get_mut(&id) {
+            message.quoted = Some(quoted);
+        }
+    }
+}
+
+/// Set the preview of every reply whose target was processed and not deleted
+///
+/// Replies to messages outside the batch keep only the target id.
+fn resolve_reply_previews(processed_messages: &mut HashMap<String, ChatMessage>) {
+    let previews: HashMap<String, String> = processed_messages
+        .values()
+        .filter_map(|message| message.reply_to.as_ref())
+        .filter_map(|reply_to| {
+            let target = processed_messages.get(&reply_to.id)?;
+            (!target.is_deleted).then(|| (reply_to.id.clone(), preview_text(&target.content)))
+        })
+        .collect();
+
+    for message in processed_messages.values_mut() {
+        if let Some(reply_to) = message.reply_to.as_mut() {
+            reply_to.preview = previews.get(&reply_to.id).cloned();
+        }
+    }
+}
+
 /// Try to process deletion message (kind 5)
 /// Returns true if at least one target was found and deleted, false otherwise
 fn try_process_deletion(
@@ -202,6 +437,57 @@ fn try_process_deletion(
     any_processed
 }
 
+/// Apply edits, given in chronological order, to the messages they edit
+///
+/// Edits of edits are resolved to the original message, so the newest edit by the author
+/// ends up applied regardless of which version it referenced.
+fn apply_edits(
+    edits: &[&Message],
+    processed_messages: &mut HashMap<String, ChatMessage>,
+    parser: &dyn Parser,
+    config: &AggregatorConfig,
+) {
+    let edit_targets: HashMap<String, String> = edits
+        .iter()
+        .filter_map(|edit| {
+            let target_id = edit_handler::extract_edit_target_id(&edit.tags)?;
+            Some((edit.id.to_string(), target_id))
+        })
+        .collect();
+
+    for edit in edits {
+        let Some(target_id) = edit_targets.get(&edit.id.to_string()) else {
+            continue;
+        };
+        let root_id = edit_handler::resolve_edit_root(target_id, &edit_targets);
+        let Some(target_message) = processed_messages.get_mut(&root_id) else {
+            if config.enable_debug_logging {
+                tracing::warn!(
+                    "Edit {} references non-existent message {}, ignoring",
+                    edit.id,
+                    root_id
+                );
+            }
+            continue;
+        };
+
+        let content_tokens = parser.parse(&edit.content).unwrap_or_else(|e| {
+            tracing::warn!("Failed to parse edit content: {}", e);
+            Vec::new()
+        });
+        if !edit_handler::apply_edit_to_message(
+            target_message,
+            &edit.pubkey,
+            &edit.content,
+            content_tokens,
+            edit.created_at,
+        ) && config.enable_debug_logging
+        {
+            tracing::debug!("Ignoring edit {} of message {}", edit.id, root_id);
+        }
+    }
+}
+
 /// Extract target message IDs from deletion event e-tags
 pub(crate) fn extract_deletion_target_ids(tags: &Tags) -> Vec<String> {
     tags.iter()
@@ -264,6 +550,26 @@ mod tests {
     use super::*;
     use crate::nostr_manager::parser::MockParser;
 
+    fn message(keys: &Keys, kind: Kind, content: &str, tags: Vec<Tag>, created_at: u64) -> Message {
+        let created_at = Timestamp::from(created_at);
+        let mut event = UnsignedEvent::new(keys.public_key(), created_at, kind, tags, content);
+        event.ensure_id();
+        let id = event.id.unwrap();
+
+        Message {
+            id,
+            pubkey: keys.public_key(),
+            created_at,
+            kind,
+            tags: event.tags.clone(),
+            content: content.to_string(),
+            mls_group_id: mdk_core::prelude::GroupId::from_slice(&[1; 32]),
+            event,
+            wrapper_event_id: EventId::all_zeros(),
+            state: mdk_core::prelude::message_types::MessageState::Processed,
+        }
+    }
+
     // Test the pure logic functions that don't require complex Message structs
 
     #[test]
@@ -309,6 +615,377 @@ mod tests {
         assert!(target_ids.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_messages_with_same_timestamp_have_deterministic_order() {
+        let keys = Keys::generate();
+        let messages: Vec<Message> = (0..8)
+            .map(|i| message(&keys, Kind::Custom(9), &format!("batched {i}"), vec![], 100))
+            .chain([message(&keys, Kind::Custom(9), "earlier", vec![], 99)])
+            .collect();
+
+        let mut expected_ids: Vec<String> = messages[..8].iter().map(|m| m.id.to_hex()).collect();
+        expected_ids.sort();
+        expected_ids.insert(0, messages[8].id.to_hex());
+
+        for rotation in 0..messages.len() {
+            let mut input = messages.clone();
+            input.rotate_left(rotation);
+            let result = process_messages(
+                input,
+                &MockParser::new(),
+                &AggregatorConfig::default(),
+                vec![],
+            )
+            .await
+            .unwrap();
+            let ids: Vec<String> = result.into_iter().map(|m| m.id).collect();
+            assert_eq!(ids, expected_ids);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reply_previews_resolved_within_batch() {
+        let keys = Keys::generate();
+        let original = message(&keys, Kind::Custom(9), "hello there", vec![], 100);
+        let reply = message(
+            &keys,
+            Kind::Custom(9),
+            "hi!",
+            vec![Tag::event(original.id)],
+            101,
+        );
+        let deleted = message(&keys, Kind::Custom(9), "oops", vec![], 102);
+        let reply_to_deleted = message(
+            &keys,
+            Kind::Custom(9),
+            "what did you say?",
+            vec![Tag::event(deleted.id)],
+            103,
+        );
+        let deletion = message(
+            &keys,
+            Kind::EventDeletion,
+            "",
+            vec![Tag::event(deleted.id)],
+            104,
+        );
+
+        let result = process_messages(
+            vec![
+                original.clone(),
+                reply.clone(),
+                deleted.clone(),
+                reply_to_deleted.clone(),
+                deletion,
+            ],
+            &MockParser::new(),
+            &AggregatorConfig::default(),
+            vec![],
+        )
+        .await
+        .unwrap();
+        let find = |id: EventId| result.iter().find(|m| m.id == id.to_string()).unwrap();
+
+        assert_eq!(
+            find(reply.id).reply_to,
+            Some(ChatMessageRef {
+                id: original.id.to_string(),
+                preview: Some("hello there".to_string()),
+            })
+        );
+        assert!(find(original.id).reply_to.is_none());
+
+        // The target of a reply to a deleted message is still identified, without content
+        assert_eq!(
+            find(reply_to_deleted.id).reply_to,
+            Some(ChatMessageRef::unresolved(deleted.id.to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_quote_keeps_preview_after_target_is_deleted() {
+        let keys = Keys::generate();
+        let original = message(&keys, Kind::Custom(9), "quotable", vec![], 100);
+        let reply_target = message(&keys, Kind::Custom(9), "question", vec![], 101);
+        let quote_tag = Tag::parse(vec![
+            "q".to_string(),
+            original.id.to_hex(),
+            String::new(),
+            keys.public_key().to_hex(),
+            "quotable".to_string(),
+        ])
+        .unwrap();
+        let quote = message(
+            &keys,
+            Kind::Custom(9),
+            "look at this",
+            vec![Tag::event(reply_target.id), quote_tag],
+            102,
+        );
+        let deletion = message(
+            &keys,
+            Kind::EventDeletion,
+            "",
+            vec![Tag::event(original.id)],
+            103,
+        );
+
+        let result = process_messages(
+            vec![
+                original.clone(),
+                reply_target.clone(),
+                quote.clone(),
+                deletion,
+            ],
+            &MockParser::new(),
+            &AggregatorConfig::default(),
+            vec![],
+        )
+        .await
+        .unwrap();
+        let quote = result
+            .iter()
+            .find(|m| m.id == quote.id.to_string())
+            .unwrap();
+
+        // Replying and quoting are independent
+        assert_eq!(quote.reply_to_id, Some(reply_target.id.to_string()));
+        assert_eq!(
+            quote.reply_to.as_ref().unwrap().preview.as_deref(),
+            Some("question")
+        );
+        assert_eq!(
+            quote.quoted,
+            Some(ChatMessageRef {
+                id: original.id.to_string(),
+                preview: Some("quotable".to_string()),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reply_to_message_outside_batch_keeps_id() {
+        let keys = Keys::generate();
+        let missing_id = EventId::all_zeros();
+        let reply = message(
+            &keys,
+            Kind::Custom(9),
+            "replying to something older",
+            vec![Tag::event(missing_id)],
+            100,
+        );
+
+        let result = process_messages(
+            vec![reply],
+            &MockParser::new(),
+            &AggregatorConfig::default(),
+            vec![],
+        )
+        .await
+        .unwrap();
+
+        assert!(result[0].is_reply);
+        assert_eq!(
+            result[0].reply_to,
+            Some(ChatMessageRef::unresolved(missing_id.to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_muted_authors_hides_or_marks_messages() {
+        let parser = MockParser::new();
+        let alice = Keys::generate();
+        let muted = Keys::generate();
+
+        let original = message(&alice, Kind::Custom(9), "hi", vec![], 1000);
+        let muted_message = message(&muted, Kind::Custom(9), "spam", vec![], 1001);
+        let muted_reaction = message(
+            &muted,
+            Kind::Reaction,
+            "👎",
+            vec![Tag::event(original.id)],
+            1002,
+        );
+        let messages = process_messages(
+            vec![original, muted_message, muted_reaction],
+            &parser,
+            &AggregatorConfig::default(),
+            Vec::new(),
+        )
+        .await
+        .unwrap();
+        let muted_set = HashSet::from([muted.public_key()]);
+
+        let hidden = apply_muted_authors(messages.clone(), &muted_set, MutedAuthors::Hide);
+        assert_eq!(hidden.len(), 1);
+        assert_eq!(hidden[0].author, alice.public_key());
+        assert!(hidden[0].reactions.user_reactions.is_empty());
+        assert!(hidden[0].reactions.by_emoji.is_empty());
+
+        let marked = apply_muted_authors(messages, &muted_set, MutedAuthors::Mark);
+        assert_eq!(marked.len(), 2);
+        assert!(!marked[0].is_muted);
+        assert!(marked[0].reactions.user_reactions.is_empty());
+        assert!(marked[1].is_muted);
+        assert_eq!(marked[1].content, "spam");
+    }
+
+    #[tokio::test]
+    async fn test_cap_reactors_keeps_earliest_and_viewer() {
+        let parser = MockParser::new();
+        let alice = Keys::generate();
+        let original = message(&alice, Kind::Custom(9), "hi", vec![], 1000);
+        let reactors: Vec<Keys> = (0..5).map(|_| Keys::generate()).collect();
+        let mut raw = vec![original.clone()];
+        for (i, reactor) in reactors.iter().enumerate() {
+            raw.push(message(
+                reactor,
+                Kind::Reaction,
+                "😀",
+                vec![Tag::event(original.id)],
+                1001 + i as u64,
+            ));
+        }
+        raw.push(message(
+            &alice,
+            Kind::Reaction,
+            "🔥",
+            vec![Tag::event(original.id)],
+            1010,
+        ));
+        let mut messages = process_messages(raw, &parser, &AggregatorConfig::default(), Vec::new())
+            .await
+            .unwrap();
+
+        let viewer = reactors[4].public_key();
+        cap_reactors(&mut messages, 2, &viewer);
+
+        let reactions = &messages[0].reactions;
+        let smiles = &reactions.by_emoji["😀"];
+        assert_eq!(smiles.count, 5);
+        assert_eq!(
+            smiles.users,
+            vec![reactors[0].public_key(), reactors[1].public_key(), viewer]
+        );
+        assert_eq!(smiles.others_count(), 2);
+        assert_eq!(reactions.by_emoji["🔥"].users, vec![alice.public_key()]);
+        assert_eq!(reactions.user_reactions.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_edit_of_edit_collapses_onto_original() {
+        let author = Keys::generate();
+        let other = Keys::generate();
+        let original = message(&author, Kind::Custom(9), "helo", vec![], 100);
+        let reply = message(
+            &other,
+            Kind::Custom(9),
+            "typo!",
+            vec![Tag::event(original.id)],
+            101,
+        );
+        let edit_kind = Kind::Custom(edit_handler::MESSAGE_EDIT_KIND);
+        let edit = message(
+            &author,
+            edit_kind,
+            "hello",
+            vec![Tag::event(original.id)],
+            102,
+        );
+        // Edits the first edit rather than the original
+        let edit_of_edit = message(&author, edit_kind, "hello!", vec![Tag::event(edit.id)], 103);
+        // Edits by anyone but the author are ignored
+        let hijack = message(
+            &other,
+            edit_kind,
+            "hacked",
+            vec![Tag::event(original.id)],
+            104,
+        );
+
+        let result = process_messages(
+            vec![hijack, edit_of_edit, edit, reply.clone(), original.clone()],
+            &MockParser::new(),
+            &AggregatorConfig::default(),
+            vec![],
+        )
+        .await
+        .unwrap();
+
+        // Edits never show up as messages of their own
+        assert_eq!(result.len(), 2);
+        let find = |id: EventId| result.iter().find(|m| m.id == id.to_string()).unwrap();
+        let edited = find(original.id);
+        assert_eq!(edited.content, "hello!");
+        assert_eq!(edited.edited_at, Some(Timestamp::from(103)));
+        assert_eq!(edited.created_at, Timestamp::from(100));
+        assert_eq!(
+            find(reply.id).reply_to.as_ref().unwrap().preview.as_deref(),
+            Some("hello!")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_edit_of_deleted_message_is_ignored() {
+        let author = Keys::generate();
+        let edit_kind = Kind::Custom(edit_handler::MESSAGE_EDIT_KIND);
+        let deleted = message(&author, Kind::Custom(9), "oops", vec![], 100);
+        let deletion = message(
+            &author,
+            Kind::EventDeletion,
+            "",
+            vec![Tag::event(deleted.id)],
+            101,
+        );
+        let edit = message(
+            &author,
+            edit_kind,
+            "fixed",
+            vec![Tag::event(deleted.id)],
+            102,
+        );
+        // Deleting after an edit leaves the message deleted too
+        let edited = message(&author, Kind::Custom(9), "first", vec![], 103);
+        let edit_before_deletion = message(
+            &author,
+            edit_kind,
+            "second",
+            vec![Tag::event(edited.id)],
+            104,
+        );
+        let late_deletion = message(
+            &author,
+            Kind::EventDeletion,
+            "",
+            vec![Tag::event(edited.id)],
+            105,
+        );
+
+        let result = process_messages(
+            vec![
+                deleted.clone(),
+                deletion,
+                edit,
+                edited.clone(),
+                edit_before_deletion,
+                late_deletion,
+            ],
+            &MockParser::new(),
+            &AggregatorConfig::default(),
+            vec![],
+        )
+        .await
+        .unwrap();
+        let find = |id: EventId| result.iter().find(|m| m.id == id.to_string()).unwrap();
+
+        for id in [deleted.id, edited.id] {
+            let message = find(id);
+            assert!(message.is_deleted);
+            assert!(message.content.is_empty());
+            assert_eq!(message.edited_at, None);
+        }
+    }
+
     #[tokio::test]
     async fn test_empty_messages() {
         let parser = MockParser::new();
@@ -328,11 +1005,73 @@ mod tests {
         assert!(!config.enable_debug_logging);
     }
 
+    #[test]
+    fn test_cap_emoji_keeps_most_used_and_counts_the_rest() {
+        use crate::whitenoise::message_aggregator::types::{EmojiReaction, UserReaction};
+
+        // Emoji i gets i + 1 reactions, so the last ones are the most used
+        let viewer = Keys::generate().public_key();
+        let mut reactions = ReactionSummary::default();
+        let mut created_at = 1000;
+        for (i, emoji) in ["😀", "🔥", "👍", "🎉", "🚀"].into_iter().enumerate() {
+            let mut users: Vec<PublicKey> =
+                (0..=i).map(|_| Keys::generate().public_key()).collect();
+            if emoji == "😀" {
+                users.push(viewer);
+            }
+            for user in &users {
+                reactions.user_reactions.push(UserReaction {
+                    user: *user,
+                    emoji: emoji.to_string(),
+                    created_at: Timestamp::from(created_at),
+                });
+                created_at += 1;
+            }
+            reactions.by_emoji.insert(
+                emoji.to_string(),
+                EmojiReaction {
+                    emoji: emoji.to_string(),
+                    count: users.len(),
+                    users,
+                },
+            );
+        }
+
+        cap_summary_emoji(&mut reactions, 2, &viewer);
+
+        let ranked: Vec<(&str, usize)> = reactions
+            .ranked()
+            .into_iter()
+            .map(|emoji_reaction| (emoji_reaction.emoji.as_str(), emoji_reaction.count))
+            .collect();
+        // The viewer's emoji is kept even though it isn't among the top two
+        assert_eq!(ranked, vec![("🚀", 5), ("🎉", 4), ("😀", 2)]);
+        // 🔥 and 👍 are left out
+        assert_eq!(reactions.more_count, 2 + 3);
+        assert!(
+            reactions
+                .user_reactions
+                .iter()
+                .all(|reaction| reactions.by_emoji.contains_key(&reaction.emoji))
+        );
+        assert!(
+            reactions
+                .user_reactions
+                .iter()
+                .any(|reaction| reaction.user == viewer)
+        );
+    }
+
     #[test]
     fn test_config_custom() {
         let config = AggregatorConfig {
             normalize_emoji: false,
             enable_debug_logging: true,
+            persist_state: false,
+            muted_authors: MutedAuthors::Hide,
+            emoji_equivalents: HashMap::new(),
+            max_reactors_per_emoji: None,
+            max_emoji_per_message: None,
         };
 
         assert!(!config.normalize_emoji);
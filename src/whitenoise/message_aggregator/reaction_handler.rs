@@ -16,8 +16,7 @@ pub fn process_reaction(
     processed_messages: &mut HashMap<String, ChatMessage>,
     config: &AggregatorConfig,
 ) -> Result<(), ProcessingError> {
-    let reaction_emoji =
-        emoji_utils::validate_and_normalize_reaction(&message.content, config.normalize_emoji)?;
+    let reaction_emoji = emoji_utils::validate_and_normalize_reaction(&message.content, config)?;
 
     let target_id = extract_target_message_id(&message.tags)?;
 
@@ -156,26 +155,6 @@ pub(crate) fn add_reaction_to_message(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::whitenoise::message_aggregator::types::ReactionSummary;
-
-    fn create_chat_message(id: &str) -> ChatMessage {
-        let keys = Keys::generate();
-
-        ChatMessage {
-            id: id.to_string(),
-            author: keys.public_key(),
-            content: "Test message".to_string(),
-            created_at: Timestamp::from(1234567890),
-            tags: Tags::new(),
-            is_reply: false,
-            reply_to_id: None,
-            is_deleted: false,
-            content_tokens: vec![],
-            reactions: ReactionSummary::default(),
-            kind: 9, // Default to MLS group chat
-            media_attachments: vec![],
-        }
-    }
 
     #[test]
     fn test_extract_target_message_id() {
@@ -208,7 +187,7 @@ mod tests {
 
     #[test]
     fn test_add_reaction_to_message() {
-        let mut chat_message = create_chat_message("msg1");
+        let mut chat_message = ChatMessage::test_message("msg1", "Test message", 1234567890);
         let user = Keys::generate().public_key();
         let created_at = Timestamp::from(1234567890);
 
@@ -229,7 +208,7 @@ mod tests {
 
     #[test]
     fn test_replace_existing_reaction() {
-        let mut chat_message = create_chat_message("msg1");
+        let mut chat_message = ChatMessage::test_message("msg1", "Test message", 1234567890);
         let user = Keys::generate().public_key();
         let created_at = Timestamp::from(1234567890);
 
@@ -251,7 +230,7 @@ mod tests {
 
     #[test]
     fn test_multiple_users_same_emoji() {
-        let mut chat_message = create_chat_message("msg1");
+        let mut chat_message = ChatMessage::test_message("msg1", "Test message", 1234567890);
         let user1 = Keys::generate().public_key();
         let user2 = Keys::generate().public_key();
         let created_at = Timestamp::from(1234567890);
@@ -271,7 +250,7 @@ mod tests {
 
     #[test]
     fn test_add_reaction_to_message_sorting() {
-        let mut chat_message = create_chat_message("msg1");
+        let mut chat_message = ChatMessage::test_message("msg1", "Test message", 1234567890);
         let user1 = Keys::generate().public_key();
         let user2 = Keys::generate().public_key();
 
@@ -296,7 +275,7 @@ mod tests {
 
     #[test]
     fn test_reaction_removal_when_count_zero() {
-        let mut chat_message = create_chat_message("msg1");
+        let mut chat_message = ChatMessage::test_message("msg1", "Test message", 1234567890);
         let user = Keys::generate().public_key();
         let created_at = Timestamp::from(1234567890);
 
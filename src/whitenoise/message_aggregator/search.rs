@@ -0,0 +1,149 @@
+//! Message search
+//!
+//! Case-insensitive substring search over already aggregated messages. Matches inside
+//! URLs and `nostr:` references are ignored so searching for e.g. "npub" or "https"
+//! only finds words the author actually typed.
+
+use std::ops::Range;
+
+use super::types::{ChatMessage, MessageSearchResult};
+use crate::nostr_manager::parser::SerializableToken;
+
+/// Search `messages` for `query`, returning at most `limit` matches, newest first.
+///
+/// Deleted messages never match. An empty or whitespace-only query matches nothing.
+pub(crate) fn search_messages(
+    messages: &[ChatMessage],
+    query: &str,
+    limit: usize,
+) -> Vec<MessageSearchResult> {
+    let query = query.trim();
+    if query.is_empty() || limit == 0 {
+        return Vec::new();
+    }
+    let needle: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+
+    let mut results: Vec<MessageSearchResult> = messages
+        .iter()
+        .filter(|message| !message.is_deleted)
+        .filter_map(|message| {
+            let skipped = skipped_spans(message);
+            find_match(&message.content, &needle, &skipped).map(|range| MessageSearchResult {
+                message: message.clone(),
+                match_offset: range.start,
+                match_len: range.len(),
+            })
+        })
+        .collect();
+
+    results.sort_by(|a, b| b.message.created_at.cmp(&a.message.created_at));
+    results.truncate(limit);
+    results
+}
+
+/// Byte ranges of URLs and nostr references in the message content
+fn skipped_spans(message: &ChatMessage) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut cursor = 0;
+
+    for token in &message.content_tokens {
+        let text = match token {
+            SerializableToken::Url(text) | SerializableToken::Nostr(text) => text,
+            _ => continue,
+        };
+        // Tokens appear in content order, so search forward from the previous one
+        if let Some(start) = message.content[cursor..].find(text.as_str()) {
+            let start = cursor + start;
+            spans.push(start..start + text.len());
+            cursor = start + text.len();
+        }
+    }
+
+    spans
+}
+
+/// First case-insensitive occurrence of `needle` in `content` outside the skipped spans
+fn find_match(content: &str, needle: &[char], skipped: &[Range<usize>]) -> Option<Range<usize>> {
+    content.char_indices().find_map(|(start, _)| {
+        let end = match_end(&content[start..], needle)? + start;
+        let overlaps = skipped
+            .iter()
+            .any(|span| start < span.end && span.start < end);
+        (!overlaps).then_some(start..end)
+    })
+}
+
+/// Byte length of the prefix of `haystack` that lowercases to `needle`, if any
+fn match_end(haystack: &str, needle: &[char]) -> Option<usize> {
+    let mut remaining = needle;
+    for (offset, c) in haystack.char_indices() {
+        for lower in c.to_lowercase() {
+            match remaining.split_first() {
+                Some((expected, rest)) if *expected == lower => remaining = rest,
+                _ => return None,
+            }
+        }
+        if remaining.is_empty() {
+            return Some(offset + c.len_utf8());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: u8, content: &str, created_at: u64) -> ChatMessage {
+        ChatMessage::test_message(&format!("{:0>64x}", id), content, created_at)
+    }
+
+    #[test]
+    fn test_search_messages_ranks_by_recency_with_offsets() {
+        let mut with_url = message(3, "see https://example.com/Lunch", 300);
+        with_url.content_tokens = vec![
+            SerializableToken::Text("see".to_string()),
+            SerializableToken::Whitespace,
+            SerializableToken::Url("https://example.com/Lunch".to_string()),
+        ];
+        let mut deleted = message(4, "lunch is cancelled", 400);
+        deleted.is_deleted = true;
+
+        let messages = vec![
+            message(1, "Lunch at noon?", 100),
+            message(2, "Sure, see you at LUNCH", 200),
+            with_url,
+            deleted,
+            message(5, "dinner instead", 500),
+        ];
+
+        let results = search_messages(&messages, "lunch", 10);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].message.id, format!("{:0>64x}", 2));
+        assert_eq!(results[0].match_offset, 17);
+        assert_eq!(results[0].match_len, 5);
+        assert_eq!(results[1].message.id, format!("{:0>64x}", 1));
+        assert_eq!(results[1].match_offset, 0);
+
+        let limited = search_messages(&messages, "lunch", 1);
+        assert_eq!(limited.len(), 1);
+        assert_eq!(limited[0].message.id, format!("{:0>64x}", 2));
+    }
+
+    #[test]
+    fn test_search_messages_empty_query_and_no_results() {
+        let messages = vec![message(1, "Grüße aus Köln", 100)];
+
+        assert!(search_messages(&messages, "", 10).is_empty());
+        assert!(search_messages(&messages, "   ", 10).is_empty());
+        assert!(search_messages(&messages, "berlin", 10).is_empty());
+
+        let results = search_messages(&messages, "KÖLN", 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            &messages[0].content[results[0].match_offset..][..results[0].match_len],
+            "Köln"
+        );
+    }
+}
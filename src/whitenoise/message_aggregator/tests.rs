@@ -40,6 +40,11 @@ mod integration_tests {
         let config = AggregatorConfig {
             normalize_emoji: false,
             enable_debug_logging: true,
+            persist_state: false,
+            muted_authors: MutedAuthors::Hide,
+            emoji_equivalents: HashMap::new(),
+            max_reactors_per_emoji: None,
+            max_emoji_per_message: None,
         };
 
         let aggregator = MessageAggregator::with_config(config.clone());
@@ -70,6 +75,11 @@ mod integration_tests {
         let config = AggregatorConfig {
             normalize_emoji: false,
             enable_debug_logging: true,
+            persist_state: false,
+            muted_authors: MutedAuthors::Hide,
+            emoji_equivalents: HashMap::new(),
+            max_reactors_per_emoji: None,
+            max_emoji_per_message: None,
         };
 
         let cloned_config = config.clone();
@@ -172,6 +182,11 @@ mod integration_tests {
         let config = AggregatorConfig {
             normalize_emoji: true,
             enable_debug_logging: true,
+            persist_state: false,
+            muted_authors: MutedAuthors::Hide,
+            emoji_equivalents: HashMap::new(),
+            max_reactors_per_emoji: None,
+            max_emoji_per_message: None,
         };
 
         let aggregator = MessageAggregator::with_config(config);
@@ -222,11 +237,17 @@ mod integration_tests {
             tags: Tags::new(),
             is_reply: false,
             reply_to_id: None,
+            reply_to: None,
+            quoted: None,
             is_deleted: false,
+            edited_at: None,
+            is_muted: false,
             content_tokens: vec![],
+            mentions: vec![],
             reactions: ReactionSummary::default(),
             kind: 9, // Default to MLS group chat
             media_attachments: vec![],
+            delivery_status: Default::default(),
         };
 
         // Test serialization
@@ -251,11 +272,17 @@ mod integration_tests {
             tags: Tags::new(),
             is_reply: false,
             reply_to_id: None,
+            reply_to: None,
+            quoted: None,
             is_deleted: false,
+            edited_at: None,
+            is_muted: false,
             content_tokens: vec![],
+            mentions: vec![],
             reactions: ReactionSummary::default(),
             kind: 9, // Default to MLS group chat
             media_attachments: vec![],
+            delivery_status: Default::default(),
         };
 
         let message2 = message1.clone();
@@ -303,4 +330,107 @@ mod integration_tests {
         assert_eq!(target_ids.len(), 1);
         assert_eq!(target_ids[0], "test_id");
     }
+
+    fn persisting_aggregator(data_dir: &std::path::Path) -> MessageAggregator {
+        MessageAggregator::with_config(AggregatorConfig {
+            persist_state: true,
+            ..Default::default()
+        })
+        .with_data_dir(data_dir)
+    }
+
+    fn state_test_message() -> ChatMessage {
+        ChatMessage {
+            id: "a".repeat(64),
+            author: Keys::generate().public_key(),
+            content: "persisted".to_string(),
+            created_at: Timestamp::from(1_000),
+            tags: Tags::new(),
+            is_reply: false,
+            reply_to_id: None,
+            reply_to: None,
+            quoted: None,
+            is_deleted: false,
+            edited_at: None,
+            is_muted: false,
+            content_tokens: vec![],
+            mentions: vec![],
+            reactions: ReactionSummary::default(),
+            kind: 9,
+            media_attachments: vec![],
+            delivery_status: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_group_state_survives_restart_until_epoch_advances() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let group_id = GroupId::from_slice(&[1; 32]);
+        let messages = vec![state_test_message()];
+
+        persisting_aggregator(temp_dir.path())
+            .persist_group_state(&group_id, 4, messages.clone())
+            .await
+            .unwrap();
+        assert!(temp_dir.path().join("message_cache").exists());
+
+        // A fresh aggregator lazily loads the state from disk
+        let restarted = persisting_aggregator(temp_dir.path());
+        assert_eq!(
+            restarted.load_group_state(&group_id, 4).await.unwrap(),
+            Some(messages)
+        );
+
+        // Once the epoch advances the state is stale and gets dropped
+        assert_eq!(
+            restarted.load_group_state(&group_id, 5).await.unwrap(),
+            None
+        );
+        let restarted = persisting_aggregator(temp_dir.path());
+        assert_eq!(
+            restarted.load_group_state(&group_id, 4).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clear_group_state_removes_persisted_state() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let group_id = GroupId::from_slice(&[2; 32]);
+        let aggregator = persisting_aggregator(temp_dir.path());
+
+        aggregator
+            .persist_group_state(&group_id, 1, vec![state_test_message()])
+            .await
+            .unwrap();
+        aggregator.clear_group_state(&group_id).await.unwrap();
+
+        assert_eq!(
+            aggregator.load_group_state(&group_id, 1).await.unwrap(),
+            None
+        );
+        let restarted = persisting_aggregator(temp_dir.path());
+        assert_eq!(
+            restarted.load_group_state(&group_id, 1).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_group_state_not_persisted_when_disabled() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let group_id = GroupId::from_slice(&[3; 32]);
+        let aggregator = MessageAggregator::new().with_data_dir(temp_dir.path());
+
+        aggregator
+            .persist_group_state(&group_id, 1, vec![state_test_message()])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            aggregator.load_group_state(&group_id, 1).await.unwrap(),
+            None
+        );
+        assert!(!temp_dir.path().join("message_cache").exists());
+    }
 }
@@ -0,0 +1,179 @@
+//! Thread tree construction
+//!
+//! Turns the flat, chronologically ordered list of aggregated messages into a forest of
+//! reply threads so clients can render conversations without re-deriving the structure.
+
+use std::collections::{HashMap, HashSet};
+
+use super::types::{ChatMessage, ThreadNode};
+
+/// Build a forest of reply threads from aggregated messages.
+///
+/// Top-level messages become roots and replies are nested under their parent, both in
+/// chronological order. Replies whose parent is deleted or not in `messages` are collected
+/// under a single synthetic orphans root (see [`ThreadNode::is_orphans_root`]), which is
+/// always placed last. Deleted messages are not rendered as nodes themselves.
+pub(crate) fn build_thread_tree(messages: Vec<ChatMessage>) -> Vec<ThreadNode> {
+    let mut messages = messages;
+    messages.sort_by(ChatMessage::cmp_chronological);
+
+    let live_ids: HashSet<String> = messages
+        .iter()
+        .filter(|m| !m.is_deleted)
+        .map(|m| m.id.clone())
+        .collect();
+
+    let mut roots = Vec::new();
+    let mut orphans = Vec::new();
+    let mut children: HashMap<String, Vec<ChatMessage>> = HashMap::new();
+
+    for message in messages.into_iter().filter(|m| !m.is_deleted) {
+        match message.reply_to_id.clone() {
+            None => roots.push(message),
+            Some(parent_id) if live_ids.contains(&parent_id) && parent_id != message.id => {
+                children.entry(parent_id).or_default().push(message);
+            }
+            Some(_) => orphans.push(message),
+        }
+    }
+
+    let mut forest: Vec<ThreadNode> = roots
+        .into_iter()
+        .map(|message| attach_replies(message, &mut children))
+        .collect();
+
+    let mut orphan_nodes: Vec<ThreadNode> = orphans
+        .into_iter()
+        .map(|message| attach_replies(message, &mut children))
+        .collect();
+
+    // Anything left is part of a reply cycle that never reaches a root
+    let mut unreachable: Vec<ChatMessage> = children.into_values().flatten().collect();
+    unreachable.sort_by(ChatMessage::cmp_chronological);
+    orphan_nodes.extend(unreachable.into_iter().map(ThreadNode::leaf));
+
+    if !orphan_nodes.is_empty() {
+        forest.push(ThreadNode {
+            message: None,
+            replies: orphan_nodes,
+        });
+    }
+
+    forest
+}
+
+/// Recursively move the replies for `message` out of `children` and nest them.
+fn attach_replies(
+    message: ChatMessage,
+    children: &mut HashMap<String, Vec<ChatMessage>>,
+) -> ThreadNode {
+    let replies = children
+        .remove(&message.id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|reply| attach_replies(reply, children))
+        .collect();
+
+    ThreadNode {
+        message: Some(message),
+        replies,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, reply_to: Option<&str>, created_at: u64) -> ChatMessage {
+        let message = ChatMessage::test_message(id, &format!("content of {}", id), created_at);
+        match reply_to {
+            Some(parent_id) => message.with_reply_to(parent_id),
+            None => message,
+        }
+    }
+
+    fn ids(nodes: &[ThreadNode]) -> Vec<&str> {
+        nodes
+            .iter()
+            .map(|n| {
+                n.message
+                    .as_ref()
+                    .map(|m| m.id.as_str())
+                    .unwrap_or("<orphans>")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_build_thread_tree_empty() {
+        assert!(build_thread_tree(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_build_thread_tree_multi_branch() {
+        // a
+        // ├── b
+        // │   ├── d
+        // │   └── e
+        // └── c
+        //     └── f
+        // g
+        let messages = vec![
+            message("f", Some("c"), 6),
+            message("a", None, 1),
+            message("b", Some("a"), 2),
+            message("c", Some("a"), 3),
+            message("d", Some("b"), 4),
+            message("e", Some("b"), 5),
+            message("g", None, 7),
+        ];
+
+        let forest = build_thread_tree(messages);
+
+        assert_eq!(ids(&forest), vec!["a", "g"]);
+        let a = &forest[0];
+        assert_eq!(ids(&a.replies), vec!["b", "c"]);
+        assert_eq!(ids(&a.replies[0].replies), vec!["d", "e"]);
+        assert_eq!(ids(&a.replies[1].replies), vec!["f"]);
+        assert!(a.replies[0].replies[0].replies.is_empty());
+        assert!(forest[1].replies.is_empty());
+        assert_eq!(a.message_count(), 6);
+    }
+
+    #[test]
+    fn test_build_thread_tree_missing_and_deleted_parents_become_orphans() {
+        let mut deleted = message("deleted", None, 1);
+        deleted.is_deleted = true;
+        deleted.content = String::new();
+
+        let messages = vec![
+            deleted,
+            message("root", None, 2),
+            message("reply_to_deleted", Some("deleted"), 3),
+            message("reply_to_missing", Some("missing"), 4),
+            message("nested", Some("reply_to_missing"), 5),
+        ];
+
+        let forest = build_thread_tree(messages);
+
+        assert_eq!(ids(&forest), vec!["root", "<orphans>"]);
+        let orphans = forest.last().unwrap();
+        assert!(orphans.is_orphans_root());
+        assert_eq!(
+            ids(&orphans.replies),
+            vec!["reply_to_deleted", "reply_to_missing"]
+        );
+        assert_eq!(ids(&orphans.replies[1].replies), vec!["nested"]);
+    }
+
+    #[test]
+    fn test_build_thread_tree_reply_cycle_is_not_lost() {
+        let messages = vec![message("x", Some("y"), 1), message("y", Some("x"), 2)];
+
+        let forest = build_thread_tree(messages);
+
+        assert_eq!(forest.len(), 1);
+        assert!(forest[0].is_orphans_root());
+        assert_eq!(forest[0].message_count(), 2);
+    }
+}
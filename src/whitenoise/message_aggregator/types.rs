@@ -1,3 +1,4 @@
+use mdk_core::prelude::GroupId;
 use nostr_sdk::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -29,12 +30,38 @@ pub struct ChatMessage {
     /// ID of the message this is replying to (if is_reply is true)
     pub reply_to_id: Option<String>,
 
+    /// The message this is replying to, with a preview when it was aggregated in the same batch
+    #[serde(default)]
+    pub reply_to: Option<ChatMessageRef>,
+
+    /// The message this quotes (NIP-18 `q` tag), independent of the message it replies to
+    ///
+    /// The preview is the quoted message's current content when it was aggregated in the same
+    /// batch, and otherwise the preview taken when quoting, so quotes of deleted messages
+    /// still read.
+    #[serde(default)]
+    pub quoted: Option<ChatMessageRef>,
+
     /// Whether this message has been deleted
     pub is_deleted: bool,
 
+    /// When the author last edited the message (`None` if it was never edited)
+    ///
+    /// `content` and `content_tokens` hold the latest edit.
+    #[serde(default)]
+    pub edited_at: Option<Timestamp>,
+
+    /// Whether the author is muted by the account (only set with [`MutedAuthors::Mark`])
+    #[serde(default)]
+    pub is_muted: bool,
+
     /// Parsed tokens from the message content (mentions, hashtags, etc.)
     pub content_tokens: Vec<SerializableToken>,
 
+    /// Users mentioned in the content with `nostr:npub…` or `nostr:nprofile…`, in order
+    #[serde(default)]
+    pub mentions: Vec<Mention>,
+
     /// Aggregated reactions on this message
     pub reactions: ReactionSummary,
 
@@ -43,6 +70,232 @@ pub struct ChatMessage {
 
     /// Media files attached to this message
     pub media_attachments: Vec<MediaFile>,
+
+    /// Whether the message reached the group's relays, for messages the account sent
+    #[serde(default)]
+    pub delivery_status: DeliveryStatus,
+}
+
+impl ChatMessage {
+    /// The order aggregated messages are listed in: oldest first, and messages created in the
+    /// same second by event ID, so the order is the same every time they are aggregated
+    pub(crate) fn cmp_chronological(&self, other: &Self) -> std::cmp::Ordering {
+        self.created_at
+            .cmp(&other.created_at)
+            .then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+#[cfg(test)]
+impl ChatMessage {
+    /// A kind 9 message from a random author, with `content` as its only text token
+    ///
+    /// Shared by the aggregator's tests; adjust it with the `with_*` methods or by setting
+    /// fields directly.
+    pub(crate) fn test_message(id: &str, content: &str, created_at: u64) -> Self {
+        Self {
+            id: id.to_string(),
+            author: Keys::generate().public_key(),
+            content: content.to_string(),
+            created_at: Timestamp::from(created_at),
+            tags: Tags::new(),
+            is_reply: false,
+            reply_to_id: None,
+            reply_to: None,
+            quoted: None,
+            is_deleted: false,
+            edited_at: None,
+            is_muted: false,
+            content_tokens: vec![SerializableToken::Text(content.to_string())],
+            mentions: vec![],
+            reactions: ReactionSummary::default(),
+            kind: 9,
+            media_attachments: vec![],
+            delivery_status: DeliveryStatus::default(),
+        }
+    }
+
+    /// The message, sent by `author`
+    pub(crate) fn with_author(mut self, author: PublicKey) -> Self {
+        self.author = author;
+        self
+    }
+
+    /// The message, as a reply to the message with ID `parent_id`
+    pub(crate) fn with_reply_to(mut self, parent_id: &str) -> Self {
+        self.is_reply = true;
+        self.reply_to_id = Some(parent_id.to_string());
+        self.reply_to = Some(ChatMessageRef::unresolved(parent_id.to_string()));
+        self
+    }
+}
+
+/// Delivery of a message to the group's relays
+///
+/// Only messages sent by the account go through `Pending`; messages received from
+/// other members are always `Sent`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    /// The message was created locally and is being published
+    Pending,
+
+    /// The message is on the relays
+    ///
+    /// `relays` is the number of relays that accepted it when the account published it,
+    /// and 0 for messages received from relays.
+    Sent { relays: usize },
+
+    /// No relay accepted the message
+    Failed,
+}
+
+impl Default for DeliveryStatus {
+    fn default() -> Self {
+        Self::Sent { relays: 0 }
+    }
+}
+
+/// One version of an edited message, as returned by the edit history API
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MessageEdit {
+    /// ID of the event carrying this version (the original message or an edit)
+    pub id: String,
+
+    /// Content of the message in this version
+    pub content: String,
+
+    /// When this version was created
+    pub created_at: Timestamp,
+}
+
+/// Reference from a reply or quote to the message it refers to
+///
+/// The id is always set so the target can be fetched lazily when it wasn't loaded.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChatMessageRef {
+    /// ID of the referenced message
+    pub id: String,
+
+    /// Start of the referenced message's content (`None` if it wasn't loaded or was deleted)
+    pub preview: Option<String>,
+}
+
+impl ChatMessageRef {
+    pub(crate) fn unresolved(id: String) -> Self {
+        Self { id, preview: None }
+    }
+}
+
+/// A user mentioned in a message's content
+///
+/// `start..end` is the byte range of the `nostr:` URI in the content. `display_name` comes
+/// from the locally cached profile and is `None` while the user's metadata is unknown, in
+/// which case the UI can show the pubkey and fetch the profile.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Mention {
+    /// The mentioned user
+    pub pubkey: PublicKey,
+
+    /// Display name or name from the user's cached profile
+    pub display_name: Option<String>,
+
+    /// Byte offset of the start of the mention in the content
+    pub start: usize,
+
+    /// Byte offset just past the end of the mention in the content
+    pub end: usize,
+}
+
+/// A message and its nested replies, as returned by the thread tree API
+///
+/// The synthetic orphans root has no message of its own and collects replies whose
+/// parent was deleted or is not available locally.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ThreadNode {
+    /// The message at this node (`None` for the synthetic orphans root)
+    pub message: Option<ChatMessage>,
+
+    /// Direct replies to this message, in chronological order
+    pub replies: Vec<ThreadNode>,
+}
+
+impl ThreadNode {
+    pub(crate) fn leaf(message: ChatMessage) -> Self {
+        Self {
+            message: Some(message),
+            replies: Vec::new(),
+        }
+    }
+
+    /// Whether this is the synthetic root holding replies to deleted or missing messages
+    pub fn is_orphans_root(&self) -> bool {
+        self.message.is_none()
+    }
+
+    /// Number of messages in this subtree, not counting the synthetic orphans root
+    pub fn message_count(&self) -> usize {
+        usize::from(self.message.is_some())
+            + self
+                .replies
+                .iter()
+                .map(ThreadNode::message_count)
+                .sum::<usize>()
+    }
+}
+
+/// Per-group activity since a point in time, for digests and badge counts
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GroupActivity {
+    /// The group this activity belongs to
+    pub group_id: GroupId,
+
+    /// Number of new messages from other members
+    pub new_messages: usize,
+
+    /// Number of new messages that mention the account
+    pub mentions: usize,
+
+    /// Number of reactions other members added to the account's messages
+    pub reactions_to_own_messages: usize,
+
+    /// Number of messages from other members after the account's read position in the group,
+    /// regardless of the point in time the activity was summarized since
+    pub unread_messages: usize,
+
+    /// When the newest message in the group was sent, or `None` if there are none
+    pub last_activity_at: Option<Timestamp>,
+}
+
+impl GroupActivity {
+    /// No activity in the group
+    pub(crate) fn new(group_id: GroupId) -> Self {
+        Self {
+            group_id,
+            new_messages: 0,
+            mentions: 0,
+            reactions_to_own_messages: 0,
+            unread_messages: 0,
+            last_activity_at: None,
+        }
+    }
+
+    /// Whether there was no activity at all since the point in time summarized from
+    pub fn is_empty(&self) -> bool {
+        self.new_messages == 0 && self.mentions == 0 && self.reactions_to_own_messages == 0
+    }
+}
+
+/// A message matching a search query, with the location of the match for highlighting
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MessageSearchResult {
+    /// The matching message
+    pub message: ChatMessage,
+
+    /// Byte offset of the first match in `message.content`
+    pub match_offset: usize,
+
+    /// Byte length of the match in `message.content`
+    pub match_len: usize,
 }
 
 /// Summary of reactions on a message
@@ -53,6 +306,30 @@ pub struct ReactionSummary {
 
     /// List of all users who have reacted and with what
     pub user_reactions: Vec<UserReaction>,
+
+    /// Number of reactions on emoji left out of `by_emoji` by
+    /// [`AggregatorConfig::max_emoji_per_message`]
+    #[serde(default)]
+    pub more_count: usize,
+}
+
+impl ReactionSummary {
+    /// The emoji reactions, most used first (ties ordered by emoji)
+    pub fn ranked(&self) -> Vec<&EmojiReaction> {
+        let mut ranked: Vec<&EmojiReaction> = self.by_emoji.values().collect();
+        ranked.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.emoji.cmp(&b.emoji)));
+        ranked
+    }
+}
+
+/// What [`Whitenoise::toggle_reaction`](crate::Whitenoise::toggle_reaction) did
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ReactionAction {
+    /// A reaction was published
+    Added,
+
+    /// A deletion of the account's previous reaction was published
+    Removed,
 }
 
 /// Details for a specific emoji reaction
@@ -65,9 +342,18 @@ pub struct EmojiReaction {
     pub count: usize,
 
     /// List of users who used this reaction
+    ///
+    /// Only the earliest reactors when [`AggregatorConfig::max_reactors_per_emoji`] is set.
     pub users: Vec<PublicKey>,
 }
 
+impl EmojiReaction {
+    /// Number of reactors counted but left out of [`EmojiReaction::users`]
+    pub fn others_count(&self) -> usize {
+        self.count.saturating_sub(self.users.len())
+    }
+}
+
 /// Individual user's reaction
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct UserReaction {
@@ -89,6 +375,49 @@ pub struct AggregatorConfig {
 
     /// Whether to enable detailed logging of processing steps
     pub enable_debug_logging: bool,
+
+    /// Whether aggregated group state may be persisted to disk and reloaded
+    ///
+    /// When set, fetching a group's messages reuses the state persisted at the group's current
+    /// epoch. The state is dropped whenever the group's message cache changes.
+    pub persist_state: bool,
+
+    /// What to do with messages from authors the account has muted
+    pub muted_authors: MutedAuthors,
+
+    /// Reactions to treat as another emoji, keyed by reaction content
+    ///
+    /// Consulted for the reaction as sent and, when `normalize_emoji` is set, for its
+    /// normalized form. Keys don't need to be emoji, so `:custom:` shortcodes can map to a
+    /// canonical emoji. Empty by default.
+    pub emoji_equivalents: HashMap<String, String>,
+
+    /// How many reactors to list per emoji when messages are fetched (`None` lists all)
+    ///
+    /// The earliest reactors are kept and [`EmojiReaction::count`] stays accurate, so the UI
+    /// can show "and N others" and load the rest with
+    /// [`Whitenoise::reaction_details`](crate::Whitenoise::reaction_details).
+    pub max_reactors_per_emoji: Option<usize>,
+
+    /// How many distinct emoji to list per message when messages are fetched (`None` lists all)
+    ///
+    /// The most used emoji are kept, plus the one the viewer reacted with, and the reactions
+    /// on the rest are counted in [`ReactionSummary::more_count`]. The full summary is
+    /// available from [`Whitenoise::message_reactions`](crate::Whitenoise::message_reactions).
+    pub max_emoji_per_message: Option<usize>,
+}
+
+/// How messages from muted authors are aggregated
+///
+/// Reactions from muted authors are dropped either way.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum MutedAuthors {
+    /// Leave their messages out
+    #[default]
+    Hide,
+
+    /// Keep their messages with [`ChatMessage::is_muted`] set, so they can be collapsed
+    Mark,
 }
 
 impl Default for AggregatorConfig {
@@ -96,6 +425,11 @@ impl Default for AggregatorConfig {
         Self {
             normalize_emoji: true,
             enable_debug_logging: false,
+            persist_state: false,
+            muted_authors: MutedAuthors::default(),
+            emoji_equivalents: HashMap::new(),
+            max_reactors_per_emoji: None,
+            max_emoji_per_message: None,
         }
     }
 }
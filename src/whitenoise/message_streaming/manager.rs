@@ -3,22 +3,92 @@
 //! Manages broadcast channels for real-time message updates, with lazy stream
 //! creation and automatic cleanup when all receivers are dropped.
 
+use std::sync::Arc;
+use std::time::Duration;
+
 use dashmap::DashMap;
 use mdk_core::prelude::GroupId;
+use nostr_sdk::{PublicKey, Timestamp};
 use tokio::sync::broadcast;
 
-use super::types::MessageUpdate;
+use super::types::{MessageUpdate, UpdateTrigger};
+use crate::whitenoise::message_aggregator::ChatMessage;
 
 const BUFFER_SIZE: usize = 100;
 
+type Streams = DashMap<GroupId, broadcast::Sender<MessageUpdate>>;
+
+/// Latest typing indicator per group member that is still typing
+type Typing = DashMap<(GroupId, PublicKey), ChatMessage>;
+
+/// Clones share the same streams, so updates can be emitted from background tasks
+#[derive(Clone)]
 pub struct MessageStreamManager {
-    streams: DashMap<GroupId, broadcast::Sender<MessageUpdate>>,
+    streams: Arc<Streams>,
+    typing: Arc<Typing>,
+}
+
+/// Removes a group's stream once its last subscriber goes away.
+///
+/// Held by [`super::GroupMessageSubscription`] so dropping a subscription releases the
+/// sender registration right away instead of waiting for the next emit.
+pub(crate) struct StreamRegistration {
+    streams: Arc<Streams>,
+    group_id: GroupId,
+}
+
+impl Drop for StreamRegistration {
+    fn drop(&mut self) {
+        remove_if_unused(&self.streams, &self.group_id);
+    }
+}
+
+fn emit_to(streams: &Streams, group_id: &GroupId, update: MessageUpdate) {
+    if let Some(sender) = streams.get(group_id)
+        && sender.send(update).is_err()
+    {
+        drop(sender);
+        remove_if_unused(streams, group_id);
+    }
+}
+
+fn typing_stopped(indicator: ChatMessage) -> MessageUpdate {
+    MessageUpdate {
+        trigger: UpdateTrigger::TypingStopped {
+            pubkey: indicator.author,
+        },
+        message: indicator,
+        notify: false,
+    }
+}
+
+fn remove_if_unused(streams: &Streams, group_id: &GroupId) {
+    // Atomically check and remove to avoid race with concurrent subscribe()
+    if streams
+        .remove_if(group_id, |_, s| s.receiver_count() == 0)
+        .is_some()
+    {
+        tracing::debug!(
+            target: "whitenoise::message_streaming",
+            "Cleaned up stream for group {} (no active receivers)",
+            hex::encode(group_id.as_slice()),
+        );
+    }
 }
 
 impl MessageStreamManager {
     pub fn new() -> Self {
         Self {
-            streams: DashMap::new(),
+            streams: Arc::new(DashMap::new()),
+            typing: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Registration guard that cleans up the group's stream when dropped, if unused
+    pub(crate) fn registration(&self, group_id: &GroupId) -> StreamRegistration {
+        StreamRegistration {
+            streams: Arc::clone(&self.streams),
+            group_id: group_id.clone(),
         }
     }
 
@@ -30,22 +100,52 @@ impl MessageStreamManager {
     }
 
     pub fn emit(&self, group_id: &GroupId, update: MessageUpdate) {
-        if let Some(sender) = self.streams.get(group_id)
-            && sender.send(update).is_err()
-        {
-            drop(sender);
-            // Atomically check and remove to avoid race with concurrent subscribe()
-            if self
-                .streams
-                .remove_if(group_id, |_, s| s.receiver_count() == 0)
-                .is_some()
+        emit_to(&self.streams, group_id, update);
+    }
+
+    /// Emit a typing update, followed by a `TypingStopped` update once it expires
+    ///
+    /// A newer indicator from the same author restarts the expiry.
+    pub(crate) fn emit_typing(
+        &self,
+        group_id: &GroupId,
+        indicator: ChatMessage,
+        expires_at: Timestamp,
+    ) {
+        let key = (group_id.clone(), indicator.author);
+        let indicator_id = indicator.id.clone();
+        self.typing.insert(key.clone(), indicator.clone());
+        self.emit(
+            group_id,
+            MessageUpdate {
+                trigger: UpdateTrigger::Typing {
+                    pubkey: indicator.author,
+                },
+                message: indicator,
+                notify: false,
+            },
+        );
+
+        let streams = Arc::clone(&self.streams);
+        let typing = Arc::clone(&self.typing);
+        let ttl = expires_at
+            .as_u64()
+            .saturating_sub(Timestamp::now().as_u64());
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(ttl)).await;
+            // Skip if a newer indicator or the author's message took over in the meantime
+            if let Some((_, indicator)) =
+                typing.remove_if(&key, |_, current| current.id == indicator_id)
             {
-                tracing::debug!(
-                    target: "whitenoise::message_streaming",
-                    "Cleaned up stream for group {} (no active receivers)",
-                    hex::encode(group_id.as_slice()),
-                );
+                emit_to(&streams, &key.0, typing_stopped(indicator));
             }
+        });
+    }
+
+    /// End the author's typing indicator in the group, if there is one
+    pub(crate) fn clear_typing(&self, group_id: &GroupId, author: &PublicKey) {
+        if let Some((_, indicator)) = self.typing.remove(&(group_id.clone(), *author)) {
+            self.emit(group_id, typing_stopped(indicator));
         }
     }
 }
@@ -76,11 +176,17 @@ mod tests {
             tags: Tags::new(),
             is_reply: false,
             reply_to_id: None,
+            reply_to: None,
+            quoted: None,
             is_deleted: false,
+            edited_at: None,
+            is_muted: false,
             content_tokens: vec![],
+            mentions: vec![],
             reactions: ReactionSummary::default(),
             kind: 9,
             media_attachments: vec![],
+            delivery_status: Default::default(),
         }
     }
 
@@ -88,6 +194,7 @@ mod tests {
         MessageUpdate {
             trigger,
             message: make_test_message(id),
+            notify: false,
         }
     }
 
@@ -182,6 +289,65 @@ mod tests {
         assert!(manager.streams.contains_key(&group2));
     }
 
+    #[test]
+    fn dropping_registration_after_receiver_cleans_up() {
+        let manager = MessageStreamManager::new();
+        let group_id = make_test_group_id(9);
+
+        let rx = manager.subscribe(&group_id);
+        let registration = manager.registration(&group_id);
+
+        drop(rx);
+        drop(registration);
+
+        assert!(!manager.streams.contains_key(&group_id));
+    }
+
+    #[test]
+    fn dropping_registration_keeps_stream_with_live_receivers() {
+        let manager = MessageStreamManager::new();
+        let group_id = make_test_group_id(10);
+
+        let _rx = manager.subscribe(&group_id);
+        drop(manager.registration(&group_id));
+
+        assert!(manager.streams.contains_key(&group_id));
+    }
+
+    #[tokio::test]
+    async fn typing_indicator_expires_unless_cleared() {
+        let manager = MessageStreamManager::new();
+        let group_id = make_test_group_id(11);
+        let mut rx = manager.subscribe(&group_id);
+
+        let indicator = make_test_message("typing1");
+        let author = indicator.author;
+        manager.emit_typing(&group_id, indicator, Timestamp::now() + 1);
+        let received = rx.recv().await.unwrap();
+        assert_eq!(received.trigger, UpdateTrigger::Typing { pubkey: author });
+
+        let received = rx.recv().await.unwrap();
+        assert_eq!(
+            received.trigger,
+            UpdateTrigger::TypingStopped { pubkey: author }
+        );
+        assert!(manager.typing.is_empty());
+
+        // A message from the author ends the indicator right away, and only once
+        let indicator = make_test_message("typing2");
+        let author = indicator.author;
+        manager.emit_typing(&group_id, indicator, Timestamp::now() + 5);
+        rx.recv().await.unwrap();
+        manager.clear_typing(&group_id, &author);
+        let received = rx.recv().await.unwrap();
+        assert_eq!(
+            received.trigger,
+            UpdateTrigger::TypingStopped { pubkey: author }
+        );
+        assert!(manager.typing.is_empty());
+        assert!(rx.try_recv().is_err());
+    }
+
     #[test]
     fn default_creates_empty_manager() {
         let manager = MessageStreamManager::default();
@@ -3,15 +3,18 @@
 //! These types enable real-time message updates to be pushed to subscribers
 //! as events are processed, without requiring polling.
 
+use nostr_sdk::PublicKey;
 use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
 
+use super::manager::StreamRegistration;
 use crate::whitenoise::message_aggregator::ChatMessage;
 
 /// What triggered a message update.
 ///
 /// The accompanying `message` field in [`MessageUpdate`] always contains
-/// the complete, up-to-date state of the affected message.
+/// the complete, up-to-date state of the affected message, except for the
+/// typing triggers, where it is the typing indicator itself.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum UpdateTrigger {
     /// A new message was added to the group.
@@ -25,13 +28,38 @@ pub enum UpdateTrigger {
 
     /// The message itself was marked as deleted.
     MessageDeleted,
+
+    /// The author edited the message; it carries the latest content.
+    MessageEdited,
+
+    /// The relays answered for a message the account sent.
+    DeliveryStatusChanged,
+
+    /// A member started typing.
+    ///
+    /// Typing indicators are never part of the message history.
+    Typing { pubkey: PublicKey },
+
+    /// A member's typing indicator expired, or their message arrived.
+    TypingStopped { pubkey: PublicKey },
+}
+
+impl UpdateTrigger {
+    /// Whether this is a typing trigger rather than a change to the message history
+    pub fn is_typing(&self) -> bool {
+        matches!(
+            self,
+            UpdateTrigger::Typing { .. } | UpdateTrigger::TypingStopped { .. }
+        )
+    }
 }
 
 /// Represents a single update to be sent to subscribers.
 ///
 /// Always contains the complete, current state of the affected message.
 /// The `message` field is always the BASE message (kind 9), never a reaction
-/// or deletion event directly.
+/// or deletion event directly. Typing updates are the exception: they carry the
+/// typing indicator, which has empty content and is not cached.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessageUpdate {
     /// What triggered this update.
@@ -39,6 +67,15 @@ pub struct MessageUpdate {
 
     /// The complete, current state of the affected message.
     pub message: ChatMessage,
+
+    /// Whether the update should raise a notification.
+    ///
+    /// Only set for new messages from other members that the account's notification level
+    /// for the group lets through (see [`Whitenoise::set_group_notifications`]).
+    ///
+    /// [`Whitenoise::set_group_notifications`]: crate::Whitenoise::set_group_notifications
+    #[serde(default)]
+    pub notify: bool,
 }
 
 /// Result of subscribing to group messages.
@@ -46,12 +83,18 @@ pub struct MessageUpdate {
 /// Contains both the initial snapshot and a receiver for real-time updates.
 /// The initial snapshot is already deduplicated with any updates that arrived
 /// during the fetch operation, ensuring no race conditions.
+///
+/// Dropping the subscription unregisters the group's stream once no other
+/// subscriber is listening.
 pub struct GroupMessageSubscription {
     /// All current messages in the group at subscription time.
     pub initial_messages: Vec<ChatMessage>,
 
     /// Receiver for real-time updates after the initial snapshot.
     pub updates: broadcast::Receiver<MessageUpdate>,
+
+    // Declared after `updates` so the receiver is dropped before the cleanup check runs
+    pub(crate) _registration: StreamRegistration,
 }
 
 #[cfg(test)]
@@ -75,6 +118,11 @@ mod tests {
             UpdateTrigger::ReactionAdded,
             UpdateTrigger::ReactionRemoved,
             UpdateTrigger::MessageDeleted,
+            UpdateTrigger::MessageEdited,
+            UpdateTrigger::DeliveryStatusChanged,
+            UpdateTrigger::Typing {
+                pubkey: nostr_sdk::Keys::generate().public_key(),
+            },
         ];
 
         for trigger in triggers {
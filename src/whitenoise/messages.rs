@@ -4,13 +4,23 @@ use crate::{
         Whitenoise,
         accounts::Account,
         aggregated_message::AggregatedMessage,
+        database::{drafts::Drafts, group_read_state::GroupReadState, outbox::Outbox},
         error::{Result, WhitenoiseError},
+        group_information::GroupInformation,
         media_files::MediaFile,
-        message_aggregator::ChatMessage,
+        message_aggregator::{
+            ChatMessage, DeliveryStatus, GroupActivity, MESSAGE_EDIT_KIND, MessageEdit,
+            MessageSearchResult, MutedAuthors, ReactionAction, ReactionSummary, ThreadNode,
+            UserReaction, edit_handler, emoji_utils, search, threads,
+        },
+        message_streaming::{MessageUpdate, UpdateTrigger},
+        users::{User, profile_name},
+        utils::preview_text,
     },
 };
 use mdk_core::prelude::{message_types::Message, *};
 use nostr_sdk::prelude::*;
+use std::collections::{HashMap, HashSet};
 
 impl Whitenoise {
     /// Sends a message to a specific group and returns the message with parsed tokens.
@@ -30,6 +40,16 @@ impl Whitenoise {
     ///   (e.g., text note, reaction, etc.).
     /// * `tags` - Optional vector of Nostr tags to include with the message. If None, an empty
     ///   tag list will be used.
+    ///
+    /// Chat messages (kind 9) are streamed to subscribers right away with a
+    /// [`DeliveryStatus::Pending`] status, which is updated once the relays have answered. The
+    /// status is recorded and carried over to the message once it is cached.
+    /// Sending one also removes the account's draft for the group, see [`Self::save_draft`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::SlowModeActive`] with the remaining cooldown if the group has
+    /// slow mode enabled and the account sent a chat message (kind 9) too recently.
     pub async fn send_message_to_group(
         &self,
         account: &Account,
@@ -38,6 +58,14 @@ impl Whitenoise {
         kind: u16,
         tags: Option<Vec<Tag>>,
     ) -> Result<MessageWithTokens> {
+        if kind == 9
+            && let Some(remaining) = self.group_slow_mode_cooldown(account, group_id).await?
+        {
+            return Err(WhitenoiseError::SlowModeActive {
+                remaining_secs: remaining.as_secs(),
+            });
+        }
+
         let (inner_event, event_id) =
             self.create_unsigned_nostr_event(&account.pubkey, &message, kind, tags)?;
 
@@ -48,20 +76,424 @@ impl Whitenoise {
             .ok_or(WhitenoiseError::MdkCoreError(
                 mdk_core::error::Error::MessageNotFound,
             ))?;
-        let group_relays = mdk.get_relays(group_id)?;
+        let group_relays = mdk.get_relays(group_id)?.into_iter().collect::<Vec<_>>();
 
         // Publish message in background without blocking
-        self.nostr.background_publish_event_to(
-            message_event,
-            account.pubkey,
-            group_relays.into_iter().collect::<Vec<_>>(),
-        );
+        if kind == 9 {
+            self.publish_chat_message(account, group_id, &message, message_event, group_relays)
+                .await?;
+            // The message is cached and queued for delivery, so what the user typed is sent.
+            // Failing here must not report the sent message as failed.
+            if let Some(account_id) = account.id
+                && let Err(e) = Drafts::delete(account_id, group_id, &self.database).await
+            {
+                tracing::warn!(
+                    target: "whitenoise::messages::send_message_to_group",
+                    "Failed to remove draft for group {} after sending: {}",
+                    hex::encode(group_id.as_slice()),
+                    e
+                );
+            }
+        } else {
+            self.nostr
+                .background_publish_event_to(message_event, account.pubkey, group_relays);
+        }
 
         let tokens = self.nostr.parse(&message.content);
 
         Ok(MessageWithTokens::new(message, tokens))
     }
 
+    /// Record a chat message the account sent as pending and publish it in the background
+    ///
+    /// Subscribers get the pending message right away, followed by a
+    /// [`UpdateTrigger::DeliveryStatusChanged`] update once the relays have answered. A message
+    /// no relay accepted is queued in the account's outbox for [`Whitenoise::retry_outbox`].
+    async fn publish_chat_message(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        message: &Message,
+        event: Event,
+        relays: Vec<RelayUrl>,
+    ) -> Result<()> {
+        let media_files = MediaFile::find_by_group(&self.database, group_id).await?;
+        let mut chat_message = self
+            .message_aggregator
+            .process_single_message(message, &self.nostr, media_files)
+            .await?;
+        chat_message.delivery_status = DeliveryStatus::Pending;
+        AggregatedMessage::record_pending_delivery(&chat_message, group_id, &self.database).await?;
+        self.message_stream_manager.emit(
+            group_id,
+            MessageUpdate {
+                trigger: UpdateTrigger::NewMessage,
+                message: chat_message.clone(),
+                notify: false,
+            },
+        );
+
+        let nostr = self.nostr.clone();
+        let database = self.database.clone();
+        let streams = self.message_stream_manager.clone();
+        let message_aggregator = self.message_aggregator.clone();
+        let account_pubkey = account.pubkey;
+        let account_id = account.id;
+        let inner_event = message.event.clone();
+        let group_id = group_id.clone();
+        tokio::spawn(async move {
+            chat_message.delivery_status = match nostr
+                .publish_event_to(event, &account_pubkey, &relays)
+                .await
+            {
+                Ok(outcome) if outcome.is_success() => DeliveryStatus::Sent {
+                    relays: outcome.accepted().count(),
+                },
+                Ok(_) => DeliveryStatus::Failed,
+                Err(e) => {
+                    tracing::error!(
+                        target: "whitenoise::messages::publish_chat_message",
+                        "Failed to publish message {}: {}",
+                        chat_message.id,
+                        e
+                    );
+                    DeliveryStatus::Failed
+                }
+            };
+
+            if chat_message.delivery_status == DeliveryStatus::Failed
+                && let Some(account_id) = account_id
+                && let Err(e) = Outbox::add(account_id, &group_id, &inner_event, &database).await
+            {
+                tracing::error!(
+                    target: "whitenoise::messages::publish_chat_message",
+                    "Failed to queue message {} in the outbox: {}",
+                    chat_message.id,
+                    e
+                );
+            }
+
+            if let Err(e) = AggregatedMessage::update_delivery_status(
+                &chat_message.id,
+                &group_id,
+                chat_message.delivery_status,
+                &database,
+            )
+            .await
+            {
+                tracing::error!(
+                    target: "whitenoise::messages::publish_chat_message",
+                    "Failed to update delivery status of message {}: {}",
+                    chat_message.id,
+                    e
+                );
+            }
+            message_aggregator.invalidate_group_state(&group_id).await;
+            streams.emit(
+                &group_id,
+                MessageUpdate {
+                    trigger: UpdateTrigger::DeliveryStatusChanged,
+                    message: chat_message,
+                    notify: false,
+                },
+            );
+        });
+
+        Ok(())
+    }
+
+    /// Adds the account's reaction to a message, or removes it if it's already there
+    ///
+    /// The account's current reaction is looked up in the message cache. If it is the same
+    /// emoji (compared after normalization when [`AggregatorConfig::normalize_emoji`] is set,
+    /// so skin tone variants count as the same reaction, and after resolving
+    /// [`AggregatorConfig::emoji_equivalents`]), a deletion of the account's reaction
+    /// events with that emoji is published. Otherwise a new reaction is published, which replaces any other
+    /// emoji the account reacted with.
+    ///
+    /// # Arguments
+    /// * `account` - The account reacting
+    /// * `group_id` - The group the message belongs to
+    /// * `target_message_id` - The message to react to
+    /// * `emoji` - The reaction emoji (or `+`/`-`)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::MessageAggregation`] if `emoji` is not a valid reaction.
+    ///
+    /// [`AggregatorConfig::normalize_emoji`]: crate::whitenoise::message_aggregator::AggregatorConfig::normalize_emoji
+    /// [`AggregatorConfig::emoji_equivalents`]: crate::whitenoise::message_aggregator::AggregatorConfig::emoji_equivalents
+    pub async fn toggle_reaction(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        target_message_id: &EventId,
+        emoji: &str,
+    ) -> Result<ReactionAction> {
+        let normalized =
+            emoji_utils::validate_and_normalize_reaction(emoji, self.message_aggregator.config())?;
+        let target_id = target_message_id.to_hex();
+
+        let target = AggregatedMessage::find_by_id(&target_id, group_id, &self.database).await?;
+        let already_reacted = target.as_ref().is_some_and(|target| {
+            target
+                .reactions
+                .user_reactions
+                .iter()
+                .any(|reaction| reaction.user == account.pubkey && reaction.emoji == normalized)
+        });
+
+        if already_reacted {
+            let reactions = AggregatedMessage::find_active_reactions_by_author(
+                &target_id,
+                &account.pubkey,
+                group_id,
+                &self.database,
+            )
+            .await?;
+
+            // Only retract events of the emoji being toggled, not older ones the account
+            // reacted with before
+            let config = self.message_aggregator.config();
+            let matching: Vec<_> = reactions
+                .iter()
+                .filter(|reaction| {
+                    emoji_utils::validate_and_normalize_reaction(&reaction.content, config)
+                        .is_ok_and(|emoji| emoji == normalized)
+                })
+                .collect();
+
+            if !matching.is_empty() {
+                let tags = matching
+                    .iter()
+                    .map(|reaction| Tag::event(reaction.event_id))
+                    .collect();
+                self.send_message_to_group(account, group_id, String::new(), 5, Some(tags))
+                    .await?;
+                return Ok(ReactionAction::Removed);
+            }
+        }
+
+        let mut tags = vec![Tag::event(*target_message_id)];
+        if let Some(target) = &target {
+            tags.push(Tag::public_key(target.author));
+        }
+        self.send_message_to_group(account, group_id, emoji.to_string(), 7, Some(tags))
+            .await?;
+
+        Ok(ReactionAction::Added)
+    }
+
+    /// Edits one of the account's messages
+    ///
+    /// Publishes an edit ([`MESSAGE_EDIT_KIND`]) referencing the original message. Members'
+    /// caches collapse it onto the original, which then shows `new_content` and an
+    /// `edited_at` timestamp. Passing the ID of an earlier edit edits the original message.
+    ///
+    /// # Arguments
+    /// * `account` - The account editing its message
+    /// * `group_id` - The group the message belongs to
+    /// * `target_message_id` - The message to edit
+    /// * `new_content` - The new content of the message
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::MessageNotFound`] if the message isn't cached,
+    /// [`WhitenoiseError::AccountNotAuthorized`] if the account didn't author it, and
+    /// [`WhitenoiseError::MessageDeleted`] if it was deleted.
+    pub async fn edit_message(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        target_message_id: &EventId,
+        new_content: String,
+    ) -> Result<MessageWithTokens> {
+        let target = self
+            .find_edit_root(group_id, target_message_id)
+            .await?
+            .ok_or(WhitenoiseError::MessageNotFound)?;
+        if target.author != account.pubkey {
+            return Err(WhitenoiseError::AccountNotAuthorized);
+        }
+        if target.is_deleted {
+            return Err(WhitenoiseError::MessageDeleted);
+        }
+
+        let tags = vec![Tag::parse(vec!["e", &target.id])?];
+        self.send_message_to_group(
+            account,
+            group_id,
+            new_content,
+            MESSAGE_EDIT_KIND,
+            Some(tags),
+        )
+        .await
+    }
+
+    /// Sends a chat message quoting another message of the group
+    ///
+    /// The quote references the message with a NIP-18 `q` tag and a `nostr:` link appended to
+    /// `content`. The tag also carries a preview of the quoted content, so the quote still
+    /// reads if the original is deleted later; see [`ChatMessage::quoted`].
+    ///
+    /// # Arguments
+    /// * `account` - The account sending the quote
+    /// * `group_id` - The group to send it to
+    /// * `quoted_message_id` - The message to quote
+    /// * `content` - What the account says about the quoted message (may be empty)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::MessageNotFound`] if the quoted message isn't cached and
+    /// [`WhitenoiseError::MessageDeleted`] if it was deleted.
+    pub async fn quote_message(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        quoted_message_id: &EventId,
+        content: String,
+    ) -> Result<MessageWithTokens> {
+        let quoted =
+            AggregatedMessage::find_by_id(&quoted_message_id.to_hex(), group_id, &self.database)
+                .await?
+                .ok_or(WhitenoiseError::MessageNotFound)?;
+        if quoted.is_deleted {
+            return Err(WhitenoiseError::MessageDeleted);
+        }
+
+        let link = format!(
+            "nostr:{}",
+            quoted_message_id
+                .to_bech32()
+                .map_err(|e| WhitenoiseError::Other(e.into()))?
+        );
+        let content = if content.is_empty() {
+            link
+        } else {
+            format!("{}\n\n{}", content, link)
+        };
+
+        // Group messages travel through the group's relays, so there is no relay hint
+        let tags = vec![Tag::parse(vec![
+            "q".to_string(),
+            quoted_message_id.to_hex(),
+            String::new(),
+            quoted.author.to_hex(),
+            preview_text(&quoted.content),
+        ])?];
+        self.send_message_to_group(account, group_id, content, 9, Some(tags))
+            .await
+    }
+
+    /// Everyone who reacted to a message with an emoji, earliest first
+    ///
+    /// Fetched messages list only the first few reactors per emoji when
+    /// [`AggregatorConfig::max_reactors_per_emoji`](crate::whitenoise::message_aggregator::AggregatorConfig::max_reactors_per_emoji)
+    /// is set; this returns the complete list for when the user expands a reaction.
+    ///
+    /// # Arguments
+    /// * `account` - The account viewing the reactions
+    /// * `group_id` - The group the message belongs to
+    /// * `message_id` - The message the reactions are on
+    /// * `emoji` - The reaction, as it appears in the message's reaction summary
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::GroupNotFound`] or [`WhitenoiseError::AccountNotGroupMember`]
+    /// if the account isn't an active member of the group, and
+    /// [`WhitenoiseError::MessageNotFound`] if the message isn't cached.
+    pub async fn reaction_details(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        message_id: &EventId,
+        emoji: &str,
+    ) -> Result<Vec<UserReaction>> {
+        self.ensure_active_member(account, group_id)?;
+
+        let message = AggregatedMessage::find_by_id(&message_id.to_hex(), group_id, &self.database)
+            .await?
+            .ok_or(WhitenoiseError::MessageNotFound)?;
+
+        let mut reactions: Vec<UserReaction> = message
+            .reactions
+            .user_reactions
+            .into_iter()
+            .filter(|reaction| reaction.emoji == emoji)
+            .collect();
+        reactions.sort_by_key(|reaction| reaction.created_at);
+        Ok(reactions)
+    }
+
+    /// Every version of a message, from the original content to the latest edit
+    ///
+    /// Only edits by the message's author are included, in the order they were made.
+    ///
+    /// # Arguments
+    /// * `account` - The account requesting the history
+    /// * `group_id` - The group the message belongs to
+    /// * `message_id` - The message (or one of its edits)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::MessageNotFound`] if the message isn't cached and
+    /// [`WhitenoiseError::MessageDeleted`] if it was deleted.
+    pub async fn message_edit_history(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        message_id: &EventId,
+    ) -> Result<Vec<MessageEdit>> {
+        let message = self
+            .find_edit_root(group_id, message_id)
+            .await?
+            .ok_or(WhitenoiseError::MessageNotFound)?;
+        if message.is_deleted {
+            return Err(WhitenoiseError::MessageDeleted);
+        }
+
+        // The cache holds the latest edit, the original content is kept by MDK
+        let original_id = EventId::from_hex(&message.id)
+            .map_err(|e| WhitenoiseError::InvalidEvent(e.to_string()))?;
+        let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
+        let original = mdk
+            .get_message(&original_id)?
+            .ok_or(WhitenoiseError::MessageNotFound)?;
+
+        let edits =
+            AggregatedMessage::find_edit_chain(&message.id, group_id, &self.database).await?;
+        let history = std::iter::once(MessageEdit {
+            id: message.id.clone(),
+            content: original.content,
+            created_at: original.created_at,
+        })
+        .chain(
+            edits
+                .into_iter()
+                .filter(|edit| edit.author == message.author)
+                .map(|edit| MessageEdit {
+                    id: edit.event_id.to_hex(),
+                    content: edit.content,
+                    created_at: Timestamp::from(edit.created_at.timestamp() as u64),
+                }),
+        )
+        .collect();
+
+        Ok(history)
+    }
+
+    /// The cached message a message ID or edit ID ultimately refers to
+    async fn find_edit_root(
+        &self,
+        group_id: &GroupId,
+        message_id: &EventId,
+    ) -> Result<Option<ChatMessage>> {
+        let root_id =
+            AggregatedMessage::resolve_edit_root(&message_id.to_hex(), group_id, &self.database)
+                .await?;
+        Ok(AggregatedMessage::find_by_id(&root_id, group_id, &self.database).await?)
+    }
+
     /// Fetches all messages for a specific group with parsed tokens.
     ///
     /// This method retrieves all messages that have been sent to a particular group,
@@ -96,6 +528,9 @@ impl Whitenoise {
     /// - Event processor: Caches messages as they arrive (real-time updates)
     /// - Startup sync: Populates cache with existing messages on initialization
     ///
+    /// Messages and reactions from users the account has muted are hidden or marked as
+    /// configured in [`AggregatorConfig::muted_authors`](crate::whitenoise::message_aggregator::AggregatorConfig).
+    ///
     /// # Arguments
     /// * `pubkey` - The public key of the user requesting messages
     /// * `group_id` - The group to fetch messages for
@@ -104,160 +539,610 @@ impl Whitenoise {
         pubkey: &PublicKey,
         group_id: &GroupId,
     ) -> Result<Vec<ChatMessage>> {
-        Account::find_by_pubkey(pubkey, &self.database).await?; // Verify account exists (security check)
+        let account = Account::find_by_pubkey(pubkey, &self.database).await?; // Verify account exists (security check)
 
-        AggregatedMessage::find_messages_by_group(group_id, &self.database)
-            .await
-            .map_err(|e| {
-                WhitenoiseError::from(anyhow::anyhow!("Failed to read cached messages: {}", e))
-            })
+        let mut messages = self.visible_group_messages(&account, group_id).await?;
+        self.shorten_reactions_for_display(&mut messages, &account.pubkey);
+        Ok(messages)
     }
 
-    /// Creates an unsigned nostr event with the given parameters
-    fn create_unsigned_nostr_event(
+    /// A group's cached messages as the account sees them, with complete reactions
+    ///
+    /// Applies the account's mute list and resolves mention names. Features that compute
+    /// from messages (activity, media, search) use this; lists returned for display also go
+    /// through [`Self::shorten_reactions_for_display`].
+    pub(crate) async fn visible_group_messages(
         &self,
-        pubkey: &PublicKey,
-        message: &String,
-        kind: u16,
-        tags: Option<Vec<Tag>>,
-    ) -> Result<(UnsignedEvent, EventId)> {
-        let final_tags = tags.unwrap_or_default();
-
-        let mut inner_event =
-            UnsignedEvent::new(*pubkey, Timestamp::now(), kind.into(), final_tags, message);
-
-        inner_event.ensure_id();
-
-        let event_id = inner_event.id.unwrap(); // This is guaranteed to be Some by ensure_id
+        account: &Account,
+        group_id: &GroupId,
+    ) -> Result<Vec<ChatMessage>> {
+        let messages = self.cached_group_messages(account, group_id).await?;
+        let muted = self.muted_pubkeys(account).await?;
+        let mut messages = self
+            .message_aggregator
+            .apply_muted_authors(messages, &muted);
+        self.resolve_mention_names(&mut messages).await?;
+        Ok(messages)
+    }
 
-        Ok((inner_event, event_id))
+    /// Limit the reactions listed on messages about to be shown, as configured in the
+    /// [`AggregatorConfig`](crate::whitenoise::message_aggregator::AggregatorConfig)
+    fn shorten_reactions_for_display(&self, messages: &mut [ChatMessage], viewer: &PublicKey) {
+        self.message_aggregator.cap_reactors(messages, viewer);
+        self.message_aggregator.cap_emoji(messages, viewer);
     }
 
-    /// Synchronize message cache with MDK on startup
+    /// Read all cached chat messages of a group
     ///
-    /// MUST be called BEFORE event processor starts to avoid race conditions.
-    /// Uses simple count comparison to detect sync needs, then incrementally syncs missing events.
-    pub(crate) async fn sync_message_cache_on_startup(&self) -> Result<()> {
-        tracing::info!(
-            target: "whitenoise::cache",
-            "Starting message cache synchronization..."
-        );
-
-        let mut total_synced = 0;
-        let mut total_groups_checked = 0;
-
-        let accounts = Account::all(&self.database).await?;
-
-        for account in accounts {
-            let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
-            let groups = mdk.get_groups()?;
-
-            for group_info in groups {
-                total_groups_checked += 1;
-
-                let mdk_messages = mdk.get_messages(&group_info.mls_group_id)?;
-
-                if self
-                    .cache_needs_sync(&group_info.mls_group_id, &mdk_messages)
-                    .await?
-                {
-                    tracing::info!(
-                        target: "whitenoise::cache",
-                        "Syncing cache for group {} (account {}): {} events",
-                        hex::encode(group_info.mls_group_id.as_slice()),
-                        account.pubkey.to_hex(),
-                        mdk_messages.len()
-                    );
-
-                    self.sync_cache_for_group(
-                        &account.pubkey,
-                        &group_info.mls_group_id,
-                        mdk_messages,
-                    )
-                    .await?;
-
-                    total_synced += 1;
-                }
+    /// When [`AggregatorConfig::persist_state`](crate::whitenoise::message_aggregator::AggregatorConfig::persist_state)
+    /// is set, the aggregator state persisted at the group's current epoch is used instead of
+    /// the database, and a miss persists what was read for the next call.
+    async fn cached_group_messages(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+    ) -> Result<Vec<ChatMessage>> {
+        let epoch = if self.message_aggregator.config().persist_state {
+            Account::create_mdk(account.pubkey, &self.config.data_dir)?
+                .get_group(group_id)?
+                .map(|group| group.epoch)
+        } else {
+            None
+        };
+
+        if let Some(epoch) = epoch {
+            match self
+                .message_aggregator
+                .load_group_state(group_id, epoch)
+                .await
+            {
+                Ok(Some(messages)) => return Ok(messages),
+                Ok(None) => {}
+                Err(e) => tracing::warn!(
+                    target: "whitenoise::messages",
+                    "Failed to load aggregator state for group {}: {}",
+                    hex::encode(group_id.as_slice()),
+                    e
+                ),
             }
         }
 
-        tracing::info!(
-            target: "whitenoise::cache",
-            "Message cache synchronization complete: synced {}/{} groups",
-            total_synced,
-            total_groups_checked
-        );
-
-        Ok(())
-    }
-
-    async fn cache_needs_sync(&self, group_id: &GroupId, mdk_messages: &[Message]) -> Result<bool> {
-        if mdk_messages.is_empty() {
-            return Ok(false);
-        }
-
-        let cached_count = AggregatedMessage::count_by_group(group_id, &self.database)
+        let messages = AggregatedMessage::find_messages_by_group(group_id, &self.database)
             .await
             .map_err(|e| {
-                WhitenoiseError::from(anyhow::anyhow!("Failed to count cached events: {}", e))
+                WhitenoiseError::from(anyhow::anyhow!("Failed to read cached messages: {}", e))
             })?;
 
-        if mdk_messages.len() != cached_count {
-            tracing::debug!(
-                target: "whitenoise::cache",
-                "Cache count mismatch for group {}: MDK={}, Cache={}",
+        if let Some(epoch) = epoch
+            && let Err(e) = self
+                .message_aggregator
+                .persist_group_state(group_id, epoch, messages.clone())
+                .await
+        {
+            tracing::warn!(
+                target: "whitenoise::messages",
+                "Failed to persist aggregator state for group {}: {}",
                 hex::encode(group_id.as_slice()),
-                mdk_messages.len(),
-                cached_count
+                e
             );
-            return Ok(true);
         }
 
-        Ok(false)
+        Ok(messages)
     }
 
-    /// Synchronize cache for a specific group
+    /// Fetch one page of a group's cached messages, newest first
     ///
-    /// Filters out events already in cache, then processes and saves only new events.
-    async fn sync_cache_for_group(
+    /// Returns up to `limit` messages created strictly before `before`, or the most recent
+    /// messages when `before` is `None`. Messages are ordered by `created_at` descending,
+    /// with messages from the same second in reverse arrival order. To load older history,
+    /// pass the `created_at` of the last message of the previous page as `before`.
+    ///
+    /// A page never ends partway through a second, so paging this way never skips or
+    /// repeats messages. This can make a page shorter than `limit` while older messages
+    /// remain, and in the rare case of more than `limit` messages sharing one second, that
+    /// whole second is returned as a single page. Only an empty page marks the end of history.
+    ///
+    /// Reactions and deletions are aggregated into messages when they arrive, so every
+    /// message carries its complete reactions and deletion state regardless of which page
+    /// the reaction or deletion events themselves would fall on. Hiding messages from muted
+    /// users can also make a page shorter than `limit`.
+    ///
+    /// # Arguments
+    /// * `pubkey` - The public key of the user requesting messages
+    /// * `group_id` - The group to fetch messages for
+    /// * `before` - Only return messages created before this time
+    /// * `limit` - Maximum number of messages to return
+    pub async fn fetch_aggregated_messages_for_group_paginated(
         &self,
         pubkey: &PublicKey,
         group_id: &GroupId,
-        mdk_messages: Vec<Message>,
-    ) -> Result<()> {
-        if mdk_messages.is_empty() {
-            return Ok(());
-        }
-
-        let cached_ids = AggregatedMessage::get_all_event_ids_by_group(group_id, &self.database)
-            .await
-            .map_err(|e| {
-                WhitenoiseError::from(anyhow::anyhow!("Failed to get cached event IDs: {}", e))
-            })?;
+        before: Option<Timestamp>,
+        limit: usize,
+    ) -> Result<Vec<ChatMessage>> {
+        let account = Account::find_by_pubkey(pubkey, &self.database).await?; // Verify account exists (security check)
+
+        let messages = AggregatedMessage::find_messages_by_group_paginated(
+            group_id,
+            before,
+            limit,
+            &self.database,
+        )
+        .await
+        .map_err(|e| {
+            WhitenoiseError::from(anyhow::anyhow!("Failed to read cached messages: {}", e))
+        })?;
+        let muted = self.muted_pubkeys(&account).await?;
+        let mut messages = self
+            .message_aggregator
+            .apply_muted_authors(messages, &muted);
+        self.resolve_mention_names(&mut messages).await?;
+        self.shorten_reactions_for_display(&mut messages, &account.pubkey);
+        Ok(messages)
+    }
 
-        let new_events: Vec<Message> = mdk_messages
-            .into_iter()
-            .filter(|msg| !cached_ids.contains(&msg.id.to_string()))
+    /// Fill in the names of mentioned users from their cached metadata
+    async fn resolve_mention_names(&self, messages: &mut [ChatMessage]) -> Result<()> {
+        let pubkeys: HashSet<PublicKey> = messages
+            .iter()
+            .flat_map(|message| message.mentions.iter().map(|mention| mention.pubkey))
             .collect();
-
-        if new_events.is_empty() {
-            tracing::debug!(
-                target: "whitenoise::cache",
-                "No new events to sync for group {}",
-                hex::encode(group_id.as_slice())
-            );
+        if pubkeys.is_empty() {
             return Ok(());
         }
 
-        let num_new_events = new_events.len();
+        let pubkeys: Vec<PublicKey> = pubkeys.into_iter().collect();
+        let names: HashMap<PublicKey, String> = User::find_by_pubkeys(&pubkeys, &self.database)
+            .await?
+            .into_iter()
+            .filter_map(|user| Some((user.pubkey, profile_name(&user.metadata)?.clone())))
+            .collect();
+        self.message_aggregator.resolve_mentions(messages, &names);
+        Ok(())
+    }
 
-        tracing::info!(
-            target: "whitenoise::cache",
-            "Found {} new events to cache for group {}",
-            num_new_events,
+    /// The complete reaction summary of a message
+    ///
+    /// Fetched messages list only the most used emoji when
+    /// [`AggregatorConfig::max_emoji_per_message`](crate::whitenoise::message_aggregator::AggregatorConfig::max_emoji_per_message)
+    /// is set; this returns every emoji and reactor for when the user opens all reactions.
+    ///
+    /// # Arguments
+    /// * `account` - The account viewing the reactions
+    /// * `group_id` - The group the message belongs to
+    /// * `message_id` - The message the reactions are on
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::GroupNotFound`] or [`WhitenoiseError::AccountNotGroupMember`]
+    /// if the account isn't an active member of the group, and
+    /// [`WhitenoiseError::MessageNotFound`] if the message isn't cached.
+    pub async fn message_reactions(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        message_id: &EventId,
+    ) -> Result<ReactionSummary> {
+        self.ensure_active_member(account, group_id)?;
+
+        let message = AggregatedMessage::find_by_id(&message_id.to_hex(), group_id, &self.database)
+            .await?
+            .ok_or(WhitenoiseError::MessageNotFound)?;
+        Ok(message.reactions)
+    }
+
+    /// Fetch a group's cached messages organized as reply threads
+    ///
+    /// Returns a forest where each top-level message is a root and replies are nested
+    /// under their parent. Replies to deleted or missing messages are grouped under a
+    /// synthetic orphans root (see [`ThreadNode::is_orphans_root`]) placed last.
+    ///
+    /// # Arguments
+    /// * `account` - The account requesting the messages
+    /// * `group_id` - The group to build threads for
+    pub async fn group_thread_tree(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+    ) -> Result<Vec<ThreadNode>> {
+        Account::find_by_pubkey(&account.pubkey, &self.database).await?; // Verify account exists (security check)
+        let mut messages = self.visible_group_messages(account, group_id).await?;
+        self.shorten_reactions_for_display(&mut messages, &account.pubkey);
+        Ok(threads::build_thread_tree(messages))
+    }
+
+    /// Search a group's cached messages for text
+    ///
+    /// Matching is a case-insensitive substring match on what the author typed; URLs and
+    /// `nostr:` references are not searched. Results are ranked newest first and carry the
+    /// byte range of the first match for highlighting. An empty query returns no results.
+    ///
+    /// # Arguments
+    /// * `account` - The account searching
+    /// * `group_id` - The group to search in
+    /// * `query` - The text to look for
+    /// * `limit` - Maximum number of results to return
+    pub async fn search_messages(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<MessageSearchResult>> {
+        if query.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Account::find_by_pubkey(&account.pubkey, &self.database).await?; // Verify account exists (security check)
+        let mut messages = self.visible_group_messages(account, group_id).await?;
+        self.shorten_reactions_for_display(&mut messages, &account.pubkey);
+
+        Ok(search::search_messages(&messages, query, limit))
+    }
+
+    /// Summarize activity in the account's active groups since a point in time
+    ///
+    /// Reports, per group, the number of new messages from other members, how many of them
+    /// mention the account, and how many reactions other members added to the account's
+    /// messages, along with the group's unread count and last activity. Computed with
+    /// aggregate queries over the message cache, so it's cheap enough for badge counts.
+    /// Muted users are left out the same way they are from fetched messages.
+    ///
+    /// # Arguments
+    /// * `account` - The account to summarize activity for
+    /// * `since` - Only activity at or after this timestamp is counted
+    pub async fn activity_summary(
+        &self,
+        account: &Account,
+        since: Timestamp,
+    ) -> Result<Vec<GroupActivity>> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        let group_ids: Vec<GroupId> = self
+            .groups(account, true)
+            .await?
+            .into_iter()
+            .map(|group| group.mls_group_id)
+            .collect();
+        let muted = self.muted_pubkeys(account).await?;
+        let hide_muted = self.message_aggregator.config().muted_authors == MutedAuthors::Hide;
+
+        Ok(AggregatedMessage::summarize_activity(
+            account_id,
+            &account.pubkey,
+            &group_ids,
+            since,
+            &muted,
+            hide_muted,
+            &self.database,
+        )
+        .await?)
+    }
+
+    /// Marks the group as read up to a point in time for the account
+    ///
+    /// The read position only moves forward; marking an older timestamp is a no-op. It is
+    /// persisted, so unread counts survive restarts.
+    ///
+    /// # Arguments
+    /// * `account` - The account that read the messages
+    /// * `group_id` - The group that was read
+    /// * `up_to` - Messages created at or before this timestamp count as read
+    pub async fn mark_group_read(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        up_to: Timestamp,
+    ) -> Result<()> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        GroupReadState::mark_read(account_id, group_id, up_to, &self.database).await?;
+        Ok(())
+    }
+
+    /// The account's read position in the group, or `None` if it never marked it read
+    ///
+    /// # Arguments
+    /// * `account` - The account whose read position to look up
+    /// * `group_id` - The group to look up
+    pub async fn group_last_read_at(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+    ) -> Result<Option<Timestamp>> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        Ok(GroupReadState::last_read_at(account_id, group_id, &self.database).await?)
+    }
+
+    /// Store what the account was typing in the group, so it survives the app closing
+    ///
+    /// Drafts are kept on this device only and never published. Saving empty content removes
+    /// the draft, and sending a chat message to the group removes it too.
+    ///
+    /// # Arguments
+    /// * `account` - The account that typed the draft
+    /// * `group_id` - The group the draft is for
+    /// * `content` - The unsent message
+    pub async fn save_draft(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        content: String,
+    ) -> Result<()> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        if content.is_empty() {
+            Drafts::delete(account_id, group_id, &self.database).await?;
+        } else {
+            Drafts::save(account_id, group_id, &content, &self.database).await?;
+        }
+        Ok(())
+    }
+
+    /// The account's draft for the group, or `None` if it has none
+    ///
+    /// # Arguments
+    /// * `account` - The account whose draft to look up
+    /// * `group_id` - The group to look up
+    pub async fn get_draft(&self, account: &Account, group_id: &GroupId) -> Result<Option<String>> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        Ok(Drafts::find(account_id, group_id, &self.database).await?)
+    }
+
+    /// Remove the account's draft for the group
+    ///
+    /// # Arguments
+    /// * `account` - The account whose draft to remove
+    /// * `group_id` - The group to remove the draft for
+    pub async fn clear_draft(&self, account: &Account, group_id: &GroupId) -> Result<()> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        Drafts::delete(account_id, group_id, &self.database).await?;
+        Ok(())
+    }
+
+    /// Number of cached messages in the group the account hasn't read yet
+    ///
+    /// Counts chat messages from other members created after the account's read position
+    /// (see [`Self::mark_group_read`]); deleted messages are not counted.
+    ///
+    /// # Arguments
+    /// * `account` - The account to count unread messages for
+    /// * `group_id` - The group to count in
+    pub async fn unread_count(&self, account: &Account, group_id: &GroupId) -> Result<u64> {
+        let counts = self
+            .unread_counts(account, std::slice::from_ref(group_id))
+            .await?;
+        Ok(counts.get(group_id).copied().unwrap_or_default())
+    }
+
+    /// Unread message counts for several groups at once, e.g. to render a chat list
+    ///
+    /// Uses a single query regardless of the number of groups. Every requested group is
+    /// present in the result. See [`Self::unread_count`] for what counts as unread.
+    ///
+    /// # Arguments
+    /// * `account` - The account to count unread messages for
+    /// * `group_ids` - The groups to count in
+    pub async fn unread_counts(
+        &self,
+        account: &Account,
+        group_ids: &[GroupId],
+    ) -> Result<HashMap<GroupId, u64>> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        Ok(
+            GroupReadState::unread_counts(account_id, &account.pubkey, group_ids, &self.database)
+                .await?,
+        )
+    }
+
+    /// Returns how long the account has to wait before it can send a chat message to the group
+    ///
+    /// Returns `None` when slow mode is disabled, the account is an exempt admin, or the
+    /// interval since the account's last chat message has elapsed. Clients can use this to
+    /// disable the composer and show a countdown.
+    ///
+    /// # Arguments
+    /// * `account` - The account that wants to send
+    /// * `group_id` - The group to check
+    pub async fn group_slow_mode_cooldown(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+    ) -> Result<Option<std::time::Duration>> {
+        let slow_mode = match GroupInformation::find_by_mls_group_id(group_id, &self.database).await
+        {
+            Ok(group_information) => group_information.slow_mode,
+            Err(WhitenoiseError::SqlxError(sqlx::Error::RowNotFound)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if !slow_mode.is_enabled() {
+            return Ok(None);
+        }
+
+        let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
+        let is_admin = mdk
+            .get_group(group_id)?
+            .ok_or(WhitenoiseError::GroupNotFound)?
+            .admin_pubkeys
+            .contains(&account.pubkey);
+        let last_sent_at = AggregatedMessage::find_last_message_time_by_author(
+            group_id,
+            &account.pubkey,
+            &self.database,
+        )
+        .await?
+        .map(|created_at| created_at.as_u64());
+
+        Ok(slow_mode.remaining_cooldown(last_sent_at, Timestamp::now().as_u64(), is_admin))
+    }
+
+    /// Creates an unsigned nostr event with the given parameters
+    fn create_unsigned_nostr_event(
+        &self,
+        pubkey: &PublicKey,
+        message: &String,
+        kind: u16,
+        tags: Option<Vec<Tag>>,
+    ) -> Result<(UnsignedEvent, EventId)> {
+        let final_tags = tags.unwrap_or_default();
+
+        let mut inner_event =
+            UnsignedEvent::new(*pubkey, Timestamp::now(), kind.into(), final_tags, message);
+
+        inner_event.ensure_id();
+
+        let event_id = inner_event.id.unwrap(); // This is guaranteed to be Some by ensure_id
+
+        Ok((inner_event, event_id))
+    }
+
+    /// Synchronize message cache with MDK on startup
+    ///
+    /// MUST be called BEFORE event processor starts to avoid race conditions.
+    /// Uses simple count comparison to detect sync needs, then incrementally syncs missing events.
+    pub(crate) async fn sync_message_cache_on_startup(&self) -> Result<()> {
+        tracing::info!(
+            target: "whitenoise::cache",
+            "Starting message cache synchronization..."
+        );
+
+        let mut total_synced = 0;
+        let mut total_groups_checked = 0;
+
+        let failed = AggregatedMessage::fail_pending_deliveries(&self.database).await?;
+        if failed > 0 {
+            tracing::info!(
+                target: "whitenoise::cache",
+                "Marked {} messages left pending by the previous run as failed",
+                failed
+            );
+        }
+
+        let accounts = Account::all(&self.database).await?;
+
+        for account in accounts {
+            let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
+            let groups = mdk.get_groups()?;
+
+            for group_info in groups {
+                total_groups_checked += 1;
+
+                let mdk_messages = mdk.get_messages(&group_info.mls_group_id)?;
+
+                if self
+                    .cache_needs_sync(&group_info.mls_group_id, &mdk_messages)
+                    .await?
+                {
+                    tracing::info!(
+                        target: "whitenoise::cache",
+                        "Syncing cache for group {} (account {}): {} events",
+                        hex::encode(group_info.mls_group_id.as_slice()),
+                        account.pubkey.to_hex(),
+                        mdk_messages.len()
+                    );
+
+                    self.sync_cache_for_group(
+                        &account.pubkey,
+                        &group_info.mls_group_id,
+                        mdk_messages,
+                    )
+                    .await?;
+
+                    total_synced += 1;
+                }
+            }
+        }
+
+        tracing::info!(
+            target: "whitenoise::cache",
+            "Message cache synchronization complete: synced {}/{} groups",
+            total_synced,
+            total_groups_checked
+        );
+
+        Ok(())
+    }
+
+    async fn cache_needs_sync(&self, group_id: &GroupId, mdk_messages: &[Message]) -> Result<bool> {
+        if mdk_messages.is_empty() {
+            return Ok(false);
+        }
+
+        let cached_count = AggregatedMessage::count_by_group(group_id, &self.database)
+            .await
+            .map_err(|e| {
+                WhitenoiseError::from(anyhow::anyhow!("Failed to count cached events: {}", e))
+            })?;
+
+        if mdk_messages.len() != cached_count {
+            tracing::debug!(
+                target: "whitenoise::cache",
+                "Cache count mismatch for group {}: MDK={}, Cache={}",
+                hex::encode(group_id.as_slice()),
+                mdk_messages.len(),
+                cached_count
+            );
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// Synchronize cache for a specific group
+    ///
+    /// Filters out events already in cache, then processes and saves only new events.
+    pub(crate) async fn sync_cache_for_group(
+        &self,
+        pubkey: &PublicKey,
+        group_id: &GroupId,
+        mdk_messages: Vec<Message>,
+    ) -> Result<()> {
+        if mdk_messages.is_empty() {
+            return Ok(());
+        }
+
+        let cached_ids = AggregatedMessage::get_all_event_ids_by_group(group_id, &self.database)
+            .await
+            .map_err(|e| {
+                WhitenoiseError::from(anyhow::anyhow!("Failed to get cached event IDs: {}", e))
+            })?;
+
+        let new_events: Vec<Message> = mdk_messages
+            .into_iter()
+            .filter(|msg| !cached_ids.contains(&msg.id.to_string()))
+            .collect();
+
+        if new_events.is_empty() {
+            tracing::debug!(
+                target: "whitenoise::cache",
+                "No new events to sync for group {}",
+                hex::encode(group_id.as_slice())
+            );
+            return Ok(());
+        }
+
+        let num_new_events = new_events.len();
+
+        tracing::info!(
+            target: "whitenoise::cache",
+            "Found {} new events to cache for group {}",
+            num_new_events,
             hex::encode(group_id.as_slice())
         );
 
+        // The aggregator only sees this batch, so reactions, deletions and edits of messages
+        // cached by an earlier sync are applied to the cache afterwards
+        let batch_message_ids: HashSet<String> = new_events
+            .iter()
+            .filter(|event| event.kind == Kind::Custom(9))
+            .map(|event| event.id.to_string())
+            .collect();
+        let mut late_events: Vec<Message> = new_events
+            .iter()
+            .filter(|event| {
+                matches!(event.kind, Kind::Reaction | Kind::EventDeletion)
+                    || edit_handler::is_edit(event)
+            })
+            .cloned()
+            .collect();
+        late_events.sort_by_key(|event| event.created_at);
+
         let media_files = MediaFile::find_by_group(&self.database, group_id).await?;
 
         let processed_messages = self
@@ -280,6 +1165,14 @@ impl Whitenoise {
                 WhitenoiseError::from(anyhow::anyhow!("Failed to save events to cache: {}", e))
             })?;
 
+        for event in &late_events {
+            self.apply_synced_event_to_cache(event, group_id, &batch_message_ids)
+                .await?;
+        }
+        self.message_aggregator
+            .invalidate_group_state(group_id)
+            .await;
+
         tracing::debug!(
             target: "whitenoise::cache",
             "Successfully synced {} new events for group {}",
@@ -297,108 +1190,944 @@ mod tests {
     use crate::whitenoise::test_utils::*;
     use std::time::Duration;
 
-    /// Test successful message sending with various scenarios:
-    /// - Default tags (None)
-    /// - Custom tags (e.g., reply tags)
-    /// - Token parsing (URLs and other special content)
     #[tokio::test]
-    async fn test_send_message_to_group_success() {
-        // Arrange: Setup whitenoise and create a group
+    async fn test_sending_chat_message_clears_draft() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member_pubkey = members[0].0.pubkey;
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let group = whitenoise
+            .create_group(&creator_account, vec![member_pubkey], config, None)
+            .await
+            .unwrap();
+        let group_id = &group.mls_group_id;
+
+        whitenoise
+            .save_draft(&creator_account, group_id, "Half-typed".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            whitenoise
+                .get_draft(&creator_account, group_id)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("Half-typed")
+        );
+
+        let sent = whitenoise
+            .send_message_to_group(&creator_account, group_id, "Hi".to_string(), 9, None)
+            .await
+            .unwrap();
+        assert!(
+            whitenoise
+                .get_draft(&creator_account, group_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        whitenoise
+            .save_draft(&creator_account, group_id, "Another".to_string())
+            .await
+            .unwrap();
+        // Reactions don't send what was typed, so the draft stays
+        let tags = vec![Tag::event(sent.message.id)];
+        whitenoise
+            .send_message_to_group(&creator_account, group_id, "+".to_string(), 7, Some(tags))
+            .await
+            .unwrap();
+        assert!(
+            whitenoise
+                .get_draft(&creator_account, group_id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        // Saving an empty draft removes it
+        whitenoise
+            .save_draft(&creator_account, group_id, String::new())
+            .await
+            .unwrap();
+        assert!(
+            whitenoise
+                .get_draft(&creator_account, group_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    /// Test successful message sending with various scenarios:
+    /// - Default tags (None)
+    /// - Custom tags (e.g., reply tags)
+    /// - Token parsing (URLs and other special content)
+    #[tokio::test]
+    async fn test_send_message_to_group_success() {
+        // Arrange: Setup whitenoise and create a group
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member_pubkey = members[0].0.pubkey;
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let admin_pubkeys = vec![creator_account.pubkey];
+        let config = create_nostr_group_config_data(admin_pubkeys);
+        let group = whitenoise
+            .create_group(&creator_account, vec![member_pubkey], config, None)
+            .await
+            .unwrap();
+
+        // Test 1: Basic message with no tags
+        let basic_message = "Hello, world!".to_string();
+        let result = whitenoise
+            .send_message_to_group(
+                &creator_account,
+                &group.mls_group_id,
+                basic_message.clone(),
+                9,
+                None,
+            )
+            .await;
+        assert!(
+            result.is_ok(),
+            "Failed to send basic message: {:?}",
+            result.err()
+        );
+        assert_eq!(result.unwrap().message.content, basic_message);
+
+        // Test 2: Message with custom tags (reply scenario)
+        let reply_message = "This is a reply".to_string();
+        let tags = Some(vec![Tag::parse(vec!["e", "original_message_id"]).unwrap()]);
+        let result = whitenoise
+            .send_message_to_group(
+                &creator_account,
+                &group.mls_group_id,
+                reply_message.clone(),
+                9,
+                tags,
+            )
+            .await;
+        assert!(
+            result.is_ok(),
+            "Failed to send message with tags: {:?}",
+            result.err()
+        );
+        assert_eq!(result.unwrap().message.content, reply_message);
+
+        // Test 3: Message with URL (token parsing)
+        let url_message = "Check out https://example.com for info".to_string();
+        let result = whitenoise
+            .send_message_to_group(
+                &creator_account,
+                &group.mls_group_id,
+                url_message.clone(),
+                9,
+                None,
+            )
+            .await;
+        assert!(result.is_ok(), "Failed to send message with URL");
+        let message_with_tokens = result.unwrap();
+        assert_eq!(message_with_tokens.message.content, url_message);
+        assert!(
+            !message_with_tokens.tokens.is_empty(),
+            "Expected URL to be parsed as token"
+        );
+    }
+
+    /// Test error handling when sending to non-existent group
+    #[tokio::test]
+    async fn test_send_message_to_group_error_handling() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+
+        // Try to send message to a non-existent group
+        let fake_group_id = GroupId::from_slice(&[255u8; 32]);
+        let result = whitenoise
+            .send_message_to_group(
+                &creator_account,
+                &fake_group_id,
+                "Test".to_string(),
+                9,
+                None,
+            )
+            .await;
+
+        assert!(result.is_err(), "Expected error for non-existent group");
+    }
+
+    /// Test edge cases: empty content, long content, different event kinds
+    #[tokio::test]
+    async fn test_send_message_to_group_edge_cases() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member_pubkey = members[0].0.pubkey;
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let admin_pubkeys = vec![creator_account.pubkey];
+        let config = create_nostr_group_config_data(admin_pubkeys);
+        let group = whitenoise
+            .create_group(&creator_account, vec![member_pubkey], config, None)
+            .await
+            .unwrap();
+
+        // Test empty content
+        let empty_result = whitenoise
+            .send_message_to_group(
+                &creator_account,
+                &group.mls_group_id,
+                String::new(),
+                9,
+                None,
+            )
+            .await;
+        assert!(empty_result.is_ok(), "Empty message should be allowed");
+
+        // Test long content
+        let long_content = "A".repeat(10000);
+        let long_result = whitenoise
+            .send_message_to_group(
+                &creator_account,
+                &group.mls_group_id,
+                long_content.clone(),
+                9,
+                None,
+            )
+            .await;
+        assert!(long_result.is_ok(), "Long message should be sendable");
+        assert_eq!(
+            long_result.unwrap().message.content.len(),
+            long_content.len()
+        );
+
+        // Test different event kinds
+        for kind in [7, 9, 10] {
+            let result = whitenoise
+                .send_message_to_group(
+                    &creator_account,
+                    &group.mls_group_id,
+                    format!("Kind {}", kind),
+                    kind,
+                    None,
+                )
+                .await;
+            assert!(result.is_ok(), "Message with kind {} should succeed", kind);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_uses_persisted_state_until_cache_changes() {
+        use crate::whitenoise::message_aggregator::{AggregatorConfig, MessageAggregator};
+
+        let (mut whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        whitenoise.message_aggregator = std::sync::Arc::new(
+            MessageAggregator::with_config(AggregatorConfig {
+                persist_state: true,
+                ..Default::default()
+            })
+            .with_data_dir(&whitenoise.config.data_dir),
+        );
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member_pubkey = members[0].0.pubkey;
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let group = whitenoise
+            .create_group(&creator_account, vec![member_pubkey], config, None)
+            .await
+            .unwrap();
+        let group_id = &group.mls_group_id;
+        let epoch = Account::create_mdk(creator_account.pubkey, &whitenoise.config.data_dir)
+            .unwrap()
+            .get_group(group_id)
+            .unwrap()
+            .unwrap()
+            .epoch;
+
+        let mdk = Account::create_mdk(creator_account.pubkey, &whitenoise.config.data_dir).unwrap();
+        whitenoise
+            .send_message_to_group(&creator_account, group_id, "first".to_string(), 9, None)
+            .await
+            .unwrap();
+        whitenoise
+            .sync_cache_for_group(
+                &creator_account.pubkey,
+                group_id,
+                mdk.get_messages(group_id).unwrap(),
+            )
+            .await
+            .unwrap();
+        let messages = whitenoise
+            .fetch_aggregated_messages_for_group(&creator_account.pubkey, group_id)
+            .await
+            .unwrap();
+        assert_eq!(messages.len(), 1);
+
+        // The fetch persisted the group's state at its current epoch
+        let persisted = whitenoise
+            .message_aggregator
+            .load_group_state(group_id, epoch)
+            .await
+            .unwrap()
+            .expect("state should be persisted after a fetch");
+        assert_eq!(persisted.len(), 1);
+
+        // Caching another message drops it, so the next fetch sees the new message
+        whitenoise
+            .send_message_to_group(&creator_account, group_id, "second".to_string(), 9, None)
+            .await
+            .unwrap();
+        whitenoise
+            .sync_cache_for_group(
+                &creator_account.pubkey,
+                group_id,
+                mdk.get_messages(group_id).unwrap(),
+            )
+            .await
+            .unwrap();
+        let messages = whitenoise
+            .fetch_aggregated_messages_for_group(&creator_account.pubkey, group_id)
+            .await
+            .unwrap();
+        let mut contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        contents.sort();
+        assert_eq!(contents, vec!["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn test_message_reactions_are_complete_and_members_only() {
+        use crate::whitenoise::group_information::GroupType;
+        use crate::whitenoise::message_aggregator::{
+            AggregatorConfig, EmojiReaction, MessageAggregator,
+        };
+
+        let (mut whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        whitenoise.message_aggregator = std::sync::Arc::new(
+            MessageAggregator::with_config(AggregatorConfig {
+                max_emoji_per_message: Some(1),
+                max_reactors_per_emoji: Some(0),
+                ..Default::default()
+            })
+            .with_data_dir(&whitenoise.config.data_dir),
+        );
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member_account = &members[0].0;
+        let group_id =
+            create_group_with_joined_member(&whitenoise, &creator_account, member_account).await;
+        GroupInformation::find_or_create_by_mls_group_id(
+            &group_id,
+            Some(GroupType::Group),
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+
+        let sent = whitenoise
+            .send_message_to_group(&creator_account, &group_id, "hi".to_string(), 9, None)
+            .await
+            .unwrap();
+        let mdk = Account::create_mdk(creator_account.pubkey, &whitenoise.config.data_dir).unwrap();
+        whitenoise
+            .sync_cache_for_group(
+                &creator_account.pubkey,
+                &group_id,
+                mdk.get_messages(&group_id).unwrap(),
+            )
+            .await
+            .unwrap();
+        let mut reactions = ReactionSummary::default();
+        for (emoji, user) in [
+            ("👍", member_account.pubkey),
+            ("🔥", creator_account.pubkey),
+        ] {
+            reactions.by_emoji.insert(
+                emoji.to_string(),
+                EmojiReaction {
+                    emoji: emoji.to_string(),
+                    count: 1,
+                    users: vec![user],
+                },
+            );
+            reactions.user_reactions.push(UserReaction {
+                user,
+                emoji: emoji.to_string(),
+                created_at: Timestamp::now(),
+            });
+        }
+        AggregatedMessage::update_reactions(
+            &sent.message.id.to_hex(),
+            &group_id,
+            &reactions,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+
+        // Fetched messages only list the viewer's emoji, the other one is counted
+        let messages = whitenoise
+            .fetch_aggregated_messages_for_group(&member_account.pubkey, &group_id)
+            .await
+            .unwrap();
+        assert_eq!(messages[0].reactions.by_emoji.len(), 1);
+        assert!(messages[0].reactions.by_emoji.contains_key("👍"));
+        assert_eq!(messages[0].reactions.more_count, 1);
+
+        // Activity is computed from the complete reactions, although the creator would only
+        // be shown their own emoji and no other reactors
+        let activity = whitenoise
+            .activity_summary(&creator_account, Timestamp::from(0))
+            .await
+            .unwrap();
+        let group_activity = activity
+            .iter()
+            .find(|activity| activity.group_id == group_id)
+            .unwrap();
+        assert_eq!(group_activity.reactions_to_own_messages, 1);
+
+        let summary = whitenoise
+            .message_reactions(member_account, &group_id, &sent.message.id)
+            .await
+            .unwrap();
+        assert_eq!(summary.by_emoji.len(), 2);
+        assert_eq!(summary.more_count, 0);
+
+        let result = whitenoise
+            .message_reactions(member_account, &group_id, &EventId::all_zeros())
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::MessageNotFound)));
+
+        // Expanding an emoji lists every reactor
+        let details = whitenoise
+            .reaction_details(member_account, &group_id, &sent.message.id, "🔥")
+            .await
+            .unwrap();
+        assert_eq!(details.len(), 1);
+        assert_eq!(details[0].user, creator_account.pubkey);
+
+        let outsider = whitenoise.create_identity().await.unwrap();
+        let result = whitenoise
+            .message_reactions(&outsider, &group_id, &sent.message.id)
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::GroupNotFound)));
+        let result = whitenoise
+            .reaction_details(&outsider, &group_id, &sent.message.id, "🔥")
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::GroupNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_activity_summary_counts_messages_and_mentions() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member_account = &members[0].0;
+        let group_id =
+            create_group_with_joined_member(&whitenoise, &creator_account, member_account).await;
+        let member_mdk =
+            Account::create_mdk(member_account.pubkey, &whitenoise.config.data_dir).unwrap();
+
+        let since = Timestamp::now();
+        let mut last_sent_at = since;
+        for (content, tags) in [
+            ("hello", vec![]),
+            (
+                "are you there?",
+                vec![Tag::public_key(creator_account.pubkey)],
+            ),
+            ("anyone?", vec![]),
+        ] {
+            let rumor = EventBuilder::new(Kind::Custom(9), content)
+                .tags(tags)
+                .build(member_account.pubkey);
+            last_sent_at = last_sent_at.max(rumor.created_at);
+            let message_event = member_mdk.create_message(&group_id, rumor).unwrap();
+            whitenoise
+                .handle_mls_message(&creator_account, message_event)
+                .await
+                .unwrap();
+        }
+
+        let summary = whitenoise
+            .activity_summary(&creator_account, since)
+            .await
+            .unwrap();
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].group_id, group_id);
+        assert_eq!(summary[0].new_messages, 3);
+        assert_eq!(summary[0].mentions, 1);
+        assert_eq!(summary[0].reactions_to_own_messages, 0);
+        assert_eq!(summary[0].unread_messages, 3);
+        assert_eq!(summary[0].last_activity_at, Some(last_sent_at));
+
+        // Reading the group clears the unread count, not the activity since `since`
+        whitenoise
+            .mark_group_read(&creator_account, &group_id, last_sent_at)
+            .await
+            .unwrap();
+        let summary = whitenoise
+            .activity_summary(&creator_account, since)
+            .await
+            .unwrap();
+        assert_eq!(summary[0].new_messages, 3);
+        assert_eq!(summary[0].unread_messages, 0);
+
+        // Nothing happened after the last message
+        let later = Timestamp::from(last_sent_at.as_u64() + 1);
+        let summary = whitenoise
+            .activity_summary(&creator_account, later)
+            .await
+            .unwrap();
+        assert!(summary[0].is_empty());
+    }
+
+    /// Test that slow mode rate-limits chat messages and that admins can be exempted
+    #[tokio::test]
+    async fn test_send_message_to_group_slow_mode() {
+        use crate::whitenoise::group_information::SlowMode;
+
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member_pubkey = members[0].0.pubkey;
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let group = whitenoise
+            .create_group(&creator_account, vec![member_pubkey], config, None)
+            .await
+            .unwrap();
+
+        // Without the admin exemption the creator is limited like any other member
+        let group_information = whitenoise
+            .set_group_slow_mode(
+                &creator_account,
+                &group.mls_group_id,
+                SlowMode {
+                    interval_secs: 3600,
+                    admins_exempt: false,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(group_information.slow_mode.interval_secs, 3600);
+
+        whitenoise
+            .send_message_to_group(
+                &creator_account,
+                &group.mls_group_id,
+                "first".to_string(),
+                9,
+                None,
+            )
+            .await
+            .unwrap();
+        let cooldown = whitenoise
+            .group_slow_mode_cooldown(&creator_account, &group.mls_group_id)
+            .await
+            .unwrap()
+            .expect("cooldown should be active after sending");
+        assert!(cooldown.as_secs() > 3500);
+
+        let result = whitenoise
+            .send_message_to_group(
+                &creator_account,
+                &group.mls_group_id,
+                "second".to_string(),
+                9,
+                None,
+            )
+            .await;
+        match result {
+            Err(WhitenoiseError::SlowModeActive { remaining_secs }) => {
+                assert!(remaining_secs > 0 && remaining_secs <= 3600);
+            }
+            other => panic!("Expected SlowModeActive, got {:?}", other.map(|_| ())),
+        }
+
+        // Reactions are not rate-limited
+        let result = whitenoise
+            .send_message_to_group(
+                &creator_account,
+                &group.mls_group_id,
+                "+".to_string(),
+                7,
+                None,
+            )
+            .await;
+        assert!(result.is_ok());
+
+        // With the exemption enabled the admin can send freely
+        whitenoise
+            .set_group_slow_mode(
+                &creator_account,
+                &group.mls_group_id,
+                SlowMode {
+                    interval_secs: 3600,
+                    admins_exempt: true,
+                },
+            )
+            .await
+            .unwrap();
+        assert!(
+            whitenoise
+                .group_slow_mode_cooldown(&creator_account, &group.mls_group_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        for content in ["third", "fourth"] {
+            let result = whitenoise
+                .send_message_to_group(
+                    &creator_account,
+                    &group.mls_group_id,
+                    content.to_string(),
+                    9,
+                    None,
+                )
+                .await;
+            assert!(result.is_ok(), "Exempt admin should not be rate-limited");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sent_message_is_pending_until_relays_answer() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let group = whitenoise
+            .create_group(&creator_account, vec![members[0].0.pubkey], config, None)
+            .await
+            .unwrap();
+        let group_id = &group.mls_group_id;
+        let mut subscription = whitenoise
+            .subscribe_to_group_messages(&creator_account, group_id)
+            .await
+            .unwrap();
+
+        let sent = whitenoise
+            .send_message_to_group(&creator_account, group_id, "hi".to_string(), 9, None)
+            .await
+            .unwrap();
+
+        let update = subscription.updates.recv().await.unwrap();
+        assert_eq!(update.trigger, UpdateTrigger::NewMessage);
+        assert_eq!(update.message.id, sent.message.id.to_string());
+        assert_eq!(update.message.delivery_status, DeliveryStatus::Pending);
+
+        let update = tokio::time::timeout(Duration::from_secs(30), subscription.updates.recv())
+            .await
+            .expect("relays should answer")
+            .unwrap();
+        assert_eq!(update.trigger, UpdateTrigger::DeliveryStatusChanged);
+        assert_ne!(update.message.delivery_status, DeliveryStatus::Pending);
+
+        // The status recorded when sending is carried over once the message is cached
+        assert!(
+            AggregatedMessage::find_by_id(&update.message.id, group_id, &whitenoise.database)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let mdk = Account::create_mdk(creator_account.pubkey, &whitenoise.config.data_dir).unwrap();
+        whitenoise
+            .sync_cache_for_group(
+                &creator_account.pubkey,
+                group_id,
+                mdk.get_messages(group_id).unwrap(),
+            )
+            .await
+            .unwrap();
+        let cached =
+            AggregatedMessage::find_by_id(&update.message.id, group_id, &whitenoise.database)
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(cached.delivery_status, update.message.delivery_status);
+    }
+
+    #[tokio::test]
+    async fn test_toggle_reaction_adds_then_removes() {
         let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
         let creator_account = whitenoise.create_identity().await.unwrap();
         let members = setup_multiple_test_accounts(&whitenoise, 1).await;
-        let member_pubkey = members[0].0.pubkey;
 
         tokio::time::sleep(Duration::from_millis(200)).await;
 
-        let admin_pubkeys = vec![creator_account.pubkey];
-        let config = create_nostr_group_config_data(admin_pubkeys);
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
         let group = whitenoise
-            .create_group(&creator_account, vec![member_pubkey], config, None)
+            .create_group(&creator_account, vec![members[0].0.pubkey], config, None)
             .await
             .unwrap();
 
-        // Test 1: Basic message with no tags
-        let basic_message = "Hello, world!".to_string();
-        let result = whitenoise
+        let sent = whitenoise
             .send_message_to_group(
                 &creator_account,
                 &group.mls_group_id,
-                basic_message.clone(),
+                "react to me".to_string(),
                 9,
                 None,
             )
-            .await;
-        assert!(
-            result.is_ok(),
-            "Failed to send basic message: {:?}",
-            result.err()
-        );
-        assert_eq!(result.unwrap().message.content, basic_message);
+            .await
+            .unwrap();
+
+        let action = whitenoise
+            .toggle_reaction(
+                &creator_account,
+                &group.mls_group_id,
+                &sent.message.id,
+                "👍🏽",
+            )
+            .await
+            .unwrap();
+        assert_eq!(action, ReactionAction::Added);
+
+        let mdk = Account::create_mdk(creator_account.pubkey, &whitenoise.config.data_dir).unwrap();
+        let mdk_messages = mdk.get_messages(&group.mls_group_id).unwrap();
+        let reaction = mdk_messages
+            .iter()
+            .find(|message| message.kind == Kind::Reaction)
+            .expect("reaction should have been sent")
+            .clone();
+        whitenoise
+            .sync_cache_for_group(&creator_account.pubkey, &group.mls_group_id, mdk_messages)
+            .await
+            .unwrap();
+
+        // A different skin tone of the same emoji is the same reaction
+        let action = whitenoise
+            .toggle_reaction(
+                &creator_account,
+                &group.mls_group_id,
+                &sent.message.id,
+                "👍",
+            )
+            .await
+            .unwrap();
+        assert_eq!(action, ReactionAction::Removed);
+
+        let deletion = mdk
+            .get_messages(&group.mls_group_id)
+            .unwrap()
+            .into_iter()
+            .find(|message| message.kind == Kind::EventDeletion)
+            .expect("deletion should have been sent");
+        assert_eq!(deletion.tags.event_ids().next(), Some(&reaction.id));
 
-        // Test 2: Message with custom tags (reply scenario)
-        let reply_message = "This is a reply".to_string();
-        let tags = Some(vec![Tag::parse(vec!["e", "original_message_id"]).unwrap()]);
         let result = whitenoise
-            .send_message_to_group(
+            .toggle_reaction(
                 &creator_account,
                 &group.mls_group_id,
-                reply_message.clone(),
-                9,
-                tags,
+                &sent.message.id,
+                "nope",
             )
             .await;
-        assert!(
-            result.is_ok(),
-            "Failed to send message with tags: {:?}",
-            result.err()
-        );
-        assert_eq!(result.unwrap().message.content, reply_message);
+        assert!(matches!(
+            result,
+            Err(WhitenoiseError::MessageAggregation(_))
+        ));
+    }
 
-        // Test 3: Message with URL (token parsing)
-        let url_message = "Check out https://example.com for info".to_string();
-        let result = whitenoise
+    #[tokio::test]
+    async fn test_toggle_reaction_only_removes_toggled_emoji() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let group = whitenoise
+            .create_group(&creator_account, vec![members[0].0.pubkey], config, None)
+            .await
+            .unwrap();
+        let group_id = &group.mls_group_id;
+        let mdk = Account::create_mdk(creator_account.pubkey, &whitenoise.config.data_dir).unwrap();
+        let sync = async || {
+            whitenoise
+                .sync_cache_for_group(
+                    &creator_account.pubkey,
+                    group_id,
+                    mdk.get_messages(group_id).unwrap(),
+                )
+                .await
+                .unwrap();
+        };
+
+        let sent = whitenoise
             .send_message_to_group(
                 &creator_account,
-                &group.mls_group_id,
-                url_message.clone(),
+                group_id,
+                "react to me".to_string(),
                 9,
                 None,
             )
-            .await;
-        assert!(result.is_ok(), "Failed to send message with URL");
-        let message_with_tokens = result.unwrap();
-        assert_eq!(message_with_tokens.message.content, url_message);
-        assert!(
-            !message_with_tokens.tokens.is_empty(),
-            "Expected URL to be parsed as token"
+            .await
+            .unwrap();
+
+        for emoji in ["🎉", "👍"] {
+            let action = whitenoise
+                .toggle_reaction(&creator_account, group_id, &sent.message.id, emoji)
+                .await
+                .unwrap();
+            assert_eq!(action, ReactionAction::Added);
+            sync().await;
+        }
+
+        let action = whitenoise
+            .toggle_reaction(&creator_account, group_id, &sent.message.id, "👍")
+            .await
+            .unwrap();
+        assert_eq!(action, ReactionAction::Removed);
+
+        let messages = mdk.get_messages(group_id).unwrap();
+        let thumbs_up = messages
+            .iter()
+            .find(|message| message.kind == Kind::Reaction && message.content == "👍")
+            .unwrap();
+        let deletion = messages
+            .iter()
+            .find(|message| message.kind == Kind::EventDeletion)
+            .expect("deletion should have been sent");
+        assert_eq!(
+            deletion.tags.event_ids().collect::<Vec<_>>(),
+            vec![&thumbs_up.id]
         );
     }
 
-    /// Test error handling when sending to non-existent group
     #[tokio::test]
-    async fn test_send_message_to_group_error_handling() {
+    async fn test_edit_message_collapses_onto_original() {
         let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
         let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member_account = &members[0].0;
 
-        // Try to send message to a non-existent group
-        let fake_group_id = GroupId::from_slice(&[255u8; 32]);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let group = whitenoise
+            .create_group(&creator_account, vec![member_account.pubkey], config, None)
+            .await
+            .unwrap();
+        let group_id = &group.mls_group_id;
+        let mdk = Account::create_mdk(creator_account.pubkey, &whitenoise.config.data_dir).unwrap();
+        let sync = async || {
+            whitenoise
+                .sync_cache_for_group(
+                    &creator_account.pubkey,
+                    group_id,
+                    mdk.get_messages(group_id).unwrap(),
+                )
+                .await
+                .unwrap();
+        };
+
+        let sent = whitenoise
+            .send_message_to_group(&creator_account, group_id, "helo".to_string(), 9, None)
+            .await
+            .unwrap();
+        sync().await;
+
+        let edit = whitenoise
+            .edit_message(
+                &creator_account,
+                group_id,
+                &sent.message.id,
+                "hello".to_string(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(edit.message.kind, Kind::Custom(MESSAGE_EDIT_KIND));
+        sync().await;
+
+        // Editing an edit edits the original
+        whitenoise
+            .edit_message(
+                &creator_account,
+                group_id,
+                &edit.message.id,
+                "hello!".to_string(),
+            )
+            .await
+            .unwrap();
+        sync().await;
+
+        let messages = whitenoise
+            .fetch_aggregated_messages_for_group(&creator_account.pubkey, group_id)
+            .await
+            .unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, sent.message.id.to_string());
+        assert_eq!(messages[0].content, "hello!");
+        assert!(messages[0].edited_at.is_some());
+
+        let history = whitenoise
+            .message_edit_history(&creator_account, group_id, &sent.message.id)
+            .await
+            .unwrap();
+        let contents: Vec<&str> = history.iter().map(|v| v.content.as_str()).collect();
+        assert_eq!(contents, vec!["helo", "hello", "hello!"]);
+
+        // Only the author can edit
         let result = whitenoise
-            .send_message_to_group(
+            .edit_message(
+                member_account,
+                group_id,
+                &sent.message.id,
+                "nope".to_string(),
+            )
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::AccountNotAuthorized)));
+
+        // Deleted messages can't be edited
+        AggregatedMessage::mark_deleted(
+            &sent.message.id.to_string(),
+            group_id,
+            &EventId::all_zeros().to_hex(),
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        let result = whitenoise
+            .edit_message(
                 &creator_account,
-                &fake_group_id,
-                "Test".to_string(),
-                9,
-                None,
+                group_id,
+                &sent.message.id,
+                "again".to_string(),
             )
             .await;
+        assert!(matches!(result, Err(WhitenoiseError::MessageDeleted)));
 
-        assert!(result.is_err(), "Expected error for non-existent group");
+        let result = whitenoise
+            .edit_message(
+                &creator_account,
+                group_id,
+                &EventId::all_zeros(),
+                "x".to_string(),
+            )
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::MessageNotFound)));
     }
 
-    /// Test edge cases: empty content, long content, different event kinds
     #[tokio::test]
-    async fn test_send_message_to_group_edge_cases() {
+    async fn test_quote_message_keeps_preview_of_deleted_message() {
+        use crate::whitenoise::message_aggregator::ChatMessageRef;
+
         let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
         let creator_account = whitenoise.create_identity().await.unwrap();
         let members = setup_multiple_test_accounts(&whitenoise, 1).await;
@@ -406,55 +2135,87 @@ mod tests {
 
         tokio::time::sleep(Duration::from_millis(200)).await;
 
-        let admin_pubkeys = vec![creator_account.pubkey];
-        let config = create_nostr_group_config_data(admin_pubkeys);
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
         let group = whitenoise
             .create_group(&creator_account, vec![member_pubkey], config, None)
             .await
             .unwrap();
+        let group_id = &group.mls_group_id;
 
-        // Test empty content
-        let empty_result = whitenoise
+        let original = whitenoise
             .send_message_to_group(
                 &creator_account,
-                &group.mls_group_id,
-                String::new(),
+                group_id,
+                "worth quoting".to_string(),
                 9,
                 None,
             )
-            .await;
-        assert!(empty_result.is_ok(), "Empty message should be allowed");
-
-        // Test long content
-        let long_content = "A".repeat(10000);
-        let long_result = whitenoise
-            .send_message_to_group(
+            .await
+            .unwrap();
+        let mdk = Account::create_mdk(creator_account.pubkey, &whitenoise.config.data_dir).unwrap();
+        whitenoise
+            .sync_cache_for_group(
+                &creator_account.pubkey,
+                group_id,
+                mdk.get_messages(group_id).unwrap(),
+            )
+            .await
+            .unwrap();
+        let quote = whitenoise
+            .quote_message(
                 &creator_account,
-                &group.mls_group_id,
-                long_content.clone(),
-                9,
-                None,
+                group_id,
+                &original.message.id,
+                "see this".to_string(),
             )
-            .await;
-        assert!(long_result.is_ok(), "Long message should be sendable");
+            .await
+            .unwrap();
+        assert!(quote.message.content.starts_with("see this\n\nnostr:note1"));
+        whitenoise
+            .sync_cache_for_group(
+                &creator_account.pubkey,
+                group_id,
+                mdk.get_messages(group_id).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        AggregatedMessage::mark_deleted(
+            &original.message.id.to_string(),
+            group_id,
+            &EventId::all_zeros().to_hex(),
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+
+        let messages = whitenoise
+            .fetch_aggregated_messages_for_group(&creator_account.pubkey, group_id)
+            .await
+            .unwrap();
+        let cached_quote = messages
+            .iter()
+            .find(|m| m.id == quote.message.id.to_string())
+            .unwrap();
+        assert!(!cached_quote.is_reply);
         assert_eq!(
-            long_result.unwrap().message.content.len(),
-            long_content.len()
+            cached_quote.quoted,
+            Some(ChatMessageRef {
+                id: original.message.id.to_string(),
+                preview: Some("worth quoting".to_string()),
+            })
         );
 
-        // Test different event kinds
-        for kind in [7, 9, 10] {
-            let result = whitenoise
-                .send_message_to_group(
-                    &creator_account,
-                    &group.mls_group_id,
-                    format!("Kind {}", kind),
-                    kind,
-                    None,
-                )
-                .await;
-            assert!(result.is_ok(), "Message with kind {} should succeed", kind);
-        }
+        // Deleted messages can't be quoted anymore
+        let result = whitenoise
+            .quote_message(
+                &creator_account,
+                group_id,
+                &original.message.id,
+                String::new(),
+            )
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::MessageDeleted)));
     }
 
     /// Test helper method: create_unsigned_nostr_event
@@ -741,6 +2502,64 @@ mod tests {
         assert!(contents.contains(&"Third".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_incremental_sync_applies_reactions_to_earlier_messages() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator = whitenoise.create_identity().await.unwrap();
+        let member = whitenoise.create_identity().await.unwrap();
+
+        let group = whitenoise
+            .create_group(
+                &creator,
+                vec![member.pubkey],
+                crate::whitenoise::test_utils::create_nostr_group_config_data(vec![creator.pubkey]),
+                None,
+            )
+            .await
+            .unwrap();
+        let group_id = &group.mls_group_id;
+        let mdk = Account::create_mdk(creator.pubkey, &whitenoise.config.data_dir).unwrap();
+
+        let sent = whitenoise
+            .send_message_to_group(&creator, group_id, "React to me".to_string(), 9, None)
+            .await
+            .unwrap();
+        whitenoise
+            .sync_cache_for_group(
+                &creator.pubkey,
+                group_id,
+                mdk.get_messages(group_id).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // The reaction arrives in a later batch than the message it reacts to
+        let tags = vec![Tag::event(sent.message.id)];
+        whitenoise
+            .send_message_to_group(&creator, group_id, "👍".to_string(), 7, Some(tags))
+            .await
+            .unwrap();
+        whitenoise
+            .sync_cache_for_group(
+                &creator.pubkey,
+                group_id,
+                mdk.get_messages(group_id).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let message = AggregatedMessage::find_by_id(
+            &sent.message.id.to_hex(),
+            group_id,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(message.reactions.by_emoji["👍"].count, 1);
+        assert_eq!(message.reactions.user_reactions[0].user, creator.pubkey);
+    }
+
     #[tokio::test]
     async fn test_sync_message_cache_on_startup() {
         let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
@@ -0,0 +1,139 @@
+//! Whether a user has set up secure messaging
+//!
+//! Inviting someone to a group needs one of their MLS key packages, and delivering the
+//! welcome or a direct message needs relays they read from. [`Whitenoise::messaging_readiness`]
+//! checks both before the UI offers to message a user.
+
+use std::time::{Duration, Instant};
+
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::RelayType;
+use crate::whitenoise::{Whitenoise, error::Result, users::UserSyncMode};
+
+/// How long a readiness result is reused before the user's relays are asked again
+const MESSAGING_READINESS_TTL: Duration = Duration::from_secs(60);
+
+/// What a user has published that others need to message them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessagingReadiness {
+    /// The user has a NIP-65 relay list (kind 10002)
+    pub has_nip65_relays: bool,
+
+    /// The user has an inbox relay list (kind 10050)
+    pub has_inbox_relays: bool,
+
+    /// The user has a correctly signed MLS key package (kind 443) on their relays
+    pub has_key_package: bool,
+}
+
+impl MessagingReadiness {
+    /// Whether the user can be added to a group
+    ///
+    /// The welcome goes to their inbox relays, or their NIP-65 relays when they have none.
+    pub fn can_be_invited(&self) -> bool {
+        self.has_key_package && (self.has_inbox_relays || self.has_nip65_relays)
+    }
+
+    /// Whether the user can receive NIP-17 direct messages
+    pub fn can_receive_direct_messages(&self) -> bool {
+        self.has_inbox_relays
+    }
+}
+
+impl Whitenoise {
+    /// Reports whether a user has published what's needed to message them.
+    ///
+    /// Refreshes the user's relay lists and looks up their latest key package. Results are
+    /// cached for a minute, so a contact list can check every entry without hammering relays.
+    ///
+    /// # Arguments
+    /// * `pubkey` - The user to check
+    pub async fn messaging_readiness(&self, pubkey: &PublicKey) -> Result<MessagingReadiness> {
+        if let Some(cached) = self.messaging_readiness_cache.get(pubkey)
+            && cached.0.elapsed() < MESSAGING_READINESS_TTL
+        {
+            return Ok(cached.1);
+        }
+
+        let user = self
+            .find_or_create_user_by_pubkey(pubkey, UserSyncMode::Background)
+            .await?;
+        if let Err(e) = user.update_relay_lists(self).await {
+            tracing::warn!(
+                target: "whitenoise::messaging_readiness",
+                "Failed to refresh relay lists of {}, using cached ones: {}",
+                pubkey.to_hex(),
+                e
+            );
+        }
+
+        let has_nip65_relays = !user
+            .relays(RelayType::Nip65, &self.database)
+            .await?
+            .is_empty();
+        let has_inbox_relays = !user
+            .relays(RelayType::Inbox, &self.database)
+            .await?
+            .is_empty();
+        let has_key_package = user
+            .key_package_event(self)
+            .await?
+            .is_some_and(|event| event.verify().is_ok());
+
+        let readiness = MessagingReadiness {
+            has_nip65_relays,
+            has_inbox_relays,
+            has_key_package,
+        };
+        self.messaging_readiness_cache
+            .insert(*pubkey, (Instant::now(), readiness));
+        Ok(readiness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::*;
+
+    #[test]
+    fn test_readiness_requirements() {
+        let ready = MessagingReadiness {
+            has_nip65_relays: true,
+            has_inbox_relays: false,
+            has_key_package: true,
+        };
+        assert!(ready.can_be_invited());
+        assert!(!ready.can_receive_direct_messages());
+
+        assert!(!MessagingReadiness::default().can_be_invited());
+        assert!(
+            !MessagingReadiness {
+                has_key_package: false,
+                ..ready
+            }
+            .can_be_invited()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_messaging_readiness_is_cached() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let pubkey = Keys::generate().public_key();
+        let cached = MessagingReadiness {
+            has_nip65_relays: true,
+            has_inbox_relays: true,
+            has_key_package: true,
+        };
+        whitenoise
+            .messaging_readiness_cache
+            .insert(pubkey, (Instant::now(), cached));
+
+        assert_eq!(
+            whitenoise.messaging_readiness(&pubkey).await.unwrap(),
+            cached
+        );
+    }
+}
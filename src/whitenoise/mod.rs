@@ -1,10 +1,12 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Context;
 use dashmap::DashMap;
-use nostr_sdk::{PublicKey, RelayUrl, ToBech32};
+use nostr_sdk::nips::nip49::{EncryptedSecretKey, KeySecurity};
+use nostr_sdk::{EventId, PublicKey, RelayUrl, Timestamp, ToBech32, Url};
 use tokio::sync::{
     Mutex, OnceCell, Semaphore, broadcast,
     mpsc::{self, Sender},
@@ -12,33 +14,53 @@ use tokio::sync::{
 };
 use tokio::task::JoinHandle;
 
+pub mod account_sync;
 pub mod accounts;
 pub mod aggregated_message;
 pub mod app_settings;
+pub mod backup;
+pub mod blossom;
+pub mod chat_list;
 pub mod database;
+pub mod direct_messages;
 pub mod error;
-mod event_processor;
+pub mod event_processor;
 pub mod event_tracker;
 pub mod follows;
 pub mod group_information;
+pub mod group_sync;
 pub mod groups;
+pub mod history_export;
 pub mod key_packages;
+pub mod link_previews;
+pub mod logs;
 pub mod media_files;
 pub mod message_aggregator;
 pub mod message_streaming;
 pub mod messages;
+pub mod messaging_readiness;
+pub mod nip05;
+pub mod onboarding;
+pub mod outbox;
+pub mod relay_auth;
 pub mod relays;
 pub mod scheduled_tasks;
 pub mod secrets_store;
+pub mod self_check;
 pub mod storage;
+pub mod storage_usage;
+pub mod typing_indicators;
 pub mod users;
 pub mod utils;
 pub mod welcomes;
 
-use crate::init_tracing;
-use crate::nostr_manager::NostrManager;
+use crate::nostr_manager::{
+    CacheStats, FetchTimeouts, NostrManager, ReconnectPolicy, SubscriptionBreakdown,
+    SubscriptionCategory,
+};
+use crate::{init_tracing, reinit_tracing, release_log_file_writer, set_log_filter};
 
-use crate::types::ProcessableEvent;
+use crate::types::{ProcessableEventSender, RetryConfig, RetryPolicy};
 use accounts::*;
 use app_settings::*;
 use database::*;
@@ -58,9 +80,132 @@ pub struct WhitenoiseConfig {
 
     /// Configuration for the message aggregator
     pub message_aggregator_config: Option<message_aggregator::AggregatorConfig>,
+
+    /// Whether to re-target the giftwrap subscription when an account's inbox relays change
+    pub resubscribe_giftwrap_on_inbox_change: bool,
+
+    /// Whether `delete_all_data` should release the open log file and start a fresh one
+    pub reinitialize_tracing_on_data_reset: bool,
+
+    /// Retry limits, backoff, and retryable error classes per event kind
+    pub retry_config: RetryConfig,
+
+    /// Blossom servers for media, in order of preference
+    ///
+    /// Uploads are mirrored to every server; downloads try each in turn.
+    pub blossom_servers: Vec<Url>,
+
+    /// How long the authorization signed for each Blossom upload stays valid
+    ///
+    /// Authorizations are also scoped to the hash of the uploaded blob. Some servers reject
+    /// long-lived authorizations; lower this for them, keeping it above the time an upload
+    /// takes to start on slow connections.
+    pub blossom_auth_expiry: Duration,
+
+    /// How often to check that connected relays still answer requests (`None` disables the probe)
+    pub relay_health_probe_interval: Option<Duration>,
+
+    /// Reconnection delays and backoff for relays that keep failing
+    pub reconnect_policy: ReconnectPolicy,
+
+    /// How often to re-establish subscriptions that are no longer operational (`None` disables the check)
+    pub ensure_subscriptions_interval: Option<Duration>,
+
+    /// How often to retry failed messages in the accounts' outboxes (`None` disables the retry)
+    ///
+    /// Only messages whose group has a connected relay are retried, so queued messages go out
+    /// shortly after the relays reconnect.
+    pub outbox_retry_interval: Option<Duration>,
+
+    /// How often to re-check users' NIP-05 identifiers (`None` disables the re-check)
+    ///
+    /// Each run checks a batch of users whose cached result expired or whose identifier
+    /// changed, see [`Whitenoise::verify_nip05`].
+    pub nip05_verification_interval: Option<Duration>,
+
+    /// Age after which a published key package is replaced with a fresh one
+    pub key_package_max_age: Duration,
+
+    /// Largest chat media file that may be uploaded, in bytes
+    pub max_media_bytes: u64,
+
+    /// Whether chat media whose contents don't match its declared MIME type is rejected
+    ///
+    /// Off by default: the type detected from the file's contents is used instead, and the
+    /// mismatch is only logged. When on, uploads fail with [`WhitenoiseError::MimeMismatch`].
+    pub reject_mismatched_media_types: bool,
+
+    /// Window in which further contact list updates of an account are coalesced into one
+    ///
+    /// The first update after a quiet period is processed right away; later ones within the
+    /// window are held back and only the newest is processed when it ends. Zero disables
+    /// debouncing.
+    pub contact_list_debounce: Duration,
+
+    /// Whether a corrupt database is backed up and replaced with a fresh one on startup
+    ///
+    /// Off by default, so a corrupt database fails initialization with
+    /// [`WhitenoiseError::DbCorrupt`] and the app can ask the user before starting over.
+    pub recover_corrupt_database: bool,
+
+    /// Connection pool size and timeouts for the database
+    ///
+    /// The defaults suit an app with a few accounts. Headless deployments serving many
+    /// accounts can raise the pool size and acquire timeout if queries time out waiting for a
+    /// connection; see [`DatabasePoolConfig`] for the tradeoffs.
+    pub database_pool: DatabasePoolConfig,
+
+    /// How long one-off fetches wait for relays, per kind of event
+    ///
+    /// Giving up only shortens the result: a fetch returns whatever arrived before its
+    /// timeout. Raise these on slow networks if profiles or key packages go missing.
+    pub fetch_timeouts: FetchTimeouts,
+
+    /// Whether [`Whitenoise::fetch_link_preview`] may fetch pages of links shared in messages
+    ///
+    /// Turn this off for users who don't want websites to learn when they read a chat; no
+    /// preview requests are made then.
+    pub link_previews_enabled: bool,
+
+    /// Whether link previews may fetch pages on loopback, private and link-local addresses
+    ///
+    /// Off by default, since links come from other members and could point at services on
+    /// the user's own network. Only turn this on for local development.
+    pub link_previews_allow_private_hosts: bool,
+
+    /// How publishes that failed on some relays are retried (`None` disables retries)
+    ///
+    /// Only relays that timed out, couldn't be reached or reported a temporary problem are
+    /// retried; relays that rejected the event for good are not.
+    pub publish_retry: Option<RetryPolicy>,
+
+    /// Relays used to bootstrap, replacing the built-in list (`None` uses the built-in list)
+    ///
+    /// New accounts start out with these relays, and they are queried for users who haven't
+    /// published a relay list. Set this where the built-in relays are blocked or to use
+    /// self-hosted relays. An empty list is rejected by [`Whitenoise::initialize_whitenoise`].
+    pub default_relays: Option<Vec<RelayUrl>>,
+
+    /// Whether the salt that subscription ids are derived with is kept across restarts
+    ///
+    /// Off by default, so subscription ids change with every launch and relays can't link the
+    /// subscriptions of one session to the next. Turning it on keeps the salt in the data
+    /// directory, which lets relays reuse subscriptions and makes logs comparable across
+    /// sessions, at the cost of relays being able to recognize the same client and the
+    /// accounts it follows after a restart.
+    pub persist_session_salt: bool,
 }
 
 impl WhitenoiseConfig {
+    /// Default limit for chat media uploads (100 MiB)
+    pub const DEFAULT_MAX_MEDIA_BYTES: u64 = 100 * 1024 * 1024;
+
+    /// Default validity of Blossom upload authorizations
+    pub const DEFAULT_BLOSSOM_AUTH_EXPIRY: Duration = Duration::from_secs(5 * 60);
+
+    /// Default window for coalescing contact list updates
+    pub const DEFAULT_CONTACT_LIST_DEBOUNCE: Duration = Duration::from_secs(2);
+
     pub fn new(data_dir: &Path, logs_dir: &Path) -> Self {
         let env_suffix = if cfg!(debug_assertions) {
             "dev"
@@ -74,6 +219,28 @@ impl WhitenoiseConfig {
             data_dir: formatted_data_dir,
             logs_dir: formatted_logs_dir,
             message_aggregator_config: None, // Use default MessageAggregator configuration
+            resubscribe_giftwrap_on_inbox_change: true,
+            reinitialize_tracing_on_data_reset: true,
+            retry_config: RetryConfig::default(),
+            blossom_servers: vec![Whitenoise::default_blossom_url()],
+            blossom_auth_expiry: Self::DEFAULT_BLOSSOM_AUTH_EXPIRY,
+            relay_health_probe_interval: Some(Duration::from_secs(60)),
+            reconnect_policy: ReconnectPolicy::default(),
+            ensure_subscriptions_interval: Some(Duration::from_secs(15 * 60)),
+            outbox_retry_interval: Some(Duration::from_secs(30)),
+            nip05_verification_interval: Some(Duration::from_secs(60 * 60)),
+            key_package_max_age: scheduled_tasks::DEFAULT_KEY_PACKAGE_MAX_AGE,
+            max_media_bytes: Self::DEFAULT_MAX_MEDIA_BYTES,
+            reject_mismatched_media_types: false,
+            contact_list_debounce: Self::DEFAULT_CONTACT_LIST_DEBOUNCE,
+            recover_corrupt_database: false,
+            database_pool: DatabasePoolConfig::default(),
+            fetch_timeouts: FetchTimeouts::default(),
+            link_previews_enabled: true,
+            link_previews_allow_private_hosts: false,
+            publish_retry: Some(RetryPolicy::for_publishing()),
+            default_relays: None,
+            persist_session_salt: false,
         }
     }
 
@@ -83,20 +250,41 @@ impl WhitenoiseConfig {
         logs_dir: &Path,
         aggregator_config: message_aggregator::AggregatorConfig,
     ) -> Self {
-        let env_suffix = if cfg!(debug_assertions) {
-            "dev"
-        } else {
-            "release"
-        };
-        let formatted_data_dir = data_dir.join(env_suffix);
-        let formatted_logs_dir = logs_dir.join(env_suffix);
-
         Self {
-            data_dir: formatted_data_dir,
-            logs_dir: formatted_logs_dir,
             message_aggregator_config: Some(aggregator_config),
+            ..Self::new(data_dir, logs_dir)
         }
     }
+
+    /// Replace the Blossom server list used for media uploads and downloads
+    pub fn with_blossom_servers(mut self, servers: Vec<Url>) -> Self {
+        self.blossom_servers = servers;
+        self
+    }
+
+    /// Replace the built-in default relays
+    pub fn with_default_relays(mut self, relays: Vec<RelayUrl>) -> Self {
+        self.default_relays = Some(relays);
+        self
+    }
+
+    /// Replace the database connection pool settings
+    pub fn with_database_pool(mut self, database_pool: DatabasePoolConfig) -> Self {
+        self.database_pool = database_pool;
+        self
+    }
+
+    /// Keep the subscription id salt across restarts; see [`Self::persist_session_salt`]
+    pub fn with_persistent_session_salt(mut self, enabled: bool) -> Self {
+        self.persist_session_salt = enabled;
+        self
+    }
+
+    /// Back up and recreate the database instead of failing when it is corrupt
+    pub fn with_corrupt_database_recovery(mut self, enabled: bool) -> Self {
+        self.recover_corrupt_database = enabled;
+        self
+    }
 }
 
 pub struct Whitenoise {
@@ -105,16 +293,41 @@ pub struct Whitenoise {
     nostr: NostrManager,
     secrets_store: SecretsStore,
     storage: storage::Storage,
-    message_aggregator: message_aggregator::MessageAggregator,
+    message_aggregator: Arc<message_aggregator::MessageAggregator>,
     message_stream_manager: message_streaming::MessageStreamManager,
-    event_sender: Sender<ProcessableEvent>,
+    event_sender: ProcessableEventSender,
     shutdown_sender: Sender<()>,
     /// Per-account concurrency guards to prevent race conditions in contact list processing
     contact_list_guards: DashMap<PublicKey, Arc<Semaphore>>,
+    /// Per-account debounce state for contact list updates
+    contact_list_debounce: DashMap<PublicKey, event_processor::ContactListDebounce>,
     /// Shutdown signal for scheduled tasks
     scheduler_shutdown: watch::Sender<bool>,
     /// Handles for spawned scheduler tasks
     scheduler_handles: Mutex<Vec<JoinHandle<()>>>,
+    /// Configured Blossom server that most recently served a download
+    last_successful_blossom_server: std::sync::RwLock<Option<Url>>,
+    /// Counters reported by `event_processor_stats`
+    event_processor_metrics: event_processor::stats::EventProcessorMetrics,
+    /// When each account last sent a typing indicator to each group
+    typing_indicators_sent: DashMap<(PublicKey, mdk_core::prelude::GroupId), std::time::Instant>,
+    /// Held while retrying an outbox so the same message is never re-published twice at once
+    outbox_retry: Mutex<()>,
+    /// Per-blob guards so concurrent uploads of the same encrypted media share one Blossom upload
+    media_upload_guards: DashMap<[u8; 32], Arc<Semaphore>>,
+    /// Background tasks spawned on behalf of each account, aborted when it logs out
+    account_background_tasks: DashMap<PublicKey, Vec<tokio::task::AbortHandle>>,
+    /// Link previews fetched so far, by URL
+    link_preview_cache: DashMap<String, link_previews::CachedLinkPreview>,
+    /// Recent messaging readiness checks, with the time they were made
+    messaging_readiness_cache:
+        DashMap<PublicKey, (std::time::Instant, messaging_readiness::MessagingReadiness)>,
+    /// Groups that fell behind their MLS epoch, by account and hex-encoded Nostr group ID
+    group_sync_status: DashMap<(PublicKey, String), group_sync::GroupSyncStatus>,
+    /// Relays an AUTH handshake is running for, with a newer challenge that arrived meanwhile
+    relay_auth_in_flight: DashMap<RelayUrl, Option<String>>,
+    /// Categories paused by `enter_background_mode`, `None` while in the foreground
+    background_paused: Mutex<Option<Vec<SubscriptionCategory>>>,
 }
 
 static GLOBAL_WHITENOISE: OnceCell<Whitenoise> = OnceCell::const_new();
@@ -132,8 +345,23 @@ impl std::fmt::Debug for Whitenoise {
             .field("event_sender", &"<REDACTED>")
             .field("shutdown_sender", &"<REDACTED>")
             .field("contact_list_guards", &"<REDACTED>")
+            .field("contact_list_debounce", &"<REDACTED>")
             .field("scheduler_shutdown", &"<REDACTED>")
             .field("scheduler_handles", &"<REDACTED>")
+            .field("event_processor_metrics", &"<REDACTED>")
+            .field("typing_indicators_sent", &"<REDACTED>")
+            .field("outbox_retry", &"<REDACTED>")
+            .field("media_upload_guards", &"<REDACTED>")
+            .field("account_background_tasks", &"<REDACTED>")
+            .field("link_preview_cache", &"<REDACTED>")
+            .field("messaging_readiness_cache", &"<REDACTED>")
+            .field("group_sync_status", &"<REDACTED>")
+            .field("relay_auth_in_flight", &"<REDACTED>")
+            .field("background_paused", &"<REDACTED>")
+            .field(
+                "last_successful_blossom_server",
+                &self.last_successful_blossom_server,
+            )
             .finish()
     }
 }
@@ -148,9 +376,32 @@ impl Whitenoise {
     /// # Arguments
     ///
     /// * `config` - A [`WhitenoiseConfig`] struct specifying the data and log directories.
+    ///
+    /// # Errors
+    ///
+    /// A database that can't be opened fails with [`WhitenoiseError::DbLocked`] when another
+    /// process holds it, [`WhitenoiseError::DbCorrupt`] when the file is damaged (unless
+    /// [`WhitenoiseConfig::recover_corrupt_database`] is set) and
+    /// [`WhitenoiseError::DbOpenFailed`] otherwise. An empty
+    /// [`WhitenoiseConfig::default_relays`] override or a database pool without connections
+    /// fails with [`WhitenoiseError::Configuration`].
     pub async fn initialize_whitenoise(config: WhitenoiseConfig) -> Result<()> {
+        if config.default_relays.as_ref().is_some_and(Vec::is_empty) {
+            return Err(WhitenoiseError::Configuration(
+                "default_relays must list at least one relay, or be None to use the built-in relays"
+                    .to_string(),
+            ));
+        }
+        if config.database_pool.max_connections == 0 {
+            return Err(WhitenoiseError::Configuration(
+                "database_pool.max_connections must be at least 1".to_string(),
+            ));
+        }
+
         // Create event processing channels
+        let (priority_event_sender, priority_event_receiver) = mpsc::channel(500);
         let (event_sender, event_receiver) = mpsc::channel(500);
+        let event_sender = ProcessableEventSender::new(priority_event_sender, event_sender);
         let (shutdown_sender, shutdown_receiver) = mpsc::channel(1);
 
         // Create scheduler shutdown channel
@@ -173,25 +424,42 @@ impl Whitenoise {
 
         tracing::debug!(target: "whitenoise::initialize_whitenoise", "Logging initialized in directory: {:?}", logs_dir);
 
-        let database = Arc::new(Database::new(data_dir.join("whitenoise.sqlite")).await?);
+        // Create SecretsStore
+        let secrets_store = SecretsStore::new(data_dir);
+
+        #[cfg(feature = "sqlcipher")]
+        let passphrase = Some(secrets_store.get_or_create_database_passphrase()?);
+        #[cfg(not(feature = "sqlcipher"))]
+        let passphrase: Option<String> = None;
+        let database = Arc::new(Self::open_database(&config, passphrase.as_deref()).await?);
 
         // Create NostrManager with event_sender for direct event queuing
         let nostr =
-            NostrManager::new(event_sender.clone(), Arc::new(WhitenoiseEventTracker::new(database.clone())), NostrManager::default_timeout())
-                .await?;
-
-        // Create SecretsStore
-        let secrets_store = SecretsStore::new(data_dir);
+            NostrManager::with_reconnect_policy(event_sender.clone(), Arc::new(WhitenoiseEventTracker::new(database.clone())), NostrManager::default_timeout(), config.reconnect_policy.clone())
+                .await?
+                .with_fetch_timeouts(config.fetch_timeouts.clone())
+                .with_publish_retry(config.publish_retry.clone());
+        let nostr = if config.persist_session_salt {
+            let session_salt = NostrManager::load_or_create_session_salt(
+                &data_dir.join(crate::nostr_manager::SESSION_SALT_FILE),
+            )?;
+            nostr.with_session_salt(session_salt)
+        } else {
+            nostr
+        };
 
         // Create Storage
         let storage = storage::Storage::new(data_dir).await?;
 
         // Create message aggregator - always initialize, use custom config if provided
-        let message_aggregator = if let Some(aggregator_config) = config.message_aggregator_config.clone() {
-            message_aggregator::MessageAggregator::with_config(aggregator_config)
-        } else {
-            message_aggregator::MessageAggregator::new()
-        };
+        let message_aggregator = Arc::new(
+            if let Some(aggregator_config) = config.message_aggregator_config.clone() {
+                message_aggregator::MessageAggregator::with_config(aggregator_config)
+            } else {
+                message_aggregator::MessageAggregator::new()
+            }
+            .with_data_dir(data_dir),
+        );
 
         let whitenoise = Self {
             config,
@@ -204,13 +472,25 @@ impl Whitenoise {
             event_sender,
             shutdown_sender,
             contact_list_guards: DashMap::new(),
+            contact_list_debounce: DashMap::new(),
             scheduler_shutdown,
             scheduler_handles: Mutex::new(Vec::new()),
+            last_successful_blossom_server: std::sync::RwLock::new(None),
+            event_processor_metrics: event_processor::stats::EventProcessorMetrics::new(),
+            typing_indicators_sent: DashMap::new(),
+            outbox_retry: Mutex::new(()),
+            media_upload_guards: DashMap::new(),
+            account_background_tasks: DashMap::new(),
+            link_preview_cache: DashMap::new(),
+            messaging_readiness_cache: DashMap::new(),
+            group_sync_status: DashMap::new(),
+            relay_auth_in_flight: DashMap::new(),
+            background_paused: Mutex::new(None),
         };
 
         // Create default relays in the database if they don't exist
         // TODO: Make this batch fetch and insert all relays at once
-        for relay in Relay::defaults() {
+        for relay in whitenoise.default_relays() {
             let _ = whitenoise.find_or_create_relay_by_url(&relay.url).await?;
         }
 
@@ -218,11 +498,13 @@ impl Whitenoise {
         AppSettings::find_or_create_default(&whitenoise.database).await?;
 
         // Add default relays to the Nostr client if they aren't already added
-        if whitenoise.nostr.client.relays().await.is_empty() {
+        if whitenoise.nostr.relays().await.is_empty() {
             // First time starting the app
-            for relay in Relay::defaults() {
-                whitenoise.nostr.client.add_relay(relay.url).await?;
-            }
+            let default_relay_urls = Relay::urls(&whitenoise.default_relays());
+            whitenoise
+                .nostr
+                .ensure_relays_connected(&default_relay_urls)
+                .await?;
         }
 
         // No need to wait for all the relays to be up
@@ -254,11 +536,34 @@ impl Whitenoise {
             "Starting event processing loop for loaded accounts"
         );
 
-        Self::start_event_processing_loop(whitenoise_ref, event_receiver, shutdown_receiver).await;
+        Self::start_event_processing_loop(
+            whitenoise_ref,
+            priority_event_receiver,
+            event_receiver,
+            shutdown_receiver,
+        )
+        .await;
 
         // Register and start scheduled background tasks
-        let tasks: Vec<Arc<dyn scheduled_tasks::Task>> =
-            vec![Arc::new(scheduled_tasks::KeyPackageMaintenance)];
+        let mut tasks: Vec<Arc<dyn scheduled_tasks::Task>> = vec![Arc::new(
+            scheduled_tasks::KeyPackageMaintenance::new(whitenoise_ref.config.key_package_max_age),
+        )];
+        if let Some(interval) = whitenoise_ref.config.relay_health_probe_interval {
+            tasks.push(Arc::new(scheduled_tasks::RelayHealthProbe::new(interval)));
+        }
+        if let Some(interval) = whitenoise_ref.config.ensure_subscriptions_interval {
+            tasks.push(Arc::new(scheduled_tasks::EnsureSubscriptions::new(
+                interval,
+            )));
+        }
+        if let Some(interval) = whitenoise_ref.config.outbox_retry_interval {
+            tasks.push(Arc::new(scheduled_tasks::OutboxRetry::new(interval)));
+        }
+        if let Some(interval) = whitenoise_ref.config.nip05_verification_interval {
+            tasks.push(Arc::new(scheduled_tasks::Nip05Reverification::new(
+                interval,
+            )));
+        }
         let scheduler_handles = scheduled_tasks::start_scheduled_tasks(
             whitenoise_ref,
             scheduler_shutdown_rx,
@@ -278,6 +583,47 @@ impl Whitenoise {
         Ok(())
     }
 
+    /// Open the database in the data directory, recreating it if it is corrupt and the
+    /// configuration allows it
+    async fn open_database(
+        config: &WhitenoiseConfig,
+        passphrase: Option<&str>,
+    ) -> Result<Database> {
+        let db_path = config.data_dir.join("whitenoise.sqlite");
+        match Self::open_database_at(&db_path, passphrase, &config.database_pool).await {
+            Err(WhitenoiseError::DbCorrupt(e)) if config.recover_corrupt_database => {
+                let backup = Database::move_aside(&db_path)
+                    .await
+                    .map_err(WhitenoiseError::DbOpenFailed)?;
+                tracing::warn!(
+                    target: "whitenoise::initialize_whitenoise",
+                    "Database is corrupt ({}), moved it to {:?} and starting with a fresh one",
+                    e,
+                    backup
+                );
+                Self::open_database_at(&db_path, passphrase, &config.database_pool).await
+            }
+            result => result,
+        }
+    }
+
+    #[cfg_attr(not(feature = "sqlcipher"), allow(unused_variables))]
+    async fn open_database_at(
+        db_path: &Path,
+        passphrase: Option<&str>,
+        pool_config: &DatabasePoolConfig,
+    ) -> Result<Database> {
+        #[cfg(feature = "sqlcipher")]
+        if let Some(passphrase) = passphrase {
+            return Database::new_encrypted(db_path.to_path_buf(), passphrase, pool_config)
+                .await
+                .map_err(WhitenoiseError::from_database_open);
+        }
+        Database::new_with_pool_config(db_path.to_path_buf(), pool_config)
+            .await
+            .map_err(WhitenoiseError::from_database_open)
+    }
+
     pub async fn setup_all_subscriptions(whitenoise_ref: &'static Whitenoise) -> Result<()> {
         Self::setup_global_users_subscriptions(whitenoise_ref).await?;
         Self::setup_accounts_subscriptions(whitenoise_ref).await?;
@@ -286,7 +632,7 @@ impl Whitenoise {
 
     async fn setup_global_users_subscriptions(whitenoise_ref: &Whitenoise) -> Result<()> {
         let users_with_relays = User::all_users_with_relay_urls(whitenoise_ref).await?;
-        let default_relays: Vec<RelayUrl> = Relay::urls(&Relay::defaults());
+        let default_relays: Vec<RelayUrl> = Relay::urls(&whitenoise_ref.default_relays());
 
         let Some(signer_account) = Account::first(&whitenoise_ref.database).await? else {
             tracing::info!(
@@ -358,9 +704,14 @@ impl Whitenoise {
     }
 
     async fn setup_accounts_subscriptions(whitenoise_ref: &'static Whitenoise) -> Result<()> {
-        let accounts = Account::all(&whitenoise_ref.database).await?;
+        let mut accounts = Account::all(&whitenoise_ref.database).await?;
+        // The active account's subscriptions come first so its chats are live soonest
+        if let Some(active) = AppSettings::active_account_pubkey(&whitenoise_ref.database).await? {
+            accounts.sort_by_key(|account| account.pubkey != active);
+        }
         for account in accounts {
-            let nip65_relays = account.nip65_relays(whitenoise_ref).await?;
+            // The account publishes its own follow and mute lists to its write relays
+            let nip65_relays = account.write_relays(whitenoise_ref).await?;
             let inbox_relays = account.inbox_relays(whitenoise_ref).await?;
             // Setup subscriptions for this account
             match whitenoise_ref
@@ -433,6 +784,8 @@ impl Whitenoise {
     /// This asynchronous method removes all persistent data associated with the Whitenoise instance.
     /// It deletes the nostr cache, database, MLS-related directories, media cache, and all log files.
     /// If the MLS directory exists, it is removed and then recreated as an empty directory.
+    /// Unless disabled in the config, the open log file is released before the logs are removed
+    /// and a fresh one is started in the same directory afterwards.
     /// This is useful for resetting the application to a clean state.
     pub async fn delete_all_data(&self) -> Result<()> {
         tracing::debug!(target: "whitenoise::delete_all_data", "Deleting all data");
@@ -440,6 +793,16 @@ impl Whitenoise {
         // Shutdown gracefully before deleting data
         self.shutdown().await?;
 
+        // Stop background work that would write to the data being deleted
+        let pubkeys: Vec<PublicKey> = self
+            .account_background_tasks
+            .iter()
+            .map(|entry| *entry.key())
+            .collect();
+        for pubkey in pubkeys {
+            self.cancel_background_fetch(&pubkey);
+        }
+
         // Remove nostr cache
         self.nostr.delete_all_data().await?;
 
@@ -462,6 +825,11 @@ impl Whitenoise {
         // Always recreate the empty MLS directory
         tokio::fs::create_dir_all(&mls_dir).await?;
 
+        // Release the open log file before removing it so its handle isn't leaked
+        if self.config.reinitialize_tracing_on_data_reset {
+            release_log_file_writer();
+        }
+
         // Remove logs
         if self.config.logs_dir.exists() {
             for entry in std::fs::read_dir(&self.config.logs_dir)? {
@@ -475,9 +843,48 @@ impl Whitenoise {
             }
         }
 
+        if self.config.reinitialize_tracing_on_data_reset {
+            reinit_tracing(&self.config.logs_dir)?;
+        }
+
         Ok(())
     }
 
+    /// Re-initializes file logging in a new logs directory.
+    ///
+    /// Tracing is installed globally once per process. This swaps the log file writer
+    /// underneath the existing subscriber, dropping the previous appender guard (which
+    /// flushes and closes the old file) and installing a new one in `logs_dir`.
+    /// Useful for embedders that reset or relocate application state at runtime.
+    ///
+    /// # Arguments
+    ///
+    /// * `logs_dir` - Directory the new log files should be written to
+    pub fn reinitialize_tracing(logs_dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(logs_dir)
+            .with_context(|| format!("Failed to create logs directory: {:?}", logs_dir))
+            .map_err(WhitenoiseError::from)?;
+        reinit_tracing(logs_dir)
+    }
+
+    /// Changes which log messages are recorded, without restarting.
+    ///
+    /// Replaces the filter that was read from `RUST_LOG` at startup, for both stdout and the
+    /// log file. Frontends can use it to raise verbosity while a user reproduces a bug and
+    /// lower it again afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `directives` - Filter in `RUST_LOG` syntax, e.g. `debug` or `info,whitenoise=trace`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::InvalidLogFilter`] if `directives` can't be parsed; the
+    /// current filter stays in place.
+    pub fn set_log_level(&self, directives: &str) -> Result<()> {
+        set_log_filter(directives)
+    }
+
     /// Gracefully shuts down all scheduled tasks.
     ///
     /// Sends shutdown signal to all running tasks and waits for them to complete.
@@ -533,6 +940,63 @@ impl Whitenoise {
             .unwrap())
     }
 
+    /// Fetches a single event by id.
+    ///
+    /// The local event cache is checked first; otherwise the event is requested from every
+    /// relay the client knows, waiting at most `timeout` (the default request timeout when
+    /// `None`). Fetched events are cached for later lookups.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the event to fetch
+    /// * `timeout` - How long to wait for relays to answer
+    ///
+    /// # Returns
+    /// The event, or `None` if no relay has it
+    pub async fn fetch_event(
+        &self,
+        id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<Option<nostr_sdk::Event>> {
+        let mut relays: Vec<RelayUrl> = self.nostr.relays().await;
+        if relays.is_empty() {
+            relays = Relay::urls(&self.default_relays());
+        }
+        Ok(self.nostr.fetch_event_by_id(id, &relays, timeout).await?)
+    }
+
+    /// Exports the account's private key encrypted with a passphrase (NIP-49).
+    ///
+    /// The returned `ncryptsec1...` string is safe to keep in cloud storage or a password
+    /// manager; [`Whitenoise::login_with_ncryptsec`] logs back in with it.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account whose key to export
+    /// * `passphrase` - The passphrase to encrypt the key with
+    pub async fn export_account_ncryptsec(
+        &self,
+        account: &Account,
+        passphrase: &str,
+    ) -> Result<String> {
+        let keys = self
+            .secrets_store
+            .get_nostr_keys_for_pubkey(&account.pubkey)?;
+        let passphrase = passphrase.to_string();
+        // scrypt is deliberately slow, keep it off the async runtime
+        tokio::task::spawn_blocking(move || {
+            EncryptedSecretKey::new(
+                keys.secret_key(),
+                passphrase,
+                backup::PASSPHRASE_LOG_N,
+                KeySecurity::Medium,
+            )
+            .map_err(|e| WhitenoiseError::Other(e.into()))?
+            .to_bech32()
+            .map_err(|e| WhitenoiseError::Other(e.into()))
+        })
+        .await?
+    }
+
     pub async fn export_account_npub(&self, account: &Account) -> Result<String> {
         Ok(account.pubkey.to_bech32().unwrap())
     }
@@ -551,15 +1015,31 @@ impl Whitenoise {
     /// - Any updates that arrived during fetch are merged into `initial_messages`
     /// - The receiver only yields updates AFTER the initial snapshot
     ///
+    /// Updates are emitted whenever the event processor caches a new message, reaction,
+    /// deletion or edit for the group, and when the relays answer for a message the account
+    /// sent; [`message_streaming::UpdateTrigger`] says which. Dropping the
+    /// subscription releases the stream when no other subscriber remains.
+    ///
     /// # Arguments
+    /// * `account` - The account subscribing to the group
     /// * `group_id` - The group to subscribe to
     ///
     /// # Returns
     /// A [`message_streaming::GroupMessageSubscription`] containing initial messages and a broadcast receiver
+    ///
+    /// # Errors
+    /// * [`WhitenoiseError::GroupNotFound`] - If the account doesn't know the group
+    /// * [`WhitenoiseError::AccountNotGroupMember`] - If the account isn't an active member
     pub async fn subscribe_to_group_messages(
         &self,
+        account: &Account,
         group_id: &mdk_core::prelude::GroupId,
     ) -> Result<message_streaming::GroupMessageSubscription> {
+        Account::find_by_pubkey(&account.pubkey, &self.database).await?; // Verify account exists (security check)
+
+        self.ensure_active_member(account, group_id)?;
+
+        let _registration = self.message_stream_manager.registration(group_id);
         let mut updates = self.message_stream_manager.subscribe(group_id);
 
         let fetched_messages =
@@ -576,6 +1056,8 @@ impl Whitenoise {
 
         loop {
             match updates.try_recv() {
+                // Typing indicators aren't part of the history
+                Ok(update) if update.trigger.is_typing() => continue,
                 Ok(update) => {
                     // Apply update: insert or replace by message ID
                     messages_map.insert(update.message.id.clone(), update.message);
@@ -598,6 +1080,7 @@ impl Whitenoise {
         Ok(message_streaming::GroupMessageSubscription {
             initial_messages,
             updates,
+            _registration,
         })
     }
 
@@ -611,7 +1094,7 @@ impl Whitenoise {
 
     pub(crate) async fn refresh_global_subscription_for_user(&self, user: &User) -> Result<()> {
         let users_with_relays = User::all_users_with_relay_urls(self).await?;
-        let default_relays: Vec<RelayUrl> = Relay::urls(&Relay::defaults());
+        let default_relays: Vec<RelayUrl> = Relay::urls(&self.default_relays());
 
         let Some(signer_account) = Account::first(&self.database).await? else {
             tracing::info!(
@@ -725,21 +1208,190 @@ impl Whitenoise {
         Ok(())
     }
 
+    /// Pauses the given subscription categories without touching the others.
+    ///
+    /// Useful to save battery by dropping non-essential subscriptions (e.g. user metadata)
+    /// while keeping giftwrap and group message subscriptions live. Paused categories are
+    /// skipped by [`Whitenoise::ensure_all_subscriptions`] and other refreshes until they
+    /// are resumed with [`Whitenoise::resume_subscriptions`].
+    ///
+    /// # Arguments
+    /// * `categories` - The subscription categories to pause
+    pub async fn pause_subscriptions(&self, categories: &[SubscriptionCategory]) -> Result<()> {
+        tracing::info!(
+            target: "whitenoise::pause_subscriptions",
+            "Pausing subscriptions: {:?}",
+            categories
+        );
+        self.nostr.pause_subscription_categories(categories).await;
+        Ok(())
+    }
+
+    /// Resumes previously paused subscription categories and re-creates their subscriptions.
+    ///
+    /// Uses the same best-effort strategy as [`Whitenoise::ensure_all_subscriptions`]:
+    /// subscription failures are logged, database errors are returned.
+    ///
+    /// # Arguments
+    /// * `categories` - The subscription categories to resume
+    pub async fn resume_subscriptions(&self, categories: &[SubscriptionCategory]) -> Result<()> {
+        tracing::info!(
+            target: "whitenoise::resume_subscriptions",
+            "Resuming subscriptions: {:?}",
+            categories
+        );
+        self.nostr.resume_subscription_categories(categories);
+
+        if categories.contains(&SubscriptionCategory::Metadata)
+            && let Err(e) = Self::setup_global_users_subscriptions(self).await
+        {
+            tracing::warn!(
+                target: "whitenoise::resume_subscriptions",
+                "Failed to resume global subscriptions: {}", e
+            );
+        }
+
+        let resumes_account_subscriptions = categories
+            .iter()
+            .any(|category| *category != SubscriptionCategory::Metadata);
+        if resumes_account_subscriptions {
+            for account in Account::all(&self.database).await? {
+                if let Err(e) = self.refresh_account_subscriptions(&account).await {
+                    tracing::warn!(
+                        target: "whitenoise::resume_subscriptions",
+                        "Failed to resume subscriptions for account {}: {}",
+                        account.pubkey.to_hex(),
+                        e
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Subscriptions dropped while the app is in the background
+    ///
+    /// Giftwraps and group messages stay live, so invites and messages still arrive.
+    const BACKGROUND_PAUSED_CATEGORIES: [SubscriptionCategory; 2] = [
+        SubscriptionCategory::Metadata,
+        SubscriptionCategory::FollowLists,
+    ];
+
+    /// Drops non-essential subscriptions when the app goes to the background.
+    ///
+    /// User metadata and follow list subscriptions are paused, giftwrap and group message
+    /// subscriptions are kept so invites and messages aren't missed. Paused subscriptions
+    /// aren't re-established by reconnects or [`Whitenoise::ensure_all_subscriptions`] until
+    /// [`Whitenoise::enter_foreground_mode`] is called. Does nothing if already in the
+    /// background.
+    pub async fn enter_background_mode(&self) -> Result<()> {
+        let mut background_paused = self.background_paused.lock().await;
+        if background_paused.is_some() {
+            return Ok(());
+        }
+
+        // Categories the app paused itself stay paused when coming back to the foreground
+        let already_paused = self.nostr.paused_subscription_categories();
+        let categories: Vec<SubscriptionCategory> = Self::BACKGROUND_PAUSED_CATEGORIES
+            .into_iter()
+            .filter(|category| !already_paused.contains(category))
+            .collect();
+
+        tracing::info!(
+            target: "whitenoise::enter_background_mode",
+            "Entering background mode, pausing {:?}",
+            categories
+        );
+        self.nostr.pause_subscription_categories(&categories).await;
+        *background_paused = Some(categories);
+        Ok(())
+    }
+
+    /// Restores the subscriptions dropped by [`Whitenoise::enter_background_mode`].
+    ///
+    /// Runs [`Whitenoise::ensure_all_subscriptions`], which also re-establishes any
+    /// subscription lost while in the background. Does nothing if not in the background.
+    pub async fn enter_foreground_mode(&self) -> Result<()> {
+        let mut background_paused = self.background_paused.lock().await;
+        let Some(categories) = background_paused.take() else {
+            return Ok(());
+        };
+
+        tracing::info!(
+            target: "whitenoise::enter_foreground_mode",
+            "Entering foreground mode, resuming {:?}",
+            categories
+        );
+        self.nostr.resume_subscription_categories(&categories);
+        self.ensure_all_subscriptions().await
+    }
+
+    /// Whether the app is in background mode, see [`Whitenoise::enter_background_mode`]
+    pub async fn is_in_background_mode(&self) -> bool {
+        self.background_paused.lock().await.is_some()
+    }
+
+    /// Returns the subscription categories that are currently paused
+    pub fn paused_subscriptions(&self) -> Vec<SubscriptionCategory> {
+        self.nostr
+            .paused_subscription_categories()
+            .into_iter()
+            .collect()
+    }
+
+    /// Returns how many of each of the account's subscriptions are active, and on which relays
+    ///
+    /// When [`Whitenoise::is_account_subscriptions_operational`] reports a problem, this shows
+    /// which subscription is missing and where the others are active, including inbox relays
+    /// that don't hold the giftwrap subscription.
+    pub async fn subscription_breakdown(&self, account: &Account) -> Result<SubscriptionBreakdown> {
+        let inbox_relays = Relay::urls(&account.inbox_relays(self).await?);
+        Ok(self
+            .nostr
+            .subscription_breakdown(&account.pubkey, &inbox_relays)
+            .await)
+    }
+
+    /// Returns the number, approximate size and age range of the locally cached Nostr events
+    pub async fn nostr_cache_stats(&self) -> Result<CacheStats> {
+        Ok(self.nostr.cache_stats().await?)
+    }
+
+    /// Drops locally cached Nostr events created before `older_than`
+    ///
+    /// Recent events stay cached and active subscriptions keep running. Pruned events are
+    /// fetched from relays again the next time they are needed.
+    pub async fn prune_nostr_cache(&self, older_than: Timestamp) -> Result<()> {
+        Ok(self.nostr.prune_cache(older_than).await?)
+    }
+
     /// Checks if account subscriptions are operational
     ///
     /// Returns true if at least one relay is connected or connecting AND
     /// expected subscriptions exist (minimum: follow_list and giftwrap).
+    ///
+    /// Relays backed off under [`ReconnectPolicy`] don't count as connected, so an account whose
+    /// relays are all in backoff is not operational until one of them reconnects.
     pub async fn is_account_subscriptions_operational(&self, account: &Account) -> Result<bool> {
         let sub_count = self
             .nostr
             .count_subscriptions_for_account(&account.pubkey)
             .await;
 
-        if sub_count < 2 {
+        // Follow list and giftwrap subscriptions are always expected unless paused
+        let expected_count = [
+            SubscriptionCategory::FollowLists,
+            SubscriptionCategory::Giftwraps,
+        ]
+        .into_iter()
+        .filter(|category| !self.nostr.is_subscription_category_paused(*category))
+        .count();
+        if sub_count < expected_count {
             return Ok(false); // Early exit if subscriptions missing
         }
 
-        let user_relays: Vec<RelayUrl> = Relay::urls(&account.nip65_relays(self).await?);
+        let user_relays: Vec<RelayUrl> = Relay::urls(&account.write_relays(self).await?);
         let inbox_relays: Vec<RelayUrl> = Relay::urls(&account.inbox_relays(self).await?);
 
         let (group_relays, _) = self.extract_groups_relays_and_ids(account).await?;
@@ -761,7 +1413,14 @@ impl Whitenoise {
     /// Returns true if at least one relay (from the client pool) is connected or connecting
     /// AND at least one global subscription exists.
     pub async fn is_global_subscriptions_operational(&self) -> Result<bool> {
-        let all_relays: Vec<RelayUrl> = self.nostr.client.relays().await.into_keys().collect();
+        if self
+            .nostr
+            .is_subscription_category_paused(SubscriptionCategory::Metadata)
+        {
+            return Ok(true); // Nothing to check while paused
+        }
+
+        let all_relays: Vec<RelayUrl> = self.nostr.relays().await;
 
         if !self.nostr.has_any_relay_connected(&all_relays).await {
             return Ok(false);
@@ -787,6 +1446,7 @@ impl Whitenoise {
 #[cfg(test)]
 pub mod test_utils {
     use super::*;
+    use crate::nostr_manager::nostr_client::MemoryNostrClient;
     use crate::whitenoise::relays::Relay;
     use mdk_core::prelude::*;
     use nostr_sdk::{Keys, PublicKey, RelayUrl};
@@ -812,7 +1472,8 @@ pub mod test_utils {
     /// Creates a mock Whitenoise instance for testing.
     ///
     /// This function creates a Whitenoise instance with a minimal configuration and database.
-    /// It also creates a NostrManager instance that connects to the local test relays.
+    /// Its NostrManager connects, publishes, fetches and subscribes through a
+    /// [`MemoryNostrClient`], so no relay has to be running.
     ///
     /// # Returns
     ///
@@ -822,9 +1483,6 @@ pub mod test_utils {
     ///   - `TempDir`: The temporary directory for data storage
     ///   - `TempDir`: The temporary directory for log storage
     pub(crate) async fn create_mock_whitenoise() -> (Whitenoise, TempDir, TempDir) {
-        // Wait for local relays to be ready in test environment
-        wait_for_test_relays().await;
-
         let (config, data_temp, logs_temp) = create_test_config();
 
         // Create directories manually to avoid issues
@@ -842,34 +1500,28 @@ pub mod test_utils {
         let secrets_store = SecretsStore::new(&config.data_dir);
 
         // Create channels but don't start processing loop to avoid network calls
+        let (priority_event_sender, _priority_event_receiver) = mpsc::channel(10);
         let (event_sender, _event_receiver) = mpsc::channel(10);
+        let event_sender = ProcessableEventSender::new(priority_event_sender, event_sender);
         let (shutdown_sender, _shutdown_receiver) = mpsc::channel(1);
         let (scheduler_shutdown, _scheduler_shutdown_rx) = watch::channel(false);
 
-        // Create NostrManager for testing - now with actual relay connections
-        // to use the local development relays running in docker
+        // Create NostrManager for testing - relays and events stay in memory
         let nostr = NostrManager::new(
             event_sender.clone(),
             Arc::new(event_tracker::WhitenoiseEventTracker::new(database.clone())),
             NostrManager::default_timeout(),
         )
         .await
-        .expect("Failed to create NostrManager");
-
-        // connect to default relays
-        let default_relays_urls: Vec<RelayUrl> = Relay::urls(&Relay::defaults());
-
-        for relay in default_relays_urls {
-            nostr.client.add_relay(relay).await.unwrap();
-        }
-
-        nostr.client.connect().await;
+        .expect("Failed to create NostrManager")
+        .with_network(Arc::new(MemoryNostrClient::default()));
 
         // Create Storage
         let storage = storage::Storage::new(data_temp.path()).await.unwrap();
 
         // Create message aggregator for testing
-        let message_aggregator = message_aggregator::MessageAggregator::new();
+        let message_aggregator =
+            Arc::new(message_aggregator::MessageAggregator::new().with_data_dir(&config.data_dir));
 
         let whitenoise = Whitenoise {
             config,
@@ -882,92 +1534,25 @@ pub mod test_utils {
             event_sender,
             shutdown_sender,
             contact_list_guards: DashMap::new(),
+            contact_list_debounce: DashMap::new(),
             scheduler_shutdown,
             scheduler_handles: Mutex::new(Vec::new()),
+            last_successful_blossom_server: std::sync::RwLock::new(None),
+            event_processor_metrics: event_processor::stats::EventProcessorMetrics::new(),
+            typing_indicators_sent: DashMap::new(),
+            outbox_retry: Mutex::new(()),
+            media_upload_guards: DashMap::new(),
+            account_background_tasks: DashMap::new(),
+            link_preview_cache: DashMap::new(),
+            messaging_readiness_cache: DashMap::new(),
+            group_sync_status: DashMap::new(),
+            relay_auth_in_flight: DashMap::new(),
+            background_paused: Mutex::new(None),
         };
 
         (whitenoise, data_temp, logs_temp)
     }
 
-    /// Wait for local test relays to be ready
-    async fn wait_for_test_relays() {
-        use std::time::Duration;
-        use tokio::time::{sleep, timeout};
-
-        // Only wait for relays in debug builds (where we use localhost relays)
-        if !cfg!(debug_assertions) {
-            return;
-        }
-
-        tracing::debug!(target: "whitenoise::test_utils", "Waiting for local test relays to be ready...");
-
-        let relay_urls = vec!["ws://localhost:8080", "ws://localhost:7777"];
-
-        for relay_url in relay_urls {
-            let mut attempts = 0;
-            const MAX_ATTEMPTS: u32 = 10;
-            const WAIT_INTERVAL: Duration = Duration::from_millis(500);
-
-            while attempts < MAX_ATTEMPTS {
-                // Try to establish a WebSocket connection to test readiness
-                match timeout(Duration::from_secs(2), test_relay_connection(relay_url)).await {
-                    Ok(Ok(())) => {
-                        tracing::debug!(target: "whitenoise::test_utils", "Relay {} is ready", relay_url);
-                        break;
-                    }
-                    Ok(Err(e)) => {
-                        tracing::debug!(target: "whitenoise::test_utils",
-                            "Relay {} not ready yet (attempt {}/{}): {:?}",
-                            relay_url, attempts + 1, MAX_ATTEMPTS, e);
-                    }
-                    Err(_) => {
-                        tracing::debug!(target: "whitenoise::test_utils",
-                            "Relay {} connection timeout (attempt {}/{})",
-                            relay_url, attempts + 1, MAX_ATTEMPTS);
-                    }
-                }
-
-                attempts += 1;
-                if attempts < MAX_ATTEMPTS {
-                    sleep(WAIT_INTERVAL).await;
-                }
-            }
-
-            if attempts >= MAX_ATTEMPTS {
-                tracing::warn!(target: "whitenoise::test_utils",
-                    "Relay {} may not be fully ready after {} attempts", relay_url, MAX_ATTEMPTS);
-            }
-        }
-
-        // Give relays a bit more time to stabilize
-        sleep(Duration::from_millis(100)).await;
-        tracing::debug!(target: "whitenoise::test_utils", "Relay readiness check completed");
-    }
-
-    /// Test if a relay is ready by attempting a simple connection
-    async fn test_relay_connection(
-        relay_url: &str,
-    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        use nostr_sdk::prelude::*;
-
-        // Create a minimal client for testing connection
-        let client = Client::default();
-        client.add_relay(relay_url).await?;
-
-        // Try to connect - this will fail if relay isn't ready
-        client.connect().await;
-
-        // Give it a moment to establish connection
-        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
-
-        // Check if we're connected
-        let relay_url_parsed = RelayUrl::parse(relay_url)?;
-        match client.relay(&relay_url_parsed).await {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e.into()),
-        }
-    }
-
     pub(crate) async fn test_get_whitenoise() -> &'static Whitenoise {
         // Initialize whitenoise for this specific test
         let (config, _data_temp, _logs_temp) = create_test_config();
@@ -996,6 +1581,44 @@ pub mod test_utils {
         )
     }
 
+    /// Creates a group with MDK directly and has `member` accept the welcome, so both accounts
+    /// are active members without waiting on relays, and messages to the group can be cached.
+    /// `member` needs a published key package.
+    pub(crate) async fn create_group_with_joined_member(
+        whitenoise: &Whitenoise,
+        creator: &Account,
+        member: &Account,
+    ) -> GroupId {
+        let key_package_relays = creator.key_package_relays(whitenoise).await.unwrap();
+        let key_package = whitenoise
+            .nostr
+            .fetch_user_key_package(member.pubkey, &Relay::urls(&key_package_relays))
+            .await
+            .unwrap()
+            .expect("member must have a published key package");
+        let creator_mdk = Account::create_mdk(creator.pubkey, &whitenoise.config.data_dir).unwrap();
+        let created = creator_mdk
+            .create_group(
+                &creator.pubkey,
+                vec![key_package],
+                create_nostr_group_config_data(vec![creator.pubkey]),
+            )
+            .unwrap();
+        let member_mdk = Account::create_mdk(member.pubkey, &whitenoise.config.data_dir).unwrap();
+        let welcome = member_mdk
+            .process_welcome(&EventId::all_zeros(), &created.welcome_rumors[0])
+            .unwrap();
+        member_mdk.accept_welcome(&welcome).unwrap();
+        group_information::GroupInformation::get_by_mls_group_id(
+            creator.pubkey,
+            &created.group.mls_group_id,
+            whitenoise,
+        )
+        .await
+        .unwrap();
+        created.group.mls_group_id
+    }
+
     pub(crate) async fn setup_multiple_test_accounts(
         whitenoise: &Whitenoise,
         count: usize,
@@ -1072,6 +1695,22 @@ mod tests {
             assert!(debug_str.contains("message_aggregator_config"));
         }
 
+        #[tokio::test]
+        async fn test_fetch_timeouts_reach_nostr_manager() {
+            let (mut whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+            assert_eq!(whitenoise.config.fetch_timeouts, FetchTimeouts::default());
+
+            let fetch_timeouts = FetchTimeouts {
+                key_packages: Duration::from_secs(30),
+                ..Default::default()
+            };
+            whitenoise.nostr = whitenoise
+                .nostr
+                .clone()
+                .with_fetch_timeouts(fetch_timeouts.clone());
+            assert_eq!(whitenoise.nostr.fetch_timeouts, fetch_timeouts);
+        }
+
         #[test]
         fn test_whitenoise_config_with_custom_aggregator() {
             let data_dir = std::path::Path::new("/test/data");
@@ -1081,6 +1720,11 @@ mod tests {
             let custom_config = message_aggregator::AggregatorConfig {
                 normalize_emoji: false,
                 enable_debug_logging: true,
+                persist_state: false,
+                muted_authors: message_aggregator::MutedAuthors::Hide,
+                emoji_equivalents: HashMap::new(),
+                max_reactors_per_emoji: None,
+                max_emoji_per_message: None,
             };
 
             let config = WhitenoiseConfig::new_with_aggregator_config(
@@ -1142,6 +1786,41 @@ mod tests {
                     .is_empty()
             );
         }
+
+        #[tokio::test]
+        async fn test_initialize_rejects_database_pool_without_connections() {
+            let (config, _data_temp, _logs_temp) = create_test_config();
+            let config = config.with_database_pool(DatabasePoolConfig {
+                max_connections: 0,
+                ..DatabasePoolConfig::default()
+            });
+
+            let result = Whitenoise::initialize_whitenoise(config).await;
+            assert!(matches!(result, Err(WhitenoiseError::Configuration(_))));
+        }
+
+        #[tokio::test]
+        async fn test_open_database_recovers_only_when_enabled() {
+            let (config, _data_temp, _logs_temp) = create_test_config();
+            std::fs::create_dir_all(&config.data_dir).unwrap();
+            let db_path = config.data_dir.join("whitenoise.sqlite");
+            std::fs::write(&db_path, vec![0x42; 8192]).unwrap();
+
+            let result = Whitenoise::open_database(&config, None).await;
+            assert!(matches!(result, Err(WhitenoiseError::DbCorrupt(_))));
+
+            let config = config.with_corrupt_database_recovery(true);
+            let database = Whitenoise::open_database(&config, None).await.unwrap();
+            assert!(Account::all(&database).await.unwrap().is_empty());
+
+            // The corrupt file is kept next to the fresh database
+            let backups = std::fs::read_dir(&config.data_dir)
+                .unwrap()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_name().to_string_lossy().contains(".corrupt-"))
+                .count();
+            assert_eq!(backups, 1);
+        }
     }
 
     // Data Management Tests
@@ -1150,6 +1829,8 @@ mod tests {
 
         #[tokio::test]
         async fn test_delete_all_data() {
+            // Deleting all data releases the global log file writer
+            let _tracing = crate::TRACING_TEST_LOCK.lock().await;
             let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
 
             // Create test files in the whitenoise directories
@@ -1196,9 +1877,7 @@ mod tests {
         }
     }
 
-    // API Tests (using mock to minimize network calls)
-    // NOTE: These tests still make some network calls through NostrManager
-    // For complete isolation, implement the trait-based mocking described above
+    // API Tests (using mock to avoid network calls)
     mod api_tests {
         use super::*;
         use mdk_core::prelude::GroupId;
@@ -1241,8 +1920,12 @@ mod tests {
         #[tokio::test]
         async fn test_subscribe_to_group_messages_returns_initial_messages() {
             let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
-            let group_id = GroupId::from_slice(&[99; 32]);
-            let test_pubkey = nostr_sdk::Keys::generate().public_key();
+            let creator_account = whitenoise.create_identity().await.unwrap();
+            let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+            let account = members[0].0.clone();
+            let group_id =
+                create_group_with_joined_member(&whitenoise, &creator_account, &account).await;
+            let test_pubkey = creator_account.pubkey;
 
             // Setup: Create group (required for foreign key constraint)
             group_information::GroupInformation::find_or_create_by_mls_group_id(
@@ -1262,11 +1945,17 @@ mod tests {
                 tags: nostr_sdk::Tags::new(),
                 is_reply: false,
                 reply_to_id: None,
+                reply_to: None,
+                quoted: None,
                 is_deleted: false,
+                edited_at: None,
+                is_muted: false,
                 content_tokens: vec![],
+                mentions: vec![],
                 reactions: message_aggregator::ReactionSummary::default(),
                 kind: 9,
                 media_attachments: vec![],
+                delivery_status: Default::default(),
             };
             let msg2 = message_aggregator::ChatMessage {
                 id: format!("{:0>64x}", 2),
@@ -1276,11 +1965,17 @@ mod tests {
                 tags: nostr_sdk::Tags::new(),
                 is_reply: false,
                 reply_to_id: None,
+                reply_to: None,
+                quoted: None,
                 is_deleted: false,
+                edited_at: None,
+                is_muted: false,
                 content_tokens: vec![],
+                mentions: vec![],
                 reactions: message_aggregator::ReactionSummary::default(),
                 kind: 9,
                 media_attachments: vec![],
+                delivery_status: Default::default(),
             };
 
             aggregated_message::AggregatedMessage::insert_message(
@@ -1300,7 +1995,7 @@ mod tests {
 
             // Test: Subscribe and verify initial messages
             let subscription = whitenoise
-                .subscribe_to_group_messages(&group_id)
+                .subscribe_to_group_messages(&account, &group_id)
                 .await
                 .unwrap();
 
@@ -1323,8 +2018,12 @@ mod tests {
         #[tokio::test]
         async fn test_subscribe_merges_concurrent_updates() {
             let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
-            let group_id = GroupId::from_slice(&[42; 32]);
-            let test_pubkey = nostr_sdk::Keys::generate().public_key();
+            let creator_account = whitenoise.create_identity().await.unwrap();
+            let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+            let account = members[0].0.clone();
+            let group_id =
+                create_group_with_joined_member(&whitenoise, &creator_account, &account).await;
+            let test_pubkey = creator_account.pubkey;
 
             // First emit an update before subscribing (simulates concurrent update scenario)
             // This tests the merge logic path
@@ -1336,11 +2035,17 @@ mod tests {
                 tags: nostr_sdk::Tags::new(),
                 is_reply: false,
                 reply_to_id: None,
+                reply_to: None,
+                quoted: None,
                 is_deleted: false,
+                edited_at: None,
+                is_muted: false,
                 content_tokens: vec![],
+                mentions: vec![],
                 reactions: message_aggregator::ReactionSummary::default(),
                 kind: 9,
                 media_attachments: vec![],
+                delivery_status: Default::default(),
             };
 
             // Emit an update (will be caught by subscriber during drain phase)
@@ -1349,13 +2054,14 @@ mod tests {
                 message_streaming::MessageUpdate {
                     trigger: message_streaming::UpdateTrigger::NewMessage,
                     message: test_message.clone(),
+                    notify: false,
                 },
             );
 
             // Subscribe - the drain loop should find the channel empty (no subscriber existed)
             // This test verifies the deduplication logic path compiles and runs
             let subscription = whitenoise
-                .subscribe_to_group_messages(&group_id)
+                .subscribe_to_group_messages(&account, &group_id)
                 .await
                 .unwrap();
 
@@ -1367,6 +2073,27 @@ mod tests {
                 "Initial messages should be empty (stream created on subscribe)"
             );
         }
+
+        #[tokio::test]
+        async fn test_subscribe_to_group_messages_requires_membership() {
+            let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+            let creator_account = whitenoise.create_identity().await.unwrap();
+            let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+            let group_id =
+                create_group_with_joined_member(&whitenoise, &creator_account, &members[0].0).await;
+            let outsider = whitenoise.create_identity().await.unwrap();
+
+            // An account that never joined the group doesn't know it
+            let result = whitenoise
+                .subscribe_to_group_messages(&outsider, &group_id)
+                .await;
+            assert!(matches!(result, Err(WhitenoiseError::GroupNotFound)));
+
+            let result = whitenoise
+                .subscribe_to_group_messages(&creator_account, &GroupId::from_slice(&[7; 32]))
+                .await;
+            assert!(matches!(result, Err(WhitenoiseError::GroupNotFound)));
+        }
     }
 
     // Subscription Status Tests
@@ -1392,6 +2119,77 @@ mod tests {
             );
         }
 
+        #[tokio::test]
+        async fn test_subscription_breakdown_with_live_subscriptions() {
+            let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+            let account = whitenoise.create_identity().await.unwrap();
+            let inbox_relays = Relay::urls(&account.inbox_relays(&whitenoise).await.unwrap());
+
+            // create_identity sets up follow_list and giftwrap subscriptions
+            let breakdown = whitenoise.subscription_breakdown(&account).await.unwrap();
+            assert!(breakdown.follow_list.count > 0);
+            assert_eq!(
+                breakdown.follow_list.count,
+                breakdown.follow_list.relays.len()
+            );
+            assert!(breakdown.giftwrap.count > 0);
+            assert_eq!(breakdown.inbox.count, inbox_relays.len());
+            assert!(breakdown.inbox_missing.is_empty());
+            assert_eq!(breakdown.group_messages.count, 0);
+            assert!(breakdown.missing(false).is_empty());
+            assert_eq!(
+                breakdown.missing(true),
+                vec![SubscriptionCategory::GroupMessages]
+            );
+
+            // Without subscriptions every inbox relay is reported as missing giftwraps
+            whitenoise
+                .nostr
+                .unsubscribe_account_subscriptions(&account.pubkey)
+                .await
+                .unwrap();
+            let breakdown = whitenoise.subscription_breakdown(&account).await.unwrap();
+            assert_eq!(breakdown.giftwrap.count, 0);
+            assert_eq!(breakdown.inbox_missing.len(), inbox_relays.len());
+            assert_eq!(
+                breakdown.missing(false),
+                vec![
+                    SubscriptionCategory::FollowLists,
+                    SubscriptionCategory::Giftwraps
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn test_follow_list_subscription_uses_write_relays() {
+            use crate::whitenoise::relays::{RelayMarker, RelayType};
+
+            let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+            let account = whitenoise.create_identity().await.unwrap();
+            let nip65_relays = account.nip65_relays(&whitenoise).await.unwrap();
+            assert!(nip65_relays.len() > 1);
+
+            // The account's own lists are published to its write relays, not a read-only one
+            let read_relay = &nip65_relays[0];
+            let user = account.user(&whitenoise.database).await.unwrap();
+            user.set_relay_marker(
+                read_relay,
+                RelayType::Nip65,
+                RelayMarker::Read,
+                &whitenoise.database,
+            )
+            .await
+            .unwrap();
+            whitenoise
+                .refresh_account_subscriptions(&account)
+                .await
+                .unwrap();
+
+            let breakdown = whitenoise.subscription_breakdown(&account).await.unwrap();
+            assert!(!breakdown.follow_list.relays.contains(&read_relay.url));
+            assert_eq!(breakdown.follow_list.count, nip65_relays.len() - 1);
+        }
+
         #[tokio::test]
         async fn test_is_global_subscriptions_operational_no_subscriptions() {
             let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
@@ -1618,6 +2416,245 @@ mod tests {
                 "Account2 should remain operational after ensure_all"
             );
         }
+
+        /// Processes what the network delivered, the way the event loops would, and
+        /// returns the kinds that arrived
+        async fn process_delivered_events(
+            whitenoise: &Whitenoise,
+            account: &Account,
+            events: &mut tokio::sync::mpsc::Receiver<crate::types::ProcessableEvent>,
+        ) -> Vec<nostr_sdk::Kind> {
+            let mut kinds = Vec::new();
+            while let Ok(processable) = events.try_recv() {
+                let crate::types::ProcessableEvent::NostrEvent {
+                    event,
+                    subscription_id: Some(subscription_id),
+                    ..
+                } = processable
+                else {
+                    continue;
+                };
+                kinds.push(event.kind);
+                if subscription_id.ends_with("_mls_messages") {
+                    let _ = whitenoise.handle_mls_message(account, event).await;
+                } else if subscription_id.starts_with("global_users_")
+                    && event.kind == nostr_sdk::Kind::Metadata
+                {
+                    whitenoise.handle_metadata(event).await.unwrap();
+                }
+            }
+            kinds
+        }
+
+        #[tokio::test]
+        async fn test_pause_and_resume_metadata_subscriptions() {
+            use nostr_sdk::{EventBuilder, Keys, Kind, Metadata};
+
+            let (mut whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+            let (event_sender, mut events) = tokio::sync::mpsc::channel(1000);
+            whitenoise.nostr = whitenoise.nostr.clone().with_network(Arc::new(
+                crate::nostr_manager::nostr_client::MemoryNostrClient::with_event_sender(
+                    event_sender.into(),
+                ),
+            ));
+            let account = whitenoise.create_identity().await.unwrap();
+            let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+            let member = members[0].0.clone();
+            let group_id = create_group_with_joined_member(&whitenoise, &account, &member).await;
+            let contact_keys = Keys::generate();
+            User::find_or_create_by_pubkey(&contact_keys.public_key(), &whitenoise.database)
+                .await
+                .unwrap();
+
+            whitenoise.ensure_all_subscriptions().await.unwrap();
+            whitenoise
+                .refresh_account_subscriptions(&account)
+                .await
+                .unwrap();
+            assert!(whitenoise.nostr.count_global_subscriptions().await > 0);
+            let account_sub_count = whitenoise
+                .nostr
+                .count_subscriptions_for_account(&account.pubkey)
+                .await;
+            // Drop what the new subscriptions replayed
+            while events.try_recv().is_ok() {}
+
+            whitenoise
+                .pause_subscriptions(&[SubscriptionCategory::Metadata])
+                .await
+                .unwrap();
+            assert_eq!(
+                whitenoise.paused_subscriptions(),
+                vec![SubscriptionCategory::Metadata]
+            );
+
+            // Metadata subscriptions are gone, message and giftwrap subscriptions stay live
+            assert_eq!(whitenoise.nostr.count_global_subscriptions().await, 0);
+            assert_eq!(
+                whitenoise
+                    .nostr
+                    .count_subscriptions_for_account(&account.pubkey)
+                    .await,
+                account_sub_count
+            );
+            assert!(
+                !whitenoise
+                    .nostr
+                    .giftwrap_subscription_relays(&account.pubkey)
+                    .await
+                    .is_empty()
+            );
+
+            // Periodic ensure must not resurrect paused subscriptions
+            whitenoise.ensure_all_subscriptions().await.unwrap();
+            assert_eq!(whitenoise.nostr.count_global_subscriptions().await, 0);
+            assert!(
+                whitenoise
+                    .is_account_subscriptions_operational(&account)
+                    .await
+                    .unwrap()
+            );
+
+            // While paused, a contact's metadata isn't processed but a group message is
+            let metadata_event = EventBuilder::metadata(&Metadata::new().name("Paused Contact"))
+                .sign_with_keys(&contact_keys)
+                .unwrap();
+            whitenoise
+                .nostr
+                .network
+                .send_event_to(&Relay::urls(&whitenoise.default_relays()), &metadata_event)
+                .await
+                .unwrap();
+
+            let member_mdk =
+                Account::create_mdk(member.pubkey, &whitenoise.config.data_dir).unwrap();
+            let mut rumor =
+                EventBuilder::new(Kind::Custom(9), "sent while paused").build(member.pubkey);
+            rumor.ensure_id();
+            let rumor_id = rumor.id.unwrap();
+            let message_event = member_mdk.create_message(&group_id, rumor).unwrap();
+            let group_relays: Vec<RelayUrl> = member_mdk
+                .get_relays(&group_id)
+                .unwrap()
+                .into_iter()
+                .collect();
+            whitenoise
+                .nostr
+                .network
+                .send_event_to(&group_relays, &message_event)
+                .await
+                .unwrap();
+
+            let delivered = process_delivered_events(&whitenoise, &account, &mut events).await;
+            assert!(!delivered.contains(&Kind::Metadata));
+            assert!(delivered.contains(&Kind::MlsGroupMessage));
+            let contact = User::find_by_pubkey(&contact_keys.public_key(), &whitenoise.database)
+                .await
+                .unwrap();
+            assert_eq!(contact.metadata.name, None);
+            assert!(
+                aggregated_message::AggregatedMessage::find_by_id(
+                    &rumor_id.to_string(),
+                    &group_id,
+                    &whitenoise.database
+                )
+                .await
+                .unwrap()
+                .is_some()
+            );
+
+            // Resuming picks the metadata back up
+            whitenoise
+                .resume_subscriptions(&[SubscriptionCategory::Metadata])
+                .await
+                .unwrap();
+            assert!(whitenoise.paused_subscriptions().is_empty());
+            assert!(whitenoise.nostr.count_global_subscriptions().await > 0);
+
+            let delivered = process_delivered_events(&whitenoise, &account, &mut events).await;
+            assert!(delivered.contains(&Kind::Metadata));
+            let contact = User::find_by_pubkey(&contact_keys.public_key(), &whitenoise.database)
+                .await
+                .unwrap();
+            assert_eq!(contact.metadata.name.as_deref(), Some("Paused Contact"));
+        }
+
+        #[tokio::test]
+        async fn test_background_and_foreground_mode() {
+            let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+            let account = whitenoise.create_identity().await.unwrap();
+            whitenoise.ensure_all_subscriptions().await.unwrap();
+            let account_sub_count = whitenoise
+                .nostr
+                .count_subscriptions_for_account(&account.pubkey)
+                .await;
+
+            whitenoise.enter_background_mode().await.unwrap();
+            assert!(whitenoise.is_in_background_mode().await);
+            assert_eq!(whitenoise.nostr.count_global_subscriptions().await, 0);
+            assert_eq!(
+                whitenoise
+                    .nostr
+                    .count_subscriptions_for_account(&account.pubkey)
+                    .await,
+                1,
+                "Only the giftwrap subscription should remain"
+            );
+
+            // Reconnects and periodic checks must not bring paused subscriptions back
+            whitenoise.ensure_all_subscriptions().await.unwrap();
+            assert_eq!(whitenoise.nostr.count_global_subscriptions().await, 0);
+
+            whitenoise.enter_foreground_mode().await.unwrap();
+            assert!(!whitenoise.is_in_background_mode().await);
+            assert!(whitenoise.paused_subscriptions().is_empty());
+            assert!(whitenoise.nostr.count_global_subscriptions().await > 0);
+            assert_eq!(
+                whitenoise
+                    .nostr
+                    .count_subscriptions_for_account(&account.pubkey)
+                    .await,
+                account_sub_count
+            );
+        }
+
+        #[tokio::test]
+        async fn test_pause_and_resume_account_subscription_category() {
+            let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+            let account = whitenoise.create_identity().await.unwrap();
+
+            whitenoise
+                .pause_subscriptions(&[SubscriptionCategory::FollowLists])
+                .await
+                .unwrap();
+            assert_eq!(
+                whitenoise
+                    .nostr
+                    .count_subscriptions_for_account(&account.pubkey)
+                    .await,
+                1,
+                "Only the giftwrap subscription should remain"
+            );
+            assert!(
+                whitenoise
+                    .is_account_subscriptions_operational(&account)
+                    .await
+                    .unwrap(),
+                "Paused subscriptions shouldn't count as missing"
+            );
+
+            whitenoise
+                .resume_subscriptions(&[SubscriptionCategory::FollowLists])
+                .await
+                .unwrap();
+            assert_eq!(
+                whitenoise
+                    .nostr
+                    .count_subscriptions_for_account(&account.pubkey)
+                    .await,
+                2
+            );
+        }
     }
 
     // Scheduler Lifecycle Tests
@@ -0,0 +1,303 @@
+//! NIP-05 verification of users' internet identifiers
+//!
+//! An identifier `name@domain` is verified by fetching `https://domain/.well-known/nostr.json`
+//! and checking that it maps `name` to the user's pubkey. Results are cached in the users table
+//! for [`NIP05_VERIFICATION_TTL`] and only count for the identifier that was checked, so a new
+//! `nip05` in the user's metadata is checked again. A scheduled task re-checks stale results in
+//! the background.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::Utc;
+use nostr_sdk::prelude::*;
+use serde::Deserialize;
+
+use crate::whitenoise::{
+    Whitenoise,
+    database::users::Nip05Verification,
+    error::{Result, WhitenoiseError},
+    users::User,
+};
+
+/// How long a verification result is used before the domain is asked again
+const NIP05_VERIFICATION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long a request for a domain's `nostr.json` may take
+const NIP05_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many users a background re-check covers per run
+const NIP05_REVERIFICATION_BATCH: usize = 50;
+
+/// The part of `nostr.json` needed to verify an identifier
+#[derive(Debug, Deserialize)]
+struct Nip05Document {
+    #[serde(default)]
+    names: HashMap<String, String>,
+}
+
+impl Whitenoise {
+    /// Checks that a NIP-05 identifier belongs to a user.
+    ///
+    /// Results are cached for a day. When the domain can't be reached the last known result
+    /// for the same identifier is returned instead of an error, or `false` if there is none.
+    ///
+    /// # Arguments
+    /// * `pubkey` - The user the identifier should belong to
+    /// * `identifier` - The identifier from the user's metadata, `name@domain` or just `domain`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::InvalidInput`] if `identifier` isn't a NIP-05 identifier.
+    pub async fn verify_nip05(&self, pubkey: &PublicKey, identifier: &str) -> Result<bool> {
+        let (name, domain) = parse_nip05_identifier(identifier)?;
+        let url = Url::parse(&format!("https://{}/.well-known/nostr.json", domain))
+            .map_err(|e| WhitenoiseError::InvalidInput(format!("Invalid NIP-05 domain: {}", e)))?;
+        self.verify_nip05_at(pubkey, identifier, &name, url).await
+    }
+
+    async fn verify_nip05_at(
+        &self,
+        pubkey: &PublicKey,
+        identifier: &str,
+        name: &str,
+        mut url: Url,
+    ) -> Result<bool> {
+        let cached = User::nip05_verification(pubkey, &self.database)
+            .await?
+            .filter(|verification| verification.identifier == identifier);
+        if let Some(cached) = &cached
+            && is_fresh(cached)
+        {
+            return Ok(cached.verified);
+        }
+
+        url.query_pairs_mut().append_pair("name", name);
+        match fetch_nip05_pubkey(&url, name).await {
+            Ok(found) => {
+                let verified = found == Some(*pubkey);
+                User::save_nip05_verification(pubkey, identifier, verified, &self.database).await?;
+                Ok(verified)
+            }
+            Err(e) => {
+                tracing::warn!(
+                    target: "whitenoise::nip05",
+                    "Could not verify {} for {}, using cached result: {}",
+                    identifier,
+                    pubkey.to_hex(),
+                    e
+                );
+                User::save_failed_nip05_check(pubkey, identifier, &self.database).await?;
+                Ok(cached.is_some_and(|cached| cached.verified))
+            }
+        }
+    }
+
+    /// Re-checks a batch of NIP-05 identifiers that were never checked, expired or changed
+    ///
+    /// Returns how many users were checked.
+    pub(crate) async fn reverify_stale_nip05(&self) -> Result<usize> {
+        let checked_before = Utc::now()
+            - chrono::Duration::from_std(NIP05_VERIFICATION_TTL)
+                .expect("NIP-05 TTL fits in a chrono duration");
+        let stale =
+            User::find_stale_nip05(checked_before, NIP05_REVERIFICATION_BATCH, &self.database)
+                .await?;
+
+        for (pubkey, identifier) in &stale {
+            if let Err(e) = self.verify_nip05(pubkey, identifier).await {
+                tracing::debug!(
+                    target: "whitenoise::nip05",
+                    "Skipping NIP-05 identifier {} of {}: {}",
+                    identifier,
+                    pubkey.to_hex(),
+                    e
+                );
+                User::save_failed_nip05_check(pubkey, identifier, &self.database).await?;
+            }
+        }
+        Ok(stale.len())
+    }
+}
+
+fn is_fresh(verification: &Nip05Verification) -> bool {
+    (Utc::now() - verification.verified_at)
+        .to_std()
+        .is_ok_and(|age| age < NIP05_VERIFICATION_TTL)
+}
+
+/// Splits an identifier into its lowercased local part and domain
+///
+/// A bare domain stands for `_@domain`, the domain's root identifier.
+fn parse_nip05_identifier(identifier: &str) -> Result<(String, String)> {
+    let identifier = identifier.trim().to_lowercase();
+    let (name, domain) = identifier
+        .split_once('@')
+        .unwrap_or(("_", identifier.as_str()));
+
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid_name || domain.is_empty() || domain.contains(['/', '@', '?', '#']) {
+        return Err(WhitenoiseError::InvalidInput(format!(
+            "Invalid NIP-05 identifier: {}",
+            identifier
+        )));
+    }
+    Ok((name.to_string(), domain.to_string()))
+}
+
+/// The pubkey `nostr.json` at `url` lists for `name`, if any
+///
+/// Redirects are not followed, as NIP-05 requires.
+async fn fetch_nip05_pubkey(url: &Url, name: &str) -> Result<Option<PublicKey>> {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(NIP05_REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| WhitenoiseError::Nip05(e.to_string()))?;
+
+    let response = client
+        .get(url.clone())
+        .send()
+        .await
+        .map_err(|e| WhitenoiseError::Nip05(format!("Request failed: {}", e)))?;
+    if !response.status().is_success() {
+        return Err(WhitenoiseError::Nip05(format!(
+            "{} answered with {}",
+            url,
+            response.status()
+        )));
+    }
+
+    let document: Nip05Document = response
+        .json()
+        .await
+        .map_err(|e| WhitenoiseError::Nip05(format!("Invalid nostr.json: {}", e)))?;
+    Ok(document
+        .names
+        .get(name)
+        .and_then(|hex| PublicKey::from_hex(hex).ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::*;
+
+    #[test]
+    fn test_parse_nip05_identifier() {
+        assert_eq!(
+            parse_nip05_identifier("Alice@Example.com").unwrap(),
+            ("alice".to_string(), "example.com".to_string())
+        );
+        assert_eq!(
+            parse_nip05_identifier("example.com").unwrap(),
+            ("_".to_string(), "example.com".to_string())
+        );
+        assert!(parse_nip05_identifier("bob smith@example.com").is_err());
+        assert!(parse_nip05_identifier("bob@").is_err());
+        assert!(parse_nip05_identifier("bob@example.com/path").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verify_nip05_caches_and_survives_network_failures() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let keys = Keys::generate();
+        User {
+            id: None,
+            pubkey: keys.public_key(),
+            metadata: Metadata::new().nip05("alice@example.com"),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+        .save(&whitenoise.database)
+        .await
+        .unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/.well-known/nostr.json")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "name".to_string(),
+                "alice".to_string(),
+            ))
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"names": {{"alice": "{}"}}}}"#,
+                keys.public_key().to_hex()
+            ))
+            .expect(1)
+            .create_async()
+            .await;
+        let url = Url::parse(&format!("{}/.well-known/nostr.json", server.url())).unwrap();
+
+        let verify = |identifier: &'static str, url: Url| {
+            whitenoise.verify_nip05_at(&keys.public_key(), identifier, "alice", url)
+        };
+        assert!(verify("alice@example.com", url.clone()).await.unwrap());
+        // The second check is answered from the cache
+        assert!(verify("alice@example.com", url).await.unwrap());
+        mock.assert_async().await;
+
+        // The stale result is marked for re-checking once its identifier changes
+        User::save_nip05_verification(
+            &keys.public_key(),
+            "alice@old.example.com",
+            true,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+        let stale = User::find_stale_nip05(Utc::now(), 10, &whitenoise.database)
+            .await
+            .unwrap();
+        assert_eq!(
+            stale,
+            vec![(keys.public_key(), "alice@example.com".to_string())]
+        );
+
+        // An unreachable domain doesn't fail, and a result for another identifier isn't used
+        let unreachable = Url::parse("http://127.0.0.1:1/.well-known/nostr.json").unwrap();
+        assert!(!verify("alice@example.com", unreachable).await.unwrap());
+
+        // The failed check still counts, so the identifier isn't re-checked right away
+        assert!(
+            User::find_stale_nip05(
+                Utc::now() - chrono::Duration::hours(1),
+                10,
+                &whitenoise.database
+            )
+            .await
+            .unwrap()
+            .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reverify_stale_nip05_moves_past_invalid_identifiers() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let invalid_count = NIP05_REVERIFICATION_BATCH + 5;
+        for _ in 0..invalid_count {
+            User {
+                id: None,
+                pubkey: Keys::generate().public_key(),
+                metadata: Metadata::new().nip05("not an identifier"),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            }
+            .save(&whitenoise.database)
+            .await
+            .unwrap();
+        }
+
+        // Each run checks users the previous runs didn't get to, until none are left
+        assert_eq!(
+            whitenoise.reverify_stale_nip05().await.unwrap(),
+            NIP05_REVERIFICATION_BATCH
+        );
+        assert_eq!(whitenoise.reverify_stale_nip05().await.unwrap(), 5);
+        assert_eq!(whitenoise.reverify_stale_nip05().await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,205 @@
+//! Account onboarding status
+//!
+//! An account is fully onboarded once it has inbox relays, key package relays and a key
+//! package that others can find on those relays. Onboarding a new account can stop halfway,
+//! e.g. when publishing the key package hits a transient relay error; the status is
+//! recomputed on demand so such accounts can be diagnosed and finished later.
+
+use serde::{Deserialize, Serialize};
+
+use crate::whitenoise::{
+    Whitenoise,
+    accounts::Account,
+    error::Result,
+    relays::{Relay, RelayType},
+    users::User,
+};
+
+/// Which onboarding steps an account has completed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct OnboardingState {
+    /// The account has inbox relays (kind 10050) to receive welcomes on
+    pub inbox_relays: bool,
+
+    /// The account has key package relays (kind 10051)
+    pub key_package_relays: bool,
+
+    /// A key package of the account can be fetched from its key package relays
+    pub key_package_published: bool,
+}
+
+impl OnboardingState {
+    /// Whether every onboarding step is done
+    pub fn is_complete(&self) -> bool {
+        self.inbox_relays && self.key_package_relays && self.key_package_published
+    }
+}
+
+impl Whitenoise {
+    /// Checks which onboarding steps the account has completed.
+    ///
+    /// Relay lists are read from the local database; whether a key package is published is
+    /// checked by querying the account's key package relays.
+    ///
+    /// # Arguments
+    /// * `account` - The account to check
+    pub async fn onboarding_status(&self, account: &Account) -> Result<OnboardingState> {
+        let inbox_relays = account.inbox_relays(self).await?;
+        let key_package_relays = account.key_package_relays(self).await?;
+        let key_package_published = self
+            .is_key_package_published(account, &key_package_relays)
+            .await?;
+
+        Ok(OnboardingState {
+            inbox_relays: !inbox_relays.is_empty(),
+            key_package_relays: !key_package_relays.is_empty(),
+            key_package_published,
+        })
+    }
+
+    /// Finishes onboarding the account by doing whatever steps are missing.
+    ///
+    /// Missing inbox or key package relay lists are set to the default relays and published,
+    /// and a key package is published if none can be found on the key package relays. Steps
+    /// that are already done are left alone, so calling this again is harmless.
+    ///
+    /// # Arguments
+    /// * `account` - The account to finish onboarding
+    ///
+    /// # Returns
+    /// The onboarding status after the missing steps were attempted
+    pub async fn complete_onboarding(&self, account: &Account) -> Result<OnboardingState> {
+        let mut inbox_relays = account.inbox_relays(self).await?;
+        let mut key_package_relays = account.key_package_relays(self).await?;
+
+        if inbox_relays.is_empty() || key_package_relays.is_empty() {
+            let mut default_relays = Vec::new();
+            for Relay { url, .. } in self.default_relays() {
+                default_relays.push(self.find_or_create_relay_by_url(&url).await?);
+            }
+            let user = account.user(&self.database).await?;
+
+            if inbox_relays.is_empty() {
+                self.restore_default_relays(account, &user, &default_relays, RelayType::Inbox)
+                    .await?;
+                inbox_relays = default_relays.clone();
+            }
+            if key_package_relays.is_empty() {
+                self.restore_default_relays(account, &user, &default_relays, RelayType::KeyPackage)
+                    .await?;
+                key_package_relays = default_relays;
+            }
+        }
+
+        let mut key_package_published = self
+            .is_key_package_published(account, &key_package_relays)
+            .await?;
+        if !key_package_published {
+            tracing::info!(
+                target: "whitenoise::onboarding::complete_onboarding",
+                "Publishing missing key package for account {}",
+                account.pubkey.to_hex()
+            );
+            key_package_published = self
+                .publish_key_package_to_relays(account, &key_package_relays)
+                .await?
+                .is_success();
+        }
+
+        Ok(OnboardingState {
+            inbox_relays: !inbox_relays.is_empty(),
+            key_package_relays: !key_package_relays.is_empty(),
+            key_package_published,
+        })
+    }
+
+    /// Store `relays` as the account's relays of `relay_type` and publish the list
+    async fn restore_default_relays(
+        &self,
+        account: &Account,
+        user: &User,
+        relays: &[Relay],
+        relay_type: RelayType,
+    ) -> Result<()> {
+        tracing::info!(
+            target: "whitenoise::onboarding::complete_onboarding",
+            "Restoring default {:?} relays for account {}",
+            relay_type,
+            account.pubkey.to_hex()
+        );
+        user.add_relays(relays, relay_type, &self.database).await?;
+        self.background_publish_account_relay_list(account, relay_type, Some(relays))
+            .await
+    }
+
+    async fn is_key_package_published(
+        &self,
+        account: &Account,
+        key_package_relays: &[Relay],
+    ) -> Result<bool> {
+        if key_package_relays.is_empty() {
+            return Ok(false);
+        }
+        Ok(self
+            .nostr
+            .fetch_user_key_package(account.pubkey, &Relay::urls(key_package_relays))
+            .await?
+            .is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::create_mock_whitenoise;
+
+    #[test]
+    fn test_is_complete() {
+        assert!(!OnboardingState::default().is_complete());
+        assert!(
+            OnboardingState {
+                inbox_relays: true,
+                key_package_relays: true,
+                key_package_published: true,
+            }
+            .is_complete()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_complete_onboarding_restores_missing_relays() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+
+        let status = whitenoise.onboarding_status(&account).await.unwrap();
+        assert!(status.inbox_relays);
+        assert!(status.key_package_relays);
+
+        // Lose the inbox relays, as a half-finished onboarding would
+        let user = account.user(&whitenoise.database).await.unwrap();
+        for relay in account.inbox_relays(&whitenoise).await.unwrap() {
+            user.remove_relay(&relay, RelayType::Inbox, &whitenoise.database)
+                .await
+                .unwrap();
+        }
+        let status = whitenoise.onboarding_status(&account).await.unwrap();
+        assert!(!status.inbox_relays);
+        assert!(!status.is_complete());
+
+        let status = whitenoise.complete_onboarding(&account).await.unwrap();
+        assert!(status.inbox_relays);
+        assert!(status.key_package_relays);
+        assert_eq!(
+            account.inbox_relays(&whitenoise).await.unwrap().len(),
+            Relay::defaults().len()
+        );
+
+        // Nothing left to restore the second time
+        let again = whitenoise.complete_onboarding(&account).await.unwrap();
+        assert!(again.inbox_relays);
+        assert_eq!(
+            account.inbox_relays(&whitenoise).await.unwrap().len(),
+            Relay::defaults().len()
+        );
+    }
+}
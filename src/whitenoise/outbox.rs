@@ -0,0 +1,260 @@
+//! Outbox of chat messages that failed to publish
+//!
+//! When no relay accepts a chat message the account sent, its inner event is queued in the
+//! outbox. Retrying encrypts the same inner event again for the group's current epoch, so
+//! receivers see the original message ID and a retry that overlaps a late delivery of the
+//! original collapses onto the same cached message.
+
+use mdk_core::prelude::*;
+use mdk_sqlite_storage::MdkSqliteStorage;
+use nostr_sdk::prelude::*;
+
+use crate::whitenoise::{
+    Whitenoise,
+    accounts::Account,
+    aggregated_message::AggregatedMessage,
+    database::outbox::{Outbox, OutboxEntry},
+    error::{Result, WhitenoiseError},
+    message_aggregator::DeliveryStatus,
+    message_streaming::{MessageUpdate, UpdateTrigger},
+};
+
+impl Whitenoise {
+    /// Publishes the account's failed chat messages again.
+    ///
+    /// Every message in the account's outbox is re-published to its group's relays. Messages
+    /// that at least one relay accepts leave the outbox; the others stay queued for the next
+    /// attempt. Each message's [`DeliveryStatus`] is updated and streamed to subscribers of its
+    /// group as it goes.
+    ///
+    /// Retries never overlap, so a message is never published twice at once, and a message
+    /// that left the outbox is not published again.
+    ///
+    /// # Arguments
+    /// * `account` - The account whose outbox to retry
+    ///
+    /// # Returns
+    /// The number of messages that were delivered
+    pub async fn retry_outbox(&self, account: &Account) -> Result<usize> {
+        self.retry_outbox_entries(account, false).await
+    }
+
+    /// Retry the account's outbox, optionally skipping groups whose relays are all offline
+    ///
+    /// Used by the scheduled retry, which only wants to publish once a relay is back.
+    pub(crate) async fn retry_outbox_entries(
+        &self,
+        account: &Account,
+        only_connected: bool,
+    ) -> Result<usize> {
+        let account_id = account.id.ok_or(WhitenoiseError::AccountNotFound)?;
+        let _guard = self.outbox_retry.lock().await;
+
+        let entries = Outbox::find_by_account(account_id, &self.database).await?;
+        if entries.is_empty() {
+            return Ok(0);
+        }
+
+        let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
+        let mut delivered = 0;
+        for entry in entries {
+            match self
+                .retry_outbox_entry(&mdk, account, account_id, &entry, only_connected)
+                .await
+            {
+                Ok(true) => delivered += 1,
+                Ok(false) => {}
+                Err(e) => tracing::warn!(
+                    target: "whitenoise::outbox::retry_outbox_entries",
+                    "Failed to retry message {} from the outbox: {}",
+                    entry.event_id,
+                    e
+                ),
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    /// Publish a single outbox entry again, returning whether a relay accepted it
+    async fn retry_outbox_entry(
+        &self,
+        mdk: &MDK<MdkSqliteStorage>,
+        account: &Account,
+        account_id: i64,
+        entry: &OutboxEntry,
+        only_connected: bool,
+    ) -> Result<bool> {
+        let relays = mdk
+            .get_relays(&entry.mls_group_id)?
+            .into_iter()
+            .collect::<Vec<_>>();
+        if only_connected && !self.nostr.has_any_relay_connected(&relays).await {
+            return Ok(false);
+        }
+
+        self.set_outbox_delivery_status(entry, DeliveryStatus::Pending)
+            .await?;
+        let message_event =
+            mdk.create_message(&entry.mls_group_id, entry.unsigned_event(account.pubkey))?;
+        let status = match self
+            .nostr
+            .publish_event_to(message_event, &account.pubkey, &relays)
+            .await
+        {
+            Ok(outcome) if outcome.is_success() => DeliveryStatus::Sent {
+                relays: outcome.accepted().count(),
+            },
+            Ok(_) => DeliveryStatus::Failed,
+            Err(e) => {
+                tracing::warn!(
+                    target: "whitenoise::outbox::retry_outbox_entry",
+                    "Failed to publish message {}: {}",
+                    entry.event_id,
+                    e
+                );
+                DeliveryStatus::Failed
+            }
+        };
+
+        let delivered = status != DeliveryStatus::Failed;
+        if delivered {
+            Outbox::remove(account_id, &entry.event_id, &self.database).await?;
+        } else {
+            Outbox::add(
+                account_id,
+                &entry.mls_group_id,
+                &entry.unsigned_event(account.pubkey),
+                &self.database,
+            )
+            .await?;
+        }
+        self.set_outbox_delivery_status(entry, status).await?;
+
+        Ok(delivered)
+    }
+
+    /// Store the delivery status of an outbox message and stream it if the message is cached
+    async fn set_outbox_delivery_status(
+        &self,
+        entry: &OutboxEntry,
+        status: DeliveryStatus,
+    ) -> Result<()> {
+        let message_id = entry.event_id.to_hex();
+        AggregatedMessage::update_delivery_status(
+            &message_id,
+            &entry.mls_group_id,
+            status,
+            &self.database,
+        )
+        .await?;
+        self.message_aggregator
+            .invalidate_group_state(&entry.mls_group_id)
+            .await;
+
+        if let Some(message) =
+            AggregatedMessage::find_by_id(&message_id, &entry.mls_group_id, &self.database).await?
+        {
+            self.message_stream_manager.emit(
+                &entry.mls_group_id,
+                MessageUpdate {
+                    trigger: UpdateTrigger::DeliveryStatusChanged,
+                    message,
+                    notify: false,
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::whitenoise::test_utils::*;
+
+    #[tokio::test]
+    async fn test_retry_outbox_delivers_queued_message() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member_pubkeys = members.iter().map(|(acc, _)| acc.pubkey).collect();
+        let group = whitenoise
+            .create_group(
+                &creator,
+                member_pubkeys,
+                create_nostr_group_config_data(vec![creator.pubkey]),
+                None,
+            )
+            .await
+            .unwrap();
+        let group_id = group.mls_group_id;
+        let account_id = creator.id.unwrap();
+
+        // Nothing to retry
+        assert_eq!(whitenoise.retry_outbox(&creator).await.unwrap(), 0);
+
+        let mut subscription = whitenoise
+            .subscribe_to_group_messages(&creator, &group_id)
+            .await
+            .unwrap();
+        let sent = whitenoise
+            .send_message_to_group(&creator, &group_id, "Lost".to_string(), 9, None)
+            .await
+            .unwrap();
+        let message_id = sent.message.id;
+
+        // Let the first attempt finish, then queue the message as if no relay had accepted it
+        let update = subscription.updates.recv().await.unwrap();
+        assert_eq!(update.trigger, UpdateTrigger::NewMessage);
+        let update = tokio::time::timeout(Duration::from_secs(30), subscription.updates.recv())
+            .await
+            .expect("relays should answer")
+            .unwrap();
+        assert_eq!(update.trigger, UpdateTrigger::DeliveryStatusChanged);
+        let mdk = Account::create_mdk(creator.pubkey, &whitenoise.config.data_dir).unwrap();
+        whitenoise
+            .sync_cache_for_group(
+                &creator.pubkey,
+                &group_id,
+                mdk.get_messages(&group_id).unwrap(),
+            )
+            .await
+            .unwrap();
+        Outbox::remove(account_id, &message_id, &whitenoise.database)
+            .await
+            .unwrap();
+        Outbox::add(
+            account_id,
+            &group_id,
+            &sent.message.event,
+            &whitenoise.database,
+        )
+        .await
+        .unwrap();
+
+        let delivered = whitenoise.retry_outbox(&creator).await.unwrap();
+        let remaining = Outbox::find_by_account(account_id, &whitenoise.database)
+            .await
+            .unwrap();
+        assert_eq!(delivered + remaining.len(), 1);
+
+        // Subscribers see the retry start and finish
+        let update = subscription.updates.try_recv().unwrap();
+        assert_eq!(update.trigger, UpdateTrigger::DeliveryStatusChanged);
+        assert_eq!(update.message.id, message_id.to_hex());
+        assert_eq!(update.message.delivery_status, DeliveryStatus::Pending);
+        let update = subscription.updates.try_recv().unwrap();
+        assert_ne!(update.message.delivery_status, DeliveryStatus::Pending);
+
+        if delivered == 1 {
+            // A delivered message is not sent again
+            assert_eq!(whitenoise.retry_outbox(&creator).await.unwrap(), 0);
+            assert!(subscription.updates.try_recv().is_err());
+        } else {
+            assert_eq!(remaining[0].attempts, 2);
+        }
+    }
+}
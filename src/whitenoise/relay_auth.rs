@@ -0,0 +1,223 @@
+//! Answering NIP-42 AUTH challenges for the accounts using a relay
+//!
+//! Relays can restrict reads and writes to authenticated clients. Since one connection serves
+//! every account, a challenge is answered for each account that uses the relay, with that
+//! account's keys. Once a relay accepts, the account's subscriptions on that relay are set up
+//! again, as the relay will have closed the ones it received before.
+//!
+//! A relay may take up to the request timeout to reply, so handshakes run in their own task
+//! rather than holding up the event processor, one at a time per relay.
+
+use dashmap::mapref::entry::Entry;
+use nostr_sdk::prelude::*;
+
+use crate::nostr_manager::relay_auth::RelayAuthStatus;
+use crate::whitenoise::{
+    Whitenoise,
+    accounts::Account,
+    error::{Result, WhitenoiseError},
+    relays::Relay,
+};
+
+impl Whitenoise {
+    /// Starts answering an AUTH challenge from `relay_url` in the background
+    ///
+    /// Only one handshake runs per relay. A challenge that arrives while one is running is
+    /// answered once it finishes, and only the latest such challenge is kept, since a relay's
+    /// new challenge replaces its old one.
+    pub(crate) fn spawn_relay_auth(&'static self, relay_url: RelayUrl, challenge: String) {
+        match self.relay_auth_in_flight.entry(relay_url.clone()) {
+            Entry::Occupied(mut entry) => {
+                entry.insert(Some(challenge));
+                return;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(None);
+            }
+        }
+
+        tokio::spawn(async move {
+            let mut challenge = challenge;
+            loop {
+                if let Err(e) = self.handle_relay_auth(&relay_url, &challenge).await {
+                    tracing::warn!(
+                        target: "whitenoise::relay_auth",
+                        "Failed to authenticate with {}: {}",
+                        relay_url,
+                        e
+                    );
+                }
+
+                match self.relay_auth_in_flight.entry(relay_url.clone()) {
+                    Entry::Occupied(mut entry) => match entry.get_mut().take() {
+                        Some(next) => challenge = next,
+                        None => {
+                            entry.remove();
+                            break;
+                        }
+                    },
+                    Entry::Vacant(_) => break,
+                }
+            }
+        });
+    }
+
+    /// Answers an AUTH challenge from `relay_url` for every account that uses the relay
+    ///
+    /// Accounts that already answered this challenge are skipped, so a relay repeating it
+    /// doesn't make us loop.
+    ///
+    /// # Errors
+    /// Returns [`WhitenoiseError::RelayAuthRequired`] if no account uses the relay, and
+    /// [`WhitenoiseError::RelayAuthFailed`] if the relay rejected every answer or none could be
+    /// sent.
+    pub(crate) async fn handle_relay_auth(
+        &self,
+        relay_url: &RelayUrl,
+        challenge: &str,
+    ) -> Result<()> {
+        let mut accounts = Vec::new();
+        for account in self.all_accounts().await? {
+            if self.account_uses_relay(&account, relay_url).await? {
+                accounts.push(account);
+            }
+        }
+        if accounts.is_empty() {
+            return Err(WhitenoiseError::RelayAuthRequired(relay_url.clone()));
+        }
+
+        let mut failure = None;
+        let mut authenticated = false;
+        for account in &accounts {
+            let keys = self
+                .secrets_store
+                .get_nostr_keys_for_pubkey(&account.pubkey)?;
+            let status = match self
+                .nostr
+                .authenticate_relay_with_signer(relay_url, challenge, account.pubkey, keys)
+                .await
+            {
+                Ok(status) => status,
+                Err(e) => {
+                    failure = Some(e.to_string());
+                    continue;
+                }
+            };
+
+            match status {
+                Some(RelayAuthStatus::Authenticated) => {
+                    authenticated = true;
+                    tracing::info!(
+                        target: "whitenoise::relay_auth",
+                        "Authenticated with {} as {}",
+                        relay_url,
+                        account.pubkey.to_hex()
+                    );
+                    self.resubscribe_account_on_relay(account, relay_url)
+                        .await?;
+                }
+                Some(RelayAuthStatus::Failed { reason }) => failure = Some(reason),
+                Some(RelayAuthStatus::Pending) => {
+                    failure = Some("Relay did not reply to the authentication".to_string())
+                }
+                // Already answered for this challenge
+                None => {}
+            }
+        }
+
+        match failure {
+            Some(reason) if !authenticated => Err(WhitenoiseError::RelayAuthFailed {
+                relay_url: relay_url.clone(),
+                reason,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets up the account's subscriptions again on `relay_url` only
+    async fn resubscribe_account_on_relay(
+        &self,
+        account: &Account,
+        relay_url: &RelayUrl,
+    ) -> Result<()> {
+        let user_relays = Relay::urls(&account.write_relays(self).await?);
+        let inbox_relays = Relay::urls(&account.inbox_relays(self).await?);
+        let (group_relays, nostr_group_ids) = self.extract_groups_relays_and_ids(account).await?;
+        let keys = self
+            .secrets_store
+            .get_nostr_keys_for_pubkey(&account.pubkey)?;
+
+        self.nostr
+            .resubscribe_account_on_relay_with_signer(
+                account.pubkey,
+                relay_url,
+                &user_relays,
+                &inbox_relays,
+                &group_relays,
+                &nostr_group_ids,
+                keys,
+            )
+            .await
+            .map_err(WhitenoiseError::from)
+    }
+
+    async fn account_uses_relay(&self, account: &Account, relay_url: &RelayUrl) -> Result<bool> {
+        let mut relays = Relay::urls(&account.nip65_relays(self).await?);
+        relays.extend(Relay::urls(&account.inbox_relays(self).await?));
+        relays.extend(Relay::urls(&account.key_package_relays(self).await?));
+        relays.extend(self.extract_groups_relays_and_ids(account).await?.0);
+        Ok(relays.contains(relay_url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::*;
+
+    #[tokio::test]
+    async fn test_handle_relay_auth_requires_an_account_using_the_relay() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        whitenoise.create_identity().await.unwrap();
+        let relay_url = RelayUrl::parse("wss://unused.example.com").unwrap();
+
+        let result = whitenoise.handle_relay_auth(&relay_url, "challenge").await;
+        assert!(matches!(
+            result,
+            Err(WhitenoiseError::RelayAuthRequired(url)) if url == relay_url
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_spawn_relay_auth_runs_one_handshake_per_relay() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let whitenoise: &'static Whitenoise = Box::leak(Box::new(whitenoise));
+        let relay_url = RelayUrl::parse("wss://unused.example.com").unwrap();
+
+        // A challenge arriving during a handshake waits for it, replacing older ones
+        whitenoise
+            .relay_auth_in_flight
+            .insert(relay_url.clone(), None);
+        whitenoise.spawn_relay_auth(relay_url.clone(), "challenge-1".to_string());
+        whitenoise.spawn_relay_auth(relay_url.clone(), "challenge-2".to_string());
+        assert_eq!(
+            whitenoise
+                .relay_auth_in_flight
+                .get(&relay_url)
+                .unwrap()
+                .clone(),
+            Some("challenge-2".to_string())
+        );
+
+        // Otherwise the handshake runs in the background and clears its guard when done
+        whitenoise.relay_auth_in_flight.remove(&relay_url);
+        whitenoise.spawn_relay_auth(relay_url.clone(), "challenge-3".to_string());
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while whitenoise.relay_auth_in_flight.contains_key(&relay_url) {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -1,10 +1,16 @@
 use std::{collections::HashSet, str::FromStr};
 
 use chrono::{DateTime, Utc};
-use nostr_sdk::prelude::*;
+use mdk_core::prelude::GroupId;
+use nostr_sdk::{nips::nip65::RelayMetadata, prelude::*};
 use serde::{Deserialize, Serialize};
 
-use crate::whitenoise::{Whitenoise, accounts::Account, error::Result};
+use crate::nostr_manager::RelayHealth;
+use crate::whitenoise::{
+    Whitenoise,
+    accounts::Account,
+    error::{Result, WhitenoiseError},
+};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Hash)]
 pub struct Relay {
@@ -14,6 +20,50 @@ pub struct Relay {
     pub updated_at: DateTime<Utc>,
 }
 
+/// A relay a group message would be published to, see [`Whitenoise::message_delivery_preview`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupRelayStatus {
+    pub url: RelayUrl,
+    /// Connection status, [`RelayStatus::Disconnected`] for relays the client never used
+    pub status: RelayStatus,
+    /// Whether the relay failed the most recent health probe despite being connected
+    pub degraded: bool,
+}
+
+impl GroupRelayStatus {
+    /// Whether a message published now would likely reach the relay right away
+    pub fn is_reachable(&self) -> bool {
+        self.status == RelayStatus::Connected && !self.degraded
+    }
+}
+
+/// Where a group message would be delivered, see [`Whitenoise::message_delivery_preview`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeliveryPreview {
+    /// The group's relays, in the order the group lists them
+    pub relays: Vec<GroupRelayStatus>,
+}
+
+impl DeliveryPreview {
+    /// How many of the group's relays would likely get the message right away
+    pub fn reachable_count(&self) -> usize {
+        self.relays
+            .iter()
+            .filter(|relay| relay.is_reachable())
+            .count()
+    }
+
+    /// How many of the group's relays are offline or degraded
+    pub fn unreachable_count(&self) -> usize {
+        self.relays.len() - self.reachable_count()
+    }
+
+    /// Whether delivery may be delayed because some of the group's relays are unreachable
+    pub fn may_be_delayed(&self) -> bool {
+        self.unreachable_count() > 0
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum RelayType {
     Nip65,
@@ -75,6 +125,72 @@ impl From<Kind> for RelayType {
     }
 }
 
+/// How a relay in a NIP-65 relay list is used
+///
+/// Relays marked `read` receive events addressed to the user, relays marked `write` are
+/// where the user publishes. Unmarked relays are used for both.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum RelayMarker {
+    Read,
+    Write,
+    #[default]
+    ReadWrite,
+}
+
+impl RelayMarker {
+    /// Whether events addressed to the user are read from the relay
+    pub fn is_read(&self) -> bool {
+        matches!(self, Self::Read | Self::ReadWrite)
+    }
+
+    /// Whether the user's events are published to the relay
+    pub fn is_write(&self) -> bool {
+        matches!(self, Self::Write | Self::ReadWrite)
+    }
+
+    /// The marker of a NIP-65 `r` tag, `None` meaning both
+    pub(crate) fn metadata(&self) -> Option<RelayMetadata> {
+        match self {
+            Self::Read => Some(RelayMetadata::Read),
+            Self::Write => Some(RelayMetadata::Write),
+            Self::ReadWrite => None,
+        }
+    }
+}
+
+impl From<Option<RelayMetadata>> for RelayMarker {
+    fn from(metadata: Option<RelayMetadata>) -> Self {
+        match metadata {
+            Some(RelayMetadata::Read) => Self::Read,
+            Some(RelayMetadata::Write) => Self::Write,
+            None => Self::ReadWrite,
+        }
+    }
+}
+
+impl FromStr for RelayMarker {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            "read_write" => Ok(Self::ReadWrite),
+            _ => Err(format!("Invalid relay marker: {}", s)),
+        }
+    }
+}
+
+impl From<RelayMarker> for String {
+    fn from(marker: RelayMarker) -> Self {
+        match marker {
+            RelayMarker::Read => "read".to_string(),
+            RelayMarker::Write => "write".to_string(),
+            RelayMarker::ReadWrite => "read_write".to_string(),
+        }
+    }
+}
+
 impl Relay {
     pub(crate) fn new(url: &RelayUrl) -> Self {
         Relay {
@@ -110,11 +226,44 @@ impl Relay {
     }
 }
 
+/// Relays added to and removed from a relay list
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct RelayListDiff {
+    pub added: Vec<RelayUrl>,
+    pub removed: Vec<RelayUrl>,
+}
+
+impl RelayListDiff {
+    /// What changes going from `current` to `requested`, ordered by URL
+    pub(crate) fn between(current: &HashSet<RelayUrl>, requested: &HashSet<RelayUrl>) -> Self {
+        let mut added: Vec<RelayUrl> = requested.difference(current).cloned().collect();
+        let mut removed: Vec<RelayUrl> = current.difference(requested).cloned().collect();
+        added.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        removed.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        Self { added, removed }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
 impl Whitenoise {
     pub async fn find_or_create_relay_by_url(&self, url: &RelayUrl) -> Result<Relay> {
         Relay::find_or_create_by_url(url, &self.database).await
     }
 
+    /// The relays to bootstrap with: [`WhitenoiseConfig::default_relays`] if set, else the
+    /// built-in [`Relay::defaults`].
+    ///
+    /// [`WhitenoiseConfig::default_relays`]: crate::WhitenoiseConfig::default_relays
+    pub fn default_relays(&self) -> Vec<Relay> {
+        match &self.config.default_relays {
+            Some(urls) => urls.iter().map(Relay::new).collect(),
+            None => Relay::defaults(),
+        }
+    }
+
     /// Get connection status for all of an account's relays.
     ///
     /// This method returns a list of relay statuses for relays that are configured
@@ -163,12 +312,101 @@ impl Whitenoise {
 
         Ok(relay_statuses)
     }
+
+    /// Lists the relays a message to a group would be published to, with their status.
+    ///
+    /// Lets the UI warn before sending when some of the group's relays are offline, e.g.
+    /// "2 of 3 group relays are offline; delivery may be delayed". Nothing is published and
+    /// no connections are opened.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account that would send the message
+    /// * `group_id` - The group the message would be sent to
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::GroupNotFound`] if the account doesn't know the group.
+    pub async fn message_delivery_preview(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+    ) -> Result<DeliveryPreview> {
+        let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
+        if mdk.get_group(group_id)?.is_none() {
+            return Err(WhitenoiseError::GroupNotFound);
+        }
+
+        // The same relays `send_message_to_group` publishes to
+        let group_relays = mdk.get_relays(group_id)?;
+        let degraded: HashSet<RelayUrl> = self.degraded_relays().into_iter().collect();
+
+        let mut relays = Vec::with_capacity(group_relays.len());
+        for url in group_relays {
+            let status = self
+                .nostr
+                .get_relay_status(&url)
+                .await
+                .unwrap_or(RelayStatus::Disconnected);
+            relays.push(GroupRelayStatus {
+                degraded: degraded.contains(&url),
+                url,
+                status,
+            });
+        }
+
+        Ok(DeliveryPreview { relays })
+    }
+
+    /// Relays that failed the most recent health probe.
+    ///
+    /// These relays may still report [`RelayStatus::Connected`] but stopped answering
+    /// requests; the probe has already asked the client to reconnect them.
+    pub fn degraded_relays(&self) -> Vec<RelayUrl> {
+        self.nostr.degraded_relays()
+    }
+
+    /// Connection quality metrics for a relay.
+    ///
+    /// Counts connects, disconnects and NOTICE/CLOSED errors, and tracks the average delay
+    /// of live events and the last EOSE. Metrics accumulate across reconnects and are
+    /// cleared by [`Whitenoise::delete_all_data`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::RelayNotFound`] if the client has never used the relay.
+    pub async fn relay_health(&self, url: &RelayUrl) -> Result<RelayHealth> {
+        self.nostr
+            .relay_health(url)
+            .await
+            .ok_or(WhitenoiseError::RelayNotFound)
+    }
+
+    /// Connection quality metrics for every relay the client has used, ordered by URL.
+    pub async fn all_relay_health(&self) -> Vec<RelayHealth> {
+        self.nostr.all_relay_health().await
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_relay_list_diff() {
+        let a = RelayUrl::parse("wss://a.example.com").unwrap();
+        let b = RelayUrl::parse("wss://b.example.com").unwrap();
+        let c = RelayUrl::parse("wss://c.example.com").unwrap();
+
+        let current = HashSet::from([a.clone(), b.clone()]);
+        let diff = RelayListDiff::between(&current, &HashSet::from([b.clone(), c.clone()]));
+        assert_eq!(diff.added, vec![c]);
+        assert_eq!(diff.removed, vec![a]);
+        assert!(!diff.is_empty());
+
+        assert!(RelayListDiff::between(&current, &current).is_empty());
+    }
+
     fn create_test_relay(url: &RelayUrl) -> super::Relay {
         super::Relay {
             id: None,
@@ -201,4 +439,52 @@ mod tests {
 
         assert_eq!(urls, vec![url1, url2, url3]);
     }
+
+    #[tokio::test]
+    async fn test_message_delivery_preview() {
+        use crate::whitenoise::test_utils::*;
+
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator_account = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let config = create_nostr_group_config_data(vec![creator_account.pubkey]);
+        let group_relays = config.relays.clone();
+        let group = whitenoise
+            .create_group(&creator_account, vec![members[0].0.pubkey], config, None)
+            .await
+            .unwrap();
+
+        let preview = whitenoise
+            .message_delivery_preview(&creator_account, &group.mls_group_id)
+            .await
+            .unwrap();
+        let urls: Vec<RelayUrl> = preview.relays.iter().map(|r| r.url.clone()).collect();
+        assert_eq!(urls, group_relays);
+        assert_eq!(
+            preview.reachable_count() + preview.unreachable_count(),
+            group_relays.len()
+        );
+
+        let unknown = GroupId::from_slice(&[7; 32]);
+        assert!(matches!(
+            whitenoise
+                .message_delivery_preview(&creator_account, &unknown)
+                .await,
+            Err(WhitenoiseError::GroupNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_default_relays_override() {
+        let (mut whitenoise, _data_temp, _logs_temp) =
+            crate::whitenoise::test_utils::create_mock_whitenoise().await;
+        assert_eq!(
+            Relay::urls(&whitenoise.default_relays()),
+            Relay::urls(&Relay::defaults())
+        );
+
+        let custom = RelayUrl::parse("wss://relay.self-hosted.example").unwrap();
+        whitenoise.config.default_relays = Some(vec![custom.clone()]);
+        assert_eq!(Relay::urls(&whitenoise.default_relays()), vec![custom]);
+    }
 }
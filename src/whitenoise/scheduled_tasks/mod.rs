@@ -11,7 +11,10 @@ use crate::WhitenoiseError;
 
 mod tasks;
 
-pub(crate) use self::tasks::KeyPackageMaintenance;
+pub(crate) use self::tasks::{
+    DEFAULT_KEY_PACKAGE_MAX_AGE, EnsureSubscriptions, KeyPackageMaintenance, Nip05Reverification,
+    OutboxRetry, RelayHealthProbe,
+};
 
 /// Trait for implementing scheduled background tasks.
 ///
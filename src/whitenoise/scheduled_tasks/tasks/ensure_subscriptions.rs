@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::whitenoise::Whitenoise;
+use crate::whitenoise::error::WhitenoiseError;
+use crate::whitenoise::scheduled_tasks::Task;
+
+/// Periodically re-establishes global and account subscriptions that are no longer
+/// operational, e.g. after relays dropped them or the connection was lost.
+pub(crate) struct EnsureSubscriptions {
+    interval: Duration,
+}
+
+impl EnsureSubscriptions {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self { interval }
+    }
+}
+
+#[async_trait]
+impl Task for EnsureSubscriptions {
+    fn name(&self) -> &'static str {
+        "ensure_subscriptions"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn execute(&self, whitenoise: &'static Whitenoise) -> Result<(), WhitenoiseError> {
+        tracing::debug!(
+            target: "whitenoise::scheduler::ensure_subscriptions",
+            "Checking subscriptions"
+        );
+
+        whitenoise.ensure_all_subscriptions().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::watch;
+
+    use super::*;
+    use crate::whitenoise::scheduled_tasks::start_scheduled_tasks;
+    use crate::whitenoise::test_utils::create_mock_whitenoise;
+
+    #[test]
+    fn test_task_properties() {
+        let task = EnsureSubscriptions::new(Duration::from_secs(900));
+
+        assert_eq!(task.name(), "ensure_subscriptions");
+        assert_eq!(task.interval(), Duration::from_secs(900));
+    }
+
+    #[tokio::test]
+    async fn test_task_restores_dropped_account_subscriptions() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let whitenoise: &'static Whitenoise = Box::leak(Box::new(whitenoise));
+        let account = whitenoise.create_identity().await.unwrap();
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+        let handles = start_scheduled_tasks(
+            whitenoise,
+            shutdown_rx,
+            None,
+            vec![Arc::new(EnsureSubscriptions::new(Duration::from_millis(
+                100,
+            )))],
+        );
+
+        // Let the first run finish, then drop the account's subscriptions behind its back
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        whitenoise
+            .nostr
+            .unsubscribe_account_subscriptions(&account.pubkey)
+            .await
+            .unwrap();
+        assert!(
+            !whitenoise
+                .is_account_subscriptions_operational(&account)
+                .await
+                .unwrap()
+        );
+
+        // A later run notices and re-subscribes
+        let restored = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if whitenoise
+                    .is_account_subscriptions_operational(&account)
+                    .await
+                    .unwrap()
+                {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await;
+        assert!(restored.is_ok(), "Subscriptions should be re-established");
+
+        let _ = shutdown_tx.send(true);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+    }
+}
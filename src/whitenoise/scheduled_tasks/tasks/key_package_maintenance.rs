@@ -2,20 +2,33 @@ use std::time::Duration;
 
 use async_trait::async_trait;
 use futures::stream::{self, StreamExt};
-use nostr_sdk::{Event, Timestamp};
+use nostr_sdk::{Event, PublicKey, Timestamp};
 
 use crate::whitenoise::Whitenoise;
 use crate::whitenoise::accounts::Account;
 use crate::whitenoise::error::WhitenoiseError;
 use crate::whitenoise::scheduled_tasks::Task;
 
-/// Maximum age for a key package before it should be rotated (30 days).
-const KEY_PACKAGE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Default maximum age for a key package before it should be rotated (30 days).
+pub(crate) const DEFAULT_KEY_PACKAGE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
 
 /// Maximum number of accounts to process concurrently.
 const MAX_CONCURRENT_ACCOUNTS: usize = 5;
 
-pub(crate) struct KeyPackageMaintenance;
+/// Keeps every account's published key packages usable so it can keep receiving invites.
+///
+/// Publishes a key package for accounts that have none and rotates packages older than
+/// `max_age`. The replacement is published before the stale packages are deleted, so the
+/// account is never left without a key package on its relays.
+pub(crate) struct KeyPackageMaintenance {
+    max_age: Duration,
+}
+
+impl KeyPackageMaintenance {
+    pub(crate) fn new(max_age: Duration) -> Self {
+        Self { max_age }
+    }
+}
 
 #[async_trait]
 impl Task for KeyPackageMaintenance {
@@ -49,14 +62,18 @@ impl Task for KeyPackageMaintenance {
         let mut skipped = 0usize;
         let mut errors = 0usize;
 
-        let results: Vec<MaintenanceResult> = stream::iter(accounts)
-            .map(|account| async move { maintain_key_packages(whitenoise, &account).await })
+        let max_age = self.max_age;
+        let results: Vec<(PublicKey, MaintenanceResult)> = stream::iter(accounts)
+            .map(|account| async move {
+                let result = maintain_key_packages(whitenoise, &account, max_age).await;
+                (account.pubkey, result)
+            })
             .buffer_unordered(MAX_CONCURRENT_ACCOUNTS)
             .collect()
             .await;
 
         // Summarize results
-        for result in results {
+        for (pubkey, result) in results {
             checked += 1;
             match result {
                 MaintenanceResult::Fresh => {}
@@ -67,7 +84,8 @@ impl Task for KeyPackageMaintenance {
                     rotated += 1;
                     tracing::debug!(
                         target: "whitenoise::scheduler::key_package_maintenance",
-                        "Rotated key package, deleted {} old one(s)",
+                        "Rotated key package for account {}, deleted {} old one(s)",
+                        pubkey.to_hex(),
                         deleted
                     );
                 }
@@ -78,7 +96,8 @@ impl Task for KeyPackageMaintenance {
                     errors += 1;
                     tracing::warn!(
                         target: "whitenoise::scheduler::key_package_maintenance",
-                        "Error during key package maintenance: {}",
+                        "Error during key package maintenance for account {}: {}",
+                        pubkey.to_hex(),
                         e
                     );
                 }
@@ -112,7 +131,11 @@ enum MaintenanceResult {
     Error(WhitenoiseError),
 }
 
-async fn maintain_key_packages(whitenoise: &Whitenoise, account: &Account) -> MaintenanceResult {
+async fn maintain_key_packages(
+    whitenoise: &Whitenoise,
+    account: &Account,
+    max_age: Duration,
+) -> MaintenanceResult {
     let packages = match whitenoise.fetch_all_key_packages_for_account(account).await {
         Ok(packages) => packages,
         Err(WhitenoiseError::AccountMissingKeyPackageRelays) => {
@@ -132,7 +155,7 @@ async fn maintain_key_packages(whitenoise: &Whitenoise, account: &Account) -> Ma
     }
 
     // Case 2: Check for expired packages
-    let expired_packages = find_expired_packages(&packages);
+    let expired_packages = find_expired_packages(&packages, max_age);
 
     if expired_packages.is_empty() {
         tracing::debug!(
@@ -149,9 +172,9 @@ async fn maintain_key_packages(whitenoise: &Whitenoise, account: &Account) -> Ma
 }
 
 /// Returns key packages that are older than the maximum age threshold.
-fn find_expired_packages(packages: &[Event]) -> Vec<Event> {
+fn find_expired_packages(packages: &[Event], max_age: Duration) -> Vec<Event> {
     let now = Timestamp::now();
-    let max_age_secs = KEY_PACKAGE_MAX_AGE.as_secs();
+    let max_age_secs = max_age.as_secs();
 
     packages
         .iter()
@@ -235,9 +258,31 @@ mod tests {
 
     #[test]
     fn test_task_properties() {
-        let task = KeyPackageMaintenance;
+        let task = KeyPackageMaintenance::new(DEFAULT_KEY_PACKAGE_MAX_AGE);
 
         assert_eq!(task.name(), "key_package_maintenance");
         assert_eq!(task.interval(), Duration::from_secs(60 * 10)); // 10 minutes
     }
+
+    #[test]
+    fn test_find_expired_packages_uses_max_age() {
+        let keys = nostr_sdk::Keys::generate();
+        let package_aged = |age_secs: u64| {
+            nostr_sdk::EventBuilder::new(nostr_sdk::Kind::MlsKeyPackage, "")
+                .custom_created_at(Timestamp::from(Timestamp::now().as_u64() - age_secs))
+                .sign_with_keys(&keys)
+                .unwrap()
+        };
+        let fresh = package_aged(60);
+        let stale = package_aged(2 * 60 * 60);
+
+        let expired = find_expired_packages(
+            &[fresh.clone(), stale.clone()],
+            Duration::from_secs(60 * 60),
+        );
+        assert_eq!(expired, vec![stale]);
+
+        // With the default TTL neither package is old enough to rotate
+        assert!(find_expired_packages(&[fresh], DEFAULT_KEY_PACKAGE_MAX_AGE).is_empty());
+    }
 }
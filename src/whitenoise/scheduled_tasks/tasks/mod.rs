@@ -1,3 +1,11 @@
+mod ensure_subscriptions;
 mod key_package_maintenance;
+mod nip05_reverification;
+mod outbox_retry;
+mod relay_health_probe;
 
-pub(crate) use key_package_maintenance::KeyPackageMaintenance;
+pub(crate) use ensure_subscriptions::EnsureSubscriptions;
+pub(crate) use key_package_maintenance::{DEFAULT_KEY_PACKAGE_MAX_AGE, KeyPackageMaintenance};
+pub(crate) use nip05_reverification::Nip05Reverification;
+pub(crate) use outbox_retry::OutboxRetry;
+pub(crate) use relay_health_probe::RelayHealthProbe;
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::whitenoise::Whitenoise;
+use crate::whitenoise::error::WhitenoiseError;
+use crate::whitenoise::scheduled_tasks::Task;
+
+/// Periodically re-checks users' NIP-05 identifiers.
+///
+/// Covers identifiers that were never checked, whose cached result expired, or that changed
+/// in the user's metadata. Domains that can't be reached keep their cached result.
+pub(crate) struct Nip05Reverification {
+    interval: Duration,
+}
+
+impl Nip05Reverification {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self { interval }
+    }
+}
+
+#[async_trait]
+impl Task for Nip05Reverification {
+    fn name(&self) -> &'static str {
+        "nip05_reverification"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn execute(&self, whitenoise: &'static Whitenoise) -> Result<(), WhitenoiseError> {
+        let checked = whitenoise.reverify_stale_nip05().await?;
+        if checked > 0 {
+            tracing::debug!(
+                target: "whitenoise::scheduler::nip05_reverification",
+                "Checked NIP-05 identifiers of {} user(s)",
+                checked
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_properties() {
+        let task = Nip05Reverification::new(Duration::from_secs(3600));
+
+        assert_eq!(task.name(), "nip05_reverification");
+        assert_eq!(task.interval(), Duration::from_secs(3600));
+    }
+}
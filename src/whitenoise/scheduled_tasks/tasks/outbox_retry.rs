@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::whitenoise::Whitenoise;
+use crate::whitenoise::error::WhitenoiseError;
+use crate::whitenoise::scheduled_tasks::Task;
+
+/// Periodically re-publishes messages in the accounts' outboxes.
+///
+/// Messages are only retried once one of their group's relays is connected again, so a run
+/// while offline leaves the outboxes untouched.
+pub(crate) struct OutboxRetry {
+    interval: Duration,
+}
+
+impl OutboxRetry {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self { interval }
+    }
+}
+
+#[async_trait]
+impl Task for OutboxRetry {
+    fn name(&self) -> &'static str {
+        "outbox_retry"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn execute(&self, whitenoise: &'static Whitenoise) -> Result<(), WhitenoiseError> {
+        for account in whitenoise.all_accounts().await? {
+            match whitenoise.retry_outbox_entries(&account, true).await {
+                Ok(0) => {}
+                Ok(delivered) => tracing::info!(
+                    target: "whitenoise::scheduler::outbox_retry",
+                    "Delivered {} queued message(s) for account {}",
+                    delivered,
+                    account.pubkey.to_hex()
+                ),
+                Err(e) => tracing::warn!(
+                    target: "whitenoise::scheduler::outbox_retry",
+                    "Failed to retry outbox for account {}: {}",
+                    account.pubkey.to_hex(),
+                    e
+                ),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_properties() {
+        let task = OutboxRetry::new(Duration::from_secs(30));
+
+        assert_eq!(task.name(), "outbox_retry");
+        assert_eq!(task.interval(), Duration::from_secs(30));
+    }
+}
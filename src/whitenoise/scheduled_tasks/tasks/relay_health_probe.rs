@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::nostr_manager::NostrManager;
+use crate::whitenoise::Whitenoise;
+use crate::whitenoise::error::WhitenoiseError;
+use crate::whitenoise::scheduled_tasks::Task;
+
+/// Periodically checks that connected relays still answer requests.
+///
+/// Relays that don't answer within the client timeout are marked degraded and reconnected.
+pub(crate) struct RelayHealthProbe {
+    interval: Duration,
+}
+
+impl RelayHealthProbe {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self { interval }
+    }
+}
+
+#[async_trait]
+impl Task for RelayHealthProbe {
+    fn name(&self) -> &'static str {
+        "relay_health_probe"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn execute(&self, whitenoise: &'static Whitenoise) -> Result<(), WhitenoiseError> {
+        let report = whitenoise
+            .nostr
+            .probe_relay_health(NostrManager::default_timeout())
+            .await;
+
+        if report.degraded.is_empty() {
+            tracing::debug!(
+                target: "whitenoise::scheduler::relay_health_probe",
+                "All {} connected relay(s) responsive",
+                report.probed
+            );
+        } else {
+            tracing::info!(
+                target: "whitenoise::scheduler::relay_health_probe",
+                "Relay health probe completed: {} probed, {} degraded, {} reconnected",
+                report.probed,
+                report.degraded.len(),
+                report.reconnected.len()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_properties() {
+        let task = RelayHealthProbe::new(Duration::from_secs(45));
+
+        assert_eq!(task.name(), "relay_health_probe");
+        assert_eq!(task.interval(), Duration::from_secs(45));
+    }
+}
@@ -5,7 +5,7 @@ use std::{
 
 use base64::{Engine as _, engine::general_purpose};
 use keyring::Entry;
-use nostr_sdk::{Keys, PublicKey};
+use nostr_sdk::{EventBuilder, Keys, PublicKey};
 use serde_json::{Value, json};
 use thiserror::Error;
 use uuid::Uuid;
@@ -37,8 +37,25 @@ pub enum SecretsStoreError {
     KeyNotFound,
 }
 
+/// Outcome of checking whether an account's private key can be used for signing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretsStatus {
+    /// The key is present and produced a valid signature
+    Usable,
+    /// No key is stored for the account
+    Missing,
+    /// The stored key belongs to a different public key
+    Mismatched,
+    /// The key could not be read or used (e.g. keyring access was denied)
+    Inaccessible(String),
+}
+
 const SERVICE_NAME: &str = "whitenoise";
 
+/// Secrets store entry holding the database encryption passphrase
+#[cfg(feature = "sqlcipher")]
+const DATABASE_PASSPHRASE_ENTRY: &str = "database_passphrase";
+
 pub struct SecretsStore {
     data_dir: PathBuf,
 }
@@ -185,6 +202,77 @@ impl SecretsStore {
         }
     }
 
+    /// Checks that the private key for a given public key is present and can sign.
+    ///
+    /// The key is loaded from the secrets store and used to sign a random nonce, and the
+    /// resulting signature is verified against the expected public key.
+    ///
+    /// # Arguments
+    ///
+    /// * `pubkey` - A reference to the PublicKey whose private key should be checked.
+    ///
+    /// # Returns
+    ///
+    /// * `SecretsStatus` - The status of the stored key. Failures are reported as a status
+    ///   rather than an error so callers can surface an actionable state to the user.
+    pub fn verify_private_key(&self, pubkey: &PublicKey) -> SecretsStatus {
+        let keys = match self.get_nostr_keys_for_pubkey(pubkey) {
+            Ok(keys) => keys,
+            Err(
+                SecretsStoreError::KeyNotFound
+                | SecretsStoreError::KeyringError(keyring::Error::NoEntry),
+            ) => return SecretsStatus::Missing,
+            Err(e) => return SecretsStatus::Inaccessible(e.to_string()),
+        };
+
+        if keys.public_key() != *pubkey {
+            return SecretsStatus::Mismatched;
+        }
+
+        match EventBuilder::text_note(Uuid::new_v4().to_string()).sign_with_keys(&keys) {
+            Ok(event) if event.pubkey == *pubkey && event.verify().is_ok() => SecretsStatus::Usable,
+            Ok(_) => SecretsStatus::Inaccessible("Signature verification failed".to_string()),
+            Err(e) => SecretsStatus::Inaccessible(e.to_string()),
+        }
+    }
+
+    /// Returns the passphrase the database is encrypted with, generating and storing a random
+    /// one the first time.
+    ///
+    /// The passphrase is kept in the system's keyring (or the obfuscated secrets file on
+    /// Android) next to the account keys, so it never has to be entered by the user.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the passphrase can't be read or stored.
+    #[cfg(feature = "sqlcipher")]
+    pub fn get_or_create_database_passphrase(&self) -> Result<String, SecretsStoreError> {
+        if cfg!(target_os = "android") {
+            let mut secrets = self.read_secrets_file()?;
+            if let Some(obfuscated) = secrets[DATABASE_PASSPHRASE_ENTRY].as_str() {
+                return self.deobfuscate(obfuscated);
+            }
+            let passphrase = hex::encode(rand::random::<[u8; 32]>());
+            secrets[DATABASE_PASSPHRASE_ENTRY] = json!(self.obfuscate(&passphrase));
+            self.write_secrets_file(&secrets)?;
+            Ok(passphrase)
+        } else {
+            let entry = Entry::new(SERVICE_NAME, DATABASE_PASSPHRASE_ENTRY)
+                .map_err(SecretsStoreError::KeyringError)?;
+            match entry.get_password() {
+                Ok(passphrase) => Ok(passphrase),
+                Err(keyring::Error::NoEntry) => {
+                    let passphrase = hex::encode(rand::random::<[u8; 32]>());
+                    entry
+                        .set_password(&passphrase)
+                        .map_err(SecretsStoreError::KeyringError)?;
+                    Ok(passphrase)
+                }
+                Err(e) => Err(SecretsStoreError::KeyringError(e)),
+            }
+        }
+    }
+
     /// Removes the private key associated with a given public key from the system's keyring.
     ///
     /// This function attempts to delete the credential entry for the specified public key
@@ -286,6 +374,33 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_verify_private_key() -> Result<(), SecretsStoreError> {
+        let (secrets_store, _temp_dir) = create_test_secrets_store();
+        let keys = Keys::generate();
+        let pubkey = keys.public_key();
+
+        assert_eq!(
+            secrets_store.verify_private_key(&pubkey),
+            SecretsStatus::Missing
+        );
+
+        secrets_store.store_private_key(&keys)?;
+        assert_eq!(
+            secrets_store.verify_private_key(&pubkey),
+            SecretsStatus::Usable
+        );
+
+        // Clean up
+        secrets_store.remove_private_key_for_pubkey(&pubkey)?;
+        assert_eq!(
+            secrets_store.verify_private_key(&pubkey),
+            SecretsStatus::Missing
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_secrets_store_creation() {
         let temp_dir = TempDir::new().expect("Failed to create temp directory");
@@ -0,0 +1,266 @@
+//! Self-test of the local setup
+//!
+//! [`Whitenoise::self_check`] looks at each subsystem Whitenoise needs to work and reports
+//! on all of them at once, so a "it won't start" report can be narrowed down to the part
+//! that is broken. A failing check never stops the others from running.
+
+use std::time::Duration;
+
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::nostr_manager::relay_health::RelayProber;
+use crate::whitenoise::{
+    Whitenoise, accounts::Account, error::Result, secrets_store::SecretsStatus,
+};
+
+/// How long the self-check waits for a Blossom server to answer
+const BLOSSOM_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of a single self-check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheckStatus {
+    /// The subsystem works
+    Ok,
+    /// The subsystem works, but something is off
+    Warn,
+    /// The subsystem doesn't work
+    Fail,
+}
+
+/// Status of one subsystem with a human readable explanation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+impl CheckResult {
+    fn ok(message: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Ok,
+            message: message.into(),
+        }
+    }
+
+    fn warn(message: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Warn,
+            message: message.into(),
+        }
+    }
+
+    fn fail(message: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Fail,
+            message: message.into(),
+        }
+    }
+}
+
+/// Result of [`Whitenoise::self_check`], one entry per subsystem
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelfCheckReport {
+    /// The database answers queries and every migration is applied
+    pub database: CheckResult,
+
+    /// The MLS storage directory is writable
+    pub mls_storage: CheckResult,
+
+    /// The private keys of all accounts can be read from the secrets store
+    pub secrets_store: CheckResult,
+
+    /// At least one relay is connected
+    pub relays: CheckResult,
+
+    /// A configured Blossom server responds
+    pub blossom: CheckResult,
+}
+
+impl SelfCheckReport {
+    /// Whether no check failed (warnings are fine)
+    pub fn is_healthy(&self) -> bool {
+        [
+            &self.database,
+            &self.mls_storage,
+            &self.secrets_store,
+            &self.relays,
+            &self.blossom,
+        ]
+        .iter()
+        .all(|check| check.status != CheckStatus::Fail)
+    }
+}
+
+impl Whitenoise {
+    /// Checks every subsystem of the local setup and reports on each.
+    ///
+    /// Verifies that:
+    /// - the database can be queried and its migrations are current
+    /// - the MLS storage directory is writable
+    /// - the secrets store holds a usable private key for every account
+    /// - at least one relay is connected
+    /// - a configured Blossom server responds
+    ///
+    /// Problems are reported in the returned [`SelfCheckReport`] rather than as errors, so
+    /// support gets the state of all subsystems from a single call.
+    pub async fn self_check(&self) -> Result<SelfCheckReport> {
+        let report = SelfCheckReport {
+            database: self.check_database().await,
+            mls_storage: self.check_mls_storage().await,
+            secrets_store: self.check_secrets_store().await,
+            relays: self.check_relays().await,
+            blossom: self.check_blossom().await,
+        };
+
+        tracing::info!(
+            target: "whitenoise::self_check",
+            "Self-check finished: {:?}",
+            report
+        );
+        Ok(report)
+    }
+
+    async fn check_database(&self) -> CheckResult {
+        match self.database.pending_migrations().await {
+            Ok(0) => CheckResult::ok("Database is open and up to date"),
+            Ok(pending) => CheckResult::fail(format!(
+                "{} database migrations have not been applied",
+                pending
+            )),
+            Err(e) => CheckResult::fail(format!("Database query failed: {}", e)),
+        }
+    }
+
+    async fn check_mls_storage(&self) -> CheckResult {
+        let mls_dir = self.config.data_dir.join("mls");
+        let probe = mls_dir.join(".self_check");
+        let result = async {
+            tokio::fs::create_dir_all(&mls_dir).await?;
+            tokio::fs::write(&probe, b"ok").await?;
+            tokio::fs::remove_file(&probe).await
+        }
+        .await;
+
+        match result {
+            Ok(()) => CheckResult::ok(format!("{} is writable", mls_dir.display())),
+            Err(e) => CheckResult::fail(format!("{} is not writable: {}", mls_dir.display(), e)),
+        }
+    }
+
+    async fn check_secrets_store(&self) -> CheckResult {
+        let accounts = match Account::all(&self.database).await {
+            Ok(accounts) => accounts,
+            Err(e) => return CheckResult::fail(format!("Could not load accounts: {}", e)),
+        };
+        if accounts.is_empty() {
+            return CheckResult::ok("No accounts to check");
+        }
+
+        let problems: Vec<String> = accounts
+            .iter()
+            .filter_map(
+                |account| match self.secrets_store.verify_private_key(&account.pubkey) {
+                    SecretsStatus::Usable => None,
+                    status => Some(format!("{}: {:?}", account.pubkey.to_hex(), status)),
+                },
+            )
+            .collect();
+
+        if problems.is_empty() {
+            CheckResult::ok(format!(
+                "Keys of all {} accounts are usable",
+                accounts.len()
+            ))
+        } else {
+            CheckResult::fail(format!(
+                "Keys of {} of {} accounts are not usable ({})",
+                problems.len(),
+                accounts.len(),
+                problems.join(", ")
+            ))
+        }
+    }
+
+    async fn check_relays(&self) -> CheckResult {
+        let connected = self.nostr.connected_relays().await;
+        if connected.is_empty() {
+            return CheckResult::fail("No relay is connected");
+        }
+
+        let degraded = self.degraded_relays();
+        if degraded.is_empty() {
+            CheckResult::ok(format!("{} relays connected", connected.len()))
+        } else {
+            CheckResult::warn(format!(
+                "{} relays connected, {} not answering requests",
+                connected.len(),
+                degraded.len()
+            ))
+        }
+    }
+
+    async fn check_blossom(&self) -> CheckResult {
+        let servers = self.blossom_download_servers(None);
+        let mut unreachable = Vec::new();
+        for server in &servers {
+            if Self::blossom_server_responds(server).await {
+                return if unreachable.is_empty() {
+                    CheckResult::ok(format!("Blossom server {} responds", server))
+                } else {
+                    CheckResult::warn(format!(
+                        "Blossom server {} responds, {} does not",
+                        server,
+                        unreachable.join(", ")
+                    ))
+                };
+            }
+            unreachable.push(server.to_string());
+        }
+
+        CheckResult::fail(format!(
+            "No Blossom server responds ({})",
+            unreachable.join(", ")
+        ))
+    }
+
+    /// Whether the server answers HTTP requests at all (any status code counts)
+    async fn blossom_server_responds(server: &Url) -> bool {
+        matches!(
+            tokio::time::timeout(BLOSSOM_CHECK_TIMEOUT, reqwest::get(server.clone())).await,
+            Ok(Ok(_))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::*;
+
+    #[tokio::test]
+    async fn test_self_check_reports_each_subsystem() {
+        let (mut whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .with_status(200)
+            .create_async()
+            .await;
+        whitenoise.config.blossom_servers = vec![Url::parse(&server.url()).unwrap()];
+        whitenoise.create_identity().await.unwrap();
+
+        let report = whitenoise.self_check().await.unwrap();
+        assert_eq!(report.database.status, CheckStatus::Ok);
+        assert_eq!(report.mls_storage.status, CheckStatus::Ok);
+        assert_eq!(report.secrets_store.status, CheckStatus::Ok);
+        assert_eq!(report.blossom.status, CheckStatus::Ok);
+
+        // An unreachable Blossom server fails only its own check
+        whitenoise.config.blossom_servers = vec![Url::parse("http://127.0.0.1:1").unwrap()];
+        let report = whitenoise.self_check().await.unwrap();
+        assert_eq!(report.blossom.status, CheckStatus::Fail);
+        assert_eq!(report.database.status, CheckStatus::Ok);
+        assert!(!report.is_healthy());
+    }
+}
@@ -128,6 +128,11 @@ impl MediaFileStorage {
         None
     }
 
+    /// Total size in bytes of the cached files
+    pub(crate) async fn size(&self) -> Result<u64> {
+        super::directory_size(&self.cache_dir).await
+    }
+
     /// Returns the cache directory path
     #[cfg(test)]
     pub(crate) fn cache_dir(&self) -> &Path {
@@ -1,7 +1,7 @@
 pub mod media_files;
 
 use crate::whitenoise::error::Result;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Storage layer for managing filesystem operations
 ///
@@ -39,3 +39,61 @@ impl Storage {
         Ok(())
     }
 }
+
+/// Total size in bytes of the files under `path`, or of `path` itself if it's a file
+///
+/// Symlinks are neither followed nor counted, so nothing outside `path` is included, and a
+/// missing path counts as empty. The walk awaits between entries, so dropping the future
+/// stops it.
+pub(crate) async fn directory_size(path: &Path) -> Result<u64> {
+    let mut total = 0;
+    let mut pending: Vec<PathBuf> = vec![path.to_path_buf()];
+
+    while let Some(path) = pending.pop() {
+        let metadata = match tokio::fs::symlink_metadata(&path).await {
+            Ok(metadata) => metadata,
+            // Files can disappear while walking, e.g. rotated logs or SQLite journals
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        if metadata.is_file() {
+            total += metadata.len();
+        } else if metadata.is_dir() {
+            let mut entries = tokio::fs::read_dir(&path).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                pending.push(entry.path());
+            }
+        }
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_directory_size_walks_subdirectories_without_following_symlinks() {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("nested");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(temp_dir.path().join("a.bin"), [0u8; 10]).unwrap();
+        std::fs::write(nested.join("b.bin"), [0u8; 32]).unwrap();
+
+        let outside = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("big.bin"), [0u8; 1024]).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(outside.path(), temp_dir.path().join("link")).unwrap();
+
+        assert_eq!(directory_size(temp_dir.path()).await.unwrap(), 42);
+        assert_eq!(
+            directory_size(&temp_dir.path().join("missing"))
+                .await
+                .unwrap(),
+            0
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Disk space used by Whitenoise
+//!
+//! [`Whitenoise::storage_usage`] breaks the space down by what uses it, so the app can show
+//! where it goes and offer to clear the parts that can be rebuilt, like the media cache.
+
+use std::ffi::OsString;
+use std::path::Path;
+
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::whitenoise::{Whitenoise, error::Result, storage::directory_size};
+
+/// Disk space used by one account's MLS storage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountStorageUsage {
+    pub pubkey: PublicKey,
+    pub bytes: u64,
+}
+
+/// Disk space used by Whitenoise, in bytes
+///
+/// Relay events are cached in memory only and take no disk space.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageUsage {
+    /// The SQLite database, including its write-ahead log
+    pub database: u64,
+
+    /// MLS group state, per account
+    pub mls: Vec<AccountStorageUsage>,
+
+    /// Downloaded media files
+    pub media_cache: u64,
+
+    /// Log files
+    pub logs: u64,
+}
+
+impl StorageUsage {
+    /// Total disk space used
+    pub fn total(&self) -> u64 {
+        self.database
+            + self.mls.iter().map(|account| account.bytes).sum::<u64>()
+            + self.media_cache
+            + self.logs
+    }
+}
+
+impl Whitenoise {
+    /// Measures the disk space used by the database, MLS storage, media cache and logs.
+    ///
+    /// Walks the data and logs directories without following symlinks. The walk can take a
+    /// while on a large media cache; dropping the returned future cancels it.
+    pub async fn storage_usage(&self) -> Result<StorageUsage> {
+        let database = sqlite_size(&self.database.path).await?;
+
+        let mut mls = Vec::new();
+        let mls_dir = self.config.data_dir.join("mls");
+        if mls_dir.exists() {
+            let mut entries = tokio::fs::read_dir(&mls_dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                // Each account keeps its MLS storage in a SQLite file named after its pubkey
+                let Some(pubkey) = entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| PublicKey::from_hex(name).ok())
+                else {
+                    continue;
+                };
+                mls.push(AccountStorageUsage {
+                    pubkey,
+                    bytes: sqlite_size(&entry.path()).await?,
+                });
+            }
+        }
+        mls.sort_by_key(|account| account.pubkey);
+
+        Ok(StorageUsage {
+            database,
+            mls,
+            media_cache: self.storage.media_files.size().await?,
+            logs: directory_size(&self.config.logs_dir).await?,
+        })
+    }
+}
+
+/// Size of a SQLite database file together with its `-wal` and `-shm` siblings
+async fn sqlite_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for suffix in ["", "-wal", "-shm"] {
+        let mut file = OsString::from(path.as_os_str());
+        file.push(suffix);
+        size += directory_size(Path::new(&file)).await?;
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::test_utils::*;
+
+    #[tokio::test]
+    async fn test_storage_usage_breakdown() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+        whitenoise
+            .storage
+            .media_files
+            .store_file("cached.bin", &[0u8; 100])
+            .await
+            .unwrap();
+
+        let usage = whitenoise.storage_usage().await.unwrap();
+        assert!(usage.database > 0);
+        assert_eq!(usage.media_cache, 100);
+        assert!(usage.mls.iter().any(|mls| mls.pubkey == account.pubkey));
+
+        // The store's write-ahead log counts towards the account's usage
+        let account_bytes = |usage: &StorageUsage| {
+            usage
+                .mls
+                .iter()
+                .find(|mls| mls.pubkey == account.pubkey)
+                .unwrap()
+                .bytes
+        };
+        let wal = whitenoise
+            .config
+            .data_dir
+            .join("mls")
+            .join(format!("{}-wal", account.pubkey.to_hex()));
+        let mut wal_file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&wal)
+            .unwrap();
+        std::io::Write::write_all(&mut wal_file, &[0u8; 1000]).unwrap();
+        drop(wal_file);
+        let with_wal = whitenoise.storage_usage().await.unwrap();
+        assert_eq!(account_bytes(&with_wal), account_bytes(&usage) + 1000);
+        assert_eq!(with_wal.mls.len(), usage.mls.len());
+
+        assert_eq!(
+            usage.total(),
+            usage.database
+                + usage.mls.iter().map(|mls| mls.bytes).sum::<u64>()
+                + usage.media_cache
+                + usage.logs
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! Typing indicators
+//!
+//! A typing indicator is an MLS application message of [`TYPING_INDICATOR_KIND`] with empty
+//! content and a NIP-40 expiration tag. The message aggregator skips its kind, so indicators
+//! never show up in the chat history; received indicators are only surfaced on the group's
+//! message stream.
+
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use mdk_core::prelude::GroupId;
+use mdk_core::prelude::message_types::Message;
+use nostr_sdk::prelude::*;
+
+use crate::whitenoise::{
+    Whitenoise, accounts::Account, error::Result, message_aggregator::ChatMessage,
+    message_aggregator::ReactionSummary,
+};
+
+/// Event kind of the MLS application message announcing that a member is typing
+///
+/// Taken from the NIP-01 ephemeral range, since an indicator is meaningless once it expires.
+pub const TYPING_INDICATOR_KIND: u16 = 20_009;
+
+/// Minimum time between two typing indicators an account sends to the same group
+pub const TYPING_INDICATOR_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How long a typing indicator stays valid without a newer one
+pub const TYPING_INDICATOR_TTL: Duration = Duration::from_secs(8);
+
+impl Whitenoise {
+    /// Tells the other members of a group that the account is typing.
+    ///
+    /// Meant to be called on every keystroke: indicators are sent at most once every
+    /// [`TYPING_INDICATOR_INTERVAL`] per group, and calls in between are skipped. Receivers
+    /// drop the indicator after [`TYPING_INDICATOR_TTL`] unless a newer one or a message
+    /// from the account arrives first.
+    ///
+    /// # Arguments
+    /// * `account` - The account that is typing
+    /// * `group_id` - The group the account is typing in
+    ///
+    /// # Returns
+    /// Whether an indicator was sent (`false` if it was skipped by the rate limit)
+    pub async fn send_typing_indicator(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+    ) -> Result<bool> {
+        let now = Instant::now();
+        let key = (account.pubkey, group_id.clone());
+        if let Some(last_sent) = self.typing_indicators_sent.get(&key)
+            && now.duration_since(*last_sent) < TYPING_INDICATOR_INTERVAL
+        {
+            return Ok(false);
+        }
+        self.typing_indicators_sent.insert(key, now);
+
+        let expires_at = Timestamp::now() + TYPING_INDICATOR_TTL;
+        self.send_message_to_group(
+            account,
+            group_id,
+            String::new(),
+            TYPING_INDICATOR_KIND,
+            Some(vec![Tag::expiration(expires_at)]),
+        )
+        .await?;
+        Ok(true)
+    }
+
+    /// Surface a typing indicator received in a group on the group's message stream
+    ///
+    /// Indicators from the account itself or from muted members, and ones that already
+    /// expired (e.g. when catching up on old group messages), are ignored.
+    pub(crate) fn handle_typing_indicator(
+        &self,
+        account: &Account,
+        group_id: &GroupId,
+        message: &Message,
+        muted: &HashSet<PublicKey>,
+    ) {
+        if message.pubkey == account.pubkey || muted.contains(&message.pubkey) {
+            return;
+        }
+
+        // Without an expiration tag the indicator lasts the default time from its creation
+        let expires_at = message
+            .tags
+            .expiration()
+            .copied()
+            .unwrap_or(message.created_at + TYPING_INDICATOR_TTL);
+        if expires_at <= Timestamp::now() {
+            return;
+        }
+
+        let indicator = ChatMessage {
+            id: message.id.to_string(),
+            author: message.pubkey,
+            content: String::new(),
+            created_at: message.created_at,
+            tags: message.tags.clone(),
+            is_reply: false,
+            reply_to_id: None,
+            reply_to: None,
+            quoted: None,
+            is_deleted: false,
+            edited_at: None,
+            is_muted: false,
+            content_tokens: vec![],
+            mentions: vec![],
+            reactions: ReactionSummary::default(),
+            kind: TYPING_INDICATOR_KIND,
+            media_attachments: vec![],
+            delivery_status: Default::default(),
+        };
+        self.message_stream_manager
+            .emit_typing(group_id, indicator, expires_at);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whitenoise::message_streaming::UpdateTrigger;
+    use crate::whitenoise::test_utils::*;
+
+    #[tokio::test]
+    async fn test_typing_indicator_is_streamed_but_not_cached() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let group_id = create_group_with_joined_member(&whitenoise, &creator, &members[0].0).await;
+
+        assert!(
+            whitenoise
+                .send_typing_indicator(&creator, &group_id)
+                .await
+                .unwrap()
+        );
+        // Rate limited right after
+        assert!(
+            !whitenoise
+                .send_typing_indicator(&creator, &group_id)
+                .await
+                .unwrap()
+        );
+
+        // A member sees the indicator on the stream
+        let (member, _) = &members[0];
+        let mut subscription = whitenoise
+            .subscribe_to_group_messages(member, &group_id)
+            .await
+            .unwrap();
+        let message = Message {
+            id: EventId::all_zeros(),
+            pubkey: creator.pubkey,
+            created_at: Timestamp::now(),
+            kind: Kind::Custom(TYPING_INDICATOR_KIND),
+            tags: Tags::new(),
+            content: String::new(),
+            mls_group_id: group_id.clone(),
+            event: UnsignedEvent::new(
+                creator.pubkey,
+                Timestamp::now(),
+                Kind::Custom(TYPING_INDICATOR_KIND),
+                Tags::new(),
+                "",
+            ),
+            wrapper_event_id: EventId::all_zeros(),
+            state: mdk_core::prelude::message_types::MessageState::Processed,
+        };
+        whitenoise.handle_typing_indicator(member, &group_id, &message, &HashSet::new());
+        let update = subscription.updates.try_recv().unwrap();
+        assert_eq!(
+            update.trigger,
+            UpdateTrigger::Typing {
+                pubkey: creator.pubkey
+            }
+        );
+
+        // Indicators never make it into the history
+        let messages = whitenoise
+            .fetch_aggregated_messages_for_group(&creator.pubkey, &group_id)
+            .await
+            .unwrap();
+        assert!(
+            messages
+                .iter()
+                .all(|message| message.kind != TYPING_INDICATOR_KIND)
+        );
+    }
+}
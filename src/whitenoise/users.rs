@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use chrono::{DateTime, Duration, Utc};
 use nostr_sdk::prelude::*;
@@ -8,9 +8,10 @@ use crate::{
     nostr_manager::NostrManager,
     whitenoise::{
         Whitenoise,
+        accounts::Account,
         database::processed_events::ProcessedEvent,
         error::{Result, WhitenoiseError},
-        relays::{Relay, RelayType},
+        relays::{Relay, RelayMarker, RelayType},
         utils::timestamp_to_datetime,
     },
 };
@@ -19,6 +20,14 @@ use crate::{
 /// Set to 24 hours - metadata doesn't change frequently for most users
 const METADATA_TTL_HOURS: i64 = 24;
 
+/// Display name or name from a profile, whichever is set first
+pub(crate) fn profile_name(metadata: &Metadata) -> Option<&String> {
+    [&metadata.display_name, &metadata.name]
+        .into_iter()
+        .flatten()
+        .find(|name| !name.trim().is_empty())
+}
+
 /// Specifies how user metadata and relay lists should be synchronized when finding or creating a user.
 ///
 /// This enum controls the synchronization behavior in `find_or_create_user_by_pubkey`, allowing
@@ -45,6 +54,37 @@ pub enum UserSyncMode {
     Background,
 }
 
+/// How fresh the metadata returned by [`Whitenoise::get_user`] must be.
+///
+/// The default is `IfStale` with [`RefreshPolicy::DEFAULT_MAX_AGE`] (24 hours), the same
+/// threshold background syncs use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RefreshPolicy {
+    /// Return what's cached without touching the network.
+    ///
+    /// Fails with [`WhitenoiseError::UserNotFound`] for users that aren't cached yet.
+    CachedOnly,
+
+    /// Return the cached user, first fetching their latest metadata from relays if it was
+    /// last updated longer ago than the given duration, was never fetched, or the user is new.
+    IfStale(std::time::Duration),
+
+    /// Always fetch the latest metadata from relays before returning.
+    ForceSync,
+}
+
+impl RefreshPolicy {
+    /// Age after which `IfStale` refreshes by default
+    pub const DEFAULT_MAX_AGE: std::time::Duration =
+        std::time::Duration::from_secs(METADATA_TTL_HOURS as u64 * 60 * 60);
+}
+
+impl Default for RefreshPolicy {
+    fn default() -> Self {
+        Self::IfStale(Self::DEFAULT_MAX_AGE)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct User {
     pub id: Option<i64>,
@@ -65,17 +105,23 @@ impl User {
     /// * `true` if metadata should be refreshed
     /// * `false` if metadata is still fresh
     fn needs_metadata_refresh(&self) -> bool {
-        let now = Utc::now();
-        let ttl_duration = Duration::hours(METADATA_TTL_HOURS);
-        let stale_threshold = now - ttl_duration;
+        self.metadata_older_than(Duration::hours(METADATA_TTL_HOURS))
+    }
 
+    /// Checks if the user's metadata was last updated more than `max_age` ago, or is
+    /// default (empty) metadata that was never fetched.
+    ///
+    /// An age reaching back further than timestamps go never makes metadata stale.
+    fn metadata_older_than(&self, max_age: Duration) -> bool {
         // Always refresh if metadata is default (empty)
         if self.metadata == Metadata::new() {
             return true;
         }
 
-        // Refresh if updated_at is older than TTL
-        self.updated_at < stale_threshold
+        // Refresh if updated_at is older than max_age
+        Utc::now()
+            .checked_sub_signed(max_age)
+            .is_some_and(|cutoff| self.updated_at < cutoff)
     }
 
     /// Syncs the user's metadata by fetching the latest version from Nostr relays.
@@ -216,7 +262,7 @@ impl User {
                 "User {} has no stored NIP-65 relays, using default relays",
                 self.pubkey,
             );
-            Ok(Relay::defaults())
+            Ok(whitenoise.default_relays())
         } else {
             Ok(stored_relays)
         }
@@ -302,46 +348,11 @@ impl User {
         new_relay_urls: &HashSet<RelayUrl>,
         event_created_at: Option<DateTime<Utc>>,
     ) -> Result<bool> {
-        // First, check if we should process this event based on timestamp
-        if let Some(new_timestamp) = event_created_at {
-            let newest_stored_timestamp = ProcessedEvent::newest_relay_event_timestamp(
-                &self.pubkey,
-                relay_type,
-                &whitenoise.database,
-            )
-            .await?;
-
-            match newest_stored_timestamp {
-                Some(stored_timestamp)
-                    if new_timestamp.timestamp_millis() <= stored_timestamp.timestamp_millis() =>
-                {
-                    tracing::debug!(
-                        target: "whitenoise::users::sync_relay_urls",
-                        "Ignoring stale {:?} relay event for user {} (event: {}, stored: {})",
-                        relay_type,
-                        self.pubkey,
-                        new_timestamp.timestamp_millis(),
-                        stored_timestamp.timestamp_millis()
-                    );
-                    return Ok(false);
-                }
-                None => {
-                    tracing::debug!(
-                        target: "whitenoise::users::sync_relay_urls",
-                        "No stored {:?} relay timestamps for user {}, accepting new event",
-                        relay_type,
-                        self.pubkey
-                    );
-                }
-                Some(_) => {
-                    tracing::debug!(
-                        target: "whitenoise::users::sync_relay_urls",
-                        "New {:?} relay event is newer for user {}, proceeding with sync",
-                        relay_type,
-                        self.pubkey
-                    );
-                }
-            }
+        if self
+            .is_stale_relay_event(whitenoise, relay_type, event_created_at)
+            .await?
+        {
+            return Ok(false);
         }
 
         let stored_relays = self.relays(relay_type, &whitenoise.database).await?;
@@ -411,6 +422,94 @@ impl User {
         Ok(true)
     }
 
+    /// Whether a relay list event is older than the newest one already applied for this type
+    ///
+    /// Events without a timestamp are never stale.
+    async fn is_stale_relay_event(
+        &self,
+        whitenoise: &Whitenoise,
+        relay_type: RelayType,
+        event_created_at: Option<DateTime<Utc>>,
+    ) -> Result<bool> {
+        let Some(new_timestamp) = event_created_at else {
+            return Ok(false);
+        };
+        let newest_stored_timestamp = ProcessedEvent::newest_relay_event_timestamp(
+            &self.pubkey,
+            relay_type,
+            &whitenoise.database,
+        )
+        .await?;
+
+        match newest_stored_timestamp {
+            Some(stored_timestamp)
+                if new_timestamp.timestamp_millis() <= stored_timestamp.timestamp_millis() =>
+            {
+                tracing::debug!(
+                    target: "whitenoise::users::is_stale_relay_event",
+                    "Ignoring stale {:?} relay event for user {} (event: {}, stored: {})",
+                    relay_type,
+                    self.pubkey,
+                    new_timestamp.timestamp_millis(),
+                    stored_timestamp.timestamp_millis()
+                );
+                return Ok(true);
+            }
+            None => {
+                tracing::debug!(
+                    target: "whitenoise::users::is_stale_relay_event",
+                    "No stored {:?} relay timestamps for user {}, accepting new event",
+                    relay_type,
+                    self.pubkey
+                );
+            }
+            Some(_) => {
+                tracing::debug!(
+                    target: "whitenoise::users::is_stale_relay_event",
+                    "New {:?} relay event is newer for user {}, proceeding with sync",
+                    relay_type,
+                    self.pubkey
+                );
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Stores the NIP-65 markers of this user's relays
+    ///
+    /// Relays missing from `markers` are read+write. Only relays the user already has are
+    /// updated, so this runs after [`Self::sync_relay_urls`] and skips the same stale events.
+    ///
+    /// Returns `true` if any marker changed
+    pub(crate) async fn sync_relay_markers(
+        &self,
+        whitenoise: &Whitenoise,
+        markers: &HashMap<RelayUrl, RelayMarker>,
+        event_created_at: Option<DateTime<Utc>>,
+    ) -> Result<bool> {
+        if self
+            .is_stale_relay_event(whitenoise, RelayType::Nip65, event_created_at)
+            .await?
+        {
+            return Ok(false);
+        }
+
+        let mut changed = false;
+        for (relay, stored_marker) in self
+            .relays_with_markers(RelayType::Nip65, &whitenoise.database)
+            .await?
+        {
+            let marker = markers.get(&relay.url).copied().unwrap_or_default();
+            if marker != stored_marker {
+                self.set_relay_marker(&relay, RelayType::Nip65, marker, &whitenoise.database)
+                    .await?;
+                changed = true;
+            }
+        }
+        Ok(changed)
+    }
+
     /// Synchronizes relays for a specific type with the network state
     ///
     /// Returns `true` if changes were made, `false` if no changes needed
@@ -437,14 +536,16 @@ impl User {
         match relay_event {
             Some(event) => {
                 let relay_hashset: HashSet<_> = NostrManager::relay_urls_from_event(&event);
-                let changed = self
-                    .sync_relay_urls(
-                        whitenoise,
-                        relay_type,
-                        &relay_hashset,
-                        Some(timestamp_to_datetime(event.created_at)?),
-                    )
+                let event_created_at = Some(timestamp_to_datetime(event.created_at)?);
+                let mut changed = self
+                    .sync_relay_urls(whitenoise, relay_type, &relay_hashset, event_created_at)
                     .await?;
+                if relay_type == RelayType::Nip65 {
+                    let markers = NostrManager::relay_markers_from_event(&event);
+                    changed |= self
+                        .sync_relay_markers(whitenoise, &markers, event_created_at)
+                        .await?;
+                }
 
                 if changed {
                     whitenoise
@@ -473,6 +574,10 @@ impl User {
         }
     }
 
+    /// Every user with the NIP-65 relays they publish to
+    ///
+    /// Relays marked `read` only receive events addressed to the user, so they are left out
+    /// unless none of the user's relays is marked for writing.
     pub(crate) async fn all_users_with_relay_urls(
         whitenoise: &Whitenoise,
     ) -> Result<Vec<(PublicKey, Vec<RelayUrl>)>> {
@@ -480,8 +585,15 @@ impl User {
         let mut users_with_relays = Vec::new();
 
         for user in users {
-            let relays = user.relays(RelayType::Nip65, &whitenoise.database).await?;
-            let relay_urls: Vec<RelayUrl> = Relay::urls(&relays);
+            let relays = user
+                .relays_with_markers(RelayType::Nip65, &whitenoise.database)
+                .await?;
+            let has_write = relays.iter().any(|(_, marker)| marker.is_write());
+            let relay_urls: Vec<RelayUrl> = relays
+                .into_iter()
+                .filter(|(_, marker)| !has_write || marker.is_write())
+                .map(|(relay, _)| relay.url)
+                .collect();
             users_with_relays.push((user.pubkey, relay_urls));
         }
 
@@ -684,6 +796,130 @@ impl Whitenoise {
         }
     }
 
+    /// Gets a user, refreshing their metadata from relays as `refresh` requires.
+    ///
+    /// Unlike [`Whitenoise::find_or_create_user_by_pubkey`], which either always blocks or
+    /// refreshes in the background, this lets callers trade freshness against latency per
+    /// call. Users that aren't cached yet are created unless `refresh` is
+    /// [`RefreshPolicy::CachedOnly`]. Failing to reach relays isn't an error: the cached
+    /// metadata is returned instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `pubkey` - The Nostr public key of the user
+    /// * `refresh` - How fresh the metadata must be; [`RefreshPolicy::default`] refreshes
+    ///   metadata older than 24 hours
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WhitenoiseError::UserNotFound`] if `refresh` is
+    /// [`RefreshPolicy::CachedOnly`] and the user isn't cached, or an error if the database
+    /// can't be read.
+    pub async fn get_user(&self, pubkey: &PublicKey, refresh: RefreshPolicy) -> Result<User> {
+        let max_age = match refresh {
+            RefreshPolicy::CachedOnly => return User::find_by_pubkey(pubkey, &self.database).await,
+            RefreshPolicy::IfStale(max_age) => Some(max_age),
+            RefreshPolicy::ForceSync => None,
+        };
+
+        let (user, created) = User::find_or_create_by_pubkey(pubkey, &self.database).await?;
+        let stale = match max_age {
+            Some(max_age) => {
+                created
+                    || user
+                        .metadata_older_than(Duration::from_std(max_age).unwrap_or(Duration::MAX))
+            }
+            None => true,
+        };
+        if !stale {
+            return Ok(user);
+        }
+        self.sync_user_blocking(&user, created).await
+    }
+
+    /// Fetches the metadata of many users at once.
+    ///
+    /// Asks the default relays and the NIP-65 relays of all accounts for the newest metadata of
+    /// every pubkey in one request (split up for very long lists) instead of one request per
+    /// user. Users are created if needed and newer metadata is saved to them.
+    ///
+    /// # Arguments
+    ///
+    /// * `pubkeys` - The users to fetch metadata for
+    ///
+    /// # Returns
+    ///
+    /// The metadata of each pubkey: the fetched metadata, else the previously cached metadata,
+    /// or `None` if the user has never published any.
+    pub async fn fetch_metadata_batch(
+        &self,
+        pubkeys: &[PublicKey],
+    ) -> Result<HashMap<PublicKey, Option<Metadata>>> {
+        let pubkeys: Vec<PublicKey> = pubkeys
+            .iter()
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        if pubkeys.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut query_relays: HashSet<RelayUrl> =
+            Relay::urls(&self.default_relays()).into_iter().collect();
+        for account in Account::all(&self.database).await? {
+            query_relays.extend(Relay::urls(&account.nip65_relays(self).await?));
+        }
+        let query_relays: Vec<RelayUrl> = query_relays.into_iter().collect();
+
+        let events = self
+            .nostr
+            .fetch_metadata_batch(&pubkeys, &query_relays)
+            .await?;
+
+        let mut result = HashMap::with_capacity(pubkeys.len());
+        for pubkey in pubkeys {
+            let (mut user, created) =
+                User::find_or_create_by_pubkey(&pubkey, &self.database).await?;
+            if let Some(event) = events.get(&pubkey) {
+                match Metadata::from_json(&event.content) {
+                    Ok(metadata) => {
+                        if user
+                            .should_update_metadata(event, created, &self.database)
+                            .await?
+                        {
+                            user.metadata = metadata;
+                            user = user.save(&self.database).await?;
+                            self.nostr
+                                .event_tracker
+                                .track_processed_global_event(event)
+                                .await?;
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            target: "whitenoise::users::fetch_metadata_batch",
+                            "Ignoring invalid metadata of user {}: {}",
+                            pubkey,
+                            e
+                        );
+                    }
+                }
+            }
+
+            let metadata = (user.metadata != Metadata::default()).then_some(user.metadata);
+            result.insert(pubkey, metadata);
+        }
+
+        tracing::debug!(
+            target: "whitenoise::users::fetch_metadata_batch",
+            "Fetched metadata of {} of {} users",
+            events.len(),
+            result.len()
+        );
+        Ok(result)
+    }
+
     async fn sync_user_blocking(&self, user: &User, is_new: bool) -> Result<User> {
         tracing::debug!(
             target: "whitenoise::users::sync_user_blocking",
@@ -1050,6 +1286,43 @@ mod tests {
         assert_eq!(user_after.pubkey, test_pubkey);
     }
 
+    #[tokio::test]
+    async fn test_fetch_metadata_batch_falls_back_to_cached_metadata() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+
+        let known_pubkey = nostr_sdk::Keys::generate().public_key();
+        User {
+            id: None,
+            pubkey: known_pubkey,
+            metadata: Metadata::new().name("Known User"),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+        .save(&whitenoise.database)
+        .await
+        .unwrap();
+        let unknown_pubkey = nostr_sdk::Keys::generate().public_key();
+
+        let metadata = whitenoise
+            .fetch_metadata_batch(&[known_pubkey, unknown_pubkey, known_pubkey])
+            .await
+            .unwrap();
+
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(
+            metadata[&known_pubkey].as_ref().unwrap().name,
+            Some("Known User".to_string())
+        );
+        assert_eq!(metadata[&unknown_pubkey], None);
+        // Users are created for pubkeys seen for the first time
+        assert!(
+            whitenoise
+                .find_user_by_pubkey(&unknown_pubkey)
+                .await
+                .is_ok()
+        );
+    }
+
     #[tokio::test]
     async fn test_update_metadata_preserves_user_state() {
         let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
@@ -1120,7 +1393,42 @@ mod tests {
         let users_with_relays = User::all_users_with_relay_urls(&whitenoise).await.unwrap();
         assert_eq!(users_with_relays.len(), 1);
         assert_eq!(users_with_relays[0].0, test_pubkey);
+        assert_eq!(users_with_relays[0].1, vec![relay_url.clone()]);
+
+        // Read-only relays are skipped once the user has a relay to write to
+        let read_url = RelayUrl::parse("wss://read.example.com").unwrap();
+        let read_relay = whitenoise
+            .find_or_create_relay_by_url(&read_url)
+            .await
+            .unwrap();
+        saved_user
+            .add_relay(&read_relay, RelayType::Nip65, &whitenoise.database)
+            .await
+            .unwrap();
+        saved_user
+            .set_relay_marker(
+                &read_relay,
+                RelayType::Nip65,
+                RelayMarker::Read,
+                &whitenoise.database,
+            )
+            .await
+            .unwrap();
+        let users_with_relays = User::all_users_with_relay_urls(&whitenoise).await.unwrap();
         assert_eq!(users_with_relays[0].1, vec![relay_url]);
+
+        // With only read relays left, the user is still listened to on them
+        saved_user
+            .set_relay_marker(
+                &relay,
+                RelayType::Nip65,
+                RelayMarker::Read,
+                &whitenoise.database,
+            )
+            .await
+            .unwrap();
+        let users_with_relays = User::all_users_with_relay_urls(&whitenoise).await.unwrap();
+        assert_eq!(users_with_relays[0].1.len(), 2);
     }
 
     #[tokio::test]
@@ -1569,6 +1877,78 @@ mod tests {
             // TODO: Find a way to mock or spy on the sync methods to verify they were called
         }
 
+        #[tokio::test]
+        async fn test_get_user_respects_refresh_policy() {
+            let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+            let test_pubkey = nostr_sdk::Keys::generate().public_key();
+
+            // Nothing is cached yet
+            assert!(matches!(
+                whitenoise
+                    .get_user(&test_pubkey, RefreshPolicy::CachedOnly)
+                    .await,
+                Err(WhitenoiseError::UserNotFound)
+            ));
+
+            let saved_user = User {
+                id: None,
+                pubkey: test_pubkey,
+                metadata: Metadata::new().name("Cached User"),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            }
+            .save(&whitenoise.database)
+            .await
+            .unwrap();
+
+            let cached = whitenoise
+                .get_user(&test_pubkey, RefreshPolicy::CachedOnly)
+                .await
+                .unwrap();
+            assert_eq!(cached.id, saved_user.id);
+            assert_eq!(cached.metadata.name, Some("Cached User".to_string()));
+
+            // Fresh metadata is returned as cached
+            let fresh = whitenoise
+                .get_user(&test_pubkey, RefreshPolicy::default())
+                .await
+                .unwrap();
+            assert_eq!(fresh, cached);
+
+            assert_eq!(
+                RefreshPolicy::default(),
+                RefreshPolicy::IfStale(std::time::Duration::from_secs(24 * 60 * 60))
+            );
+
+            // Ages too large to subtract from now never make metadata stale
+            for max_age in [
+                std::time::Duration::MAX,
+                std::time::Duration::from_secs(10u64.pow(13)),
+            ] {
+                let user = whitenoise
+                    .get_user(&test_pubkey, RefreshPolicy::IfStale(max_age))
+                    .await
+                    .unwrap();
+                assert_eq!(user, cached);
+            }
+        }
+
+        #[tokio::test]
+        async fn test_metadata_older_than() {
+            let updated_at = Utc::now() - Duration::hours(2);
+            let user = User {
+                id: Some(1),
+                pubkey: nostr_sdk::Keys::generate().public_key(),
+                metadata: Metadata::new().name("Test User"),
+                created_at: updated_at,
+                updated_at,
+            };
+
+            assert!(user.metadata_older_than(Duration::hours(1)));
+            assert!(!user.metadata_older_than(Duration::hours(3)));
+            assert!(!user.metadata_older_than(Duration::MAX));
+        }
+
         #[tokio::test]
         async fn test_find_or_create_returns_immediately_with_background_sync() {
             let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
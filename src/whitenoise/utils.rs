@@ -52,6 +52,20 @@ impl Whitenoise {
     }
 }
 
+/// Maximum number of characters of message content shown in a preview
+const PREVIEW_MAX_CHARS: usize = 80;
+
+/// Shorten message content for display in a reply, quote or chat list preview
+pub(crate) fn preview_text(content: &str) -> String {
+    let mut chars = content.chars();
+    let preview: String = chars.by_ref().take(PREVIEW_MAX_CHARS).collect();
+    if chars.next().is_some() {
+        format!("{}…", preview.trim_end())
+    } else {
+        preview
+    }
+}
+
 /// Converts a Nostr timestamp to a DateTime<Utc> with proper error handling.
 ///
 /// Nostr event timestamps are in seconds since Unix epoch. This function handles
@@ -82,6 +96,16 @@ mod tests {
     use super::*;
     use chrono::Datelike;
 
+    #[test]
+    fn test_preview_text_truncates_long_content() {
+        assert_eq!(preview_text("short"), "short");
+
+        let long = "é".repeat(PREVIEW_MAX_CHARS + 10);
+        let preview = preview_text(&long);
+        assert_eq!(preview.chars().count(), PREVIEW_MAX_CHARS + 1);
+        assert!(preview.ends_with('…'));
+    }
+
     #[test]
     fn test_capitalize_first_letter() {
         assert_eq!(Whitenoise::capitalize_first_letter("satoshi"), "Satoshi");
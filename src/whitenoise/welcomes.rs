@@ -1,16 +1,43 @@
-use std::collections::BTreeSet;
-
 use mdk_core::prelude::*;
 use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::whitenoise::{
     Whitenoise,
     accounts::Account,
+    database::left_groups::LeftGroups,
     error::{Result, WhitenoiseError},
     group_information::GroupInformation,
     relays::Relay,
 };
 
+/// What a group invitation is for, shown before the user decides to join
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WelcomePreview {
+    /// The MLS group the welcome invites to
+    pub mls_group_id: GroupId,
+    pub group_name: String,
+    pub group_description: String,
+    /// Number of members, including the invited user
+    pub member_count: u32,
+    pub admin_pubkeys: Vec<PublicKey>,
+    /// The user who sent the invitation
+    pub inviter_pubkey: PublicKey,
+}
+
+impl From<&welcome_types::Welcome> for WelcomePreview {
+    fn from(welcome: &welcome_types::Welcome) -> Self {
+        Self {
+            mls_group_id: welcome.mls_group_id.clone(),
+            group_name: welcome.group_name.clone(),
+            group_description: welcome.group_description.clone(),
+            member_count: welcome.member_count,
+            admin_pubkeys: welcome.group_admin_pubkeys.iter().copied().collect(),
+            inviter_pubkey: welcome.welcomer,
+        }
+    }
+}
+
 impl Whitenoise {
     /// Finds a specific welcome message by its event ID for a given public key.
     ///
@@ -58,6 +85,27 @@ impl Whitenoise {
         Ok(welcomes)
     }
 
+    /// Shows what a welcome invites to without joining the group.
+    ///
+    /// The group details were decrypted and stored when the welcome arrived, so this only
+    /// reads them: the MLS group isn't joined, the key package isn't used up and the
+    /// welcome stays pending, so it can still be accepted or declined afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account the welcome was sent to
+    /// * `welcome_event_id` - The event ID of the welcome message to preview (as a hex string)
+    pub async fn preview_welcome(
+        &self,
+        account: &Account,
+        welcome_event_id: String,
+    ) -> Result<WelcomePreview> {
+        let welcome = self
+            .find_welcome_by_event_id(&account.pubkey, welcome_event_id)
+            .await?;
+        Ok(WelcomePreview::from(&welcome))
+    }
+
     /// Accepts a welcome message and joins the associated MLS group.
     ///
     /// This method processes a pending welcome message by accepting the group invitation
@@ -79,50 +127,30 @@ impl Whitenoise {
 
         let mdk = Account::create_mdk(account.pubkey, &self.config.data_dir)?;
 
-        let welcome = mdk.get_welcome(&welcome_event_id)?;
-        let result = if let Some(welcome) = welcome {
-            mdk.accept_welcome(&welcome)?;
-
-            // Create group information with GroupType inferred from group name
-            GroupInformation::create_for_group(
-                self,
-                &welcome.mls_group_id,
-                None,
-                &welcome.group_name,
-            )
+        let welcome = mdk
+            .get_welcome(&welcome_event_id)?
+            .ok_or(WhitenoiseError::WelcomeNotFound)?;
+        mdk.accept_welcome(&welcome)?;
+
+        // Create group information with GroupType inferred from group name
+        GroupInformation::create_for_group(self, &welcome.mls_group_id, None, &welcome.group_name)
             .await?;
 
-            let groups = mdk.get_groups()?;
-            let mut group_relays_set = BTreeSet::new();
-            let group_ids = groups
-                .iter()
-                .map(|g| hex::encode(g.nostr_group_id))
-                .collect::<Vec<_>>();
-
-            // Collect all relays from all groups into a single vector
-            for group in &groups {
-                let relays = mdk.get_relays(&group.mls_group_id)?;
-                group_relays_set.extend(relays);
-            }
-
-            let group_relays = group_relays_set.into_iter().collect::<Vec<_>>();
-            Ok((group_ids, group_relays))
-        } else {
-            Err(WhitenoiseError::WelcomeNotFound)
-        }?;
+        // Rejoining a group the account left makes it subscribe to the group again
+        if let Some(account_id) = account.id {
+            LeftGroups::unmark(account_id, &welcome.mls_group_id, &self.database).await?;
+        }
 
-        let (group_ids, group_relays) = result;
+        let (group_relays, group_ids) = self.extract_groups_relays_and_ids(&account).await?;
 
         for relay in &group_relays {
             let _ = Relay::find_or_create_by_url(relay, &self.database).await?;
         }
 
-        let group_relays_urls = group_relays.into_iter().collect::<Vec<_>>();
-
         self.nostr
             .setup_group_messages_subscriptions_with_signer(
                 *pubkey,
-                &group_relays_urls,
+                &group_relays,
                 &group_ids,
                 keys,
             )
@@ -218,6 +246,86 @@ mod tests {
         assert!(!result.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_preview_welcome_unknown_welcome() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let account = whitenoise.create_identity().await.unwrap();
+
+        let result = whitenoise
+            .preview_welcome(&account, EventId::all_zeros().to_hex())
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::WelcomeNotFound)));
+
+        let result = whitenoise
+            .preview_welcome(&account, "not-an-event-id".to_string())
+            .await;
+        assert!(matches!(result, Err(WhitenoiseError::InvalidEvent(_))));
+    }
+
+    #[tokio::test]
+    async fn test_preview_welcome_leaves_welcome_pending() {
+        let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;
+        let creator = whitenoise.create_identity().await.unwrap();
+        let members = setup_multiple_test_accounts(&whitenoise, 1).await;
+        let member = &members[0].0;
+
+        // Deliver a real welcome to the member's MDK without waiting on relays
+        let key_package_relays = creator.key_package_relays(&whitenoise).await.unwrap();
+        let key_package = whitenoise
+            .nostr
+            .fetch_user_key_package(member.pubkey, &Relay::urls(&key_package_relays))
+            .await
+            .unwrap()
+            .unwrap();
+        let mut config = create_nostr_group_config_data(vec![creator.pubkey]);
+        config.name = "Preview Group".to_string();
+        config.description = "Previewed before joining".to_string();
+        let creator_mdk = Account::create_mdk(creator.pubkey, &whitenoise.config.data_dir).unwrap();
+        let created = creator_mdk
+            .create_group(&creator.pubkey, vec![key_package], config)
+            .unwrap();
+        let welcome_event_id = EventId::all_zeros();
+        let member_mdk = Account::create_mdk(member.pubkey, &whitenoise.config.data_dir).unwrap();
+        member_mdk
+            .process_welcome(&welcome_event_id, &created.welcome_rumors[0])
+            .unwrap();
+
+        let preview = whitenoise
+            .preview_welcome(member, welcome_event_id.to_hex())
+            .await
+            .unwrap();
+        assert_eq!(preview.mls_group_id, created.group.mls_group_id);
+        assert_eq!(preview.group_name, "Preview Group");
+        assert_eq!(preview.group_description, "Previewed before joining");
+        assert_eq!(preview.member_count, 2);
+        assert_eq!(preview.admin_pubkeys, vec![creator.pubkey]);
+        assert_eq!(preview.inviter_pubkey, creator.pubkey);
+
+        // Previewing doesn't join the group or use up the welcome
+        let pending = whitenoise.pending_welcomes(&member.pubkey).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].mls_group_id, created.group.mls_group_id);
+
+        whitenoise
+            .accept_welcome(&member.pubkey, welcome_event_id.to_hex())
+            .await
+            .unwrap();
+        assert!(
+            whitenoise
+                .pending_welcomes(&member.pubkey)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            whitenoise
+                .group_members(member, &created.group.mls_group_id)
+                .await
+                .unwrap()
+                .contains(&member.pubkey)
+        );
+    }
+
     #[tokio::test]
     async fn test_accept_welcome_creates_group_information() {
         let (whitenoise, _data_temp, _logs_temp) = create_mock_whitenoise().await;